@@ -0,0 +1,75 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Compares serializing batches of elements with
+//! [`Group::serialize_elem_batch`] to serializing every element with
+//! [`Group::serialize_elem`], with `cargo bench --bench serialize --features
+//! secp256k1,sm2`
+
+use std::time::{Duration, Instant};
+
+use generic_array::GenericArray;
+use rand::rngs::OsRng;
+use voprf::Group;
+
+const BATCH_SIZES: [usize; 4] = [2, 8, 32, 128];
+
+/// How often and how long every variant runs, the fastest round counts
+const ROUNDS: u32 = 5;
+const DURATION: Duration = Duration::from_millis(400);
+
+/// Returns the mean time of `f` in microseconds in the fastest round
+fn measure(mut f: impl FnMut()) -> f64 {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            let mut iterations = 0_u32;
+
+            while start.elapsed() < DURATION {
+                f();
+                iterations += 1;
+            }
+
+            start.elapsed().as_secs_f64() * 1e6 / f64::from(iterations)
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn bench<G: Group>(name: &str) {
+    let mut rng = OsRng;
+
+    for batch_size in BATCH_SIZES {
+        let elems: Vec<_> = (0..batch_size)
+            .map(|_| G::base_elem() * &G::random_scalar(&mut rng))
+            .collect();
+        let mut output = vec![GenericArray::default(); batch_size];
+
+        let each = measure(|| {
+            for (elem, output) in elems.iter().zip(&mut output) {
+                *output = G::serialize_elem(*elem);
+            }
+        });
+        let batch = measure(|| G::serialize_elem_batch(&elems, &mut output));
+
+        println!(
+            "{}: batch of {:>3}: each {:>7.1} µs, batch {:>7.1} µs ({:.2}x)",
+            name,
+            batch_size,
+            each,
+            batch,
+            each / batch,
+        );
+    }
+}
+
+fn main() {
+    bench::<p256::NistP256>("P-256");
+    #[cfg(feature = "secp256k1")]
+    bench::<voprf::Secp256k1>("secp256k1");
+    #[cfg(feature = "sm2")]
+    bench::<voprf::Sm2>("SM2");
+}
@@ -0,0 +1,110 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Compares finalizing batches with verifying the proof against the public key,
+//! a [`VerifierContext`] and a precomputed one, with
+//! `cargo bench --bench verifier --features alloc,secp256k1`
+
+use std::time::{Duration, Instant};
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand::rngs::OsRng;
+use voprf::{CipherSuite, VerifierContext, VoprfClient, VoprfServer};
+
+const BATCH_SIZES: [usize; 3] = [1, 4, 16];
+
+/// How often and how long every variant runs, the fastest round counts
+const ROUNDS: u32 = 5;
+const DURATION: Duration = Duration::from_millis(400);
+
+/// Returns the mean time of `f` in microseconds in the fastest round
+fn measure(mut f: impl FnMut()) -> f64 {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            let mut iterations = 0_u32;
+
+            while start.elapsed() < DURATION {
+                f();
+                iterations += 1;
+            }
+
+            start.elapsed().as_secs_f64() * 1e6 / f64::from(iterations)
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn bench<CS: CipherSuite>(name: &str)
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut rng = OsRng;
+    let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+    let pk = server.get_public_key();
+    let context = VerifierContext::<CS>::voprf(pk);
+    let precomputed = context.clone().precompute();
+
+    let precompute = measure(|| {
+        drop(VerifierContext::<CS>::voprf(pk).precompute());
+    });
+    println!("{}: precompute {:.0} µs", name, precompute);
+
+    for batch_size in BATCH_SIZES {
+        let inputs: Vec<_> = (0..batch_size).map(|i| i.to_be_bytes()).collect();
+        let (clients, blinded): (Vec<_>, Vec<_>) = inputs
+            .iter()
+            .map(|input| {
+                let result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+                (result.state, result.message)
+            })
+            .unzip();
+        let result = server.batch_blind_evaluate(&mut rng, &blinded).unwrap();
+
+        let public_key = measure(|| {
+            VoprfClient::batch_finalize(&inputs, &clients, &result.messages, &result.proof, pk)
+                .unwrap()
+                .for_each(|output| drop(output.unwrap()));
+        });
+        let with_context = |context: &VerifierContext<CS>| {
+            measure(|| {
+                VoprfClient::batch_finalize_with_context(
+                    &inputs,
+                    &clients,
+                    &result.messages,
+                    &result.proof,
+                    context,
+                )
+                .unwrap()
+                .for_each(|output| drop(output.unwrap()));
+            })
+        };
+        let context = with_context(&context);
+        let precomputed = with_context(&precomputed);
+
+        println!(
+            "{}: batch of {:>2}: public key {:>6.0} µs, context {:>6.0} µs ({:.2}x), precomputed \
+             {:>6.0} µs ({:.2}x)",
+            name,
+            batch_size,
+            public_key,
+            context,
+            public_key / context,
+            precomputed,
+            public_key / precomputed,
+        );
+    }
+}
+
+fn main() {
+    bench::<voprf::Ristretto255>("Ristretto255");
+    bench::<p256::NistP256>("P-256");
+    #[cfg(feature = "secp256k1")]
+    bench::<voprf::Secp256k1>("secp256k1");
+}
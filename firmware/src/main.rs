@@ -0,0 +1,130 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A firmware image of a client that blinds an input, deserializes the
+//! server's response and finalizes the output, in the base mode or, with the
+//! `voprf` or `poprf` feature, in a verifiable mode. The suite is Ristretto255
+//! with the `ristretto255` feature, P-256 with the `p256` feature and secp256k1
+//! with the `secp256k1` feature, and the `size-optimized` and `small-stack`
+//! features enable the ones of the crate.
+//!
+//! It isn't meant to run, but to be measured by `scripts/size.sh` and
+//! `scripts/stack.py`.
+
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+use core::panic::PanicInfo;
+
+use generic_array::typenum::Unsigned;
+use rand_core::{CryptoRng, Error, RngCore};
+use voprf::{EvaluationElement, EvaluationElementLen};
+
+#[cfg(feature = "p256")]
+type Suite = p256::NistP256;
+#[cfg(feature = "ristretto255")]
+type Suite = voprf::Ristretto255;
+#[cfg(feature = "secp256k1")]
+type Suite = voprf::Secp256k1;
+
+/// The length of the input and of every message in the buffer
+const LEN: usize = 128;
+
+/// Stands in for the hardware random number generator.
+struct Rng(u64);
+
+impl RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1);
+        self.0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for Rng {}
+
+/// Runs the client on the input in the first chunk of the `buffer`. The blinded
+/// element is written to the second chunk, the evaluation element, proof and
+/// public key are read from the following ones and the output is written to
+/// the last one.
+#[inline(never)]
+fn client(buffer: &mut [[u8; LEN]; 6]) -> Option<()> {
+    let [input, blinded_element, evaluation_element, _proof, _pk, output] = buffer;
+    let input = &input[..];
+    let mut rng = Rng(0);
+
+    #[cfg(not(any(feature = "voprf", feature = "poprf")))]
+    let blind_result = voprf::OprfClient::<Suite>::blind(input, &mut rng).ok()?;
+    #[cfg(feature = "voprf")]
+    let blind_result = voprf::VoprfClient::<Suite>::blind(input, &mut rng).ok()?;
+    #[cfg(feature = "poprf")]
+    let blind_result = voprf::PoprfClient::<Suite>::blind(input, &mut rng).ok()?;
+
+    let message = blind_result.message.serialize();
+    blinded_element
+        .get_mut(..message.len())?
+        .copy_from_slice(&message);
+
+    let evaluation_element = EvaluationElement::<Suite>::deserialize(
+        evaluation_element.get(..EvaluationElementLen::<Suite>::USIZE)?,
+    )
+    .ok()?;
+
+    #[cfg(not(any(feature = "voprf", feature = "poprf")))]
+    let result = blind_result.state.finalize(input, &evaluation_element);
+    #[cfg(any(feature = "voprf", feature = "poprf"))]
+    let (proof, pk) = (
+        voprf::Proof::<Suite>::deserialize(_proof.get(..voprf::ProofLen::<Suite>::USIZE)?).ok()?,
+        <<Suite as voprf::CipherSuite>::Group as voprf::Group>::deserialize_elem(
+            _pk.get(..EvaluationElementLen::<Suite>::USIZE)?,
+        )
+        .ok()?,
+    );
+    #[cfg(feature = "voprf")]
+    let result = blind_result
+        .state
+        .finalize(input, &evaluation_element, &proof, pk);
+    #[cfg(feature = "poprf")]
+    let result = blind_result
+        .state
+        .finalize(input, &evaluation_element, &proof, pk, Some(b"info"));
+
+    let result = result.ok()?;
+    output.get_mut(..result.len())?.copy_from_slice(&result);
+
+    Some(())
+}
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    let mut buffer = [[0; LEN]; 6];
+    // Keeps the compiler from evaluating the client at compile time
+    black_box(client(black_box(&mut buffer)));
+
+    loop {}
+}
+
+#[panic_handler]
+fn panic(_: &PanicInfo) -> ! {
+    loop {}
+}
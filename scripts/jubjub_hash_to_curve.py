@@ -0,0 +1,189 @@
+#!/usr/bin/env python3
+# Copyright (c) Facebook, Inc. and its affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""Reference implementation of hashing to Jubjub for the test vectors of
+`src/group/jubjub.rs`.
+
+Follows RFC 9380 literally with Python integers and the standard library only:
+`expand_message_xmd` with SHA-512, `hash_to_field` with L = 48, the generic
+`map_to_curve_elligator2` of section 6.7.1, the rational map of appendix D.1 and
+cofactor clearing by 8. The Montgomery constants J and K and the non-square Z
+are derived from the curve equation and `find_z_ell2` of appendix H.3 instead
+of being copied from the Rust implementation, so they are checked as well.
+
+Run with `python3 scripts/jubjub_hash_to_curve.py`.
+"""
+
+import hashlib
+
+# The base field of Jubjub, the scalar field of BLS12-381
+P = 0x73EDA753299D7D483339D80809A1D80553BDA402FFFE5BFEFFFFFFFF00000001
+# The order of the prime-order subgroup
+R = 0x0E7DB4EA6533AFA906673B0101343B00A6682093CCC81082D0970E5ED6F72CB7
+# The twisted Edwards curve a * v^2 + w^2 = 1 + d * v^2 * w^2
+A = P - 1
+D = (-10240 * pow(10241, -1, P)) % P
+H_EFF = 8
+L = 48
+
+DST = b"QUUX-V01-CS02-with-jubjub_XMD:SHA-512_ELL2_RO_"
+MESSAGES = [b"", b"abc", b"abcdef0123456789", b"q128_" + b"q" * 128, b"a512_" + b"a" * 512]
+
+
+def inv0(x):
+    return pow(x, P - 2, P)
+
+
+def is_square(x):
+    return x == 0 or pow(x, (P - 1) // 2, P) == 1
+
+
+def sqrt(x):
+    # Tonelli-Shanks, the base field has a 2-adicity of 32
+    if x == 0:
+        return 0
+    q, s = P - 1, 0
+    while q % 2 == 0:
+        q, s = q // 2, s + 1
+    z = 2
+    while is_square(z):
+        z += 1
+    m, c, t, r = s, pow(z, q, P), pow(x, q, P), pow(x, (q + 1) // 2, P)
+    while t != 1:
+        i, t2 = 0, t
+        while t2 != 1:
+            t2, i = t2 * t2 % P, i + 1
+        b = pow(c, 1 << (m - i - 1), P)
+        m, c, t, r = i, b * b % P, t * b * b % P, r * b % P
+    assert r * r % P == x
+    return r
+
+
+def sgn0(x):
+    return x % 2
+
+
+def find_z_ell2():
+    ctr = 1
+    while True:
+        for z in (ctr, P - ctr):
+            if not is_square(z):
+                return z
+        ctr += 1
+
+
+# The birationally equivalent Montgomery curve K * t^2 = s^3 + J * s^2 + s
+J = 2 * (A + D) * inv0(A - D) % P
+K = 4 * inv0(A - D) % P
+Z = find_z_ell2()
+
+
+def expand_message_xmd(msg, dst, len_in_bytes):
+    b_in_bytes, r_in_bytes = 64, 128
+    ell = -(-len_in_bytes // b_in_bytes)
+    assert ell <= 255 and len(dst) <= 255
+    dst_prime = dst + bytes([len(dst)])
+    msg_prime = bytes(r_in_bytes) + msg + len_in_bytes.to_bytes(2, "big") + b"\x00" + dst_prime
+    b_0 = hashlib.sha512(msg_prime).digest()
+    b = [hashlib.sha512(b_0 + b"\x01" + dst_prime).digest()]
+    for i in range(2, ell + 1):
+        xored = bytes(x ^ y for x, y in zip(b_0, b[-1]))
+        b.append(hashlib.sha512(xored + bytes([i]) + dst_prime).digest())
+    return b"".join(b)[:len_in_bytes]
+
+
+def hash_to_field(msg, count):
+    uniform_bytes = expand_message_xmd(msg, DST, count * L)
+    return [int.from_bytes(uniform_bytes[i * L : (i + 1) * L], "big") % P for i in range(count)]
+
+
+def map_to_curve_elligator2(u):
+    # Section 6.7.1, for the curve rescaled by K
+    j_over_k = J * inv0(K) % P
+    one_over_k_square = inv0(K * K % P)
+
+    def g(x):
+        return (x * x * x + j_over_k * x * x + one_over_k_square * x) % P
+
+    x1 = -j_over_k * inv0(1 + Z * u * u) % P
+    if x1 == 0:
+        x1 = -j_over_k % P
+    x2 = (-x1 - j_over_k) % P
+    if is_square(g(x1)):
+        x, y = x1, sqrt(g(x1))
+        if sgn0(y) != 1:
+            y = P - y
+    else:
+        x, y = x2, sqrt(g(x2))
+        if sgn0(y) != 0:
+            y = (P - y) % P
+    s, t = x * K % P, y * K % P
+    assert K * t * t % P == (s * s * s + J * s * s + s) % P
+    return s, t
+
+
+def to_edwards(s, t):
+    # Appendix D.1, with the exceptional cases mapped to the identity
+    if t == 0 or (s + 1) % P == 0:
+        return 0, 1
+    v = s * inv0(t) % P
+    w = (s - 1) * inv0(s + 1) % P
+    return v, w
+
+
+def is_on_curve(point):
+    v, w = point
+    return (A * v * v + w * w) % P == (1 + D * v * v * w * w) % P
+
+
+def add(p, q):
+    (v1, w1), (v2, w2) = p, q
+    t = D * v1 * v2 * w1 * w2 % P
+    v3 = (v1 * w2 + w1 * v2) * inv0(1 + t) % P
+    w3 = (w1 * w2 - A * v1 * v2) * inv0(1 - t) % P
+    return v3, w3
+
+
+def mul(k, point):
+    result = (0, 1)
+    while k:
+        if k & 1:
+            result = add(result, point)
+        point, k = add(point, point), k >> 1
+    return result
+
+
+def encode(point):
+    # Like `jubjub::AffinePoint::to_bytes`: w in little-endian with the sign of
+    # v in the most significant bit
+    v, w = point
+    return (w | (v % 2) << 255).to_bytes(32, "little")
+
+
+def hash_to_curve(msg):
+    u0, u1 = hash_to_field(msg, 2)
+    q0 = to_edwards(*map_to_curve_elligator2(u0))
+    q1 = to_edwards(*map_to_curve_elligator2(u1))
+    point = mul(H_EFF, add(q0, q1))
+    assert is_on_curve(point) and mul(R, point) == (0, 1)
+    return point
+
+
+def main():
+    print("J =", J, "K =", K - P, "Z =", Z)
+    print("DST =", DST.decode())
+    for msg in MESSAGES:
+        label = msg if len(msg) <= 16 else msg[:5] + b"..."
+        print(label.decode(), encode(hash_to_curve(msg)).hex())
+    print("map_to_curve")
+    for u in range(4):
+        print(u, encode(to_edwards(*map_to_curve_elligator2(u))).hex())
+
+
+if __name__ == "__main__":
+    main()
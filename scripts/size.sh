@@ -0,0 +1,65 @@
+#!/bin/sh
+# Builds the firmware image of `firmware/` for a Cortex-M0 with every suite and
+# mode, with and without the `size-optimized` feature, and prints the flash it
+# takes. The release profile of `firmware/Cargo.toml` is the one the crate
+# documentation recommends for microcontrollers (`opt-level = "z"`, fat LTO,
+# one codegen unit, `panic = "abort"`), and every image is built with
+#
+#     cargo build --release --target thumbv6m-none-eabi --features <features>
+#
+# in `firmware/`, where the features are the suite, the mode unless it is the
+# base mode and `size-optimized` when enabled:
+#
+#     scripts/size.sh
+#
+# Also checks that the image of the base mode contains no code of the
+# verifiable modes, by listing the functions of the crate that were compiled in
+# from the debug information, which includes inlined functions.
+
+set -eu
+
+cd "$(dirname "$0")/../firmware"
+
+target=thumbv6m-none-eabi
+elf=target/$target/release/voprf-firmware
+
+# The functions of the crate compiled into the image, without their hashes
+functions() {
+	readelf --debug-dump=info "$elf" |
+		grep -o '_ZN5voprf[0-9A-Za-z_$.]*' |
+		c++filt |
+		sed 's/::h[0-9a-f]*$//' |
+		sort -u
+}
+
+printf '%-14s %-6s %-14s %8s %8s %8s\n' suite mode size-optimized text rodata total
+
+for suite in ristretto255 p256 secp256k1; do
+	for mode in oprf voprf poprf; do
+		for optimized in no yes; do
+			features=$suite
+			if [ $mode != oprf ]; then
+				features=$features,$mode
+			fi
+			if [ $optimized = yes ]; then
+				features=$features,size-optimized
+			fi
+
+			cargo build --quiet --release --target $target --features "$features"
+
+			size -A "$elf" | awk -v suite=$suite -v mode=$mode -v optimized=$optimized '
+				$1 == ".text" { text = $2 }
+				$1 == ".rodata" { rodata = $2 }
+				END {
+					printf "%-14s %-6s %-14s %8d %8d %8d\n",
+						suite, mode, optimized, text, rodata, text + rodata
+				}'
+
+			if [ $mode = oprf ] &&
+				functions | grep -E 'voprf::(voprf|poprf|verifier)::|proof|tweak'; then
+				echo "The base mode contains the functions above of the verifiable modes" >&2
+				exit 1
+			fi
+		done
+	done
+done
@@ -0,0 +1,244 @@
+#!/usr/bin/env python3
+# Copyright (c) Facebook, Inc. and its affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""Reference implementation of hashing to SM2 for the test vectors of
+`src/group/sm2.rs`.
+
+Follows RFC 9380 literally with Python integers and the standard library only:
+`expand_message_xmd`, `hash_to_field` with L = 48 and the generic
+`map_to_curve_simple_swu` of section 6.6.2 with affine point addition. The
+non-square Z is derived with `find_z_sswu` of appendix H.2 instead of being
+copied from the Rust implementation. To check the implementation itself, it
+first reproduces the P-256 test vectors of the RFC with the same code.
+
+SM3 is taken from OpenSSL through `hashlib`. Run with
+`python3 scripts/sm2_hash_to_curve.py`.
+"""
+
+import hashlib
+
+P256 = {
+    "p": 2**256 - 2**224 + 2**192 + 2**96 - 1,
+    "a": -3,
+    "b": 0x5AC635D8AA3A93E7B3EBBD55769886BC651D06B0CC53B0F63BCE3C3E27D2604B,
+}
+# GB/T 32918.5
+SM2 = {
+    "p": 0xFFFFFFFEFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF00000000FFFFFFFFFFFFFFFF,
+    "a": -3,
+    "b": 0x28E9FA9E9D9F5E344D5A9E4BCF6509A7F39789F515AB8F92DDBCBD414D940E93,
+}
+L = 48
+
+# Appendix J.1.1 of RFC 9380, the x- and y-coordinates of the outputs
+P256_DST = b"QUUX-V01-CS02-with-P256_XMD:SHA-256_SSWU_RO_"
+P256_VECTORS = [
+    (
+        b"",
+        0x2C15230B26DBC6FC9A37051158C95B79656E17A1A920B11394CA91C44247D3E4,
+        0x8A7A74985CC5C776CDFE4B1F19884970453912E9D31528C060BE9AB5C43E8415,
+    ),
+    (
+        b"abc",
+        0x0BB8B87485551AA43ED54F009230450B492FEAD5F1CC91658775DAC4A3388A0F,
+        0x5C41B3D0731A27A7B14BC0BF0CCDED2D8751F83493404C84A88E71FFD424212E,
+    ),
+]
+
+SHA256_DST = b"QUUX-V01-CS02-with-SM2_XMD:SHA-256_SSWU_RO_"
+SM3_DST = b"QUUX-V01-CS02-with-SM2_XMD:SM3_SSWU_RO_"
+MESSAGES = [b"", b"abc", b"abcdef0123456789", b"q128_" + b"q" * 128, b"a512_" + b"a" * 512]
+
+
+def sha256():
+    return hashlib.sha256()
+
+
+def sm3():
+    return hashlib.new("sm3")
+
+
+def expand_message_xmd(hash_fn, msg, dst, len_in_bytes):
+    b_in_bytes, r_in_bytes = hash_fn().digest_size, hash_fn().block_size
+    ell = -(-len_in_bytes // b_in_bytes)
+    assert ell <= 255 and len(dst) <= 255
+
+    def hash_of(data):
+        h = hash_fn()
+        h.update(data)
+        return h.digest()
+
+    dst_prime = dst + bytes([len(dst)])
+    msg_prime = bytes(r_in_bytes) + msg + len_in_bytes.to_bytes(2, "big") + b"\x00" + dst_prime
+    b_0 = hash_of(msg_prime)
+    b = [hash_of(b_0 + b"\x01" + dst_prime)]
+    for i in range(2, ell + 1):
+        xored = bytes(x ^ y for x, y in zip(b_0, b[-1]))
+        b.append(hash_of(xored + bytes([i]) + dst_prime))
+    return b"".join(b)[:len_in_bytes]
+
+
+class Curve:
+    def __init__(self, params):
+        self.p, self.a, self.b = params["p"], params["a"] % params["p"], params["b"]
+        # Both moduli are 3 mod 4, so square roots are a single exponentiation
+        assert self.p % 4 == 3
+        self.z = self.find_z_sswu()
+
+    def inv0(self, x):
+        return pow(x, self.p - 2, self.p)
+
+    def is_square(self, x):
+        return x % self.p == 0 or pow(x, (self.p - 1) // 2, self.p) == 1
+
+    def sqrt(self, x):
+        root = pow(x, (self.p + 1) // 4, self.p)
+        assert root * root % self.p == x % self.p
+        return root
+
+    def g(self, x):
+        return (x * x * x + self.a * x + self.b) % self.p
+
+    def has_root(self, c):
+        # Whether x^3 + a * x + c has a root, i.e. gcd(x^p - x, x^3 + a * x + c)
+        # isn't constant, with polynomials as lists of coefficients from the
+        # constant term
+        p = self.p
+
+        def mul_mod(f, g):
+            product = [0] * (len(f) + len(g) - 1)
+            for i, fi in enumerate(f):
+                for j, gj in enumerate(g):
+                    product[i + j] = (product[i + j] + fi * gj) % p
+            # Reduces by x^3 = -a * x - c
+            for degree in range(len(product) - 1, 2, -1):
+                top, product[degree] = product[degree], 0
+                product[degree - 2] = (product[degree - 2] - top * self.a) % p
+                product[degree - 3] = (product[degree - 3] - top * c) % p
+            return (product + [0, 0, 0])[:3]
+
+        result, base, exponent = [1, 0, 0], [0, 1, 0], p
+        while exponent:
+            if exponent & 1:
+                result = mul_mod(result, base)
+            base, exponent = mul_mod(base, base), exponent >> 1
+        # x^p - x mod f
+        r = [result[0], (result[1] - 1) % p, result[2]]
+        f = [c % p, self.a, 0, 1]
+
+        def trim(poly):
+            while poly and poly[-1] == 0:
+                poly = poly[:-1]
+            return poly
+
+        def poly_mod(f, g):
+            f = trim(list(f))
+            inverse = self.inv0(g[-1])
+            while len(f) >= len(g):
+                factor = f[-1] * inverse % p
+                shift = len(f) - len(g)
+                for i, gi in enumerate(g):
+                    f[shift + i] = (f[shift + i] - factor * gi) % p
+                f = trim(f)
+            return f
+
+        f, r = trim(f), trim(r)
+        while r:
+            f, r = r, poly_mod(f, r)
+        return len(f) > 1
+
+    def find_z_sswu(self):
+        # Appendix H.2
+        def is_good(z):
+            return (
+                not self.is_square(z)
+                and z != self.p - 1
+                and not self.has_root(self.b - z)
+                and self.is_square(self.g(self.b * self.inv0(z * self.a)))
+            )
+
+        ctr = 1
+        while True:
+            for z in (ctr, self.p - ctr):
+                if is_good(z):
+                    return z
+            ctr += 1
+
+    def hash_to_field(self, hash_fn, msg, dst, count):
+        uniform_bytes = expand_message_xmd(hash_fn, msg, dst, count * L)
+        return [
+            int.from_bytes(uniform_bytes[i * L : (i + 1) * L], "big") % self.p for i in range(count)
+        ]
+
+    def map_to_curve_simple_swu(self, u):
+        # Section 6.6.2
+        p, a, b, z = self.p, self.a, self.b, self.z
+        tv1 = self.inv0(z * z * pow(u, 4, p) + z * u * u)
+        if tv1 == 0:
+            x1 = b * self.inv0(z * a) % p
+        else:
+            x1 = -b * self.inv0(a) * (1 + tv1) % p
+        x2 = z * u * u * x1 % p
+        if self.is_square(self.g(x1)):
+            x, y = x1, self.sqrt(self.g(x1))
+        else:
+            x, y = x2, self.sqrt(self.g(x2))
+        if u % 2 != y % 2:
+            y = (p - y) % p
+        assert y * y % p == self.g(x)
+        return x, y
+
+    def add(self, point, other):
+        # Affine addition, None is the identity
+        p = self.p
+        if point is None:
+            return other
+        if other is None:
+            return point
+        (x1, y1), (x2, y2) = point, other
+        if x1 == x2 and (y1 + y2) % p == 0:
+            return None
+        if point == other:
+            slope = (3 * x1 * x1 + self.a) * self.inv0(2 * y1) % p
+        else:
+            slope = (y2 - y1) * self.inv0(x2 - x1) % p
+        x3 = (slope * slope - x1 - x2) % p
+        return x3, (slope * (x1 - x3) - y1) % p
+
+    def hash_to_curve(self, hash_fn, msg, dst):
+        # The cofactor of both curves is 1
+        u0, u1 = self.hash_to_field(hash_fn, msg, dst, 2)
+        point = self.add(self.map_to_curve_simple_swu(u0), self.map_to_curve_simple_swu(u1))
+        assert point is not None and point[1] ** 2 % self.p == self.g(point[0])
+        return point
+
+
+def encode(point):
+    # Compressed SEC1
+    x, y = point
+    return (bytes([2 | y % 2]) + x.to_bytes(32, "big")).hex()
+
+
+def main():
+    p256 = Curve(P256)
+    assert p256.z == p256.p - 10
+    for msg, x, y in P256_VECTORS:
+        assert p256.hash_to_curve(sha256, msg, P256_DST) == (x, y)
+    print("P-256 vectors of RFC 9380 reproduced")
+
+    sm2 = Curve(SM2)
+    print("Z =", sm2.z - sm2.p)
+    for name, hash_fn, dst in (("SHA-256", sha256, SHA256_DST), ("SM3", sm3, SM3_DST)):
+        print(name, dst.decode())
+        for msg in MESSAGES:
+            label = msg if len(msg) <= 16 else msg[:5] + b"..."
+            print(label.decode(), encode(sm2.hash_to_curve(hash_fn, msg, dst)))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,223 @@
+#!/usr/bin/env python3
+"""
+Builds the firmware image of `firmware/` for a Cortex-M33 with every suite and
+mode, with and without the `small-stack` feature, and prints the most stack the
+client of the image can take. The release profile of `firmware/Cargo.toml` is
+used and every image is built with
+
+    RUSTFLAGS="-Z emit-stack-sizes -C symbol-mangling-version=legacy \\
+        -Z unstable-options" cargo +nightly-2026-05-20 build --release \\
+        --target thumbv8m.main-none-eabi --features <features>
+
+in `firmware/`, where the features are the suite, the mode unless it is the
+base mode and `small-stack` when enabled. The toolchain needs the target and
+the `llvm-tools` component:
+
+    scripts/stack.py [--limit <bytes>] [<suite>...]
+
+The frame of every function is read from the `.stack_sizes` section LLVM emits
+and, for the precompiled functions of `compiler_builtins`, from its prologue.
+Calls are read from the disassembly, calls through a register are assumed to
+reach every function whose address the caller loads, and a recursive or
+unresolved call fails the measurement, so the result bounds the stack depth of
+the client. With a limit, fails if an image with `small-stack` takes more.
+"""
+
+import argparse
+import os
+import re
+import subprocess
+import sys
+
+NIGHTLY = "nightly-2026-05-20"
+TARGET = "thumbv8m.main-none-eabi"
+HOST = "x86_64-unknown-linux-gnu"
+ELF = "target/" + TARGET + "/release/voprf-firmware"
+
+WORD = re.compile(r"^\s*([0-9a-f]+):.*\s\.word\s+0x([0-9a-f]+)")
+INSTRUCTION = re.compile(r"^\s*([0-9a-f]+):\s+(\S+)\s*(.*)$")
+REGISTERS = {"r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10",
+             "r11", "r12"}
+
+
+def run(*command, env=None):
+    return subprocess.run(command, check=True, stdout=subprocess.PIPE, env=env,
+                          universal_newlines=True).stdout
+
+
+def tool(name):
+    """Returns the path of an LLVM tool of the `llvm-tools` component"""
+    sysroot = run("rustc", "+" + NIGHTLY, "--print", "sysroot").strip()
+    return os.path.join(sysroot, "lib", "rustlib", HOST, "bin", name)
+
+
+def build(features):
+    env = dict(os.environ, RUSTFLAGS="-Z emit-stack-sizes "
+               "-C symbol-mangling-version=legacy -Z unstable-options")
+    run("cargo", "+" + NIGHTLY, "build", "--quiet", "--release", "--target",
+        TARGET, "--features", features, env=env)
+
+
+def functions():
+    """Returns the start address and the name of every function by address"""
+    result = {}
+    for line in run(tool("llvm-readobj"), "--elf-output-style=GNU",
+                       "--symbols", "--wide", ELF).splitlines():
+        fields = line.split()
+        if len(fields) == 8 and fields[3] == "FUNC":
+            result[int(fields[1], 16) & ~1] = fields[7]
+    return result
+
+
+def stack_sizes():
+    """Returns the frame size LLVM emitted for every function by name"""
+    result = {}
+    names = []
+    for line in run(tool("llvm-readobj"), "--stack-sizes", ELF).splitlines():
+        line = line.strip()
+        if line.startswith("Functions: ["):
+            names = line[len("Functions: ["):-1].split(", ")
+        elif line.startswith("Size: "):
+            for name in names:
+                result[name] = int(line[len("Size: "):], 0)
+    return result
+
+
+def disassembly(starts):
+    """Returns the instructions of every function and the words of the image"""
+    instructions = {}
+    words = {}
+    current = None
+    for line in run(tool("llvm-objdump"), "--disassemble", "--no-show-raw-insn",
+                    ELF).splitlines():
+        match = re.match(r"^([0-9a-f]+) <", line)
+        if match and int(match.group(1), 16) in starts:
+            current = instructions.setdefault(int(match.group(1), 16), [])
+            continue
+        match = WORD.match(line)
+        if match:
+            words[int(match.group(1), 16)] = int(match.group(2), 16)
+            continue
+        match = INSTRUCTION.match(line)
+        if match and current is not None:
+            current.append((match.group(2), match.group(3)))
+    return instructions, words
+
+
+def prologue(name, instructions):
+    """Returns the frame size of a function without an emitted one"""
+    size = 0
+    for mnemonic, operands in instructions:
+        registers = re.match(r"^\{(.*)\}", operands)
+        if mnemonic in ("push", "push.w") and registers:
+            size += 4 * count(registers.group(1))
+        elif mnemonic == "vpush" and registers:
+            size += 8 * count(registers.group(1))
+        elif mnemonic in ("sub", "sub.w", "subw") and operands.startswith("sp"):
+            immediate = re.search(r"#(\w+)$", operands)
+            if not immediate:
+                sys.exit("{}: dynamic stack allocation".format(name))
+            size += int(immediate.group(1), 0)
+        elif mnemonic.startswith("b") or mnemonic in ("pop", "pop.w"):
+            break
+    return size
+
+
+def count(registers):
+    result = 0
+    for register in registers.split(", "):
+        ends = re.findall(r"\d+", register)
+        result += 1 if len(ends) < 2 else int(ends[1]) - int(ends[0]) + 1
+    return result
+
+
+def measure():
+    starts = functions()
+    emitted = stack_sizes()
+    instructions, words = disassembly(starts)
+    frames = {}
+    calls = {}
+
+    for start, body in instructions.items():
+        name = starts[start]
+        frames[start] = emitted.get(name, None)
+        if frames[start] is None:
+            frames[start] = prologue(name, body)
+
+        # Function addresses loaded from literal pools, the targets of calls
+        # through registers
+        loaded = set()
+        for mnemonic, operands in body:
+            literal = re.search(r"\[pc, #-?\w+\]\s+@ 0x([0-9a-f]+)", operands)
+            if mnemonic.startswith("ldr") and literal:
+                word = words.get(int(literal.group(1), 16), 0)
+                if word & 1 and word & ~1 in starts:
+                    loaded.add(word & ~1)
+
+        targets = set()
+        for mnemonic, operands in body:
+            operand = operands.split()[0] if operands else ""
+            if mnemonic in ("bl", "blx") and operand.startswith("0x"):
+                targets.add(int(operand, 16))
+            elif mnemonic in ("b", "b.w") and operand.startswith("0x"):
+                # Tail calls, branches within the function are ignored
+                if int(operand, 16) in starts and int(operand, 16) != start:
+                    targets.add(int(operand, 16))
+            elif mnemonic in ("blx", "bx") and operand in REGISTERS:
+                if mnemonic == "bx" and not loaded:
+                    continue  # A jump within the function
+                if not loaded:
+                    sys.exit("{}: unresolved call through {}".format(name, operand))
+                targets |= loaded
+        calls[start] = targets
+
+    depths = {}
+
+    def depth(function, path):
+        if function in path:
+            sys.exit("recursion through {}".format(starts[function]))
+        if function not in depths:
+            callees = calls.get(function, ())
+            deepest = max((depth(callee, path | {function})
+                           for callee in callees), default=0)
+            depths[function] = frames.get(function, 0) + deepest
+        return depths[function]
+
+    entry = next(start for start, name in starts.items()
+                 if name.startswith("_ZN14voprf_firmware6client"))
+    return depth(entry, frozenset())
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--limit", type=int)
+    parser.add_argument("suites", nargs="*",
+                        default=["ristretto255", "p256", "secp256k1"])
+    arguments = parser.parse_args()
+
+    os.chdir(os.path.join(os.path.dirname(os.path.abspath(__file__)), "..",
+                          "firmware"))
+    print("{:<14} {:<6} {:<11} {:>8}".format("suite", "mode", "small-stack",
+                                            "stack"))
+    exceeded = False
+    for suite in arguments.suites:
+        for mode in ("oprf", "voprf", "poprf"):
+            for small in ("no", "yes"):
+                features = [suite]
+                if mode != "oprf":
+                    features.append(mode)
+                if small == "yes":
+                    features.append("small-stack")
+                build(",".join(features))
+                stack = measure()
+                print("{:<14} {:<6} {:<11} {:>8}".format(suite, mode, small,
+                                                        stack))
+                exceeded |= (small == "yes" and arguments.limit is not None
+                             and stack > arguments.limit)
+    if exceeded:
+        sys.exit("An image with small-stack takes more than {} bytes".format(
+            arguments.limit))
+
+
+if __name__ == "__main__":
+    main()
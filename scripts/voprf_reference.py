@@ -0,0 +1,362 @@
+#!/usr/bin/env python3
+# Copyright (c) Facebook, Inc. and its affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+"""Reference implementation of the VOPRF specification for the `interop` tests.
+
+Reads the parameters of a test vector as a JSON object on standard input and
+prints the missing fields, see `src/tests/interop.rs`:
+
+    VOPRF_REFERENCE="python3 scripts/voprf_reference.py" \\
+        cargo test --features interop interop
+
+Covers the ristretto255 and P-256 suites with Python integers and the standard
+library only, following the pseudocode of draft-irtf-cfrg-voprf-10, RFC 9496
+and RFC 9380 literally. The protocol is the one of RFC 9497, only the context
+strings still follow the draft, like in this crate. Elements are kept in affine
+coordinates and nothing is constant time.
+"""
+
+import hashlib
+import json
+import sys
+
+MODES = {"OPRF": 0, "VOPRF": 1, "POPRF": 2}
+
+
+def i2osp(value, length):
+    return value.to_bytes(length, "big")
+
+
+def length_prefixed(data):
+    return i2osp(len(data), 2) + data
+
+
+def expand_message_xmd(hash_fn, msg, dst, len_in_bytes):
+    # Section 5.3.1 of RFC 9380
+    b_in_bytes, r_in_bytes = hash_fn().digest_size, hash_fn().block_size
+    ell = -(-len_in_bytes // b_in_bytes)
+    assert ell <= 255 and len(dst) <= 255
+    dst_prime = dst + bytes([len(dst)])
+    msg_prime = bytes(r_in_bytes) + msg + i2osp(len_in_bytes, 2) + b"\x00" + dst_prime
+    b_0 = hash_fn(msg_prime).digest()
+    b = [hash_fn(b_0 + b"\x01" + dst_prime).digest()]
+    for i in range(2, ell + 1):
+        xored = bytes(x ^ y for x, y in zip(b_0, b[-1]))
+        b.append(hash_fn(xored + bytes([i]) + dst_prime).digest())
+    return b"".join(b)[:len_in_bytes]
+
+
+class Ristretto255:
+    # RFC 9496, on the twisted Edwards curve -x^2 + y^2 = 1 + d * x^2 * y^2
+    identifier = 0x0001
+    hash_fn = hashlib.sha512
+    p = 2**255 - 19
+    order = 2**252 + 27742317777372353535851937790883648493
+    d = -121665 * pow(121666, p - 2, p) % p
+    SQRT_M1 = 19681161376707505956807079304988542015446066515923890162744021073123829784752
+    SQRT_AD_MINUS_ONE = (
+        25063068953384623474111414158702152701244531502492656460079210482610430750235
+    )
+    INVSQRT_A_MINUS_D = (
+        54469307008909316920995813868745141605393597292927456921205312896311721017578
+    )
+    ONE_MINUS_D_SQ = 1159843021668779879193775521855586647937357759715417654439879720876111806838
+    D_MINUS_ONE_SQ = 40440834346308536858101042469323190826248399146238708352240133220865137265952
+    identity = (0, 1)
+
+    def __init__(self):
+        p = self.p
+        y = 4 * pow(5, p - 2, p) % p
+        _, x = self.sqrt_ratio_m1((y * y - 1) % p, (self.d * y * y + 1) % p)
+        self.generator = (x, y)
+
+    def is_negative(self, x):
+        return x % self.p % 2 == 1
+
+    def abs(self, x):
+        return -x % self.p if self.is_negative(x) else x % self.p
+
+    def sqrt_ratio_m1(self, u, v):
+        p = self.p
+        r = u * pow(v, 3, p) * pow(u * pow(v, 7, p), (p - 5) // 8, p) % p
+        check = v * r * r % p
+        correct_sign_sqrt = check == u % p
+        flipped_sign_sqrt = check == -u % p
+        flipped_sign_sqrt_i = check == -u * self.SQRT_M1 % p
+        if flipped_sign_sqrt or flipped_sign_sqrt_i:
+            r = self.SQRT_M1 * r % p
+        return correct_sign_sqrt or flipped_sign_sqrt, self.abs(r)
+
+    def add(self, point, other):
+        p = self.p
+        (x1, y1), (x2, y2) = point, other
+        t = self.d * x1 * x2 * y1 * y2 % p
+        x3 = (x1 * y2 + y1 * x2) * pow(1 + t, p - 2, p) % p
+        y3 = (y1 * y2 + x1 * x2) * pow(1 - t, p - 2, p) % p
+        return x3, y3
+
+    def serialize_element(self, point):
+        p = self.p
+        (x0, y0), z0 = point, 1
+        t0 = x0 * y0 % p
+        u1 = (z0 + y0) * (z0 - y0) % p
+        u2 = x0 * y0 % p
+        _, invsqrt = self.sqrt_ratio_m1(1, u1 * u2 * u2 % p)
+        den1 = invsqrt * u1 % p
+        den2 = invsqrt * u2 % p
+        z_inv = den1 * den2 * t0 % p
+        if self.is_negative(t0 * z_inv):
+            x, y = y0 * self.SQRT_M1 % p, x0 * self.SQRT_M1 % p
+            den_inv = den1 * self.INVSQRT_A_MINUS_D % p
+        else:
+            x, y, den_inv = x0, y0, den2
+        if self.is_negative(x * z_inv):
+            y = -y % p
+        return self.abs(den_inv * (z0 - y)).to_bytes(32, "little")
+
+    def map(self, t):
+        p, d = self.p, self.d
+        r = self.SQRT_M1 * t * t % p
+        u = (r + 1) * self.ONE_MINUS_D_SQ % p
+        v = (-1 - r * d) * (r + d) % p
+        was_square, s = self.sqrt_ratio_m1(u, v)
+        c = -1
+        if not was_square:
+            s, c = -self.abs(s * t) % p, r
+        n = (c * (r - 1) * self.D_MINUS_ONE_SQ - v) % p
+        w0 = 2 * s * v
+        w1 = n * self.SQRT_AD_MINUS_ONE
+        w2 = 1 - s * s
+        w3 = 1 + s * s
+        # The extended coordinates (w0 * w3, w2 * w1, w1 * w3, w0 * w2)
+        z_inv = pow(w1 * w3, p - 2, p)
+        return w0 * w3 * z_inv % p, w2 * w1 * z_inv % p
+
+    def hash_to_group(self, msg, dst):
+        uniform_bytes = expand_message_xmd(self.hash_fn, msg, dst, 64)
+        halves = (uniform_bytes[:32], uniform_bytes[32:])
+        t0, t1 = (int.from_bytes(half, "little") % 2**255 for half in halves)
+        return self.add(self.map(t0), self.map(t1))
+
+    def hash_to_scalar(self, msg, dst):
+        uniform_bytes = expand_message_xmd(self.hash_fn, msg, dst, 64)
+        return int.from_bytes(uniform_bytes, "little") % self.order
+
+    def serialize_scalar(self, scalar):
+        return scalar.to_bytes(32, "little")
+
+    def deserialize_scalar(self, data):
+        return int.from_bytes(data, "little")
+
+
+class P256:
+    # Section 8.2 of RFC 9380 for hashing
+    identifier = 0x0003
+    hash_fn = hashlib.sha256
+    p = 2**256 - 2**224 + 2**192 + 2**96 - 1
+    order = 0xFFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551
+    a = -3 % p
+    b = 0x5AC635D8AA3A93E7B3EBBD55769886BC651D06B0CC53B0F63BCE3C3E27D2604B
+    z = -10 % p
+    generator = (
+        0x6B17D1F2E12C4247F8BCE6E563A440F277037D812DEB33A0F4A13945D898C296,
+        0x4FE342E2FE1A7F9B8EE7EB4A7C0F9E162BCE33576B315ECECBB6406837BF51F5,
+    )
+    # None is the point at infinity
+    identity = None
+    L = 48
+
+    def inv0(self, x):
+        return pow(x, self.p - 2, self.p)
+
+    def g(self, x):
+        return (x * x * x + self.a * x + self.b) % self.p
+
+    def is_square(self, x):
+        return x % self.p == 0 or pow(x, (self.p - 1) // 2, self.p) == 1
+
+    def sqrt(self, x):
+        return pow(x, (self.p + 1) // 4, self.p)
+
+    def add(self, point, other):
+        p = self.p
+        if point is None:
+            return other
+        if other is None:
+            return point
+        (x1, y1), (x2, y2) = point, other
+        if x1 == x2 and (y1 + y2) % p == 0:
+            return None
+        if point == other:
+            slope = (3 * x1 * x1 + self.a) * self.inv0(2 * y1) % p
+        else:
+            slope = (y2 - y1) * self.inv0(x2 - x1) % p
+        x3 = (slope * slope - x1 - x2) % p
+        return x3, (slope * (x1 - x3) - y1) % p
+
+    def serialize_element(self, point):
+        # Compressed SEC1, the identity isn't serializable
+        x, y = point
+        return bytes([2 | y % 2]) + x.to_bytes(32, "big")
+
+    def map_to_curve_simple_swu(self, u):
+        # Section 6.6.2 of RFC 9380
+        p, a, b, z = self.p, self.a, self.b, self.z
+        tv1 = self.inv0(z * z * pow(u, 4, p) + z * u * u)
+        if tv1 == 0:
+            x1 = b * self.inv0(z * a) % p
+        else:
+            x1 = -b * self.inv0(a) * (1 + tv1) % p
+        x2 = z * u * u * x1 % p
+        x = x1 if self.is_square(self.g(x1)) else x2
+        y = self.sqrt(self.g(x))
+        if u % 2 != y % 2:
+            y = -y % p
+        return x, y
+
+    def hash_to_group(self, msg, dst):
+        uniform_bytes = expand_message_xmd(self.hash_fn, msg, dst, 2 * self.L)
+        u0, u1 = (int.from_bytes(uniform_bytes[i * self.L : (i + 1) * self.L], "big") % self.p for i in range(2))
+        return self.add(self.map_to_curve_simple_swu(u0), self.map_to_curve_simple_swu(u1))
+
+    def hash_to_scalar(self, msg, dst):
+        uniform_bytes = expand_message_xmd(self.hash_fn, msg, dst, self.L)
+        return int.from_bytes(uniform_bytes, "big") % self.order
+
+    def serialize_scalar(self, scalar):
+        return scalar.to_bytes(32, "big")
+
+    def deserialize_scalar(self, data):
+        return int.from_bytes(data, "big")
+
+
+SUITES = {"ristretto255, SHA-512": Ristretto255, "P-256, SHA-256": P256}
+
+
+class Oprf:
+    def __init__(self, group, mode):
+        self.group = group
+        self.context = b"VOPRF10-" + i2osp(MODES[mode], 1) + i2osp(group.identifier, 2)
+
+    def hash(self, data):
+        return self.group.hash_fn(data).digest()
+
+    def hash_to_scalar(self, msg):
+        return self.group.hash_to_scalar(msg, b"HashToScalar-" + self.context)
+
+    def hash_to_group(self, msg):
+        return self.group.hash_to_group(msg, b"HashToGroup-" + self.context)
+
+    def mul(self, scalar, point):
+        result = self.group.identity
+        for bit in bin(scalar % self.group.order)[2:]:
+            result = self.group.add(result, result)
+            if bit == "1":
+                result = self.group.add(result, point)
+        return result
+
+    def element(self, point):
+        assert point != self.group.identity
+        return self.group.serialize_element(point)
+
+    def derive_key_pair(self, seed, info):
+        # Section 3.2.1 of RFC 9497
+        derive_input = seed + length_prefixed(info)
+        for counter in range(256):
+            sk = self.group.hash_to_scalar(
+                derive_input + i2osp(counter, 1), b"DeriveKeyPair" + self.context
+            )
+            if sk != 0:
+                return sk, self.mul(sk, self.group.generator)
+        raise ValueError("DeriveKeyPairError")
+
+    def compute_composites_fast(self, k, b, cs, ds):
+        # Section 2.2.1 of RFC 9497
+        seed = self.hash(length_prefixed(self.element(b)) + length_prefixed(b"Seed-" + self.context))
+        m = self.group.identity
+        for i, (c, d) in enumerate(zip(cs, ds)):
+            composite_transcript = (
+                length_prefixed(seed)
+                + i2osp(i, 2)
+                + length_prefixed(self.element(c))
+                + length_prefixed(self.element(d))
+                + b"Composite"
+            )
+            m = self.group.add(self.mul(self.hash_to_scalar(composite_transcript), c), m)
+        return m, self.mul(k, m)
+
+    def generate_proof(self, k, a, b, cs, ds, r):
+        # Section 2.2.1 of RFC 9497
+        m, z = self.compute_composites_fast(k, b, cs, ds)
+        t2 = self.mul(r, a)
+        t3 = self.mul(r, m)
+        challenge_transcript = b"".join(
+            length_prefixed(self.element(point)) for point in (b, m, z, t2, t3)
+        )
+        c = self.hash_to_scalar(challenge_transcript + b"Challenge")
+        s = (r - c * k) % self.group.order
+        return self.group.serialize_scalar(c) + self.group.serialize_scalar(s)
+
+
+def complete(request):
+    group = SUITES[request["Suite"]]()
+    mode = request["Mode"]
+    oprf = Oprf(group, mode)
+    order = group.order
+
+    sk, pk = oprf.derive_key_pair(bytes.fromhex(request["Seed"]), bytes.fromhex(request["KeyInfo"]))
+    inputs = [bytes.fromhex(x) for x in request["Input"].split(",")]
+    blinds = [group.deserialize_scalar(bytes.fromhex(x)) for x in request["Blind"].split(",")]
+    info = bytes.fromhex(request.get("Info", ""))
+
+    blinded = [oprf.mul(blind, oprf.hash_to_group(x)) for x, blind in zip(inputs, blinds)]
+    if mode == "POPRF":
+        # Section 3.3.3 of RFC 9497
+        framed_info = b"Info" + length_prefixed(info)
+        t = (sk + oprf.hash_to_scalar(framed_info)) % order
+        assert t != 0
+        evaluated = [oprf.mul(pow(t, -1, order), element) for element in blinded]
+    else:
+        t = sk
+        evaluated = [oprf.mul(sk, element) for element in blinded]
+
+    response = {
+        "skSm": group.serialize_scalar(sk).hex(),
+        "pkSm": oprf.element(pk).hex(),
+        "BlindedElement": ",".join(oprf.element(x).hex() for x in blinded),
+        "EvaluationElement": ",".join(oprf.element(x).hex() for x in evaluated),
+    }
+
+    r = group.deserialize_scalar(bytes.fromhex(request.get("ProofRandomScalar", "")))
+    if mode == "VOPRF":
+        proof = oprf.generate_proof(sk, group.generator, pk, blinded, evaluated, r)
+        response["Proof"] = proof.hex()
+    elif mode == "POPRF":
+        tweaked_key = oprf.mul(t, group.generator)
+        proof = oprf.generate_proof(t, group.generator, tweaked_key, evaluated, blinded, r)
+        response["Proof"] = proof.hex()
+
+    outputs = []
+    for x, blind, element in zip(inputs, blinds, evaluated):
+        unblinded = oprf.element(oprf.mul(pow(blind, -1, order), element))
+        if mode == "POPRF":
+            hash_input = length_prefixed(x) + length_prefixed(info) + length_prefixed(unblinded)
+        else:
+            hash_input = length_prefixed(x) + length_prefixed(unblinded)
+        outputs.append(oprf.hash(hash_input + b"Finalize").hex())
+    response["Output"] = ",".join(outputs)
+
+    return response
+
+
+def main():
+    print(json.dumps(complete(json.load(sys.stdin))))
+
+
+if __name__ == "__main__":
+    main()
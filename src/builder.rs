@@ -0,0 +1,324 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains a builder for the servers of all modes
+
+use core::fmt::{self, Debug, Formatter};
+use core::marker::PhantomData;
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{CipherSuite, OprfServer, PoprfServer, Result, VoprfServer};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// Builder for an [`OprfServer`], [`VoprfServer`] or [`PoprfServer`], created
+/// by their `builder` function.
+///
+/// The source of the private key has to be chosen exactly once, with
+/// [`rng`](Self::rng), [`seed`](Self::seed) or [`key`](Self::key), before the
+/// server can be built. Choosing none or several of them, or deriving from a
+/// seed without an `info`, doesn't compile:
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::VoprfServer;
+///
+/// let server = VoprfServer::<CipherSuite>::builder()
+///     .seed(b"seed", b"info")
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     server.serialize(),
+///     VoprfServer::<CipherSuite>::new_from_seed(b"seed", b"info")
+///         .unwrap()
+///         .serialize()
+/// );
+///
+/// let server = VoprfServer::<CipherSuite>::builder()
+///     .rng(&mut OsRng)
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// ```compile_fail
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::VoprfServer;
+///
+/// let server = VoprfServer::<CipherSuite>::builder()
+///     .rng(&mut OsRng)
+///     .seed(b"seed", b"info")
+///     .build();
+/// ```
+pub struct ServerBuilder<S, K> {
+    source: K,
+    server: PhantomData<S>,
+}
+
+/// Key source of a [`ServerBuilder`] that wasn't chosen yet.
+#[derive(Clone, Copy, Debug)]
+pub struct NoKeySource;
+
+/// Key source of a [`ServerBuilder`] that generates a random private key, see
+/// [`ServerBuilder::rng`].
+pub struct RngKeySource<'a, R>(&'a mut R);
+
+/// Key source of a [`ServerBuilder`] that derives the private key from a seed,
+/// see [`ServerBuilder::seed`].
+#[derive(Clone, Copy)]
+pub struct SeedKeySource<'a> {
+    seed: &'a [u8],
+    info: &'a [u8],
+}
+
+/// Key source of a [`ServerBuilder`] that uses an existing private key, see
+/// [`ServerBuilder::key`].
+#[derive(Clone, Copy)]
+pub struct PrivateKeySource<'a>(&'a [u8]);
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<S> ServerBuilder<S, NoKeySource> {
+    fn new() -> Self {
+        Self {
+            source: NoKeySource,
+            server: PhantomData,
+        }
+    }
+
+    /// Generates a random private key with the supplied RNG, like `new`.
+    pub fn rng<R: RngCore + CryptoRng>(self, rng: &mut R) -> ServerBuilder<S, RngKeySource<'_, R>> {
+        ServerBuilder {
+            source: RngKeySource(rng),
+            server: PhantomData,
+        }
+    }
+
+    /// Derives the private key from `seed` and `info`, like `new_from_seed`.
+    pub fn seed<'a>(self, seed: &'a [u8], info: &'a [u8]) -> ServerBuilder<S, SeedKeySource<'a>> {
+        ServerBuilder {
+            source: SeedKeySource { seed, info },
+            server: PhantomData,
+        }
+    }
+
+    /// Uses the serialized private key `key`, like `new_with_key`.
+    pub fn key(self, key: &[u8]) -> ServerBuilder<S, PrivateKeySource<'_>> {
+        ServerBuilder {
+            source: PrivateKeySource(key),
+            server: PhantomData,
+        }
+    }
+}
+
+/// Implements `builder` and the `build` functions for every key source on the
+/// given server.
+macro_rules! impl_builder {
+    ($server:ident) => {
+        impl<CS: CipherSuite> $server<CS>
+        where
+            <CS::Hash as OutputSizeUser>::OutputSize:
+                IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        {
+            /// Starts building the server, see [`ServerBuilder`].
+            pub fn builder() -> ServerBuilder<Self, NoKeySource> {
+                ServerBuilder::new()
+            }
+        }
+
+        impl<CS: CipherSuite, R: RngCore + CryptoRng>
+            ServerBuilder<$server<CS>, RngKeySource<'_, R>>
+        where
+            <CS::Hash as OutputSizeUser>::OutputSize:
+                IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        {
+            /// Builds the server, like `new`.
+            ///
+            /// # Errors
+            /// [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+            /// fails and can't be completed.
+            pub fn build(self) -> Result<$server<CS>> {
+                $server::new(self.source.0)
+            }
+        }
+
+        impl<CS: CipherSuite> ServerBuilder<$server<CS>, SeedKeySource<'_>>
+        where
+            <CS::Hash as OutputSizeUser>::OutputSize:
+                IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        {
+            /// Builds the server, like `new_from_seed`.
+            ///
+            /// # Errors
+            /// - [`ErrorKind::DeriveKeyPair`](crate::ErrorKind::DeriveKeyPair) if the
+            ///   `info` and `seed` together are longer then `u16::MAX - 3`.
+            /// - [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+            ///   fails and can't be completed.
+            pub fn build(self) -> Result<$server<CS>> {
+                $server::new_from_seed(self.source.seed, self.source.info)
+            }
+        }
+
+        impl<CS: CipherSuite> ServerBuilder<$server<CS>, PrivateKeySource<'_>>
+        where
+            <CS::Hash as OutputSizeUser>::OutputSize:
+                IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        {
+            /// Builds the server, like `new_with_key`.
+            ///
+            /// # Errors
+            /// [`ErrorKind::Deserialization`](crate::ErrorKind::Deserialization)
+            /// if the private key is not a valid point on the group or zero.
+            pub fn build(self) -> Result<$server<CS>> {
+                $server::new_with_key(self.source.0)
+            }
+        }
+    };
+}
+
+impl_builder!(OprfServer);
+impl_builder!(VoprfServer);
+impl_builder!(PoprfServer);
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+impl<S, K: Debug> Debug for ServerBuilder<S, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerBuilder")
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+/// The RNG isn't printed, as it may not implement [`Debug`].
+impl<R> Debug for RngKeySource<'_, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RngKeySource").finish_non_exhaustive()
+    }
+}
+
+/// The seed isn't printed, as it is secret.
+impl Debug for SeedKeySource<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeedKeySource")
+            .field("info", &self.info)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The private key isn't printed, as it is secret.
+impl Debug for PrivateKeySource<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivateKeySource").finish_non_exhaustive()
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::Group;
+
+    fn build_servers<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+
+        let server = OprfServer::<CS>::builder()
+            .seed(b"seed", b"info")
+            .build()
+            .unwrap();
+        assert_eq!(
+            CS::Group::serialize_scalar(server.get_private_key()),
+            CS::Group::serialize_scalar(
+                OprfServer::<CS>::new_from_seed(b"seed", b"info")
+                    .unwrap()
+                    .get_private_key()
+            )
+        );
+        let key = CS::Group::serialize_scalar(server.get_private_key());
+        let server = OprfServer::<CS>::builder().key(&key).build().unwrap();
+        assert_eq!(CS::Group::serialize_scalar(server.get_private_key()), key);
+
+        let server = VoprfServer::<CS>::builder()
+            .seed(b"seed", b"info")
+            .build()
+            .unwrap();
+        assert_eq!(
+            CS::Group::serialize_scalar(server.get_private_key()),
+            CS::Group::serialize_scalar(
+                VoprfServer::<CS>::new_from_seed(b"seed", b"info")
+                    .unwrap()
+                    .get_private_key()
+            )
+        );
+        let key = CS::Group::serialize_scalar(server.get_private_key());
+        let server = VoprfServer::<CS>::builder().key(&key).build().unwrap();
+        assert_eq!(CS::Group::serialize_scalar(server.get_private_key()), key);
+        VoprfServer::<CS>::builder().rng(&mut rng).build().unwrap();
+
+        let server = PoprfServer::<CS>::builder()
+            .seed(b"seed", b"info")
+            .build()
+            .unwrap();
+        assert_eq!(
+            CS::Group::serialize_scalar(server.get_private_key()),
+            CS::Group::serialize_scalar(
+                PoprfServer::<CS>::new_from_seed(b"seed", b"info")
+                    .unwrap()
+                    .get_private_key()
+            )
+        );
+        PoprfServer::<CS>::builder().rng(&mut rng).build().unwrap();
+
+        assert!(PoprfServer::<CS>::builder().key(&[0; 1]).build().is_err());
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            build_servers::<Ristretto255>();
+        }
+
+        build_servers::<NistP256>();
+
+        Ok(())
+    }
+}
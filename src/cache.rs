@@ -0,0 +1,379 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the memoization of server evaluations
+
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::iter;
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(any(feature = "metrics", feature = "tracing"))]
+use crate::common::Mode;
+#[cfg(feature = "alloc")]
+use crate::VoprfServerBatchEvaluateResult;
+use crate::{
+    BlindedElement, CipherSuite, EvaluationElement, Group, OprfServer, Result, VoprfServer,
+    VoprfServerEvaluateResult,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// Storage for [`EvaluationElement`]s, keyed by the serialized
+/// [`BlindedElement`] they were evaluated from, so repeated identical requests,
+/// e.g. retries or duplicate submissions, don't redo the scalar multiplication.
+///
+/// A cache is only valid for the key of a single server and has to be cleared
+/// when the key is rotated. Proofs are bound to the whole batch and are always
+/// computed fresh.
+pub trait EvaluationCache<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Retrieves the [`EvaluationElement`] of `blinded_element`, if present.
+    fn get(
+        &mut self,
+        blinded_element: &GenericArray<u8, <CS::Group as Group>::ElemLen>,
+    ) -> Option<EvaluationElement<CS>>;
+
+    /// Stores the [`EvaluationElement`] of `blinded_element`.
+    fn insert(
+        &mut self,
+        blinded_element: GenericArray<u8, <CS::Group as Group>::ElemLen>,
+        evaluation_element: EvaluationElement<CS>,
+    );
+
+    /// Called on every lookup that was found in the cache, e.g. for metrics.
+    fn record_hit(&mut self) {}
+
+    /// Called on every lookup that wasn't found in the cache, e.g. for metrics.
+    fn record_miss(&mut self) {}
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> OprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`OprfServer::blind_evaluate`], but looks up the evaluation in the
+    /// `cache` first. See [`EvaluationCache`].
+    pub fn blind_evaluate_cached<C: EvaluationCache<CS> + ?Sized>(
+        &self,
+        blinded_element: &BlindedElement<CS>,
+        cache: &mut C,
+    ) -> EvaluationElement<CS> {
+        debug_span!("blind_evaluate_cached", mode = ?Mode::Oprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Oprf.label());
+
+        evaluate_cached(self.sk, blinded_element, cache)
+    }
+}
+
+impl<CS: CipherSuite> VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`VoprfServer::try_blind_evaluate`], but looks up the evaluation in
+    /// the `cache` first. See [`EvaluationCache`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    /// fails and can't be completed.
+    pub fn blind_evaluate_cached<R: RngCore + CryptoRng, C: EvaluationCache<CS> + ?Sized>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        cache: &mut C,
+    ) -> Result<VoprfServerEvaluateResult<CS>> {
+        debug_span!("blind_evaluate_cached", mode = ?Mode::Voprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Voprf.label());
+
+        let message = evaluate_cached(self.sk, blinded_element, cache);
+        let proof = self.prove(rng, iter::once(blinded_element), iter::once(&message))?;
+
+        Ok(VoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Like [`VoprfServer::batch_blind_evaluate`], but looks up the evaluations
+    /// in the `cache` first. See [`EvaluationCache`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Batch`](crate::ErrorKind::Batch) if the number of
+    /// `blinded_elements` is longer then [`u16::MAX`]
+    #[cfg(feature = "alloc")]
+    pub fn batch_blind_evaluate_cached<'a, R, C, I>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &'a I,
+        cache: &mut C,
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>>
+    where
+        CS: 'a,
+        R: RngCore + CryptoRng,
+        C: EvaluationCache<CS> + ?Sized,
+        &'a I: IntoIterator<Item = &'a BlindedElement<CS>>,
+        <&'a I as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        debug_span!(
+            "batch_blind_evaluate_cached",
+            mode = ?Mode::Voprf,
+            batch_size = blinded_elements.into_iter().len(),
+        );
+        count!("voprf_evaluations_total", blinded_elements.into_iter().len(), "mode" => Mode::Voprf.label());
+        record!("voprf_batch_size", blinded_elements.into_iter().len(), "mode" => Mode::Voprf.label());
+
+        let messages: Vec<_> = blinded_elements
+            .into_iter()
+            .map(|blinded_element| evaluate_cached(self.sk, blinded_element, cache))
+            .collect();
+        let proof = self.prove(rng, blinded_elements.into_iter(), messages.iter())?;
+
+        Ok(VoprfServerBatchEvaluateResult { messages, proof })
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+/// An unbounded cache, mainly useful for short-lived servers or as a building
+/// block for a cache with an eviction policy.
+#[cfg(feature = "alloc")]
+impl<CS: CipherSuite> EvaluationCache<CS>
+    for BTreeMap<GenericArray<u8, <CS::Group as Group>::ElemLen>, EvaluationElement<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn get(
+        &mut self,
+        blinded_element: &GenericArray<u8, <CS::Group as Group>::ElemLen>,
+    ) -> Option<EvaluationElement<CS>> {
+        BTreeMap::get(self, blinded_element).cloned()
+    }
+
+    fn insert(
+        &mut self,
+        blinded_element: GenericArray<u8, <CS::Group as Group>::ElemLen>,
+        evaluation_element: EvaluationElement<CS>,
+    ) {
+        BTreeMap::insert(self, blinded_element, evaluation_element);
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+fn evaluate_cached<CS: CipherSuite, C: EvaluationCache<CS> + ?Sized>(
+    sk: <CS::Group as Group>::Scalar,
+    blinded_element: &BlindedElement<CS>,
+    cache: &mut C,
+) -> EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let key = CS::Group::serialize_elem(blinded_element.0);
+
+    if let Some(evaluation_element) = cache.get(&key) {
+        debug_event!("evaluation cache hit");
+        cache.record_hit();
+        return evaluation_element;
+    }
+
+    debug_event!("evaluation cache miss");
+    cache.record_miss();
+    let evaluation_element = EvaluationElement(blinded_element.0 * &sk);
+    cache.insert(key, evaluation_element.clone());
+
+    evaluation_element
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use ::alloc::collections::BTreeMap;
+    #[cfg(feature = "alloc")]
+    use ::alloc::vec;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{OprfClient, VoprfClient};
+
+    struct CountingCache<CS: CipherSuite>
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        evaluations:
+            BTreeMap<GenericArray<u8, <CS::Group as Group>::ElemLen>, EvaluationElement<CS>>,
+        hits: usize,
+        misses: usize,
+    }
+
+    impl<CS: CipherSuite> EvaluationCache<CS> for CountingCache<CS>
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        fn get(
+            &mut self,
+            blinded_element: &GenericArray<u8, <CS::Group as Group>::ElemLen>,
+        ) -> Option<EvaluationElement<CS>> {
+            self.evaluations.get(blinded_element).cloned()
+        }
+
+        fn insert(
+            &mut self,
+            blinded_element: GenericArray<u8, <CS::Group as Group>::ElemLen>,
+            evaluation_element: EvaluationElement<CS>,
+        ) {
+            self.evaluations.insert(blinded_element, evaluation_element);
+        }
+
+        fn record_hit(&mut self) {
+            self.hits += 1;
+        }
+
+        fn record_miss(&mut self) {
+            self.misses += 1;
+        }
+    }
+
+    fn cached_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let mut cache = CountingCache::<CS> {
+            evaluations: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
+        };
+
+        let client_blind_result = OprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        for _ in 0..2 {
+            let message = server.blind_evaluate_cached(&client_blind_result.message, &mut cache);
+            let output = client_blind_result.state.finalize(input, &message).unwrap();
+            assert_eq!(output, server.evaluate(input).unwrap());
+        }
+        assert_eq!((cache.hits, cache.misses), (1, 1));
+
+        // Retries of a verifiable request get a fresh proof
+        let mut cache = CountingCache::<CS> {
+            evaluations: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
+        };
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        for _ in 0..2 {
+            let result = server
+                .blind_evaluate_cached(&mut rng, &client_blind_result.message, &mut cache)
+                .unwrap();
+            let output = client_blind_result
+                .state
+                .finalize(
+                    input,
+                    &result.message,
+                    &result.proof,
+                    server.get_public_key(),
+                )
+                .unwrap();
+            assert_eq!(output, server.evaluate(input).unwrap());
+        }
+        assert_eq!((cache.hits, cache.misses), (1, 1));
+    }
+
+    #[cfg(feature = "alloc")]
+    fn verifiable_batch_cached_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let inputs: [&[u8]; 3] = [b"first", b"second", b"first"];
+        let mut rng = OsRng;
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        for input in inputs {
+            let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        // A duplicate submission of the first request
+        client_states.push(client_states[0].clone());
+        client_messages.push(client_messages[0].clone());
+        let inputs = [inputs[0], inputs[1], inputs[2], inputs[0]];
+
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let mut cache = BTreeMap::new();
+        let result = server
+            .batch_blind_evaluate_cached(&mut rng, &client_messages, &mut cache)
+            .unwrap();
+        assert_eq!(cache.len(), 3);
+        let outputs = VoprfClient::batch_finalize(
+            &inputs,
+            &client_states,
+            &result.messages,
+            &result.proof,
+            server.get_public_key(),
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+        for (input, output) in inputs.iter().zip(outputs) {
+            assert_eq!(output, server.evaluate(input).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            cached_retrieval::<Ristretto255>();
+            #[cfg(feature = "alloc")]
+            verifiable_batch_cached_retrieval::<Ristretto255>();
+        }
+
+        cached_retrieval::<NistP256>();
+        #[cfg(feature = "alloc")]
+        verifiable_batch_cached_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the proofs over chunks of huge batches
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+#[cfg(feature = "alloc")]
+use rand_core::{CryptoRng, RngCore};
+
+use crate::common::verify_proof;
+#[cfg(feature = "alloc")]
+use crate::{BlindedElement, EvaluationElement, VoprfServer};
+use crate::{CipherSuite, Group, Mode, Proof, Result};
+#[cfg(feature = "alloc")]
+use crate::{Error, ErrorKind, Operation};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A proof over a whole batch of evaluations, either a single [`Proof`] or a
+/// [`ChunkedProof`]. Accepted by
+/// [`VoprfClient::batch_finalize`](crate::VoprfClient::batch_finalize).
+pub trait BatchProof<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Verifies that the elements `ds` are the elements `cs` multiplied by the
+    /// discrete logarithm of `b` to the base `a`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Batch`](crate::ErrorKind) if the number of `cs` and `ds`
+    ///   don't match the proof.
+    /// - [`ErrorKind::ProofVerification`](crate::ErrorKind) if the proof failed
+    ///   to verify.
+    fn verify_batch<IC, ID>(
+        &self,
+        a: <CS::Group as Group>::Elem,
+        b: <CS::Group as Group>::Elem,
+        cs: IC,
+        ds: ID,
+        mode: Mode,
+    ) -> Result<()>
+    where
+        IC: ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        ID: ExactSizeIterator<Item = <CS::Group as Group>::Elem>;
+}
+
+/// Proofs over consecutive chunks of a batch, so huge batches don't have to be
+/// proven at once. Every chunk has `chunk_size` items, except the last one,
+/// which may be shorter.
+#[cfg(feature = "alloc")]
+#[derive_where(Clone)]
+#[derive_where(Debug, Eq, Hash, Ord, PartialEq, PartialOrd; <CS::Group as Group>::Scalar)]
+pub struct ChunkedProof<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    pub(crate) chunk_size: u16,
+    pub(crate) proofs: Vec<Proof<CS>>,
+}
+
+/// Contains the fields that are returned by a verifiable server batch evaluate
+/// with chunked proofs.
+#[cfg(feature = "alloc")]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct VoprfServerBatchEvaluateChunkedResult<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The messages to send to the client
+    pub messages: Vec<EvaluationElement<CS>>,
+    /// The proofs for the client to verify
+    pub proof: ChunkedProof<CS>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+#[cfg(feature = "alloc")]
+impl<CS: CipherSuite> ChunkedProof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Returns the number of items every chunk is proven over.
+    pub fn chunk_size(&self) -> u16 {
+        self.chunk_size
+    }
+
+    /// Returns the [`Proof`] of every chunk.
+    pub fn proofs(&self) -> &[Proof<CS>] {
+        &self.proofs
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<CS: CipherSuite> VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`VoprfServer::batch_blind_evaluate`], but proves every
+    /// `chunk_size` [`BlindedElement`]s separately. Batches up to `chunk_size`
+    /// get a single proof. See [`ChunkedProof`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Batch`] if `chunk_size` is zero.
+    pub fn batch_blind_evaluate_chunked<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        chunk_size: u16,
+    ) -> Result<VoprfServerBatchEvaluateChunkedResult<CS>> {
+        debug_span!(
+            "batch_blind_evaluate_chunked",
+            mode = ?Mode::Voprf,
+            batch_size = blinded_elements.len(),
+            chunk_size,
+        );
+        count!("voprf_evaluations_total", blinded_elements.len(), "mode" => Mode::Voprf.label());
+        record!("voprf_batch_size", blinded_elements.len(), "mode" => Mode::Voprf.label());
+
+        if chunk_size == 0 {
+            return Err(Error::new(ErrorKind::Batch, Operation::Evaluate));
+        }
+
+        let messages: Vec<_> = self.evaluate_only(blinded_elements.iter()).collect();
+        let proofs = blinded_elements
+            .chunks(chunk_size.into())
+            .zip(messages.chunks(chunk_size.into()))
+            .map(|(blinded_elements, messages)| {
+                self.prove(rng, blinded_elements.iter(), messages.iter())
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(VoprfServerBatchEvaluateChunkedResult {
+            messages,
+            proof: ChunkedProof { chunk_size, proofs },
+        })
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<CS: CipherSuite> BatchProof<CS> for Proof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn verify_batch<IC, ID>(
+        &self,
+        a: <CS::Group as Group>::Elem,
+        b: <CS::Group as Group>::Elem,
+        cs: IC,
+        ds: ID,
+        mode: Mode,
+    ) -> Result<()>
+    where
+        IC: ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        ID: ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    {
+        verify_proof(a, b, cs, ds, self, mode)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<CS: CipherSuite> BatchProof<CS> for ChunkedProof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The returned [`ErrorKind::ProofVerification`] carries the index of the
+    /// chunk that failed to verify.
+    fn verify_batch<IC, ID>(
+        &self,
+        a: <CS::Group as Group>::Elem,
+        b: <CS::Group as Group>::Elem,
+        mut cs: IC,
+        mut ds: ID,
+        mode: Mode,
+    ) -> Result<()>
+    where
+        IC: ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        ID: ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    {
+        let error = Error::new(ErrorKind::Batch, Operation::VerifyProof);
+        let chunk_size = usize::from(self.chunk_size);
+
+        if chunk_size == 0
+            || cs.len() != ds.len()
+            || (cs.len() + chunk_size - 1) / chunk_size != self.proofs.len()
+        {
+            return Err(error);
+        }
+
+        for (index, proof) in self.proofs.iter().enumerate() {
+            verify_proof(
+                a,
+                b,
+                cs.by_ref().take(chunk_size),
+                ds.by_ref().take(chunk_size),
+                proof,
+                mode,
+            )
+            .map_err(|error| error.with_index(index))?;
+        }
+
+        Ok(())
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use core::ops::Add;
+
+    use generic_array::typenum::Sum;
+    use generic_array::ArrayLength;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::VoprfClient;
+
+    fn verifiable_chunked_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+        Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let inputs: Vec<_> = (0u8..10).map(|index| [index; 8]).collect();
+        let mut client_states = Vec::new();
+        let mut client_messages = Vec::new();
+        for input in &inputs {
+            let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+
+        for (chunk_size, chunks) in [(3, 4), (5, 2), (10, 1), (20, 1)] {
+            let VoprfServerBatchEvaluateChunkedResult { messages, proof } = server
+                .batch_blind_evaluate_chunked(&mut rng, &client_messages, chunk_size)
+                .unwrap();
+            assert_eq!(proof.chunk_size(), chunk_size);
+            assert_eq!(proof.proofs().len(), chunks);
+
+            // The proofs survive serialization
+            let proof = ChunkedProof::<CS>::deserialize(&proof.serialize()).unwrap();
+
+            let outputs = VoprfClient::batch_finalize(
+                &inputs,
+                &client_states,
+                &messages,
+                &proof,
+                server.get_public_key(),
+            )
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+            for (input, output) in inputs.iter().zip(outputs) {
+                assert_eq!(output, server.evaluate(input).unwrap());
+            }
+        }
+
+        let VoprfServerBatchEvaluateChunkedResult {
+            mut messages,
+            proof,
+        } = server
+            .batch_blind_evaluate_chunked(&mut rng, &client_messages, 3)
+            .unwrap();
+
+        // Missing items don't match the chunks
+        assert!(matches!(
+            VoprfClient::batch_finalize(
+                &inputs[..9].to_vec(),
+                &client_states[..9].to_vec(),
+                &messages[..9].to_vec(),
+                &proof,
+                server.get_public_key(),
+            ),
+            Err(e) if e == Error::new(ErrorKind::Batch, Operation::VerifyProof)
+        ));
+
+        // The index of the failing chunk is reported
+        messages.swap(4, 5);
+        assert!(matches!(
+            VoprfClient::batch_finalize(
+                &inputs,
+                &client_states,
+                &messages,
+                &proof,
+                server.get_public_key(),
+            ),
+            Err(e) if e == Error::new(ErrorKind::ProofVerification, Operation::VerifyProof).with_index(1)
+        ));
+
+        assert!(matches!(
+            server.batch_blind_evaluate_chunked(&mut rng, &client_messages, 0),
+            Err(e) if e == Error::new(ErrorKind::Batch, Operation::Evaluate)
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            verifiable_chunked_retrieval::<Ristretto255>();
+        }
+
+        verifiable_chunked_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
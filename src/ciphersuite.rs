@@ -7,12 +7,20 @@
 
 //! Defines the CipherSuite trait to specify the underlying primitives for VOPRF
 
+use core::marker::PhantomData;
+
 use digest::core_api::BlockSizeUser;
 use digest::{Digest, OutputSizeUser};
 use elliptic_curve::VoprfParameters;
-use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use generic_array::ArrayLength;
+use rand_core::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
 
-use crate::Group;
+use crate::{
+    BlindedElement, Error, ErrorKind, Group, Operation, OprfClient, OprfServer, PoprfClient,
+    PoprfServer, Result, VoprfClient, VoprfServer,
+};
 
 /// Configures the underlying primitives used in VOPRF
 pub trait CipherSuite
@@ -28,9 +36,15 @@ where
     /// customization on how to hash an input to a curve point. See [`Group`].
     type Group: Group;
 
-    /// The main hash function to use (for HKDF computations and hashing
-    /// transcripts).
+    /// The main hash function to use (for hashing to the group, HKDF
+    /// computations and hashing transcripts).
     type Hash: BlockSizeUser + Digest;
+
+    /// The hash function of the Finalize step, which determines the outputs of
+    /// the protocol. The specification uses [`CipherSuite::Hash`], but other
+    /// specifications may e.g. hash to the group with SHA-256 and derive
+    /// 64-byte outputs with SHA-512.
+    type FinalizeHash: Digest;
 }
 
 impl<T: VoprfParameters> CipherSuite for T
@@ -45,4 +59,340 @@ where
     type Group = T;
 
     type Hash = T::Hash;
+
+    type FinalizeHash = T::Hash;
+}
+
+/// Bundles the bounds on the output size of a [`CipherSuite::Hash`], which
+/// Rust doesn't imply from [`CipherSuite`] itself. Implemented for every hash
+/// function that satisfies them.
+///
+/// Generic code can require `CS::Hash: SuitableHash` instead of repeating the
+/// `typenum` bounds of the output size:
+///
+/// ```
+/// use voprf::{CipherSuite, Result, SuitableHash, VoprfServer};
+///
+/// fn evaluate<CS: CipherSuite>(server: &VoprfServer<CS>, input: &[u8]) -> Result<Vec<u8>>
+/// where
+///     CS::Hash: SuitableHash,
+/// {
+///     Ok(server.evaluate(input)?.to_vec())
+/// }
+/// ```
+pub trait SuitableHash:
+    BlockSizeUser + Digest + OutputSizeUser<OutputSize = <Self as SuitableHash>::SuitableOutputSize>
+{
+    /// Equal to the output size of the hash function, with the necessary
+    /// bounds.
+    type SuitableOutputSize: ArrayLength<u8> + IsLess<U256> + IsLessOrEqual<Self::BlockSize>;
+}
+
+impl<H> SuitableHash for H
+where
+    H: BlockSizeUser + Digest,
+    H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+{
+    type SuitableOutputSize = H::OutputSize;
+}
+
+/// Defines a new [`CipherSuite`] from a [`Group`] and a hash function.
+///
+/// This generates a unit struct, its [`CipherSuite`] implementation with the
+/// given ID and a test module, named after `tests`, that runs
+/// [`check_conformance`] on it. The hash function has to implement
+/// [`Digest`] and [`BlockSizeUser`] and its output size has to be less then
+/// 256 bytes and not exceed its block size. An optional `finalize_hash`
+/// selects a different [`CipherSuite::FinalizeHash`] for the outputs, which
+/// only has to implement [`Digest`].
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # {
+/// voprf::impl_ciphersuite!(
+///     /// Ristretto255 with SHA-256.
+///     pub Ristretto255Sha256,
+///     group = voprf::Ristretto255,
+///     hash = sha2::Sha256,
+///     id = 0xFF01,
+///     tests = ristretto255_sha256_conformance,
+/// );
+///
+/// use voprf::CipherSuite;
+/// assert_eq!(Ristretto255Sha256::ID, 0xFF01);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! impl_ciphersuite {
+    (
+        $(#[$attr:meta])*
+        $vis:vis $name:ident,
+        group = $group:ty,
+        hash = $hash:ty,
+        id = $id:expr,
+        tests = $tests:ident $(,)?
+    ) => {
+        $crate::impl_ciphersuite!(
+            $(#[$attr])*
+            $vis $name,
+            group = $group,
+            hash = $hash,
+            finalize_hash = $hash,
+            id = $id,
+            tests = $tests,
+        );
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis $name:ident,
+        group = $group:ty,
+        hash = $hash:ty,
+        finalize_hash = $finalize_hash:ty,
+        id = $id:expr,
+        tests = $tests:ident $(,)?
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        $vis struct $name;
+
+        impl $crate::CipherSuite for $name {
+            const ID: u16 = $id;
+
+            type Group = $group;
+
+            type Hash = $hash;
+
+            type FinalizeHash = $finalize_hash;
+        }
+
+        #[cfg(test)]
+        mod $tests {
+            #[test]
+            fn conformance() {
+                $crate::check_conformance::<super::$name>().unwrap();
+            }
+        }
+    };
+}
+
+/// Checks that `CS` works with all modes of the protocol and that its group
+/// serializes consistently. Used by the tests generated by
+/// [`impl_ciphersuite`], but can also be called for manually implemented
+/// ciphersuites.
+///
+/// All randomness is derived deterministically from `CS::Hash`, so this must
+/// only be used for testing.
+///
+/// # Errors
+/// [`ErrorKind::Protocol`] if any check failed, or the error of the failing
+/// protocol step.
+pub fn check_conformance<CS: CipherSuite>() -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let input = b"conformance input";
+    let info = b"conformance info";
+    let mut rng = ConformanceRng::<CS> {
+        counter: 0,
+        _cs: PhantomData,
+    };
+
+    // Group serialization
+    let elem = CS::Group::base_elem() * &CS::Group::random_scalar(&mut rng);
+    let elem_bytes = CS::Group::serialize_elem(elem);
+    check(
+        CS::Group::deserialize_elem(&elem_bytes)?
+            .ct_eq(&elem)
+            .into(),
+    )?;
+    let identity_bytes = CS::Group::serialize_elem(CS::Group::identity_elem());
+    if cfg!(feature = "danger-allow-identity") {
+        check(CS::Group::is_identity_elem(CS::Group::deserialize_elem(&identity_bytes)?).into())?;
+    } else {
+        check(CS::Group::deserialize_elem(&identity_bytes).is_err())?;
+    }
+    let scalar = CS::Group::random_scalar(&mut rng);
+    let scalar_bytes = CS::Group::serialize_scalar(scalar);
+    check(
+        CS::Group::deserialize_scalar(&scalar_bytes)?
+            .ct_eq(&scalar)
+            .into(),
+    )?;
+
+    // Base mode
+    let server = OprfServer::<CS>::new(&mut rng)?;
+    let blind_result = OprfClient::<CS>::blind(input, &mut rng)?;
+    let message = BlindedElement::<CS>::deserialize(&blind_result.message.serialize())?;
+    let output = blind_result
+        .state
+        .finalize(input, &server.blind_evaluate(&message))?;
+    check(output == server.evaluate(input)?)?;
+
+    // Verifiable mode
+    let server = VoprfServer::<CS>::new(&mut rng)?;
+    let blind_result = VoprfClient::<CS>::blind(input, &mut rng)?;
+    let result = server.try_blind_evaluate(&mut rng, &blind_result.message)?;
+    let output = blind_result.state.finalize(
+        input,
+        &result.message,
+        &result.proof,
+        server.get_public_key(),
+    )?;
+    check(output == server.evaluate(input)?)?;
+
+    // Partially-oblivious mode
+    let server = PoprfServer::<CS>::new(&mut rng)?;
+    let blind_result = PoprfClient::<CS>::blind(input, &mut rng)?;
+    let result = server.blind_evaluate(&mut rng, &blind_result.message, Some(info))?;
+    let output = blind_result.state.finalize(
+        input,
+        &result.message,
+        &result.proof,
+        server.get_public_key(),
+        Some(info),
+    )?;
+    check(output == server.evaluate(input, Some(info))?)?;
+
+    Ok(())
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+fn check(condition: bool) -> Result<()> {
+    if condition {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::Protocol, Operation::CheckConformance))
+    }
+}
+
+/// Deterministic random number generator for [`check_conformance`], hashing
+/// an incrementing counter. Only used for testing.
+struct ConformanceRng<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    counter: u64,
+    _cs: PhantomData<CS>,
+}
+
+impl<CS: CipherSuite> RngCore for ConformanceRng<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(<CS::Hash as OutputSizeUser>::OutputSize::USIZE) {
+            let output = CS::Hash::new()
+                .chain_update(STR_CONFORMANCE)
+                .chain_update(self.counter.to_be_bytes())
+                .finalize();
+            self.counter += 1;
+
+            for (byte, output) in chunk.iter_mut().zip(output) {
+                *byte = output;
+            }
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<CS: CipherSuite> CryptoRng for ConformanceRng<CS> where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>
+{
+}
+
+const STR_CONFORMANCE: [u8; 11] = *b"Conformance";
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    impl_ciphersuite!(
+        /// NIST P-256 with SHA-512.
+        P256Sha512,
+        group = p256::NistP256,
+        hash = sha2::Sha512,
+        id = 0xFFFF,
+        tests = p256_sha512_conformance,
+    );
+
+    impl_ciphersuite!(
+        /// NIST P-256 with SHA-256, but SHA-512 outputs.
+        P256Sha512Outputs,
+        group = p256::NistP256,
+        hash = sha2::Sha256,
+        finalize_hash = sha2::Sha512,
+        id = 0xFFFD,
+        tests = p256_sha512_outputs_conformance,
+    );
+
+    // Only bounded by `SuitableHash`, which has to be enough to call into the
+    // protocol
+    fn suitable_hash_conformance<CS: CipherSuite>() -> Result<()>
+    where
+        CS::Hash: SuitableHash,
+    {
+        check_conformance::<CS>()
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            check_conformance::<Ristretto255>()?;
+            suitable_hash_conformance::<Ristretto255>()?;
+        }
+
+        check_conformance::<NistP256>()?;
+        suitable_hash_conformance::<NistP256>()?;
+
+        assert_eq!(P256Sha512::ID, 0xFFFF);
+
+        // The finalize hash determines the length of outputs
+        let server = OprfServer::<P256Sha512Outputs>::new(&mut OsRng)?;
+        let blind_result = OprfClient::<P256Sha512Outputs>::blind(b"input", &mut OsRng)?;
+        let output = blind_result
+            .state
+            .finalize(b"input", &server.blind_evaluate(&blind_result.message))?;
+        assert_eq!(output.len(), 64);
+        assert_eq!(output, server.evaluate(b"input")?);
+        assert_eq!(
+            OprfServer::<NistP256>::new(&mut OsRng)?
+                .evaluate(b"input")?
+                .len(),
+            32
+        );
+
+        Ok(())
+    }
 }
@@ -8,19 +8,22 @@
 //! Common functionality between multiple OPRF modes.
 
 use core::convert::TryFrom;
+use core::hash::{Hash, Hasher};
+use core::iter::FusedIterator;
 
 use derive_where::derive_where;
 use digest::core_api::BlockSizeUser;
 use digest::{Digest, Output, OutputSizeUser};
 use generic_array::sequence::Concat;
-use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U11, U2, U256};
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U11, U2, U21, U23, U256};
 use generic_array::{ArrayLength, GenericArray};
 use rand_core::{CryptoRng, RngCore};
 use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
 
 #[cfg(feature = "serde")]
 use crate::serialization::serde::{Element, Scalar};
-use crate::{CipherSuite, Error, Group, InternalError, Result};
+use crate::{kernel, CipherSuite, Error, ErrorKind, Group, InternalError, Operation, Result};
 
 ///////////////
 // Constants //
@@ -36,10 +39,12 @@ pub(crate) const STR_INFO: [u8; 4] = *b"Info";
 pub(crate) const STR_VOPRF: [u8; 8] = *b"VOPRF10-";
 pub(crate) const STR_HASH_TO_SCALAR: [u8; 13] = *b"HashToScalar-";
 pub(crate) const STR_HASH_TO_GROUP: [u8; 12] = *b"HashToGroup-";
+pub(crate) const STR_LONG_INPUT: [u8; 10] = *b"-LongInput";
+pub(crate) const STR_HEDGED_NONCE: [u8; 12] = *b"HedgedNonce-";
 
 /// Determines the mode of operation (either base mode or verifiable mode). This
 /// is only used for custom implementations for [`Group`].
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Mode {
     /// Non-verifiable mode.
     Oprf,
@@ -47,6 +52,8 @@ pub enum Mode {
     Voprf,
     /// Partially-oblivious mode.
     Poprf,
+    /// Experimental mode of a research protocol, see [`CustomMode`].
+    Custom(CustomMode),
 }
 
 impl Mode {
@@ -56,8 +63,60 @@ impl Mode {
             Mode::Oprf => 0,
             Mode::Voprf => 1,
             Mode::Poprf => 2,
+            Mode::Custom(mode) => mode.id,
         }
     }
+
+    /// Mode as it is labeled in metrics.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Mode::Oprf => "oprf",
+            Mode::Voprf => "voprf",
+            Mode::Poprf => "poprf",
+            Mode::Custom(_) => "custom",
+        }
+    }
+}
+
+/// An experimental mode, for research protocols that want to reuse the
+/// blinding, evaluation and proofs of this crate under their own context
+/// string, e.g. with [`custom_blind`](crate::custom_blind), instead of forking
+/// it.
+///
+/// The context string of a custom mode is built like the ones of the
+/// specified modes, `"VOPRF10-" || I2OSP(id, 1) || I2OSP(suiteID, 2)`,
+/// so two custom modes are separated by their `id`. The `name` is only used
+/// for debugging and tracing.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CustomMode {
+    id: u8,
+    name: &'static str,
+}
+
+impl CustomMode {
+    /// Creates a custom mode with the given `id` and `name`.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `id` is the one of a specified mode, i.e.
+    /// `0`, `1` or `2`.
+    pub fn new(id: u8, name: &'static str) -> Result<Self> {
+        if id <= Mode::Poprf.to_u8() {
+            return Err(Error::new(ErrorKind::Input, Operation::CustomMode));
+        }
+
+        Ok(Self { id, name })
+    }
+
+    /// Returns the identifier of the mode in the context string.
+    pub fn id(self) -> u8 {
+        self.id
+    }
+
+    /// Returns the name of the mode.
+    pub fn name(self) -> &'static str {
+        self.name
+    }
 }
 
 ////////////////////////////
@@ -68,7 +127,7 @@ impl Mode {
 /// The first client message sent from a client (either verifiable or not) to a
 /// server (either verifiable or not).
 #[derive_where(Clone, ZeroizeOnDrop)]
-#[derive_where(Debug, Eq, Hash, Ord, PartialEq, PartialOrd; <CS::Group as Group>::Elem)]
+#[derive_where(Debug, Ord, PartialOrd; <CS::Group as Group>::Elem)]
 #[cfg_attr(
     feature = "serde",
     derive(serde::Deserialize, serde::Serialize),
@@ -85,7 +144,7 @@ where
 /// The server's response to the [BlindedElement] message from a client (either
 /// verifiable or not) to a server (either verifiable or not).
 #[derive_where(Clone, ZeroizeOnDrop)]
-#[derive_where(Debug, Eq, Hash, Ord, PartialEq, PartialOrd; <CS::Group as Group>::Elem)]
+#[derive_where(Debug, Ord, PartialOrd; <CS::Group as Group>::Elem)]
 #[cfg_attr(
     feature = "serde",
     derive(serde::Deserialize, serde::Serialize),
@@ -102,7 +161,8 @@ where
 /// Contains prepared [`EvaluationElement`]s by a server batch evaluate
 /// preparation.
 #[derive_where(Clone, ZeroizeOnDrop)]
-#[derive_where(Debug, Eq, Hash, Ord, PartialEq, PartialOrd; <CS::Group as Group>::Elem)]
+#[derive_where(Eq, Hash, PartialEq)]
+#[derive_where(Debug, Ord, PartialOrd; <CS::Group as Group>::Elem)]
 #[cfg_attr(
     feature = "serde",
     derive(serde::Deserialize, serde::Serialize),
@@ -133,20 +193,180 @@ where
     pub(crate) s_scalar: <CS::Group as Group>::Scalar,
 }
 
+/// Iterator pairing every item of `I` with a copy of a value, like
+/// `iter.zip(iter::repeat(value))`, but implementing [`ExactSizeIterator`] and
+/// [`DoubleEndedIterator`] if `I` does, so batch results can be counted and
+/// streamed in either direction.
+#[derive(Clone, Debug)]
+pub struct ZipRepeat<I, T> {
+    iter: I,
+    value: T,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates a [`BlindedElement`] from a group element, e.g. one computed by
+    /// an advanced protocol built on top of this crate, or one already
+    /// deserialized and validated by an earlier stage of a pipeline, so servers
+    /// can evaluate it without parsing it again. Unlike deserialization, this
+    /// doesn't reject the identity element.
+    pub fn from_value(value: <CS::Group as Group>::Elem) -> Self {
+        Self(value)
+    }
+
+    /// Returns the underlying group element.
+    pub fn get_value(&self) -> <CS::Group as Group>::Elem {
+        self.0
+    }
+
+    /// Re-blinds this element with a fresh random scalar, e.g. by a proxy that
+    /// unlinks client requests from the ones the server sees. Returns the
+    /// re-randomized element together with the scalar `delta`, which the client
+    /// has to fold into its blind before finalizing, see
+    /// [`OprfClient::apply_rerandomization`](crate::OprfClient::apply_rerandomization).
+    pub fn rerandomize<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+    ) -> (Self, <CS::Group as Group>::Scalar) {
+        let delta = CS::Group::random_scalar(rng);
+        (Self(self.0 * &delta), delta)
+    }
+}
+
+impl<CS: CipherSuite> EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates an [`EvaluationElement`] from a group element, e.g. when
+    /// combining the evaluations of several servers in a threshold setting.
+    pub fn from_value(value: <CS::Group as Group>::Elem) -> Self {
+        Self(value)
+    }
+
+    /// Returns the underlying group element.
+    pub fn get_value(&self) -> <CS::Group as Group>::Elem {
+        self.0
+    }
+}
+
+/// Provides [`ZipRepeat`] as an iterator adapter.
+pub(crate) trait ZipRepeatExt: Iterator + Sized {
+    /// Pairs every item with a copy of `value`, see [`ZipRepeat`].
+    fn zip_repeat<T: Clone>(self, value: T) -> ZipRepeat<Self, T> {
+        ZipRepeat { iter: self, value }
+    }
+}
+
+impl<I: Iterator> ZipRepeatExt for I {}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<I: Iterator, T: Clone> Iterator for ZipRepeat<I, T> {
+    type Item = (I::Item, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|item| (item, self.value.clone()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator, T: Clone> ExactSizeIterator for ZipRepeat<I, T> {}
+
+impl<I: DoubleEndedIterator, T: Clone> DoubleEndedIterator for ZipRepeat<I, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|item| (item, self.value.clone()))
+    }
+}
+
+impl<I: FusedIterator, T: Clone> FusedIterator for ZipRepeat<I, T> {}
+
+// Group elements don't implement `Hash` and not necessarily `PartialEq`, so
+// messages are compared in constant-time and hashed by their serialization.
+
+impl<CS: CipherSuite> PartialEq for BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl<CS: CipherSuite> Eq for BlindedElement<CS> where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>
+{
+}
+
+impl<CS: CipherSuite> Hash for BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        CS::Group::serialize_elem(self.0).hash(state);
+    }
+}
+
+impl<CS: CipherSuite> PartialEq for EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl<CS: CipherSuite> Eq for EvaluationElement<CS> where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>
+{
+}
+
+impl<CS: CipherSuite> Hash for EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        CS::Group::serialize_elem(self.0).hash(state);
+    }
+}
+
 /////////////////////
 // Proof Functions //
 // =============== //
 /////////////////////
 
-/// Can only fail with [`Error::Batch`].
+/// The nonce of the proof is hedged against weak RNGs, see
+/// [`derive_hedged_nonce`].
+///
+/// Can only fail with [`ErrorKind::Batch`].
 #[allow(clippy::many_single_char_names)]
 pub(crate) fn generate_proof<CS: CipherSuite, R: RngCore + CryptoRng>(
     rng: &mut R,
     k: <CS::Group as Group>::Scalar,
     a: <CS::Group as Group>::Elem,
     b: <CS::Group as Group>::Elem,
-    cs: impl Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
-    ds: impl Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
+    cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
     mode: Mode,
 ) -> Result<Proof<CS>>
 where
@@ -154,200 +374,121 @@ where
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
     // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-11.html#section-2.2.1
+    debug_span!("generate_proof", ?mode, batch_size = cs.len());
 
-    let (m, z) = compute_composites::<CS, _, _>(Some(k), b, cs, ds, mode)?;
+    let (m, z) = kernel::compute_composites::<CS, _, _>(Some(k), b, cs, ds, mode)
+        .map_err(|error| error.with_operation(Operation::Evaluate))?;
 
-    let r = CS::Group::random_scalar(rng);
-    let t2 = a * &r;
-    let t3 = m * &r;
-
-    // Bm = GG.SerializeElement(B)
-    let bm = CS::Group::serialize_elem(b);
-    // a0 = GG.SerializeElement(M)
-    let a0 = CS::Group::serialize_elem(m);
-    // a1 = GG.SerializeElement(Z)
-    let a1 = CS::Group::serialize_elem(z);
-    // a2 = GG.SerializeElement(t2)
-    let a2 = CS::Group::serialize_elem(t2);
-    // a3 = GG.SerializeElement(t3)
-    let a3 = CS::Group::serialize_elem(t3);
-
-    let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
-
-    // h2Input = I2OSP(len(Bm), 2) || Bm ||
-    //           I2OSP(len(a0), 2) || a0 ||
-    //           I2OSP(len(a1), 2) || a1 ||
-    //           I2OSP(len(a2), 2) || a2 ||
-    //           I2OSP(len(a3), 2) || a3 ||
-    //           "Challenge"
-    let h2_input = [
-        &elem_len,
-        bm.as_slice(),
-        &elem_len,
-        &a0,
-        &elem_len,
-        &a1,
-        &elem_len,
-        &a2,
-        &elem_len,
-        &a3,
-        &STR_CHALLENGE,
-    ];
-
-    let dst = GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string::<CS>(mode));
-    // This can't fail, the size of the `input` is known.
-    let c_scalar = CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &dst).unwrap();
-    let s_scalar = r - &(c_scalar * &k);
+    let mut entropy = [0; 32];
+    rng.fill_bytes(&mut entropy);
+    let r = derive_hedged_nonce::<CS>(k, m, z, &entropy, mode);
+    entropy.zeroize();
 
-    Ok(Proof { c_scalar, s_scalar })
+    kernel::compute_proof(k, r?, a, b, m, z, mode)
 }
 
-/// Can only fail with [`Error::ProofVerification`] or [`Error::Batch`].
-#[allow(clippy::many_single_char_names)]
-pub(crate) fn verify_proof<CS: CipherSuite>(
+/// Like [`generate_proof`], but with the nonce `r` given directly, as in the
+/// test vectors of the specification.
+#[cfg(any(test, all(feature = "danger", feature = "alloc")))]
+pub(crate) fn generate_proof_with_nonce<CS: CipherSuite>(
+    r: <CS::Group as Group>::Scalar,
+    k: <CS::Group as Group>::Scalar,
     a: <CS::Group as Group>::Elem,
     b: <CS::Group as Group>::Elem,
-    cs: impl Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
-    ds: impl Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
-    proof: &Proof<CS>,
+    cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
     mode: Mode,
-) -> Result<()>
+) -> Result<Proof<CS>>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-11.html#section-2.2.2
-    let (m, z) = compute_composites::<CS, _, _>(None, b, cs, ds, mode)?;
-    let t2 = (a * &proof.s_scalar) + &(b * &proof.c_scalar);
-    let t3 = (m * &proof.s_scalar) + &(z * &proof.c_scalar);
+    let (m, z) = kernel::compute_composites::<CS, _, _>(Some(k), b, cs, ds, mode)
+        .map_err(|error| error.with_operation(Operation::Evaluate))?;
 
-    // Bm = GG.SerializeElement(B)
-    let bm = CS::Group::serialize_elem(b);
-    // a0 = GG.SerializeElement(M)
+    kernel::compute_proof(k, r, a, b, m, z, mode)
+}
+
+/// Derives the nonce `r` of a proof by hashing the private key `k`, the
+/// composites `m` and `z` and the `entropy` drawn from the RNG:
+///
+/// ```text
+/// r = G.HashToScalar(I2OSP(len(k), 2) || k ||
+///                    I2OSP(len(M), 2) || M ||
+///                    I2OSP(len(Z), 2) || Z ||
+///                    I2OSP(len(entropy), 2) || entropy,
+///                    "HedgedNonce-" || contextString)
+/// ```
+///
+/// Verifiers can't distinguish `r` from a random scalar, so the proof stays
+/// compliant with the specification. But a weak or repeating RNG can't leak `k`
+/// through biased or reused nonces anymore, as long as the statement differs.
+///
+/// Can only fail with [`ErrorKind::Protocol`].
+pub(crate) fn derive_hedged_nonce<CS: CipherSuite>(
+    k: <CS::Group as Group>::Scalar,
+    m: <CS::Group as Group>::Elem,
+    z: <CS::Group as Group>::Elem,
+    entropy: &[u8; 32],
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut km = CS::Group::serialize_scalar(k);
     let a0 = CS::Group::serialize_elem(m);
-    // a1 = GG.SerializeElement(Z)
     let a1 = CS::Group::serialize_elem(z);
-    // a2 = GG.SerializeElement(t2)
-    let a2 = CS::Group::serialize_elem(t2);
-    // a3 = GG.SerializeElement(t3)
-    let a3 = CS::Group::serialize_elem(t3);
 
+    let scalar_len = <CS::Group as Group>::ScalarLen::U16.to_be_bytes();
     let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
+    let entropy_len = 32_u16.to_be_bytes();
 
-    // h2Input = I2OSP(len(Bm), 2) || Bm ||
-    //           I2OSP(len(a0), 2) || a0 ||
-    //           I2OSP(len(a1), 2) || a1 ||
-    //           I2OSP(len(a2), 2) || a2 ||
-    //           I2OSP(len(a3), 2) || a3 ||
-    //           "Challenge"
-    let h2_input = [
-        &elem_len,
-        bm.as_slice(),
+    let input = [
+        &scalar_len,
+        km.as_slice(),
         &elem_len,
         &a0,
         &elem_len,
         &a1,
-        &elem_len,
-        &a2,
-        &elem_len,
-        &a3,
-        &STR_CHALLENGE,
+        &entropy_len,
+        entropy,
     ];
 
-    let dst = GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string::<CS>(mode));
+    let dst = GenericArray::from(STR_HEDGED_NONCE).concat(create_context_string::<CS>(mode));
     // This can't fail, the size of the `input` is known.
-    let c = CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &dst).unwrap();
+    let r = CS::Group::hash_to_scalar::<CS::Hash>(&input, &dst)
+        .map_err(|_| Error::new(ErrorKind::Protocol, Operation::Evaluate));
+    km.as_mut_slice().zeroize();
 
-    match c.ct_eq(&proof.c_scalar).into() {
-        true => Ok(()),
-        false => Err(Error::ProofVerification),
-    }
+    r
 }
 
-type ComputeCompositesResult<CS> = (
-    <<CS as CipherSuite>::Group as Group>::Elem,
-    <<CS as CipherSuite>::Group as Group>::Elem,
-);
-
-/// Can only fail with [`Error::Batch`].
-fn compute_composites<
-    CS: CipherSuite,
-    IC: Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
-    ID: Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
->(
-    k_option: Option<<CS::Group as Group>::Scalar>,
+/// Can only fail with [`ErrorKind::ProofVerification`] or [`ErrorKind::Batch`].
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn verify_proof<CS: CipherSuite>(
+    a: <CS::Group as Group>::Elem,
     b: <CS::Group as Group>::Elem,
-    c_slice: IC,
-    d_slice: ID,
+    cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    proof: &Proof<CS>,
     mode: Mode,
-) -> Result<ComputeCompositesResult<CS>>
+) -> Result<()>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-11.html#section-2.2.1
-
-    let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
-
-    if c_slice.len() != d_slice.len() {
-        return Err(Error::Batch);
-    }
-
-    let len = u16::try_from(c_slice.len()).map_err(|_| Error::Batch)?;
-
-    // seedDST = "Seed-" || contextString
-    let seed_dst = GenericArray::from(STR_SEED).concat(create_context_string::<CS>(mode));
-
-    // h1Input = I2OSP(len(Bm), 2) || Bm ||
-    //           I2OSP(len(seedDST), 2) || seedDST
-    // seed = Hash(h1Input)
-    let seed = CS::Hash::new()
-        .chain_update(&elem_len)
-        .chain_update(CS::Group::serialize_elem(b))
-        .chain_update(i2osp_2_array(&seed_dst))
-        .chain_update(seed_dst)
-        .finalize();
-    let seed_len = i2osp_2_array(&seed);
-
-    let mut m = CS::Group::identity_elem();
-    let mut z = CS::Group::identity_elem();
-
-    for (i, (c, d)) in (0..len).zip(c_slice.zip(d_slice)) {
-        // Ci = GG.SerializeElement(Cs[i])
-        let ci = CS::Group::serialize_elem(c);
-        // Di = GG.SerializeElement(Ds[i])
-        let di = CS::Group::serialize_elem(d);
-        // h2Input = I2OSP(len(seed), 2) || seed || I2OSP(i, 2) ||
-        //           I2OSP(len(Ci), 2) || Ci ||
-        //           I2OSP(len(Di), 2) || Di ||
-        //           "Composite"
-        let h2_input = [
-            seed_len.as_slice(),
-            &seed,
-            &i.to_be_bytes(),
-            &elem_len,
-            &ci,
-            &elem_len,
-            &di,
-            &STR_COMPOSITE,
-        ];
-
-        let dst = GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string::<CS>(mode));
-        // This can't fail, the size of the `input` is known.
-        let di = CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &dst).unwrap();
-        m = c * &di + &m;
-        z = match k_option {
-            Some(_) => z,
-            None => d * &di + &z,
-        };
-    }
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-11.html#section-2.2.2
+    debug_span!("verify_proof", ?mode, batch_size = cs.len());
 
-    z = match k_option {
-        Some(k) => m * &k,
-        None => z,
-    };
+    kernel::verify_proof(a, b, cs, ds, proof, mode).map_err(|error| {
+        if error.kind() == ErrorKind::ProofVerification {
+            debug_event!("proof failed to verify");
+            count!("voprf_proof_verification_failures_total", 1, "mode" => mode.label());
+        }
 
-    Ok((m, z))
+        error
+    })
 }
 
 /////////////////////
@@ -355,7 +496,7 @@ where
 // =============== //
 /////////////////////
 
-/// Can only fail with [`Error::DeriveKeyPair`] and [`Error::Protocol`].
+/// Can only fail with [`ErrorKind::DeriveKeyPair`] and [`ErrorKind::Protocol`].
 pub(crate) fn derive_key_internal<CS: CipherSuite>(
     seed: &[u8],
     info: &[u8],
@@ -368,7 +509,8 @@ where
     let context_string = create_context_string::<CS>(mode);
     let dst = GenericArray::from(STR_DERIVE_KEYPAIR).concat(context_string);
 
-    let info_len = i2osp_2(info.len()).map_err(|_| Error::DeriveKeyPair)?;
+    let info_len = i2osp_2(info.len())
+        .map_err(|_| Error::new(ErrorKind::DeriveKeyPair, Operation::DeriveKeyPair))?;
 
     for counter in 0_u8..=u8::MAX {
         // deriveInput = seed || I2OSP(len(info), 2) || info
@@ -378,17 +520,23 @@ where
             &[seed, &info_len, info, &counter.to_be_bytes()],
             &dst,
         )
-        .map_err(|_| Error::DeriveKeyPair)?;
+        .map_err(|_| Error::new(ErrorKind::DeriveKeyPair, Operation::DeriveKeyPair))?;
 
         if !bool::from(CS::Group::is_zero_scalar(sk_s)) {
             return Ok(sk_s);
         }
     }
 
-    Err(Error::Protocol)
+    Err(Error::new(ErrorKind::Protocol, Operation::DeriveKeyPair))
 }
 
-/// Can only fail with [`Error::DeriveKeyPair`] and [`Error::Protocol`].
+/// Corresponds to the key derivation part of the DeriveKeyPair() function from
+/// the VOPRF specification.
+///
+/// # Errors
+/// - [`ErrorKind::DeriveKeyPair`] if the `input` and `seed` together are longer
+///   then `u16::MAX - 3`.
+/// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
 #[cfg(feature = "danger")]
 pub fn derive_key<CS: CipherSuite>(
     seed: &[u8],
@@ -407,7 +555,7 @@ type DeriveKeypairResult<CS> = (
     <<CS as CipherSuite>::Group as Group>::Elem,
 );
 
-/// Can only fail with [`Error::DeriveKeyPair`] and [`Error::Protocol`].
+/// Can only fail with [`ErrorKind::DeriveKeyPair`] and [`ErrorKind::Protocol`].
 pub(crate) fn derive_keypair<CS: CipherSuite>(
     seed: &[u8],
     info: &[u8],
@@ -427,7 +575,7 @@ where
 /// been chosen, and therefore takes it as input. Does not check if the blinding
 /// factor is non-zero.
 ///
-/// Can only fail with [`Error::Input`].
+/// Can only fail with [`ErrorKind::Input`].
 pub(crate) fn deterministic_blind_unchecked<CS: CipherSuite>(
     input: &[u8],
     blind: &<CS::Group as Group>::Scalar,
@@ -437,11 +585,67 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    let hashed_point = hash_to_group::<CS>(input, mode)?;
+    debug_span!("blind", ?mode);
+
+    kernel::blind::<CS>(input, blind, mode)
+}
+
+/// Inverts `scalar` in constant-time, without branching on it being zero.
+/// Blinds and tweaks are checked to be non-zero on construction; a zero
+/// `scalar` maps to zero.
+pub(crate) fn invert_scalar<CS: CipherSuite>(
+    scalar: <CS::Group as Group>::Scalar,
+) -> <CS::Group as Group>::Scalar
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    CS::Group::invert_scalar_ct(scalar).unwrap_or(scalar)
+}
+
+/// Like [`deterministic_blind_unchecked`], but pre-hashes the `input` in the
+/// long input mode.
+///
+/// Can only fail with [`ErrorKind::Input`].
+pub(crate) fn deterministic_blind_long_input_unchecked<CS: CipherSuite>(
+    input: &[u8],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Elem>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    debug_span!("blind", ?mode);
+
+    let prehashed_input = prehash_long_input::<CS>(input, mode);
+    let hashed_point = hash_long_input_to_group::<CS>(&prehashed_input, mode)
+        .map_err(|error| error.with_operation(Operation::Blind))?;
     Ok(hashed_point * blind)
 }
 
-/// Hashes `input` to a point on the curve
+/// Blinds the `hashed_element` an input was already hashed to with
+/// [`hash_to_group`].
+///
+/// Can only fail with [`ErrorKind::Input`] if the `hashed_element` is the
+/// identity element, which [`hash_to_group`] never returns.
+pub(crate) fn blind_hashed_element<CS: CipherSuite>(
+    hashed_element: <CS::Group as Group>::Elem,
+    blind: &<CS::Group as Group>::Scalar,
+) -> Result<<CS::Group as Group>::Elem>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    if bool::from(CS::Group::is_identity_elem(hashed_element)) {
+        return Err(Error::new(ErrorKind::Input, Operation::Blind));
+    }
+
+    Ok(hashed_element * blind)
+}
+
+/// Hashes `input` to a point on the curve. The returned error doesn't carry an
+/// [`Operation`] yet.
 pub(crate) fn hash_to_group<CS: CipherSuite>(
     input: &[u8],
     mode: Mode,
@@ -450,17 +654,27 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    let dst = GenericArray::from(STR_HASH_TO_GROUP).concat(create_context_string::<CS>(mode));
-    CS::Group::hash_to_curve::<CS::Hash>(&[input], &dst).map_err(|_| Error::Input)
+    CS::Group::hash_to_curve::<CS::Hash>(&[input], &hash_to_group_dst::<CS>(mode))
+        .map_err(|_| ErrorKind::Input.into())
+}
+
+/// The domain separation tag of [`hash_to_group`], to compute it only once
+/// when hashing many inputs.
+pub(crate) fn hash_to_group_dst<CS: CipherSuite>(mode: Mode) -> GenericArray<u8, U23>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    GenericArray::from(STR_HASH_TO_GROUP).concat(create_context_string::<CS>(mode))
 }
 
 /// Internal function that finalizes the hash input for OPRF, VOPRF & POPRF.
-/// Returned values can only fail with [`Error::Input`].
+/// Returned values can only fail with [`ErrorKind::Input`].
 pub(crate) fn server_evaluate_hash_input<CS: CipherSuite>(
     input: &[u8],
     info: Option<&[u8]>,
     issued_element: GenericArray<u8, <<CS as CipherSuite>::Group as Group>::ElemLen>,
-) -> Result<Output<CS::Hash>>
+) -> Result<Output<CS::FinalizeHash>>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
@@ -477,16 +691,25 @@ where
     //             I2OSP(len(issuedElement), 2) || issuedElement ||
     //             "Finalize"
 
-    let mut hash = CS::Hash::new()
-        .chain_update(i2osp_2(input.as_ref().len()).map_err(|_| Error::Input)?)
+    let mut hash = CS::FinalizeHash::new()
+        .chain_update(
+            i2osp_2(input.as_ref().len())
+                .map_err(|_| Error::new(ErrorKind::Input, Operation::Evaluate))?,
+        )
         .chain_update(input.as_ref());
     if let Some(info) = info {
         hash = hash
-            .chain_update(i2osp_2(info.as_ref().len()).map_err(|_| Error::Input)?)
+            .chain_update(
+                i2osp_2(info.as_ref().len())
+                    .map_err(|_| Error::new(ErrorKind::Input, Operation::Evaluate))?,
+            )
             .chain_update(info.as_ref());
     }
     Ok(hash
-        .chain_update(i2osp_2(issued_element.as_ref().len()).map_err(|_| Error::Input)?)
+        .chain_update(
+            i2osp_2(issued_element.as_ref().len())
+                .map_err(|_| Error::new(ErrorKind::Input, Operation::Evaluate))?,
+        )
         .chain_update(issued_element)
         .chain_update(STR_FINALIZE)
         .finalize())
@@ -504,6 +727,45 @@ where
         .concat(CS::ID.to_be_bytes().into())
 }
 
+/// The context string of the long input mode, where inputs are pre-hashed.
+/// Appending "-LongInput" separates it from the regular context string.
+pub(crate) fn create_long_input_context_string<CS: CipherSuite>(mode: Mode) -> GenericArray<u8, U21>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    create_context_string::<CS>(mode).concat(STR_LONG_INPUT.into())
+}
+
+/// Pre-hashes an `input` of any length in the long input mode.
+pub(crate) fn prehash_long_input<CS: CipherSuite>(input: &[u8], mode: Mode) -> Output<CS::Hash>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // prehashedInput = Hash(longInputContextString || input)
+    CS::Hash::new()
+        .chain_update(create_long_input_context_string::<CS>(mode))
+        .chain_update(input)
+        .finalize()
+}
+
+/// Hashes a pre-hashed input to a point on the curve in the long input mode.
+/// The returned error doesn't carry an [`Operation`] yet.
+pub(crate) fn hash_long_input_to_group<CS: CipherSuite>(
+    prehashed_input: &[u8],
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Elem>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let dst =
+        GenericArray::from(STR_HASH_TO_GROUP).concat(create_long_input_context_string::<CS>(mode));
+    CS::Group::hash_to_curve::<CS::Hash>(&[prehashed_input], &dst)
+        .map_err(|_| ErrorKind::Input.into())
+}
+
 ///////////////////////
 // Utility Functions //
 // ================= //
@@ -520,3 +782,50 @@ pub(crate) fn i2osp_2_array<L: ArrayLength<u8> + IsLess<U256>>(
 ) -> GenericArray<u8, U2> {
     L::U16.to_be_bytes().into()
 }
+
+/// Equivalent to `core::array::from_fn`, which isn't available in our MSRV.
+pub(crate) fn array_from_fn<T, const N: usize>(mut f: impl FnMut(usize) -> T) -> [T; N] {
+    let mut index = 0;
+    [(); N].map(|()| {
+        let value = f(index);
+        index += 1;
+        value
+    })
+}
+
+/// Equivalent to `array.each_ref().map(f)`, which isn't available in our MSRV.
+// `index` is always in bounds, this is the only place indexing arrays.
+#[allow(clippy::indexing_slicing)]
+pub(crate) fn array_map_ref<T, U, const N: usize>(
+    array: &[T; N],
+    mut f: impl FnMut(&T) -> U,
+) -> [U; N] {
+    array_from_fn(|index| f(&array[index]))
+}
+
+/// Like [`array_from_fn`], but returns the first error, annotated with the
+/// index of the offending item.
+pub(crate) fn try_array_from_fn<T, const N: usize>(
+    f: impl FnMut(usize) -> Result<T>,
+) -> Result<[T; N]> {
+    try_array_from_results(array_from_fn(f))
+}
+
+/// Returns the first error in `results`, annotated with the index of the
+/// offending item, or all values if there is none.
+// The `unreachable!` is guarded by the check for errors above it.
+#[allow(clippy::unreachable)]
+pub(crate) fn try_array_from_results<T, const N: usize>(results: [Result<T>; N]) -> Result<[T; N]> {
+    if let Some((index, error)) = results
+        .iter()
+        .enumerate()
+        .find_map(|(index, result)| result.as_ref().err().map(|error| (index, error)))
+    {
+        return Err(error.with_index(index));
+    }
+
+    Ok(results.map(|result| match result {
+        Ok(value) => value,
+        Err(_) => unreachable!("errors were returned above"),
+    }))
+}
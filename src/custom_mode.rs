@@ -0,0 +1,292 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the building blocks of the protocol for experimental modes
+
+use core::iter;
+
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::common::{deterministic_blind_unchecked, generate_proof, verify_proof};
+use crate::{
+    kernel, BlindedElement, CipherSuite, CustomMode, EvaluationElement, Group, Mode, Proof, Result,
+};
+
+///////////////////////////
+// Convenience Functions //
+// ===================== //
+///////////////////////////
+
+/// Blinds the `input` under the context string of a [`CustomMode`]. Returns
+/// the blind, which has to be kept secret until [`custom_finalize`], and the
+/// [`BlindedElement`] to send to the server.
+///
+/// # Errors
+/// [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty or
+/// longer then [`u16::MAX`].
+pub fn custom_blind<CS: CipherSuite, R: RngCore + CryptoRng>(
+    mode: CustomMode,
+    input: &[u8],
+    blinding_factor_rng: &mut R,
+) -> Result<(<CS::Group as Group>::Scalar, BlindedElement<CS>)>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let blind = CS::Group::random_scalar(blinding_factor_rng);
+    let blinded_element = deterministic_blind_unchecked::<CS>(input, &blind, Mode::Custom(mode))?;
+
+    Ok((blind, BlindedElement(blinded_element)))
+}
+
+/// Evaluates a [`BlindedElement`] with the `private_key`, like
+/// [`VoprfServer::blind_evaluate`](crate::VoprfServer::blind_evaluate), but
+/// proves the evaluation under the context string of a [`CustomMode`].
+///
+/// # Errors
+/// [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol fails
+/// and can't be completed.
+pub fn custom_blind_evaluate<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    mode: CustomMode,
+    private_key: <CS::Group as Group>::Scalar,
+    blinded_element: &BlindedElement<CS>,
+) -> Result<(EvaluationElement<CS>, Proof<CS>)>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let public_key = CS::Group::base_elem() * &private_key;
+    let evaluation_element = blinded_element.0 * &private_key;
+    let proof = generate_proof(
+        rng,
+        private_key,
+        CS::Group::base_elem(),
+        public_key,
+        iter::once(blinded_element.0),
+        iter::once(evaluation_element),
+        Mode::Custom(mode),
+    )?;
+
+    Ok((EvaluationElement(evaluation_element), proof))
+}
+
+/// Proves that every element of `ds` is the matching element of `cs`
+/// multiplied by `k`, where `b = k * a`, under the context string of a
+/// [`CustomMode`]. The building block of [`custom_blind_evaluate`], for
+/// protocols that evaluate differently, e.g. with a tweaked key as in the
+/// partially-oblivious mode.
+///
+/// # Errors
+/// [`ErrorKind::Batch`](crate::ErrorKind::Batch) if `cs` and `ds` have
+/// different lengths or are longer then [`u16::MAX`].
+#[allow(clippy::many_single_char_names)]
+pub fn custom_generate_proof<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    mode: CustomMode,
+    k: <CS::Group as Group>::Scalar,
+    a: <CS::Group as Group>::Elem,
+    b: <CS::Group as Group>::Elem,
+    cs: &[<CS::Group as Group>::Elem],
+    ds: &[<CS::Group as Group>::Elem],
+) -> Result<Proof<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    generate_proof(
+        rng,
+        k,
+        a,
+        b,
+        cs.iter().copied(),
+        ds.iter().copied(),
+        Mode::Custom(mode),
+    )
+}
+
+/// Verifies a [`Proof`] of [`custom_generate_proof`] or
+/// [`custom_blind_evaluate`] under the context string of a [`CustomMode`].
+///
+/// # Errors
+/// - [`ErrorKind::Batch`](crate::ErrorKind::Batch) if `cs` and `ds` have
+///   different lengths or are longer then [`u16::MAX`].
+/// - [`ErrorKind::ProofVerification`](crate::ErrorKind::ProofVerification) if
+///   the `proof` failed to verify.
+#[allow(clippy::many_single_char_names)]
+pub fn custom_verify_proof<CS: CipherSuite>(
+    mode: CustomMode,
+    a: <CS::Group as Group>::Elem,
+    b: <CS::Group as Group>::Elem,
+    cs: &[<CS::Group as Group>::Elem],
+    ds: &[<CS::Group as Group>::Elem],
+    proof: &Proof<CS>,
+) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    verify_proof(
+        a,
+        b,
+        cs.iter().copied(),
+        ds.iter().copied(),
+        proof,
+        Mode::Custom(mode),
+    )
+}
+
+/// Unblinds an [`EvaluationElement`] with the `blind` of [`custom_blind`] and
+/// hashes it to the output, as in the specified modes. The `info` is included
+/// as in the partially-oblivious mode if given.
+///
+/// # Errors
+/// - [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is longer
+///   then [`u16::MAX`].
+/// - [`ErrorKind::Info`](crate::ErrorKind::Info) if the `info` is longer then
+///   [`u16::MAX`].
+pub fn custom_finalize<CS: CipherSuite>(
+    input: &[u8],
+    blind: <CS::Group as Group>::Scalar,
+    evaluation_element: &EvaluationElement<CS>,
+    info: Option<&[u8]>,
+) -> Result<Output<CS::FinalizeHash>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let unblinded_element = kernel::unblind::<CS>(blind, evaluation_element.0);
+
+    kernel::finalize::<CS>(input, info, unblinded_element)
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+    use crate::common::{hash_to_group, server_evaluate_hash_input};
+    use crate::{Error, ErrorKind, Operation, OprfServer};
+
+    fn custom_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let mode = CustomMode::new(0x80, "Experimental").unwrap();
+        let other_mode = CustomMode::new(0x81, "Experimental").unwrap();
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let private_key = CS::Group::deserialize_scalar(&server.serialize()).unwrap();
+        let public_key = CS::Group::base_elem() * &private_key;
+
+        let (blind, message) = custom_blind::<CS, _>(mode, input, &mut rng).unwrap();
+        let (evaluation_element, proof) =
+            custom_blind_evaluate(&mut rng, mode, private_key, &message).unwrap();
+        let verify = |mode| {
+            custom_verify_proof(
+                mode,
+                CS::Group::base_elem(),
+                public_key,
+                &[message.0],
+                &[evaluation_element.0],
+                &proof,
+            )
+        };
+        assert!(verify(mode).is_ok());
+
+        // Proofs of other modes don't verify
+        let error = Error::new(ErrorKind::ProofVerification, Operation::VerifyProof);
+        assert!(matches!(verify(other_mode), Err(e) if e == error));
+        let voprf_blinded_element =
+            deterministic_blind_unchecked::<CS>(input, &blind, Mode::Voprf).unwrap();
+        assert!(!bool::from(voprf_blinded_element.ct_eq(&message.0)));
+
+        // The output hashes the input to the group under the custom mode, so it
+        // differs from the one of the base mode
+        let output = custom_finalize(input, blind, &evaluation_element, None).unwrap();
+        let expected = server_evaluate_hash_input::<CS>(
+            input,
+            None,
+            CS::Group::serialize_elem(
+                hash_to_group::<CS>(input, Mode::Custom(mode)).unwrap() * &private_key,
+            ),
+        )
+        .unwrap();
+        assert_eq!(output, expected);
+        assert_ne!(output, server.evaluate(input).unwrap());
+        assert_ne!(
+            output,
+            custom_finalize(input, blind, &evaluation_element, Some(b"info")).unwrap()
+        );
+
+        let proof = custom_generate_proof::<CS, _>(
+            &mut rng,
+            mode,
+            private_key,
+            CS::Group::base_elem(),
+            public_key,
+            &[message.0],
+            &[evaluation_element.0],
+        )
+        .unwrap();
+        assert!(custom_verify_proof(
+            mode,
+            CS::Group::base_elem(),
+            public_key,
+            &[message.0],
+            &[evaluation_element.0],
+            &proof
+        )
+        .is_ok());
+        assert!(matches!(
+            custom_verify_proof(
+                mode,
+                CS::Group::base_elem(),
+                public_key,
+                &[message.0],
+                &[],
+                &proof
+            ),
+            Err(e) if e.kind() == ErrorKind::Batch
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        let error = Error::new(ErrorKind::Input, Operation::CustomMode);
+        for id in 0..=2 {
+            assert!(matches!(CustomMode::new(id, "Standard"), Err(e) if e == error));
+        }
+        let mode = CustomMode::new(3, "Experimental")?;
+        assert_eq!((mode.id(), mode.name()), (3, "Experimental"));
+        assert_eq!(Mode::Custom(mode).to_u8(), 3);
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            custom_retrieval::<Ristretto255>();
+        }
+
+        custom_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
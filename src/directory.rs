@@ -0,0 +1,521 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the directory of key commitments an issuer publishes
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use generic_array::GenericArray;
+
+use crate::{
+    serialization, CipherSuite, Error, ErrorKind, Group, KeyCommitment, Operation, Result,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// The directory of [`KeyCommitment`]s an issuer publishes for clients to
+/// fetch, e.g. at a well-known URL.
+///
+/// The entries are kept in a canonical order, sorted by the start of their
+/// validity period and then by key ID, so the same set of keys always
+/// serializes to the same bytes or JSON. Clients should pin the key IDs they
+/// expect and select the public key with [`IssuerDirectory::select_pinned`].
+#[derive_where(Clone, Eq, PartialEq)]
+#[derive_where(Debug; <CS::Group as Group>::Elem)]
+pub struct IssuerDirectory<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    pub(crate) entries: Vec<KeyCommitment<CS>>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> IssuerDirectory<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates an empty directory.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds `entry` to the directory, keeping the canonical order.
+    ///
+    /// # Errors
+    /// [`ErrorKind::KeyCommitment`] if the directory already contains an entry
+    /// with the same key ID.
+    pub fn insert(&mut self, entry: KeyCommitment<CS>) -> Result<()> {
+        if self.get(entry.key_id()).is_some() {
+            return Err(Error::new(
+                ErrorKind::KeyCommitment,
+                Operation::VerifyKeyCommitment,
+            ));
+        }
+
+        let index = self.entries.partition_point(|existing| {
+            (existing.not_before, existing.key_id.as_slice())
+                < (entry.not_before, entry.key_id.as_slice())
+        });
+        self.entries.insert(index, entry);
+
+        Ok(())
+    }
+
+    /// Returns the entries in canonical order.
+    pub fn entries(&self) -> &[KeyCommitment<CS>] {
+        &self.entries
+    }
+
+    /// Returns the entry with the given `key_id`, without verifying it.
+    pub fn get(&self, key_id: &[u8]) -> Option<&KeyCommitment<CS>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key_id.as_slice() == key_id)
+    }
+
+    /// Returns the verified public key of the entry valid at the timestamp
+    /// `now` that started being valid most recently. This is the key servers
+    /// should evaluate with.
+    ///
+    /// # Errors
+    /// [`ErrorKind::KeyCommitment`] if no entry is valid at `now`.
+    pub fn current(&self, now: u64) -> Result<<CS::Group as Group>::Elem> {
+        self.entries
+            .iter()
+            .rev()
+            .find_map(|entry| entry.verify(now).ok())
+            .ok_or_else(|| Error::new(ErrorKind::KeyCommitment, Operation::VerifyKeyCommitment))
+    }
+
+    /// Returns the verified public key of the entry with the given `key_id`,
+    /// if it is among the `pinned` key IDs the client expects and valid at the
+    /// timestamp `now`. This prevents a compromised directory from introducing
+    /// keys that could be used to tag clients.
+    ///
+    /// # Errors
+    /// [`ErrorKind::KeyCommitment`] if `key_id` isn't pinned, isn't in the
+    /// directory or its entry isn't valid at `now`.
+    pub fn select_pinned(
+        &self,
+        key_id: &[u8],
+        pinned: &[Output<CS::Hash>],
+        now: u64,
+    ) -> Result<<CS::Group as Group>::Elem> {
+        let error = Error::new(ErrorKind::KeyCommitment, Operation::VerifyKeyCommitment);
+
+        if !pinned.iter().any(|pin| pin.as_slice() == key_id) {
+            return Err(error);
+        }
+
+        self.get(key_id).ok_or(error)?.verify(now)
+    }
+
+    /// Serializes the directory into canonical JSON, without any whitespace:
+    ///
+    /// ```text
+    /// {"keys":[{"suite_id":1,"public_key":"<hex>","key_id":"<hex>","not_before":10,"not_after":20}]}
+    /// ```
+    ///
+    /// Public keys and key IDs are encoded as lowercase hex.
+    pub fn to_json(&self) -> String {
+        let mut output = String::from("{\"keys\":[");
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            if index != 0 {
+                output.push(',');
+            }
+
+            output.push_str("{\"suite_id\":");
+            push_display(&mut output, CS::ID);
+            output.push_str(",\"public_key\":\"");
+            push_hex(&mut output, &CS::Group::serialize_elem(entry.public_key));
+            output.push_str("\",\"key_id\":\"");
+            push_hex(&mut output, &entry.key_id);
+            output.push_str("\",\"not_before\":");
+            push_display(&mut output, entry.not_before);
+            output.push_str(",\"not_after\":");
+            push_display(&mut output, entry.not_after);
+            output.push('}');
+        }
+
+        output.push_str("]}");
+        output
+    }
+
+    /// Parses a directory from JSON in the format of
+    /// [`IssuerDirectory::to_json`]. Whitespace between tokens and any order of
+    /// the fields in an object are accepted, unknown fields, escape sequences
+    /// and duplicate key IDs are not.
+    ///
+    /// This doesn't verify the entries, see [`IssuerDirectory::select_pinned`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to parse `input` or an entry
+    /// belongs to a different ciphersuite.
+    pub fn from_json(input: &str) -> Result<Self> {
+        let error = Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeKeyCommitment,
+        );
+
+        let mut parser = JsonParser {
+            input: input.as_bytes(),
+        };
+        let mut directory = Self::new();
+
+        parser.expect(b'{').ok_or(error)?;
+
+        if parser.string().ok_or(error)? != b"keys" {
+            return Err(error);
+        }
+
+        parser.expect(b':').ok_or(error)?;
+        parser.expect(b'[').ok_or(error)?;
+
+        if !parser.consume(b']') {
+            loop {
+                let entry = parse_json_entry::<CS>(&mut parser).ok_or(error)?;
+                directory.insert(entry).map_err(|_| error)?;
+
+                if parser.consume(b']') {
+                    break;
+                }
+
+                parser.expect(b',').ok_or(error)?;
+            }
+        }
+
+        parser.expect(b'}').ok_or(error)?;
+        parser.skip_whitespace();
+
+        if !parser.input.is_empty() {
+            return Err(error);
+        }
+
+        Ok(directory)
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<CS: CipherSuite> Default for IssuerDirectory<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+fn push_display(output: &mut String, value: impl core::fmt::Display) {
+    // Writing into a `String` can't fail.
+    let _ = write!(output, "{}", value);
+}
+
+fn push_hex(output: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        // Writing into a `String` can't fail.
+        let _ = write!(output, "{:02x}", byte);
+    }
+}
+
+fn decode_hex(input: &[u8]) -> Option<Vec<u8>> {
+    let mut output = vec![0; input.len() / 2];
+
+    if serialization::decode_hex(input, &mut output) {
+        Some(output)
+    } else {
+        None
+    }
+}
+
+fn parse_json_entry<CS: CipherSuite>(parser: &mut JsonParser<'_>) -> Option<KeyCommitment<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut suite_id = None;
+    let mut public_key = None;
+    let mut key_id = None;
+    let mut not_before = None;
+    let mut not_after = None;
+
+    parser.expect(b'{')?;
+
+    loop {
+        let field = parser.string()?;
+        parser.expect(b':')?;
+
+        let duplicate = match field {
+            b"suite_id" => suite_id.replace(parser.number()?).is_some(),
+            b"public_key" => public_key
+                .replace(CS::Group::deserialize_elem(&decode_hex(parser.string()?)?).ok()?)
+                .is_some(),
+            b"key_id" => {
+                let bytes = decode_hex(parser.string()?)?;
+
+                if bytes.len() != <CS::Hash as OutputSizeUser>::OutputSize::USIZE {
+                    return None;
+                }
+
+                key_id
+                    .replace(GenericArray::clone_from_slice(&bytes))
+                    .is_some()
+            }
+            b"not_before" => not_before.replace(parser.number()?).is_some(),
+            b"not_after" => not_after.replace(parser.number()?).is_some(),
+            _ => return None,
+        };
+
+        if duplicate {
+            return None;
+        }
+
+        if parser.consume(b'}') {
+            break;
+        }
+
+        parser.expect(b',')?;
+    }
+
+    if suite_id? != u64::from(CS::ID) {
+        return None;
+    }
+
+    Some(KeyCommitment {
+        public_key: public_key?,
+        not_before: not_before?,
+        not_after: not_after?,
+        key_id: key_id?,
+    })
+}
+
+/// A minimal parser for the JSON subset produced by
+/// [`IssuerDirectory::to_json`].
+struct JsonParser<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> JsonParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while let [b' ' | b'\t' | b'\n' | b'\r', rest @ ..] = self.input {
+            self.input = rest;
+        }
+    }
+
+    fn consume(&mut self, token: u8) -> bool {
+        self.skip_whitespace();
+
+        match self.input {
+            [first, rest @ ..] if *first == token => {
+                self.input = rest;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, token: u8) -> Option<()> {
+        self.consume(token).then(|| ())
+    }
+
+    /// Parses a string without escape sequences.
+    fn string(&mut self) -> Option<&'a [u8]> {
+        self.expect(b'"')?;
+
+        let end = self
+            .input
+            .iter()
+            .position(|&byte| byte == b'"' || byte == b'\\')?;
+        let (string, rest) = self.input.split_at(end);
+
+        // Rejects escape sequences
+        match rest {
+            [b'"', rest @ ..] => {
+                self.input = rest;
+                Some(string)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses an unsigned integer without leading zeros.
+    fn number(&mut self) -> Option<u64> {
+        self.skip_whitespace();
+
+        let end = self
+            .input
+            .iter()
+            .position(|byte| !byte.is_ascii_digit())
+            .unwrap_or(self.input.len());
+        let (digits, rest) = self.input.split_at(end);
+
+        if digits.is_empty() || (digits.len() > 1 && digits.first() == Some(&b'0')) {
+            return None;
+        }
+
+        self.input = rest;
+
+        digits.iter().try_fold(0u64, |number, digit| {
+            number.checked_mul(10)?.checked_add(u64::from(digit - b'0'))
+        })
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use core::ops::Add;
+
+    use generic_array::typenum::{Sum, U16, U2};
+    use generic_array::ArrayLength;
+    use rand::rngs::OsRng;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+    use crate::{KeyCommitmentLen, VoprfServer};
+
+    fn issuer_directory<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        U2: Add<<CS::Group as Group>::ElemLen>,
+        Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+        Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+            ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+        KeyCommitmentLen<CS>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let old_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let new_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let other_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+
+        let mut directory = IssuerDirectory::<CS>::new();
+        directory.insert(new_server.key_commitment(20, 40)).unwrap();
+        directory.insert(old_server.key_commitment(0, 30)).unwrap();
+        assert!(directory.insert(old_server.key_commitment(0, 30)).is_err());
+
+        // The order of insertion doesn't matter
+        let mut reversed = IssuerDirectory::<CS>::new();
+        reversed.insert(old_server.key_commitment(0, 30)).unwrap();
+        reversed.insert(new_server.key_commitment(20, 40)).unwrap();
+        assert_eq!(directory.to_json(), reversed.to_json());
+        assert!(directory == reversed);
+        assert_eq!(directory.entries()[0].key_id(), &old_server.key_id());
+
+        // JSON survives a round trip and tolerates whitespace
+        let json = directory.to_json();
+        assert!(json.starts_with("{\"keys\":[{\"suite_id\":"));
+        assert!(IssuerDirectory::<CS>::from_json(&json).unwrap() == directory);
+        let pretty = json.replace(',', ",\n  ").replace(':', ": ");
+        assert!(IssuerDirectory::<CS>::from_json(&pretty).unwrap() == directory);
+        assert!(
+            IssuerDirectory::<CS>::from_json("{\"keys\":[]}").unwrap() == IssuerDirectory::new()
+        );
+
+        let error = Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeKeyCommitment,
+        );
+        for invalid in [
+            json.replacen("\"suite_id\":", "\"suite_id\":1", 1),
+            json.replacen("\"not_before\"", "\"not_after\"", 1),
+            json.replacen("\"key_id\":\"", "\"key_id\":\"00", 1),
+            json.replacen("}]}", ",\"extra\":1}]}", 1),
+            format!("{} ", json) + "x",
+            json[..json.len() - 1].into(),
+        ] {
+            assert!(matches!(
+                IssuerDirectory::<CS>::from_json(&invalid),
+                Err(e) if e == error
+            ));
+        }
+
+        // Binary survives a round trip
+        let bytes = directory.serialize();
+        assert!(IssuerDirectory::<CS>::deserialize(&bytes).unwrap() == directory);
+        assert!(matches!(
+            IssuerDirectory::<CS>::deserialize(&bytes[1..]),
+            Err(e) if e.kind() == ErrorKind::Deserialization
+        ));
+
+        // The most recent valid key is current
+        let current = directory.current(25).unwrap();
+        assert!(bool::from(current.ct_eq(&new_server.get_public_key())));
+        let current = directory.current(10).unwrap();
+        assert!(bool::from(current.ct_eq(&old_server.get_public_key())));
+        assert!(directory.current(41).is_err());
+
+        // Only pinned keys are selected
+        let pinned = [old_server.key_id()];
+        let error = Error::new(ErrorKind::KeyCommitment, Operation::VerifyKeyCommitment);
+        let selected = directory
+            .select_pinned(&old_server.key_id(), &pinned, 10)
+            .unwrap();
+        assert!(bool::from(selected.ct_eq(&old_server.get_public_key())));
+        assert!(matches!(
+            directory.select_pinned(&new_server.key_id(), &pinned, 25),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            directory.select_pinned(&old_server.key_id(), &pinned, 31),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            directory.select_pinned(&other_server.key_id(), &[other_server.key_id()], 10),
+            Err(e) if e == error
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            issuer_directory::<Ristretto255>();
+        }
+
+        issuer_directory::<NistP256>();
+
+        Ok(())
+    }
+}
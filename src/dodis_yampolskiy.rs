@@ -0,0 +1,373 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the Dodis-Yampolskiy VRF
+
+use core::iter;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::sequence::Concat;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::common::{
+    create_context_string, generate_proof, i2osp_2, server_evaluate_hash_input, verify_proof,
+    STR_HASH_TO_SCALAR,
+};
+#[cfg(feature = "serde")]
+use crate::serialization::serde::{Element, Scalar};
+use crate::{
+    CipherSuite, CustomMode, Error, ErrorKind, EvaluationElement, Group, Mode, Operation, Proof,
+    Result,
+};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_DODIS_YAMPOLSKIY: [u8; 16] = *b"DodisYampolskiy-";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A server evaluating the VRF of Dodis and Yampolskiy, "A Verifiable Random
+/// Function With Short Proofs and Keys", PKC 2005:
+///
+/// ```text
+/// x = G.HashToScalar(input, "HashToScalar-DodisYampolskiy-" || contextString)
+/// output = Hash(I2OSP(len(input), 2) || input ||
+///               I2OSP(len(Y), 2) || G.SerializeElement(Y) ||
+///               "Finalize")
+/// where Y = (1 / (k + x)) * G.Generator()
+/// ```
+///
+/// Unlike the OPRF of the specification, the key is inverted together with
+/// the input instead of multiplied with it, which gives the VRF the algebraic
+/// structure anonymous credentials and e-cash schemes build on. It is meant to
+/// compare these constructions against the specified modes, with the same
+/// [`Group`]s and proofs, under the context string of a [`CustomMode`].
+///
+/// This is not an OPRF, the server sees the `input`: evaluating the inversion
+/// obliviously requires a two-party computation over additively homomorphic
+/// encryption, e.g. the one of Jarecki and Liu, TCC 2009, which this crate
+/// doesn't provide. The evaluation is proven against the public key instead,
+/// with a proof of [`custom_generate_proof`](crate::custom_generate_proof) that
+/// `Y * (k + x) = G.Generator()`, and verified by [`dy_vrf_verify`].
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::{dy_vrf_verify, CustomMode, DyVrfServer};
+///
+/// let mut rng = OsRng;
+/// let mode = CustomMode::new(0x80, "DodisYampolskiy").unwrap();
+/// let server = DyVrfServer::<CipherSuite>::new(&mut rng).unwrap();
+///
+/// let result = server.evaluate(&mut rng, mode, b"input").unwrap();
+/// let output = dy_vrf_verify(
+///     mode,
+///     b"input",
+///     &result.message,
+///     &result.proof,
+///     server.get_public_key(),
+/// )
+/// .unwrap();
+/// ```
+#[derive_where(Clone, ZeroizeOnDrop)]
+#[derive_where(Debug, Eq, Hash, Ord, PartialEq, PartialOrd; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(crate = "serde", bound = "")
+)]
+pub struct DyVrfServer<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    #[cfg_attr(feature = "serde", serde(with = "Scalar::<CS::Group>"))]
+    pub(crate) sk: <CS::Group as Group>::Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "Element::<CS::Group>"))]
+    pub(crate) pk: <CS::Group as Group>::Elem,
+}
+
+/// Contains the fields that are returned by a [`DyVrfServer`] evaluate
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct DyVrfEvaluateResult<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The message to send to the client
+    pub message: EvaluationElement<CS>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> DyVrfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Produces a new instance of a [`DyVrfServer`] using a supplied RNG
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
+        let sk = CS::Group::random_scalar(rng);
+        let pk = CS::Group::base_elem() * &sk;
+        Ok(Self { sk, pk })
+    }
+
+    /// Produces a new instance of a [`DyVrfServer`] using a supplied set of
+    /// bytes to represent the server's private key
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the private key is not a valid scalar
+    /// or zero.
+    pub fn new_with_key(key: &[u8]) -> Result<Self> {
+        let sk = CS::Group::deserialize_scalar(key)?;
+        let pk = CS::Group::base_elem() * &sk;
+        Ok(Self { sk, pk })
+    }
+
+    /// Evaluates the VRF on the `input` and proves the evaluation against the
+    /// public key under the context string of the `mode`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the `input` hashes to the negated private
+    ///   key, which only happens with negligible probability.
+    pub fn evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        mode: CustomMode,
+        input: &[u8],
+    ) -> Result<DyVrfEvaluateResult<CS>> {
+        let x = hash_input::<CS>(mode, input)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        let exponent = self.sk + &x;
+        let inverse =
+            Option::<<CS::Group as Group>::Scalar>::from(CS::Group::invert_scalar_ct(exponent))
+                .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))?;
+        let evaluation_element = CS::Group::base_elem() * &inverse;
+
+        let proof = generate_proof(
+            rng,
+            exponent,
+            CS::Group::base_elem(),
+            compute_statement::<CS>(self.pk, x),
+            iter::once(evaluation_element),
+            iter::once(CS::Group::base_elem()),
+            Mode::Custom(mode),
+        )?;
+
+        Ok(DyVrfEvaluateResult {
+            message: EvaluationElement(evaluation_element),
+            proof,
+        })
+    }
+
+    /// Retrieves the server's public key
+    pub fn get_public_key(&self) -> <CS::Group as Group>::Elem {
+        self.pk
+    }
+}
+
+///////////////////////////
+// Convenience Functions //
+// ===================== //
+///////////////////////////
+
+/// Verifies the `proof` of a [`DyVrfServer`] evaluation of the `input` against
+/// the `public_key` and hashes the [`EvaluationElement`] to the output.
+///
+/// # Errors
+/// - [`ErrorKind::Input`] if the `input` is longer then [`u16::MAX`].
+/// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+pub fn dy_vrf_verify<CS: CipherSuite>(
+    mode: CustomMode,
+    input: &[u8],
+    evaluation_element: &EvaluationElement<CS>,
+    proof: &Proof<CS>,
+    public_key: <CS::Group as Group>::Elem,
+) -> Result<Output<CS::FinalizeHash>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let x =
+        hash_input::<CS>(mode, input).map_err(|error| error.with_operation(Operation::Finalize))?;
+
+    verify_proof(
+        CS::Group::base_elem(),
+        compute_statement::<CS>(public_key, x),
+        iter::once(evaluation_element.0),
+        iter::once(CS::Group::base_elem()),
+        proof,
+        Mode::Custom(mode),
+    )?;
+
+    server_evaluate_hash_input::<CS>(input, None, CS::Group::serialize_elem(evaluation_element.0))
+        .map_err(|error| error.with_operation(Operation::Finalize))
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+/// Can only fail with [`ErrorKind::Input`]. The returned error doesn't carry
+/// an [`Operation`] yet.
+fn hash_input<CS: CipherSuite>(
+    mode: CustomMode,
+    input: &[u8],
+) -> Result<<CS::Group as Group>::Scalar>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // The output hashes the length of the `input` in two bytes.
+    i2osp_2(input.len()).map_err(|_| Error::from(ErrorKind::Input))?;
+
+    let dst = GenericArray::from(STR_HASH_TO_SCALAR)
+        .concat(STR_DODIS_YAMPOLSKIY.into())
+        .concat(create_context_string::<CS>(Mode::Custom(mode)));
+
+    CS::Group::hash_to_scalar::<CS::Hash>(&[input], &dst).map_err(|_| Error::from(ErrorKind::Input))
+}
+
+/// The element whose discrete logarithm `k + x` is proven, `pk + x * G`.
+fn compute_statement<CS: CipherSuite>(
+    public_key: <CS::Group as Group>::Elem,
+    x: <CS::Group as Group>::Scalar,
+) -> <CS::Group as Group>::Elem
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    public_key + &(CS::Group::base_elem() * &x)
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+
+    fn dy_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let mode = CustomMode::new(0x80, "DodisYampolskiy").unwrap();
+        let other_mode = CustomMode::new(0x81, "DodisYampolskiy").unwrap();
+        let server = DyVrfServer::<CS>::new(&mut rng).unwrap();
+        let public_key = server.get_public_key();
+
+        let result = server.evaluate(&mut rng, mode, b"input").unwrap();
+        let output =
+            dy_vrf_verify(mode, b"input", &result.message, &result.proof, public_key).unwrap();
+
+        // The evaluation is the inverse of the key and the hashed input
+        let x = hash_input::<CS>(mode, b"input").unwrap();
+        let exponent = server.sk + &x;
+        assert!(bool::from(
+            (result.message.0 * &exponent).ct_eq(&CS::Group::base_elem())
+        ));
+
+        // Evaluations are deterministic, but differ by input, key and mode
+        let again = server.evaluate(&mut rng, mode, b"input").unwrap();
+        assert!(bool::from(again.message.0.ct_eq(&result.message.0)));
+        let other = server.evaluate(&mut rng, mode, b"other").unwrap();
+        assert_ne!(
+            output,
+            dy_vrf_verify(mode, b"other", &other.message, &other.proof, public_key).unwrap()
+        );
+        let other = server.evaluate(&mut rng, other_mode, b"input").unwrap();
+        assert!(!bool::from(other.message.0.ct_eq(&result.message.0)));
+        let other_server = DyVrfServer::<CS>::new(&mut rng).unwrap();
+        let other = other_server.evaluate(&mut rng, mode, b"input").unwrap();
+        assert!(!bool::from(other.message.0.ct_eq(&result.message.0)));
+
+        // The server is restored from its private key
+        let restored = DyVrfServer::<CS>::new_with_key(&server.serialize()).unwrap();
+        assert!(bool::from(restored.get_public_key().ct_eq(&public_key)));
+        let restored = DyVrfServer::<CS>::deserialize(&server.serialize()).unwrap();
+        assert_eq!(restored.serialize(), server.serialize());
+        assert_eq!(server.serialize().len(), DyVrfServer::<CS>::LEN);
+
+        // Proofs fail for other inputs, modes, keys and evaluations
+        let error = Error::new(ErrorKind::ProofVerification, Operation::VerifyProof);
+        assert!(matches!(
+            dy_vrf_verify(mode, b"other", &result.message, &result.proof, public_key),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            dy_vrf_verify(other_mode, b"input", &result.message, &result.proof, public_key),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            dy_vrf_verify(
+                mode,
+                b"input",
+                &result.message,
+                &result.proof,
+                other_server.get_public_key()
+            ),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            dy_vrf_verify(mode, b"input", &other.message, &result.proof, public_key),
+            Err(e) if e == error
+        ));
+
+        let error = Error::new(ErrorKind::Input, Operation::Evaluate);
+        assert!(matches!(
+            server.evaluate(&mut rng, mode, &[0; u16::MAX as usize + 1]),
+            Err(e) if e == error
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            dy_retrieval::<Ristretto255>();
+        }
+
+        dy_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,539 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Object-safe wrappers around [`CipherSuite`], to select the group at runtime
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{self, Debug, Formatter};
+use core::marker::PhantomData;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Digest, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use rand_core::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::{CipherSuite, Error, ErrorKind, Group, Operation, Result};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A group element of a [`DynSuite`], with its type erased.
+pub struct DynGroupElement(Box<dyn Value>);
+
+/// A scalar of a [`DynSuite`], with its type erased. Zeroized on drop.
+pub struct DynScalar(Box<dyn Value>);
+
+/// An object-safe random number generator, implemented for every
+/// [`RngCore`] + [`CryptoRng`].
+pub trait DynCryptoRng: CryptoRng + RngCore {}
+
+impl<T: CryptoRng + RngCore + ?Sized> DynCryptoRng for T {}
+
+/// An object-safe version of [`CipherSuite`] and its [`Group`], so that the
+/// ciphersuite can be chosen at runtime, e.g. by a plugin, without the
+/// application being generic over it.
+///
+/// Elements and scalars can only be used with a suite of the same [`Group`]
+/// they were created by, otherwise [`ErrorKind::Suite`] is returned.
+///
+/// Every [`CipherSuite`] is available as a [`DynSuite`] through
+/// [`StaticSuite`].
+pub trait DynSuite: Send + Sync {
+    /// The ciphersuite identifier, see [`CipherSuite::ID`].
+    fn id(&self) -> u16;
+
+    /// The byte length of serialized group elements.
+    fn elem_len(&self) -> usize;
+
+    /// The byte length of serialized scalars.
+    fn scalar_len(&self) -> usize;
+
+    /// Hashes the concatenated `input` with the hash function of the suite.
+    fn hash(&self, input: &[&[u8]]) -> Vec<u8>;
+
+    /// See [`Group::hash_to_curve`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    fn hash_to_group(&self, input: &[&[u8]], dst: &[u8]) -> Result<DynGroupElement>;
+
+    /// See [`Group::hash_to_scalar`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    fn hash_to_scalar(&self, input: &[&[u8]], dst: &[u8]) -> Result<DynScalar>;
+
+    /// See [`Group::base_elem`].
+    fn base_elem(&self) -> DynGroupElement;
+
+    /// See [`Group::identity_elem`].
+    fn identity_elem(&self) -> DynGroupElement;
+
+    /// Returns `true` if the elements are equal, computed in constant-time.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if an element doesn't belong to this suite.
+    fn elem_eq(&self, a: &DynGroupElement, b: &DynGroupElement) -> Result<bool>;
+
+    /// Adds two elements.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if an element doesn't belong to this suite.
+    fn add_elems(&self, a: &DynGroupElement, b: &DynGroupElement) -> Result<DynGroupElement>;
+
+    /// Multiplies an element by a scalar.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if the element or the scalar doesn't belong to
+    /// this suite.
+    fn mul_elem(&self, elem: &DynGroupElement, scalar: &DynScalar) -> Result<DynGroupElement>;
+
+    /// See [`Group::serialize_elem`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if the element doesn't belong to this suite.
+    fn serialize_elem(&self, elem: &DynGroupElement) -> Result<Vec<u8>>;
+
+    /// See [`Group::deserialize_elem`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the element is not a valid point on
+    /// the group or the identity element.
+    fn deserialize_elem(&self, element_bits: &[u8]) -> Result<DynGroupElement>;
+
+    /// See [`Group::random_scalar`].
+    fn random_scalar(&self, rng: &mut dyn DynCryptoRng) -> DynScalar;
+
+    /// Adds two scalars.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if a scalar doesn't belong to this suite.
+    fn add_scalars(&self, a: &DynScalar, b: &DynScalar) -> Result<DynScalar>;
+
+    /// Subtracts `b` from `a`.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if a scalar doesn't belong to this suite.
+    fn sub_scalars(&self, a: &DynScalar, b: &DynScalar) -> Result<DynScalar>;
+
+    /// Multiplies two scalars.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if a scalar doesn't belong to this suite.
+    fn mul_scalars(&self, a: &DynScalar, b: &DynScalar) -> Result<DynScalar>;
+
+    /// See [`Group::invert_scalar_ct`]. Returns [`None`] if the scalar is
+    /// zero.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if the scalar doesn't belong to this suite.
+    fn invert_scalar(&self, scalar: &DynScalar) -> Result<Option<DynScalar>>;
+
+    /// See [`Group::serialize_scalar`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Suite`] if the scalar doesn't belong to this suite.
+    fn serialize_scalar(&self, scalar: &DynScalar) -> Result<Vec<u8>>;
+
+    /// See [`Group::deserialize_scalar`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the scalar is not valid or zero.
+    fn deserialize_scalar(&self, scalar_bits: &[u8]) -> Result<DynScalar>;
+}
+
+/// Implements [`DynSuite`] for the [`CipherSuite`] `CS`.
+#[derive_where(Clone, Copy, Debug, Default)]
+pub struct StaticSuite<CS: CipherSuite>(PhantomData<fn() -> CS>)
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>;
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl DynGroupElement {
+    /// Wraps a group element of `G`.
+    pub fn from_elem<G: Group>(elem: G::Elem) -> Self
+    where
+        G::Elem: Send + Sync + 'static,
+    {
+        Self(Box::new(elem))
+    }
+
+    /// Unwraps the group element, returns [`None`] if it doesn't belong to
+    /// `G`.
+    pub fn to_elem<G: Group>(&self) -> Option<G::Elem>
+    where
+        G::Elem: 'static,
+    {
+        self.0.as_any().downcast_ref().copied()
+    }
+}
+
+impl DynScalar {
+    /// Wraps a scalar of `G`.
+    pub fn from_scalar<G: Group>(scalar: G::Scalar) -> Self
+    where
+        G::Scalar: Send + Sync + 'static,
+    {
+        Self(Box::new(scalar))
+    }
+
+    /// Unwraps the scalar, returns [`None`] if it doesn't belong to `G`.
+    pub fn to_scalar<G: Group>(&self) -> Option<G::Scalar>
+    where
+        G::Scalar: 'static,
+    {
+        self.0.as_any().downcast_ref().copied()
+    }
+}
+
+impl<CS: CipherSuite> StaticSuite<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates the [`DynSuite`] of `CS`.
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<CS: CipherSuite + 'static> DynSuite for StaticSuite<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::Elem: Send + Sync + 'static,
+    <CS::Group as Group>::Scalar: Send + Sync + 'static,
+{
+    fn id(&self) -> u16 {
+        CS::ID
+    }
+
+    fn elem_len(&self) -> usize {
+        <CS::Group as Group>::ElemLen::USIZE
+    }
+
+    fn scalar_len(&self) -> usize {
+        <CS::Group as Group>::ScalarLen::USIZE
+    }
+
+    fn hash(&self, input: &[&[u8]]) -> Vec<u8> {
+        input
+            .iter()
+            .fold(CS::Hash::new(), |hash, input| hash.chain_update(input))
+            .finalize()
+            .to_vec()
+    }
+
+    fn hash_to_group(&self, input: &[&[u8]], dst: &[u8]) -> Result<DynGroupElement> {
+        CS::Group::hash_to_curve::<CS::Hash>(input, dst)
+            .map(DynGroupElement::from_elem::<CS::Group>)
+            .map_err(|_| Error::new(ErrorKind::Input, Operation::DynSuite))
+    }
+
+    fn hash_to_scalar(&self, input: &[&[u8]], dst: &[u8]) -> Result<DynScalar> {
+        CS::Group::hash_to_scalar::<CS::Hash>(input, dst)
+            .map(DynScalar::from_scalar::<CS::Group>)
+            .map_err(|_| Error::new(ErrorKind::Input, Operation::DynSuite))
+    }
+
+    fn base_elem(&self) -> DynGroupElement {
+        DynGroupElement::from_elem::<CS::Group>(CS::Group::base_elem())
+    }
+
+    fn identity_elem(&self) -> DynGroupElement {
+        DynGroupElement::from_elem::<CS::Group>(CS::Group::identity_elem())
+    }
+
+    fn elem_eq(&self, a: &DynGroupElement, b: &DynGroupElement) -> Result<bool> {
+        Ok(downcast_elem::<CS::Group>(a)?
+            .ct_eq(&downcast_elem::<CS::Group>(b)?)
+            .into())
+    }
+
+    fn add_elems(&self, a: &DynGroupElement, b: &DynGroupElement) -> Result<DynGroupElement> {
+        Ok(DynGroupElement::from_elem::<CS::Group>(
+            downcast_elem::<CS::Group>(a)? + &downcast_elem::<CS::Group>(b)?,
+        ))
+    }
+
+    fn mul_elem(&self, elem: &DynGroupElement, scalar: &DynScalar) -> Result<DynGroupElement> {
+        Ok(DynGroupElement::from_elem::<CS::Group>(
+            downcast_elem::<CS::Group>(elem)? * &downcast_scalar::<CS::Group>(scalar)?,
+        ))
+    }
+
+    fn serialize_elem(&self, elem: &DynGroupElement) -> Result<Vec<u8>> {
+        Ok(CS::Group::serialize_elem(downcast_elem::<CS::Group>(elem)?).to_vec())
+    }
+
+    fn deserialize_elem(&self, element_bits: &[u8]) -> Result<DynGroupElement> {
+        CS::Group::deserialize_elem(element_bits).map(DynGroupElement::from_elem::<CS::Group>)
+    }
+
+    fn random_scalar(&self, rng: &mut dyn DynCryptoRng) -> DynScalar {
+        DynScalar::from_scalar::<CS::Group>(CS::Group::random_scalar(&mut DynRng(rng)))
+    }
+
+    fn add_scalars(&self, a: &DynScalar, b: &DynScalar) -> Result<DynScalar> {
+        Ok(DynScalar::from_scalar::<CS::Group>(
+            downcast_scalar::<CS::Group>(a)? + &downcast_scalar::<CS::Group>(b)?,
+        ))
+    }
+
+    fn sub_scalars(&self, a: &DynScalar, b: &DynScalar) -> Result<DynScalar> {
+        Ok(DynScalar::from_scalar::<CS::Group>(
+            downcast_scalar::<CS::Group>(a)? - &downcast_scalar::<CS::Group>(b)?,
+        ))
+    }
+
+    fn mul_scalars(&self, a: &DynScalar, b: &DynScalar) -> Result<DynScalar> {
+        Ok(DynScalar::from_scalar::<CS::Group>(
+            downcast_scalar::<CS::Group>(a)? * &downcast_scalar::<CS::Group>(b)?,
+        ))
+    }
+
+    fn invert_scalar(&self, scalar: &DynScalar) -> Result<Option<DynScalar>> {
+        let inverse = CS::Group::invert_scalar_ct(downcast_scalar::<CS::Group>(scalar)?);
+        Ok(Option::from(inverse).map(DynScalar::from_scalar::<CS::Group>))
+    }
+
+    fn serialize_scalar(&self, scalar: &DynScalar) -> Result<Vec<u8>> {
+        Ok(CS::Group::serialize_scalar(downcast_scalar::<CS::Group>(scalar)?).to_vec())
+    }
+
+    fn deserialize_scalar(&self, scalar_bits: &[u8]) -> Result<DynScalar> {
+        CS::Group::deserialize_scalar(scalar_bits).map(DynScalar::from_scalar::<CS::Group>)
+    }
+}
+
+impl Clone for DynGroupElement {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+
+impl Debug for DynGroupElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynGroupElement").finish_non_exhaustive()
+    }
+}
+
+impl Clone for DynScalar {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+
+impl Debug for DynScalar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynScalar").finish_non_exhaustive()
+    }
+}
+
+impl Drop for DynScalar {
+    fn drop(&mut self) {
+        self.0.zeroize_value();
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+fn downcast_elem<G: Group>(elem: &DynGroupElement) -> Result<G::Elem>
+where
+    G::Elem: 'static,
+{
+    elem.to_elem::<G>()
+        .ok_or_else(|| Error::new(ErrorKind::Suite, Operation::DynSuite))
+}
+
+fn downcast_scalar<G: Group>(scalar: &DynScalar) -> Result<G::Scalar>
+where
+    G::Scalar: 'static,
+{
+    scalar
+        .to_scalar::<G>()
+        .ok_or_else(|| Error::new(ErrorKind::Suite, Operation::DynSuite))
+}
+
+/// A type-erased group element or scalar.
+trait Value: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn clone_box(&self) -> Box<dyn Value>;
+
+    fn zeroize_value(&mut self);
+}
+
+impl<T: Any + Copy + Send + Sync + Zeroize> Value for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Value> {
+        Box::new(*self)
+    }
+
+    fn zeroize_value(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Makes a [`DynCryptoRng`] usable where a sized random number generator is
+/// required.
+pub(crate) struct DynRng<'a>(pub(crate) &'a mut dyn DynCryptoRng);
+
+impl RngCore for DynRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for DynRng<'_> {}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::common::{create_context_string, STR_HASH_TO_GROUP};
+    use crate::{Mode, OprfClient, OprfServer};
+
+    fn dyn_retrieval<CS: CipherSuite + 'static>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::Elem: Send + Sync + 'static,
+        <CS::Group as Group>::Scalar: Send + Sync + 'static,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let suite: Box<dyn DynSuite> = Box::new(StaticSuite::<CS>::new());
+        assert_eq!(suite.id(), CS::ID);
+
+        // The blinding of the typed client, computed through the dynamic suite
+        let dst = [
+            &STR_HASH_TO_GROUP[..],
+            &create_context_string::<CS>(Mode::Oprf),
+        ]
+        .concat();
+        let blind = suite.random_scalar(&mut rng);
+        let hashed_point = suite.hash_to_group(&[input], &dst).unwrap();
+        let blinded_element = suite.mul_elem(&hashed_point, &blind).unwrap();
+
+        let typed_blind_result = OprfClient::<CS>::deterministic_blind_unchecked(
+            input,
+            blind.to_scalar::<CS::Group>().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            suite.serialize_elem(&blinded_element).unwrap(),
+            typed_blind_result.message.serialize().to_vec()
+        );
+
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+
+        // Unblinding the evaluation recovers the evaluated input
+        let key = suite
+            .deserialize_scalar(&CS::Group::serialize_scalar(server.get_private_key()))
+            .unwrap();
+        let evaluation_element = suite.mul_elem(&blinded_element, &key).unwrap();
+        let inverse = suite.invert_scalar(&blind).unwrap().unwrap();
+        let unblinded_element = suite.mul_elem(&evaluation_element, &inverse).unwrap();
+        let expected = suite.mul_elem(&hashed_point, &key).unwrap();
+        assert!(suite.elem_eq(&unblinded_element, &expected).unwrap());
+        assert!(!suite.elem_eq(&unblinded_element, &hashed_point).unwrap());
+
+        // Serialization roundtrips
+        let bytes = suite.serialize_elem(&expected).unwrap();
+        assert_eq!(bytes.len(), suite.elem_len());
+        let deserialized = suite.deserialize_elem(&bytes).unwrap();
+        assert!(suite.elem_eq(&deserialized, &expected.clone()).unwrap());
+        let bytes = suite.serialize_scalar(&key).unwrap();
+        assert_eq!(bytes.len(), suite.scalar_len());
+
+        // The zero scalar has no inverse
+        let zero = suite.sub_scalars(&key, &key).unwrap();
+        assert!(suite.invert_scalar(&zero).unwrap().is_none());
+    }
+
+    #[cfg(feature = "ristretto255")]
+    fn suite_mismatch() {
+        use p256::NistP256;
+
+        use crate::Ristretto255;
+
+        let ristretto: Box<dyn DynSuite> = Box::new(StaticSuite::<Ristretto255>::new());
+        let p256: Box<dyn DynSuite> = Box::new(StaticSuite::<NistP256>::new());
+
+        let elem = ristretto.base_elem();
+        let scalar = p256.random_scalar(&mut OsRng);
+        assert!(matches!(
+            p256.serialize_elem(&elem),
+            Err(error) if error == Error::new(ErrorKind::Suite, Operation::DynSuite)
+        ));
+        assert!(matches!(
+            ristretto.mul_elem(&elem, &scalar),
+            Err(error) if error == Error::new(ErrorKind::Suite, Operation::DynSuite)
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            dyn_retrieval::<Ristretto255>();
+            suite_mismatch();
+        }
+
+        dyn_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -7,15 +7,32 @@
 
 //! Errors which are produced during an execution of the protocol
 
+use core::fmt::{self, Display, Formatter};
+
 use displaydoc::Display;
 
 /// [`Result`](core::result::Result) shorthand that uses [`Error`].
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
 /// Represents an error in the manipulation of internal cryptographic data
+///
+/// Besides the [`ErrorKind`], it carries the [`Operation`] that failed and,
+/// for batch operations, the index of the offending item.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Error {
+    kind: ErrorKind,
+    operation: Option<Operation>,
+    index: Option<usize>,
+}
+
+/// The kind of an [`Error`]
+///
+/// New kinds are added in minor versions, so matches need a wildcard arm.
 #[derive(Clone, Copy, Debug, Display, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub enum Error {
-    /// Size of info is longer then [`u16::MAX`].
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Size of info is longer then [`u16::MAX`] or info is given to a mode
+    /// without public input.
     Info,
     /// Size of input is empty or longer then [`u16::MAX`].
     Input,
@@ -29,10 +46,286 @@ pub enum Error {
     ProofVerification,
     /// The protocol has failed and can't be completed.
     Protocol,
+    /// The info was rejected by the server's policy.
+    InfoPolicy,
+    /// A key commitment doesn't match its public key or isn't valid at the
+    /// given time.
+    KeyCommitment,
+    /// A group element or scalar was used with a `DynSuite` of another group.
+    Suite,
+    /// The threshold of key shares is zero or larger then their count, or key
+    /// shares are missing or have a duplicate or zero index.
+    KeyShare,
+    /// The evaluation was denied by the gatekeeper of the server.
+    Gatekeeper,
+    /// The salt or parameters were rejected by Argon2id.
+    Hardening,
+    /// Memory for a secret couldn't be locked or excluded from core dumps.
+    SecureMemory,
+    /// A redeemed token doesn't carry the output of its input.
+    Token,
+    /// A token was already redeemed.
+    Replay,
+    /// Tagged bytes were serialized with another suite, mode or kind of state
+    /// than they are deserialized with.
+    SuiteMismatch,
+    /// A state couldn't be sealed, or sealed bytes failed to authenticate.
+    Sealing,
+    /// A suite ID is unknown to or already registered in a `ServerPool`, or a
+    /// response is tagged with another suite ID.
+    SuiteId,
+}
+
+/// The operation during which an [`Error`] occurred
+///
+/// New operations are added in minor versions, so matches need a wildcard arm.
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Blinding
+    Blind,
+    /// Evaluation
+    Evaluate,
+    /// Finalization
+    Finalize,
+    /// Proof verification
+    VerifyProof,
+    /// Key derivation
+    DeriveKeyPair,
+    /// Element deserialization
+    DeserializeElem,
+    /// Scalar deserialization
+    DeserializeScalar,
+    /// Proof deserialization
+    DeserializeProof,
+    /// Key commitment verification
+    VerifyKeyCommitment,
+    /// Key commitment deserialization
+    DeserializeKeyCommitment,
+    /// Key possession proof generation
+    ProveKeyPossession,
+    /// Key possession proof verification
+    VerifyKeyPossession,
+    /// Key possession proof deserialization
+    DeserializeKeyPossessionProof,
+    /// Key splitting into shares
+    SplitKey,
+    /// Key reconstruction from shares
+    ReconstructKey,
+    /// Key share deserialization
+    DeserializeKeyShare,
+    /// Message expansion
+    ExpandMessage,
+    /// Base64url decoding
+    DecodeBase64Url,
+    /// Output hardening with Argon2id
+    HardenOutput,
+    /// Pseudonymous tag derivation
+    Pseudonymize,
+    /// Custom mode creation
+    CustomMode,
+    /// Dynamic ciphersuite operation
+    DynSuite,
+    /// Ciphersuite conformance check
+    CheckConformance,
+    /// Memory locking
+    LockMemory,
+    /// Token redemption
+    Redeem,
+    /// Tagged state deserialization
+    DeserializeTagged,
+    /// State sealing
+    SealState,
+    /// Sealed state opening
+    OpenState,
+}
+
+impl Error {
+    /// Creates an [`Error`] of the given `kind` that occurred during
+    /// `operation`.
+    pub fn new(kind: ErrorKind, operation: Operation) -> Self {
+        Self {
+            kind,
+            operation: Some(operation),
+            index: None,
+        }
+    }
+
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the operation that failed, if known.
+    pub fn operation(&self) -> Option<Operation> {
+        self.operation
+    }
+
+    /// Returns the index of the offending item if this error occurred in a
+    /// batch operation.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Sets the operation that failed, replacing any previous one.
+    ///
+    /// This can be used to attach context to errors returned from
+    /// lower-level functions like
+    /// [`Group::deserialize_elem`](crate::Group::deserialize_elem).
+    #[must_use]
+    pub fn with_operation(mut self, operation: Operation) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
+    /// Sets the index of the offending item in a batch operation.
+    ///
+    /// This can be used to attach context to errors in batches processed by
+    /// the caller, e.g. when deserializing many [`BlindedElement`]s.
+    ///
+    /// [`BlindedElement`]: crate::BlindedElement
+    #[must_use]
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Returns the stable numeric code of this error, for FFI layers and
+    /// network protocols. The lower 16 bits are the [`ErrorKind::code`] and
+    /// the upper 16 bits the [`Operation::code`], or zero if the operation is
+    /// unknown. The index isn't included.
+    pub fn code(&self) -> u32 {
+        let operation = self.operation.map_or(0, Operation::code);
+
+        u32::from(operation) << 16 | u32::from(self.kind.code())
+    }
+
+    /// Returns the error of a code returned by [`code`](Self::code), without
+    /// an index, or [`None`] if the code is unknown.
+    pub fn from_code(code: u32) -> Option<Self> {
+        let [operation_high, operation_low, kind_high, kind_low] = code.to_be_bytes();
+        let kind = ErrorKind::from_code(u16::from_be_bytes([kind_high, kind_low]))?;
+        let operation = match u16::from_be_bytes([operation_high, operation_low]) {
+            0 => None,
+            operation => Some(Operation::from_code(operation)?),
+        };
+
+        Some(Self {
+            kind,
+            operation,
+            index: None,
+        })
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            operation: None,
+            index: None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (self.operation, self.index) {
+            (Some(operation), Some(index)) => {
+                write!(f, "{} of item {} failed: {}", operation, index, self.kind)
+            }
+            (Some(operation), None) => write!(f, "{} failed: {}", operation, self.kind),
+            (None, Some(index)) => write!(f, "Item {} failed: {}", index, self.kind),
+            (None, None) => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// Implements `code` and `from_code` with the given stable codes. A code must
+/// never be reused, not even after its variant is removed.
+macro_rules! stable_codes {
+    ($name:ident { $($variant:ident = $code:literal,)* }) => {
+        impl $name {
+            /// Returns the stable numeric code of this variant, which is never
+            /// zero and never changes between versions.
+            pub fn code(self) -> u16 {
+                match self {
+                    $(Self::$variant => $code,)*
+                }
+            }
+
+            /// Returns the variant of a code returned by
+            /// [`code`](Self::code), or [`None`] if the code is unknown, e.g.
+            /// because it was produced by a newer version.
+            pub fn from_code(code: u16) -> Option<Self> {
+                match code {
+                    $($code => Some(Self::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+stable_codes! {
+    ErrorKind {
+        Info = 1,
+        Input = 2,
+        DeriveKeyPair = 3,
+        Deserialization = 4,
+        Batch = 5,
+        ProofVerification = 6,
+        Protocol = 7,
+        InfoPolicy = 8,
+        KeyCommitment = 9,
+        Suite = 10,
+        KeyShare = 11,
+        Gatekeeper = 12,
+        Hardening = 13,
+        SecureMemory = 14,
+        Token = 15,
+        Replay = 16,
+        SuiteMismatch = 17,
+        Sealing = 18,
+        SuiteId = 19,
+    }
+}
+
+stable_codes! {
+    Operation {
+        Blind = 1,
+        Evaluate = 2,
+        Finalize = 3,
+        VerifyProof = 4,
+        DeriveKeyPair = 5,
+        DeserializeElem = 6,
+        DeserializeScalar = 7,
+        DeserializeProof = 8,
+        VerifyKeyCommitment = 9,
+        DeserializeKeyCommitment = 10,
+        ProveKeyPossession = 11,
+        VerifyKeyPossession = 12,
+        DeserializeKeyPossessionProof = 13,
+        SplitKey = 14,
+        ReconstructKey = 15,
+        DeserializeKeyShare = 16,
+        ExpandMessage = 17,
+        DecodeBase64Url = 18,
+        HardenOutput = 19,
+        Pseudonymize = 20,
+        CustomMode = 21,
+        DynSuite = 22,
+        CheckConformance = 23,
+        LockMemory = 24,
+        Redeem = 25,
+        DeserializeTagged = 26,
+        SealState = 27,
+        OpenState = 28,
+    }
 }
 
 /// Only used to implement [`Group`](crate::Group).
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum InternalError {
     /// Size of input is empty or longer then [`u16::MAX`].
     Input,
@@ -40,5 +333,78 @@ pub enum InternalError {
     I2osp,
 }
 
+// `core::error::Error` requires Rust 1.81, so the error types only implement
+// `std::error::Error`. Errors of the curves and hashes aren't kept, so there is
+// no source to chain to.
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InternalError {}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            InternalError::I2osp.to_string(),
+            "`input` is longer then [`u16::MAX`]."
+        );
+        assert_eq!(
+            Error::new(ErrorKind::Token, Operation::Redeem)
+                .with_index(2)
+                .to_string(),
+            "Token redemption of item 2 failed: A redeemed token doesn't carry the output of its \
+             input."
+        );
+
+        #[cfg(feature = "std")]
+        {
+            use std::error::Error as _;
+
+            assert!(Error::from(ErrorKind::Input).source().is_none());
+            assert!(InternalError::Input.source().is_none());
+        }
+    }
+
+    #[test]
+    fn test_codes() {
+        for code in 0..=u16::MAX {
+            if let Some(kind) = ErrorKind::from_code(code) {
+                assert_eq!(kind.code(), code);
+            }
+            if let Some(operation) = Operation::from_code(code) {
+                assert_eq!(operation.code(), code);
+            }
+        }
+        assert_eq!(ErrorKind::from_code(0), None);
+        assert_eq!(Operation::from_code(0), None);
+
+        // The codes are stable
+        assert_eq!(ErrorKind::Info.code(), 1);
+        assert_eq!(ErrorKind::Replay.code(), 16);
+        assert_eq!(Operation::Blind.code(), 1);
+        assert_eq!(Operation::Redeem.code(), 25);
+
+        let error = Error::new(ErrorKind::ProofVerification, Operation::VerifyProof);
+        assert_eq!(error.code(), 0x0004_0006);
+        assert_eq!(Error::from_code(error.code()), Some(error));
+        assert_eq!(Error::from_code(error.with_index(3).code()), Some(error));
+
+        let error = Error::from(ErrorKind::Batch);
+        assert_eq!(error.code(), 5);
+        assert_eq!(Error::from_code(5), Some(error));
+
+        assert_eq!(Error::from_code(0x0004_0000), None);
+        assert_eq!(Error::from_code(0xFFFF_0006), None);
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Exposes the `expand_message` functions used to hash to the group
+
+use digest::core_api::BlockSizeUser;
+use digest::{Digest, ExtendableOutput, Update};
+use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, ExpandMsgXof, Expander};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+
+use crate::{Error, ErrorKind, Operation, Result};
+
+/// Fills `output` with uniformly random bytes derived from the concatenation
+/// of `input` with `expand_message_xmd`, as used by
+/// [`Group::hash_to_curve`](crate::Group::hash_to_curve) and
+/// [`Group::hash_to_scalar`](crate::Group::hash_to_scalar).
+///
+/// Corresponds to `expand_message_xmd()` from the [hash-to-curve
+/// specification](https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-13.html#section-5.4.1).
+/// A `dst` longer than 255 bytes is hashed as the specification requires.
+///
+/// # Errors
+/// [`ErrorKind::Input`] if the `dst` is empty, the `output` is empty, longer
+/// then [`u16::MAX`] or longer then 255 times the output size of `H`.
+pub fn expand_message_xmd<H>(input: &[&[u8]], dst: &[u8], output: &mut [u8]) -> Result<()>
+where
+    H: Digest + BlockSizeUser,
+    H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+{
+    if dst.is_empty() {
+        return Err(Error::new(ErrorKind::Input, Operation::ExpandMessage));
+    }
+
+    ExpandMsgXmd::<H>::expand_message(input, dst, output.len())
+        .map_err(|_| Error::new(ErrorKind::Input, Operation::ExpandMessage))?
+        .fill_bytes(output);
+
+    Ok(())
+}
+
+/// Fills `output` with uniformly random bytes derived from the concatenation
+/// of `input` with `expand_message_xof`.
+///
+/// Corresponds to `expand_message_xof()` from the [hash-to-curve
+/// specification](https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-13.html#section-5.4.2).
+/// A `dst` longer than 255 bytes is hashed as the specification requires.
+///
+/// # Errors
+/// [`ErrorKind::Input`] if the `dst` is empty, the `output` is empty or longer
+/// then [`u16::MAX`].
+pub fn expand_message_xof<H>(input: &[&[u8]], dst: &[u8], output: &mut [u8]) -> Result<()>
+where
+    H: Default + ExtendableOutput + Update,
+{
+    if dst.is_empty() {
+        return Err(Error::new(ErrorKind::Input, Operation::ExpandMessage));
+    }
+
+    ExpandMsgXof::<H>::expand_message(input, dst, output.len())
+        .map_err(|_| Error::new(ErrorKind::Input, Operation::ExpandMessage))?
+        .fill_bytes(output);
+
+    Ok(())
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use sha2::Sha256;
+
+    use super::*;
+
+    const DST: &[u8] = b"QUUX-V01-CS02-with-expander-SHA256-128";
+
+    // Test vectors from the hash-to-curve specification, Appendix K.1
+    #[test]
+    fn test_expand_message_xmd() -> Result<()> {
+        let mut output = [0; 0x20];
+        expand_message_xmd::<Sha256>(&[b""], DST, &mut output)?;
+        assert_eq!(
+            hex::encode(output),
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"
+        );
+
+        expand_message_xmd::<Sha256>(&[b"abc"], DST, &mut output)?;
+        assert_eq!(
+            hex::encode(output),
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"
+        );
+
+        // The input is concatenated
+        let mut split_output = [0; 0x20];
+        expand_message_xmd::<Sha256>(&[b"a", b"bc"], DST, &mut split_output)?;
+        assert_eq!(output, split_output);
+
+        let error = Error::new(ErrorKind::Input, Operation::ExpandMessage);
+        assert_eq!(
+            expand_message_xmd::<Sha256>(&[b"abc"], &[], &mut output),
+            Err(error)
+        );
+        assert_eq!(
+            expand_message_xmd::<Sha256>(&[b"abc"], DST, &mut []),
+            Err(error)
+        );
+        assert_eq!(
+            expand_message_xmd::<Sha256>(&[b"abc"], DST, &mut [0; 255 * 32 + 1]),
+            Err(error)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the helpers to encode an expiry into the POPRF info
+
+use core::num::NonZeroU64;
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_EXPIRY: [u8; 7] = *b"Expiry-";
+
+/// Length of the info produced by [`ExpiryBuckets`] in bytes.
+pub const EXPIRY_INFO_LEN: usize = 23;
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// Divides time into buckets of a fixed `window` and encodes them as the
+/// public `info` of a [`PoprfServer`](crate::PoprfServer) evaluation, so
+/// outputs expire with their bucket.
+///
+/// Timestamps are in a unit chosen by the application, e.g. seconds since the
+/// Unix epoch. The bucket, or epoch, of a timestamp `now` is `now / window`.
+/// Clients finalize with [`ExpiryBuckets::info`] of the current epoch, servers
+/// pass [`ExpiryBuckets::policy`] to
+/// [`PoprfServer::blind_evaluate_with_policy`](crate::PoprfServer::blind_evaluate_with_policy),
+/// which accepts every epoch overlapping the `skew` around its own clock:
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use core::num::NonZeroU64;
+///
+/// use rand::rngs::OsRng;
+/// use voprf::{ExpiryBuckets, PoprfClient, PoprfServer};
+///
+/// let mut rng = OsRng;
+/// // Hourly buckets, tolerating clocks that are a minute off
+/// let buckets = ExpiryBuckets::new(NonZeroU64::new(3600).unwrap(), 60);
+///
+/// // The client's clock is ahead and already in the next bucket
+/// let info = buckets.info(buckets.epoch(7201));
+/// let client_blind_result = PoprfClient::<CipherSuite>::blind(b"input", &mut rng).unwrap();
+///
+/// let server = PoprfServer::<CipherSuite>::new(&mut rng).unwrap();
+/// let server_result = server
+///     .blind_evaluate_with_policy(
+///         &mut rng,
+///         &client_blind_result.message,
+///         Some(&info),
+///         buckets.policy(7170),
+///     )
+///     .unwrap();
+///
+/// let output = client_blind_result.state.finalize(
+///     b"input",
+///     &server_result.message,
+///     &server_result.proof,
+///     server.get_public_key(),
+///     Some(&server_result.info),
+/// );
+/// assert!(output.is_ok());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ExpiryBuckets {
+    window: NonZeroU64,
+    skew: u64,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl ExpiryBuckets {
+    /// Creates buckets of `window` length, where servers accept epochs within
+    /// `skew` of their own clock.
+    pub fn new(window: NonZeroU64, skew: u64) -> Self {
+        Self { window, skew }
+    }
+
+    /// Returns the length of every bucket.
+    pub fn window(&self) -> NonZeroU64 {
+        self.window
+    }
+
+    /// Returns the tolerated clock skew.
+    pub fn skew(&self) -> u64 {
+        self.skew
+    }
+
+    /// Returns the epoch containing the timestamp `now`.
+    pub fn epoch(&self, now: u64) -> u64 {
+        now / self.window
+    }
+
+    /// Returns the first timestamp after `epoch`, when outputs evaluated with
+    /// its info expire. Saturates at [`u64::MAX`].
+    pub fn expires_at(&self, epoch: u64) -> u64 {
+        epoch.saturating_add(1).saturating_mul(self.window.get())
+    }
+
+    /// Encodes `epoch` into the info:
+    ///
+    /// ```text
+    /// "Expiry-" || I2OSP(window, 8) || I2OSP(epoch, 8)
+    /// ```
+    ///
+    /// The window is included so different bucket lengths never share an
+    /// info.
+    pub fn info(&self, epoch: u64) -> [u8; EXPIRY_INFO_LEN] {
+        let mut info = [0; EXPIRY_INFO_LEN];
+
+        for (byte, value) in info.iter_mut().zip(
+            STR_EXPIRY
+                .iter()
+                .chain(&self.window.get().to_be_bytes())
+                .chain(&epoch.to_be_bytes()),
+        ) {
+            *byte = *value;
+        }
+
+        info
+    }
+
+    /// Decodes the epoch from `info`. Returns [`None`] if it isn't an info of
+    /// these buckets.
+    pub fn parse(&self, info: &[u8]) -> Option<u64> {
+        if info.len() != EXPIRY_INFO_LEN {
+            return None;
+        }
+
+        let (prefix, rest) = info.split_at(STR_EXPIRY.len());
+        let (window, epoch) = rest.split_at(8);
+
+        if prefix != STR_EXPIRY || window != self.window.get().to_be_bytes() {
+            return None;
+        }
+
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(epoch);
+        Some(u64::from_be_bytes(bytes))
+    }
+
+    /// Returns `true` if `epoch` overlaps the `skew` around the timestamp
+    /// `now`, i.e. if it contains any timestamp from `now - skew` to
+    /// `now + skew`.
+    pub fn accepts(&self, epoch: u64, now: u64) -> bool {
+        let earliest = self.epoch(now.saturating_sub(self.skew));
+        let latest = self.epoch(now.saturating_add(self.skew));
+
+        earliest <= epoch && epoch <= latest
+    }
+
+    /// Returns a policy for
+    /// [`PoprfServer::blind_evaluate_with_policy`](crate::PoprfServer::blind_evaluate_with_policy)
+    /// that only accepts infos of epochs [accepted](Self::accepts) at the
+    /// timestamp `now`.
+    pub fn policy(&self, now: u64) -> impl FnOnce(&[u8]) -> Option<[u8; EXPIRY_INFO_LEN]> {
+        let buckets = *self;
+
+        move |info| {
+            buckets
+                .parse(info)
+                .filter(|&epoch| buckets.accepts(epoch, now))
+                .map(|epoch| buckets.info(epoch))
+        }
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        let buckets = ExpiryBuckets::new(NonZeroU64::new(100).unwrap(), 10);
+
+        assert_eq!(buckets.epoch(0), 0);
+        assert_eq!(buckets.epoch(99), 0);
+        assert_eq!(buckets.epoch(100), 1);
+        assert_eq!(buckets.expires_at(1), 200);
+        assert_eq!(buckets.expires_at(u64::MAX), u64::MAX);
+
+        // Infos round trip, but not across windows
+        let info = buckets.info(42);
+        assert_eq!(&info[..7], b"Expiry-");
+        assert_eq!(buckets.parse(&info), Some(42));
+        assert_eq!(buckets.parse(&info[1..]), None);
+        let other = ExpiryBuckets::new(NonZeroU64::new(50).unwrap(), 10);
+        assert_eq!(other.parse(&info), None);
+
+        // The skew tolerates neighbouring epochs close to the boundaries
+        assert!(buckets.accepts(1, 150));
+        assert!(!buckets.accepts(0, 150));
+        assert!(!buckets.accepts(2, 150));
+        assert!(buckets.accepts(0, 109));
+        assert!(!buckets.accepts(0, 110));
+        assert!(buckets.accepts(2, 190));
+        assert!(!buckets.accepts(2, 189));
+
+        // No overflow at the edges of time
+        assert!(buckets.accepts(0, 0));
+        assert!(buckets.accepts(buckets.epoch(u64::MAX), u64::MAX));
+
+        assert_eq!(buckets.policy(150)(&buckets.info(1)), Some(buckets.info(1)));
+        assert_eq!(buckets.policy(150)(&buckets.info(2)), None);
+        assert_eq!(buckets.policy(150)(b"garbage"), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the rate-limiting hook of servers
+
+use core::fmt::{self, Debug, Formatter};
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    BlindedElement, CipherSuite, Error, ErrorKind, EvaluationElement, Operation, OprfServer,
+    PoprfServer, PoprfServerEvaluateResult, Result, VoprfServer, VoprfServerEvaluateResult,
+};
+#[cfg(feature = "alloc")]
+use crate::{PoprfServerBatchEvaluateResult, VoprfServerBatchEvaluateResult};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// Decides whether a client may have its blinded elements evaluated, e.g. to
+/// enforce the rate limits of OPRF-based password hardening.
+///
+/// A [`GatedServer`] only holds a shared reference to its gatekeeper, so
+/// implementations that keep state, e.g. counters per client, need interior
+/// mutability.
+pub trait Gatekeeper {
+    /// Returns `true` if the client identified by `client_id` may have
+    /// `batch_size` blinded elements evaluated. The `client_id` is opaque to
+    /// this crate, e.g. an account name or an IP address.
+    fn admit(&self, client_id: &[u8], batch_size: usize) -> bool;
+}
+
+/// A server that consults a [`Gatekeeper`] before every evaluation of blinded
+/// elements.
+pub struct GatedServer<'a, S> {
+    server: S,
+    gatekeeper: &'a dyn Gatekeeper,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<'a, S> GatedServer<'a, S> {
+    /// Produces a new [`GatedServer`] that consults `gatekeeper` before every
+    /// evaluation of `server`.
+    pub fn new(server: S, gatekeeper: &'a dyn Gatekeeper) -> Self {
+        Self { server, gatekeeper }
+    }
+
+    /// Returns the wrapped server, e.g. to evaluate inputs directly.
+    pub fn server(&self) -> &S {
+        &self.server
+    }
+
+    /// Returns the wrapped server.
+    pub fn into_server(self) -> S {
+        self.server
+    }
+
+    /// Can only fail with [`ErrorKind::Gatekeeper`].
+    fn admit(&self, client_id: &[u8], batch_size: usize) -> Result<()> {
+        if self.gatekeeper.admit(client_id, batch_size) {
+            Ok(())
+        } else {
+            debug_event!("evaluation denied by gatekeeper");
+            Err(Error::new(ErrorKind::Gatekeeper, Operation::Evaluate))
+        }
+    }
+}
+
+impl<'a, CS: CipherSuite> GatedServer<'a, OprfServer<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`OprfServer::blind_evaluate`], if the [`Gatekeeper`] admits
+    /// `client_id`.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Gatekeeper`] if the [`Gatekeeper`] denied the evaluation.
+    pub fn blind_evaluate(
+        &self,
+        client_id: &[u8],
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<EvaluationElement<CS>> {
+        self.admit(client_id, 1)?;
+
+        Ok(self.server.blind_evaluate(blinded_element))
+    }
+}
+
+impl<'a, CS: CipherSuite> GatedServer<'a, VoprfServer<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`VoprfServer::try_blind_evaluate`], if the [`Gatekeeper`] admits
+    /// `client_id`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Gatekeeper`] if the [`Gatekeeper`] denied the evaluation.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        client_id: &[u8],
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>> {
+        self.admit(client_id, 1)?;
+
+        self.server.try_blind_evaluate(rng, blinded_element)
+    }
+
+    /// Like [`VoprfServer::batch_blind_evaluate`], if the [`Gatekeeper`] admits
+    /// `client_id` for the whole batch.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Gatekeeper`] if the [`Gatekeeper`] denied the evaluation.
+    /// - [`ErrorKind::Batch`] if the number of `blinded_elements` is longer
+    ///   then [`u16::MAX`]
+    #[cfg(feature = "alloc")]
+    pub fn batch_blind_evaluate<'b, R: RngCore + CryptoRng, I>(
+        &self,
+        rng: &mut R,
+        client_id: &[u8],
+        blinded_elements: &'b I,
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>>
+    where
+        CS: 'b,
+        &'b I: IntoIterator<Item = &'b BlindedElement<CS>>,
+        <&'b I as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        self.admit(client_id, blinded_elements.into_iter().len())?;
+
+        self.server.batch_blind_evaluate(rng, blinded_elements)
+    }
+}
+
+impl<'a, CS: CipherSuite> GatedServer<'a, PoprfServer<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`PoprfServer::blind_evaluate`], if the [`Gatekeeper`] admits
+    /// `client_id`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Gatekeeper`] if the [`Gatekeeper`] denied the evaluation.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        client_id: &[u8],
+        blinded_element: &BlindedElement<CS>,
+        info: Option<&[u8]>,
+    ) -> Result<PoprfServerEvaluateResult<CS>> {
+        self.admit(client_id, 1)?;
+
+        self.server.blind_evaluate(rng, blinded_element, info)
+    }
+
+    /// Like [`PoprfServer::batch_blind_evaluate`], if the [`Gatekeeper`] admits
+    /// `client_id` for the whole batch.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Gatekeeper`] if the [`Gatekeeper`] denied the evaluation.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    #[cfg(feature = "alloc")]
+    pub fn batch_blind_evaluate<'b, R: RngCore + CryptoRng, IE>(
+        &self,
+        rng: &mut R,
+        client_id: &[u8],
+        blinded_elements: &'b IE,
+        info: Option<&[u8]>,
+    ) -> Result<PoprfServerBatchEvaluateResult<CS>>
+    where
+        CS: 'b,
+        &'b IE: 'b + IntoIterator<Item = &'b BlindedElement<CS>>,
+        <&'b IE as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        self.admit(client_id, blinded_elements.into_iter().len())?;
+
+        self.server
+            .batch_blind_evaluate(rng, blinded_elements, info)
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<F: Fn(&[u8], usize) -> bool> Gatekeeper for F {
+    fn admit(&self, client_id: &[u8], batch_size: usize) -> bool {
+        self(client_id, batch_size)
+    }
+}
+
+impl<S: Debug> Debug for GatedServer<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GatedServer")
+            .field("server", &self.server)
+            .finish_non_exhaustive()
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{OprfClient, VoprfClient};
+
+    /// Admits up to a budget of evaluations for a single client.
+    struct Budget {
+        client_id: &'static [u8],
+        remaining: Cell<usize>,
+    }
+
+    impl Gatekeeper for Budget {
+        fn admit(&self, client_id: &[u8], batch_size: usize) -> bool {
+            if client_id != self.client_id || batch_size > self.remaining.get() {
+                return false;
+            }
+
+            self.remaining.set(self.remaining.get() - batch_size);
+            true
+        }
+    }
+
+    fn gated_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let error = Error::new(ErrorKind::Gatekeeper, Operation::Evaluate);
+
+        let budget = Budget {
+            client_id: b"alice",
+            remaining: Cell::new(2),
+        };
+        let server = GatedServer::new(OprfServer::<CS>::new(&mut rng).unwrap(), &budget);
+        let client_blind_result = OprfClient::<CS>::blind(input, &mut rng).unwrap();
+        assert!(matches!(
+            server.blind_evaluate(b"bob", &client_blind_result.message),
+            Err(e) if e == error
+        ));
+        for _ in 0..2 {
+            let message = server
+                .blind_evaluate(b"alice", &client_blind_result.message)
+                .unwrap();
+            let output = client_blind_result.state.finalize(input, &message).unwrap();
+            assert_eq!(output, server.server().evaluate(input).unwrap());
+        }
+        assert!(matches!(
+            server.blind_evaluate(b"alice", &client_blind_result.message),
+            Err(e) if e == error
+        ));
+
+        // Closures are gatekeepers too
+        let deny_all = |_: &[u8], _| false;
+        let server = GatedServer::new(VoprfServer::<CS>::new(&mut rng).unwrap(), &deny_all);
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        assert!(matches!(
+            server.blind_evaluate(&mut rng, b"alice", &client_blind_result.message),
+            Err(e) if e == error
+        ));
+    }
+
+    #[cfg(feature = "alloc")]
+    fn gated_batch_size<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        use ::alloc::vec::Vec;
+
+        let mut rng = OsRng;
+        let budget = Budget {
+            client_id: b"alice",
+            remaining: Cell::new(3),
+        };
+        let server = GatedServer::new(PoprfServer::<CS>::new(&mut rng).unwrap(), &budget);
+        let messages: Vec<_> = (0..2)
+            .map(|_| {
+                crate::PoprfClient::<CS>::blind(b"input", &mut rng)
+                    .unwrap()
+                    .message
+            })
+            .collect();
+
+        assert!(server
+            .batch_blind_evaluate(&mut rng, b"alice", &messages, None)
+            .is_ok());
+        // Only one evaluation remains in the budget
+        assert!(matches!(
+            server.batch_blind_evaluate(&mut rng, b"alice", &messages, None),
+            Err(e) if e.kind() == ErrorKind::Gatekeeper
+        ));
+        assert!(server
+            .blind_evaluate(&mut rng, b"alice", &messages[0], None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            gated_retrieval::<Ristretto255>();
+            #[cfg(feature = "alloc")]
+            gated_batch_size::<Ristretto255>();
+        }
+
+        gated_retrieval::<NistP256>();
+        #[cfg(feature = "alloc")]
+        gated_batch_size::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,730 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+#[cfg(feature = "pythia")]
+use core::fmt::{self, Write};
+use core::ops::{Add, Mul, Sub};
+
+use bls12_381_::hash_to_curve::{HashToField, MapToCurve};
+use bls12_381_::{G1Affine, G1Projective, Scalar};
+#[cfg(feature = "pythia")]
+use bls12_381_::{G2Affine, G2Projective};
+use digest::core_api::BlockSizeUser;
+use digest::Digest;
+use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, Expander};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256, U32, U48, U64};
+#[cfg(feature = "pythia")]
+use generic_array::typenum::{U128, U576};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+use zeroize::DefaultIsZeroes;
+
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
+
+/// The byte length of a scalar hashed by `hash_to_field`, `L` in the
+/// hash-to-curve specification.
+const L: usize = 48;
+
+/// The base field, which [bls12_381](bls12_381_) only exposes through the
+/// hash-to-curve traits.
+type Fp = <G1Projective as MapToCurve>::Field;
+/// The quadratic extension `Fp2 = Fp[u] / (u^2 + 1)` of the base field.
+#[cfg(feature = "pythia")]
+type Fp2 = <G2Projective as MapToCurve>::Field;
+
+/// [`Group`] implementation for the G1 group of the BLS12-381 pairing-friendly
+/// curve.
+///
+/// Elements are encoded as compressed points, as in the [bls12_381](bls12_381_)
+/// crate and Zcash, and scalars in little-endian. Hashing to the curve follows
+/// the `BLS12381G1_XMD:SHA-256_SSWU_RO_` suite of RFC 9380, and
+/// [`Group::hash_to_scalar`] reduces 48 bytes, as for P-256. As a
+/// [`CipherSuite`](crate::CipherSuite) it uses SHA-256, under an ID that isn't
+/// assigned by the specification.
+///
+/// The pairing itself isn't needed by the protocol, but the `pythia` feature
+/// builds the partially oblivious PRF of [`PythiaServer`](crate::PythiaServer)
+/// on it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "bls12_381")]
+pub struct Bls12_381;
+
+/// An element of the G1 group of [`Bls12_381`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bls12_381Elem(G1Projective);
+
+/// A scalar of [`Bls12_381`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bls12_381Scalar(Scalar);
+
+/// Reduces big-endian bytes, as produced by `expand_message`, modulo the order
+/// of the group.
+fn scalar_from_uniform_bytes(bytes: &[u8]) -> Scalar {
+    let mut wide = [0; 64];
+    wide.iter_mut()
+        .zip(bytes.iter().rev())
+        .for_each(|(wide, byte)| *wide = *byte);
+
+    Scalar::from_bytes_wide(&wide)
+}
+
+/// Decodes a point and checks that it is in the prime-order subgroup.
+fn decode_elem(element_bits: &[u8]) -> Option<G1Projective> {
+    element_bits
+        .try_into()
+        .ok()
+        .and_then(|bytes| Option::<G1Affine>::from(G1Affine::from_compressed(bytes)))
+        .map(G1Projective::from)
+}
+
+impl From<Bls12_381Elem> for G1Projective {
+    fn from(elem: Bls12_381Elem) -> Self {
+        elem.0
+    }
+}
+
+impl From<Bls12_381Elem> for G1Affine {
+    fn from(elem: Bls12_381Elem) -> Self {
+        elem.0.into()
+    }
+}
+
+impl From<Bls12_381Scalar> for Scalar {
+    fn from(scalar: Bls12_381Scalar) -> Self {
+        scalar.0
+    }
+}
+
+impl crate::CipherSuite for Bls12_381 {
+    // Not assigned by the specification.
+    const ID: u16 = 0xFFF9;
+
+    type Group = Bls12_381;
+
+    type Hash = sha2::Sha256;
+
+    type FinalizeHash = sha2::Sha256;
+}
+
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "bls12_381")]
+impl Group for Bls12_381 {
+    type Elem = Bls12_381Elem;
+
+    type ElemLen = U48;
+
+    type Scalar = Bls12_381Scalar;
+
+    type ScalarLen = U32;
+
+    // Implements `hash_to_curve()` from
+    // https://www.rfc-editor.org/rfc/rfc9380.html#section-3 with the
+    // simplified SWU map of bls12_381
+    fn hash_to_curve<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Elem, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut u0 = GenericArray::<u8, U64>::default();
+        let mut u1 = GenericArray::<u8, U64>::default();
+        let mut expander = ExpandMsgXmd::<H>::expand_message(input, dst, u0.len() + u1.len())
+            .map_err(|_| InternalError::Input)?;
+        expander.fill_bytes(&mut u0);
+        expander.fill_bytes(&mut u1);
+
+        let point = G1Projective::map_to_curve(&Fp::from_okm(&u0))
+            + G1Projective::map_to_curve(&Fp::from_okm(&u1));
+
+        Ok(Bls12_381Elem(point.clear_h()))
+    }
+
+    // Implements the `HashToScalar()` function from
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html#section-4.1
+    fn hash_to_scalar<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Scalar, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = [0; L];
+        ExpandMsgXmd::<H>::expand_message(input, dst, L)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+
+        Ok(Bls12_381Scalar(scalar_from_uniform_bytes(&uniform_bytes)))
+    }
+
+    fn base_elem() -> Self::Elem {
+        Bls12_381Elem(G1Projective::generator())
+    }
+
+    fn identity_elem() -> Self::Elem {
+        Bls12_381Elem(G1Projective::identity())
+    }
+
+    fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
+        G1Affine::from(elem.0).to_compressed().into()
+    }
+
+    fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
+        decode_elem(element_bits)
+            .filter(|point| ALLOW_IDENTITY || !bool::from(point.is_identity()))
+            .map(Bls12_381Elem)
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ))
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+        loop {
+            let mut bytes = [0; 64];
+            rng.fill_bytes(&mut bytes);
+            let scalar = Scalar::from_bytes_wide(&bytes);
+
+            if scalar != Scalar::zero() {
+                break Bls12_381Scalar(scalar);
+            }
+        }
+    }
+
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+        scalar.0.invert().map(Bls12_381Scalar)
+    }
+
+    fn is_zero_scalar(scalar: Self::Scalar) -> Choice {
+        scalar.0.ct_eq(&Scalar::zero())
+    }
+
+    #[cfg(test)]
+    fn zero_scalar() -> Self::Scalar {
+        Bls12_381Scalar(Scalar::zero())
+    }
+
+    fn serialize_scalar(scalar: Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        scalar.0.to_bytes().into()
+    }
+
+    fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar> {
+        scalar_bits
+            .try_into()
+            .ok()
+            .and_then(|bytes| Option::<Scalar>::from(Scalar::from_bytes(bytes)))
+            .filter(|scalar| scalar != &Scalar::zero())
+            .map(Bls12_381Scalar)
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeScalar,
+            ))
+    }
+}
+
+/// Implements `hash_to_curve()` from
+/// <https://www.rfc-editor.org/rfc/rfc9380.html#section-3> for the G2 group,
+/// like [`Group::hash_to_curve`] does for G1.
+#[cfg(feature = "pythia")]
+pub(crate) fn hash_to_g2<H>(input: &[&[u8]], dst: &[u8]) -> Result<G2Projective, InternalError>
+where
+    H: Digest + BlockSizeUser,
+    H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+{
+    let mut u0 = GenericArray::<u8, U128>::default();
+    let mut u1 = GenericArray::<u8, U128>::default();
+    let mut expander = ExpandMsgXmd::<H>::expand_message(input, dst, u0.len() + u1.len())
+        .map_err(|_| InternalError::Input)?;
+    expander.fill_bytes(&mut u0);
+    expander.fill_bytes(&mut u1);
+
+    let point = G2Projective::map_to_curve(&Fp2::from_okm(&u0))
+        + G2Projective::map_to_curve(&Fp2::from_okm(&u1));
+
+    Ok(point.clear_h())
+}
+
+/// Computes the pairing of a G1 and a G2 element.
+///
+/// Returns [`None`] if the result can't be read from bls12_381, which only
+/// happens if the crate changes how it prints [`bls12_381_::Gt`].
+#[cfg(feature = "pythia")]
+pub(crate) fn pairing(p: &G1Projective, q: &G2Projective) -> Option<GtElem> {
+    let mut writer = CoefficientWriter {
+        bytes: [0; 576],
+        nibbles: 0,
+        remaining: 0,
+        after_zero: false,
+    };
+    // bls12_381 has no encoding for its target group, but its `Debug` output
+    // prints each coefficient with the canonical encoding of the base field
+    write!(
+        writer,
+        "{:?}",
+        bls12_381_::pairing(&G1Affine::from(p), &G2Affine::from(q))
+    )
+    .ok()?;
+
+    if writer.nibbles == 2 * writer.bytes.len() {
+        GtElem::from_coefficients(&writer.bytes).into()
+    } else {
+        None
+    }
+}
+
+/// Collects the hexadecimal coefficients printed by `Debug` for an element of
+/// [`bls12_381_::Gt`].
+#[cfg(feature = "pythia")]
+struct CoefficientWriter {
+    bytes: [u8; 576],
+    nibbles: usize,
+    /// The number of hexadecimal digits left in the current coefficient
+    remaining: usize,
+    after_zero: bool,
+}
+
+#[cfg(feature = "pythia")]
+impl Write for CoefficientWriter {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        for character in string.chars() {
+            if self.remaining > 0 {
+                let nibble = character
+                    .to_digit(16)
+                    .and_then(|digit| u8::try_from(digit).ok())
+                    .ok_or(fmt::Error)?;
+                let byte = self.bytes.get_mut(self.nibbles / 2).ok_or(fmt::Error)?;
+                *byte = (*byte << 4) | nibble;
+                self.nibbles += 1;
+                self.remaining -= 1;
+            } else {
+                // Every coefficient is prefixed with "0x"
+                if self.after_zero && character == 'x' {
+                    self.remaining = 96;
+                }
+
+                self.after_zero = character == '0';
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An element of `Fp6 = Fp2[v] / (v^3 - (u + 1))`.
+#[cfg(feature = "pythia")]
+#[derive(Clone, Copy, Debug)]
+struct Fp6 {
+    c0: Fp2,
+    c1: Fp2,
+    c2: Fp2,
+}
+
+#[cfg(feature = "pythia")]
+impl Fp6 {
+    const fn zero() -> Self {
+        Fp6 {
+            c0: Fp2::zero(),
+            c1: Fp2::zero(),
+            c2: Fp2::zero(),
+        }
+    }
+
+    const fn one() -> Self {
+        Fp6 {
+            c0: Fp2::one(),
+            c1: Fp2::zero(),
+            c2: Fp2::zero(),
+        }
+    }
+
+    fn add(&self, rhs: &Self) -> Self {
+        Fp6 {
+            c0: self.c0 + rhs.c0,
+            c1: self.c1 + rhs.c1,
+            c2: self.c2 + rhs.c2,
+        }
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        Fp6 {
+            c0: self.c0 * rhs.c0 + (self.c1 * rhs.c2 + self.c2 * rhs.c1).mul_by_nonresidue(),
+            c1: self.c0 * rhs.c1 + self.c1 * rhs.c0 + (self.c2 * rhs.c2).mul_by_nonresidue(),
+            c2: self.c0 * rhs.c2 + self.c1 * rhs.c1 + self.c2 * rhs.c0,
+        }
+    }
+
+    /// Multiplies by `v`.
+    fn mul_by_nonresidue(&self) -> Self {
+        Fp6 {
+            c0: self.c2.mul_by_nonresidue(),
+            c1: self.c0,
+            c2: self.c1,
+        }
+    }
+
+    fn coefficients(&self) -> [Fp; 6] {
+        [
+            self.c0.c0, self.c0.c1, self.c1.c0, self.c1.c1, self.c2.c0, self.c2.c1,
+        ]
+    }
+}
+
+/// An element of the target group of the BLS12-381 pairing, in
+/// `Fp12 = Fp6[w] / (w^2 - v)`.
+///
+/// [bls12_381](bls12_381_) can't decode these, it is implemented here so
+/// clients can receive them. Elements are encoded as the big-endian encodings
+/// of their 12 base field coefficients, in the order bls12_381 prints them.
+#[cfg(feature = "pythia")]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GtElem {
+    c0: Fp6,
+    c1: Fp6,
+}
+
+#[cfg(feature = "pythia")]
+impl GtElem {
+    pub(crate) const fn identity() -> Self {
+        GtElem {
+            c0: Fp6::one(),
+            c1: Fp6::zero(),
+        }
+    }
+
+    pub(crate) fn mul(&self, rhs: &Self) -> Self {
+        GtElem {
+            c0: self
+                .c0
+                .mul(&rhs.c0)
+                .add(&self.c1.mul(&rhs.c1).mul_by_nonresidue()),
+            c1: self.c0.mul(&rhs.c1).add(&self.c1.mul(&rhs.c0)),
+        }
+    }
+
+    /// Raises the element to the power of `exponent` in constant-time, the
+    /// target group is noted multiplicatively.
+    pub(crate) fn pow(&self, exponent: &Scalar) -> Self {
+        let mut result = Self::identity();
+
+        for byte in exponent.to_bytes().iter().rev() {
+            for bit in (0..8).rev() {
+                result = result.mul(&result);
+                let product = result.mul(self);
+                result =
+                    Self::conditional_select(&result, &product, Choice::from((byte >> bit) & 1));
+            }
+        }
+
+        result
+    }
+
+    pub(crate) fn to_bytes(self) -> GenericArray<u8, U576> {
+        let mut bytes = GenericArray::default();
+
+        for (chunk, coefficient) in bytes.chunks_exact_mut(48).zip(
+            self.c0
+                .coefficients()
+                .iter()
+                .chain(self.c1.coefficients().iter()),
+        ) {
+            chunk.copy_from_slice(&coefficient.to_bytes());
+        }
+
+        bytes
+    }
+
+    /// Decodes the coefficients, without checking if the element is in the
+    /// target group.
+    fn from_coefficients(bytes: &[u8]) -> CtOption<Self> {
+        let mut coefficients = [Fp::zero(); 12];
+        let mut is_some = Choice::from(u8::from(bytes.len() == 576));
+
+        for (coefficient, chunk) in coefficients.iter_mut().zip(bytes.chunks_exact(48)) {
+            let mut encoding = [0; 48];
+            encoding.copy_from_slice(chunk);
+            let decoded = Fp::from_bytes(&encoding);
+            is_some &= decoded.is_some();
+            *coefficient = decoded.unwrap_or(Fp::zero());
+        }
+
+        let [a, b, c, d, e, f, g, h, i, j, k, l] = coefficients;
+        let element = GtElem {
+            c0: Fp6 {
+                c0: Fp2 { c0: a, c1: b },
+                c1: Fp2 { c0: c, c1: d },
+                c2: Fp2 { c0: e, c1: f },
+            },
+            c1: Fp6 {
+                c0: Fp2 { c0: g, c1: h },
+                c1: Fp2 { c0: i, c1: j },
+                c2: Fp2 { c0: k, c1: l },
+            },
+        };
+
+        CtOption::new(element, is_some)
+    }
+
+    /// Decodes an element and checks that it is in the target group, which is
+    /// the only subgroup of order `q` of the multiplicative group of `Fp12`.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Option::<Self>::from(Self::from_coefficients(bytes)).filter(|element| {
+            // `element^q = element^(q - 1) * element`
+            bool::from(
+                element
+                    .pow(&-Scalar::one())
+                    .mul(element)
+                    .ct_eq(&Self::identity()),
+            )
+        })
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl Add<&Bls12_381Elem> for Bls12_381Elem {
+    type Output = Bls12_381Elem;
+
+    fn add(self, rhs: &Bls12_381Elem) -> Bls12_381Elem {
+        Bls12_381Elem(self.0 + rhs.0)
+    }
+}
+
+impl Mul<&Bls12_381Scalar> for Bls12_381Elem {
+    type Output = Bls12_381Elem;
+
+    fn mul(self, rhs: &Bls12_381Scalar) -> Bls12_381Elem {
+        Bls12_381Elem(self.0 * rhs.0)
+    }
+}
+
+impl ConditionallySelectable for Bls12_381Elem {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Bls12_381Elem(G1Projective::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for Bls12_381Elem {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+// The default element is the identity.
+impl DefaultIsZeroes for Bls12_381Elem {}
+
+impl Add<&Bls12_381Scalar> for Bls12_381Scalar {
+    type Output = Bls12_381Scalar;
+
+    fn add(self, rhs: &Bls12_381Scalar) -> Bls12_381Scalar {
+        Bls12_381Scalar(self.0 + rhs.0)
+    }
+}
+
+impl Sub<&Bls12_381Scalar> for Bls12_381Scalar {
+    type Output = Bls12_381Scalar;
+
+    fn sub(self, rhs: &Bls12_381Scalar) -> Bls12_381Scalar {
+        Bls12_381Scalar(self.0 - rhs.0)
+    }
+}
+
+impl Mul<&Bls12_381Scalar> for Bls12_381Scalar {
+    type Output = Bls12_381Scalar;
+
+    fn mul(self, rhs: &Bls12_381Scalar) -> Bls12_381Scalar {
+        Bls12_381Scalar(self.0 * rhs.0)
+    }
+}
+
+impl ConditionallySelectable for Bls12_381Scalar {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Bls12_381Scalar(Scalar::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for Bls12_381Scalar {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+// The default scalar is zero.
+impl DefaultIsZeroes for Bls12_381Scalar {}
+
+#[cfg(feature = "pythia")]
+impl ConditionallySelectable for Fp6 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Fp6 {
+            c0: Fp2::conditional_select(&a.c0, &b.c0, choice),
+            c1: Fp2::conditional_select(&a.c1, &b.c1, choice),
+            c2: Fp2::conditional_select(&a.c2, &b.c2, choice),
+        }
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl ConstantTimeEq for Fp6 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.c0.ct_eq(&other.c0) & self.c1.ct_eq(&other.c1) & self.c2.ct_eq(&other.c2)
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl ConditionallySelectable for GtElem {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        GtElem {
+            c0: Fp6::conditional_select(&a.c0, &b.c0, choice),
+            c1: Fp6::conditional_select(&a.c1, &b.c1, choice),
+        }
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl ConstantTimeEq for GtElem {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.c0.ct_eq(&other.c0) & self.c1.ct_eq(&other.c1)
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl Default for GtElem {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+// The default element is the identity.
+#[cfg(feature = "pythia")]
+impl DefaultIsZeroes for GtElem {}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From https://www.rfc-editor.org/rfc/rfc9380.html#appendix-J.9.1, the
+    // uncompressed encoding of `P`
+    const DST: &[u8] = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+    const VECTORS: [(&[u8], &str); 2] = [
+        (
+            b"",
+            "052926add2207b76ca4fa57a8734416c8dc95e24501772c814278700eed6d1e4e8cf62d9c09db0fac349612b759e79a108ba738453bfed09cb546dbb0783dbb3a5f1f566ed67bb6be0e8c67e2e81a4cc68ee29813bb7994998f3eae0c9c6a265",
+        ),
+        (
+            b"abc",
+            "03567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f69030b9c15f3fe6e5cf4211f346271d7b01c8f3b28be689c8429c85b67af215533311f0b8dfaaa154fa6b88176c229f2885d",
+        ),
+    ];
+    // From https://www.rfc-editor.org/rfc/rfc9380.html#appendix-J.10.1
+    #[cfg(feature = "pythia")]
+    const G2_DST: &[u8] = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+    #[cfg(feature = "pythia")]
+    const G2_VECTORS: [(&[u8], &str); 2] = [
+        (
+            b"",
+            "05cb8437535e20ecffaef7752baddf98034139c38452458baeefab379ba13dff5bf5dd71b72418717047f5b0f37da03d0141ebfbdca40eb85b87142e130ab689c673cf60f1a3e98d69335266f30d9b8d4ac44c1038e9dcdd5393faf5c41fb78a12424ac32561493f3fe3c260708a12b7c620e7be00099a974e259ddc7d1f6395c3c811cdd19f1e8dbf3e9ecfdcbab8d60503921d7f6a12805e72940b963c0cf3471c7b2a524950ca195d11062ee75ec076daf2d4bc358c4b190c0c98064fdd92",
+        ),
+        (
+            b"abc",
+            "139cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd802c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e600aa65dae3c8d732d10ecd2c50f8a1baf3001578f71c694e03866e9f3d49ac1e1ce70dd94a733534f106d4cec0eddd161787327b68159716a37440985269cf584bcb1e621d3a7202be6ea05c4cfe244aeb197642555a0645fb87bf7466b2ba48",
+        ),
+    ];
+
+    #[test]
+    fn test_hash_to_curve() -> Result<()> {
+        for (input, expected) in VECTORS {
+            let elem = Bls12_381::hash_to_curve::<sha2::Sha256>(&[input], DST).unwrap();
+            assert_eq!(
+                hex::encode(G1Affine::from(elem).to_uncompressed()),
+                expected
+            );
+        }
+
+        #[cfg(feature = "pythia")]
+        for (input, expected) in G2_VECTORS {
+            let point = hash_to_g2::<sha2::Sha256>(&[input], G2_DST).unwrap();
+            assert_eq!(
+                hex::encode(G2Affine::from(point).to_uncompressed()),
+                expected
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize() -> Result<()> {
+        let bytes = Bls12_381::serialize_elem(Bls12_381::base_elem());
+        assert!(bool::from(
+            Bls12_381::deserialize_elem(&bytes)?.ct_eq(&Bls12_381::base_elem())
+        ));
+
+        // Points on the curve, but outside of the prime-order subgroup, are
+        // rejected
+        let mut index = 0_u8;
+        let outside = loop {
+            let mut bytes = [0; 48];
+            bytes[0] = 0x80;
+            bytes[47] = index;
+            index += 1;
+
+            if Option::<G1Affine>::from(G1Affine::from_compressed_unchecked(&bytes)).is_some() {
+                break bytes;
+            }
+        };
+        assert!(Bls12_381::deserialize_elem(&outside).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "pythia")]
+    #[test]
+    fn test_pairing() -> Result<()> {
+        let g1 = G1Projective::generator();
+        let g2 = G2Projective::generator();
+        let a = Scalar::from(1234);
+        let b = Scalar::from(5678);
+
+        // The exponentiation and multiplication here agree with bilinearity of
+        // the pairing computed by bls12_381
+        let base = pairing(&g1, &g2).unwrap();
+        let ab = a * b;
+        assert!(bool::from(
+            pairing(&(g1 * a), &(g2 * b)).unwrap().ct_eq(&base.pow(&ab))
+        ));
+        assert!(bool::from(
+            pairing(&(g1 * (a + b)), &g2)
+                .unwrap()
+                .ct_eq(&base.pow(&a).mul(&base.pow(&b)))
+        ));
+        assert!(bool::from(
+            pairing(&G1Projective::identity(), &g2)
+                .unwrap()
+                .ct_eq(&GtElem::identity())
+        ));
+
+        // Elements survive encoding, others than the ones of the target group
+        // are rejected
+        let bytes = base.pow(&a).to_bytes();
+        assert!(bool::from(
+            GtElem::from_bytes(&bytes).unwrap().ct_eq(&base.pow(&a))
+        ));
+        let mut other = bytes;
+        other[575] ^= 1;
+        assert!(GtElem::from_bytes(&other).is_none());
+        assert!(GtElem::from_bytes(&[0xff; 576]).is_none());
+        assert!(GtElem::from_bytes(&bytes[..575]).is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,935 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the encodings of elements that are indistinguishable from random
+//! bytes, with Elligator Squared
+
+#[cfg(feature = "ristretto255")]
+use core::ops::{Add, Mul, Neg, Sub};
+
+#[cfg(feature = "ristretto255")]
+use curve25519_dalek::ristretto::RistrettoPoint;
+use elliptic_curve::bigint::{ArrayEncoding, Limb, U384};
+use elliptic_curve::hash2curve::{FromOkm, MapToCurve, OsswuMap};
+use elliptic_curve::sec1::ToEncodedPoint;
+#[cfg(all(feature = "ristretto255", not(target_pointer_width = "64")))]
+use fiat_crypto::curve25519_32 as fiat;
+#[cfg(all(feature = "ristretto255", target_pointer_width = "64"))]
+use fiat_crypto::curve25519_64 as fiat;
+use generic_array::sequence::{Concat, Split};
+#[cfg(feature = "ristretto255")]
+use generic_array::typenum::U64;
+use generic_array::typenum::{U16, U48, U96};
+use generic_array::{ArrayLength, GenericArray};
+use p256::{FieldElement as P256FieldElement, NistP256, ProjectivePoint};
+use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use super::Group;
+#[cfg(feature = "ristretto255")]
+use super::Ristretto255;
+
+////////////
+// Traits //
+// ====== //
+////////////
+
+/// A [`Group`] whose elements can be encoded as byte strings that are
+/// indistinguishable from uniformly random ones, with Elligator Squared, see
+/// <https://eprint.iacr.org/2014/043>.
+///
+/// An element `P` is encoded as two field elements `u1` and `u2` with `P =
+/// f(u1) + f(u2)`, where `f` is the map to the curve of `hash_to_curve`, so
+/// decoding is `hash_to_curve` without expanding the input. Encoding picks
+/// `u2` at random and `u1` uniformly among the preimages of `P - f(u2)` under
+/// `f`, and tries again if there is none. The field arithmetic is the one of
+/// the `p256` crate for P-256, and the formally verified one of `fiat-crypto`
+/// for Ristretto255, like the `fiat` backends of `curve25519-dalek`.
+///
+/// Plain Elligator 2 encodes a point as a single field element, but it only
+/// reaches about half of the points, so a blinded element would have to be
+/// re-blinded until it is one of them. It also doesn't apply to prime-order
+/// curves like P-256. Elligator Squared covers every element of both groups,
+/// at the cost of encodings twice as long.
+pub trait UniformGroup: Group {
+    /// The byte length of uniform encodings
+    type UniformLen: ArrayLength<u8>;
+
+    /// Encodes `elem` as bytes that are indistinguishable from uniformly
+    /// random ones. Every element has many encodings, `rng` picks one of them.
+    ///
+    /// The number of tries, and so the time it takes, is random. It only
+    /// depends on `elem` through the number of preimages of `P - f(u2)`.
+    fn encode_uniform<R: RngCore + CryptoRng>(
+        elem: Self::Elem,
+        rng: &mut R,
+    ) -> GenericArray<u8, Self::UniformLen>;
+
+    /// Decodes the output of [`UniformGroup::encode_uniform`]. Every byte
+    /// string decodes to an element, which might be the identity.
+    fn decode_uniform(bytes: &GenericArray<u8, Self::UniformLen>) -> Self::Elem;
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+// Field elements are encoded as 48 bytes that `FromOkm` reduces modulo the
+// order of the field, like `hash_to_field` of the hash-to-curve specification
+// does, so the encoding is statistically close to uniform.
+impl UniformGroup for NistP256 {
+    type UniformLen = U96;
+
+    fn encode_uniform<R: RngCore + CryptoRng>(
+        elem: Self::Elem,
+        rng: &mut R,
+    ) -> GenericArray<u8, Self::UniformLen> {
+        loop {
+            let mut u2 = GenericArray::default();
+            rng.fill_bytes(&mut u2);
+
+            let (is_preimage, u1) = pick(
+                p256_preimages(elem - P256FieldElement::from_okm(&u2).map_to_curve()),
+                rng,
+            );
+
+            if bool::from(is_preimage) {
+                if let Some(u1) = p256_lift(u1, rng) {
+                    return u1.concat(u2);
+                }
+            }
+        }
+    }
+
+    fn decode_uniform(bytes: &GenericArray<u8, Self::UniformLen>) -> Self::Elem {
+        let (u1, u2): (GenericArray<u8, U48>, GenericArray<u8, U48>) = (*bytes).split();
+
+        P256FieldElement::from_okm(&u1).map_to_curve()
+            + P256FieldElement::from_okm(&u2).map_to_curve()
+    }
+}
+
+// Field elements are encoded as 32 bytes, the most significant bit is random
+// and ignored by `RistrettoPoint::from_uniform_bytes`.
+#[cfg(feature = "ristretto255")]
+impl UniformGroup for Ristretto255 {
+    type UniformLen = U64;
+
+    fn encode_uniform<R: RngCore + CryptoRng>(
+        elem: Self::Elem,
+        rng: &mut R,
+    ) -> GenericArray<u8, Self::UniformLen> {
+        loop {
+            let mut u2 = [0; 32];
+            rng.fill_bytes(&mut u2);
+
+            let (is_preimage, u1) = pick(ristretto255_preimages(elem - elligator(&u2)), rng);
+
+            if bool::from(is_preimage) {
+                // `u1` and `-u1` map to the same point, the sign and the unused
+                // bit are picked at random
+                let mut random = [0; 1];
+                rng.fill_bytes(&mut random);
+                let [random] = random;
+                let u1 = FieldElement::conditional_select(&u1, &-u1, Choice::from(random & 1));
+
+                let mut u1 = u1.to_bytes();
+                if let Some(last) = u1.last_mut() {
+                    *last |= random & 0x80;
+                }
+
+                return GenericArray::from(u1).concat(u2.into());
+            }
+        }
+    }
+
+    fn decode_uniform(bytes: &GenericArray<u8, Self::UniformLen>) -> Self::Elem {
+        RistrettoPoint::from_uniform_bytes(&(*bytes).into())
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+/// Picks one of the `candidates` uniformly at random in constant time, with
+/// whether it is a preimage. `N` has to be a power of two.
+fn pick<F, R, const N: usize>(candidates: [(Choice, F); N], rng: &mut R) -> (Choice, F)
+where
+    F: ConditionallySelectable + Default,
+    R: RngCore,
+{
+    let index = rng.next_u32() as usize % N;
+    let mut picked = (Choice::from(0), F::default());
+
+    for (candidate_index, (is_preimage, candidate)) in candidates.iter().enumerate() {
+        let is_picked = candidate_index.ct_eq(&index);
+        picked.0 |= is_picked & *is_preimage;
+        picked.1.conditional_assign(candidate, is_picked);
+    }
+
+    picked
+}
+
+/// Computes the preimages of `point` under the simplified SWU map of
+/// `hash_to_curve`, up to four. Of `u` and `-u`, only the one with the sign of
+/// `y` can be a preimage. Solving `x1(u) = x` and `x2(u) = x` for `w = Z * u^2`
+/// with `k = -A * x / B` results in the two quadratic equations
+///
+/// ```text
+/// w^2 + w - 1 / (k - 1) = 0
+/// w^2 + (1 - k) * w + 1 - k = 0
+/// ```
+///
+/// Each root is only a preimage if `x` comes from the branch of the map it was
+/// solved for, which is checked by mapping it again.
+fn p256_preimages(point: ProjectivePoint) -> [(Choice, P256FieldElement); 4] {
+    let mut preimages = [(Choice::from(0), P256FieldElement::ZERO); 4];
+    let point_encoding = point.to_affine().to_encoded_point(false);
+
+    // The map never results in the identity
+    let (x, y) = match (point_encoding.x(), point_encoding.y()) {
+        (Some(x), Some(y)) => (
+            P256FieldElement::from_bytes(x).unwrap_or(P256FieldElement::ZERO),
+            P256FieldElement::from_bytes(y).unwrap_or(P256FieldElement::ZERO),
+        ),
+        _ => return preimages,
+    };
+
+    let params = P256FieldElement::PARAMS;
+    let one = P256FieldElement::ONE;
+    let half = one.double().invert().unwrap_or(P256FieldElement::ZERO);
+    let z_inverse = params.z.invert().unwrap_or(P256FieldElement::ZERO);
+
+    let k = -params.map_a * x * params.map_b.invert().unwrap_or(P256FieldElement::ZERO);
+    let k_minus_one = k - one;
+    let k_plus_three = k + one.double() + one;
+
+    let x1_root = (k_plus_three * k_minus_one.invert().unwrap_or(P256FieldElement::ZERO)).sqrt();
+    let x1_root_exists = x1_root.is_some();
+    let x1_root = x1_root.unwrap_or(P256FieldElement::ZERO);
+    let x2_root = (k_minus_one * k_plus_three).sqrt();
+    let x2_root_exists = x2_root.is_some();
+    let x2_root = x2_root.unwrap_or(P256FieldElement::ZERO);
+
+    let roots = [
+        (x1_root_exists, (x1_root - one) * half),
+        (x1_root_exists, (-x1_root - one) * half),
+        (x2_root_exists, (k_minus_one + x2_root) * half),
+        (x2_root_exists, (k_minus_one - x2_root) * half),
+    ];
+
+    for ((is_preimage, preimage), (root_exists, w)) in preimages.iter_mut().zip(roots) {
+        let u = (w * z_inverse).sqrt();
+        let u_exists = u.is_some();
+        let u = u.unwrap_or(P256FieldElement::ZERO);
+        let u = P256FieldElement::conditional_select(&u, &-u, u.is_odd() ^ y.is_odd());
+
+        *is_preimage = root_exists & u_exists & u.map_to_curve().ct_eq(&point);
+        *preimage = u;
+    }
+
+    preimages
+}
+
+/// Returns random bytes that `FieldElement::from_okm` reduces to `u`: a random
+/// integer of 48 bytes with its residue replaced by `u`. Returns `None` if that
+/// overflows, which only happens with negligible probability.
+fn p256_lift<R: RngCore>(u: P256FieldElement, rng: &mut R) -> Option<GenericArray<u8, U48>> {
+    fn widen(element: P256FieldElement) -> U384 {
+        U384::from_be_byte_array(GenericArray::<u8, U16>::default().concat(element.to_bytes()))
+    }
+
+    let mut bytes = GenericArray::default();
+    rng.fill_bytes(&mut bytes);
+
+    let residue = widen(P256FieldElement::from_okm(&bytes));
+    let (lifted, carry) = U384::from_be_byte_array(bytes)
+        .wrapping_sub(&residue)
+        .adc(&widen(u), Limb::ZERO);
+
+    bool::from(carry.ct_eq(&Limb::ZERO)).then(|| lifted.to_be_byte_array())
+}
+
+/// Maps `u` to Ristretto255 with the Elligator map of `from_uniform_bytes`,
+/// which maps zero to the identity.
+#[cfg(feature = "ristretto255")]
+fn elligator(u: &[u8; 32]) -> RistrettoPoint {
+    let mut bytes = [0; 64];
+    bytes.iter_mut().zip(u).for_each(|(byte, u)| *byte = *u);
+
+    RistrettoPoint::from_uniform_bytes(&bytes)
+}
+
+/// Computes the preimages of `point` under the Elligator map of Ristretto255,
+/// up to eight of which `u` and `-u` only one is returned. Each of the four
+/// Edwards points that `point` represents comes from two points of the Jacobi
+/// quartic, each of which might have a preimage, after "Lizard" of
+/// <https://github.com/bwesterb/go-ristretto>.
+#[cfg(feature = "ristretto255")]
+fn ristretto255_preimages(point: RistrettoPoint) -> [(Choice, FieldElement); 8] {
+    let one = FieldElement::from_u8(1);
+    let sqrt_m1 = FieldElement::from_bytes(&SQRT_M1);
+    let mdouble_invsqrt_a_minus_d = FieldElement::from_bytes(&MDOUBLE_INVSQRT_A_MINUS_D);
+
+    // Decodes the affine coordinates of a representative, see
+    // https://www.rfc-editor.org/rfc/rfc9496#section-4.3.1
+    let s = FieldElement::from_bytes(point.compress().as_bytes());
+    let s_square = s.square();
+    let u1 = one - s_square;
+    let u2 = one + s_square;
+    let u2_square = u2.square();
+    let v = -(FieldElement::from_bytes(&EDWARDS_D) * u1.square()) - u2_square;
+    let (_, invsqrt) = FieldElement::sqrt_ratio_m1(one, v * u2_square);
+    let den_x = invsqrt * u2;
+    let x = (s + s) * den_x;
+    let x = FieldElement::conditional_select(&x, &-x, x.is_negative());
+    let y = u1 * invsqrt * den_x * v;
+
+    // The points of the Jacobi quartic of `(x, y)` and `(-x, -y)`, with `z = 1`
+    let x_square = x.square();
+    let y_square = y.square();
+    let one_minus_y_square = one - y_square;
+    let (_, gamma) =
+        FieldElement::sqrt_ratio_m1(one, y_square.square() * x_square * one_minus_y_square);
+    let den = gamma * y_square;
+    let s_over_x = den * (one - y);
+    let sp_over_xp = den * (one + y);
+    let s0 = s_over_x * x;
+    let s1 = -sp_over_xp * x;
+    let t0 = mdouble_invsqrt_a_minus_d * s_over_x;
+    let t1 = mdouble_invsqrt_a_minus_d * sp_over_xp;
+
+    // The same for the substitution `(x, y, z) = (y, x, i)`
+    let den = -one_minus_y_square * FieldElement::from_bytes(&MINVSQRT_ONE_PLUS_D) * gamma;
+    let s_over_y = den * (sqrt_m1 - x);
+    let sp_over_yp = den * (sqrt_m1 + x);
+    let s2 = s_over_y * y;
+    let s3 = -sp_over_yp * y;
+    let t2 = mdouble_invsqrt_a_minus_d * sqrt_m1 * s_over_y;
+    let t3 = mdouble_invsqrt_a_minus_d * sqrt_m1 * sp_over_yp;
+
+    // If `x` or `y` is zero, so are all `s` and `t` above
+    let x_or_y_is_zero = x.is_zero() | y.is_zero();
+    let midouble_invsqrt_a_minus_d = FieldElement::from_bytes(&MIDOUBLE_INVSQRT_A_MINUS_D);
+    let t0 = FieldElement::conditional_select(&t0, &one, x_or_y_is_zero);
+    let t1 = FieldElement::conditional_select(&t1, &one, x_or_y_is_zero);
+    let t2 = FieldElement::conditional_select(&t2, &midouble_invsqrt_a_minus_d, x_or_y_is_zero);
+    let t3 = FieldElement::conditional_select(&t3, &midouble_invsqrt_a_minus_d, x_or_y_is_zero);
+    let s2 = FieldElement::conditional_select(&s2, &one, x_or_y_is_zero);
+    let s3 = FieldElement::conditional_select(&s3, &-one, x_or_y_is_zero);
+
+    let mut preimages = [(Choice::from(0), FieldElement::default()); 8];
+    let jacobi_points = [(s0, t0), (s1, t1), (s2, t2), (s3, t3)];
+
+    for (preimages, (s, t)) in preimages.chunks_exact_mut(2).zip(jacobi_points) {
+        for (preimage, (s, t)) in preimages.iter_mut().zip([(s, t), (-s, -t)]) {
+            *preimage = jacobi_preimage(s, t);
+        }
+    }
+
+    preimages
+}
+
+/// Computes the preimage of the point `(s, t)` of the Jacobi quartic under the
+/// first step of the Elligator map of Ristretto255, if it exists.
+#[cfg(feature = "ristretto255")]
+fn jacobi_preimage(s: FieldElement, t: FieldElement) -> (Choice, FieldElement) {
+    let one = FieldElement::from_u8(1);
+
+    // If `s` is zero, `t` is either 1, the point of `sqrt(i * d)`, or -1, the
+    // point of zero
+    let s_is_zero = s.is_zero();
+    let zero_preimage = FieldElement::conditional_select(
+        &FieldElement::default(),
+        &FieldElement::from_bytes(&SQRT_ID),
+        t.ct_eq(&one),
+    );
+
+    let a = (t + one) * FieldElement::from_bytes(&DP1_OVER_DM1);
+    let s_square = s.square();
+    let (is_square, y) = FieldElement::sqrt_ratio_m1(
+        one,
+        (s_square.square() - a.square()) * FieldElement::from_bytes(&SQRT_M1),
+    );
+    let signed_s_square = FieldElement::conditional_select(&s_square, &-s_square, s.is_negative());
+    let x = (a + signed_s_square) * y;
+    let x = FieldElement::conditional_select(&x, &-x, x.is_negative());
+
+    (
+        s_is_zero | is_square,
+        FieldElement::conditional_select(&x, &zero_preimage, s_is_zero),
+    )
+}
+
+//////////////////////////////
+// Ristretto255 Field Math //
+// ======================= //
+//////////////////////////////
+
+/// `sqrt(-1)`
+#[cfg(feature = "ristretto255")]
+const SQRT_M1: [u8; 32] = [
+    0xb0, 0xa0, 0x0e, 0x4a, 0x27, 0x1b, 0xee, 0xc4, 0x78, 0xe4, 0x2f, 0xad, 0x06, 0x18, 0x43, 0x2f,
+    0xa7, 0xd7, 0xfb, 0x3d, 0x99, 0x00, 0x4d, 0x2b, 0x0b, 0xdf, 0xc1, 0x4f, 0x80, 0x24, 0x83, 0x2b,
+];
+/// The Edwards `d`, `-121665 / 121666`
+#[cfg(feature = "ristretto255")]
+const EDWARDS_D: [u8; 32] = [
+    0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75, 0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+    0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c, 0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52,
+];
+/// `(d + 1) / (d - 1)`
+#[cfg(feature = "ristretto255")]
+const DP1_OVER_DM1: [u8; 32] = [
+    0x2c, 0xbb, 0x81, 0x9b, 0x5f, 0xac, 0x7f, 0x27, 0xc8, 0x1d, 0x24, 0xcd, 0xf1, 0xe7, 0x9a, 0x48,
+    0x24, 0x18, 0x9f, 0x99, 0x5f, 0xd9, 0xf8, 0xae, 0x9d, 0xd8, 0xe8, 0xa7, 0x30, 0xc8, 0x67, 0x0e,
+];
+/// `-2 / sqrt(a - d)`, with `INVSQRT_A_MINUS_D` of RFC 9496
+#[cfg(feature = "ristretto255")]
+const MDOUBLE_INVSQRT_A_MINUS_D: [u8; 32] = [
+    0x06, 0x7e, 0x45, 0xff, 0xaa, 0x04, 0x6e, 0xcc, 0x82, 0x1a, 0x7d, 0x4b, 0xd1, 0xd3, 0xa1, 0xc5,
+    0x7e, 0x4f, 0xfc, 0x03, 0xdc, 0x08, 0x7b, 0xd2, 0xbb, 0x06, 0xa0, 0x60, 0xf4, 0xed, 0x26, 0x0f,
+];
+/// `-2 * i / sqrt(a - d)`
+#[cfg(feature = "ristretto255")]
+const MIDOUBLE_INVSQRT_A_MINUS_D: [u8; 32] = [
+    0xd8, 0xbb, 0x77, 0x63, 0x10, 0xb7, 0x5d, 0x16, 0x9c, 0x6c, 0xb5, 0xd7, 0x38, 0xee, 0xa5, 0x9c,
+    0x10, 0x59, 0x0b, 0x28, 0x85, 0x58, 0xe0, 0x3d, 0x50, 0x3d, 0x56, 0x06, 0x68, 0x0b, 0x1b, 0x14,
+];
+/// `-1 / sqrt(1 + d)`, the negation of the nonnegative inverse square root
+#[cfg(feature = "ristretto255")]
+const MINVSQRT_ONE_PLUS_D: [u8; 32] = [
+    0x01, 0x22, 0x44, 0xce, 0x77, 0x24, 0xd1, 0xf4, 0xb1, 0x49, 0x25, 0x94, 0xe3, 0x08, 0xad, 0xb1,
+    0x77, 0x53, 0xfa, 0x6b, 0xbd, 0xd3, 0x0f, 0xe1, 0x57, 0xe1, 0xd4, 0xfc, 0x4b, 0x7a, 0xf2, 0x75,
+];
+/// `sqrt(i * d)`, the nonnegative square root
+#[cfg(feature = "ristretto255")]
+const SQRT_ID: [u8; 32] = [
+    0xa8, 0x1b, 0x5c, 0x4a, 0xcb, 0x2a, 0x30, 0x75, 0xaa, 0x6d, 0xea, 0x0e, 0x2d, 0xa9, 0xbc, 0xcd,
+    0x15, 0x6e, 0xeb, 0x73, 0x99, 0x54, 0x34, 0x75, 0x97, 0xeb, 0x7b, 0xf4, 0x58, 0x55, 0xb3, 0x05,
+];
+
+/// An element of the field of Curve25519, with the arithmetic of
+/// `fiat-crypto`. It is always kept in the tight bounds of its functions.
+#[cfg(feature = "ristretto255")]
+#[derive(Clone, Copy, Default)]
+struct FieldElement(fiat::fiat_25519_tight_field_element);
+
+#[cfg(feature = "ristretto255")]
+impl FieldElement {
+    /// Decodes little-endian bytes, ignoring the most significant bit.
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut bytes = *bytes;
+        if let Some(last) = bytes.last_mut() {
+            *last &= 0x7F;
+        }
+
+        let mut output = Self::default();
+        fiat::fiat_25519_from_bytes(&mut output.0, &bytes);
+        output
+    }
+
+    fn from_u8(value: u8) -> Self {
+        let mut bytes = [0; 32];
+        if let Some(first) = bytes.first_mut() {
+            *first = value;
+        }
+
+        Self::from_bytes(&bytes)
+    }
+
+    /// Encodes the canonical representative as little-endian bytes.
+    fn to_bytes(self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        fiat::fiat_25519_to_bytes(&mut bytes, &self.0);
+        bytes
+    }
+
+    fn carry(loose: fiat::fiat_25519_loose_field_element) -> Self {
+        let mut output = Self::default();
+        fiat::fiat_25519_carry(&mut output.0, &loose);
+        output
+    }
+
+    fn square(self) -> Self {
+        let mut output = Self::default();
+        fiat::fiat_25519_carry_square(&mut output.0, &self.0);
+        output
+    }
+
+    fn is_zero(self) -> Choice {
+        self.to_bytes().ct_eq(&[0; 32])
+    }
+
+    fn is_negative(self) -> Choice {
+        let [low, ..] = self.to_bytes();
+        Choice::from(low & 1)
+    }
+
+    /// Raises `self` to `(p - 5) / 8 = 2^252 - 3`.
+    fn pow_p58(self) -> Self {
+        let mut output = self;
+
+        // `self^(2^250 - 1)`
+        for _ in 1..250 {
+            output = output.square() * self;
+        }
+
+        output.square().square() * self
+    }
+
+    /// Implements `SQRT_RATIO_M1()` from
+    /// https://www.rfc-editor.org/rfc/rfc9496#section-4.2
+    fn sqrt_ratio_m1(u: Self, v: Self) -> (Choice, Self) {
+        let sqrt_m1 = Self::from_bytes(&SQRT_M1);
+        let v3 = v.square() * v;
+        let r = u * v3 * (u * v3.square() * v).pow_p58();
+        let check = v * r.square();
+
+        let correct_sign_sqrt = check.ct_eq(&u);
+        let flipped_sign_sqrt = check.ct_eq(&-u);
+        let flipped_sign_sqrt_i = check.ct_eq(&(-u * sqrt_m1));
+
+        let r =
+            Self::conditional_select(&r, &(sqrt_m1 * r), flipped_sign_sqrt | flipped_sign_sqrt_i);
+        let r = Self::conditional_select(&r, &-r, r.is_negative());
+
+        (correct_sign_sqrt | flipped_sign_sqrt, r)
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+#[cfg(feature = "ristretto255")]
+impl Add for FieldElement {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let mut loose = fiat::fiat_25519_loose_field_element::default();
+        fiat::fiat_25519_add(&mut loose, &self.0, &rhs.0);
+        Self::carry(loose)
+    }
+}
+
+#[cfg(feature = "ristretto255")]
+impl Sub for FieldElement {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let mut loose = fiat::fiat_25519_loose_field_element::default();
+        fiat::fiat_25519_sub(&mut loose, &self.0, &rhs.0);
+        Self::carry(loose)
+    }
+}
+
+#[cfg(feature = "ristretto255")]
+impl Mul for FieldElement {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut output = Self::default();
+        fiat::fiat_25519_carry_mul(&mut output.0, &self.0, &rhs.0);
+        output
+    }
+}
+
+#[cfg(feature = "ristretto255")]
+impl Neg for FieldElement {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let mut loose = fiat::fiat_25519_loose_field_element::default();
+        fiat::fiat_25519_opp(&mut loose, &self.0);
+        Self::carry(loose)
+    }
+}
+
+#[cfg(feature = "ristretto255")]
+impl ConditionallySelectable for FieldElement {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut output = Self::default();
+        fiat::fiat_25519_selectznz(&mut output.0, choice.unwrap_u8(), &a.0, &b.0);
+        output
+    }
+}
+
+#[cfg(feature = "ristretto255")]
+impl ConstantTimeEq for FieldElement {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.to_bytes().ct_eq(&other.to_bytes())
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(all(test, feature = "ristretto255"))]
+mod tests {
+    use curve25519_dalek::ristretto::CompressedRistretto;
+    use curve25519_dalek::traits::Identity;
+    use elliptic_curve::group::Group as _;
+    use elliptic_curve::sec1::FromEncodedPoint;
+    use elliptic_curve::Field;
+    use p256::{AffinePoint, EncodedPoint};
+    use rand::rngs::OsRng;
+    use sha2::{Sha256, Sha512};
+
+    use super::*;
+    use crate::group::ALLOW_IDENTITY;
+    use crate::{expand_message_xmd, BlindedElement, EvaluationElement};
+
+    const DST: &[u8] = b"QUUX-V01-CS02-with-expander-SHA256-128";
+
+    /// Decodes a decimal integer below `2^256` into little-endian bytes.
+    fn from_decimal(decimal: &str) -> [u8; 32] {
+        let mut bytes = [0; 32];
+
+        for digit in decimal.bytes() {
+            let mut carry = u16::from(digit - b'0');
+
+            for byte in bytes.iter_mut() {
+                let value = u16::from(*byte) * 10 + carry;
+                *byte = value as u8;
+                carry = value >> 8;
+            }
+
+            assert_eq!(carry, 0);
+        }
+
+        bytes
+    }
+
+    /// Inverts `element` as `element^(p - 2) = (element^((p - 5) / 8))^8 *
+    /// element^3`.
+    fn invert(element: FieldElement) -> FieldElement {
+        let power = element.pow_p58().square().square().square();
+
+        power * element.square() * element
+    }
+
+    /// The nonnegative square root of `element`, which has to be a square.
+    fn sqrt(element: FieldElement) -> FieldElement {
+        let (is_square, root) = FieldElement::sqrt_ratio_m1(element, FieldElement::from_u8(1));
+        assert!(bool::from(is_square));
+        root
+    }
+
+    /// The nonnegative inverse square root of `element`, which has to be a
+    /// square.
+    fn invsqrt(element: FieldElement) -> FieldElement {
+        let (is_square, root) = FieldElement::sqrt_ratio_m1(FieldElement::from_u8(1), element);
+        assert!(bool::from(is_square));
+        root
+    }
+
+    fn assert_constant(constant: &[u8; 32], derived: FieldElement) {
+        assert_eq!(hex::encode(constant), hex::encode(derived.to_bytes()));
+    }
+
+    /// Derives every constant from its defining formula, and the ones that are
+    /// given in RFC 9496 are also compared to their decimal values there.
+    #[test]
+    fn constants() {
+        let one = FieldElement::from_u8(1);
+        let two = one + one;
+
+        let mut bytes = [0; 32];
+        bytes[..3].copy_from_slice(&121_665_u32.to_le_bytes()[..3]);
+        let numerator = FieldElement::from_bytes(&bytes);
+        bytes[..3].copy_from_slice(&121_666_u32.to_le_bytes()[..3]);
+        let d = -numerator * invert(FieldElement::from_bytes(&bytes));
+        assert_constant(&EDWARDS_D, d);
+        assert_eq!(
+            EDWARDS_D,
+            from_decimal(
+                "37095705934669439343138083508754565189542113879843219016388785533085940283555"
+            )
+        );
+
+        let sqrt_m1 = sqrt(-one);
+        assert_constant(&SQRT_M1, sqrt_m1);
+        assert_eq!(
+            SQRT_M1,
+            from_decimal(
+                "19681161376707505956807079304988542015446066515923890162744021073123829784752"
+            )
+        );
+
+        assert_constant(&DP1_OVER_DM1, (d + one) * invert(d - one));
+
+        // `a = -1`
+        let invsqrt_a_minus_d = invsqrt(-one - d);
+        assert_eq!(
+            invsqrt_a_minus_d.to_bytes(),
+            from_decimal(
+                "54469307008909316920995813868745141605393597292927456921205312896311721017578"
+            )
+        );
+        assert_constant(&MDOUBLE_INVSQRT_A_MINUS_D, -two * invsqrt_a_minus_d);
+        assert_constant(
+            &MIDOUBLE_INVSQRT_A_MINUS_D,
+            -two * sqrt_m1 * invsqrt_a_minus_d,
+        );
+
+        assert_constant(&MINVSQRT_ONE_PLUS_D, -invsqrt(one + d));
+        assert_constant(&SQRT_ID, sqrt(sqrt_m1 * d));
+    }
+
+    #[test]
+    fn field_element_inversion() {
+        for _ in 0..16 {
+            let mut bytes = [0; 32];
+            OsRng.fill_bytes(&mut bytes);
+            let element = FieldElement::from_bytes(&bytes);
+
+            assert!(bool::from(
+                (element * invert(element)).ct_eq(&FieldElement::from_u8(1))
+            ));
+        }
+    }
+
+    #[test]
+    fn field_element_bytes() {
+        let mut bytes = [0xFF; 32];
+        assert_eq!(FieldElement::from_bytes(&bytes).to_bytes(), {
+            // `2^255 - 1` is `18` modulo `2^255 - 19`
+            let mut expected = [0; 32];
+            expected[0] = 18;
+            expected
+        });
+
+        OsRng.fill_bytes(&mut bytes);
+        bytes[31] &= 0x7F;
+        bytes[31] |= 0x01;
+        assert_eq!(FieldElement::from_bytes(&bytes).to_bytes(), bytes);
+    }
+
+    /// Checks that every element of `elems` round-trips and that encodings are
+    /// randomized.
+    fn round_trip<G: UniformGroup>(elems: impl IntoIterator<Item = G::Elem>)
+    where
+        G::Elem: core::fmt::Debug + PartialEq,
+    {
+        for elem in elems {
+            let bytes = G::encode_uniform(elem, &mut OsRng);
+            assert_eq!(G::decode_uniform(&bytes), elem);
+            assert_ne!(G::encode_uniform(elem, &mut OsRng), bytes);
+        }
+    }
+
+    #[test]
+    fn ristretto255_round_trip() {
+        // The identity is the only element whose representative has a zero
+        // coordinate, `x = 0`. `y = 0` would need `s = ±1`, which isn't a
+        // valid encoding.
+        let mut s = [0; 32];
+        s[0] = 1;
+        assert!(CompressedRistretto(s).decompress().is_none());
+
+        round_trip::<Ristretto255>(
+            [
+                RistrettoPoint::identity(),
+                Ristretto255::base_elem(),
+                -Ristretto255::base_elem(),
+                Ristretto255::hash_to_curve::<Sha512>(&[b"input"], DST).unwrap(),
+            ]
+            .into_iter()
+            .chain((0..256).map(|_| RistrettoPoint::random(&mut OsRng))),
+        );
+    }
+
+    #[test]
+    fn p256_round_trip() {
+        // `b` is a square, so there are two points with `x = 0`. There is none
+        // with `y = 0`, as the order of P-256 is odd.
+        let y = P256FieldElement::PARAMS.map_b.sqrt().unwrap();
+        let zero_x = [y, -y].map(|y| {
+            let point = EncodedPoint::from_affine_coordinates(
+                &P256FieldElement::ZERO.to_bytes(),
+                &y.to_bytes(),
+                false,
+            );
+            ProjectivePoint::from(AffinePoint::from_encoded_point(&point).unwrap())
+        });
+
+        round_trip::<NistP256>(
+            [
+                ProjectivePoint::IDENTITY,
+                NistP256::base_elem(),
+                -NistP256::base_elem(),
+                NistP256::hash_to_curve::<Sha256>(&[b"input"], DST).unwrap(),
+            ]
+            .into_iter()
+            .chain(zero_x)
+            .chain((0..256).map(|_| ProjectivePoint::random(&mut OsRng))),
+        );
+    }
+
+    /// Checks that every bit of the encodings of a fixed element is set about
+    /// half of the time. With 1024 encodings a bit is only outside of the
+    /// bounds with probability below `2^-30`.
+    fn bits_are_balanced<G: UniformGroup>(elem: G::Elem) {
+        // Long enough for the encodings of all groups
+        let mut counts = [[0_u16; 8]; 96];
+        let mut len = 0;
+
+        for _ in 0..1024 {
+            let bytes = G::encode_uniform(elem, &mut OsRng);
+            len = bytes.len();
+
+            for (counts, byte) in counts.iter_mut().zip(bytes) {
+                for (bit, count) in counts.iter_mut().enumerate() {
+                    *count += u16::from((byte >> bit) & 1);
+                }
+            }
+        }
+
+        for (index, counts) in counts.iter().enumerate().take(len) {
+            for (bit, count) in counts.iter().enumerate() {
+                assert!(
+                    (412..=612).contains(count),
+                    "bit {} of byte {} is set {} times out of 1024",
+                    bit,
+                    index,
+                    count,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn distribution() {
+        bits_are_balanced::<Ristretto255>(Ristretto255::base_elem());
+        bits_are_balanced::<NistP256>(NistP256::base_elem());
+    }
+
+    #[test]
+    fn decode_is_hash_to_curve() {
+        for input in [&b""[..], b"abc", b"abcdef0123456789"] {
+            let mut bytes = GenericArray::default();
+            expand_message_xmd::<Sha512>(&[input], DST, &mut bytes).unwrap();
+            assert_eq!(
+                Ristretto255::decode_uniform(&bytes),
+                Ristretto255::hash_to_curve::<Sha512>(&[input], DST).unwrap(),
+            );
+
+            let mut bytes = GenericArray::default();
+            expand_message_xmd::<Sha256>(&[input], DST, &mut bytes).unwrap();
+            assert_eq!(
+                NistP256::decode_uniform(&bytes),
+                NistP256::hash_to_curve::<Sha256>(&[input], DST).unwrap(),
+            );
+        }
+    }
+
+    // Computed with `curve25519-dalek`
+    #[test]
+    fn ristretto255_elligator() {
+        let mut u = [0; 32];
+        u[..8].copy_from_slice(&hex::decode("d20a1feb8ca954ab").unwrap());
+
+        assert_eq!(
+            hex::encode(elligator(&u).compress().as_bytes()),
+            "5c1d1a874a661d19730c1145afa333eec6ce0bb6d098d1947874c7d6257d393e",
+        );
+        assert_eq!(elligator(&[0; 32]), RistrettoPoint::identity());
+    }
+
+    #[test]
+    fn ristretto255_preimages_are_complete() {
+        for _ in 0..64 {
+            let mut u = [0; 32];
+            OsRng.fill_bytes(&mut u);
+            let u = FieldElement::from_bytes(&u);
+            let point = elligator(&u.to_bytes());
+            let mut found = false;
+
+            for (is_preimage, preimage) in ristretto255_preimages(point) {
+                if bool::from(is_preimage) {
+                    assert_eq!(elligator(&preimage.to_bytes()), point);
+                    found |= bool::from(preimage.ct_eq(&u) | preimage.ct_eq(&-u));
+                }
+            }
+
+            assert!(found);
+        }
+
+        let identity = ristretto255_preimages(RistrettoPoint::identity());
+        assert!(identity
+            .iter()
+            .any(|(is_preimage, preimage)| bool::from(*is_preimage & preimage.is_zero())));
+    }
+
+    #[test]
+    fn p256_preimages_are_complete() {
+        for _ in 0..64 {
+            let u = P256FieldElement::random(&mut OsRng);
+            let point = u.map_to_curve();
+            let mut found = false;
+
+            for (is_preimage, preimage) in p256_preimages(point) {
+                if bool::from(is_preimage) {
+                    assert_eq!(preimage.map_to_curve(), point);
+                    found |= bool::from(preimage.ct_eq(&u));
+                }
+            }
+
+            assert!(found);
+        }
+
+        assert!(p256_preimages(ProjectivePoint::IDENTITY)
+            .iter()
+            .all(|(is_preimage, _)| !bool::from(*is_preimage)));
+    }
+
+    #[test]
+    fn p256_lift() {
+        for _ in 0..64 {
+            let u = P256FieldElement::random(&mut OsRng);
+
+            if let Some(bytes) = super::p256_lift(u, &mut OsRng) {
+                assert_eq!(P256FieldElement::from_okm(&bytes), u);
+            }
+        }
+    }
+
+    #[test]
+    fn messages() {
+        let elem = NistP256::hash_to_curve::<Sha256>(&[b"input"], DST).unwrap();
+        let bytes = BlindedElement::<NistP256>(elem).to_uniform_bytes(&mut OsRng);
+        assert_eq!(
+            BlindedElement::<NistP256>::from_uniform_bytes(&bytes)
+                .unwrap()
+                .0,
+            elem,
+        );
+        assert!(BlindedElement::<NistP256>::from_uniform_bytes(&bytes[1..]).is_err());
+
+        let elem = RistrettoPoint::random(&mut OsRng);
+        let bytes = EvaluationElement::<Ristretto255>(elem).to_uniform_bytes(&mut OsRng);
+        assert_eq!(
+            EvaluationElement::<Ristretto255>::from_uniform_bytes(&bytes)
+                .unwrap()
+                .0,
+            elem,
+        );
+
+        let bytes = Ristretto255::encode_uniform(RistrettoPoint::identity(), &mut OsRng);
+        assert_eq!(
+            EvaluationElement::<Ristretto255>::from_uniform_bytes(&bytes).is_ok(),
+            ALLOW_IDENTITY,
+        );
+    }
+}
@@ -8,17 +8,20 @@
 use digest::core_api::BlockSizeUser;
 use digest::Digest;
 use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::group::Curve;
 use elliptic_curve::hash2curve::{ExpandMsgXmd, FromOkm, GroupDigest};
 use elliptic_curve::sec1::{FromEncodedPoint, ModulusSize, ToEncodedPoint};
 use elliptic_curve::{
-    AffinePoint, Field, FieldSize, Group as _, ProjectivePoint, PublicKey, Scalar, SecretKey,
+    AffinePoint, Field, FieldSize, Group as _, ProjectiveArithmetic, ProjectivePoint, PublicKey,
+    Scalar, SecretKey,
 };
-use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
 use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
+use subtle::CtOption;
 
-use super::Group;
-use crate::{Error, InternalError, Result};
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
 
 impl<C> Group for C
 where
@@ -64,26 +67,41 @@ where
         ProjectivePoint::<Self>::identity()
     }
 
+    // `serialize_elem_batch` can't share the field inversion: curves of this
+    // `elliptic-curve` version, e.g. p256 0.11, neither expose the projective
+    // coordinates nor override `Curve::batch_normalize`, which inverts each
+    // element on its own.
     fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
-        let bytes = elem.to_encoded_point(true);
-        let bytes = bytes.as_bytes();
-        let mut result = GenericArray::default();
-        result[..bytes.len()].copy_from_slice(bytes);
-        result
+        serialize_affine::<Self>(&elem.to_affine())
     }
 
     fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
+        // The identity is serialized as a single zero byte, padded with zeros
+        if ALLOW_IDENTITY
+            && element_bits.len() == Self::ElemLen::USIZE
+            && element_bits.iter().all(|byte| *byte == 0)
+        {
+            return Ok(Self::identity_elem());
+        }
+
+        if !is_compressed(element_bits, Self::ElemLen::USIZE) {
+            return Err(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ));
+        }
+
         PublicKey::<Self>::from_sec1_bytes(element_bits)
             .map(|public_key| public_key.to_projective())
-            .map_err(|_| Error::Deserialization)
+            .map_err(|_| Error::new(ErrorKind::Deserialization, Operation::DeserializeElem))
     }
 
     fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
         *SecretKey::<Self>::random(rng).to_nonzero_scalar()
     }
 
-    fn invert_scalar(scalar: Self::Scalar) -> Self::Scalar {
-        Option::from(scalar.invert()).unwrap()
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+        scalar.invert()
     }
 
     fn is_zero_scalar(scalar: Self::Scalar) -> subtle::Choice {
@@ -102,6 +120,34 @@ where
     fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar> {
         SecretKey::<Self>::from_be_bytes(scalar_bits)
             .map(|secret_key| *secret_key.to_nonzero_scalar())
-            .map_err(|_| Error::Deserialization)
+            .map_err(|_| Error::new(ErrorKind::Deserialization, Operation::DeserializeScalar))
     }
 }
+
+/// Checks that `bytes` are a compressed SEC1 encoding of `len` bytes, the only
+/// one [`Group::serialize_elem`] produces. The SEC1 decoders also accept the
+/// uncompressed and the compact encoding, which would make deserialization
+/// non-canonical.
+pub(super) fn is_compressed(bytes: &[u8], len: usize) -> bool {
+    bytes.len() == len && matches!(bytes.first(), Some(0x02 | 0x03))
+}
+
+/// Encodes an affine point compressed, the identity as a single zero byte
+/// padded with zeros.
+fn serialize_affine<C>(
+    point: &AffinePoint<C>,
+) -> GenericArray<u8, <FieldSize<C> as ModulusSize>::CompressedPointSize>
+where
+    C: ProjectiveArithmetic,
+    FieldSize<C>: ModulusSize,
+    AffinePoint<C>: ToEncodedPoint<C>,
+{
+    let bytes = point.to_encoded_point(true);
+    let bytes = bytes.as_bytes();
+    let mut result = GenericArray::default();
+    result
+        .iter_mut()
+        .zip(bytes)
+        .for_each(|(result, byte)| *result = *byte);
+    result
+}
@@ -0,0 +1,493 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+use core::ops::{Add, Mul, Sub};
+
+use digest::core_api::BlockSizeUser;
+use digest::Digest;
+use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, Expander};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256, U32};
+use generic_array::GenericArray;
+use jubjub_::{AffinePoint, ExtendedPoint, Fq, Fr};
+use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+use zeroize::DefaultIsZeroes;
+
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
+
+/// The byte length of a field element hashed by `hash_to_field`, `L` in the
+/// hash-to-curve specification.
+const L: usize = 48;
+/// The non-square `Z` of the Elligator 2 map.
+const Z: Fq = Fq::from_raw([5, 0, 0, 0]);
+/// `J / K` of the Montgomery curve `K * t^2 = s^3 + J * s^2 + s` birationally
+/// equivalent to Jubjub, with `J = 40962` and `K = -40964`.
+const J_OVER_K: Fq = Fq::from_raw([
+    0x0083_2feb_6b1a_1f58,
+    0x1496_bfb6_9bab_ce93,
+    0x7afe_c903_f35e_bfea,
+    0x1549_8c73_a5fd_15a4,
+]);
+/// `1 / K^2` of the Montgomery curve.
+const ONE_OVER_K_SQUARE: Fq = Fq::from_raw([
+    0xb1de_c13d_57ee_22ee,
+    0xc36c_5019_1c27_f784,
+    0xc984_b83f_5d04_9e6b,
+    0x2b80_6e72_7d7b_6e67,
+]);
+/// `K` of the Montgomery curve.
+const K: Fq = Fq::from_raw([
+    0xffff_fffe_ffff_5ffd,
+    0x53bd_a402_fffe_5bfe,
+    0x3339_d808_09a1_d805,
+    0x73ed_a753_299d_7d48,
+]);
+/// The generator of the prime-order subgroup, the generator of the full curve
+/// multiplied by the cofactor, like `jubjub::SubgroupPoint::generator()`.
+const GENERATOR: AffinePoint = AffinePoint::from_raw_unchecked(
+    Fq::from_raw([
+        0x512d_fea3_18d5_6fe5,
+        0x0431_5c65_7fbe_375f,
+        0x5ed3_7ee3_b172_f5ee,
+        0x3ea5_c467_3a12_1ca3,
+    ]),
+    Fq::from_raw([
+        0xc10c_ea38_d50c_55cb,
+        0xb9aa_6e8e_4080_8413,
+        0x78f7_d30d_3f61_6cb3,
+        0x5713_7b83_ea6e_db4f,
+    ]),
+);
+
+/// [`Group`] implementation for the prime-order subgroup of Jubjub, the twisted
+/// Edwards curve embedded in the scalar field of BLS12-381.
+///
+/// Outputs of this group can be checked natively by zk-circuits over BLS12-381,
+/// like the ones of Zcash, instead of emulating a foreign curve in-circuit.
+/// [`JubjubElem`] and [`JubjubScalar`] convert into the types of the [jubjub]
+/// crate for that purpose.
+///
+/// Hashing to the curve follows the random-oracle encoding of RFC 9380 with the
+/// Elligator 2 map to the birationally equivalent Montgomery curve, and
+/// [`Group::hash_to_scalar`] reduces 48 bytes, as for P-256. Neither has been
+/// assigned a suite by the specification.
+///
+/// [jubjub]: https://docs.rs/jubjub
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "jubjub")]
+pub struct Jubjub;
+
+/// An element of the prime-order subgroup of [`Jubjub`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JubjubElem(ExtendedPoint);
+
+/// A scalar of the prime-order subgroup of [`Jubjub`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JubjubScalar(Fr);
+
+/// Reduces big-endian bytes, as produced by `expand_message`, modulo the order
+/// of the base field.
+fn fq_from_uniform_bytes(bytes: &[u8]) -> Fq {
+    let mut wide = [0; 64];
+    wide.iter_mut()
+        .zip(bytes.iter().rev())
+        .for_each(|(wide, byte)| *wide = *byte);
+
+    Fq::from_bytes_wide(&wide)
+}
+
+/// Reduces big-endian bytes, as produced by `expand_message`, modulo the order
+/// of the subgroup.
+fn fr_from_uniform_bytes(bytes: &[u8]) -> Fr {
+    let mut wide = [0; 64];
+    wide.iter_mut()
+        .zip(bytes.iter().rev())
+        .for_each(|(wide, byte)| *wide = *byte);
+
+    Fr::from_bytes_wide(&wide)
+}
+
+/// Evaluates `x^3 / K^2 + J / K * x^2 + x / K^2`, the right-hand side of the
+/// Montgomery curve divided by `K^3`.
+fn montgomery_rhs(x: Fq) -> Fq {
+    x * (x * (x + J_OVER_K) + ONE_OVER_K_SQUARE)
+}
+
+/// Decodes a point and checks that it is in the prime-order subgroup.
+fn decode_elem(element_bits: &[u8]) -> Option<ExtendedPoint> {
+    element_bits
+        .try_into()
+        .ok()
+        .and_then(|bytes| Option::<AffinePoint>::from(AffinePoint::from_bytes(bytes)))
+        .map(ExtendedPoint::from)
+        .filter(|point| bool::from(point.is_torsion_free()))
+}
+
+// Implements `map_to_curve_elligator2()` and the rational map to the twisted
+// Edwards curve from https://www.rfc-editor.org/rfc/rfc9380.html#section-6.8.2
+// in constant-time.
+fn map_to_curve(u: Fq) -> ExtendedPoint {
+    let zero = Fq::zero();
+    let one = Fq::one();
+    let neg_j_over_k = -J_OVER_K;
+
+    let x1 = neg_j_over_k * (one + Z * u.square()).invert().unwrap_or(zero);
+    let x1 = Fq::conditional_select(&x1, &neg_j_over_k, x1.ct_eq(&zero));
+    let x2 = neg_j_over_k - x1;
+    let y1 = montgomery_rhs(x1).sqrt();
+    let y2 = montgomery_rhs(x2).sqrt().unwrap_or(zero);
+
+    let is_square = y1.is_some();
+    let x = Fq::conditional_select(&x2, &x1, is_square);
+    let y = Fq::conditional_select(&y2, &y1.unwrap_or(zero), is_square);
+    // `sgn0(y)` has to be `1` exactly if `x1` was chosen
+    let sign = Choice::from(y.to_bytes()[0] & 1);
+    let y = Fq::conditional_select(&y, &-y, sign ^ is_square);
+
+    let s = x * K;
+    let t = y * K;
+    // Both denominators are inverted at once, the map is exceptional and
+    // returns the identity if either is zero
+    let denominator = t * (s + one);
+    let inverse = denominator.invert().unwrap_or(zero);
+    let point = AffinePoint::from_raw_unchecked(s * (s + one) * inverse, (s - one) * t * inverse);
+
+    AffinePoint::conditional_select(&point, &AffinePoint::identity(), inverse.ct_eq(&zero)).into()
+}
+
+impl From<JubjubElem> for ExtendedPoint {
+    fn from(elem: JubjubElem) -> Self {
+        elem.0
+    }
+}
+
+impl From<JubjubElem> for AffinePoint {
+    fn from(elem: JubjubElem) -> Self {
+        elem.0.into()
+    }
+}
+
+impl From<JubjubScalar> for Fr {
+    fn from(scalar: JubjubScalar) -> Self {
+        scalar.0
+    }
+}
+
+impl crate::CipherSuite for Jubjub {
+    // Not assigned by the specification.
+    const ID: u16 = 0xFFFD;
+
+    type Group = Jubjub;
+
+    type Hash = sha2::Sha512;
+
+    type FinalizeHash = sha2::Sha512;
+}
+
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "jubjub")]
+impl Group for Jubjub {
+    type Elem = JubjubElem;
+
+    type ElemLen = U32;
+
+    type Scalar = JubjubScalar;
+
+    type ScalarLen = U32;
+
+    // Implements `hash_to_curve()` from
+    // https://www.rfc-editor.org/rfc/rfc9380.html#section-3 with the Elligator 2
+    // map
+    fn hash_to_curve<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Elem, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = [0; 2 * L];
+        ExpandMsgXmd::<H>::expand_message(input, dst, 2 * L)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+        let (u0, u1) = uniform_bytes.split_at(L);
+
+        let point =
+            map_to_curve(fq_from_uniform_bytes(u0)) + map_to_curve(fq_from_uniform_bytes(u1));
+
+        Ok(JubjubElem(point.mul_by_cofactor()))
+    }
+
+    // Implements the `HashToScalar()` function from
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html#section-4.1
+    fn hash_to_scalar<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Scalar, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = [0; L];
+        ExpandMsgXmd::<H>::expand_message(input, dst, L)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+
+        Ok(JubjubScalar(fr_from_uniform_bytes(&uniform_bytes)))
+    }
+
+    fn base_elem() -> Self::Elem {
+        JubjubElem(GENERATOR.into())
+    }
+
+    fn identity_elem() -> Self::Elem {
+        JubjubElem(ExtendedPoint::identity())
+    }
+
+    fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
+        AffinePoint::from(elem.0).to_bytes().into()
+    }
+
+    fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
+        decode_elem(element_bits)
+            .filter(|point| ALLOW_IDENTITY || !bool::from(point.is_identity()))
+            .map(JubjubElem)
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ))
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+        loop {
+            let mut bytes = [0; 64];
+            rng.fill_bytes(&mut bytes);
+            let scalar = Fr::from_bytes_wide(&bytes);
+
+            if scalar != Fr::zero() {
+                break JubjubScalar(scalar);
+            }
+        }
+    }
+
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+        scalar.0.invert().map(JubjubScalar)
+    }
+
+    fn is_zero_scalar(scalar: Self::Scalar) -> Choice {
+        scalar.0.ct_eq(&Fr::zero())
+    }
+
+    #[cfg(test)]
+    fn zero_scalar() -> Self::Scalar {
+        JubjubScalar(Fr::zero())
+    }
+
+    fn serialize_scalar(scalar: Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        scalar.0.to_bytes().into()
+    }
+
+    fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar> {
+        scalar_bits
+            .try_into()
+            .ok()
+            .and_then(|bytes| Option::<Fr>::from(Fr::from_bytes(bytes)))
+            .filter(|scalar| scalar != &Fr::zero())
+            .map(JubjubScalar)
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeScalar,
+            ))
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl Add<&JubjubElem> for JubjubElem {
+    type Output = JubjubElem;
+
+    fn add(self, rhs: &JubjubElem) -> JubjubElem {
+        JubjubElem(self.0 + rhs.0)
+    }
+}
+
+impl Mul<&JubjubScalar> for JubjubElem {
+    type Output = JubjubElem;
+
+    fn mul(self, rhs: &JubjubScalar) -> JubjubElem {
+        JubjubElem(self.0 * rhs.0)
+    }
+}
+
+impl ConditionallySelectable for JubjubElem {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        JubjubElem(ExtendedPoint::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for JubjubElem {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+// The default element is the identity.
+impl DefaultIsZeroes for JubjubElem {}
+
+impl Add<&JubjubScalar> for JubjubScalar {
+    type Output = JubjubScalar;
+
+    fn add(self, rhs: &JubjubScalar) -> JubjubScalar {
+        JubjubScalar(self.0 + rhs.0)
+    }
+}
+
+impl Sub<&JubjubScalar> for JubjubScalar {
+    type Output = JubjubScalar;
+
+    fn sub(self, rhs: &JubjubScalar) -> JubjubScalar {
+        JubjubScalar(self.0 - rhs.0)
+    }
+}
+
+impl Mul<&JubjubScalar> for JubjubScalar {
+    type Output = JubjubScalar;
+
+    fn mul(self, rhs: &JubjubScalar) -> JubjubScalar {
+        JubjubScalar(self.0 * rhs.0)
+    }
+}
+
+impl ConditionallySelectable for JubjubScalar {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        JubjubScalar(Fr::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for JubjubScalar {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+// The default scalar is zero.
+impl DefaultIsZeroes for JubjubScalar {}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    // Decompression recovers the same point only if it is on the curve
+    fn is_on_curve(point: AffinePoint) -> bool {
+        Option::<AffinePoint>::from(AffinePoint::from_bytes(point.to_bytes())) == Some(point)
+    }
+
+    // Computed with `scripts/jubjub_hash_to_curve.py`, an independent
+    // implementation of RFC 9380 which derives `J`, `K` and `Z` from the curve
+    // equation, as the specification defines no suite for Jubjub
+    const DST: &[u8] = b"QUUX-V01-CS02-with-jubjub_XMD:SHA-512_ELL2_RO_";
+    const VECTORS: [(&str, &str); 5] = [
+        (
+            "",
+            "1704d5133b5530a46aba207b6cf0ececb748dcbb0f94c5482a69375f6ca5f05e",
+        ),
+        (
+            "abc",
+            "3d82e878b940c28388e835917f7be72a7d7041d09abe14ef3995c968645a2261",
+        ),
+        (
+            "abcdef0123456789",
+            "04e8697da6f46febdbafce02999bc596ad9ccb267e235d93a1312855067826ef",
+        ),
+        (
+            "q128_",
+            "c47aab81dc19513d8b1fbf80c59c96ed8a836d58726b30525c3541b00a131d2a",
+        ),
+        (
+            "a512_",
+            "e4a532f6e23d0914a733133ad631320c214c17977f13123efb680d8a3dbe94ee",
+        ),
+    ];
+    // The outputs of `map_to_curve` itself for small `u`, before clearing the
+    // cofactor
+    const MAP_VECTORS: [&str; 4] = [
+        "0100000000000000000000000000000000000000000000000000000000000000",
+        "9d4bd08b3912d9ea122d7a38b02788ba9520e4593f6ace5294102118026e11bb",
+        "6bfd556802866804836e3bfec9af63f580f2615fd2e042e3aa9fea9956c5e351",
+        "2bf35cc6d361ef37429d642cf773af76ae8841430d200ae0c72aa06fd0c57154",
+    ];
+
+    // Expands the prefixes of the long messages of RFC 9380 like in its test
+    // vectors
+    fn message(prefix: &str) -> Vec<u8> {
+        let mut message = prefix.as_bytes().to_vec();
+        match prefix {
+            "q128_" => message.extend_from_slice(&[b'q'; 128]),
+            "a512_" => message.extend_from_slice(&[b'a'; 512]),
+            _ => (),
+        }
+        message
+    }
+
+    #[test]
+    fn test_hash_to_curve() -> Result<()> {
+        for (prefix, expected) in VECTORS {
+            let elem = Jubjub::hash_to_curve::<sha2::Sha512>(&[&message(prefix)], DST).unwrap();
+            assert_eq!(hex::encode(Jubjub::serialize_elem(elem)), expected);
+
+            let point = ExtendedPoint::from(elem);
+            assert!(is_on_curve(point.into()));
+            assert!(bool::from(point.is_prime_order()));
+        }
+
+        for index in 0..64_u8 {
+            let point = ExtendedPoint::from(
+                Jubjub::hash_to_curve::<sha2::Sha512>(&[&[index]], DST).unwrap(),
+            );
+            assert!(is_on_curve(point.into()));
+            assert!(bool::from(point.is_prime_order()));
+        }
+
+        // The map itself lands on the curve, but not necessarily in the subgroup
+        for (u, expected) in (0..).zip(MAP_VECTORS) {
+            let point = AffinePoint::from(map_to_curve(Fq::from(u)));
+            assert_eq!(hex::encode(point.to_bytes()), expected);
+        }
+        for u in 0..64 {
+            let point = AffinePoint::from(map_to_curve(Fq::from(u)));
+            assert!(is_on_curve(point));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_generator() -> Result<()> {
+        let generator = ExtendedPoint::from(Jubjub::base_elem());
+        assert!(is_on_curve(generator.into()));
+        assert!(bool::from(generator.is_prime_order()));
+
+        let bytes = Jubjub::serialize_elem(Jubjub::base_elem());
+        assert!(bool::from(
+            Jubjub::deserialize_elem(&bytes)?.ct_eq(&Jubjub::base_elem())
+        ));
+
+        // Points outside of the prime-order subgroup are rejected
+        let torsion = AffinePoint::from_raw_unchecked(Fq::zero(), -Fq::one());
+        assert!(Jubjub::deserialize_elem(&torsion.to_bytes()).is_err());
+        let mixed = AffinePoint::from(generator + ExtendedPoint::from(torsion));
+        assert!(Jubjub::deserialize_elem(&mixed.to_bytes()).is_err());
+
+        Ok(())
+    }
+}
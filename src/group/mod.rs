@@ -7,29 +7,87 @@
 
 //! Defines the Group trait to specify the underlying prime order group
 
+#[cfg(feature = "bls12_381")]
+mod bls12_381;
+#[cfg(feature = "elligator")]
+mod elligator;
 mod elliptic_curve;
+#[cfg(feature = "jubjub")]
+mod jubjub;
+#[cfg(feature = "pasta")]
+mod pasta;
 #[cfg(feature = "ristretto255")]
 mod ristretto;
+#[cfg(feature = "secp256k1")]
+mod secp256k1;
+#[cfg(feature = "sm2")]
+mod sm2;
+#[cfg(feature = "toy-group")]
+mod toy;
 
 use core::ops::{Add, Mul, Sub};
 
+#[cfg(feature = "pythia")]
+pub(crate) use bls12_381::{hash_to_g2, pairing, GtElem};
+#[cfg(feature = "bls12_381")]
+pub use bls12_381::{Bls12_381, Bls12_381Elem, Bls12_381Scalar};
 use digest::core_api::BlockSizeUser;
 use digest::Digest;
+#[cfg(feature = "elligator")]
+pub use elligator::UniformGroup;
 use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
 use generic_array::{ArrayLength, GenericArray};
+#[cfg(feature = "jubjub")]
+pub use jubjub::{Jubjub, JubjubElem, JubjubScalar};
+#[cfg(feature = "pasta")]
+pub use pasta::{Pallas, PallasElem, PallasScalar, Vesta, VestaElem, VestaScalar};
 use rand_core::{CryptoRng, RngCore};
 #[cfg(feature = "ristretto255")]
 pub use ristretto::Ristretto255;
-use subtle::{Choice, ConstantTimeEq};
+#[cfg(feature = "secp256k1")]
+pub use secp256k1::Secp256k1;
+#[cfg(feature = "sm2")]
+pub use sm2::Sm2;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+#[cfg(feature = "toy-group")]
+pub use toy::{ToyElem, ToyGroup, ToyScalar};
 use zeroize::Zeroize;
 
 use crate::{InternalError, Result};
 
+/// Whether [`Group::deserialize_elem`] accepts the identity element, which only
+/// the `danger-allow-identity` feature enables.
+pub(crate) const ALLOW_IDENTITY: bool = cfg!(feature = "danger-allow-identity");
+
 /// A prime-order subgroup of a base field (EC, prime-order field ...). This
 /// subgroup is noted additively — as in the draft RFC — in this trait.
+///
+/// Elements and scalars have to be [`ConditionallySelectable`] and
+/// [`ConstantTimeEq`], so the protocol can handle secret values, like blinds,
+/// private keys and tweaks, without branching on them. E.g. the inversion of
+/// blinds and tweaks goes through [`Group::invert_scalar_ct`] and only the
+/// final [`Choice`] of whether a tweak is valid decides if an error is
+/// returned. The constant-time properties of the arithmetic itself are the ones
+/// of the backend:
+/// - [`Ristretto255`]: curve25519-dalek's scalar and point arithmetic is
+///   constant-time, with every backend.
+/// - `Bls12_381`: the arithmetic of the `bls12_381` crate and the map to the
+///   curve are constant-time.
+/// - Curves implemented with the `elliptic-curve` crate, e.g. `p256`: the
+///   RustCrypto implementations are constant-time, other implementations have
+///   to document their own guarantees.
+/// - `Secp256k1`: the arithmetic of the `k256` crate is constant-time.
+/// - `Jubjub`: the arithmetic of the `jubjub` crate and the map to the curve
+///   are constant-time.
+/// - `Pallas` and `Vesta`: the arithmetic of the `pasta_curves` crate and the
+///   map to the curve are constant-time, but serializing elements is not.
+/// - `Sm2`: the arithmetic of the `sm2` crate and the map to the curve are
+///   constant-time.
+/// - `ToyGroup`: not constant-time, it is insecure anyway.
 pub trait Group {
     /// The type of group elements
-    type Elem: ConstantTimeEq
+    type Elem: ConditionallySelectable
+        + ConstantTimeEq
         + Copy
         + Zeroize
         + for<'a> Add<&'a Self::Elem, Output = Self::Elem>
@@ -39,7 +97,8 @@ pub trait Group {
     type ElemLen: ArrayLength<u8> + 'static;
 
     /// The type of base field scalars
-    type Scalar: ConstantTimeEq
+    type Scalar: ConditionallySelectable
+        + ConstantTimeEq
         + Copy
         + Zeroize
         + for<'a> Add<&'a Self::Scalar, Output = Self::Scalar>
@@ -52,8 +111,8 @@ pub trait Group {
     /// Transforms a password and domain separation tag (DST) into a curve point
     ///
     /// # Errors
-    /// [`Error::Input`](crate::Error::Input) if the `input` is empty or longer
-    /// then [`u16::MAX`].
+    /// [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty or
+    /// longer then [`u16::MAX`].
     fn hash_to_curve<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Elem, InternalError>
     where
         H: Digest + BlockSizeUser,
@@ -62,8 +121,8 @@ pub trait Group {
     /// Hashes a slice of pseudo-random bytes to a scalar
     ///
     /// # Errors
-    /// [`Error::Input`](crate::Error::Input) if the `input` is empty or longer
-    /// then [`u16::MAX`].
+    /// [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty or
+    /// longer then [`u16::MAX`].
     fn hash_to_scalar<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Scalar, InternalError>
     where
         H: Digest + BlockSizeUser,
@@ -83,19 +142,54 @@ pub trait Group {
     /// Serializes the `self` group element
     fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen>;
 
+    /// Serializes the `elems` into `output`, like [`Group::serialize_elem`]
+    /// does for each of them. Groups can override it to share work across the
+    /// batch, e.g. the field inversion of the conversion to affine coordinates.
+    /// Only as many elements as fit into `output` are serialized.
+    fn serialize_elem_batch(elems: &[Self::Elem], output: &mut [GenericArray<u8, Self::ElemLen>]) {
+        for (elem, output) in elems.iter().zip(output) {
+            *output = Self::serialize_elem(*elem);
+        }
+    }
+
+    /// Computes `elem * a + base_elem() * b` over public values, e.g. in proof
+    /// verification. Groups can override it with a variable-time double-scalar
+    /// multiplication, which is faster than two constant-time ones, but must
+    /// never be used with secret values.
+    fn vartime_double_mul_base(a: Self::Scalar, elem: Self::Elem, b: Self::Scalar) -> Self::Elem {
+        (elem * &a) + &(Self::base_elem() * &b)
+    }
+
+    /// Computes `x * a + y * b` over public values, e.g. in proof
+    /// verification. Like [`Group::vartime_double_mul_base`], groups can
+    /// override it with a variable-time double-scalar multiplication.
+    fn vartime_double_mul(
+        a: Self::Scalar,
+        x: Self::Elem,
+        b: Self::Scalar,
+        y: Self::Elem,
+    ) -> Self::Elem {
+        (x * &a) + &(y * &b)
+    }
+
     /// Return an element from its fixed-length bytes representation. If the
-    /// element is the identity element, return an error.
+    /// element is the identity element, return an error, unless the
+    /// **dangerous** `danger-allow-identity` feature is enabled.
+    ///
+    /// Only the canonical encoding of an element may be accepted, so that
+    /// [`Group::serialize_elem`] returns the same bytes.
     ///
     /// # Errors
-    /// [`Error::Deserialization`](crate::Error::Deserialization) if the element
-    /// is not a valid point on the group or the identity element.
+    /// [`ErrorKind::Deserialization`](crate::ErrorKind::Deserialization) if the
+    /// element is not a valid point on the group or the identity element.
     fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem>;
 
     /// picks a scalar at random
     fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar;
 
-    /// The multiplicative inverse of this scalar
-    fn invert_scalar(scalar: Self::Scalar) -> Self::Scalar;
+    /// The multiplicative inverse of this scalar, computed in constant-time.
+    /// Returns none if the scalar is zero.
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar>;
 
     /// Returns `true` if the scalar is zero.
     fn is_zero_scalar(scalar: Self::Scalar) -> Choice;
@@ -110,9 +204,12 @@ pub trait Group {
     /// Return a scalar from its fixed-length bytes representation. If the
     /// scalar is zero or invalid, then return an error.
     ///
+    /// Only the canonical encoding of a scalar may be accepted, so that
+    /// [`Group::serialize_scalar`] returns the same bytes.
+    ///
     /// # Errors
-    /// [`Error::Deserialization`](crate::Error::Deserialization) if the scalar
-    /// is not a valid point on the group or zero.
+    /// [`ErrorKind::Deserialization`](crate::ErrorKind::Deserialization) if the
+    /// scalar is not a valid point on the group or zero.
     fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar>;
 }
 
@@ -0,0 +1,418 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+use core::ops::{Add, Mul, Sub};
+
+use digest::core_api::BlockSizeUser;
+use digest::Digest;
+use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, Expander};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256, U32};
+use generic_array::GenericArray;
+use pasta_curves::arithmetic::FieldExt;
+use pasta_curves::group::ff::{Field, PrimeField};
+use pasta_curves::group::prime::PrimeCurveAffine;
+use pasta_curves::group::{Group as _, GroupEncoding};
+use pasta_curves::{pallas, vesta};
+use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+use zeroize::DefaultIsZeroes;
+
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
+
+/// The byte length of a field element hashed by `hash_to_field`, `L` in the
+/// hash-to-curve specification. Like the `pasta_curves` crate, this targets a
+/// security level of 256 bits instead of 128.
+const L: usize = 64;
+/// The constant `B` of the curves isogenous to Pallas and Vesta, which is the
+/// same for both.
+const ISO_B: u64 = 1265;
+
+/// Reduces big-endian bytes, as produced by `expand_message`, to a field
+/// element.
+fn from_uniform_bytes<F: FieldExt>(bytes: &[u8]) -> F {
+    let mut wide = [0; 64];
+    wide.iter_mut()
+        .zip(bytes.iter().rev())
+        .for_each(|(wide, byte)| *wide = *byte);
+
+    F::from_bytes_wide(&wide)
+}
+
+// Implements `map_to_curve_simple_swu()` from
+// https://www.rfc-editor.org/rfc/rfc9380.html#section-6.6.2 in constant-time,
+// returning the affine coordinates on the isogenous curve
+// `y^2 = x^3 + a * x + b`.
+fn map_to_iso_curve<F: FieldExt>(u: F, a: F, b: F, z: F) -> (F, F) {
+    let zero = F::zero();
+    let a_inverse = a.invert().unwrap_or(zero);
+
+    let z_u2 = z * u.square();
+    let tv1 = z_u2.square() + z_u2;
+    let x1 = -b * a_inverse * (F::one() + tv1.invert().unwrap_or(zero));
+    let exceptional_x1 = b * a_inverse * z.invert().unwrap_or(zero);
+    let x1 = F::conditional_select(&x1, &exceptional_x1, tv1.is_zero());
+    let x2 = z_u2 * x1;
+    let y1 = ((x1.square() + a) * x1 + b).sqrt();
+    let y2 = ((x2.square() + a) * x2 + b).sqrt().unwrap_or(zero);
+
+    let is_square = y1.is_some();
+    let x = F::conditional_select(&x2, &x1, is_square);
+    let y = F::conditional_select(&y2, &y1.unwrap_or(zero), is_square);
+    let y = F::conditional_select(&y, &-y, u.is_odd() ^ y.is_odd());
+
+    (x, y)
+}
+
+// Implements the 3-isogeny map from the isogenous curve, given its 13
+// constants, and returns the compressed encoding of the resulting point.
+fn iso_map<F: FieldExt<Repr = [u8; 32]>>(x: F, y: F, k: &[F; 13]) -> [u8; 32] {
+    let num_x = ((k[0] * x + k[1]) * x + k[2]) * x + k[3];
+    let div_x = (x + k[4]) * x + k[5];
+    let num_y = (((k[6] * x + k[7]) * x + k[8]) * x + k[9]) * y;
+    let div_y = ((x + k[10]) * x + k[11]) * x + k[12];
+    // Both denominators are inverted at once, which is zero if either is
+    // zero, making the encoding invalid
+    let inverse = (div_x * div_y).invert().unwrap_or(F::zero());
+
+    let mut bytes = (num_x * div_y * inverse).to_repr();
+    bytes[31] |= (num_y * div_x * inverse).is_odd().unwrap_u8() << 7;
+    bytes
+}
+
+macro_rules! impl_pasta {
+    (
+        $(#[$attr:meta])*
+        $group:ident, $elem:ident, $scalar:ident, $curve:ident, $id:literal, $iso_a:expr
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        // `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+        #[cfg(feature = "pasta")]
+        pub struct $group;
+
+        #[doc = concat!("An element of [`", stringify!($group), "`].")]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $elem($curve::Point);
+
+        #[doc = concat!("A scalar of [`", stringify!($group), "`].")]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $scalar($curve::Scalar);
+
+        impl From<$elem> for $curve::Point {
+            fn from(elem: $elem) -> Self {
+                elem.0
+            }
+        }
+
+        impl From<$scalar> for $curve::Scalar {
+            fn from(scalar: $scalar) -> Self {
+                scalar.0
+            }
+        }
+
+        impl crate::CipherSuite for $group {
+            // Not assigned by the specification.
+            const ID: u16 = $id;
+
+            type Group = $group;
+
+            type Hash = sha2::Sha512;
+
+            type FinalizeHash = sha2::Sha512;
+        }
+
+        // `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+        #[cfg(feature = "pasta")]
+        impl Group for $group {
+            type Elem = $elem;
+
+            type ElemLen = U32;
+
+            type Scalar = $scalar;
+
+            type ScalarLen = U32;
+
+            // Implements `hash_to_curve()` from
+            // https://www.rfc-editor.org/rfc/rfc9380.html#section-3 with the
+            // simplified SWU map to the isogenous curve
+            fn hash_to_curve<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Elem, InternalError>
+            where
+                H: Digest + BlockSizeUser,
+                H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+            {
+                let mut uniform_bytes = [0; 2 * L];
+                ExpandMsgXmd::<H>::expand_message(input, dst, 2 * L)
+                    .map_err(|_| InternalError::Input)?
+                    .fill_bytes(&mut uniform_bytes);
+
+                let point = uniform_bytes
+                    .chunks_exact(L)
+                    .map(|bytes| {
+                        let (x, y) = map_to_iso_curve(
+                            from_uniform_bytes(bytes),
+                            $iso_a,
+                            $curve::Base::from(ISO_B),
+                            $curve::Point::Z,
+                        );
+                        let bytes = iso_map(x, y, &$curve::Point::ISOGENY_CONSTANTS);
+
+                        $curve::Affine::from_bytes(&bytes).unwrap_or($curve::Affine::identity())
+                    })
+                    .fold($curve::Point::identity(), |sum, point| sum + point);
+
+                Ok($elem(point))
+            }
+
+            // Implements the `HashToScalar()` function from
+            // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html#section-4.1
+            fn hash_to_scalar<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Scalar, InternalError>
+            where
+                H: Digest + BlockSizeUser,
+                H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+            {
+                let mut uniform_bytes = [0; L];
+                ExpandMsgXmd::<H>::expand_message(input, dst, L)
+                    .map_err(|_| InternalError::Input)?
+                    .fill_bytes(&mut uniform_bytes);
+
+                Ok($scalar(from_uniform_bytes(&uniform_bytes)))
+            }
+
+            fn base_elem() -> Self::Elem {
+                $elem($curve::Point::generator())
+            }
+
+            fn identity_elem() -> Self::Elem {
+                $elem($curve::Point::identity())
+            }
+
+            fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
+                elem.0.to_bytes().into()
+            }
+
+            fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
+                element_bits
+                    .try_into()
+                    .ok()
+                    .and_then(|bytes| Option::from($curve::Point::from_bytes(bytes)))
+                    .filter(|point: &$curve::Point| {
+                        ALLOW_IDENTITY || !bool::from(point.is_identity())
+                    })
+                    .map($elem)
+                    .ok_or(Error::new(
+                        ErrorKind::Deserialization,
+                        Operation::DeserializeElem,
+                    ))
+            }
+
+            fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+                loop {
+                    let scalar = $curve::Scalar::random(&mut *rng);
+
+                    if !bool::from(scalar.is_zero()) {
+                        break $scalar(scalar);
+                    }
+                }
+            }
+
+            fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+                scalar.0.invert().map($scalar)
+            }
+
+            fn is_zero_scalar(scalar: Self::Scalar) -> Choice {
+                scalar.0.is_zero()
+            }
+
+            #[cfg(test)]
+            fn zero_scalar() -> Self::Scalar {
+                $scalar($curve::Scalar::zero())
+            }
+
+            fn serialize_scalar(scalar: Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+                scalar.0.to_repr().into()
+            }
+
+            fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar> {
+                scalar_bits
+                    .try_into()
+                    .ok()
+                    .and_then(|bytes| Option::from($curve::Scalar::from_repr(bytes)))
+                    .filter(|scalar: &$curve::Scalar| !bool::from(scalar.is_zero()))
+                    .map($scalar)
+                    .ok_or(Error::new(
+                        ErrorKind::Deserialization,
+                        Operation::DeserializeScalar,
+                    ))
+            }
+        }
+
+        impl Add<&$elem> for $elem {
+            type Output = $elem;
+
+            fn add(self, rhs: &$elem) -> $elem {
+                $elem(self.0 + rhs.0)
+            }
+        }
+
+        impl Mul<&$scalar> for $elem {
+            type Output = $elem;
+
+            fn mul(self, rhs: &$scalar) -> $elem {
+                $elem(self.0 * rhs.0)
+            }
+        }
+
+        impl ConditionallySelectable for $elem {
+            fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+                $elem($curve::Point::conditional_select(&a.0, &b.0, choice))
+            }
+        }
+
+        impl ConstantTimeEq for $elem {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                self.0.ct_eq(&other.0)
+            }
+        }
+
+        // The default element is the identity.
+        impl DefaultIsZeroes for $elem {}
+
+        impl Add<&$scalar> for $scalar {
+            type Output = $scalar;
+
+            fn add(self, rhs: &$scalar) -> $scalar {
+                $scalar(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub<&$scalar> for $scalar {
+            type Output = $scalar;
+
+            fn sub(self, rhs: &$scalar) -> $scalar {
+                $scalar(self.0 - rhs.0)
+            }
+        }
+
+        impl Mul<&$scalar> for $scalar {
+            type Output = $scalar;
+
+            fn mul(self, rhs: &$scalar) -> $scalar {
+                $scalar(self.0 * rhs.0)
+            }
+        }
+
+        impl ConditionallySelectable for $scalar {
+            fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+                $scalar($curve::Scalar::conditional_select(&a.0, &b.0, choice))
+            }
+        }
+
+        impl ConstantTimeEq for $scalar {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                self.0.ct_eq(&other.0)
+            }
+        }
+
+        // The default scalar is zero.
+        impl DefaultIsZeroes for $scalar {}
+    };
+}
+
+impl_pasta!(
+    /// [`Group`] implementation for Pallas, the curve of the Pasta cycle whose
+    /// scalar field is the base field of [`Vesta`].
+    ///
+    /// Proof systems over the Pasta cycle, like Halo 2, can handle elements of
+    /// this group with the curve arithmetic they already use, instead of
+    /// emulating a foreign curve in-circuit. [`PallasElem`] and [`PallasScalar`]
+    /// convert into the types of the [pasta_curves] crate for that purpose.
+    ///
+    /// Hashing to the curve follows the random-oracle encoding of RFC 9380 with
+    /// the simplified SWU map to the 3-isogenous curve, so with BLAKE2b-512 as
+    /// the hash it matches the `hash_to_curve` of the [pasta_curves] crate.
+    /// Neither group has been assigned a suite by the specification.
+    ///
+    /// [pasta_curves]: https://docs.rs/pasta_curves
+    Pallas,
+    PallasElem,
+    PallasScalar,
+    pallas,
+    0xFFFC,
+    pallas::Base::from_raw([
+        0x92bb_4b0b_657a_014b,
+        0xb741_3458_1a27_a59f,
+        0x49be_2d72_5837_0742,
+        0x1835_4a2e_b0ea_8c9c,
+    ])
+);
+
+impl_pasta!(
+    /// [`Group`] implementation for Vesta, the curve of the Pasta cycle whose
+    /// scalar field is the base field of [`Pallas`].
+    ///
+    /// See [`Pallas`] for the details, which apply to both curves.
+    Vesta,
+    VestaElem,
+    VestaScalar,
+    vesta,
+    0xFFFB,
+    vesta::Base::from_raw([
+        0xc515_ad72_42ea_a6b1,
+        0x9673_928c_7d01_b212,
+        0x8163_9c4d_96f7_8773,
+        0x267f_9b2e_e592_271a,
+    ])
+);
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use pasta_curves::arithmetic::CurveExt;
+
+    use super::*;
+
+    const INPUTS: [&[u8]; 3] = [b"", b"abc", &[0xFF; 200]];
+
+    // With BLAKE2b-512 the encoding matches the one of the `pasta_curves` crate
+    #[test]
+    fn test_hash_to_curve() -> Result<()> {
+        let hash_to_pallas = pallas::Point::hash_to_curve("voprf");
+        for input in INPUTS {
+            let elem = Pallas::hash_to_curve::<blake2::Blake2b512>(
+                &[input],
+                b"voprf-pallas_XMD:BLAKE2b_SSWU_RO_",
+            )
+            .unwrap();
+            assert!(!bool::from(Pallas::is_identity_elem(elem)));
+            assert_eq!(
+                pallas::Point::from(elem).to_bytes(),
+                hash_to_pallas(input).to_bytes()
+            );
+        }
+
+        let hash_to_vesta = vesta::Point::hash_to_curve("voprf");
+        for input in INPUTS {
+            let elem = Vesta::hash_to_curve::<blake2::Blake2b512>(
+                &[input],
+                b"voprf-vesta_XMD:BLAKE2b_SSWU_RO_",
+            )
+            .unwrap();
+            assert!(!bool::from(Vesta::is_identity_elem(elem)));
+            assert_eq!(
+                vesta::Point::from(elem).to_bytes(),
+                hash_to_vesta(input).to_bytes()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -15,10 +15,10 @@ use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, Expander};
 use generic_array::typenum::{IsLess, IsLessOrEqual, U256, U32, U64};
 use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
-use subtle::ConstantTimeEq;
+use subtle::{ConstantTimeEq, CtOption};
 
-use super::Group;
-use crate::{Error, InternalError, Result};
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
 
 /// [`Group`] implementation for Ristretto255.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -33,6 +33,8 @@ impl crate::CipherSuite for Ristretto255 {
     type Group = Ristretto255;
 
     type Hash = sha2::Sha512;
+
+    type FinalizeHash = sha2::Sha512;
 }
 
 // `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
@@ -84,6 +86,12 @@ impl Group for Ristretto255 {
         RistrettoPoint::identity()
     }
 
+    // Uses a precomputed table of odd multiples of the base point.
+    #[cfg(not(any(feature = "size-optimized", feature = "small-stack")))]
+    fn vartime_double_mul_base(a: Self::Scalar, elem: Self::Elem, b: Self::Scalar) -> Self::Elem {
+        RistrettoPoint::vartime_double_scalar_mul_basepoint(&a, &elem, &b)
+    }
+
     // serialization of a group element
     fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
         elem.compress().to_bytes().into()
@@ -91,13 +99,19 @@ impl Group for Ristretto255 {
 
     fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
         if element_bits.len() != 32 {
-            return Err(Error::Deserialization);
+            return Err(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ));
         }
 
         CompressedRistretto::from_slice(element_bits)
             .decompress()
-            .filter(|point| point != &RistrettoPoint::identity())
-            .ok_or(Error::Deserialization)
+            .filter(|point| ALLOW_IDENTITY || point != &RistrettoPoint::identity())
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ))
     }
 
     fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
@@ -110,8 +124,8 @@ impl Group for Ristretto255 {
         }
     }
 
-    fn invert_scalar(scalar: Self::Scalar) -> Self::Scalar {
-        scalar.invert()
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+        CtOption::new(scalar.invert(), !scalar.ct_eq(&Scalar::zero()))
     }
 
     fn is_zero_scalar(scalar: Self::Scalar) -> subtle::Choice {
@@ -133,6 +147,9 @@ impl Group for Ristretto255 {
             .ok()
             .and_then(Scalar::from_canonical_bytes)
             .filter(|scalar| scalar != &Scalar::zero())
-            .ok_or(Error::Deserialization)
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeScalar,
+            ))
     }
 }
@@ -0,0 +1,367 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+use digest::core_api::BlockSizeUser;
+use digest::Digest;
+use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, Expander};
+use generic_array::sequence::Split;
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256, U32, U33, U48, U96};
+use generic_array::GenericArray;
+use k256::elliptic_curve::bigint::U512;
+use k256::elliptic_curve::group::GroupEncoding;
+use k256::elliptic_curve::hash2curve::{FromOkm, GroupDigest, MapToCurve};
+#[cfg(not(any(feature = "size-optimized", feature = "small-stack")))]
+use k256::elliptic_curve::ops::LinearCombination;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::BatchNormalize;
+use k256::{ProjectivePoint, PublicKey, Scalar, SecretKey};
+use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, CtOption};
+
+use super::elliptic_curve::is_compressed;
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
+
+/// The byte length of a scalar hashed by `hash_to_field`, `L` in the
+/// hash-to-curve specification.
+const L: usize = 48;
+/// The number of elements [`Group::serialize_elem_batch`] normalizes at once,
+/// bounding the stack used for the affine points.
+const BATCH_NORMALIZE_SIZE: usize = 32;
+
+/// The base field of secp256k1.
+type FieldElement = <k256::Secp256k1 as GroupDigest>::FieldElement;
+
+/// [`Group`] implementation for secp256k1, the curve of Bitcoin and Ethereum,
+/// with the arithmetic of the [k256] crate.
+///
+/// Scalar multiplication, [`Group::vartime_double_mul_base`] and
+/// [`Group::vartime_double_mul`] go through the GLV endomorphism of k256, which
+/// splits every scalar into two half-length ones. All are constant-time.
+///
+/// Elements are encoded as compressed SEC1 points and scalars in big-endian, as
+/// for P-256. Hashing to the curve is `secp256k1_XMD:SHA-256_SSWU_RO_` of RFC
+/// 9380 and [`Group::hash_to_scalar`] reduces 48 bytes, as for P-256. k256
+/// doesn't implement the latter, so this group doesn't come from the generic
+/// implementation for `elliptic-curve` curves. The OPRF specification doesn't
+/// define a suite for secp256k1, as a [`CipherSuite`](crate::CipherSuite) it
+/// uses SHA-256 under an ID that isn't assigned by the specification.
+///
+/// [k256]: https://docs.rs/k256
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "secp256k1")]
+pub struct Secp256k1;
+
+impl crate::CipherSuite for Secp256k1 {
+    // Not assigned by the specification.
+    const ID: u16 = 0xFFF8;
+
+    type Group = Secp256k1;
+
+    type Hash = sha2::Sha256;
+
+    type FinalizeHash = sha2::Sha256;
+}
+
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "secp256k1")]
+impl Group for Secp256k1 {
+    type Elem = ProjectivePoint;
+
+    type ElemLen = U33;
+
+    type Scalar = Scalar;
+
+    type ScalarLen = U32;
+
+    // Implements `hash_to_curve()` from
+    // https://www.rfc-editor.org/rfc/rfc9380.html#section-3 with the simplified
+    // SWU map to a 3-isogenous curve. The cofactor is 1, so clearing it is a
+    // no-op. The bytes are expanded here instead of in k256, whose version of
+    // `elliptic-curve` requires more of `H`.
+    fn hash_to_curve<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Elem, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = GenericArray::<u8, U96>::default();
+        ExpandMsgXmd::<H>::expand_message(input, dst, U96::USIZE)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+        let (u0, u1): (GenericArray<u8, U48>, GenericArray<u8, U48>) = uniform_bytes.split();
+
+        Ok(FieldElement::from_okm(&u0).map_to_curve() + FieldElement::from_okm(&u1).map_to_curve())
+    }
+
+    // Implements the `HashToScalar()` function from
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html#section-4.1
+    fn hash_to_scalar<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Scalar, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut wide = GenericArray::default();
+        let uniform_bytes = wide.get_mut(64 - L..).ok_or(InternalError::Input)?;
+        ExpandMsgXmd::<H>::expand_message(input, dst, L)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(uniform_bytes);
+
+        Ok(<Scalar as Reduce<U512>>::reduce_bytes(&wide))
+    }
+
+    fn base_elem() -> Self::Elem {
+        ProjectivePoint::GENERATOR
+    }
+
+    fn identity_elem() -> Self::Elem {
+        ProjectivePoint::IDENTITY
+    }
+
+    fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
+        elem.to_affine().to_bytes()
+    }
+
+    // Converts the elements to affine coordinates with `BatchNormalize`, which
+    // shares a single field inversion of the `Z` coordinates across a batch
+    // with Montgomery's trick, instead of one inversion per element. The last
+    // batch is padded with the identity, which costs a few multiplications per
+    // element.
+    fn serialize_elem_batch(elems: &[Self::Elem], output: &mut [GenericArray<u8, Self::ElemLen>]) {
+        for (elems, output) in elems
+            .chunks(BATCH_NORMALIZE_SIZE)
+            .zip(output.chunks_mut(BATCH_NORMALIZE_SIZE))
+        {
+            // A single element isn't worth the padding
+            if let ([elem], [output]) = (elems, &mut *output) {
+                *output = Self::serialize_elem(*elem);
+                continue;
+            }
+
+            let mut batch = [ProjectivePoint::IDENTITY; BATCH_NORMALIZE_SIZE];
+            batch
+                .iter_mut()
+                .zip(elems)
+                .for_each(|(batch, elem)| *batch = *elem);
+
+            for (output, affine) in
+                output
+                    .iter_mut()
+                    .zip(<ProjectivePoint as BatchNormalize<_>>::batch_normalize(
+                        &batch,
+                    ))
+            {
+                *output = affine.to_bytes();
+            }
+        }
+    }
+
+    // Interleaves the multiplications with an extra table of both elements.
+    #[cfg(not(any(feature = "size-optimized", feature = "small-stack")))]
+    fn vartime_double_mul_base(a: Self::Scalar, elem: Self::Elem, b: Self::Scalar) -> Self::Elem {
+        ProjectivePoint::lincomb(&elem, &a, &ProjectivePoint::GENERATOR, &b)
+    }
+
+    #[cfg(not(any(feature = "size-optimized", feature = "small-stack")))]
+    fn vartime_double_mul(
+        a: Self::Scalar,
+        x: Self::Elem,
+        b: Self::Scalar,
+        y: Self::Elem,
+    ) -> Self::Elem {
+        ProjectivePoint::lincomb(&x, &a, &y, &b)
+    }
+
+    fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
+        // The identity is serialized as a single zero byte, padded with zeros
+        if ALLOW_IDENTITY
+            && element_bits.len() == Self::ElemLen::USIZE
+            && element_bits.iter().all(|byte| *byte == 0)
+        {
+            return Ok(Self::identity_elem());
+        }
+
+        if !is_compressed(element_bits, Self::ElemLen::USIZE) {
+            return Err(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ));
+        }
+
+        PublicKey::from_sec1_bytes(element_bits)
+            .map(|public_key| public_key.to_projective())
+            .map_err(|_| Error::new(ErrorKind::Deserialization, Operation::DeserializeElem))
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+        *SecretKey::random(rng).to_nonzero_scalar()
+    }
+
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+        scalar.invert()
+    }
+
+    fn is_zero_scalar(scalar: Self::Scalar) -> Choice {
+        scalar.is_zero()
+    }
+
+    #[cfg(test)]
+    fn zero_scalar() -> Self::Scalar {
+        Scalar::ZERO
+    }
+
+    fn serialize_scalar(scalar: Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        scalar.into()
+    }
+
+    fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar> {
+        GenericArray::from_exact_iter(scalar_bits.iter().copied())
+            .and_then(|bytes| SecretKey::from_bytes(&bytes).ok())
+            .map(|secret_key| *secret_key.to_nonzero_scalar())
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeScalar,
+            ))
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+
+    // The test vectors of `secp256k1_XMD:SHA-256_SSWU_RO_` in
+    // https://www.rfc-editor.org/rfc/rfc9380.html#appendix-J.8.1, compressed
+    const DST: &[u8] = b"QUUX-V01-CS02-with-secp256k1_XMD:SHA-256_SSWU_RO_";
+    const VECTORS: [(&str, &str); 5] = [
+        (
+            "",
+            "03c1cae290e291aee617ebaef1be6d73861479c48b841eaba9b7b5852ddfeb1346",
+        ),
+        (
+            "abc",
+            "023377e01eab42db296b512293120c6cee72b6ecf9f9205760bd9ff11fb3cb2c4b",
+        ),
+        (
+            "abcdef0123456789",
+            "02bac54083f293f1fe08e4a70137260aa90783a5cb84d3f35848b324d0674b0e3a",
+        ),
+        (
+            "q128_",
+            "03e2167bc785333a37aa562f021f1e881defb853839babf52a7f72b102e41890e9",
+        ),
+        (
+            "a512_",
+            "02e3c8d35aaaf0b9b647e88a0a0a7ee5d5bed5ad38238152e4e6fd8c1f8cb7c998",
+        ),
+    ];
+    // Computed with Python's `hashlib`: `expand_message_xmd` of RFC 9380 with
+    // SHA-256 to 48 bytes, reduced modulo the order of the group
+    const SCALAR_DST: &[u8] = b"HashToScalar-QUUX-V01-CS02-with-secp256k1_XMD:SHA-256";
+    const SCALAR_VECTORS: [(&str, &str); 3] = [
+        (
+            "",
+            "9dd12fca6c967fd4388664d72c38ba20261449874eff215c86e3d0906036cbd4",
+        ),
+        (
+            "abc",
+            "352fe84ded632de56ddd97f6a06a3b3fae8857626663894001a6d0414ed8c493",
+        ),
+        (
+            "abcdef0123456789",
+            "f5a0488ed006886cb1ad6cb66ee0856af0ac4879d4587022e6f68e67fae99918",
+        ),
+    ];
+    // The scalars `d` and the compressed encodings of `d * G` of SEC 2
+    const MUL_VECTORS: [(&str, &str); 4] = [
+        (
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+        ),
+        (
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+            "0379be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        ),
+    ];
+
+    // Expands the prefixes of the long messages of RFC 9380 like in its test
+    // vectors
+    fn message(prefix: &str) -> Vec<u8> {
+        let mut message = prefix.as_bytes().to_vec();
+        match prefix {
+            "q128_" => message.extend_from_slice(&[b'q'; 128]),
+            "a512_" => message.extend_from_slice(&[b'a'; 512]),
+            _ => (),
+        }
+        message
+    }
+
+    fn encoding(elem: ProjectivePoint) -> String {
+        hex::encode(Secp256k1::serialize_elem(elem))
+    }
+
+    #[test]
+    fn test_hash_to_curve() -> Result<()> {
+        for (prefix, expected) in VECTORS {
+            let elem = Secp256k1::hash_to_curve::<sha2::Sha256>(&[&message(prefix)], DST).unwrap();
+            assert_eq!(encoding(elem), expected);
+        }
+
+        for (input, expected) in SCALAR_VECTORS {
+            let scalar =
+                Secp256k1::hash_to_scalar::<sha2::Sha256>(&[input.as_bytes()], SCALAR_DST).unwrap();
+            assert_eq!(hex::encode(Secp256k1::serialize_scalar(scalar)), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        for (d, expected) in MUL_VECTORS {
+            let scalar = Secp256k1::deserialize_scalar(&hex::decode(d).unwrap())?;
+            assert_eq!(hex::encode(Secp256k1::serialize_scalar(scalar)), d);
+
+            let elem = Secp256k1::base_elem() * scalar;
+            assert_eq!(encoding(elem), expected);
+
+            // Decoding recovers the same point
+            let decoded = Secp256k1::deserialize_elem(&hex::decode(expected).unwrap())?;
+            assert!(bool::from(decoded.ct_eq(&elem)));
+            assert_eq!(encoding(decoded), expected);
+        }
+
+        // Only the compressed encoding is canonical
+        let mut compact = Secp256k1::serialize_elem(Secp256k1::base_elem());
+        compact[0] = 0x05;
+        assert!(Secp256k1::deserialize_elem(&compact).is_err());
+
+        // The order of the group is out of range
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+            .unwrap();
+        assert!(Secp256k1::deserialize_scalar(&order).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,478 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+use digest::core_api::BlockSizeUser;
+use digest::Digest;
+use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, Expander};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256, U32, U33};
+use generic_array::GenericArray;
+use primeorder::PrimeCurveParams;
+use rand_core::{CryptoRng, RngCore};
+use sm2_::elliptic_curve::ff::PrimeField;
+use sm2_::elliptic_curve::group::GroupEncoding;
+use sm2_::elliptic_curve::sec1::FromEncodedPoint;
+use sm2_::elliptic_curve::BatchNormalize;
+use sm2_::{
+    AffinePoint, EncodedPoint, FieldBytes, NonZeroScalar, ProjectivePoint, PublicKey, Scalar,
+};
+use subtle::{Choice, ConditionallySelectable, CtOption};
+
+use super::elliptic_curve::is_compressed;
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
+
+/// The byte length of a field element hashed by `hash_to_field`, `L` in the
+/// hash-to-curve specification.
+const L: usize = 48;
+/// The absolute value of the non-square `Z = -9` of the simplified SWU map.
+const MINUS_Z: u64 = 9;
+/// The number of elements [`Group::serialize_elem_batch`] normalizes at once,
+/// bounding the stack used for the affine points.
+const BATCH_NORMALIZE_SIZE: usize = 32;
+
+/// The base field of SM2.
+type FieldElement = <sm2_::Sm2 as PrimeCurveParams>::FieldElement;
+
+/// [`Group`] implementation for the SM2 curve of GB/T 32918, with the
+/// arithmetic of the RustCrypto [sm2] crate.
+///
+/// Elements are encoded as compressed SEC1 points and scalars in big-endian, as
+/// for P-256. Hashing to the curve follows the random-oracle encoding of RFC
+/// 9380 with the simplified SWU map and `Z = -9`, though neither the
+/// hash-to-curve nor the OPRF specification define a suite for SM2. sm2 doesn't
+/// implement it, so the map is implemented here with the constant-time field
+/// operations of sm2. As a [`CipherSuite`](crate::CipherSuite) it uses SM3 of
+/// GB/T 32905 as the hash function, under an ID that isn't assigned by the
+/// specification.
+///
+/// [sm2]: https://docs.rs/sm2
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "sm2")]
+pub struct Sm2;
+
+/// Reduces big-endian `bytes` modulo the order of `F`, 16 bytes at a time, so
+/// every chunk is in range.
+fn from_uniform_bytes<F: PrimeField<Repr = FieldBytes>>(bytes: &[u8]) -> F {
+    let shift = F::from_u128(u128::MAX) + F::ONE;
+
+    bytes.chunks(16).fold(F::ZERO, |result, chunk| {
+        let mut repr = FieldBytes::default();
+        repr.iter_mut()
+            .rev()
+            .zip(chunk.iter().rev())
+            .for_each(|(repr, byte)| *repr = *byte);
+
+        result * shift + F::from_repr(repr).unwrap_or(F::ZERO)
+    })
+}
+
+/// Computes `x^3 + a * x + b`, the right-hand side of the curve equation.
+fn curve_rhs(x: FieldElement) -> FieldElement {
+    (x.square() + sm2_::Sm2::EQUATION_A) * x + sm2_::Sm2::EQUATION_B
+}
+
+// Implements `map_to_curve_simple_swu()` from
+// https://www.rfc-editor.org/rfc/rfc9380.html#section-6.6.2 in constant-time.
+fn map_to_curve(u: FieldElement) -> ProjectivePoint {
+    let zero = FieldElement::ZERO;
+    let a = sm2_::Sm2::EQUATION_A;
+    let b = sm2_::Sm2::EQUATION_B;
+    let z = -FieldElement::from(MINUS_Z);
+    let a_inverse = a.invert().unwrap_or(zero);
+
+    let z_u2 = z * u.square();
+    let tv1 = z_u2.square() + z_u2;
+    let x1 = -b * a_inverse * (FieldElement::ONE + tv1.invert().unwrap_or(zero));
+    let exceptional_x1 = b * a_inverse * z.invert().unwrap_or(zero);
+    let x1 = FieldElement::conditional_select(&x1, &exceptional_x1, tv1.is_zero());
+    let x2 = z_u2 * x1;
+    let y1 = curve_rhs(x1).sqrt();
+    let y2 = curve_rhs(x2).sqrt().unwrap_or(zero);
+
+    let is_square = y1.is_some();
+    let x = FieldElement::conditional_select(&x2, &x1, is_square);
+    let y = FieldElement::conditional_select(&y2, &y1.unwrap_or(zero), is_square);
+    let y = FieldElement::conditional_select(&y, &-y, u.is_odd() ^ y.is_odd());
+
+    // `(x, y)` is on the curve, decoding it only checks that again
+    let point = EncodedPoint::from_affine_coordinates(&x.to_repr(), &y.to_repr(), false);
+    AffinePoint::from_encoded_point(&point)
+        .unwrap_or(AffinePoint::IDENTITY)
+        .into()
+}
+
+/// Decodes a scalar and checks that it is in range.
+fn decode_scalar(scalar_bits: &[u8]) -> Option<Scalar> {
+    GenericArray::from_exact_iter(scalar_bits.iter().copied())
+        .and_then(|repr| Option::from(Scalar::from_repr(repr)))
+}
+
+impl crate::CipherSuite for Sm2 {
+    // Not assigned by the specification.
+    const ID: u16 = 0xFFFA;
+
+    type Group = Sm2;
+
+    type Hash = sm3::Sm3;
+
+    type FinalizeHash = sm3::Sm3;
+}
+
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "sm2")]
+impl Group for Sm2 {
+    type Elem = ProjectivePoint;
+
+    type ElemLen = U33;
+
+    type Scalar = Scalar;
+
+    type ScalarLen = U32;
+
+    // Implements `hash_to_curve()` from
+    // https://www.rfc-editor.org/rfc/rfc9380.html#section-3 with the simplified
+    // SWU map. The cofactor is 1, so clearing it is a no-op.
+    fn hash_to_curve<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Elem, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = [0; 2 * L];
+        ExpandMsgXmd::<H>::expand_message(input, dst, 2 * L)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+        let (u0, u1) = uniform_bytes.split_at(L);
+
+        Ok(map_to_curve(from_uniform_bytes(u0)) + map_to_curve(from_uniform_bytes(u1)))
+    }
+
+    // Implements the `HashToScalar()` function from
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html#section-4.1
+    fn hash_to_scalar<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Scalar, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = [0; L];
+        ExpandMsgXmd::<H>::expand_message(input, dst, L)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+
+        Ok(from_uniform_bytes(&uniform_bytes))
+    }
+
+    fn base_elem() -> Self::Elem {
+        ProjectivePoint::GENERATOR
+    }
+
+    fn identity_elem() -> Self::Elem {
+        ProjectivePoint::IDENTITY
+    }
+
+    fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
+        elem.to_affine().to_bytes()
+    }
+
+    // Converts the elements to affine coordinates with `BatchNormalize`, which
+    // shares a single field inversion of the `Z` coordinates across a batch
+    // with Montgomery's trick, instead of one inversion per element. The last
+    // batch is padded with the identity, which costs a few multiplications per
+    // element.
+    fn serialize_elem_batch(elems: &[Self::Elem], output: &mut [GenericArray<u8, Self::ElemLen>]) {
+        for (elems, output) in elems
+            .chunks(BATCH_NORMALIZE_SIZE)
+            .zip(output.chunks_mut(BATCH_NORMALIZE_SIZE))
+        {
+            // A single element isn't worth the padding
+            if let ([elem], [output]) = (elems, &mut *output) {
+                *output = Self::serialize_elem(*elem);
+                continue;
+            }
+
+            let mut batch = [ProjectivePoint::IDENTITY; BATCH_NORMALIZE_SIZE];
+            batch
+                .iter_mut()
+                .zip(elems)
+                .for_each(|(batch, elem)| *batch = *elem);
+
+            for (output, affine) in
+                output
+                    .iter_mut()
+                    .zip(<ProjectivePoint as BatchNormalize<_>>::batch_normalize(
+                        &batch,
+                    ))
+            {
+                *output = affine.to_bytes();
+            }
+        }
+    }
+
+    fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
+        // The identity is serialized as zeros
+        if ALLOW_IDENTITY && element_bits.len() == 33 && element_bits.iter().all(|byte| *byte == 0)
+        {
+            return Ok(Self::identity_elem());
+        }
+
+        if !is_compressed(element_bits, 33) {
+            return Err(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ));
+        }
+
+        PublicKey::from_sec1_bytes(element_bits)
+            .map(|public_key| public_key.to_projective())
+            .map_err(|_| Error::new(ErrorKind::Deserialization, Operation::DeserializeElem))
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+        *NonZeroScalar::random(rng)
+    }
+
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+        scalar.invert()
+    }
+
+    fn is_zero_scalar(scalar: Self::Scalar) -> Choice {
+        scalar.is_zero()
+    }
+
+    #[cfg(test)]
+    fn zero_scalar() -> Self::Scalar {
+        Scalar::ZERO
+    }
+
+    fn serialize_scalar(scalar: Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        scalar.to_repr()
+    }
+
+    fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar> {
+        decode_scalar(scalar_bits)
+            .filter(|scalar| !bool::from(scalar.is_zero()))
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeScalar,
+            ))
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+
+    // Computed with `scripts/sm2_hash_to_curve.py`, an independent
+    // implementation of RFC 9380 which derives `Z` itself and reproduces the
+    // P-256 test vectors of the specification
+    const DST: &[u8] = b"QUUX-V01-CS02-with-SM2_XMD:SHA-256_SSWU_RO_";
+    const VECTORS: [(&str, &str); 5] = [
+        (
+            "",
+            "0230f5cb893085362e6c082492f7b57683ea3a5dd876fbddda0b7cd4c4b8c2bd5b",
+        ),
+        (
+            "abc",
+            "02b91a67d5fc0ad6b34c07d20e59ab17c5646d987a3477c010e7ebb82f58ccf6c8",
+        ),
+        (
+            "abcdef0123456789",
+            "030e91868d8a8e3c443e1efb06118c97239f1b9e1cd3488baaab0ad2b40ca43071",
+        ),
+        (
+            "q128_",
+            "03afd474bcf916eb272d9eb01168d486e2375b8aeaffea17b9b5eba90b063e06c3",
+        ),
+        (
+            "a512_",
+            "028ab8005661c4cbef9863456a5bcb272e1797447e7443ab33a4fc0fcc35d4cbd0",
+        ),
+    ];
+    // Computed the same way, with SM3 instead of SHA-256
+    const SM3_DST: &[u8] = b"QUUX-V01-CS02-with-SM2_XMD:SM3_SSWU_RO_";
+    const SM3_VECTORS: [(&str, &str); 5] = [
+        (
+            "",
+            "0380048bf6454de460598966bc3bc9a3213e8776668817d85cf447eda370991a41",
+        ),
+        (
+            "abc",
+            "037cf8871dffcb584997d9b27cbc1b12308eec4544f38688f7b8c53531afb9fdcd",
+        ),
+        (
+            "abcdef0123456789",
+            "039fbfac2f80e2492165c664f1329a2e8391d39ec33e6c7a57c0e582d17e533c0e",
+        ),
+        (
+            "q128_",
+            "037eccdb5a62d795ff497c6f24ba10049945a384df187717667deddeea465cd927",
+        ),
+        (
+            "a512_",
+            "02ac24c8657b4e116c8b5a92136d41947839e5a61fdab3ac1529d2fbd9b9959691",
+        ),
+    ];
+    // Computed with version 0.13 of the RustCrypto `sm2` crate before this
+    // group was built on it, so they guard the encodings and against changes of
+    // the arithmetic: the scalars `d` and the compressed encodings of `d * G`
+    const MUL_VECTORS: [(&str, &str); 6] = [
+        (
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0232c4ae2c1f1981195f9904466a39c9948fe30bbff2660be1715a4589334c74c7",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "0356cefd60d7c87c000d58ef57fa73ba4d9c0dfa08c08a7331495c2e1da3f2bd52",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "02a97f7cd4b3c993b4be2daa8cdb41e24ca13f6bd945302244e26918f1d0509ebf",
+        ),
+        (
+            "8b53639f152c8fc6ef30802fde462ba0be9cf085f7580dc69efd72e002abbb35",
+            "039535e1fe8258235ac17c856a8d2b1a56fead9f7d689abf4716b967a6a6a25a9e",
+        ),
+        (
+            "e788103ee15318fcd2af9b73b4ebbb33a903b020de7b307d71f5fed0f433e548",
+            "0390784e2b19a0e360e0151469442d6adcf41a86ce935928997f85d870f44f9355",
+        ),
+        (
+            "fffffffeffffffffffffffffffffffff7203df6b21c6052b53bbf40939d54122",
+            "0332c4ae2c1f1981195f9904466a39c9948fe30bbff2660be1715a4589334c74c7",
+        ),
+    ];
+    // Computed the same way for `P = d1 * G` and `Q = d2 * G` of the fourth and
+    // fifth vector above
+    const P_PLUS_Q: &str = "036c3e9631778543402bc7ef564a83b56cabf4e882b673cd387f0f4cb314b2f6f5";
+    const P_DOUBLED: &str = "02cfa03fe98a2991faa758a4b92f0832bead274d1f7782721897c9ba2236ae0c6f";
+    const D1_TIMES_D2: &str = "9232cf57e3864077ae89a3f591a9de62fa204182767b6fa5b6998b876633d032";
+    const D1_PLUS_D2: &str = "72db73def67fa8c3c1e01ba39331e6d4f59cc13bb40d3918bd377da7bd0a5f5a";
+    const D1_INVERSE: &str = "9c10939e07b34e6a53a576d8c6206ab8ba20954f27b658f54eb7ee037d1a0cbe";
+    // The x-coordinate of the generator, in big-endian
+    const GENERATOR_X: [u8; 32] = [
+        0x32, 0xc4, 0xae, 0x2c, 0x1f, 0x19, 0x81, 0x19, 0x5f, 0x99, 0x04, 0x46, 0x6a, 0x39, 0xc9,
+        0x94, 0x8f, 0xe3, 0x0b, 0xbf, 0xf2, 0x66, 0x0b, 0xe1, 0x71, 0x5a, 0x45, 0x89, 0x33, 0x4c,
+        0x74, 0xc7,
+    ];
+    // The order of the group minus one, in big-endian
+    const ORDER_MINUS_ONE: [u8; 32] = [
+        0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x72, 0x03, 0xdf, 0x6b, 0x21, 0xc6, 0x05, 0x2b, 0x53, 0xbb, 0xf4, 0x09, 0x39, 0xd5,
+        0x41, 0x22,
+    ];
+
+    // Expands the prefixes of the long messages of RFC 9380 like in its test
+    // vectors
+    fn message(prefix: &str) -> Vec<u8> {
+        let mut message = prefix.as_bytes().to_vec();
+        match prefix {
+            "q128_" => message.extend_from_slice(&[b'q'; 128]),
+            "a512_" => message.extend_from_slice(&[b'a'; 512]),
+            _ => (),
+        }
+        message
+    }
+
+    fn scalar(hex: &str) -> Result<Scalar> {
+        Sm2::deserialize_scalar(&hex::decode(hex).unwrap())
+    }
+
+    fn encoding(elem: ProjectivePoint) -> String {
+        hex::encode(Sm2::serialize_elem(elem))
+    }
+
+    #[test]
+    fn test_hash_to_curve() -> Result<()> {
+        for (prefix, expected) in VECTORS {
+            let elem = Sm2::hash_to_curve::<sha2::Sha256>(&[&message(prefix)], DST).unwrap();
+            assert_eq!(encoding(elem), expected);
+        }
+
+        for (prefix, expected) in SM3_VECTORS {
+            let elem = Sm2::hash_to_curve::<sm3::Sm3>(&[&message(prefix)], SM3_DST).unwrap();
+            assert_eq!(encoding(elem), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_known_answers() -> Result<()> {
+        for (d, expected) in MUL_VECTORS {
+            let elem = Sm2::base_elem() * scalar(d)?;
+            assert_eq!(encoding(elem), expected);
+
+            // Decoding recovers the same point
+            let decoded = Sm2::deserialize_elem(&hex::decode(expected).unwrap())?;
+            assert!(bool::from(decoded.ct_eq(&elem)));
+            assert_eq!(encoding(decoded), expected);
+        }
+
+        let (d1, p) = MUL_VECTORS[3];
+        let (d2, q) = MUL_VECTORS[4];
+        let p = Sm2::deserialize_elem(&hex::decode(p).unwrap())?;
+        let q = Sm2::deserialize_elem(&hex::decode(q).unwrap())?;
+        assert_eq!(encoding(p + q), P_PLUS_Q);
+        assert_eq!(encoding(p + p), P_DOUBLED);
+
+        let (d1, d2) = (scalar(d1)?, scalar(d2)?);
+        assert_eq!(hex::encode(Sm2::serialize_scalar(d1 * d2)), D1_TIMES_D2);
+        assert_eq!(hex::encode(Sm2::serialize_scalar(d1 + d2)), D1_PLUS_D2);
+        let inverse = Option::<Scalar>::from(Sm2::invert_scalar_ct(d1)).unwrap();
+        assert_eq!(hex::encode(Sm2::serialize_scalar(inverse)), D1_INVERSE);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic() -> Result<()> {
+        let generator = Sm2::base_elem();
+        let encoded = Sm2::serialize_elem(generator);
+        assert_eq!(encoded[0], 0x02);
+        assert_eq!(encoded[1..], GENERATOR_X);
+        assert!(bool::from(
+            Sm2::deserialize_elem(&encoded)?.ct_eq(&generator)
+        ));
+
+        // `(n - 1) * G = -G`, so adding `G` results in the identity
+        let minus_one = Sm2::deserialize_scalar(&ORDER_MINUS_ONE)?;
+        let minus_generator = generator * minus_one;
+        let encoded = Sm2::serialize_elem(minus_generator);
+        assert_eq!(encoded[0], 0x03);
+        assert_eq!(encoded[1..], GENERATOR_X);
+        assert!(bool::from(Sm2::is_identity_elem(
+            minus_generator + generator
+        )));
+
+        // Doubling agrees with multiplying by two
+        let two = Scalar::from(2u64);
+        assert!(bool::from(
+            (generator + generator).ct_eq(&(generator * two))
+        ));
+        assert!(bool::from((minus_one * minus_one).ct_eq(&Scalar::ONE)));
+
+        // Values of at least the modulus are rejected
+        let mut order = ORDER_MINUS_ONE;
+        order[31] += 1;
+        assert!(Sm2::deserialize_scalar(&order).is_err());
+        let mut modulus = [0xff; 33];
+        modulus[0] = 0x02;
+        assert!(Sm2::deserialize_elem(&modulus).is_err());
+
+        Ok(())
+    }
+}
@@ -7,7 +7,14 @@
 
 //! Includes a series of tests for the group implementations
 
-use crate::{Error, Group, Result};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use generic_array::GenericArray;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use super::ALLOW_IDENTITY;
+use crate::{Error, ErrorKind, Group, Operation, Result};
 
 // Test that the deserialization of a group element should throw an error if the
 // identity element can be deserialized properly
@@ -22,19 +29,155 @@ fn test_group_properties() -> Result<()> {
 
         test_identity_element_error::<Ristretto255>()?;
         test_zero_scalar_error::<Ristretto255>()?;
+        test_scalar_inversion::<Ristretto255>()?;
+        test_conditional_selection::<Ristretto255>()?;
+        test_batch_serialization::<Ristretto255>()?;
+        test_vartime_double_mul_base::<Ristretto255>()?;
     }
 
     test_identity_element_error::<NistP256>()?;
     test_zero_scalar_error::<NistP256>()?;
+    test_scalar_inversion::<NistP256>()?;
+    test_conditional_selection::<NistP256>()?;
+    test_batch_serialization::<NistP256>()?;
+    test_vartime_double_mul_base::<NistP256>()?;
+
+    // Only the compressed encoding of NIST curve elements is canonical, the
+    // compact and uncompressed ones are rejected
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+    let scalar = NistP256::random_scalar(&mut rand::rngs::OsRng);
+    let element = NistP256::base_elem() * scalar;
+    let mut compact = NistP256::serialize_elem(element);
+    compact[0] = 0x05;
+    let uncompressed = element.to_affine().to_encoded_point(false);
+    for bytes in [compact.as_slice(), uncompressed.as_bytes()] {
+        assert!(matches!(
+            NistP256::deserialize_elem(bytes),
+            Err(error) if error == Error::new(ErrorKind::Deserialization, Operation::DeserializeElem)
+        ));
+    }
+
+    #[cfg(feature = "bls12_381")]
+    {
+        use crate::Bls12_381;
+
+        test_identity_element_error::<Bls12_381>()?;
+        test_zero_scalar_error::<Bls12_381>()?;
+        test_scalar_inversion::<Bls12_381>()?;
+        test_conditional_selection::<Bls12_381>()?;
+    }
+
+    #[cfg(feature = "jubjub")]
+    {
+        use crate::Jubjub;
+
+        test_identity_element_error::<Jubjub>()?;
+        test_zero_scalar_error::<Jubjub>()?;
+        test_scalar_inversion::<Jubjub>()?;
+        test_conditional_selection::<Jubjub>()?;
+    }
+
+    #[cfg(feature = "pasta")]
+    {
+        use crate::{Pallas, Vesta};
+
+        test_identity_element_error::<Pallas>()?;
+        test_zero_scalar_error::<Pallas>()?;
+        test_scalar_inversion::<Pallas>()?;
+        test_conditional_selection::<Pallas>()?;
+
+        test_identity_element_error::<Vesta>()?;
+        test_zero_scalar_error::<Vesta>()?;
+        test_scalar_inversion::<Vesta>()?;
+        test_conditional_selection::<Vesta>()?;
+    }
+
+    #[cfg(feature = "secp256k1")]
+    {
+        use crate::Secp256k1;
+
+        test_identity_element_error::<Secp256k1>()?;
+        test_zero_scalar_error::<Secp256k1>()?;
+        test_scalar_inversion::<Secp256k1>()?;
+        test_conditional_selection::<Secp256k1>()?;
+        test_batch_serialization::<Secp256k1>()?;
+        test_vartime_double_mul_base::<Secp256k1>()?;
+    }
+
+    #[cfg(feature = "sm2")]
+    {
+        use crate::Sm2;
+
+        test_identity_element_error::<Sm2>()?;
+        test_zero_scalar_error::<Sm2>()?;
+        test_scalar_inversion::<Sm2>()?;
+        test_conditional_selection::<Sm2>()?;
+    }
+
+    #[cfg(feature = "toy-group")]
+    {
+        use crate::ToyGroup;
+
+        test_identity_element_error::<ToyGroup>()?;
+        test_zero_scalar_error::<ToyGroup>()?;
+        test_scalar_inversion::<ToyGroup>()?;
+        test_conditional_selection::<ToyGroup>()?;
+    }
 
     Ok(())
 }
 
-// Checks that the identity element cannot be deserialized
+// Checks every element and scalar of the toy group
+#[cfg(feature = "toy-group")]
+#[test]
+fn test_toy_group_exhaustive() -> Result<()> {
+    use crate::{check_conformance, ToyGroup};
+
+    let mut elements = 0;
+    for (element, scalar) in ToyGroup::elements().zip(ToyGroup::scalars()) {
+        assert!(bool::from(
+            (ToyGroup::base_elem() * &scalar).ct_eq(&element)
+        ));
+
+        let deserialized = ToyGroup::deserialize_elem(&ToyGroup::serialize_elem(element))?;
+        assert!(bool::from(deserialized.ct_eq(&element)));
+        let deserialized = ToyGroup::deserialize_scalar(&ToyGroup::serialize_scalar(scalar))?;
+        assert!(bool::from(deserialized.ct_eq(&scalar)));
+
+        let inverse =
+            Option::<<ToyGroup as Group>::Scalar>::from(ToyGroup::invert_scalar_ct(scalar))
+                .unwrap();
+        assert_eq!((scalar * &inverse).value(), 1);
+
+        elements += 1;
+    }
+    assert_eq!(elements, 1018);
+
+    // Values outside of the group are rejected, the identity unless allowed
+    let rejected = (0..2039u16)
+        .filter(|value| ToyGroup::deserialize_elem(&value.to_be_bytes()).is_err())
+        .count();
+    assert_eq!(rejected, 2039 - 1018 - usize::from(ALLOW_IDENTITY));
+    assert!(ToyGroup::deserialize_scalar(&1019u16.to_be_bytes()).is_err());
+
+    check_conformance::<ToyGroup>()
+}
+
+// Checks that the identity element cannot be deserialized, unless the
+// `danger-allow-identity` feature is enabled
 fn test_identity_element_error<G: Group>() -> Result<()> {
     let identity = G::identity_elem();
     let result = G::deserialize_elem(&G::serialize_elem(identity));
-    assert!(matches!(result, Err(Error::Deserialization)));
+
+    if ALLOW_IDENTITY {
+        assert!(bool::from(G::is_identity_elem(result?)));
+    } else {
+        assert!(matches!(
+            result,
+            Err(error) if error == Error::new(ErrorKind::Deserialization, Operation::DeserializeElem)
+        ));
+    }
 
     Ok(())
 }
@@ -43,7 +186,94 @@ fn test_identity_element_error<G: Group>() -> Result<()> {
 fn test_zero_scalar_error<G: Group>() -> Result<()> {
     let zero_scalar = G::zero_scalar();
     let result = G::deserialize_scalar(&G::serialize_scalar(zero_scalar));
-    assert!(matches!(result, Err(Error::Deserialization)));
+    assert!(matches!(
+        result,
+        Err(error) if error == Error::new(ErrorKind::Deserialization, Operation::DeserializeScalar)
+    ));
+
+    Ok(())
+}
+
+// Checks that scalars are inverted and the zero scalar has no inverse
+fn test_scalar_inversion<G: Group>() -> Result<()> {
+    let scalar = G::random_scalar(&mut rand::rngs::OsRng);
+    let inverse = Option::<G::Scalar>::from(G::invert_scalar_ct(scalar)).unwrap();
+    let one = scalar * &inverse;
+    let element = G::base_elem() * &one;
+    assert!(bool::from(element.ct_eq(&G::base_elem())));
+
+    assert!(bool::from(G::invert_scalar_ct(G::zero_scalar()).is_none()));
+
+    Ok(())
+}
+
+// Checks that elements and scalars are selected by the `Choice`
+fn test_conditional_selection<G: Group>() -> Result<()> {
+    let scalar = G::random_scalar(&mut rand::rngs::OsRng);
+    let element = G::base_elem() * &scalar;
+
+    let selected = G::Scalar::conditional_select(&scalar, &G::zero_scalar(), Choice::from(0));
+    assert!(bool::from(selected.ct_eq(&scalar)));
+    let selected = G::Scalar::conditional_select(&scalar, &G::zero_scalar(), Choice::from(1));
+    assert!(bool::from(G::is_zero_scalar(selected)));
+
+    let selected = G::Elem::conditional_select(&element, &G::base_elem(), Choice::from(0));
+    assert!(bool::from(selected.ct_eq(&element)));
+    let selected = G::Elem::conditional_select(&element, &G::base_elem(), Choice::from(1));
+    assert!(bool::from(selected.ct_eq(&G::base_elem())));
+
+    Ok(())
+}
+
+// Checks that elements are serialized in a batch as they are one by one,
+// across several chunks and with the identity in between
+fn test_batch_serialization<G: Group>() -> Result<()> {
+    let elems: Vec<_> = (0..70)
+        .map(|index| match index {
+            42 => G::identity_elem(),
+            _ => G::base_elem() * &G::random_scalar(&mut rand::rngs::OsRng),
+        })
+        .collect();
+    let mut output = vec![GenericArray::default(); elems.len()];
+    G::serialize_elem_batch(&elems, &mut output);
+
+    for (elem, output) in elems.iter().zip(&output) {
+        assert_eq!(&G::serialize_elem(*elem), output);
+    }
+
+    // Only as many elements as fit are serialized
+    let mut output = vec![GenericArray::default(); 3];
+    G::serialize_elem_batch(&elems, &mut output);
+    assert_eq!(output[2], G::serialize_elem(elems[2]));
+
+    Ok(())
+}
+
+// Checks that the variable-time double-scalar multiplications match the
+// constant-time ones, including for the identity and zero scalars
+fn test_vartime_double_mul_base<G: Group>() -> Result<()> {
+    let mut rng = rand::rngs::OsRng;
+    let elem = G::base_elem() * &G::random_scalar(&mut rng);
+
+    for (a, elem, b) in [
+        (G::random_scalar(&mut rng), elem, G::random_scalar(&mut rng)),
+        (
+            G::random_scalar(&mut rng),
+            G::identity_elem(),
+            G::random_scalar(&mut rng),
+        ),
+        (G::zero_scalar(), elem, G::random_scalar(&mut rng)),
+        (G::random_scalar(&mut rng), elem, G::zero_scalar()),
+    ] {
+        assert_eq!(
+            G::serialize_elem(G::vartime_double_mul_base(a, elem, b)),
+            G::serialize_elem((elem * &a) + &(G::base_elem() * &b))
+        );
+        assert_eq!(
+            G::serialize_elem(G::vartime_double_mul(b, G::base_elem(), a, elem)),
+            G::serialize_elem((elem * &a) + &(G::base_elem() * &b))
+        );
+    }
 
     Ok(())
 }
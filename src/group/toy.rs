@@ -0,0 +1,330 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+use core::ops::{Add, Mul, Sub};
+
+use digest::core_api::BlockSizeUser;
+use digest::Digest;
+use elliptic_curve::hash2curve::{ExpandMsg, ExpandMsgXmd, Expander};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U2, U256};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+use zeroize::Zeroize;
+
+use super::{Group, ALLOW_IDENTITY};
+use crate::{Error, ErrorKind, InternalError, Operation, Result};
+
+/// The safe prime `p = 2 * q + 1` of the base field.
+const P: u32 = 2039;
+/// The prime order `q` of the group.
+const Q: u32 = 1019;
+/// The generator of the group, the square of `2`.
+const G: u32 = 4;
+
+/// **INSECURE** [`Group`] implementation over the quadratic residues modulo the
+/// prime 2039, a group of prime order 1019.
+///
+/// The discrete logarithm in this group can be computed instantly, so it
+/// provides no security whatsoever and must never be used in a real
+/// deployment. It exists so property tests can exhaustively enumerate all
+/// [`ToyElem`]s and [`ToyScalar`]s, and so examples can show the algebra with
+/// small numbers. Though written additively by [`Group`], the group operation
+/// is multiplication modulo 2039:
+///
+/// ```
+/// use voprf::{Group, ToyGroup, ToyScalar};
+///
+/// let three = ToyScalar::from(3);
+/// // 4^3 mod 2039
+/// assert_eq!((ToyGroup::base_elem() * &three).value(), 64);
+/// // 4 * 4 mod 2039
+/// assert_eq!((ToyGroup::base_elem() + &ToyGroup::base_elem()).value(), 16);
+/// // 3^-1 mod 1019
+/// let inverse = Option::<ToyScalar>::from(ToyGroup::invert_scalar_ct(three)).unwrap();
+/// assert_eq!(inverse.value(), 340);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "toy-group")]
+pub struct ToyGroup;
+
+/// An element of the [`ToyGroup`], a quadratic residue modulo 2039.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ToyElem(u16);
+
+/// A scalar of the [`ToyGroup`], an integer modulo 1019.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ToyScalar(u16);
+
+/// Computes `base^exponent mod modulus`.
+fn pow_mod(base: u32, mut exponent: u32, modulus: u32) -> u32 {
+    let mut base = base % modulus;
+    let mut result = 1;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % modulus;
+        }
+
+        base = base * base % modulus;
+        exponent >>= 1;
+    }
+
+    result
+}
+
+/// Reduces an arbitrary value modulo a modulus that fits into a [`u16`].
+fn reduce(value: u64, modulus: u32) -> u16 {
+    // The result is smaller then the modulus, which fits into a `u16`.
+    (value % u64::from(modulus)) as u16
+}
+
+impl ToyGroup {
+    /// Returns every [`ToyElem`] except the identity element, in the order of
+    /// their discrete logarithm, starting with the [`Group::base_elem`].
+    pub fn elements() -> impl Iterator<Item = ToyElem> {
+        (1..Q).map(|exponent| ToyElem(pow_mod(G, exponent, P) as u16))
+    }
+
+    /// Returns every [`ToyScalar`] except zero, in ascending order.
+    pub fn scalars() -> impl Iterator<Item = ToyScalar> {
+        (1..Q).map(|scalar| ToyScalar(scalar as u16))
+    }
+}
+
+impl ToyElem {
+    /// Returns the integer modulo 2039 representing this element.
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl ToyScalar {
+    /// Returns the integer modulo 1019 representing this scalar.
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl crate::CipherSuite for ToyGroup {
+    // Not assigned by the specification, the group isn't a valid choice.
+    const ID: u16 = 0xFFFE;
+
+    type Group = ToyGroup;
+
+    type Hash = sha2::Sha256;
+
+    type FinalizeHash = sha2::Sha256;
+}
+
+// `cfg` here is only needed because of a bug in Rust's crate feature documentation. See: https://github.com/rust-lang/rust/issues/83428
+#[cfg(feature = "toy-group")]
+impl Group for ToyGroup {
+    type Elem = ToyElem;
+
+    type ElemLen = U2;
+
+    type Scalar = ToyScalar;
+
+    type ScalarLen = U2;
+
+    // Squares a hashed element of the base field, which always results in a
+    // quadratic residue.
+    fn hash_to_curve<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Elem, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = [0; 8];
+        ExpandMsgXmd::<H>::expand_message(input, dst, 8)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+
+        let element = u32::from(reduce(u64::from_be_bytes(uniform_bytes), P - 1)) + 1;
+
+        Ok(ToyElem((element * element % P) as u16))
+    }
+
+    fn hash_to_scalar<H>(input: &[&[u8]], dst: &[u8]) -> Result<Self::Scalar, InternalError>
+    where
+        H: Digest + BlockSizeUser,
+        H::OutputSize: IsLess<U256> + IsLessOrEqual<H::BlockSize>,
+    {
+        let mut uniform_bytes = [0; 8];
+        ExpandMsgXmd::<H>::expand_message(input, dst, 8)
+            .map_err(|_| InternalError::Input)?
+            .fill_bytes(&mut uniform_bytes);
+
+        Ok(ToyScalar(reduce(u64::from_be_bytes(uniform_bytes), Q)))
+    }
+
+    fn base_elem() -> Self::Elem {
+        ToyElem(G as u16)
+    }
+
+    fn identity_elem() -> Self::Elem {
+        ToyElem(1)
+    }
+
+    fn serialize_elem(elem: Self::Elem) -> GenericArray<u8, Self::ElemLen> {
+        elem.0.to_be_bytes().into()
+    }
+
+    fn deserialize_elem(element_bits: &[u8]) -> Result<Self::Elem> {
+        element_bits
+            .try_into()
+            .ok()
+            .map(u16::from_be_bytes)
+            .filter(|&element| {
+                let element = u32::from(element);
+                (element > 1 || (ALLOW_IDENTITY && element == 1))
+                    && element < P
+                    && pow_mod(element, Q, P) == 1
+            })
+            .map(ToyElem)
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ))
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+        loop {
+            // Rejection sampling from the next power of two
+            let scalar = rng.next_u32() & 0x3FF;
+
+            if scalar != 0 && scalar < Q {
+                break ToyScalar(scalar as u16);
+            }
+        }
+    }
+
+    fn invert_scalar_ct(scalar: Self::Scalar) -> CtOption<Self::Scalar> {
+        CtOption::new(
+            ToyScalar(pow_mod(scalar.0.into(), Q - 2, Q) as u16),
+            !Self::is_zero_scalar(scalar),
+        )
+    }
+
+    fn is_zero_scalar(scalar: Self::Scalar) -> Choice {
+        scalar.0.ct_eq(&0)
+    }
+
+    #[cfg(test)]
+    fn zero_scalar() -> Self::Scalar {
+        ToyScalar(0)
+    }
+
+    fn serialize_scalar(scalar: Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        scalar.0.to_be_bytes().into()
+    }
+
+    fn deserialize_scalar(scalar_bits: &[u8]) -> Result<Self::Scalar> {
+        scalar_bits
+            .try_into()
+            .ok()
+            .map(u16::from_be_bytes)
+            .filter(|&scalar| scalar != 0 && u32::from(scalar) < Q)
+            .map(ToyScalar)
+            .ok_or(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeScalar,
+            ))
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl From<u16> for ToyScalar {
+    /// Reduces the integer modulo 1019.
+    fn from(value: u16) -> Self {
+        ToyScalar(reduce(value.into(), Q))
+    }
+}
+
+impl Add<&ToyElem> for ToyElem {
+    type Output = ToyElem;
+
+    fn add(self, rhs: &ToyElem) -> ToyElem {
+        ToyElem((u32::from(self.0) * u32::from(rhs.0) % P) as u16)
+    }
+}
+
+impl Mul<&ToyScalar> for ToyElem {
+    type Output = ToyElem;
+
+    fn mul(self, rhs: &ToyScalar) -> ToyElem {
+        ToyElem(pow_mod(self.0.into(), rhs.0.into(), P) as u16)
+    }
+}
+
+impl ConstantTimeEq for ToyElem {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl Zeroize for ToyElem {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Add<&ToyScalar> for ToyScalar {
+    type Output = ToyScalar;
+
+    fn add(self, rhs: &ToyScalar) -> ToyScalar {
+        ToyScalar(reduce(u64::from(self.0) + u64::from(rhs.0), Q))
+    }
+}
+
+impl Sub<&ToyScalar> for ToyScalar {
+    type Output = ToyScalar;
+
+    fn sub(self, rhs: &ToyScalar) -> ToyScalar {
+        ToyScalar(reduce(
+            u64::from(self.0) + u64::from(Q) - u64::from(rhs.0),
+            Q,
+        ))
+    }
+}
+
+impl Mul<&ToyScalar> for ToyScalar {
+    type Output = ToyScalar;
+
+    fn mul(self, rhs: &ToyScalar) -> ToyScalar {
+        ToyScalar(reduce(u64::from(self.0) * u64::from(rhs.0), Q))
+    }
+}
+
+impl ConditionallySelectable for ToyElem {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        ToyElem(u16::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConditionallySelectable for ToyScalar {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        ToyScalar(u16::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for ToyScalar {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl Zeroize for ToyScalar {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
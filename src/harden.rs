@@ -0,0 +1,322 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the memory-hard post-processing of outputs with Argon2id
+
+use alloc::vec::Vec;
+
+use argon2_::{Algorithm, Argon2, Params, Version};
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+
+use crate::common::create_context_string;
+use crate::{
+    CipherSuite, Error, ErrorKind, EvaluationElement, Group, Mode, Operation, OprfClient,
+    PoprfClient, Proof, Result, VoprfClient,
+};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_ARGON2ID: [u8; 9] = *b"Argon2id-";
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> OprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`OprfClient::finalize`], but runs the output through
+    /// [`harden_output`] with the given `salt` and `params`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::Hardening`] if the `salt` or `params` are rejected by
+    ///   Argon2id.
+    pub fn finalize_hardened(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        salt: &[u8],
+        params: Params,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let output = self.finalize(input, evaluation_element)?;
+        harden_output::<CS>(&output, Mode::Oprf, salt, params)
+    }
+}
+
+impl<CS: CipherSuite> VoprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`VoprfClient::finalize`], but runs the output through
+    /// [`harden_output`] with the given `salt` and `params`. The `proof` is
+    /// verified before the comparatively expensive hardening.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Hardening`] if the `salt` or `params` are rejected by
+    ///   Argon2id.
+    pub fn finalize_hardened(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+        salt: &[u8],
+        params: Params,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let output = self.finalize(input, evaluation_element, proof, pk)?;
+        harden_output::<CS>(&output, Mode::Voprf, salt, params)
+    }
+}
+
+impl<CS: CipherSuite> PoprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`PoprfClient::finalize`], but runs the output through
+    /// [`harden_output`] with the given `salt` and `params`. The `proof` is
+    /// verified before the comparatively expensive hardening.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer than
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Hardening`] if the `salt` or `params` are rejected by
+    ///   Argon2id.
+    #[allow(clippy::too_many_arguments)]
+    pub fn finalize_hardened(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+        salt: &[u8],
+        params: Params,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let output = self.finalize(input, evaluation_element, proof, pk, info)?;
+        harden_output::<CS>(&output, Mode::Poprf, salt, params)
+    }
+}
+
+/// Runs an `output` of the given `mode` through Argon2id, for deployments that
+/// want memory-hard outputs on top of the oblivious evaluation, e.g. so a
+/// compromised client database can't be used to cheaply test password
+/// guesses.
+///
+/// The output is the Argon2id password, the salt is
+///
+/// ```text
+/// "Argon2id-" || contextString || salt
+/// ```
+///
+/// where `contextString` separates the modes and ciphersuites, as in the rest
+/// of the protocol. The caller supplies the `salt`, e.g. a user identifier,
+/// which may be empty, and the memory, iteration and parallelism costs in
+/// `params`. The hardened output has the same length as the `output`, so the
+/// `output_len` of the `params` must be unset or match
+/// [`OutputSize`](OutputSizeUser::OutputSize).
+///
+/// Every client and server of a deployment has to use the same `salt` and
+/// `params`, e.g. the server can harden the result of
+/// [`VoprfServer::evaluate`](crate::VoprfServer::evaluate) to compare it with
+/// the hardened output of a client.
+///
+/// # Errors
+/// [`ErrorKind::Hardening`] if the `salt` or `params` are rejected by
+/// Argon2id.
+pub fn harden_output<CS: CipherSuite>(
+    output: &Output<CS::FinalizeHash>,
+    mode: Mode,
+    salt: &[u8],
+    params: Params,
+) -> Result<Output<CS::FinalizeHash>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let context_string = create_context_string::<CS>(mode);
+    let mut hardened_salt =
+        Vec::with_capacity(STR_ARGON2ID.len() + context_string.len() + salt.len());
+    hardened_salt.extend_from_slice(&STR_ARGON2ID);
+    hardened_salt.extend_from_slice(&context_string);
+    hardened_salt.extend_from_slice(salt);
+
+    let mut hardened_output = Output::<CS::FinalizeHash>::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(output, &hardened_salt, &mut hardened_output)
+        .map_err(|_| Error::new(ErrorKind::Hardening, Operation::HardenOutput))?;
+
+    Ok(hardened_output)
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{OprfServer, PoprfServer, VoprfServer};
+
+    /// Small costs, to keep the tests fast
+    fn params() -> Params {
+        Params::new(Params::MIN_M_COST, 1, 1, None).unwrap()
+    }
+
+    fn hardened_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let salt = b"user";
+        let mut rng = OsRng;
+
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = OprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let message = server.blind_evaluate(&client_blind_result.message);
+        let output = client_blind_result.state.finalize(input, &message).unwrap();
+        let hardened = client_blind_result
+            .state
+            .finalize_hardened(input, &message, salt, params())
+            .unwrap();
+        assert_eq!(
+            hardened,
+            harden_output::<CS>(&server.evaluate(input).unwrap(), Mode::Oprf, salt, params())
+                .unwrap()
+        );
+        assert_ne!(hardened, output);
+
+        // The mode, salt and costs are all separated
+        assert_ne!(
+            hardened,
+            harden_output::<CS>(&output, Mode::Voprf, salt, params()).unwrap()
+        );
+        assert_ne!(
+            hardened,
+            harden_output::<CS>(&output, Mode::Oprf, b"other", params()).unwrap()
+        );
+        assert_ne!(
+            hardened,
+            harden_output::<CS>(
+                &output,
+                Mode::Oprf,
+                salt,
+                Params::new(Params::MIN_M_COST, 2, 1, None).unwrap()
+            )
+            .unwrap()
+        );
+        // An empty salt is fine, the prefix is long enough for Argon2id
+        assert!(harden_output::<CS>(&output, Mode::Oprf, &[], params()).is_ok());
+
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let result = server.blind_evaluate(&mut rng, &client_blind_result.message);
+        let hardened = client_blind_result
+            .state
+            .finalize_hardened(
+                input,
+                &result.message,
+                &result.proof,
+                server.get_public_key(),
+                salt,
+                params(),
+            )
+            .unwrap();
+        assert_eq!(
+            hardened,
+            harden_output::<CS>(
+                &server.evaluate(input).unwrap(),
+                Mode::Voprf,
+                salt,
+                params()
+            )
+            .unwrap()
+        );
+
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let result = server
+            .blind_evaluate(&mut rng, &client_blind_result.message, Some(b"info"))
+            .unwrap();
+        let hardened = client_blind_result
+            .state
+            .finalize_hardened(
+                input,
+                &result.message,
+                &result.proof,
+                server.get_public_key(),
+                Some(b"info"),
+                salt,
+                params(),
+            )
+            .unwrap();
+        assert_eq!(
+            hardened,
+            harden_output::<CS>(
+                &server.evaluate(input, Some(b"info")).unwrap(),
+                Mode::Poprf,
+                salt,
+                params()
+            )
+            .unwrap()
+        );
+    }
+
+    fn hardening_errors<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let output = Output::<CS::FinalizeHash>::default();
+        let params = Params::new(Params::MIN_M_COST, 1, 1, Some(4)).unwrap();
+
+        let result = harden_output::<CS>(&output, Mode::Oprf, &[], params);
+        assert!(matches!(
+            result,
+            Err(e) if e == Error::new(ErrorKind::Hardening, Operation::HardenOutput)
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            hardened_retrieval::<Ristretto255>();
+            hardening_errors::<Ristretto255>();
+        }
+
+        hardened_retrieval::<NistP256>();
+        hardening_errors::<NistP256>();
+
+        Ok(())
+    }
+}
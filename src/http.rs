@@ -0,0 +1,446 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the encoding of messages for HTTP headers
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Add;
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, Sum, U16, U2, U256};
+use generic_array::{ArrayLength, GenericArray};
+use zeroize::Zeroize;
+
+use crate::{
+    BlindedElement, ChunkedProof, CipherSuite, Error, ErrorKind, EvaluationElement, Group,
+    KeyCommitment, KeyCommitmentLen, KeyPossessionProof, KeyPossessionProofLen, Operation, Proof,
+    ProofLen, Result,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A message that can be carried in an HTTP header value, as the unpadded
+/// base64url encoding of its serialization, like Privacy Pass does.
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::{BlindedElement, HttpMessage, VoprfClient};
+///
+/// let client_blind_result = VoprfClient::<CipherSuite>::blind(b"input", &mut OsRng).unwrap();
+/// let header = client_blind_result.message.to_header_value();
+/// let message = BlindedElement::<CipherSuite>::from_header_value(&header).unwrap();
+/// ```
+pub trait HttpMessage: Sized {
+    /// Encodes the serialized message as unpadded base64url.
+    fn to_header_value(&self) -> String;
+
+    /// Decodes a message from the output of
+    /// [`HttpMessage::to_header_value`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if `value` isn't valid unpadded
+    /// base64url or failed to deserialize.
+    fn from_header_value(value: &str) -> Result<Self>;
+
+    /// Encodes a batch of messages like [`HttpMessage::to_header_value`] does
+    /// for each of them. Messages override it to serialize the batch at once.
+    fn to_header_values(messages: &[Self]) -> Vec<String> {
+        messages.iter().map(Self::to_header_value).collect()
+    }
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+/// Encodes `input` as base64url without padding, see
+/// [RFC 4648](https://www.rfc-editor.org/rfc/rfc4648#section-5). Runs in
+/// constant time with respect to the bytes of `input`, so it can be used on
+/// secrets.
+pub fn encode_base64url(input: &[u8]) -> String {
+    let mut output = String::with_capacity((input.len() * 4 + 2) / 3);
+
+    for chunk in input.chunks(3) {
+        let mut bytes = [0; 4];
+
+        for (byte, value) in bytes.iter_mut().skip(1).zip(chunk) {
+            *byte = *value;
+        }
+
+        let group = u32::from_be_bytes(bytes);
+
+        for index in 0..=chunk.len() {
+            let sextet = (group >> (18 - 6 * index)) & 0x3F;
+            output.push(char::from(encode_base64url_sextet(sextet)));
+        }
+    }
+
+    output
+}
+
+/// Decodes base64url without padding, see
+/// [RFC 4648](https://www.rfc-editor.org/rfc/rfc4648#section-5). Padding,
+/// whitespace and non-canonical trailing bits are rejected. Runs in constant
+/// time with respect to the characters of `input`, only its length and whether
+/// it is valid are leaked.
+///
+/// # Errors
+/// [`ErrorKind::Deserialization`] if `input` isn't valid unpadded base64url.
+pub fn decode_base64url(input: &str) -> Result<Vec<u8>> {
+    let error = Error::new(ErrorKind::Deserialization, Operation::DecodeBase64Url);
+
+    let input = input.as_bytes();
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut invalid = 0;
+
+    for chunk in input.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(error);
+        }
+
+        let mut group = 0u32;
+
+        for (index, &character) in chunk.iter().enumerate() {
+            let sextet = decode_base64url_character(character);
+            invalid |= sextet >> 8;
+            group |= (sextet & 0x3F) << (18 - 6 * index);
+        }
+
+        let bytes = group.to_be_bytes();
+        let (bytes, trailing) = bytes.split_at(chunk.len());
+
+        // The first byte is always zero, the bits after the last full byte have
+        // to be as well.
+        for &byte in trailing {
+            invalid |= u32::from(byte);
+        }
+
+        output.extend(bytes.iter().skip(1));
+    }
+
+    if invalid != 0 {
+        output.zeroize();
+        return Err(error);
+    }
+
+    Ok(output)
+}
+
+/// Encodes a batch of messages as a comma-separated list of header values.
+pub fn batch_to_header_value<M: HttpMessage>(messages: &[M]) -> String {
+    M::to_header_values(messages).join(",")
+}
+
+/// Decodes a batch of messages from the output of [`batch_to_header_value`].
+/// Whitespace around the items of the list is ignored.
+///
+/// # Errors
+/// [`ErrorKind::Deserialization`] if any item isn't valid unpadded base64url
+/// or failed to deserialize. The error carries the index of the item.
+pub fn batch_from_header_value<M: HttpMessage>(value: &str) -> Result<Vec<M>> {
+    if value.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    value
+        .split(',')
+        .enumerate()
+        .map(|(index, item)| {
+            M::from_header_value(item.trim()).map_err(|error| error.with_index(index))
+        })
+        .collect()
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<CS: CipherSuite> HttpMessage for BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn to_header_value(&self) -> String {
+        encode_base64url(&self.serialize())
+    }
+
+    fn from_header_value(value: &str) -> Result<Self> {
+        Self::deserialize(&decode_base64url(value)?)
+    }
+}
+
+impl<CS: CipherSuite> HttpMessage for EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn to_header_value(&self) -> String {
+        encode_base64url(&self.serialize())
+    }
+
+    fn from_header_value(value: &str) -> Result<Self> {
+        Self::deserialize(&decode_base64url(value)?)
+    }
+
+    fn to_header_values(messages: &[Self]) -> Vec<String> {
+        let mut serialized = alloc::vec![GenericArray::default(); messages.len()];
+        Self::serialize_batch(messages, &mut serialized);
+
+        serialized
+            .iter()
+            .map(|bytes| encode_base64url(bytes))
+            .collect()
+    }
+}
+
+impl<CS: CipherSuite> HttpMessage for Proof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    ProofLen<CS>: ArrayLength<u8>,
+{
+    fn to_header_value(&self) -> String {
+        encode_base64url(&self.serialize())
+    }
+
+    fn from_header_value(value: &str) -> Result<Self> {
+        Self::deserialize(&decode_base64url(value)?)
+    }
+}
+
+impl<CS: CipherSuite> HttpMessage for ChunkedProof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    ProofLen<CS>: ArrayLength<u8>,
+{
+    fn to_header_value(&self) -> String {
+        encode_base64url(&self.serialize())
+    }
+
+    fn from_header_value(value: &str) -> Result<Self> {
+        Self::deserialize(&decode_base64url(value)?)
+    }
+}
+
+impl<CS: CipherSuite> HttpMessage for KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    U2: Add<<CS::Group as Group>::ElemLen>,
+    Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+    Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+        ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+    KeyCommitmentLen<CS>: ArrayLength<u8>,
+{
+    fn to_header_value(&self) -> String {
+        encode_base64url(&self.serialize())
+    }
+
+    fn from_header_value(value: &str) -> Result<Self> {
+        Self::deserialize(&decode_base64url(value)?)
+    }
+}
+
+impl<CS: CipherSuite> HttpMessage for KeyPossessionProof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    KeyPossessionProofLen<CS>: ArrayLength<u8>,
+{
+    fn to_header_value(&self) -> String {
+        encode_base64url(&self.serialize())
+    }
+
+    fn from_header_value(value: &str) -> Result<Self> {
+        Self::deserialize(&decode_base64url(value)?)
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+/// Maps a sextet to its character without a table lookup or a branch: the
+/// offset from `A` is corrected by each range of the alphabet the sextet lies
+/// in or beyond.
+fn encode_base64url_sextet(sextet: u32) -> u8 {
+    // Is all ones if `sextet >= bound`, zero otherwise
+    let at_least =
+        |bound: u32| 0u32.wrapping_sub((bound.wrapping_sub(1).wrapping_sub(sextet) >> 8) & 1);
+
+    let mut character = sextet.wrapping_add(u32::from(b'A'));
+    character = character.wrapping_add(at_least(26) & 6);
+    character = character.wrapping_add(at_least(52) & 0u32.wrapping_sub(75));
+    character = character.wrapping_add(at_least(62) & 0u32.wrapping_sub(13));
+    character = character.wrapping_add(at_least(63) & 49);
+
+    character.to_le_bytes()[0]
+}
+
+/// Maps a character to its sextet without a table lookup or a branch, or to a
+/// value with bits above the sextet set if it isn't in the alphabet. Each range
+/// of the alphabet contributes the sextet plus one if the character lies in it.
+fn decode_base64url_character(character: u8) -> u32 {
+    let character = u32::from(character);
+    // Is all ones if `low <= character <= high`, zero otherwise
+    let within = |low: u32, high: u32| {
+        0u32.wrapping_sub(
+            ((low.wrapping_sub(1).wrapping_sub(character) & character.wrapping_sub(high + 1))
+                >> 31)
+                & 1,
+        )
+    };
+
+    let mut sextet = 0u32.wrapping_sub(1);
+    sextet = sextet.wrapping_add(within(0x41, 0x5A) & character.wrapping_sub(0x41 - 1));
+    sextet = sextet.wrapping_add(within(0x61, 0x7A) & character.wrapping_sub(0x61 - 27));
+    sextet = sextet.wrapping_add(within(0x30, 0x39) & character.wrapping_add(52 + 1 - 0x30));
+    sextet = sextet.wrapping_add(within(0x2D, 0x2D) & 63);
+    sextet = sextet.wrapping_add(within(0x5F, 0x5F) & 64);
+
+    sextet
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec};
+
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{VoprfClient, VoprfServer};
+
+    #[test]
+    fn test_base64url() -> Result<()> {
+        // Test vectors from RFC 4648, without padding
+        for (input, output) in [
+            ("", ""),
+            ("f", "Zg"),
+            ("fo", "Zm8"),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg"),
+            ("fooba", "Zm9vYmE"),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode_base64url(input.as_bytes()), output);
+            assert_eq!(decode_base64url(output)?, input.as_bytes());
+        }
+
+        // The URL-safe alphabet is used
+        assert_eq!(encode_base64url(&[0xFB, 0xFF]), "-_8");
+        assert_eq!(decode_base64url("-_8")?, vec![0xFB, 0xFF]);
+
+        let error = Error::new(ErrorKind::Deserialization, Operation::DecodeBase64Url);
+        for invalid in ["Zg==", "Z", "Zh", "Zm9", "+/8", "Zm 9v"] {
+            assert!(
+                matches!(decode_base64url(invalid), Err(e) if e == error),
+                "{}",
+                invalid
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_base64url_alphabet() {
+        let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+        for (sextet, &character) in (0..).zip(alphabet) {
+            assert_eq!(encode_base64url_sextet(sextet), character);
+        }
+
+        for character in 0..=u8::MAX {
+            let sextet = decode_base64url_character(character);
+
+            match alphabet.iter().position(|&c| c == character) {
+                Some(position) => assert_eq!(sextet, position as u32),
+                None => assert_ne!(sextet >> 8, 0, "{}", character),
+            }
+        }
+    }
+
+    fn header_round_trip<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+        ProofLen<CS>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let inputs = [b"first", b"input"];
+        let messages: Vec<_> = inputs
+            .iter()
+            .map(|input| VoprfClient::<CS>::blind(*input, &mut rng).unwrap().message)
+            .collect();
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let result = server.batch_blind_evaluate(&mut rng, &messages).unwrap();
+
+        let header = batch_to_header_value(&messages);
+        assert_eq!(header.matches(',').count(), 1);
+        let decoded: Vec<BlindedElement<CS>> = batch_from_header_value(&header).unwrap();
+        assert!(decoded == messages);
+        let decoded: Vec<BlindedElement<CS>> =
+            batch_from_header_value(&header.replace(',', ", ")).unwrap();
+        assert!(decoded == messages);
+        assert!(batch_from_header_value::<BlindedElement<CS>>("")
+            .unwrap()
+            .is_empty());
+
+        let decoded: Vec<EvaluationElement<CS>> =
+            batch_from_header_value(&batch_to_header_value(&result.messages)).unwrap();
+        assert!(decoded == result.messages);
+
+        let proof = Proof::<CS>::from_header_value(&result.proof.to_header_value()).unwrap();
+        assert_eq!(proof.serialize(), result.proof.serialize());
+        assert!(!result.proof.to_header_value().contains('='));
+
+        // The index of the invalid item is reported
+        let header = format!("{},AAAA", messages[0].to_header_value());
+        assert!(matches!(
+            batch_from_header_value::<BlindedElement<CS>>(&header),
+            Err(e) if e.kind() == ErrorKind::Deserialization && e.index() == Some(1)
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            header_round_trip::<Ristretto255>();
+        }
+
+        header_round_trip::<NistP256>();
+
+        Ok(())
+    }
+}
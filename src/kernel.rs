@@ -0,0 +1,569 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the side-effect-free core of the protocol
+//!
+//! Every function in here is a deterministic function of its arguments: none
+//! of them draws randomness, emits tracing events, allocates or panics. The
+//! protocol modes wrap them with the RNG, the hedging of nonces, the tracing
+//! and the batching, so this module alone carries the arithmetic and hashing
+//! of the specification and is intended to be extractable with
+//! [hax](https://github.com/cryspen/hax) to be checked against it:
+//!
+//! ```text
+//! cargo hax into -i '-** +voprf::kernel::** +voprf::common::create_context_string' fstar
+//! ```
+//!
+//! The extraction is not verified yet: the `hax` job of the CI attempts it with
+//! a pinned hax, but is allowed to fail until it has been seen to succeed. Keep
+//! new code in here within the subset of Rust supported by hax, i.e. no
+//! closures capturing state, `dyn` or interior mutability.
+
+use core::convert::TryFrom;
+
+use digest::core_api::BlockSizeUser;
+use digest::{Digest, Output, OutputSizeUser};
+use generic_array::sequence::Concat;
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use generic_array::GenericArray;
+use subtle::ConstantTimeEq;
+
+use crate::common::{
+    create_context_string, hash_to_group, i2osp_2, i2osp_2_array, invert_scalar, Mode, Proof,
+    STR_CHALLENGE, STR_COMPOSITE, STR_FINALIZE, STR_HASH_TO_SCALAR, STR_SEED,
+};
+use crate::{CipherSuite, Error, ErrorKind, Group, Operation, Result};
+
+/////////////////////
+// Protocol Kernel //
+// =============== //
+/////////////////////
+
+/// Blinds the `input` with the `blind`:
+///
+/// ```text
+/// blindedElement = G.HashToGroup(input) * blind
+/// ```
+///
+/// Does not check if the `blind` is non-zero.
+///
+/// Can only fail with [`ErrorKind::Input`].
+pub(crate) fn blind<CS: CipherSuite>(
+    input: &[u8],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Elem>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let hashed_point =
+        hash_to_group::<CS>(input, mode).map_err(|error| error.with_operation(Operation::Blind))?;
+    Ok(hashed_point * blind)
+}
+
+/// Evaluates a blinded element with the key `k`, which is the private key in
+/// the OPRF and VOPRF modes and the inverted tweaked key in the POPRF mode:
+///
+/// ```text
+/// evaluatedElement = blindedElement * k
+/// ```
+pub(crate) fn blind_evaluate<CS: CipherSuite>(
+    k: <CS::Group as Group>::Scalar,
+    blinded_element: <CS::Group as Group>::Elem,
+) -> <CS::Group as Group>::Elem
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    blinded_element * &k
+}
+
+/// Unblinds an evaluated element with the `blind`:
+///
+/// ```text
+/// unblindedElement = evaluatedElement * (blind^(-1))
+/// ```
+pub(crate) fn unblind<CS: CipherSuite>(
+    blind: <CS::Group as Group>::Scalar,
+    evaluated_element: <CS::Group as Group>::Elem,
+) -> <CS::Group as Group>::Elem
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    evaluated_element * &invert_scalar::<CS>(blind)
+}
+
+/// Hashes the `input`, the `info` of the POPRF mode and the unblinded element
+/// to the output of the protocol.
+///
+/// Can only fail with [`ErrorKind::Input`] or [`ErrorKind::Info`].
+pub(crate) fn finalize<CS: CipherSuite>(
+    input: &[u8],
+    info: Option<&[u8]>,
+    unblinded_element: <CS::Group as Group>::Elem,
+) -> Result<Output<CS::FinalizeHash>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // OPRF & VOPRF
+    // hashInput = I2OSP(len(input), 2) || input ||
+    //             I2OSP(len(unblindedElement), 2) || unblindedElement ||
+    //             "Finalize"
+    // return Hash(hashInput)
+    //
+    // POPRF
+    // hashInput = I2OSP(len(input), 2) || input ||
+    //             I2OSP(len(info), 2) || info ||
+    //             I2OSP(len(unblindedElement), 2) || unblindedElement ||
+    //             "Finalize"
+    let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
+
+    let mut hash = CS::FinalizeHash::new()
+        .chain_update(
+            i2osp_2(input.len()).map_err(|_| Error::new(ErrorKind::Input, Operation::Finalize))?,
+        )
+        .chain_update(input);
+    if let Some(info) = info {
+        hash = hash
+            .chain_update(
+                i2osp_2(info.len())
+                    .map_err(|_| Error::new(ErrorKind::Info, Operation::Finalize))?,
+            )
+            .chain_update(info);
+    }
+    Ok(hash
+        .chain_update(elem_len)
+        .chain_update(CS::Group::serialize_elem(unblinded_element))
+        .chain_update(STR_FINALIZE)
+        .finalize())
+}
+
+//////////////////
+// Proof Kernel //
+// ============ //
+//////////////////
+
+pub(crate) type ComputeCompositesResult<CS> = (
+    <<CS as CipherSuite>::Group as Group>::Elem,
+    <<CS as CipherSuite>::Group as Group>::Elem,
+);
+
+pub(crate) type CompositeScalarResult<CS> = Result<(
+    <<CS as CipherSuite>::Group as Group>::Scalar,
+    <<CS as CipherSuite>::Group as Group>::Elem,
+    <<CS as CipherSuite>::Group as Group>::Elem,
+)>;
+
+/// Computes the composites `M` and `Z` of a batch, with the fast path of the
+/// prover if `k_option` is given.
+///
+/// Can only fail with [`ErrorKind::Batch`].
+pub(crate) fn compute_composites<
+    CS: CipherSuite,
+    IC: Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
+    ID: Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
+>(
+    k_option: Option<<CS::Group as Group>::Scalar>,
+    b: <CS::Group as Group>::Elem,
+    c_slice: IC,
+    d_slice: ID,
+    mode: Mode,
+) -> Result<ComputeCompositesResult<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let seed = composite_seed::<CS>(&CS::Group::serialize_elem(b), mode);
+
+    compute_composites_with_seed::<CS, _, _>(k_option, &seed, c_slice, d_slice, mode)
+}
+
+/// Computes the seed of the composites for the serialized public key `bm`,
+/// which only depends on `bm` and the `mode`.
+pub(crate) fn composite_seed<CS: CipherSuite>(
+    bm: &GenericArray<u8, <CS::Group as Group>::ElemLen>,
+    mode: Mode,
+) -> Output<CS::Hash>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-11.html#section-2.2.1
+
+    let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
+
+    // seedDST = "Seed-" || contextString
+    let seed_dst = GenericArray::from(STR_SEED).concat(create_context_string::<CS>(mode));
+
+    // h1Input = I2OSP(len(Bm), 2) || Bm ||
+    //           I2OSP(len(seedDST), 2) || seedDST
+    // seed = Hash(h1Input)
+    CS::Hash::new()
+        .chain_update(elem_len)
+        .chain_update(bm)
+        .chain_update(i2osp_2_array(&seed_dst))
+        .chain_update(seed_dst)
+        .finalize()
+}
+
+/// Like [`compute_composites`], with the `seed` computed by
+/// [`composite_seed`].
+///
+/// Can only fail with [`ErrorKind::Batch`].
+pub(crate) fn compute_composites_with_seed<
+    CS: CipherSuite,
+    IC: Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
+    ID: Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
+>(
+    k_option: Option<<CS::Group as Group>::Scalar>,
+    seed: &Output<CS::Hash>,
+    c_slice: IC,
+    d_slice: ID,
+    mode: Mode,
+) -> Result<ComputeCompositesResult<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut m = CS::Group::identity_elem();
+    let mut z = CS::Group::identity_elem();
+
+    for result in composite_scalars::<CS, _, _>(seed, c_slice, d_slice, mode)? {
+        let (di, c, d) = result?;
+        m = c * &di + &m;
+        z = match k_option {
+            Some(_) => z,
+            None => d * &di + &z,
+        };
+    }
+
+    z = match k_option {
+        Some(k) => m * &k,
+        None => z,
+    };
+
+    Ok((m, z))
+}
+
+/// Returns the scalar `di` of the composites for every pair of `c_slice` and
+/// `d_slice`, together with the pair.
+///
+/// Can only fail with [`ErrorKind::Batch`], the items only with
+/// [`ErrorKind::Protocol`].
+pub(crate) fn composite_scalars<
+    'a,
+    CS: 'a + CipherSuite,
+    IC: 'a + Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
+    ID: 'a + Iterator<Item = <CS::Group as Group>::Elem> + ExactSizeIterator,
+>(
+    seed: &'a Output<CS::Hash>,
+    c_slice: IC,
+    d_slice: ID,
+    mode: Mode,
+) -> Result<impl 'a + Iterator<Item = CompositeScalarResult<CS>>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    if c_slice.len() != d_slice.len() {
+        return Err(ErrorKind::Batch.into());
+    }
+
+    let len = u16::try_from(c_slice.len()).map_err(|_| ErrorKind::Batch)?;
+
+    Ok((0..len)
+        .zip(c_slice.zip(d_slice))
+        .map(move |(i, (c, d))| composite_scalar::<CS>(seed, i, c, d, mode)))
+}
+
+/// Computes the scalar `di` of the composites for the pair `c` and `d` at index
+/// `i` of the batch.
+///
+/// Kept out of line with the `small-stack` feature, so the buffers of the hash
+/// don't share a frame with the scalar multiplications of the caller.
+///
+/// Can only fail with [`ErrorKind::Protocol`].
+#[cfg_attr(feature = "small-stack", inline(never))]
+fn composite_scalar<CS: CipherSuite>(
+    seed: &Output<CS::Hash>,
+    i: u16,
+    c: <CS::Group as Group>::Elem,
+    d: <CS::Group as Group>::Elem,
+    mode: Mode,
+) -> CompositeScalarResult<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
+    let seed_len = i2osp_2_array(seed);
+
+    // Ci = GG.SerializeElement(Cs[i])
+    let ci = CS::Group::serialize_elem(c);
+    // Di = GG.SerializeElement(Ds[i])
+    let di = CS::Group::serialize_elem(d);
+    // h2Input = I2OSP(len(seed), 2) || seed || I2OSP(i, 2) ||
+    //           I2OSP(len(Ci), 2) || Ci ||
+    //           I2OSP(len(Di), 2) || Di ||
+    //           "Composite"
+    let h2_input = [
+        seed_len.as_slice(),
+        seed,
+        &i.to_be_bytes(),
+        &elem_len,
+        &ci,
+        &elem_len,
+        &di,
+        &STR_COMPOSITE,
+    ];
+
+    let dst = GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string::<CS>(mode));
+    // This can't fail, the size of the `input` is known.
+    let di =
+        CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &dst).map_err(|_| ErrorKind::Protocol)?;
+
+    Ok((di, c, d))
+}
+
+/// Computes the proof over the composites `m` and `z` with the nonce `r`, which
+/// has to be drawn uniformly at random or hedged by the caller.
+///
+/// Can only fail with [`ErrorKind::Protocol`].
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn compute_proof<CS: CipherSuite>(
+    k: <CS::Group as Group>::Scalar,
+    r: <CS::Group as Group>::Scalar,
+    a: <CS::Group as Group>::Elem,
+    b: <CS::Group as Group>::Elem,
+    m: <CS::Group as Group>::Elem,
+    z: <CS::Group as Group>::Elem,
+    mode: Mode,
+) -> Result<Proof<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let t2 = a * &r;
+    let t3 = m * &r;
+
+    let c_scalar = compute_challenge::<CS>(&CS::Group::serialize_elem(b), m, z, t2, t3, mode)
+        .map_err(|error| error.with_operation(Operation::Evaluate))?;
+    let s_scalar = r - &(c_scalar * &k);
+
+    Ok(Proof { c_scalar, s_scalar })
+}
+
+/// Can only fail with [`ErrorKind::ProofVerification`] or [`ErrorKind::Batch`].
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn verify_proof<CS: CipherSuite>(
+    a: <CS::Group as Group>::Elem,
+    b: <CS::Group as Group>::Elem,
+    cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    proof: &Proof<CS>,
+    mode: Mode,
+) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // Bm = GG.SerializeElement(B)
+    let bm = CS::Group::serialize_elem(b);
+    let seed = composite_seed::<CS>(&bm, mode);
+
+    verify_proof_with_seed(a, b, &bm, &seed, cs, ds, proof, mode)
+}
+
+/// Like [`verify_proof`], with `bm` being the serialization of `b` and the
+/// `seed` computed by [`composite_seed`].
+///
+/// Can only fail with [`ErrorKind::ProofVerification`] or [`ErrorKind::Batch`].
+#[allow(clippy::many_single_char_names, clippy::too_many_arguments)]
+pub(crate) fn verify_proof_with_seed<CS: CipherSuite>(
+    a: <CS::Group as Group>::Elem,
+    b: <CS::Group as Group>::Elem,
+    bm: &GenericArray<u8, <CS::Group as Group>::ElemLen>,
+    seed: &Output<CS::Hash>,
+    cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    proof: &Proof<CS>,
+    mode: Mode,
+) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-11.html#section-2.2.2
+
+    let (m, z) = compute_composites_with_seed::<CS, _, _>(None, seed, cs, ds, mode)
+        .map_err(|error| error.with_operation(Operation::VerifyProof))?;
+    // All values are public, so `t2` can be computed in variable time when `a`
+    // is the base element, as it is for proofs of the server's key.
+    let t2 = if a.ct_eq(&CS::Group::base_elem()).into() {
+        CS::Group::vartime_double_mul_base(proof.c_scalar, b, proof.s_scalar)
+    } else {
+        CS::Group::vartime_double_mul(proof.s_scalar, a, proof.c_scalar, b)
+    };
+    let t3 = CS::Group::vartime_double_mul(proof.s_scalar, m, proof.c_scalar, z);
+
+    check_challenge(bm, m, z, t2, t3, proof, mode)
+}
+
+/// Checks that the challenge computed from `t2` and `t3` is the one of the
+/// `proof`.
+///
+/// Can only fail with [`ErrorKind::ProofVerification`].
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn check_challenge<CS: CipherSuite>(
+    bm: &GenericArray<u8, <CS::Group as Group>::ElemLen>,
+    m: <CS::Group as Group>::Elem,
+    z: <CS::Group as Group>::Elem,
+    t2: <CS::Group as Group>::Elem,
+    t3: <CS::Group as Group>::Elem,
+    proof: &Proof<CS>,
+    mode: Mode,
+) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let c = compute_challenge::<CS>(bm, m, z, t2, t3, mode)
+        .map_err(|error| error.with_operation(Operation::VerifyProof))?;
+
+    match c.ct_eq(&proof.c_scalar).into() {
+        true => Ok(()),
+        false => Err(Error::new(
+            ErrorKind::ProofVerification,
+            Operation::VerifyProof,
+        )),
+    }
+}
+
+/// Kept out of line with the `small-stack` feature, like [`composite_scalar`].
+///
+/// Can only fail with [`ErrorKind::Protocol`].
+#[cfg_attr(feature = "small-stack", inline(never))]
+fn compute_challenge<CS: CipherSuite>(
+    bm: &GenericArray<u8, <CS::Group as Group>::ElemLen>,
+    m: <CS::Group as Group>::Elem,
+    z: <CS::Group as Group>::Elem,
+    t2: <CS::Group as Group>::Elem,
+    t3: <CS::Group as Group>::Elem,
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // a0 = GG.SerializeElement(M)
+    let a0 = CS::Group::serialize_elem(m);
+    // a1 = GG.SerializeElement(Z)
+    let a1 = CS::Group::serialize_elem(z);
+    // a2 = GG.SerializeElement(t2)
+    let a2 = CS::Group::serialize_elem(t2);
+    // a3 = GG.SerializeElement(t3)
+    let a3 = CS::Group::serialize_elem(t3);
+
+    let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
+
+    // h2Input = I2OSP(len(Bm), 2) || Bm ||
+    //           I2OSP(len(a0), 2) || a0 ||
+    //           I2OSP(len(a1), 2) || a1 ||
+    //           I2OSP(len(a2), 2) || a2 ||
+    //           I2OSP(len(a3), 2) || a3 ||
+    //           "Challenge"
+    let h2_input = [
+        &elem_len,
+        bm.as_slice(),
+        &elem_len,
+        &a0,
+        &elem_len,
+        &a1,
+        &elem_len,
+        &a2,
+        &elem_len,
+        &a3,
+        &STR_CHALLENGE,
+    ];
+
+    let dst = GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string::<CS>(mode));
+    // This can't fail, the size of the `input` is known.
+    CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &dst).map_err(|_| ErrorKind::Protocol.into())
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use core::iter;
+
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{OprfServer, VoprfServer};
+
+    fn kernel_matches_modes<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let input = b"input";
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let r = CS::Group::random_scalar(&mut rng);
+
+        let blinded_element = blind::<CS>(input, &r, Mode::Oprf).unwrap();
+        let evaluated_element = blind_evaluate::<CS>(server.get_private_key(), blinded_element);
+        let output = finalize::<CS>(input, None, unblind::<CS>(r, evaluated_element)).unwrap();
+        assert_eq!(output, server.evaluate(input).unwrap());
+
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let k = server.get_private_key();
+        let a = CS::Group::base_elem();
+        let b = server.get_public_key();
+        let c = CS::Group::hash_to_curve::<CS::Hash>(&[input], b"dst").unwrap();
+        let d = blind_evaluate::<CS>(k, c);
+
+        let (m, z) =
+            compute_composites::<CS, _, _>(Some(k), b, iter::once(c), iter::once(d), Mode::Voprf)
+                .unwrap();
+        let proof = compute_proof::<CS>(k, r, a, b, m, z, Mode::Voprf).unwrap();
+        verify_proof(a, b, iter::once(c), iter::once(d), &proof, Mode::Voprf).unwrap();
+        let (_, expected) =
+            compute_composites::<CS, _, _>(None, b, iter::once(c), iter::once(d), Mode::Voprf)
+                .unwrap();
+        assert!(bool::from(expected.ct_eq(&z)));
+        assert_eq!(
+            verify_proof(a, b, iter::once(c), iter::once(c), &proof, Mode::Voprf)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::ProofVerification
+        );
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            kernel_matches_modes::<Ristretto255>();
+        }
+
+        kernel_matches_modes::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the commitment a server publishes to its public key
+
+use core::hash::{Hash, Hasher};
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Digest, Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use subtle::ConstantTimeEq;
+
+#[cfg(feature = "serde")]
+use crate::serialization::serde::Element;
+use crate::{CipherSuite, Error, ErrorKind, Group, Operation, PoprfServer, Result, VoprfServer};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_KEY_ID: [u8; 6] = *b"KeyID-";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A commitment to the public key of a server, as published in a key directory
+/// for clients to fetch, e.g. by Privacy Pass issuers.
+///
+/// It binds the public key to the ciphersuite, a validity period and its
+/// [`key_id`]. Clients should check it with
+/// [`KeyCommitment::verify`] before using the public key returned from it.
+#[derive_where(Clone, ZeroizeOnDrop)]
+#[derive_where(Debug; <CS::Group as Group>::Elem)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(crate = "serde", bound = "")
+)]
+pub struct KeyCommitment<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    #[cfg_attr(feature = "serde", serde(with = "Element::<CS::Group>"))]
+    pub(crate) public_key: <CS::Group as Group>::Elem,
+    pub(crate) not_before: u64,
+    pub(crate) not_after: u64,
+    pub(crate) key_id: Output<CS::Hash>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates a commitment to `public_key` that is valid from `not_before`
+    /// until `not_after`, inclusive. Both are timestamps in a unit chosen by
+    /// the application, e.g. seconds since the Unix epoch.
+    pub fn new(public_key: <CS::Group as Group>::Elem, not_before: u64, not_after: u64) -> Self {
+        Self {
+            public_key,
+            not_before,
+            not_after,
+            key_id: key_id::<CS>(public_key),
+        }
+    }
+
+    /// Returns the ciphersuite identifier the public key belongs to.
+    pub fn suite_id(&self) -> u16 {
+        CS::ID
+    }
+
+    /// Returns the committed public key without verifying the commitment.
+    pub fn public_key(&self) -> <CS::Group as Group>::Elem {
+        self.public_key
+    }
+
+    /// Returns the start of the validity period.
+    pub fn not_before(&self) -> u64 {
+        self.not_before
+    }
+
+    /// Returns the end of the validity period.
+    pub fn not_after(&self) -> u64 {
+        self.not_after
+    }
+
+    /// Returns the key ID of the public key, see [`key_id`].
+    pub fn key_id(&self) -> &Output<CS::Hash> {
+        &self.key_id
+    }
+
+    /// Checks that the key ID matches the public key and that the commitment is
+    /// valid at the timestamp `now`, then returns the public key.
+    ///
+    /// # Errors
+    /// [`ErrorKind::KeyCommitment`] if the key ID doesn't match the public key
+    /// or `now` is outside of the validity period.
+    pub fn verify(&self, now: u64) -> Result<<CS::Group as Group>::Elem> {
+        if key_id::<CS>(self.public_key) != self.key_id
+            || now < self.not_before
+            || now > self.not_after
+        {
+            return Err(Error::new(
+                ErrorKind::KeyCommitment,
+                Operation::VerifyKeyCommitment,
+            ));
+        }
+
+        Ok(self.public_key)
+    }
+}
+
+impl<CS: CipherSuite> PartialEq for KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        bool::from(self.public_key.ct_eq(&other.public_key))
+            && self.not_before == other.not_before
+            && self.not_after == other.not_after
+            && self.key_id == other.key_id
+    }
+}
+
+impl<CS: CipherSuite> Eq for KeyCommitment<CS> where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>
+{
+}
+
+impl<CS: CipherSuite> Hash for KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        CS::Group::serialize_elem(self.public_key).hash(state);
+        self.not_before.hash(state);
+        self.not_after.hash(state);
+        self.key_id.hash(state);
+    }
+}
+
+impl<CS: CipherSuite> VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates a [`KeyCommitment`] to the public key of this server for
+    /// publication. See [`KeyCommitment::new`].
+    pub fn key_commitment(&self, not_before: u64, not_after: u64) -> KeyCommitment<CS> {
+        KeyCommitment::new(self.pk, not_before, not_after)
+    }
+
+    /// Returns the key ID of the public key of this server. See [`key_id`].
+    pub fn key_id(&self) -> Output<CS::Hash> {
+        key_id::<CS>(self.pk)
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates a [`KeyCommitment`] to the public key of this server for
+    /// publication. See [`KeyCommitment::new`].
+    pub fn key_commitment(&self, not_before: u64, not_after: u64) -> KeyCommitment<CS> {
+        KeyCommitment::new(self.pk, not_before, not_after)
+    }
+
+    /// Returns the key ID of the public key of this server. See [`key_id`].
+    pub fn key_id(&self) -> Output<CS::Hash> {
+        key_id::<CS>(self.pk)
+    }
+}
+
+/// Computes the key ID of `public_key`, identifying it in key directories:
+///
+/// ```text
+/// Hash("KeyID-" || I2OSP(ID, 2) || SerializeElement(public_key))
+/// ```
+///
+/// Where `Hash` is [`CipherSuite::Hash`] and `ID` is [`CipherSuite::ID`], so
+/// the same public key has a different ID in every ciphersuite. Protocols that
+/// only transmit a truncated key ID, like Privacy Pass, should use
+/// [`truncated_key_id`].
+pub fn key_id<CS: CipherSuite>(public_key: <CS::Group as Group>::Elem) -> Output<CS::Hash>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    CS::Hash::new()
+        .chain_update(STR_KEY_ID)
+        .chain_update(CS::ID.to_be_bytes())
+        .chain_update(CS::Group::serialize_elem(public_key))
+        .finalize()
+}
+
+/// Returns the last byte of the [`key_id`] of `public_key`, as used by Privacy
+/// Pass to select the key of a token. Multiple keys can share the same
+/// truncated key ID, so clients have to try all of them.
+pub fn truncated_key_id<CS: CipherSuite>(public_key: <CS::Group as Group>::Elem) -> u8
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    key_id::<CS>(public_key)
+        .iter()
+        .last()
+        .copied()
+        .unwrap_or_default()
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Add;
+
+    use generic_array::typenum::{Sum, U16, U2};
+    use generic_array::ArrayLength;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{KeyCommitmentLen, VoprfClient};
+
+    fn key_commitment_verification<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        U2: Add<<CS::Group as Group>::ElemLen>,
+        Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+        Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+            ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+        KeyCommitmentLen<CS>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let commitment = server.key_commitment(10, 20);
+
+        // The published commitment survives serialization
+        let bytes = commitment.serialize();
+        let commitment = KeyCommitment::<CS>::deserialize(&bytes).unwrap();
+        assert_eq!(commitment.suite_id(), CS::ID);
+
+        let public_key = commitment.verify(10).unwrap();
+        assert!(commitment.verify(20).is_ok());
+
+        // The verified public key can be used to verify evaluations
+        let client_blind_result = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let server_result = server.blind_evaluate(&mut rng, &client_blind_result.message);
+        assert!(client_blind_result
+            .state
+            .finalize(
+                b"input",
+                &server_result.message,
+                &server_result.proof,
+                public_key,
+            )
+            .is_ok());
+
+        // The key ID is bound to the public key and the ciphersuite
+        assert_eq!(commitment.key_id(), &server.key_id());
+        assert_eq!(commitment.key_id(), &key_id::<CS>(public_key));
+        assert_ne!(
+            commitment.key_id(),
+            &CS::Hash::digest(CS::Group::serialize_elem(public_key))
+        );
+        assert_eq!(
+            truncated_key_id::<CS>(public_key),
+            commitment.key_id()[commitment.key_id().len() - 1]
+        );
+
+        let error = Error::new(ErrorKind::KeyCommitment, Operation::VerifyKeyCommitment);
+        assert!(matches!(commitment.verify(9), Err(e) if e == error));
+        assert!(matches!(commitment.verify(21), Err(e) if e == error));
+
+        // A key ID that doesn't match the public key is rejected
+        let other_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let mut tampered = commitment.clone();
+        tampered.public_key = other_server.get_public_key();
+        assert!(matches!(tampered.verify(15), Err(e) if e == error));
+
+        // A commitment for a different ciphersuite is rejected
+        let mut bytes = bytes;
+        bytes[0] ^= 1;
+        assert!(matches!(
+            KeyCommitment::<CS>::deserialize(&bytes),
+            Err(error) if error == Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeKeyCommitment
+            )
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            key_commitment_verification::<Ristretto255>();
+        }
+
+        key_commitment_verification::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the proof that a server knows the private key of its public key
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::sequence::Concat;
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+
+use crate::common::{i2osp_2, STR_HASH_TO_SCALAR};
+#[cfg(feature = "serde")]
+use crate::serialization::serde::Scalar;
+use crate::{CipherSuite, Error, ErrorKind, Group, Operation, PoprfServer, Result, VoprfServer};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_KEY_POSSESSION: [u8; 14] = *b"KeyPossession-";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A Schnorr proof that a server knows the private key of its public key.
+///
+/// Registries onboarding the keys of many issuers should require it, otherwise
+/// an issuer could register a public key it doesn't know the private key of,
+/// e.g. one derived from the keys of other issuers. The proof is bound to a
+/// `context`, e.g. the name of the registry and the issuer, so it can't be
+/// replayed elsewhere.
+#[derive_where(Clone, ZeroizeOnDrop)]
+#[derive_where(Debug, Eq, Hash, Ord, PartialEq, PartialOrd; <CS::Group as Group>::Scalar)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(crate = "serde", bound = "")
+)]
+pub struct KeyPossessionProof<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    #[cfg_attr(feature = "serde", serde(with = "Scalar::<CS::Group>"))]
+    pub(crate) c_scalar: <CS::Group as Group>::Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "Scalar::<CS::Group>"))]
+    pub(crate) s_scalar: <CS::Group as Group>::Scalar,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> KeyPossessionProof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Verifies that the prover knows the private key of `public_key`, for the
+    /// same `context` it was proven with.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `context` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the proof failed to verify.
+    pub fn verify(&self, public_key: <CS::Group as Group>::Elem, context: &[u8]) -> Result<()> {
+        // R = s * G + c * pk
+        let r = CS::Group::base_elem() * &self.s_scalar + &(public_key * &self.c_scalar);
+        let c = compute_challenge::<CS>(public_key, r, context)
+            .map_err(|error| error.with_operation(Operation::VerifyKeyPossession))?;
+
+        if c.ct_eq(&self.c_scalar).into() {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::ProofVerification,
+                Operation::VerifyKeyPossession,
+            ))
+        }
+    }
+}
+
+impl<CS: CipherSuite> VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Proves the knowledge of the private key of this server for `context`.
+    /// See [`KeyPossessionProof`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `context` is longer then [`u16::MAX`].
+    pub fn prove_key_possession<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        context: &[u8],
+    ) -> Result<KeyPossessionProof<CS>> {
+        prove_key_possession(rng, self.sk, self.pk, context)
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Proves the knowledge of the private key of this server for `context`.
+    /// See [`KeyPossessionProof`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `context` is longer then [`u16::MAX`].
+    pub fn prove_key_possession<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        context: &[u8],
+    ) -> Result<KeyPossessionProof<CS>> {
+        prove_key_possession(rng, self.sk, self.pk, context)
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+fn prove_key_possession<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    sk: <CS::Group as Group>::Scalar,
+    pk: <CS::Group as Group>::Elem,
+    context: &[u8],
+) -> Result<KeyPossessionProof<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let r = CS::Group::random_scalar(rng);
+    let c_scalar = compute_challenge::<CS>(pk, CS::Group::base_elem() * &r, context)
+        .map_err(|error| error.with_operation(Operation::ProveKeyPossession))?;
+    let s_scalar = r - &(c_scalar * &sk);
+
+    Ok(KeyPossessionProof { c_scalar, s_scalar })
+}
+
+/// Computes the challenge of the proof:
+///
+/// ```text
+/// c = G.HashToScalar(I2OSP(len(pk), 2) || pk ||
+///                    I2OSP(len(R), 2) || R ||
+///                    I2OSP(len(context), 2) || context,
+///                    "HashToScalar-KeyPossession-" || I2OSP(suite ID, 2))
+/// ```
+///
+/// Can only fail with [`ErrorKind::Input`]. The returned error doesn't carry an
+/// [`Operation`] yet.
+fn compute_challenge<CS: CipherSuite>(
+    pk: <CS::Group as Group>::Elem,
+    r: <CS::Group as Group>::Elem,
+    context: &[u8],
+) -> Result<<CS::Group as Group>::Scalar>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
+    let context_len = i2osp_2(context.len()).map_err(|_| Error::from(ErrorKind::Input))?;
+    let pk = CS::Group::serialize_elem(pk);
+    let r = CS::Group::serialize_elem(r);
+
+    let input = [
+        &elem_len,
+        pk.as_slice(),
+        &elem_len,
+        &r,
+        &context_len,
+        context,
+    ];
+    let dst = GenericArray::from(STR_HASH_TO_SCALAR)
+        .concat(STR_KEY_POSSESSION.into())
+        .concat(CS::ID.to_be_bytes().into());
+
+    CS::Group::hash_to_scalar::<CS::Hash>(&input, &dst).map_err(|_| ErrorKind::Input.into())
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Add;
+
+    use generic_array::typenum::Sum;
+    use generic_array::ArrayLength;
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    fn key_possession_verification<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+        Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+    {
+        let context = b"registry";
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let proof = server.prove_key_possession(&mut rng, context).unwrap();
+
+        // The proof survives serialization
+        let proof = KeyPossessionProof::<CS>::deserialize(&proof.serialize()).unwrap();
+        assert!(proof.verify(server.get_public_key(), context).is_ok());
+
+        // The proof is bound to the public key and the context
+        let error = Error::new(ErrorKind::ProofVerification, Operation::VerifyKeyPossession);
+        let other_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        assert!(matches!(
+            proof.verify(other_server.get_public_key(), context),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            proof.verify(server.get_public_key(), b"other registry"),
+            Err(e) if e == error
+        ));
+
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let proof = server.prove_key_possession(&mut rng, context).unwrap();
+        assert!(proof.verify(server.get_public_key(), context).is_ok());
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            key_possession_verification::<Ristretto255>();
+        }
+
+        key_possession_verification::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,428 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the Shamir secret sharing of server private keys
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+#[cfg(feature = "alloc")]
+use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, ConstantTimeEq};
+#[cfg(feature = "alloc")]
+use zeroize::Zeroize;
+
+use crate::common::invert_scalar;
+#[cfg(feature = "serde")]
+use crate::serialization::serde::Scalar;
+use crate::{
+    CipherSuite, Error, ErrorKind, Group, Operation, OprfServer, PoprfServer, Result, VoprfServer,
+};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_KEY_SHARE: [u8; 9] = *b"KeyShare-";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A share of a server's private key, produced by Shamir's secret sharing over
+/// the scalar field of the group, e.g. to escrow the key with several parties.
+///
+/// The share is the evaluation of a random polynomial, whose constant term is
+/// the private key, at its index. Any `threshold` shares reconstruct the key,
+/// fewer reveal nothing about it.
+///
+/// Shares are compared in constant time with [`ConstantTimeEq`], and don't
+/// implement [`PartialEq`], [`Ord`] or [`Hash`](core::hash::Hash), which
+/// would leak the secret share.
+#[derive_where(Clone, ZeroizeOnDrop)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(crate = "serde", bound = "")
+)]
+pub struct KeyShare<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    pub(crate) index: u16,
+    #[cfg_attr(feature = "serde", serde(with = "Scalar::<CS::Group>"))]
+    pub(crate) value: <CS::Group as Group>::Scalar,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> KeyShare<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Returns the index of this share, starting at 1.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+}
+
+impl<CS: CipherSuite> OprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Splits the private key of this server into `count` [`KeyShare`]s, of
+    /// which any `threshold` reconstruct it with
+    /// [`OprfServer::from_key_shares`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::KeyShare`] if `threshold` is zero or larger then `count`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    #[cfg(feature = "alloc")]
+    pub fn split_key<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        threshold: u16,
+        count: u16,
+    ) -> Result<Vec<KeyShare<CS>>> {
+        split_key(rng, self.sk, threshold, count)
+    }
+
+    /// Reconstructs a server from the [`KeyShare`]s of its private key. See
+    /// [`OprfServer::split_key`].
+    ///
+    /// Fewer shares then the threshold can't be detected and reconstruct a
+    /// different key, so the public key should be checked afterwards.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::KeyShare`] if `shares` is empty or contains a duplicate
+    ///   index or the index zero.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn from_key_shares(shares: &[KeyShare<CS>]) -> Result<Self> {
+        Self::from_parts(reconstruct_key(shares)?)
+            .map_err(|error| error.with_operation(Operation::ReconstructKey))
+    }
+}
+
+impl<CS: CipherSuite> VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Splits the private key of this server into `count` [`KeyShare`]s, of
+    /// which any `threshold` reconstruct it with
+    /// [`VoprfServer::from_key_shares`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::KeyShare`] if `threshold` is zero or larger then `count`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    #[cfg(feature = "alloc")]
+    pub fn split_key<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        threshold: u16,
+        count: u16,
+    ) -> Result<Vec<KeyShare<CS>>> {
+        split_key(rng, self.sk, threshold, count)
+    }
+
+    /// Reconstructs a server from the [`KeyShare`]s of its private key. See
+    /// [`VoprfServer::split_key`].
+    ///
+    /// Fewer shares then the threshold can't be detected and reconstruct a
+    /// different key, so the public key should be checked afterwards.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::KeyShare`] if `shares` is empty or contains a duplicate
+    ///   index or the index zero.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn from_key_shares(shares: &[KeyShare<CS>]) -> Result<Self> {
+        Self::from_parts(reconstruct_key(shares)?)
+            .map_err(|error| error.with_operation(Operation::ReconstructKey))
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Splits the private key of this server into `count` [`KeyShare`]s, of
+    /// which any `threshold` reconstruct it with
+    /// [`PoprfServer::from_key_shares`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::KeyShare`] if `threshold` is zero or larger then `count`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    #[cfg(feature = "alloc")]
+    pub fn split_key<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        threshold: u16,
+        count: u16,
+    ) -> Result<Vec<KeyShare<CS>>> {
+        split_key(rng, self.sk, threshold, count)
+    }
+
+    /// Reconstructs a server from the [`KeyShare`]s of its private key. See
+    /// [`PoprfServer::split_key`].
+    ///
+    /// Fewer shares then the threshold can't be detected and reconstruct a
+    /// different key, so the public key should be checked afterwards.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::KeyShare`] if `shares` is empty or contains a duplicate
+    ///   index or the index zero.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn from_key_shares(shares: &[KeyShare<CS>]) -> Result<Self> {
+        Self::from_parts(reconstruct_key(shares)?)
+            .map_err(|error| error.with_operation(Operation::ReconstructKey))
+    }
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+impl<CS: CipherSuite> ConstantTimeEq for KeyShare<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.index.ct_eq(&other.index) & self.value.ct_eq(&other.value)
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+#[cfg(feature = "alloc")]
+fn split_key<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    sk: <CS::Group as Group>::Scalar,
+    threshold: u16,
+    count: u16,
+) -> Result<Vec<KeyShare<CS>>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    if threshold == 0 || threshold > count {
+        return Err(Error::new(ErrorKind::KeyShare, Operation::SplitKey));
+    }
+
+    // f(x) = sk + a_1 * x + ... + a_(threshold - 1) * x^(threshold - 1)
+    let mut coefficients: Vec<_> = (1..threshold)
+        .map(|_| CS::Group::random_scalar(rng))
+        .collect();
+    let one = scalar_one::<CS>().map_err(|error| error.with_operation(Operation::SplitKey))?;
+    let zero = one - &one;
+
+    let mut x = zero;
+    let shares = (1..=count)
+        .map(|index| {
+            x = x + &one;
+            // Horner's method
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(zero, |value, coefficient| (value + coefficient) * &x)
+                + &sk;
+
+            KeyShare { index, value }
+        })
+        .collect();
+
+    for coefficient in &mut coefficients {
+        coefficient.zeroize();
+    }
+
+    Ok(shares)
+}
+
+/// Interpolates the polynomial of the `shares` at zero.
+fn reconstruct_key<CS: CipherSuite>(shares: &[KeyShare<CS>]) -> Result<<CS::Group as Group>::Scalar>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let error = Error::new(ErrorKind::KeyShare, Operation::ReconstructKey);
+
+    if shares.is_empty() {
+        return Err(error);
+    }
+
+    for (position, share) in shares.iter().enumerate() {
+        if share.index == 0
+            || shares
+                .iter()
+                .skip(position + 1)
+                .any(|other| other.index == share.index)
+        {
+            return Err(error);
+        }
+    }
+
+    let one =
+        scalar_one::<CS>().map_err(|error| error.with_operation(Operation::ReconstructKey))?;
+    let zero = one - &one;
+    let x = |index: u16| {
+        // Double-and-add over the bits of the index
+        (0..16).rev().fold(zero, |x, bit| {
+            let x = x + &x;
+
+            if (index >> bit) & 1 == 1 {
+                x + &one
+            } else {
+                x
+            }
+        })
+    };
+
+    // sk = sum(value_i * prod(x_j / (x_j - x_i) for j != i))
+    Ok(shares.iter().fold(zero, |sk, share| {
+        let x_i = x(share.index);
+        let (numerator, denominator) = shares
+            .iter()
+            .filter(|other| other.index != share.index)
+            .fold((one, one), |(numerator, denominator), other| {
+                let x_j = x(other.index);
+                (numerator * &x_j, denominator * &(x_j - &x_i))
+            });
+
+        sk + &(share.value * &numerator * &invert_scalar::<CS>(denominator))
+    }))
+}
+
+/// The scalar `1`, which the [`Group`] can't construct directly.
+///
+/// Can only fail with [`ErrorKind::Protocol`].
+fn scalar_one<CS: CipherSuite>() -> Result<<CS::Group as Group>::Scalar>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // Any fixed non-zero scalar times its inverse
+    let scalar = CS::Group::hash_to_scalar::<CS::Hash>(&[&STR_KEY_SHARE], &STR_KEY_SHARE)
+        .map_err(|_| Error::from(ErrorKind::Protocol))?;
+    Option::from(CS::Group::invert_scalar_ct(scalar))
+        .map(|inverse| scalar * &inverse)
+        .ok_or_else(|| ErrorKind::Protocol.into())
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use core::ops::Add;
+
+    use generic_array::typenum::{Sum, U2};
+    use generic_array::ArrayLength;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::KeyShareLen;
+
+    fn key_share_reconstruction<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        U2: Add<<CS::Group as Group>::ScalarLen>,
+        KeyShareLen<CS>: ArrayLength<u8>,
+        Sum<U2, <CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let shares = server.split_key(&mut rng, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        // Shares survive serialization
+        let shares: Vec<_> = shares
+            .iter()
+            .map(|share| {
+                let deserialized = KeyShare::<CS>::deserialize(&share.serialize()).unwrap();
+                assert!(bool::from(deserialized.ct_eq(share)));
+                deserialized
+            })
+            .collect();
+        assert!(!bool::from(shares[0].ct_eq(&shares[1])));
+
+        // Any threshold of shares reconstruct the key
+        let public_key = server.get_public_key();
+        for subset in [&shares[..3], &shares[2..], &shares[1..4], &shares[..]] {
+            let reconstructed = VoprfServer::<CS>::from_key_shares(subset).unwrap();
+            assert!(bool::from(
+                reconstructed.get_public_key().ct_eq(&public_key)
+            ));
+        }
+        let reconstructed = VoprfServer::<CS>::from_key_shares(&[
+            shares[4].clone(),
+            shares[0].clone(),
+            shares[2].clone(),
+        ])
+        .unwrap();
+        assert!(bool::from(
+            reconstructed.get_public_key().ct_eq(&public_key)
+        ));
+
+        // Fewer then the threshold don't
+        let reconstructed = VoprfServer::<CS>::from_key_shares(&shares[..2]).unwrap();
+        assert!(!bool::from(
+            reconstructed.get_public_key().ct_eq(&public_key)
+        ));
+
+        // A threshold of one shares the key itself
+        let shares = server.split_key(&mut rng, 1, 2).unwrap();
+        assert!(bool::from(shares[1].value.ct_eq(&server.get_private_key())));
+
+        let error = Error::new(ErrorKind::KeyShare, Operation::SplitKey);
+        assert!(matches!(server.split_key(&mut rng, 0, 2), Err(e) if e == error));
+        assert!(matches!(server.split_key(&mut rng, 3, 2), Err(e) if e == error));
+
+        let error = Error::new(ErrorKind::KeyShare, Operation::ReconstructKey);
+        assert!(matches!(VoprfServer::<CS>::from_key_shares(&[]), Err(e) if e == error));
+        assert!(matches!(
+            VoprfServer::<CS>::from_key_shares(&[shares[0].clone(), shares[0].clone()]),
+            Err(e) if e == error
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            key_share_reconstruction::<Ristretto255>();
+        }
+
+        key_share_reconstruction::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -39,6 +39,10 @@
 //! by a server evaluation, and finishes with a client finalization and server
 //! evaluation.
 //!
+//! With the `alloc` feature, the [`simple`] module provides the same three
+//! steps for every mode taking and returning bytes, which is enough for
+//! applications that don't need batching or the other extensions.
+//!
 //! ## Base Mode
 //!
 //! In base mode, an [OprfClient] interacts with an [OprfServer] to compute the
@@ -178,6 +182,10 @@
 //! assert_eq!(client_finalize_result, server_evaluate_result);
 //! ```
 //!
+//! If the client and the server run in the same process, e.g. for tests or
+//! when migrating stored outputs, [`evaluate_full`] and [`run_locally`] compute
+//! the same output directly from the server's serialized private key.
+//!
 //! ## Verifiable Mode
 //!
 //! In verifiable mode, a [VoprfClient] interacts with a [VoprfServer] to
@@ -186,6 +194,11 @@
 //! public key. If the proof fails to verify, then the client does not receive
 //! an output.
 //!
+//! The nonce of the proof is hedged: it is derived by hashing the server's
+//! private key, the evaluated elements and the output of the RNG. Proofs are
+//! indistinguishable from the ones of the specification, but a server with a
+//! weak RNG doesn't leak its private key through biased or repeated nonces.
+//!
 //! In batch mode, a single proof can be used for multiple VOPRF evaluations.
 //! See [the batching section](#batching) for more details on how to perform
 //! batch evaluations.
@@ -498,6 +511,39 @@
 //! # }
 //! ```
 //!
+//! For batches with a size known at compile time, the `_array` variants
+//! [VoprfClient::batch_blind_array], [VoprfServer::batch_blind_evaluate_array]
+//! and [VoprfClient::batch_finalize_array] work on fixed-size arrays and don't
+//! require `alloc`:
+//!
+//! ```
+//! # #[cfg(feature = "ristretto255")]
+//! # type CipherSuite = voprf::Ristretto255;
+//! # #[cfg(not(feature = "ristretto255"))]
+//! # type CipherSuite = p256::NistP256;
+//! # use voprf::{VoprfClient, VoprfClientBatchBlindArrayResult, VoprfServer, VoprfServerBatchEvaluateArrayResult};
+//! # use rand::rngs::OsRng;
+//! #
+//! # let mut client_rng = OsRng;
+//! # let mut server_rng = OsRng;
+//! # let server = VoprfServer::<CipherSuite>::new(&mut server_rng).unwrap();
+//! let inputs: [&[u8]; 3] = [b"first", b"second", b"third"];
+//! let VoprfClientBatchBlindArrayResult { states, messages } =
+//!     VoprfClient::<CipherSuite>::batch_blind_array(&inputs, &mut client_rng)
+//!         .expect("Unable to construct clients");
+//! let VoprfServerBatchEvaluateArrayResult { messages, proof } = server
+//!     .batch_blind_evaluate_array(&mut server_rng, &messages)
+//!     .expect("Unable to perform server batch evaluate");
+//! let outputs = VoprfClient::batch_finalize_array(
+//!     &inputs,
+//!     &states,
+//!     &messages,
+//!     &proof,
+//!     server.get_public_key(),
+//! )
+//! .expect("Unable to perform client batch finalization");
+//! ```
+//!
 //! ## Metadata
 //!
 //! The optional metadata parameter included in the POPRF mode allows clients
@@ -514,19 +560,277 @@
 //! <https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-11.html#name-poprf-public-input>
 //! for more detailed information on how this public input should be used.
 //!
+//! Passing `None` as the info is the same as passing an empty info,
+//! `Some(&[])`. An empty info is still framed, hashed and applied as a tweak to
+//! the key, so it does not fall back to the VOPRF mode: its outputs differ from
+//! the ones of a [VoprfServer] with the same private key, and both parties have
+//! to use the POPRF mode. A client finalizing many evaluations without an info
+//! can spell this out with [PoprfClientWithInfo::without_info], which also
+//! computes the tweaked key only once.
+//!
+//! ## Long Inputs
+//!
+//! The specification limits inputs to [`u16::MAX`] bytes. Longer inputs can be
+//! used with the long input mode, e.g. [`VoprfClient::blind_long_input`],
+//! [`VoprfClient::finalize_long_input`] and
+//! [`VoprfServer::evaluate_long_input`], which pre-hash the input first. The
+//! mode is domain separated by appending "-LongInput" to the context string, so
+//! its outputs are unrelated to the ones of the regular mode, and both parties
+//! have to agree on using it.
+//!
+//! ## Typestate
+//!
+//! The clients of all modes can also be used through the [`Client`] wrapper,
+//! which tracks the step of the protocol in its type. This makes it a compile
+//! error to finalize a client before it received the server's response or to
+//! finalize the same client twice.
+//!
+//! ```
+//! # #[cfg(feature = "ristretto255")]
+//! # type CipherSuite = voprf::Ristretto255;
+//! # #[cfg(not(feature = "ristretto255"))]
+//! # type CipherSuite = p256::NistP256;
+//! use rand::rngs::OsRng;
+//! use voprf::{Client, VoprfClient, VoprfServer};
+//!
+//! let mut rng = OsRng;
+//! let server = VoprfServer::<CipherSuite>::new(&mut rng).unwrap();
+//!
+//! let (client, message) = Client::<VoprfClient<CipherSuite>, _>::blind(b"input", &mut rng)
+//!     .expect("Unable to construct client");
+//! let client = client.receive(server.blind_evaluate(&mut rng, &message));
+//! let output = client
+//!     .finalize(b"input", server.get_public_key())
+//!     .expect("Unable to perform client finalization");
+//! ```
+//!
+//! ## Serialization
+//!
+//! Every type with a `deserialize` function only accepts its canonical
+//! encoding, so `serialize(deserialize(bytes)) == bytes` holds for all bytes
+//! it accepts, and bytes can be signed or hashed as part of a transcript
+//! without being normalized first. Trailing bytes are rejected as well. The
+//! only exception is [`IssuerDirectory`], whose entries are accepted in any
+//! order and re-serialized in their canonical order.
+//!
+//! Ciphersuites implementing [`Group`] themselves have to uphold this, by only
+//! accepting canonical encodings of elements and scalars.
+//!
+//! The serialized states of clients and servers hold their blinds and private
+//! keys, so their `serialize` functions return the bytes wrapped in
+//! [`Zeroizing`](zeroize::Zeroizing), which zeroizes them when dropped. Copy
+//! them out only into storage that is itself protected. With the `alloc`
+//! feature, their `seal` functions encrypt them for persistence instead, with
+//! an AEAD implementing [`StateCipher`] that binds the suite, the mode and
+//! whether the state is a client's or a server's.
+//!
+//! ## Thread Safety
+//!
+//! All clients, servers, messages and result types are [`Clone`], [`Send`] and
+//! [`Sync`] if the group elements and scalars of the [`CipherSuite`] are, which
+//! is the case for all ciphersuites supported by this crate. [`Transcript`]
+//! additionally requires the same of the hash function. The wrapped clients of
+//! [`Client`] are only [`Send`] and [`Sync`], as cloning them would allow to
+//! finalize the same state twice.
+//!
+//! Servers hold no mutable state and only take `&self`, so a single server can
+//! be shared between threads, e.g. through an [`Arc`], without any locking.
+//! Only the random number generator passed to the blind evaluation has to be
+//! exclusive, so each thread should use its own.
+//!
+//! [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+//!
+//! ## Panics
+//!
+//! No function of this crate panics on any input, with these exceptions:
+//!
+//! - [`VoprfServer::blind_evaluate`], whose infallible signature hides errors
+//!   that can't happen in practice. Applications that can't tolerate panics at
+//!   all, e.g. inside an enclave, can use [`VoprfServer::try_blind_evaluate`]
+//!   instead.
+//! - The batch functions returning arrays without allocating, like
+//!   [`VoprfClient::batch_blind_array`], unpack their results with an
+//!   `unreachable!` after all of them were checked for errors, so it can't be
+//!   reached.
+//! - [`AssertStateLen::LEN`] fails if a state is larger than its capacity, but
+//!   only ever at compile time.
+//! - Running out of memory aborts the process in the functions that allocate
+//!   with the `alloc` feature, like everywhere in `alloc`.
+//!
+//! Apart from the first two, and the indexing of arrays in bounds by
+//! construction in the same batch functions, panicking operations are denied
+//! through Clippy. Panics of the dependencies, e.g. of the group
+//! implementations, are not covered.
+//!
+//! ## Code Size
+//!
+//! All protocol code is generic over the [`CipherSuite`], so only the modes an
+//! application calls are compiled into it. Firmware that only uses the base
+//! mode contains none of the proofs or the tweaks of the verifiable modes, so
+//! there are no features to turn them off. `scripts/size.sh` checks this with
+//! the debug information of the firmware image in the `firmware` directory,
+//! which lists the functions compiled in, inlined ones included.
+//!
+//! For microcontrollers, disable the default features, select the
+//! `ristretto255-u32` backend on 32-bit targets and enable the `size-optimized`
+//! feature, then build with a release profile optimized for size:
+//!
+//! ```toml
+//! [profile.release]
+//! opt-level = "z"
+//! lto = true
+//! codegen-units = 1
+//! panic = "abort"
+//! ```
+//!
+//! With this profile, the client of the firmware image, which blinds,
+//! deserializes the evaluation and finalizes, takes the following flash on
+//! `thumbv6m-none-eabi`, as `.text` plus `.rodata` printed by `scripts/size.sh`
+//! with Rust 1.95, without and with `size-optimized`:
+//!
+//! | Suite          | OPRF              | VOPRF             | POPRF             |
+//! | -------------- | ----------------- | ----------------- | ----------------- |
+//! | `Ristretto255` | 33.0 KB / 23.3 KB | 48.9 KB / 30.0 KB | 49.6 KB / 30.8 KB |
+//! | `NistP256`     | 22.2 KB / 20.5 KB | 25.7 KB / 24.0 KB | 26.3 KB / 24.6 KB |
+//! | `Secp256k1`    | 30.6 KB / 28.9 KB | 34.3 KB / 32.2 KB | 34.8 KB / 32.8 KB |
+//!
+//! `size-optimized` trades speed for flash in two ways:
+//! - SHA-2 is compiled without unrolled rounds, which saves about 10 KB with
+//!   the SHA-512 of [`Ristretto255`] and 2 KB with SHA-256, in every mode.
+//! - Proofs are verified with two separate scalar multiplications instead of an
+//!   interleaved one, which leaves out the 8 KB table of odd multiples of the
+//!   base point of [`Ristretto255`] and the interleaving code of `Secp256k1`,
+//!   so verification is slower.
+//!
+//! Keeping the group operations out of line with `#[inline(never)]` saves less
+//! than 100 bytes, so the feature doesn't.
+//!
+//! ## Stack Usage
+//!
+//! The client of the firmware image takes at most the following stack on
+//! `thumbv8m.main-none-eabi` (Cortex-M33), in bytes, without and with the
+//! `small-stack` feature. `scripts/stack.py` bounds it statically from the
+//! stack sizes LLVM emits and the call graph of the image, with the pinned
+//! nightly toolchain of the script:
+//!
+//! | Suite          | OPRF        | VOPRF         | POPRF         |
+//! | -------------- | ----------- | ------------- | ------------- |
+//! | `Ristretto255` | 4848 / 4848 | 7736 / 5808   | 8240 / 6144   |
+//! | `NistP256`     | 4760 / 4760 | 5888 / 5368   | 6048 / 5608   |
+//! | `Secp256k1`    | 9780 / 9780 | 14948 / 10524 | 14908 / 10844 |
+//!
+//! `small-stack` verifies proofs with two separate scalar multiplications
+//! instead of an interleaved one, whose tables of both elements are the largest
+//! temporaries of the verifiable modes, and keeps the hashing of the proofs out
+//! of line so its buffers don't share a frame with the multiplications, at the
+//! cost of slower verification. With it, all modes of [`Ristretto255`] and
+//! `NistP256` fit into an 8 KB stack with room for the frames of the
+//! application. `Secp256k1` doesn't even in the base mode, as the scalar
+//! multiplication of `k256` alone takes 7.1 KB.
+//!
 //! # Features
 //!
 //! - The `alloc` feature requires Rust's `alloc` crate and enables batching
 //!   VOPRF evaluations.
 //!
+//! - The `argon2` feature, disabled by default, requires the `alloc` feature
+//!   and provides `harden_output` and the `finalize_hardened` methods of all
+//!   clients, which run outputs through the memory-hard [Argon2id] with
+//!   parameters supplied by the caller.
+//!
+//! - The `derive` feature, disabled by default, provides a derive macro for
+//!   [`CipherSuite`], which implements it with the group, hash and ID of a
+//!   `#[voprf(...)]` attribute. See [`impl_ciphersuite`] for a declarative
+//!   alternative.
+//!
+//! - The `dodis-yampolskiy` feature, disabled by default, provides the
+//!   Dodis-Yampolskiy VRF of `DyVrfServer` with proofs under a [`CustomMode`],
+//!   to compare constructions that need its algebraic structure against the
+//!   specified modes. It is not an OPRF: the server sees the input.
+//!
+//! - The `elligator` feature, disabled by default, provides `UniformGroup` for
+//!   [`Ristretto255`] and P-256 of the [p256] crate, so blinded and evaluation
+//!   elements can be sent as bytes that are indistinguishable from uniformly
+//!   random ones with `to_uniform_bytes`, e.g. over censorship-resistant
+//!   transports. P-256 uses the field arithmetic of [p256] and Ristretto255 the
+//!   formally verified one of [fiat-crypto], this crate implements no field
+//!   arithmetic of its own. Proofs are not covered.
+//!
+//! - The `http` feature, disabled by default, requires the `alloc` feature and
+//!   provides unpadded base64url encodings of all messages for HTTP header
+//!   values through `HttpMessage`, as used by Privacy Pass.
+//!
+//! - The `mlock` feature, disabled by default, requires the `alloc` feature and
+//!   provides `LockedBox` on Unix, which keeps secrets like server keys in
+//!   memory that is locked against swapping and excluded from core dumps.
+//!
+//! - The `opaque` feature, disabled by default, provides
+//!   `derive_opaque_oprf_server`, which derives the OPRF key of an OPAQUE
+//!   credential as its specification does.
+//!
+//! - The `preprocess` feature, disabled by default, requires the `alloc`
+//!   feature and pulls in [unicode-normalization] for `normalize_input`, which
+//!   canonicalizes password-like inputs before blinding.
+//!
 //! - The `serde` feature, enabled by default, provides convenience functions
-//!   for serializing and deserializing with [serde](https://serde.rs/).
+//!   for serializing and deserializing with [serde](https://serde.rs/). Text
+//!   formats like JSON store scalars, e.g. the keys of servers and the blinds
+//!   of clients, as lowercase hex that is encoded and decoded in constant
+//!   time. Messages also format as hex with `Display` and parse with
+//!   `FromStr` through the same codec, no secret has these implementations.
+//!
+//! - The `small-stack` feature, disabled by default, trades slower verification
+//!   of proofs for smaller temporaries on the stack, see [Stack
+//!   Usage](#stack-usage).
+//!
+//! - The `size-optimized` feature, disabled by default, compiles SHA-2 without
+//!   unrolled rounds and verifies proofs without precomputed tables of the base
+//!   point or interleaved scalar multiplications, which saves up to 19 KB of
+//!   flash at the cost of slower hashing and verification, see [Code
+//!   Size](#code-size).
 //!
 //! - The `danger` feature, disabled by default, exposes functions for setting
 //!   and getting internal values not available in the default API. These
 //!   functions are intended for use in by higher-level cryptographic protocols
 //!   that need access to these raw values and are able to perform the necessary
-//!   validations on them (such as being valid group elements).
+//!   validations on them (such as being valid group elements). Together with
+//!   the `alloc` feature, it also enables the [`malicious`] module of
+//!   misbehaving server evaluations, to test that they are rejected.
+//!
+//! - The `danger-allow-identity` feature, disabled by default, makes
+//!   [`Group::deserialize_elem`] accept the identity element, so test harnesses
+//!   can construct degenerate transcripts through the public API. **Never
+//!   enable it in production**, it removes a check the security of the protocol
+//!   relies on.
+//!
+//! - The `tracing` feature, disabled by default, emits [tracing] spans around
+//!   blinding, evaluation, finalization and proof generation and verification,
+//!   recording the mode and batch sizes but never secret values or inputs. The
+//!   timings of these spans can be collected by any `tracing` subscriber. Note
+//!   that `tracing` requires atomic compare-and-swap, which isn't available on
+//!   some embedded targets like `thumbv6m-none-eabi`.
+//!
+//! - The `metrics` feature, disabled by default, requires the `std` feature and
+//!   reports to any recorder of the [metrics] facade, with a `mode` label:
+//!   - `voprf_evaluations_total`, a counter of the elements evaluated by
+//!     servers,
+//!   - `voprf_batch_size`, a histogram of the sizes of batch evaluations,
+//!   - `voprf_proof_verification_failures_total`, a counter of the proofs that
+//!     failed to verify.
+//!
+//!   `voprf_deserialization_errors_total` counts the messages that failed to
+//!   deserialize, with a `message` label of `blinded_element`,
+//!   `evaluation_element`, `proof` or `chunked_proof` instead. Note that
+//!   `metrics` requires Rust 1.71.1.
+//!
+//! - The `parallel` feature, disabled by default, requires the `std` feature
+//!   and pulls in [rayon] for `par_batch_blind_evaluate` and
+//!   `par_batch_finalize` on the [`VoprfServer`], [`VoprfClient`],
+//!   [`PoprfServer`] and [`PoprfClient`], which evaluate and finalize the items
+//!   of a batch in parallel with the same results as their sequential
+//!   counterparts. The batch results of servers implement
+//!   `rayon::iter::IntoParallelIterator` over their messages. Note that `rayon`
+//!   requires Rust 1.80.
 //!
 //! - The `ristretto255-ciphersuite` features enables using [`Ristretto255`] as
 //!   a [`CipherSuite`].
@@ -540,15 +844,77 @@
 //!   `ristretto255-fiat-u64` and `ristretto255-fiat-u32`. Any `ristretto255-*`
 //!   backend feature will enable the `ristretto255` feature.
 //!
+//! - The `bls12_381` feature, disabled by default, enables using `Bls12_381`,
+//!   the G1 group of the pairing-friendly curve, as a [`CipherSuite`]. No suite
+//!   is assigned to it by the specification.
+//!
+//! - The `pythia` feature, disabled by default, enables the pairing-based
+//!   partially oblivious PRF of `PythiaServer` over BLS12-381, whose keys can
+//!   be rotated without re-running the protocol on stored outputs. It enables
+//!   the `bls12_381` feature.
+//!
+//! - The `interop` feature, only used by the tests, cross-checks random
+//!   transcripts of all modes against the reference implementation given by the
+//!   `VOPRF_REFERENCE` environment variable, e.g. `scripts/voprf_reference.py`.
+//!   The tests are skipped if it isn't set, see `src/tests/interop.rs`.
+//!
+//! - The `jubjub` feature, disabled by default, enables using `Jubjub` as a
+//!   [`CipherSuite`], so outputs can be handled natively by zk-circuits over
+//!   BLS12-381. No suite is assigned to it by the specification.
+//!
+//! - The `pasta` feature, disabled by default, enables using `Pallas` and
+//!   `Vesta` as [`CipherSuite`]s, so outputs can be handled natively by proof
+//!   systems over the Pasta cycle, like Halo 2. No suite is assigned to them by
+//!   the specification.
+//!
+//! - The `secp256k1` feature, disabled by default, enables using `Secp256k1`,
+//!   the curve of Bitcoin and Ethereum, as a [`CipherSuite`] with SHA-256,
+//!   implemented with the [k256] crate and its GLV endomorphism. No suite is
+//!   assigned to it by the specification. Note that `k256` requires Rust 1.65.
+//!
+//! - The `sm2` feature, disabled by default, enables using `Sm2`, the curve of
+//!   GB/T 32918, as a [`CipherSuite`] with the SM3 hash function, implemented
+//!   with the [sm2] crate. No suite is assigned to it by the specification.
+//!   Note that `sm2` requires Rust 1.65.
+//!
+//! - The `toy-group` feature, disabled by default, enables the **insecure**
+//!   `ToyGroup` over a small prime field, which can be enumerated exhaustively
+//!   by property tests and keeps examples readable. It must never be used in a
+//!   real deployment.
+//!
 //! - The `ristretto255-simd` feature is re-exported from [curve25519-dalek] and
 //!   enables parallel formulas, using either AVX2 or AVX512-IFMA. This will
 //!   automatically enable the `ristretto255-u64` feature and requires Rust
 //!   nightly.
 //!
+//! No feature depends on `getrandom` or any other source of system randomness:
+//! every function that needs randomness takes a [`CryptoRng`] supplied by the
+//! caller, so a hardware RNG can be used on bare-metal targets.
+//!
+//! [`CryptoRng`]: rand_core::CryptoRng
+//! [Argon2id]: https://docs.rs/argon2
+//! [fiat-crypto]: https://docs.rs/fiat-crypto
+//! [k256]: https://docs.rs/k256
+//! [p256]: https://docs.rs/p256
+//! [sm2]: https://docs.rs/sm2
+//! [metrics]: https://docs.rs/metrics
+//! [rayon]: https://docs.rs/rayon
+//! [tracing]: https://docs.rs/tracing
+//! [unicode-normalization]: https://docs.rs/unicode-normalization
 //! [curve25519-dalek]:
 //!     (https://doc.dalek.rs/curve25519_dalek/index.html#backends-and-features)
 
 #![cfg_attr(not(test), deny(unsafe_code))]
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::expect_used,
+        clippy::indexing_slicing,
+        clippy::panic,
+        clippy::unreachable,
+        clippy::unwrap_used
+    )
+)]
 #![no_std]
 #![warn(
     clippy::cargo,
@@ -564,16 +930,113 @@ extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
+#[cfg(feature = "metrics")]
+extern crate metrics_ as metrics;
+#[cfg(feature = "parallel")]
+extern crate rayon_ as rayon;
 #[cfg(feature = "serde")]
 extern crate serde_ as serde;
+#[cfg(feature = "tracing")]
+extern crate tracing_ as tracing;
+
+/// Enters a debug-level `tracing` span until the end of the current scope if
+/// the `tracing` feature is enabled. Never record secret values in its fields.
+macro_rules! debug_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+/// Emits a debug-level `tracing` event if the `tracing` feature is enabled.
+/// Never record secret values in its fields.
+macro_rules! debug_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+/// Increments a `metrics` counter by `$value` if the `metrics` feature is
+/// enabled. Never use secret values as labels.
+macro_rules! count {
+    ($name:literal, $value:expr $(, $key:literal => $label:expr)* $(,)?) => {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!($name $(, $key => $label)*).increment($value as u64);
+        }
+    };
+}
 
+/// Records `$value` in a `metrics` histogram if the `metrics` feature is
+/// enabled. Never use secret values as labels.
+macro_rules! record {
+    ($name:literal, $value:expr $(, $key:literal => $label:expr)* $(,)?) => {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::histogram!($name $(, $key => $label)*).record($value as f64);
+        }
+    };
+}
+
+mod builder;
+mod cache;
+mod chunked_proof;
 mod ciphersuite;
 mod common;
+mod custom_mode;
+#[cfg(feature = "alloc")]
+mod directory;
+#[cfg(feature = "dodis-yampolskiy")]
+mod dodis_yampolskiy;
+#[cfg(feature = "alloc")]
+mod dyn_suite;
 mod error;
+mod expand;
+mod expiry;
+mod gatekeeper;
 mod group;
+#[cfg(feature = "argon2")]
+mod harden;
+#[cfg(feature = "http")]
+mod http;
+mod kernel;
+mod key_commitment;
+mod key_possession;
+mod key_share;
+#[cfg(all(any(feature = "danger", test), feature = "alloc"))]
+pub mod malicious;
+mod masked_key;
+mod migration;
+#[cfg(feature = "opaque")]
+mod opaque;
 mod oprf;
+mod ops;
+mod output_match;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod poprf;
+#[cfg(feature = "preprocess")]
+mod preprocess;
+mod pseudonym;
+#[cfg(feature = "pythia")]
+mod pythia;
+mod redemption;
+#[cfg(feature = "alloc")]
+mod sealed;
+#[cfg(all(feature = "mlock", unix))]
+mod secure_memory;
 mod serialization;
+#[cfg(feature = "alloc")]
+mod server_pool;
+#[cfg(feature = "alloc")]
+pub mod simple;
+mod stateless;
+mod tenant;
+mod transcript;
+mod typestate;
+mod usage;
+mod verifier;
 mod voprf;
 
 #[cfg(test)]
@@ -581,32 +1044,156 @@ mod tests;
 
 // Exports
 
-pub use crate::ciphersuite::CipherSuite;
+/// Derives [`CipherSuite`](trait@CipherSuite) for a unit struct from the
+/// group, hash and ID given in a `#[voprf(...)]` attribute.
+///
+/// The `group` has to implement [`Group`] and the `hash` has to implement
+/// [`Digest`](digest::Digest) and
+/// [`BlockSizeUser`](digest::core_api::BlockSizeUser), with an output size less
+/// then 256 bytes and not exceeding its block size. An optional
+/// `finalize_hash` selects a different
+/// [`CipherSuite::FinalizeHash`](trait@CipherSuite) for the outputs. Like
+/// [`impl_ciphersuite`], it generates a test module that runs
+/// [`check_conformance`] on the ciphersuite, named after the struct with a
+/// `_conformance` suffix unless given with `tests`.
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # {
+/// use voprf::CipherSuite;
+///
+/// /// Ristretto255 with SHA-256.
+/// #[derive(CipherSuite, Clone, Copy, Debug)]
+/// #[voprf(group = voprf::Ristretto255, hash = sha2::Sha256, id = 0xFF01)]
+/// pub struct Ristretto255Sha256;
+///
+/// assert_eq!(Ristretto255Sha256::ID, 0xFF01);
+/// voprf::check_conformance::<Ristretto255Sha256>().unwrap();
+/// # }
+/// ```
+///
+/// A missing `group`, `hash` or `id` doesn't compile:
+///
+/// ```compile_fail
+/// use voprf::CipherSuite;
+///
+/// #[derive(CipherSuite)]
+/// #[voprf(hash = sha2::Sha256, id = 0xFF01)]
+/// pub struct MissingGroup;
+/// ```
+#[cfg(feature = "derive")]
+pub use voprf_derive::CipherSuite;
+
+pub use crate::builder::{
+    NoKeySource, PrivateKeySource, RngKeySource, SeedKeySource, ServerBuilder,
+};
+pub use crate::cache::EvaluationCache;
+pub use crate::chunked_proof::BatchProof;
+#[cfg(feature = "alloc")]
+pub use crate::chunked_proof::{ChunkedProof, VoprfServerBatchEvaluateChunkedResult};
+pub use crate::ciphersuite::{check_conformance, CipherSuite, SuitableHash};
 #[cfg(feature = "danger")]
 pub use crate::common::derive_key;
 pub use crate::common::{
-    BlindedElement, EvaluationElement, Mode, PreparedEvaluationElement, Proof,
+    BlindedElement, CustomMode, EvaluationElement, Mode, PreparedEvaluationElement, Proof,
+    ZipRepeat,
 };
-pub use crate::error::{Error, InternalError, Result};
+pub use crate::custom_mode::{
+    custom_blind, custom_blind_evaluate, custom_finalize, custom_generate_proof,
+    custom_verify_proof,
+};
+#[cfg(feature = "alloc")]
+pub use crate::directory::IssuerDirectory;
+#[cfg(feature = "dodis-yampolskiy")]
+pub use crate::dodis_yampolskiy::{dy_vrf_verify, DyVrfEvaluateResult, DyVrfServer};
+#[cfg(feature = "alloc")]
+pub use crate::dyn_suite::{DynCryptoRng, DynGroupElement, DynScalar, DynSuite, StaticSuite};
+pub use crate::error::{Error, ErrorKind, InternalError, Operation, Result};
+pub use crate::expand::{expand_message_xmd, expand_message_xof};
+pub use crate::expiry::{ExpiryBuckets, EXPIRY_INFO_LEN};
+pub use crate::gatekeeper::{GatedServer, Gatekeeper};
 pub use crate::group::Group;
 #[cfg(feature = "ristretto255")]
 pub use crate::group::Ristretto255;
-pub use crate::oprf::{OprfClient, OprfClientBlindResult, OprfServer};
+#[cfg(feature = "secp256k1")]
+pub use crate::group::Secp256k1;
+#[cfg(feature = "sm2")]
+pub use crate::group::Sm2;
+#[cfg(feature = "elligator")]
+pub use crate::group::UniformGroup;
+#[cfg(feature = "bls12_381")]
+pub use crate::group::{Bls12_381, Bls12_381Elem, Bls12_381Scalar};
+#[cfg(feature = "jubjub")]
+pub use crate::group::{Jubjub, JubjubElem, JubjubScalar};
+#[cfg(feature = "pasta")]
+pub use crate::group::{Pallas, PallasElem, PallasScalar, Vesta, VestaElem, VestaScalar};
+#[cfg(feature = "toy-group")]
+pub use crate::group::{ToyElem, ToyGroup, ToyScalar};
+#[cfg(feature = "argon2")]
+pub use crate::harden::harden_output;
+#[cfg(feature = "http")]
+pub use crate::http::{
+    batch_from_header_value, batch_to_header_value, decode_base64url, encode_base64url, HttpMessage,
+};
+pub use crate::key_commitment::{key_id, truncated_key_id, KeyCommitment};
+pub use crate::key_possession::KeyPossessionProof;
+pub use crate::key_share::KeyShare;
+pub use crate::masked_key::{MaskedOprfServer, MaskedVoprfServer};
+pub use crate::migration::{MigrationClient, MigrationPair, MigrationServer};
+#[cfg(feature = "opaque")]
+pub use crate::opaque::derive_opaque_oprf_server;
+pub use crate::oprf::{
+    evaluate_full, run_locally, OprfClient, OprfClientBlindResult, OprfServer,
+    OprfServerEvaluateBatchResult,
+};
+pub use crate::ops::{OprfClientOps, OprfServerOps};
+pub use crate::output_match::find_output;
 #[cfg(feature = "alloc")]
 pub use crate::poprf::PoprfServerBatchEvaluateResult;
 pub use crate::poprf::{
-    PoprfClient, PoprfClientBatchFinalizeResult, PoprfPreparedTweak, PoprfServer,
-    PoprfServerBatchEvaluateFinishResult, PoprfServerBatchEvaluateFinishedMessages,
-    PoprfServerBatchEvaluatePrepareResult, PoprfServerBatchEvaluatePreparedEvaluationElements,
+    PoprfClient, PoprfClientBatchBlindArrayResult, PoprfClientBatchFinalizeResult,
+    PoprfClientBatchFinalizeWithInfosResult, PoprfClientBlindResult,
+    PoprfClientMultiKeyFinalizeResult, PoprfClientWithInfo, PoprfPreparedTweak, PoprfServer,
+    PoprfServerBatchEvaluateArrayResult, PoprfServerBatchEvaluateFinishResult,
+    PoprfServerBatchEvaluateFinishedMessages, PoprfServerBatchEvaluatePrepareResult,
+    PoprfServerBatchEvaluatePreparedEvaluationElements, PoprfServerEvaluateResult,
+    PoprfServerPolicyEvaluateResult,
+};
+#[cfg(feature = "preprocess")]
+pub use crate::preprocess::normalize_input;
+pub use crate::pseudonym::pseudonymize;
+#[cfg(feature = "pythia")]
+pub use crate::pythia::{
+    PythiaBlindedElement, PythiaClient, PythiaClientBlindResult, PythiaEvaluationElement,
+    PythiaOutput, PythiaProof, PythiaServer, PythiaServerEvaluateResult, PythiaUpdateToken,
 };
+pub use crate::redemption::NonceStore;
+#[cfg(feature = "alloc")]
+pub use crate::sealed::StateCipher;
+#[cfg(all(feature = "mlock", unix))]
+pub use crate::secure_memory::LockedBox;
+#[cfg(feature = "elligator")]
+pub use crate::serialization::UniformElementLen;
 pub use crate::serialization::{
-    BlindedElementLen, EvaluationElementLen, OprfClientLen, OprfServerLen, PoprfClientLen,
-    PoprfServerLen, ProofLen, VoprfClientLen, VoprfServerLen,
+    AssertStateLen, BlindedElementDeserializeBatchResult, BlindedElementLen,
+    EvaluationElementDeserializeBatchResult, EvaluationElementLen, KeyCommitmentLen,
+    KeyPossessionProofLen, KeyShareLen, OprfClientLen, OprfServerLen, PoprfClientLen,
+    PoprfServerLen, ProofLen, TaggedLen, VoprfClientLen, VoprfServerLen,
 };
 #[cfg(feature = "alloc")]
+pub use crate::server_pool::{split_response, tag_request, PoolServer, ServerPool};
+pub use crate::stateless::StatelessServer;
+pub use crate::tenant::TenantServer;
+pub use crate::transcript::Transcript;
+pub use crate::typestate::{Blinded, Client, Evaluated};
+pub use crate::usage::{CountedServer, KeyUsage, RotationPolicy, UsageLimits};
+pub use crate::verifier::VerifierContext;
+#[cfg(feature = "alloc")]
 pub use crate::voprf::VoprfServerBatchEvaluateResult;
 pub use crate::voprf::{
-    VoprfClient, VoprfClientBatchFinalizeResult, VoprfClientBlindResult, VoprfServer,
-    VoprfServerBatchEvaluateFinishResult, VoprfServerBatchEvaluateFinishedMessages,
-    VoprfServerBatchEvaluatePreparedEvaluationElements, VoprfServerEvaluateResult,
+    VoprfClient, VoprfClientBatchBlindArrayResult, VoprfClientBatchFinalizeResult,
+    VoprfClientBlindResult, VoprfClientMultiKeyFinalizeResult, VoprfServer,
+    VoprfServerBatchEvaluateArrayResult, VoprfServerBatchEvaluateFinishResult,
+    VoprfServerBatchEvaluateFinishedMessages, VoprfServerBatchEvaluatePreparedEvaluationElements,
+    VoprfServerEvaluateOnlyElements, VoprfServerEvaluateResult,
 };
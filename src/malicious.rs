@@ -0,0 +1,263 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Misbehaving server evaluations, to test that clients reject them.
+//!
+//! **Never use this in production.** Every function here produces evaluations
+//! or proofs a real server must never send, so integrators can assert that
+//! their error and retry handling rejects them instead of silently accepting
+//! wrong outputs:
+//!
+//! ```
+//! # #[cfg(feature = "ristretto255")]
+//! # type CipherSuite = voprf::Ristretto255;
+//! # #[cfg(not(feature = "ristretto255"))]
+//! # type CipherSuite = p256::NistP256;
+//! use rand::rngs::OsRng;
+//! use voprf::{malicious, ErrorKind, VoprfClient, VoprfServer};
+//!
+//! let mut rng = OsRng;
+//! let server = VoprfServer::<CipherSuite>::new(&mut rng).unwrap();
+//! let client_blind_result = VoprfClient::<CipherSuite>::blind(b"input", &mut rng).unwrap();
+//! let client_states = [client_blind_result.state];
+//! let client_messages = [client_blind_result.message];
+//!
+//! let result = malicious::evaluate_with_wrong_key(&mut rng, &server, &client_messages).unwrap();
+//! let error = VoprfClient::batch_finalize(
+//!     &[b"input"],
+//!     &client_states,
+//!     &result.messages,
+//!     &result.proof,
+//!     server.get_public_key(),
+//! )
+//! .unwrap_err();
+//! assert_eq!(error.kind(), ErrorKind::ProofVerification);
+//! ```
+
+use alloc::vec::Vec;
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+
+use crate::common::generate_proof_with_nonce;
+use crate::{
+    BlindedElement, CipherSuite, Group, Mode, Proof, Result, VoprfServer,
+    VoprfServerBatchEvaluateResult,
+};
+
+/// Evaluates and proves the `blinded_elements` with a random key instead of
+/// the key of `server`. The proof is valid, but not for the public key of
+/// `server`.
+///
+/// # Errors
+/// [`ErrorKind::Batch`](crate::ErrorKind::Batch) if the number of
+/// `blinded_elements` is longer then [`u16::MAX`].
+pub fn evaluate_with_wrong_key<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    server: &VoprfServer<CS>,
+    blinded_elements: &[BlindedElement<CS>],
+) -> Result<VoprfServerBatchEvaluateResult<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut other_server = VoprfServer::<CS>::new(rng)?;
+
+    // Don't accidentally pick the same key
+    while bool::from(
+        other_server
+            .get_public_key()
+            .ct_eq(&server.get_public_key()),
+    ) {
+        other_server = VoprfServer::<CS>::new(rng)?;
+    }
+
+    let messages: Vec<_> = other_server
+        .evaluate_only(blinded_elements.iter())
+        .collect();
+    let proof = other_server.prove(rng, blinded_elements.iter(), messages.iter())?;
+
+    Ok(VoprfServerBatchEvaluateResult { messages, proof })
+}
+
+/// Evaluates the `blinded_elements` correctly, but swaps the first two
+/// evaluation elements after proving them. Nothing is swapped for fewer then
+/// two `blinded_elements`.
+///
+/// # Errors
+/// [`ErrorKind::Batch`](crate::ErrorKind::Batch) if the number of
+/// `blinded_elements` is longer then [`u16::MAX`].
+pub fn evaluate_with_swapped_elements<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    server: &VoprfServer<CS>,
+    blinded_elements: &[BlindedElement<CS>],
+) -> Result<VoprfServerBatchEvaluateResult<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut messages: Vec<_> = server.evaluate_only(blinded_elements.iter()).collect();
+    let proof = server.prove(rng, blinded_elements.iter(), messages.iter())?;
+
+    if messages.len() >= 2 {
+        messages.swap(0, 1);
+    }
+
+    Ok(VoprfServerBatchEvaluateResult { messages, proof })
+}
+
+/// Evaluates the `blinded_elements` correctly, but proves them with the given
+/// `nonce` instead of a fresh one. The proof is valid, but any two proofs with
+/// the same nonce reveal the private key of `server`, see [`recover_key`].
+///
+/// # Errors
+/// [`ErrorKind::Batch`](crate::ErrorKind::Batch) if the number of
+/// `blinded_elements` is longer then [`u16::MAX`].
+pub fn evaluate_with_reused_nonce<CS: CipherSuite>(
+    server: &VoprfServer<CS>,
+    blinded_elements: &[BlindedElement<CS>],
+    nonce: <CS::Group as Group>::Scalar,
+) -> Result<VoprfServerBatchEvaluateResult<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let messages: Vec<_> = server.evaluate_only(blinded_elements.iter()).collect();
+    let proof = generate_proof_with_nonce::<CS>(
+        nonce,
+        server.sk,
+        CS::Group::base_elem(),
+        server.get_public_key(),
+        blinded_elements.iter().map(|element| element.0),
+        messages.iter().map(|element| element.0),
+        Mode::Voprf,
+    )?;
+
+    Ok(VoprfServerBatchEvaluateResult { messages, proof })
+}
+
+/// Recovers the private key from two proofs of the same key that reused the
+/// same nonce. Returns [`None`] if the proofs have the same challenge, e.g.
+/// because they are the same proof.
+///
+/// This can be used to assert that a server never reuses nonces.
+pub fn recover_key<CS: CipherSuite>(
+    first: &Proof<CS>,
+    second: &Proof<CS>,
+) -> Option<<CS::Group as Group>::Scalar>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    // s = r - c * k, so k = (s_2 - s_1) / (c_1 - c_2)
+    Option::from(CS::Group::invert_scalar_ct(
+        first.c_scalar - &second.c_scalar,
+    ))
+    .map(|inverse| (second.s_scalar - &first.s_scalar) * &inverse)
+}
+
+/// Produces a proof of random scalars, which doesn't verify for any
+/// evaluation.
+pub fn random_proof<CS: CipherSuite, R: RngCore + CryptoRng>(rng: &mut R) -> Proof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    Proof {
+        c_scalar: CS::Group::random_scalar(rng),
+        s_scalar: CS::Group::random_scalar(rng),
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use ::alloc::vec;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{Error, ErrorKind, Operation, VoprfClient};
+
+    fn malicious_rejection<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let inputs: [&[u8]; 2] = [b"first", b"second"];
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        for input in inputs {
+            let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let error = Error::new(ErrorKind::ProofVerification, Operation::VerifyProof);
+        let finalize = |result: &VoprfServerBatchEvaluateResult<CS>| {
+            VoprfClient::batch_finalize(
+                &inputs,
+                &client_states,
+                &result.messages,
+                &result.proof,
+                server.get_public_key(),
+            )
+            .map(|_| ())
+        };
+
+        let result = evaluate_with_wrong_key(&mut rng, &server, &client_messages).unwrap();
+        assert!(matches!(finalize(&result), Err(e) if e == error));
+
+        let result = evaluate_with_swapped_elements(&mut rng, &server, &client_messages).unwrap();
+        assert!(matches!(finalize(&result), Err(e) if e == error));
+
+        let mut result = server
+            .batch_blind_evaluate(&mut rng, &client_messages)
+            .unwrap();
+        result.proof = random_proof(&mut rng);
+        assert!(matches!(finalize(&result), Err(e) if e == error));
+
+        // Reused nonces verify, but leak the key
+        let nonce = CS::Group::random_scalar(&mut rng);
+        let first = evaluate_with_reused_nonce(&server, &client_messages, nonce).unwrap();
+        assert!(finalize(&first).is_ok());
+        let second = evaluate_with_reused_nonce(&server, &client_messages[..1], nonce).unwrap();
+        let key = recover_key(&first.proof, &second.proof).unwrap();
+        assert!(bool::from(key.ct_eq(&server.get_private_key())));
+        assert!(recover_key(&first.proof, &first.proof).is_none());
+
+        // Proofs with fresh nonces don't
+        let third = server
+            .batch_blind_evaluate(&mut rng, &client_messages)
+            .unwrap();
+        let key = recover_key(&first.proof, &third.proof).unwrap();
+        assert!(!bool::from(key.ct_eq(&server.get_private_key())));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            malicious_rejection::<Ristretto255>();
+        }
+
+        malicious_rejection::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the masked in-memory representation of server private keys
+
+use core::iter;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use crate::common::derive_hedged_nonce;
+use crate::{
+    kernel, BlindedElement, CipherSuite, EvaluationElement, Group, Mode, Operation, OprfServer,
+    Proof, Result, VoprfServer, VoprfServerEvaluateResult,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A private key `k` held as two shares `k = k1 + k2`, each uniformly random on
+/// its own. The key itself is never stored, the shares are only combined
+/// inside the scalar multiplication, as `P * k1 + P * k2`.
+#[derive_where(Clone, ZeroizeOnDrop)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar)]
+pub(crate) struct MaskedKey<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    k1: <CS::Group as Group>::Scalar,
+    k2: <CS::Group as Group>::Scalar,
+}
+
+/// An [`OprfServer`] whose private key is masked in memory, so a single
+/// disclosure of its memory, e.g. through a side channel or a partial memory
+/// dump, only reveals a random share instead of the key.
+///
+/// The shares should be refreshed periodically with
+/// [`rerandomize`](Self::rerandomize), e.g. after every few evaluations, so
+/// shares leaked at different times can't be combined.
+#[derive_where(Clone, ZeroizeOnDrop)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar)]
+pub struct MaskedOprfServer<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    key: MaskedKey<CS>,
+}
+
+/// A [`VoprfServer`] whose private key is masked in memory, like the one of a
+/// [`MaskedOprfServer`]. The proofs are computed on the shares as well.
+#[derive_where(Clone, ZeroizeOnDrop)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct MaskedVoprfServer<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    key: MaskedKey<CS>,
+    pk: <CS::Group as Group>::Elem,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> MaskedKey<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn new<R: RngCore + CryptoRng>(rng: &mut R, k: <CS::Group as Group>::Scalar) -> Self {
+        let k1 = CS::Group::random_scalar(rng);
+        let k2 = k - &k1;
+
+        Self { k1, k2 }
+    }
+
+    fn rerandomize<R: RngCore + CryptoRng>(&mut self, rng: &mut R) {
+        let mut mask = CS::Group::random_scalar(rng);
+        self.k1 = self.k1 + &mask;
+        self.k2 = self.k2 - &mask;
+        mask.zeroize();
+    }
+
+    fn mul(&self, elem: <CS::Group as Group>::Elem) -> <CS::Group as Group>::Elem {
+        kernel::blind_evaluate::<CS>(self.k1, elem) + &kernel::blind_evaluate::<CS>(self.k2, elem)
+    }
+
+    fn unmask(&self) -> <CS::Group as Group>::Scalar {
+        self.k1 + &self.k2
+    }
+}
+
+impl<CS: CipherSuite> MaskedOprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Masks the private key of `server`, which is zeroized afterwards.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R, server: OprfServer<CS>) -> Self {
+        Self {
+            key: MaskedKey::new(rng, server.sk),
+        }
+    }
+
+    /// Draws new shares of the private key, the key stays the same.
+    pub fn rerandomize<R: RngCore + CryptoRng>(&mut self, rng: &mut R) {
+        self.key.rerandomize(rng);
+    }
+
+    /// Computes the second step for the multiplicative blinding version of
+    /// DH-OPRF, like [`OprfServer::blind_evaluate`].
+    pub fn blind_evaluate(&self, blinded_element: &BlindedElement<CS>) -> EvaluationElement<CS> {
+        debug_span!("blind_evaluate_masked", mode = ?Mode::Oprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Oprf.label());
+
+        EvaluationElement(self.key.mul(blinded_element.0))
+    }
+
+    /// Unmasks the private key, e.g. to serialize the server.
+    pub fn into_server(self) -> OprfServer<CS> {
+        OprfServer {
+            sk: self.key.unmask(),
+        }
+    }
+}
+
+impl<CS: CipherSuite> MaskedVoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Masks the private key of `server`, which is zeroized afterwards.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R, server: VoprfServer<CS>) -> Self {
+        Self {
+            key: MaskedKey::new(rng, server.sk),
+            pk: server.pk,
+        }
+    }
+
+    /// Draws new shares of the private key, the key stays the same.
+    pub fn rerandomize<R: RngCore + CryptoRng>(&mut self, rng: &mut R) {
+        self.key.rerandomize(rng);
+    }
+
+    /// Retrieves the server's public key
+    pub fn get_public_key(&self) -> <CS::Group as Group>::Elem {
+        self.pk
+    }
+
+    /// Computes the second step for the multiplicative blinding version of
+    /// DH-OPRF, with a proof, like [`VoprfServer::try_blind_evaluate`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    /// fails and can't be completed.
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>> {
+        debug_span!("blind_evaluate_masked", mode = ?Mode::Voprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Voprf.label());
+
+        let message = EvaluationElement(self.key.mul(blinded_element.0));
+        let proof = self.generate_proof(rng, blinded_element.0, message.0)?;
+
+        Ok(VoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Unmasks the private key, e.g. to serialize the server.
+    pub fn into_server(self) -> VoprfServer<CS> {
+        VoprfServer {
+            sk: self.key.unmask(),
+            pk: self.pk,
+        }
+    }
+
+    /// Generates the proof of [`generate_proof`](crate::common::generate_proof)
+    /// on the shares: the composite `Z` is computed from the evaluated element
+    /// like a verifier does, the nonce is the sum of one hedged nonce per share
+    /// and the response is `s = (r - c * k1) - c * k2`.
+    ///
+    /// Can only fail with [`ErrorKind::Protocol`](crate::ErrorKind::Protocol).
+    fn generate_proof<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        c: <CS::Group as Group>::Elem,
+        d: <CS::Group as Group>::Elem,
+    ) -> Result<Proof<CS>> {
+        let mode = Mode::Voprf;
+        let a = CS::Group::base_elem();
+
+        let (m, z) = kernel::compute_composites::<CS, _, _>(
+            None,
+            self.pk,
+            iter::once(c),
+            iter::once(d),
+            mode,
+        )
+        .map_err(|error| error.with_operation(Operation::Evaluate))?;
+
+        let mut entropy = [[0; 32]; 2];
+        rng.fill_bytes(&mut entropy[0]);
+        rng.fill_bytes(&mut entropy[1]);
+        let r1 = derive_hedged_nonce::<CS>(self.key.k1, m, z, &entropy[0], mode);
+        let r2 = derive_hedged_nonce::<CS>(self.key.k2, m, z, &entropy[1], mode);
+        entropy.zeroize();
+        let mut r = r1? + &r2?;
+
+        let proof = kernel::compute_proof::<CS>(self.key.k1, r, a, self.pk, m, z, mode);
+        r.zeroize();
+        let proof = proof?;
+
+        Ok(Proof {
+            c_scalar: proof.c_scalar,
+            s_scalar: proof.s_scalar - &(proof.c_scalar * &self.key.k2),
+        })
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+    use crate::{OprfClient, VoprfClient};
+
+    fn masked_oprf<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let input = b"input";
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let expected = server.evaluate(input).unwrap();
+        let serialized = server.serialize();
+
+        let mut masked = MaskedOprfServer::new(&mut rng, server);
+        let key = masked.key.clone();
+        masked.rerandomize(&mut rng);
+        assert!(!bool::from(key.k1.ct_eq(&masked.key.k1)));
+        assert!(bool::from(key.unmask().ct_eq(&masked.key.unmask())));
+
+        let client_blind_result = OprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let message = masked.blind_evaluate(&client_blind_result.message);
+        let output = client_blind_result.state.finalize(input, &message).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(masked.into_server().serialize(), serialized);
+    }
+
+    fn masked_voprf<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let input = b"input";
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let expected = server.evaluate(input).unwrap();
+        let sk = server.get_private_key();
+
+        let mut masked = MaskedVoprfServer::new(&mut rng, server);
+        masked.rerandomize(&mut rng);
+
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let result = masked
+            .blind_evaluate(&mut rng, &client_blind_result.message)
+            .unwrap();
+        let output = client_blind_result
+            .state
+            .finalize(
+                input,
+                &result.message,
+                &result.proof,
+                masked.get_public_key(),
+            )
+            .unwrap();
+        assert_eq!(output, expected);
+
+        let wrong_pk = CS::Group::base_elem();
+        assert!(client_blind_result
+            .state
+            .finalize(input, &result.message, &result.proof, wrong_pk)
+            .is_err());
+        assert!(bool::from(
+            masked.into_server().get_private_key().ct_eq(&sk)
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            masked_oprf::<Ristretto255>();
+            masked_voprf::<Ristretto255>();
+        }
+
+        masked_oprf::<NistP256>();
+        masked_voprf::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the helpers to run the protocol under two ciphersuites at once
+
+use core::marker::PhantomData;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{BlindedElement, CipherSuite, OprfClientOps, OprfServerOps, Result};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A value for each of the two ciphersuites of a migration, e.g. the blinded
+/// elements a [`MigrationClient`] sends or the outputs it receives.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MigrationPair<Old, New> {
+    /// The value of the old ciphersuite
+    pub old: Old,
+    /// The value of the new ciphersuite
+    pub new: New,
+}
+
+/// A client that runs the same mode under an old ciphersuite `CS1` and a new
+/// ciphersuite `CS2` in one round trip with a [`MigrationServer`], so
+/// deployments can re-key their stored outputs to the new ciphersuite during a
+/// transition window.
+///
+/// `C1` and `C2` are the clients of the mode, e.g.
+/// [`VoprfClient`](crate::VoprfClient):
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # {
+/// use p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::{
+///     MigrationClient, MigrationServer, OprfClientOps, Ristretto255, VoprfClient, VoprfServer,
+/// };
+///
+/// let mut rng = OsRng;
+/// let server = MigrationServer::new(
+///     VoprfServer::<NistP256>::new(&mut rng).unwrap(),
+///     VoprfServer::<Ristretto255>::new(&mut rng).unwrap(),
+/// );
+///
+/// let (client, message) =
+///     MigrationClient::<_, _, VoprfClient<NistP256>, VoprfClient<Ristretto255>>::blind(
+///         b"input", &mut rng,
+///     )
+///     .unwrap();
+/// let response = server.blind_evaluate(&mut rng, &message, None).unwrap();
+/// let outputs = client
+///     .finalize(b"input", &response, server.public_keys(), None)
+///     .unwrap();
+///
+/// // The stored output of the old ciphersuite can now be replaced
+/// assert_eq!(outputs.old, server.evaluate(b"input", None).unwrap().old);
+/// # }
+/// ```
+#[derive_where(Clone; C1, C2)]
+#[derive_where(Debug; C1, C2)]
+pub struct MigrationClient<CS1, CS2, C1, C2>
+where
+    CS1: CipherSuite,
+    CS2: CipherSuite,
+    <CS1::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS1::Hash as BlockSizeUser>::BlockSize>,
+    <CS2::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS2::Hash as BlockSizeUser>::BlockSize>,
+    C1: OprfClientOps<CS1>,
+    C2: OprfClientOps<CS2>,
+{
+    old: C1,
+    new: C2,
+    #[derive_where(skip)]
+    _cs: PhantomData<(CS1, CS2)>,
+}
+
+/// A server that evaluates under an old ciphersuite `CS1` and a new
+/// ciphersuite `CS2` at once, for a [`MigrationClient`].
+///
+/// `S1` and `S2` are the servers of the mode, e.g.
+/// [`VoprfServer`](crate::VoprfServer).
+#[derive_where(Clone; S1, S2)]
+#[derive_where(Debug; S1, S2)]
+pub struct MigrationServer<CS1, CS2, S1, S2>
+where
+    CS1: CipherSuite,
+    CS2: CipherSuite,
+    <CS1::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS1::Hash as BlockSizeUser>::BlockSize>,
+    <CS2::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS2::Hash as BlockSizeUser>::BlockSize>,
+    S1: OprfServerOps<CS1>,
+    S2: OprfServerOps<CS2>,
+{
+    old: S1,
+    new: S2,
+    #[derive_where(skip)]
+    _cs: PhantomData<(CS1, CS2)>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS1, CS2, C1, C2> MigrationClient<CS1, CS2, C1, C2>
+where
+    CS1: CipherSuite,
+    CS2: CipherSuite,
+    <CS1::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS1::Hash as BlockSizeUser>::BlockSize>,
+    <CS2::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS2::Hash as BlockSizeUser>::BlockSize>,
+    C1: OprfClientOps<CS1>,
+    C2: OprfClientOps<CS2>,
+{
+    /// Blinds the `input` under both ciphersuites, with independent blinds.
+    ///
+    /// # Errors
+    /// The error of blinding under either ciphersuite.
+    #[allow(clippy::type_complexity)]
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(
+        Self,
+        MigrationPair<BlindedElement<CS1>, BlindedElement<CS2>>,
+    )> {
+        let (old, old_message) = C1::blind(input, blinding_factor_rng)?;
+        let (new, new_message) = C2::blind(input, blinding_factor_rng)?;
+
+        Ok((
+            Self {
+                old,
+                new,
+                _cs: PhantomData,
+            },
+            MigrationPair {
+                old: old_message,
+                new: new_message,
+            },
+        ))
+    }
+
+    /// Finalizes the `response` of a [`MigrationServer`] under both
+    /// ciphersuites. Fails if either fails, e.g. if either proof doesn't
+    /// verify, so the old output is never replaced by an unverified one.
+    ///
+    /// # Errors
+    /// The error of finalizing under either ciphersuite.
+    #[allow(clippy::type_complexity)]
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        response: &MigrationPair<
+            <C1::Server as OprfServerOps<CS1>>::Response,
+            <C2::Server as OprfServerOps<CS2>>::Response,
+        >,
+        pks: MigrationPair<
+            <C1::Server as OprfServerOps<CS1>>::PublicKey,
+            <C2::Server as OprfServerOps<CS2>>::PublicKey,
+        >,
+        info: Option<&[u8]>,
+    ) -> Result<MigrationPair<Output<CS1::FinalizeHash>, Output<CS2::FinalizeHash>>> {
+        Ok(MigrationPair {
+            old: self.old.finalize(input, &response.old, pks.old, info)?,
+            new: self.new.finalize(input, &response.new, pks.new, info)?,
+        })
+    }
+}
+
+impl<CS1, CS2, S1, S2> MigrationServer<CS1, CS2, S1, S2>
+where
+    CS1: CipherSuite,
+    CS2: CipherSuite,
+    <CS1::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS1::Hash as BlockSizeUser>::BlockSize>,
+    <CS2::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS2::Hash as BlockSizeUser>::BlockSize>,
+    S1: OprfServerOps<CS1>,
+    S2: OprfServerOps<CS2>,
+{
+    /// Combines the server of the `old` ciphersuite with the server of the
+    /// `new` one.
+    pub fn new(old: S1, new: S2) -> Self {
+        Self {
+            old,
+            new,
+            _cs: PhantomData,
+        }
+    }
+
+    /// Returns the server of the old ciphersuite.
+    pub fn old_server(&self) -> &S1 {
+        &self.old
+    }
+
+    /// Returns the server of the new ciphersuite.
+    pub fn new_server(&self) -> &S2 {
+        &self.new
+    }
+
+    /// Returns the public keys of both servers.
+    pub fn public_keys(&self) -> MigrationPair<S1::PublicKey, S2::PublicKey> {
+        MigrationPair {
+            old: self.old.public_key(),
+            new: self.new.public_key(),
+        }
+    }
+
+    /// Evaluates the blinded elements of a [`MigrationClient`] under both
+    /// ciphersuites.
+    ///
+    /// # Errors
+    /// The error of evaluating under either ciphersuite.
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        message: &MigrationPair<BlindedElement<CS1>, BlindedElement<CS2>>,
+        info: Option<&[u8]>,
+    ) -> Result<MigrationPair<S1::Response, S2::Response>> {
+        Ok(MigrationPair {
+            old: self.old.blind_evaluate(rng, &message.old, info)?,
+            new: self.new.blind_evaluate(rng, &message.new, info)?,
+        })
+    }
+
+    /// Computes the outputs of both ciphersuites directly, e.g. to re-key
+    /// stored outputs the server computed itself.
+    ///
+    /// # Errors
+    /// The error of evaluating under either ciphersuite.
+    #[allow(clippy::type_complexity)]
+    pub fn evaluate(
+        &self,
+        input: &[u8],
+        info: Option<&[u8]>,
+    ) -> Result<MigrationPair<Output<CS1::FinalizeHash>, Output<CS2::FinalizeHash>>> {
+        Ok(MigrationPair {
+            old: self.old.evaluate(input, info)?,
+            new: self.new.evaluate(input, info)?,
+        })
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{Error, ErrorKind, Operation, OprfClient, PoprfClient, VoprfClient};
+
+    fn migration_retrieval<CS1, CS2, C1, C2>(info: Option<&[u8]>)
+    where
+        CS1: CipherSuite,
+        CS2: CipherSuite,
+        <CS1::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS1::Hash as BlockSizeUser>::BlockSize>,
+        <CS2::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS2::Hash as BlockSizeUser>::BlockSize>,
+        C1: OprfClientOps<CS1>,
+        C2: OprfClientOps<CS2>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = MigrationServer::new(
+            C1::Server::new(&mut rng).unwrap(),
+            C2::Server::new(&mut rng).unwrap(),
+        );
+
+        let (client, message) =
+            MigrationClient::<CS1, CS2, C1, C2>::blind(input, &mut rng).unwrap();
+        let response = server.blind_evaluate(&mut rng, &message, info).unwrap();
+        let outputs = client
+            .finalize(input, &response, server.public_keys(), info)
+            .unwrap();
+
+        assert_eq!(outputs, server.evaluate(input, info).unwrap());
+        assert_eq!(
+            outputs.old,
+            server.old_server().evaluate(input, info).unwrap()
+        );
+        assert_eq!(
+            outputs.new,
+            server.new_server().evaluate(input, info).unwrap()
+        );
+
+        // A response from other keys fails under both ciphersuites
+        let other_server = MigrationServer::new(
+            C1::Server::new(&mut rng).unwrap(),
+            C2::Server::new(&mut rng).unwrap(),
+        );
+        let response = other_server
+            .blind_evaluate(&mut rng, &message, info)
+            .unwrap();
+        match client.finalize(input, &response, server.public_keys(), info) {
+            Ok(outputs) => assert_ne!(outputs, server.evaluate(input, info).unwrap()),
+            Err(error) => assert_eq!(
+                error,
+                Error::new(ErrorKind::ProofVerification, Operation::VerifyProof)
+            ),
+        }
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            migration_retrieval::<NistP256, Ristretto255, OprfClient<_>, OprfClient<_>>(None);
+            migration_retrieval::<NistP256, Ristretto255, VoprfClient<_>, VoprfClient<_>>(None);
+            migration_retrieval::<NistP256, Ristretto255, PoprfClient<_>, PoprfClient<_>>(Some(
+                b"info",
+            ));
+        }
+
+        migration_retrieval::<NistP256, NistP256, OprfClient<_>, OprfClient<_>>(None);
+        migration_retrieval::<NistP256, NistP256, VoprfClient<_>, VoprfClient<_>>(None);
+        migration_retrieval::<NistP256, NistP256, PoprfClient<_>, PoprfClient<_>>(Some(b"info"));
+
+        Ok(())
+    }
+}
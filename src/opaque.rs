@@ -0,0 +1,88 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the key derivation of the OPRF layer of OPAQUE
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+
+use crate::common::derive_key_internal;
+use crate::{CipherSuite, Mode, OprfServer, Result};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_OPAQUE_DERIVE_KEY_PAIR: [u8; 20] = *b"OPAQUE-DeriveKeyPair";
+
+///////////////////////////
+// Convenience Functions //
+// ===================== //
+///////////////////////////
+
+/// Derives the OPRF key of a credential from its `seed`, as in the
+/// `DeriveKeyPair(seed, "OPAQUE-DeriveKeyPair")` call of the OPAQUE
+/// specification. The `seed` is expanded by OPAQUE from its OPRF seed and the
+/// credential identifier beforehand.
+///
+/// # Errors
+/// [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol fails
+/// and can't be completed.
+pub fn derive_opaque_oprf_server<CS: CipherSuite>(seed: &[u8]) -> Result<OprfServer<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let sk = derive_key_internal::<CS>(seed, &STR_OPAQUE_DERIVE_KEY_PAIR, Mode::Oprf)?;
+
+    Ok(OprfServer { sk })
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::derive_keypair;
+    use crate::Group;
+
+    fn opaque_derivation<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let seed = [0x2A; 32];
+        let server = derive_opaque_oprf_server::<CS>(&seed).unwrap();
+        let (expected, _) =
+            derive_keypair::<CS>(&seed, b"OPAQUE-DeriveKeyPair", Mode::Oprf).unwrap();
+        assert_eq!(*server.serialize(), CS::Group::serialize_scalar(expected));
+
+        let other = derive_opaque_oprf_server::<CS>(&[0x2B; 32]).unwrap();
+        assert_ne!(server.serialize(), other.serialize());
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            opaque_derivation::<Ristretto255>();
+        }
+
+        opaque_derivation::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -7,22 +7,25 @@
 
 //! Contains the main OPRF API
 
-use core::iter::{self, Map};
+use core::iter::{self, Enumerate, Map};
 
 use derive_where::derive_where;
 use digest::core_api::BlockSizeUser;
-use digest::{Digest, Output, OutputSizeUser};
-use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U23, U256};
 use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
+use zeroize::Zeroize;
 
 use crate::common::{
-    derive_key_internal, deterministic_blind_unchecked, hash_to_group, i2osp_2,
-    server_evaluate_hash_input, BlindedElement, EvaluationElement, Mode, STR_FINALIZE,
+    blind_hashed_element, derive_key_internal, deterministic_blind_long_input_unchecked,
+    deterministic_blind_unchecked, hash_long_input_to_group, hash_to_group, hash_to_group_dst,
+    prehash_long_input, server_evaluate_hash_input, BlindedElement, EvaluationElement, Mode,
+    ZipRepeat, ZipRepeatExt,
 };
 #[cfg(feature = "serde")]
 use crate::serialization::serde::Scalar;
-use crate::{CipherSuite, Error, Group, Result};
+use crate::{kernel, CipherSuite, Error, ErrorKind, Group, Operation, Result};
 
 ///////////////
 // Constants //
@@ -84,7 +87,8 @@ where
     /// DH-OPRF.
     ///
     /// # Errors
-    /// [`Error::Input`] if the `input` is empty or longer then [`u16::MAX`].
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
     pub fn blind<R: RngCore + CryptoRng>(
         input: &[u8],
         blinding_factor_rng: &mut R,
@@ -103,7 +107,8 @@ where
     /// on the validity of the blinding factor!
     ///
     /// # Errors
-    /// [`Error::Input`] if the `input` is empty or longer then [`u16::MAX`].
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
     #[cfg(any(feature = "danger", test))]
     pub fn deterministic_blind_unchecked(
         input: &[u8],
@@ -112,7 +117,7 @@ where
         Self::deterministic_blind_unchecked_inner(input, blind)
     }
 
-    /// Can only fail with [`Error::Input`].
+    /// Can only fail with [`ErrorKind::Input`].
     fn deterministic_blind_unchecked_inner(
         input: &[u8],
         blind: <CS::Group as Group>::Scalar,
@@ -124,20 +129,135 @@ where
         })
     }
 
+    /// Like [`OprfClient::blind`], but in the long input mode, which pre-hashes
+    /// the `input` and therefore accepts inputs of any length. The client has
+    /// to be finalized with [`OprfClient::finalize_long_input`] and the
+    /// outputs are unrelated to the ones of the regular mode.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn blind_long_input<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<OprfClientBlindResult<CS>> {
+        let blind = CS::Group::random_scalar(blinding_factor_rng);
+        let blinded_element =
+            deterministic_blind_long_input_unchecked::<CS>(input, &blind, Mode::Oprf)?;
+        Ok(OprfClientBlindResult {
+            state: Self { blind },
+            message: BlindedElement(blinded_element),
+        })
+    }
+
+    /// Hashes the `input` to the element [`OprfClient::blind`] blinds, the
+    /// expensive half of blinding, which doesn't involve the blind. It can
+    /// therefore run on a helper device, e.g. the phone of a constrained
+    /// smartcard client, which then only runs [`OprfClient::blind_element`].
+    /// Note that the helper learns the `input`, so this is only useful if it
+    /// is trusted with the input, but not with the blind.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn hash_input_to_element(input: &[u8]) -> Result<<CS::Group as Group>::Elem> {
+        hash_to_group::<CS>(input, Mode::Oprf)
+            .map_err(|error| error.with_operation(Operation::Blind))
+    }
+
+    /// Like [`OprfClient::blind`], but takes the `hashed_element` the input was
+    /// already hashed to with [`OprfClient::hash_input_to_element`], e.g. by a
+    /// helper device or an earlier stage of a pipeline, instead of hashing the
+    /// input again, so only the scalar multiplication with the blind runs
+    /// here. The client still has to be finalized with the input, whose output
+    /// it only computes if the `hashed_element` was hashed from it.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `hashed_element` is the identity element.
+    pub fn blind_element<R: RngCore + CryptoRng>(
+        hashed_element: <CS::Group as Group>::Elem,
+        blinding_factor_rng: &mut R,
+    ) -> Result<OprfClientBlindResult<CS>> {
+        debug_span!("blind", mode = ?Mode::Oprf);
+
+        let blind = CS::Group::random_scalar(blinding_factor_rng);
+        let blinded_element = blind_hashed_element::<CS>(hashed_element, &blind)?;
+        Ok(OprfClientBlindResult {
+            state: Self { blind },
+            message: BlindedElement(blinded_element),
+        })
+    }
+
+    /// Like [`OprfClient::finalize`], but in the long input mode, see
+    /// [`OprfClient::blind_long_input`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn finalize_long_input(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        self.finalize(
+            &prehash_long_input::<CS>(input, Mode::Oprf),
+            evaluation_element,
+        )
+    }
+
     /// Computes the third step for the multiplicative blinding version of
     /// DH-OPRF, in which the client unblinds the server's message.
     ///
+    /// The client doesn't capture the `input`, so it only has to be available
+    /// again when finalizing, e.g. by reference into a buffer owned by the
+    /// caller, instead of being kept alive inside the client. It has to be
+    /// equal to the `input` passed to [`blind`](Self::blind), otherwise the
+    /// output is unrelated to it, which can't be detected.
+    ///
     /// # Errors
-    /// [`Error::Input`] if the `input` is empty or longer then [`u16::MAX`].
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
     pub fn finalize(
         &self,
         input: &[u8],
         evaluation_element: &EvaluationElement<CS>,
-    ) -> Result<Output<CS::Hash>> {
-        let unblinded_element = evaluation_element.0 * &CS::Group::invert_scalar(self.blind);
+    ) -> Result<Output<CS::FinalizeHash>> {
+        debug_span!("finalize", mode = ?Mode::Oprf);
+
+        let unblinded_element = kernel::unblind::<CS>(self.blind, evaluation_element.0);
         let mut outputs =
             finalize_after_unblind::<CS, _, _>(iter::once((input, unblinded_element)), &[]);
-        outputs.next().unwrap()
+        outputs
+            .next()
+            .unwrap_or_else(|| Err(Error::new(ErrorKind::Protocol, Operation::Finalize)))
+    }
+
+    /// Reconstructs a [OprfClient] from its `blind` and the `input` it was
+    /// computed over, e.g. after persisting it in a custom format. See
+    /// [`OprfClient::into_parts`] for the inverse.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `blind` is zero.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    pub fn from_parts(input: &[u8], blind: <CS::Group as Group>::Scalar) -> Result<Self> {
+        if CS::Group::is_zero_scalar(blind).into() {
+            return Err(ErrorKind::Deserialization.into());
+        }
+
+        Self::deterministic_blind_unchecked_inner(input, blind).map(|result| result.state)
+    }
+
+    /// Returns the `blind` of this [OprfClient]. The `input` isn't stored
+    /// and has to be persisted separately. See [`OprfClient::from_parts`]
+    /// for the inverse.
+    pub fn into_parts(self) -> <CS::Group as Group>::Scalar {
+        self.blind
+    }
+
+    /// Folds the `delta` returned by [`BlindedElement::rerandomize`] into this
+    /// client, so that it can finalize the server's response to the
+    /// re-randomized element.
+    pub fn apply_rerandomization(&mut self, delta: <CS::Group as Group>::Scalar) {
+        self.blind = self.blind * &delta;
     }
 
     /// Only used for test functions
@@ -161,7 +281,7 @@ where
     /// Produces a new instance of a [OprfServer] using a supplied RNG
     ///
     /// # Errors
-    /// [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
         let mut seed = GenericArray::<_, <CS::Group as Group>::ScalarLen>::default();
         rng.fill_bytes(&mut seed);
@@ -172,8 +292,8 @@ where
     /// of bytes to represent the server's private key
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if the private key is not a valid point on
-    /// the group or zero.
+    /// [`ErrorKind::Deserialization`] if the private key is not a valid point
+    /// on the group or zero.
     pub fn new_with_key(private_key_bytes: &[u8]) -> Result<Self> {
         let sk = CS::Group::deserialize_scalar(private_key_bytes)?;
         Ok(Self { sk })
@@ -185,15 +305,50 @@ where
     /// Corresponds to DeriveKeyPair() function from the VOPRF specification.
     ///
     /// # Errors
-    /// - [`Error::DeriveKeyPair`] if the `input` and `seed` together are longer
-    ///   then `u16::MAX - 3`.
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::DeriveKeyPair`] if the `input` and `seed` together are
+    ///   longer then `u16::MAX - 3`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn new_from_seed(seed: &[u8], info: &[u8]) -> Result<Self> {
         let sk = derive_key_internal::<CS>(seed, info, Mode::Oprf)?;
         Ok(Self { sk })
     }
 
-    // Only used for tests
+    /// Derives a child [OprfServer] from the private key of this server, domain
+    /// separated by `info`, e.g. to derive per-tenant keys from a single stored
+    /// master key on the fly. The private key is used as the seed of
+    /// DeriveKeyPair(), see [`OprfServer::new_from_seed`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::DeriveKeyPair`] if the `info` is longer then `u16::MAX -
+    ///   3` minus the private key length.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn derive_subkey(&self, info: &[u8]) -> Result<Self> {
+        let mut seed = CS::Group::serialize_scalar(self.sk);
+        let result = Self::new_from_seed(&seed, info);
+        seed.as_mut_slice().zeroize();
+        result
+    }
+
+    /// Produces a new instance of a [OprfServer] from the server's private key
+    /// scalar. See [`OprfServer::into_parts`] for the inverse.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the private key is zero.
+    pub fn from_parts(sk: <CS::Group as Group>::Scalar) -> Result<Self> {
+        if CS::Group::is_zero_scalar(sk).into() {
+            return Err(ErrorKind::Deserialization.into());
+        }
+
+        Ok(Self { sk })
+    }
+
+    /// Returns the server's private key scalar. See [`OprfServer::from_parts`]
+    /// for the inverse.
+    pub fn into_parts(self) -> <CS::Group as Group>::Scalar {
+        self.sk
+    }
+
+    /// Only used for tests
     #[cfg(test)]
     pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
         self.sk
@@ -203,32 +358,133 @@ where
     /// DH-OPRF. This message is sent from the server (who holds the OPRF key)
     /// to the client.
     pub fn blind_evaluate(&self, blinded_element: &BlindedElement<CS>) -> EvaluationElement<CS> {
-        EvaluationElement(blinded_element.0 * &self.sk)
+        debug_span!("blind_evaluate", mode = ?Mode::Oprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Oprf.label());
+
+        EvaluationElement(kernel::blind_evaluate::<CS>(self.sk, blinded_element.0))
     }
 
     /// Computes the output of the OPRF on the server side
     ///
     /// # Errors
-    /// [`Error::Input`]  if the `input` is longer then [`u16::MAX`].
-    pub fn evaluate(&self, input: &[u8]) -> Result<Output<<CS as CipherSuite>::Hash>> {
-        let input_element = hash_to_group::<CS>(input, Mode::Oprf)?;
-        if CS::Group::is_identity_elem(input_element).into() {
-            return Err(Error::Input);
-        };
-        let evaluated_element = input_element * &self.sk;
+    /// [`ErrorKind::Input`]  if the `input` is longer then [`u16::MAX`].
+    pub fn evaluate(&self, input: &[u8]) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
+        debug_span!("evaluate", mode = ?Mode::Oprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Oprf.label());
+
+        let input_element = hash_to_group::<CS>(input, Mode::Oprf)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        evaluate_element::<CS>(self.sk, input, input_element)
+    }
 
-        let issued_element = CS::Group::serialize_elem(evaluated_element);
+    /// Computes the outputs of the OPRF on the server side for many `inputs`
+    /// directly, e.g. to pseudonymize a column of identifiers under the key of
+    /// this server. The hash-to-curve setup is shared by the whole batch.
+    ///
+    /// The resulting outputs can each fail individually with the errors of
+    /// [`evaluate`](Self::evaluate), carrying the index of the offending item.
+    pub fn evaluate_batch<'a, I>(
+        &self,
+        inputs: I,
+    ) -> OprfServerEvaluateBatchResult<'a, CS, I::IntoIter>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        debug_span!("evaluate_batch", mode = ?Mode::Oprf);
+
+        inputs
+            .into_iter()
+            .zip_repeat((self.sk, hash_to_group_dst::<CS>(Mode::Oprf)))
+            .enumerate()
+            .map(|(index, (input, (sk, dst)))| {
+                count!("voprf_evaluations_total", 1, "mode" => Mode::Oprf.label());
+
+                CS::Group::hash_to_curve::<CS::Hash>(&[input], &dst)
+                    .map_err(|_| Error::from(ErrorKind::Input))
+                    .and_then(|input_element| evaluate_element::<CS>(sk, input, input_element))
+                    .map_err(|error| error.with_operation(Operation::Evaluate).with_index(index))
+            })
+    }
 
-        server_evaluate_hash_input::<CS>(input, None, issued_element)
+    /// Like [`OprfServer::evaluate`], but in the long input mode, see
+    /// [`OprfClient::blind_long_input`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn evaluate_long_input(
+        &self,
+        input: &[u8],
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
+        debug_span!("evaluate", mode = ?Mode::Oprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Oprf.label());
+
+        let prehashed_input = prehash_long_input::<CS>(input, Mode::Oprf);
+        let input_element = hash_long_input_to_group::<CS>(&prehashed_input, Mode::Oprf)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        evaluate_element::<CS>(self.sk, &prehashed_input, input_element)
     }
 }
 
+///////////////////////////
+// Convenience Functions //
+// ===================== //
+///////////////////////////
+
+/// Computes the output of the OPRF in base mode directly from the server's
+/// private key and the `input`, without running the oblivious protocol.
+///
+/// This is equivalent to [`OprfServer::new_with_key`] followed by
+/// [`OprfServer::evaluate`].
+///
+/// # Errors
+/// - [`ErrorKind::Deserialization`] if the private key is not a valid point on
+///   the group or zero.
+/// - [`ErrorKind::Input`] if the `input` is longer then [`u16::MAX`].
+pub fn evaluate_full<CS: CipherSuite>(
+    private_key_bytes: &[u8],
+    input: &[u8],
+) -> Result<Output<CS::FinalizeHash>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    OprfServer::<CS>::new_with_key(private_key_bytes)?.evaluate(input)
+}
+
+/// Runs the whole oblivious protocol in base mode in-process: blinds the
+/// `input`, evaluates it with the server's private key and finalizes the
+/// result.
+///
+/// The output is identical to the one of [`evaluate_full`], this is mainly
+/// useful for tests and for exercising the same code paths as a split client
+/// and server.
+///
+/// # Errors
+/// - [`ErrorKind::Deserialization`] if the private key is not a valid point on
+///   the group or zero.
+/// - [`ErrorKind::Input`] if the `input` is empty or longer then [`u16::MAX`].
+pub fn run_locally<CS: CipherSuite, R: RngCore + CryptoRng>(
+    blinding_factor_rng: &mut R,
+    private_key_bytes: &[u8],
+    input: &[u8],
+) -> Result<Output<CS::FinalizeHash>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let server = OprfServer::<CS>::new_with_key(private_key_bytes)?;
+    let OprfClientBlindResult { state, message } = OprfClient::blind(input, blinding_factor_rng)?;
+    let evaluation_element = server.blind_evaluate(&message);
+    state.finalize(input, &evaluation_element)
+}
+
 /////////////////////////
 // Convenience Structs //
 //==================== //
 /////////////////////////
 
 /// Contains the fields that are returned by a non-verifiable client blind
+#[derive_where(Clone)]
 #[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
 pub struct OprfClientBlindResult<CS: CipherSuite>
 where
@@ -241,17 +497,64 @@ where
     pub message: BlindedElement<CS>,
 }
 
+/// Concrete type of the outputs of [`OprfServer::evaluate_batch`].
+pub type OprfServerEvaluateBatchResult<'a, CS, I> = Map<
+    Enumerate<
+        ZipRepeat<
+            I,
+            (
+                <<CS as CipherSuite>::Group as Group>::Scalar,
+                GenericArray<u8, U23>,
+            ),
+        >,
+    >,
+    fn(
+        (
+            usize,
+            (
+                &'a [u8],
+                (
+                    <<CS as CipherSuite>::Group as Group>::Scalar,
+                    GenericArray<u8, U23>,
+                ),
+            ),
+        ),
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>>,
+>;
+
 /////////////////////
 // Inner functions //
 // =============== //
 /////////////////////
 
+/// Can only fail with [`ErrorKind::Input`].
+fn evaluate_element<CS: CipherSuite>(
+    sk: <CS::Group as Group>::Scalar,
+    input: &[u8],
+    input_element: <CS::Group as Group>::Elem,
+) -> Result<Output<<CS as CipherSuite>::FinalizeHash>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    if CS::Group::is_identity_elem(input_element).into() {
+        return Err(Error::new(ErrorKind::Input, Operation::Evaluate));
+    };
+    let evaluated_element = input_element * &sk;
+
+    let issued_element = CS::Group::serialize_elem(evaluated_element);
+
+    server_evaluate_hash_input::<CS>(input, None, issued_element)
+}
+
 type FinalizeAfterUnblindResult<'a, C, I, IE> = Map<
     IE,
-    fn((I, <<C as CipherSuite>::Group as Group>::Elem)) -> Result<Output<<C as CipherSuite>::Hash>>,
+    fn(
+        (I, <<C as CipherSuite>::Group as Group>::Elem),
+    ) -> Result<Output<<C as CipherSuite>::FinalizeHash>>,
 >;
 
-/// Returned values can only fail with [`Error::Input`].
+/// Returned values can only fail with [`ErrorKind::Input`].
 fn finalize_after_unblind<
     'a,
     CS: CipherSuite,
@@ -260,25 +563,13 @@ fn finalize_after_unblind<
 >(
     inputs_and_unblinded_elements: IE,
     _unused: &'a [u8],
-) -> FinalizeAfterUnblindResult<CS, I, IE>
+) -> FinalizeAfterUnblindResult<'a, CS, I, IE>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
     inputs_and_unblinded_elements.map(|(input, unblinded_element)| {
-        let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
-
-        // hashInput = I2OSP(len(input), 2) || input ||
-        //             I2OSP(len(unblindedElement), 2) || unblindedElement ||
-        //             "Finalize"
-        // return Hash(hashInput)
-        Ok(CS::Hash::new()
-            .chain_update(i2osp_2(input.as_ref().len()).map_err(|_| Error::Input)?)
-            .chain_update(input.as_ref())
-            .chain_update(elem_len)
-            .chain_update(CS::Group::serialize_elem(unblinded_element))
-            .chain_update(&STR_FINALIZE)
-            .finalize())
+        kernel::finalize::<CS>(input.as_ref(), None, unblinded_element)
     })
 }
 
@@ -291,6 +582,7 @@ where
 mod tests {
     use core::ptr;
 
+    use ::alloc::vec;
     use generic_array::sequence::Concat;
     use rand::rngs::OsRng;
 
@@ -303,7 +595,7 @@ mod tests {
         key: <CS::Group as Group>::Scalar,
         info: &[u8],
         mode: Mode,
-    ) -> Output<CS::Hash>
+    ) -> Output<CS::FinalizeHash>
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
             IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
@@ -334,6 +626,39 @@ mod tests {
         assert_eq!(client_finalize_result, res2);
     }
 
+    fn borrowed_input_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let input = vec![1; 4096];
+        let client_blind_result = OprfClient::<CS>::blind(&input, &mut rng).unwrap();
+        drop(input);
+
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let message = server.blind_evaluate(&client_blind_result.message);
+        let input = vec![1; 4096];
+        let output = client_blind_result
+            .state
+            .finalize(&input, &message)
+            .unwrap();
+        assert_eq!(
+            output,
+            prf::<CS>(&input, server.get_private_key(), &[], Mode::Oprf)
+        );
+
+        // A different input silently results in an unrelated output
+        let output = client_blind_result
+            .state
+            .finalize(&[2; 4096], &message)
+            .unwrap();
+        assert_ne!(
+            output,
+            prf::<CS>(&input, server.get_private_key(), &[], Mode::Oprf)
+        );
+    }
+
     fn base_inversion_unsalted<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -387,6 +712,128 @@ mod tests {
         assert!(client_finalize != server_evaluate);
     }
 
+    fn long_input_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = vec![1; usize::from(u16::MAX) + 1];
+        let mut rng = OsRng;
+        let client_blind_result = OprfClient::<CS>::blind_long_input(&input, &mut rng).unwrap();
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        assert!(server.evaluate(&input).is_err());
+        let server_result = server.blind_evaluate(&client_blind_result.message);
+        let client_finalize = client_blind_result
+            .state
+            .finalize_long_input(&input, &server_result)
+            .unwrap();
+        assert_eq!(client_finalize, server.evaluate_long_input(&input).unwrap());
+
+        // The long input mode is domain separated from the regular mode, even for
+        // the pre-hashed input
+        let prehashed_input = prehash_long_input::<CS>(&input, Mode::Oprf);
+        assert!(client_finalize != server.evaluate(&prehashed_input).unwrap());
+    }
+
+    fn batch_server_evaluate<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let long_input = vec![1; usize::from(u16::MAX) + 1];
+        let inputs: [&[u8]; 3] = [b"alice", b"bob", b"carol"];
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+
+        for (input, output) in inputs.iter().zip(server.evaluate_batch(inputs)) {
+            assert_eq!(output.unwrap(), server.evaluate(input).unwrap());
+        }
+
+        let mut outputs = server.evaluate_batch([inputs[0], &long_input, inputs[2]]);
+        assert!(outputs.next().unwrap().is_ok());
+        assert_eq!(
+            outputs.next().unwrap(),
+            Err(Error::new(ErrorKind::Input, Operation::Evaluate).with_index(1))
+        );
+        assert!(outputs.next().unwrap().is_ok());
+        assert!(outputs.next().is_none());
+    }
+
+    fn one_shot_evaluation<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let key = server.serialize();
+
+        let output = evaluate_full::<CS>(&key, input).unwrap();
+        assert_eq!(output, server.evaluate(input).unwrap());
+        assert_eq!(output, run_locally::<CS, _>(&mut rng, &key, input).unwrap());
+
+        let wrong_input = b"wrong input";
+        assert!(output != evaluate_full::<CS>(&key, wrong_input).unwrap());
+    }
+
+    fn combined_evaluation<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let client_blind_result = OprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_1 = OprfServer::<CS>::new(&mut rng).unwrap();
+        let server_2 = OprfServer::<CS>::new(&mut rng).unwrap();
+        let combined_server =
+            OprfServer::<CS>::from_parts(server_1.get_private_key() + &server_2.get_private_key())
+                .unwrap();
+
+        // The sum of the evaluations equals the evaluation under the summed key
+        let blinded_element =
+            BlindedElement::<CS>::from_value(client_blind_result.message.get_value());
+        let message_1 = server_1.blind_evaluate(&blinded_element);
+        let message_2 = server_2.blind_evaluate(&blinded_element);
+        let message =
+            EvaluationElement::<CS>::from_value(message_1.get_value() + &message_2.get_value());
+        assert!(message == combined_server.blind_evaluate(&blinded_element));
+
+        let client_finalize_result = client_blind_result.state.finalize(input, &message).unwrap();
+        assert_eq!(
+            client_finalize_result,
+            combined_server.evaluate(input).unwrap()
+        );
+    }
+
+    fn parts_roundtrip<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let server = OprfServer::<CS>::from_parts(server.into_parts()).unwrap();
+        let client_blind_result = OprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let message = server.blind_evaluate(&client_blind_result.message);
+
+        let blind = client_blind_result.state.into_parts();
+        let client = OprfClient::<CS>::from_parts(input, blind).unwrap();
+        let client_finalize_result = client.finalize(input, &message).unwrap();
+        assert_eq!(client_finalize_result, server.evaluate(input).unwrap());
+
+        assert!(matches!(
+            OprfClient::<CS>::from_parts(input, CS::Group::zero_scalar()),
+            Err(error) if error.kind() == ErrorKind::Deserialization
+        ));
+        assert!(matches!(
+            OprfServer::<CS>::from_parts(CS::Group::zero_scalar()),
+            Err(error) if error.kind() == ErrorKind::Deserialization
+        ));
+    }
+
     fn zeroize_oprf_client<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -433,16 +880,28 @@ mod tests {
             use crate::Ristretto255;
 
             base_retrieval::<Ristretto255>();
+            borrowed_input_retrieval::<Ristretto255>();
             base_inversion_unsalted::<Ristretto255>();
             server_evaluate::<Ristretto255>();
+            long_input_retrieval::<Ristretto255>();
+            batch_server_evaluate::<Ristretto255>();
+            one_shot_evaluation::<Ristretto255>();
+            combined_evaluation::<Ristretto255>();
+            parts_roundtrip::<Ristretto255>();
 
             zeroize_oprf_client::<Ristretto255>();
             zeroize_oprf_server::<Ristretto255>();
         }
 
         base_retrieval::<NistP256>();
+        borrowed_input_retrieval::<NistP256>();
         base_inversion_unsalted::<NistP256>();
         server_evaluate::<NistP256>();
+        long_input_retrieval::<NistP256>();
+        batch_server_evaluate::<NistP256>();
+        one_shot_evaluation::<NistP256>();
+        combined_evaluation::<NistP256>();
+        parts_roundtrip::<NistP256>();
 
         zeroize_oprf_client::<NistP256>();
         zeroize_oprf_server::<NistP256>();
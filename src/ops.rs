@@ -0,0 +1,389 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains traits abstracting over the clients and servers of all modes
+
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    BlindedElement, CipherSuite, Error, ErrorKind, EvaluationElement, Group, Operation, OprfClient,
+    OprfServer, PoprfClient, PoprfServer, PoprfServerEvaluateResult, Result, VoprfClient,
+    VoprfServer, VoprfServerEvaluateResult,
+};
+
+////////////
+// Traits //
+// ====== //
+////////////
+
+/// The server side of the protocol, implemented by [`OprfServer`],
+/// [`VoprfServer`] and [`PoprfServer`].
+///
+/// This allows to write code once for all modes, e.g. a generic token issuer.
+/// Modes without public input reject any `info` with [`ErrorKind::Info`].
+pub trait OprfServerOps<CS: CipherSuite>: Sized
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The public key clients need to finalize, `()` in base mode.
+    type PublicKey: Copy;
+
+    /// The response sent to the client by
+    /// [`blind_evaluate`](Self::blind_evaluate).
+    type Response;
+
+    /// Produces a new server using a supplied RNG.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self>;
+
+    /// Retrieves the server's public key.
+    fn public_key(&self) -> Self::PublicKey;
+
+    /// Computes the second step of the protocol, the response to a
+    /// [`BlindedElement`] sent by the client.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than [`u16::MAX`] or the
+    ///   mode doesn't support it.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        info: Option<&[u8]>,
+    ) -> Result<Self::Response>;
+
+    /// Computes the output of the protocol on the server side.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than [`u16::MAX`] or the
+    ///   mode doesn't support it.
+    /// - [`ErrorKind::Input`] if the `input` is longer than [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    fn evaluate(&self, input: &[u8], info: Option<&[u8]>) -> Result<Output<CS::FinalizeHash>>;
+}
+
+/// The client side of the protocol, implemented by [`OprfClient`],
+/// [`VoprfClient`] and [`PoprfClient`].
+///
+/// See [`OprfServerOps`] for the server side.
+pub trait OprfClientOps<CS: CipherSuite>: Sized
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The server of the same mode.
+    type Server: OprfServerOps<CS>;
+
+    /// Computes the first step of the protocol, returning the client state and
+    /// the [`BlindedElement`] to send to the server.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer than
+    /// [`u16::MAX`].
+    fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<CS>)>;
+
+    /// Computes the last step of the protocol from the server's `response`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than [`u16::MAX`] or the
+    ///   mode doesn't support it.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer than
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::ProofVerification`] if the proof failed to verify.
+    fn finalize(
+        &self,
+        input: &[u8],
+        response: &<Self::Server as OprfServerOps<CS>>::Response,
+        pk: <Self::Server as OprfServerOps<CS>>::PublicKey,
+        info: Option<&[u8]>,
+    ) -> Result<Output<CS::FinalizeHash>>;
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<CS: CipherSuite> OprfServerOps<CS> for OprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type PublicKey = ();
+
+    type Response = EvaluationElement<CS>;
+
+    fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
+        Self::new(rng)
+    }
+
+    fn public_key(&self) -> Self::PublicKey {}
+
+    fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        _: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        info: Option<&[u8]>,
+    ) -> Result<Self::Response> {
+        reject_info(info, Operation::Evaluate)?;
+        Ok(self.blind_evaluate(blinded_element))
+    }
+
+    fn evaluate(&self, input: &[u8], info: Option<&[u8]>) -> Result<Output<CS::FinalizeHash>> {
+        reject_info(info, Operation::Evaluate)?;
+        self.evaluate(input)
+    }
+}
+
+impl<CS: CipherSuite> OprfServerOps<CS> for VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type PublicKey = <CS::Group as Group>::Elem;
+
+    type Response = VoprfServerEvaluateResult<CS>;
+
+    fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
+        Self::new(rng)
+    }
+
+    fn public_key(&self) -> Self::PublicKey {
+        self.get_public_key()
+    }
+
+    fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        info: Option<&[u8]>,
+    ) -> Result<Self::Response> {
+        reject_info(info, Operation::Evaluate)?;
+        self.try_blind_evaluate(rng, blinded_element)
+    }
+
+    fn evaluate(&self, input: &[u8], info: Option<&[u8]>) -> Result<Output<CS::FinalizeHash>> {
+        reject_info(info, Operation::Evaluate)?;
+        self.evaluate(input)
+    }
+}
+
+impl<CS: CipherSuite> OprfServerOps<CS> for PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type PublicKey = <CS::Group as Group>::Elem;
+
+    type Response = PoprfServerEvaluateResult<CS>;
+
+    fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
+        Self::new(rng)
+    }
+
+    fn public_key(&self) -> Self::PublicKey {
+        self.get_public_key()
+    }
+
+    fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        info: Option<&[u8]>,
+    ) -> Result<Self::Response> {
+        self.blind_evaluate(rng, blinded_element, info)
+    }
+
+    fn evaluate(&self, input: &[u8], info: Option<&[u8]>) -> Result<Output<CS::FinalizeHash>> {
+        self.evaluate(input, info)
+    }
+}
+
+impl<CS: CipherSuite> OprfClientOps<CS> for OprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type Server = OprfServer<CS>;
+
+    fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<CS>)> {
+        let result = Self::blind(input, blinding_factor_rng)?;
+        Ok((result.state, result.message))
+    }
+
+    fn finalize(
+        &self,
+        input: &[u8],
+        response: &EvaluationElement<CS>,
+        _: (),
+        info: Option<&[u8]>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        reject_info(info, Operation::Finalize)?;
+        self.finalize(input, response)
+    }
+}
+
+impl<CS: CipherSuite> OprfClientOps<CS> for VoprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type Server = VoprfServer<CS>;
+
+    fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<CS>)> {
+        let result = Self::blind(input, blinding_factor_rng)?;
+        Ok((result.state, result.message))
+    }
+
+    fn finalize(
+        &self,
+        input: &[u8],
+        response: &VoprfServerEvaluateResult<CS>,
+        pk: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        reject_info(info, Operation::Finalize)?;
+        self.finalize(input, &response.message, &response.proof, pk)
+    }
+}
+
+impl<CS: CipherSuite> OprfClientOps<CS> for PoprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type Server = PoprfServer<CS>;
+
+    fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<CS>)> {
+        let result = Self::blind(input, blinding_factor_rng)?;
+        Ok((result.state, result.message))
+    }
+
+    fn finalize(
+        &self,
+        input: &[u8],
+        response: &PoprfServerEvaluateResult<CS>,
+        pk: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        self.finalize(input, &response.message, &response.proof, pk, info)
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+/// Fails with [`ErrorKind::Info`] if an `info` is given to a mode without
+/// public input.
+fn reject_info(info: Option<&[u8]>, operation: Operation) -> Result<()> {
+    match info {
+        Some(_) => Err(Error::new(ErrorKind::Info, operation)),
+        None => Ok(()),
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    /// Issues and redeems a token, written once for all modes
+    fn generic_retrieval<CS: CipherSuite, C: OprfClientOps<CS>>(info: Option<&[u8]>)
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = C::Server::new(&mut rng).unwrap();
+        let (client, message) = C::blind(input, &mut rng).unwrap();
+        let response = server.blind_evaluate(&mut rng, &message, info).unwrap();
+        let output = client
+            .finalize(input, &response, server.public_key(), info)
+            .unwrap();
+        assert_eq!(output, server.evaluate(input, info).unwrap());
+    }
+
+    fn info_rejection<CS: CipherSuite, C: OprfClientOps<CS>>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let info = Some(b"info".as_slice());
+        let mut rng = OsRng;
+        let server = C::Server::new(&mut rng).unwrap();
+        let (client, message) = C::blind(input, &mut rng).unwrap();
+
+        assert!(matches!(
+            server.blind_evaluate(&mut rng, &message, info),
+            Err(error) if error == Error::new(ErrorKind::Info, Operation::Evaluate)
+        ));
+        assert_eq!(
+            server.evaluate(input, info),
+            Err(Error::new(ErrorKind::Info, Operation::Evaluate))
+        );
+
+        let response = server.blind_evaluate(&mut rng, &message, None).unwrap();
+        assert_eq!(
+            client.finalize(input, &response, server.public_key(), info),
+            Err(Error::new(ErrorKind::Info, Operation::Finalize))
+        );
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            generic_retrieval::<Ristretto255, OprfClient<_>>(None);
+            generic_retrieval::<Ristretto255, VoprfClient<_>>(None);
+            generic_retrieval::<Ristretto255, PoprfClient<_>>(Some(b"info"));
+            info_rejection::<Ristretto255, OprfClient<_>>();
+            info_rejection::<Ristretto255, VoprfClient<_>>();
+        }
+
+        generic_retrieval::<NistP256, OprfClient<_>>(None);
+        generic_retrieval::<NistP256, VoprfClient<_>>(None);
+        generic_retrieval::<NistP256, PoprfClient<_>>(Some(b"info"));
+        info_rejection::<NistP256, OprfClient<_>>();
+        info_rejection::<NistP256, VoprfClient<_>>();
+
+        Ok(())
+    }
+}
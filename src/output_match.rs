@@ -0,0 +1,123 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the constant-time matching of outputs against stored outputs
+
+use generic_array::{ArrayLength, GenericArray};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+///////////////////////////
+// Convenience Functions //
+// ===================== //
+///////////////////////////
+
+/// Looks up `candidate` in `outputs`, e.g. a finalized output of a login
+/// attempt in the stored outputs of a user record, and returns the index of its
+/// first occurrence.
+///
+/// Every stored output is compared in full and no comparison exits early, so
+/// the running time only depends on the number of stored outputs, not on their
+/// contents or on where, or whether, `candidate` was found. Only the result
+/// itself is revealed.
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::{find_output, OprfServer};
+///
+/// let server = OprfServer::<CipherSuite>::new(&mut OsRng).unwrap();
+/// let record = [
+///     server.evaluate(b"old password").unwrap(),
+///     server.evaluate(b"password").unwrap(),
+/// ];
+///
+/// let output = server.evaluate(b"password").unwrap();
+/// assert_eq!(find_output(&output, &record), Some(1));
+/// ```
+pub fn find_output<N: ArrayLength<u8>>(
+    candidate: &GenericArray<u8, N>,
+    outputs: &[GenericArray<u8, N>],
+) -> Option<usize> {
+    let mut found = Choice::from(0);
+    let mut position = 0u64;
+
+    for (index, output) in (0..).zip(outputs) {
+        let matches = candidate.ct_eq(output) & !found;
+        position.conditional_assign(&index, matches);
+        found |= matches;
+    }
+
+    if bool::from(found) {
+        usize::try_from(position).ok()
+    } else {
+        None
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use digest::core_api::BlockSizeUser;
+    use digest::OutputSizeUser;
+    use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{CipherSuite, OprfClient, OprfServer, Result};
+
+    fn match_outputs<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let record = [
+            server.evaluate(b"first").unwrap(),
+            server.evaluate(b"second").unwrap(),
+            server.evaluate(b"first").unwrap(),
+        ];
+
+        let client_blind_result = OprfClient::<CS>::blind(b"second", &mut rng).unwrap();
+        let message = server.blind_evaluate(&client_blind_result.message);
+        let output = client_blind_result
+            .state
+            .finalize(b"second", &message)
+            .unwrap();
+        assert_eq!(find_output(&output, &record), Some(1));
+
+        // The first occurrence is returned
+        assert_eq!(find_output(&record[0], &record), Some(0));
+
+        let other = server.evaluate(b"third").unwrap();
+        assert_eq!(find_output(&other, &record), None);
+        assert_eq!(find_output(&output, &[]), None);
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            match_outputs::<Ristretto255>();
+        }
+
+        match_outputs::<NistP256>();
+
+        Ok(())
+    }
+}
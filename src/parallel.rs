@@ -0,0 +1,483 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the batch operations parallelized with [rayon](https://docs.rs/rayon)
+
+use alloc::vec::Vec;
+
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+use rayon::prelude::*;
+
+use crate::common::invert_scalar;
+use crate::poprf::compute_tweak;
+use crate::{
+    kernel, BatchProof, BlindedElement, CipherSuite, Error, ErrorKind, EvaluationElement, Group,
+    Mode, Operation, PoprfClient, PoprfPreparedTweak, PoprfServer,
+    PoprfServerBatchEvaluateArrayResult, PoprfServerBatchEvaluateFinishResult,
+    PoprfServerBatchEvaluateResult, PreparedEvaluationElement, Proof, Result, VerifierContext,
+    VoprfClient, VoprfServer, VoprfServerBatchEvaluateArrayResult,
+    VoprfServerBatchEvaluateChunkedResult, VoprfServerBatchEvaluateFinishResult,
+    VoprfServerBatchEvaluateResult,
+};
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> VoprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::Elem: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+{
+    /// Like [`batch_finalize`](Self::batch_finalize), but unblinds and
+    /// finalizes the items in parallel after verifying the `proof`, returning
+    /// the outputs in the same order.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Batch`] if the number of `inputs`, `clients` and
+    ///   `messages` don't match or is longer then [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    ///
+    /// The resulting messages can each fail individually with
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn par_batch_finalize<I, P>(
+        inputs: &[I],
+        clients: &[Self],
+        messages: &[EvaluationElement<CS>],
+        proof: &P,
+        pk: <CS::Group as Group>::Elem,
+    ) -> Result<Vec<Result<Output<CS::FinalizeHash>>>>
+    where
+        I: AsRef<[u8]> + Sync,
+        P: BatchProof<CS> + ?Sized,
+    {
+        debug_span!(
+            "par_batch_finalize",
+            mode = ?Mode::Voprf,
+            batch_size = clients.len(),
+        );
+
+        if inputs.len() != clients.len() {
+            return Err(Error::new(ErrorKind::Batch, Operation::Finalize));
+        }
+
+        proof.verify_batch(
+            CS::Group::base_elem(),
+            pk,
+            clients.iter().map(|client| client.blinded_element),
+            messages.iter().map(|message| message.0),
+            Mode::Voprf,
+        )?;
+
+        Ok(inputs
+            .par_iter()
+            .zip(clients)
+            .zip(messages)
+            .enumerate()
+            .map(|(index, ((input, client), message))| {
+                let unblinded_element = kernel::unblind::<CS>(client.blind, message.0);
+                kernel::finalize::<CS>(input.as_ref(), None, unblinded_element)
+                    .map_err(|error| error.with_index(index))
+            })
+            .collect())
+    }
+}
+
+impl<CS: CipherSuite> VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::Elem: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+{
+    /// Like [`batch_blind_evaluate`](Self::batch_blind_evaluate), but evaluates
+    /// the `blinded_elements` in parallel. The proof is generated afterwards
+    /// over the whole batch.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Batch`] if the number of `blinded_elements` is longer then
+    /// [`u16::MAX`]
+    pub fn par_batch_blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>> {
+        debug_span!(
+            "par_batch_blind_evaluate",
+            mode = ?Mode::Voprf,
+            batch_size = blinded_elements.len(),
+        );
+        count!("voprf_evaluations_total", blinded_elements.len(), "mode" => Mode::Voprf.label());
+        record!("voprf_batch_size", blinded_elements.len(), "mode" => Mode::Voprf.label());
+
+        let prepared_evaluation_elements: Vec<_> = blinded_elements
+            .par_iter()
+            .map(|blinded_element| {
+                PreparedEvaluationElement(EvaluationElement(kernel::blind_evaluate::<CS>(
+                    self.sk,
+                    blinded_element.0,
+                )))
+            })
+            .collect();
+        let VoprfServerBatchEvaluateFinishResult { messages, proof } = self
+            .batch_blind_evaluate_finish::<_, _, Vec<_>>(
+                rng,
+                blinded_elements.iter(),
+                &prepared_evaluation_elements,
+            )?;
+        let messages = messages.collect();
+
+        Ok(VoprfServerBatchEvaluateResult { messages, proof })
+    }
+}
+
+impl<CS: CipherSuite> PoprfClient<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::Elem: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+{
+    /// Like [`batch_finalize`](Self::batch_finalize), but unblinds and
+    /// finalizes the items in parallel after verifying the `proof`, returning
+    /// the outputs in the same order.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Batch`] if the number of `inputs`, `clients` and
+    ///   `messages` don't match or is longer than [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    ///
+    /// The resulting messages can each fail individually with
+    /// [`ErrorKind::Input`] if the `input` is empty or longer than
+    /// [`u16::MAX`].
+    pub fn par_batch_finalize<I: AsRef<[u8]> + Sync>(
+        inputs: &[I],
+        clients: &[Self],
+        messages: &[EvaluationElement<CS>],
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+    ) -> Result<Vec<Result<Output<CS::FinalizeHash>>>> {
+        debug_span!(
+            "par_batch_finalize",
+            mode = ?Mode::Poprf,
+            batch_size = clients.len(),
+        );
+
+        if inputs.len() != clients.len() {
+            return Err(Error::new(ErrorKind::Batch, Operation::Finalize));
+        }
+
+        let context = VerifierContext::poprf(pk, info)?;
+        context.verify(
+            messages.iter().map(|message| message.0),
+            clients.iter().map(|client| client.blinded_element),
+            proof,
+        )?;
+
+        let info = context.info();
+
+        Ok(inputs
+            .par_iter()
+            .zip(clients)
+            .zip(messages)
+            .enumerate()
+            .map(|(index, ((input, client), message))| {
+                let unblinded_element = kernel::unblind::<CS>(client.blind, message.0);
+                kernel::finalize::<CS>(input.as_ref(), Some(info), unblinded_element)
+                    .map_err(|error| error.with_index(index))
+            })
+            .collect())
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::Elem: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+{
+    /// Like [`batch_blind_evaluate`](Self::batch_blind_evaluate), but evaluates
+    /// the `blinded_elements` in parallel. The proof is generated afterwards
+    /// over the whole batch.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Batch`] if the number of `blinded_elements` is longer
+    ///   than [`u16::MAX`].
+    pub fn par_batch_blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        info: Option<&[u8]>,
+    ) -> Result<PoprfServerBatchEvaluateResult<CS>> {
+        debug_span!(
+            "par_batch_blind_evaluate",
+            mode = ?Mode::Poprf,
+            batch_size = blinded_elements.len(),
+        );
+        count!("voprf_evaluations_total", blinded_elements.len(), "mode" => Mode::Poprf.label());
+        record!("voprf_batch_size", blinded_elements.len(), "mode" => Mode::Poprf.label());
+
+        let prepared_tweak = PoprfPreparedTweak(compute_tweak::<CS>(self.sk, info)?);
+        let inverted_tweak = invert_scalar::<CS>(prepared_tweak.0);
+
+        let prepared_evaluation_elements: Vec<_> = blinded_elements
+            .par_iter()
+            .map(|blinded_element| {
+                PreparedEvaluationElement(EvaluationElement(kernel::blind_evaluate::<CS>(
+                    inverted_tweak,
+                    blinded_element.0,
+                )))
+            })
+            .collect();
+        let PoprfServerBatchEvaluateFinishResult { messages, proof } =
+            Self::batch_blind_evaluate_finish::<_, _, Vec<_>>(
+                rng,
+                blinded_elements.iter(),
+                &prepared_evaluation_elements,
+                &prepared_tweak,
+            )?;
+        let messages = messages.collect();
+
+        Ok(PoprfServerBatchEvaluateResult { messages, proof })
+    }
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+macro_rules! impl_into_parallel_iterator {
+    ($($result:ident$(<$n:ident>)? => $iter:ty),+$(,)?) => {
+        $(
+            /// Iterates over the `messages` in parallel, dropping the `proof`.
+            impl<CS: CipherSuite$(, const $n: usize)?> IntoParallelIterator
+                for $result<CS$(, $n)?>
+            where
+                <CS::Hash as OutputSizeUser>::OutputSize:
+                    IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+                <CS::Group as Group>::Elem: Send,
+            {
+                type Item = EvaluationElement<CS>;
+                type Iter = $iter;
+
+                fn into_par_iter(self) -> Self::Iter {
+                    self.messages.into_par_iter()
+                }
+            }
+
+            /// Iterates over the `messages` in parallel.
+            impl<'a, CS: CipherSuite$(, const $n: usize)?> IntoParallelIterator
+                for &'a $result<CS$(, $n)?>
+            where
+                <CS::Hash as OutputSizeUser>::OutputSize:
+                    IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+                <CS::Group as Group>::Elem: Sync,
+            {
+                type Item = &'a EvaluationElement<CS>;
+                type Iter = rayon::slice::Iter<'a, EvaluationElement<CS>>;
+
+                fn into_par_iter(self) -> Self::Iter {
+                    self.messages.par_iter()
+                }
+            }
+        )+
+    };
+}
+
+impl_into_parallel_iterator!(
+    VoprfServerBatchEvaluateResult => rayon::vec::IntoIter<EvaluationElement<CS>>,
+    VoprfServerBatchEvaluateArrayResult<N> => rayon::array::IntoIter<EvaluationElement<CS>, N>,
+    VoprfServerBatchEvaluateChunkedResult => rayon::vec::IntoIter<EvaluationElement<CS>>,
+    PoprfServerBatchEvaluateResult => rayon::vec::IntoIter<EvaluationElement<CS>>,
+    PoprfServerBatchEvaluateArrayResult<N> => rayon::array::IntoIter<EvaluationElement<CS>, N>,
+);
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    const INPUTS: [&[u8]; 4] = [b"a", b"b", b"c", b"d"];
+
+    fn voprf_par_batch<CS: CipherSuite>() -> Result<()>
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::Elem: Send + Sync,
+        <CS::Group as Group>::Scalar: Send + Sync,
+    {
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng)?;
+        let (clients, blinded_elements): (Vec<_>, Vec<_>) = INPUTS
+            .iter()
+            .map(|input| {
+                VoprfClient::<CS>::blind(input, &mut rng)
+                    .map(|result| (result.state, result.message))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+
+        let sequential = server.batch_blind_evaluate(&mut rng, &blinded_elements)?;
+        let parallel = server.par_batch_blind_evaluate(&mut rng, &blinded_elements)?;
+        assert!(parallel.messages == sequential.messages);
+
+        let parallel_messages: Vec<_> = (&parallel).into_par_iter().cloned().collect();
+        assert!(parallel_messages == sequential.messages);
+
+        let sequential_outputs: Vec<_> = VoprfClient::batch_finalize(
+            &INPUTS,
+            &clients,
+            &sequential.messages,
+            &sequential.proof,
+            server.get_public_key(),
+        )?
+        .collect();
+        let parallel_outputs = VoprfClient::par_batch_finalize(
+            &INPUTS,
+            &clients,
+            &parallel.messages,
+            &parallel.proof,
+            server.get_public_key(),
+        )?;
+        assert_eq!(parallel_outputs, sequential_outputs);
+
+        // The parallel result can be finalized sequentially as well
+        let outputs: Vec<_> = VoprfClient::batch_finalize(
+            &INPUTS,
+            &clients,
+            &parallel.messages,
+            &parallel.proof,
+            server.get_public_key(),
+        )?
+        .collect();
+        assert_eq!(outputs, sequential_outputs);
+
+        let error = VoprfClient::par_batch_finalize(
+            &INPUTS,
+            &clients,
+            &parallel.messages,
+            &parallel.proof,
+            VoprfServer::<CS>::new(&mut rng)?.get_public_key(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ProofVerification);
+
+        let error = VoprfClient::par_batch_finalize(
+            &INPUTS[1..],
+            &clients,
+            &parallel.messages,
+            &parallel.proof,
+            server.get_public_key(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Batch);
+
+        let messages: Vec<_> = parallel.into_par_iter().collect();
+        assert!(messages == sequential.messages);
+
+        Ok(())
+    }
+
+    fn poprf_par_batch<CS: CipherSuite>() -> Result<()>
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::Elem: Send + Sync,
+        <CS::Group as Group>::Scalar: Send + Sync,
+    {
+        let mut rng = OsRng;
+        let info = Some(b"info".as_slice());
+        let server = PoprfServer::<CS>::new(&mut rng)?;
+        let (clients, blinded_elements): (Vec<_>, Vec<_>) = INPUTS
+            .iter()
+            .map(|input| {
+                PoprfClient::<CS>::blind(input, &mut rng)
+                    .map(|result| (result.state, result.message))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+
+        let sequential = server.batch_blind_evaluate(&mut rng, &blinded_elements, info)?;
+        let parallel = server.par_batch_blind_evaluate(&mut rng, &blinded_elements, info)?;
+        assert!(parallel.messages == sequential.messages);
+
+        let sequential_outputs: Vec<_> = PoprfClient::batch_finalize(
+            INPUTS.iter().copied(),
+            &clients,
+            &sequential.messages,
+            &sequential.proof,
+            server.get_public_key(),
+            info,
+        )?
+        .collect();
+        let parallel_outputs = PoprfClient::par_batch_finalize(
+            &INPUTS,
+            &clients,
+            &parallel.messages,
+            &parallel.proof,
+            server.get_public_key(),
+            info,
+        )?;
+        assert_eq!(parallel_outputs, sequential_outputs);
+
+        let error = PoprfClient::par_batch_finalize(
+            &INPUTS,
+            &clients,
+            &parallel.messages,
+            &parallel.proof,
+            server.get_public_key(),
+            Some(b"other info"),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ProofVerification);
+
+        let messages: Vec<_> = parallel.into_par_iter().collect();
+        assert!(messages == sequential.messages);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            voprf_par_batch::<Ristretto255>()?;
+            poprf_par_batch::<Ristretto255>()?;
+        }
+
+        voprf_par_batch::<NistP256>()?;
+        poprf_par_batch::<NistP256>()?;
+
+        Ok(())
+    }
+}
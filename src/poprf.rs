@@ -9,25 +9,32 @@
 
 #[cfg(feature = "alloc")]
 use alloc::vec::Vec;
-use core::iter::{self, Map, Repeat, Zip};
+use core::iter::{self, Enumerate, Map, Zip};
 
 use derive_where::derive_where;
 use digest::core_api::BlockSizeUser;
-use digest::{Digest, Output, OutputSizeUser};
+use digest::{Output, OutputSizeUser};
 use generic_array::sequence::Concat;
-use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
 use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
+use subtle::CtOption;
+use zeroize::Zeroize;
 
 use crate::common::{
-    create_context_string, derive_keypair, deterministic_blind_unchecked, generate_proof,
-    hash_to_group, i2osp_2, server_evaluate_hash_input, verify_proof, BlindedElement,
-    EvaluationElement, Mode, PreparedEvaluationElement, Proof, STR_FINALIZE, STR_HASH_TO_SCALAR,
-    STR_INFO,
+    array_map_ref, blind_hashed_element, create_context_string, derive_keypair,
+    deterministic_blind_long_input_unchecked, deterministic_blind_unchecked, generate_proof,
+    hash_long_input_to_group, hash_to_group, i2osp_2, invert_scalar, prehash_long_input,
+    server_evaluate_hash_input, try_array_from_fn, try_array_from_results, BlindedElement,
+    EvaluationElement, Mode, PreparedEvaluationElement, Proof, ZipRepeat, ZipRepeatExt,
+    STR_HASH_TO_SCALAR, STR_INFO,
 };
 #[cfg(feature = "serde")]
 use crate::serialization::serde::{Element, Scalar};
-use crate::{CipherSuite, Error, Group, Result};
+use crate::{
+    kernel, CipherSuite, Error, ErrorKind, Group, Operation, OprfServer, Result, VerifierContext,
+    VoprfServer,
+};
 
 ////////////////////////////
 // High-level API Structs //
@@ -74,6 +81,22 @@ where
     pub(crate) pk: <CS::Group as Group>::Elem,
 }
 
+/// A [`PoprfClient`] finalizer bound to the public key of a server and one
+/// public `info`, e.g. the epoch tokens are issued for, so that clients
+/// evaluated with that `info` can't accidentally be finalized with another.
+///
+/// The tweaked key of the `info` is computed once on construction, like with
+/// [`VerifierContext::poprf`], and re-used by every finalization.
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Elem)]
+pub struct PoprfClientWithInfo<'a, CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    context: VerifierContext<'a, CS>,
+}
+
 /////////////////////////
 // API Implementations //
 // =================== //
@@ -88,7 +111,8 @@ where
     /// DH-OPRF.
     ///
     /// # Errors
-    /// [`Error::Input`] if the `input` is empty or longer than [`u16::MAX`].
+    /// [`ErrorKind::Input`] if the `input` is empty or longer than
+    /// [`u16::MAX`].
     pub fn blind<R: RngCore + CryptoRng>(
         input: &[u8],
         blinding_factor_rng: &mut R,
@@ -97,6 +121,24 @@ where
         Self::deterministic_blind_unchecked_inner(input, blind)
     }
 
+    /// Allows for batching of the blinding of a fixed number of `inputs`
+    /// without memory allocation.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if any of the `inputs` is empty or longer than
+    /// [`u16::MAX`], carrying the index of the offending input.
+    pub fn batch_blind_array<R: RngCore + CryptoRng, const N: usize>(
+        inputs: &[&[u8]; N],
+        blinding_factor_rng: &mut R,
+    ) -> Result<PoprfClientBatchBlindArrayResult<CS, N>> {
+        let states: [Self; N] = try_array_from_results(array_map_ref(inputs, |input| {
+            Self::blind(input, blinding_factor_rng).map(|result| result.state)
+        }))?;
+        let messages = array_map_ref(&states, |state| BlindedElement(state.blinded_element));
+
+        Ok(PoprfClientBatchBlindArrayResult { states, messages })
+    }
+
     /// Computes the first step for the multiplicative blinding version of
     /// DH-OPRF, taking a blinding factor scalar as input instead of sampling
     /// from an RNG.
@@ -107,7 +149,8 @@ where
     /// on the validity of the blinding factor!
     ///
     /// # Errors
-    /// [`Error::Input`] if the `input` is empty or longer than [`u16::MAX`].
+    /// [`ErrorKind::Input`] if the `input` is empty or longer than
+    /// [`u16::MAX`].
     #[cfg(any(feature = "danger", test))]
     pub fn deterministic_blind_unchecked(
         input: &[u8],
@@ -116,7 +159,7 @@ where
         Self::deterministic_blind_unchecked_inner(input, blind)
     }
 
-    /// Can only fail with [`Error::Input`].
+    /// Can only fail with [`ErrorKind::Input`].
     fn deterministic_blind_unchecked_inner(
         input: &[u8],
         blind: <CS::Group as Group>::Scalar,
@@ -131,14 +174,110 @@ where
         })
     }
 
+    /// Like [`PoprfClient::blind`], but in the long input mode, which
+    /// pre-hashes the `input` and therefore accepts inputs of any length. The
+    /// client has to be finalized with [`PoprfClient::finalize_long_input`] and
+    /// the outputs are unrelated to the ones of the regular mode.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn blind_long_input<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<PoprfClientBlindResult<CS>> {
+        let blind = CS::Group::random_scalar(blinding_factor_rng);
+        let blinded_element =
+            deterministic_blind_long_input_unchecked::<CS>(input, &blind, Mode::Poprf)?;
+        Ok(PoprfClientBlindResult {
+            state: Self {
+                blind,
+                blinded_element,
+            },
+            message: BlindedElement(blinded_element),
+        })
+    }
+
+    /// Hashes the `input` to the element [`PoprfClient::blind`] blinds, the
+    /// expensive half of blinding, which doesn't involve the blind. It can
+    /// therefore run on a helper device, e.g. the phone of a constrained
+    /// smartcard client, which then only runs [`PoprfClient::blind_element`].
+    /// Note that the helper learns the `input`, so this is only useful if it
+    /// is trusted with the input, but not with the blind.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn hash_input_to_element(input: &[u8]) -> Result<<CS::Group as Group>::Elem> {
+        hash_to_group::<CS>(input, Mode::Poprf)
+            .map_err(|error| error.with_operation(Operation::Blind))
+    }
+
+    /// Like [`PoprfClient::blind`], but takes the `hashed_element` the input
+    /// was already hashed to with [`PoprfClient::hash_input_to_element`],
+    /// e.g. by a helper device or an earlier stage of a pipeline, instead
+    /// of hashing the input again, so only the scalar multiplication with
+    /// the blind runs here. The client still has to be finalized with the
+    /// input, whose output it only computes if the `hashed_element` was
+    /// hashed from it.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `hashed_element` is the identity element.
+    pub fn blind_element<R: RngCore + CryptoRng>(
+        hashed_element: <CS::Group as Group>::Elem,
+        blinding_factor_rng: &mut R,
+    ) -> Result<PoprfClientBlindResult<CS>> {
+        debug_span!("blind", mode = ?Mode::Poprf);
+
+        let blind = CS::Group::random_scalar(blinding_factor_rng);
+        let blinded_element = blind_hashed_element::<CS>(hashed_element, &blind)?;
+        Ok(PoprfClientBlindResult {
+            state: Self {
+                blind,
+                blinded_element,
+            },
+            message: BlindedElement(blinded_element),
+        })
+    }
+
+    /// Like [`PoprfClient::finalize`], but in the long input mode, see
+    /// [`PoprfClient::blind_long_input`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    pub fn finalize_long_input(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        self.finalize(
+            &prehash_long_input::<CS>(input, Mode::Poprf),
+            evaluation_element,
+            proof,
+            pk,
+            info,
+        )
+    }
+
     /// Computes the third step for the multiplicative blinding version of
     /// DH-OPRF, in which the client unblinds the server's message.
     ///
+    /// Like [`OprfClient::finalize`](crate::OprfClient::finalize), the `input`
+    /// is borrowed and not captured in the client. It has to be equal to the
+    /// `input` passed to [`blind`](Self::blind): the `proof` only covers the
+    /// blinded element, so a different `input` still verifies, but the output
+    /// is unrelated to it.
+    ///
     /// # Errors
-    /// - [`Error::Info`] if the `info` is longer than `u16::MAX`.
-    /// - [`Error::Input`] if the `input` is empty or longer than [`u16::MAX`].
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
-    /// - [`Error::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer than
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
     pub fn finalize(
         &self,
         input: &[u8],
@@ -146,27 +285,103 @@ where
         proof: &Proof<CS>,
         pk: <CS::Group as Group>::Elem,
         info: Option<&[u8]>,
-    ) -> Result<Output<CS::Hash>> {
+    ) -> Result<Output<CS::FinalizeHash>> {
+        debug_span!("finalize", mode = ?Mode::Poprf);
+
         let clients = core::array::from_ref(self);
         let messages = core::array::from_ref(evaluation_element);
 
         let mut batch_result =
             Self::batch_finalize(iter::once(input), clients, messages, proof, pk, info)?;
-        batch_result.next().unwrap()
+        batch_result
+            .next()
+            .unwrap_or_else(|| Err(Error::new(ErrorKind::Protocol, Operation::Finalize)))
+    }
+
+    /// Finalizes the server's response, accepting a proof under any of the
+    /// public keys in `pks`, e.g. while issuer keys are rotated and several are
+    /// valid at the same time.
+    ///
+    /// Returns the index into `pks` of the key the proof verified against,
+    /// together with the output.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify
+    ///   against all of the `pks`.
+    pub fn finalize_with_any_key(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pks: &[<CS::Group as Group>::Elem],
+        info: Option<&[u8]>,
+    ) -> Result<(usize, Output<CS::FinalizeHash>)> {
+        for (index, pk) in pks.iter().enumerate() {
+            match self.finalize(input, evaluation_element, proof, *pk, info) {
+                Ok(output) => return Ok((index, output)),
+                Err(error) if error.kind() == ErrorKind::ProofVerification => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::ProofVerification,
+            Operation::VerifyProof,
+        ))
+    }
+
+    /// Finalizes the same blinded `input` against the responses of multiple
+    /// servers, e.g. when querying several issuers with one [BlindedElement].
+    ///
+    /// Each item of `responses` is a caller-chosen key identifier, the public
+    /// key of the server and its response. The identifier is returned together
+    /// with the result of that server, so outputs stay associated with the key
+    /// they were computed under. Note that sending the same [BlindedElement] to
+    /// multiple servers allows them to link these requests.
+    ///
+    /// The resulting outputs can each fail individually with the errors of
+    /// [`finalize`](Self::finalize).
+    pub fn finalize_multi_key<'a, K, I>(
+        &'a self,
+        input: &'a [u8],
+        info: Option<&'a [u8]>,
+        responses: I,
+    ) -> PoprfClientMultiKeyFinalizeResult<'a, CS, K, I::IntoIter>
+    where
+        I: IntoIterator<
+            Item = (
+                K,
+                <CS::Group as Group>::Elem,
+                &'a PoprfServerEvaluateResult<CS>,
+            ),
+        >,
+    {
+        responses.into_iter().zip_repeat((self, input, info)).map(
+            |((key, pk, response), (client, input, info))| {
+                let output = client.finalize(input, &response.message, &response.proof, pk, info);
+                (key, output)
+            },
+        )
     }
 
     /// Allows for batching of the finalization of multiple [PoprfClient]
     /// and [EvaluationElement] pairs
     ///
     /// # Errors
-    /// - [`Error::Info`] if the `info` is longer than `u16::MAX`.
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
-    /// - [`Error::Batch`] if the number of `inputs`, `clients` and `messages`
-    ///   don't match or is longer than [`u16::MAX`].
-    /// - [`Error::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Batch`] if the number of `inputs`, `clients` and
+    ///   `messages` don't match or is longer than [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
     ///
-    /// The resulting messages can each fail individually with [`Error::Input`]
-    /// if the `input` is empty or longer than [`u16::MAX`].
+    /// The resulting messages can each fail individually with
+    /// [`ErrorKind::Input`] if the `input` is empty or longer than
+    /// [`u16::MAX`].
+    ///
+    /// The outputs are computed lazily, from either end, as the returned
+    /// iterator is consumed.
     pub fn batch_finalize<'a, II: 'a + Iterator<Item = &'a [u8]> + ExactSizeIterator, IC, IM>(
         inputs: II,
         clients: &'a IC,
@@ -182,11 +397,208 @@ where
         &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
         <&'a IM as IntoIterator>::IntoIter: ExactSizeIterator,
     {
-        let unblinded_elements = poprf_unblind(clients, messages, pk, proof, info)?;
+        debug_span!(
+            "batch_finalize",
+            mode = ?Mode::Poprf,
+            batch_size = clients.into_iter().len(),
+        );
+
+        let context = VerifierContext::poprf(pk, info)?;
+        let unblinded_elements = poprf_unblind(clients, messages, proof, &context)?;
 
         finalize_after_unblind::<'a, CS, _, _>(unblinded_elements, inputs, info)
     }
 
+    /// Alternative version of [`batch_finalize`](Self::batch_finalize) for a
+    /// fixed number of items, returning the outputs in an array.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Input`] if any of the `inputs` is empty or longer than
+    ///   [`u16::MAX`], carrying the index of the offending input.
+    pub fn batch_finalize_array<const N: usize>(
+        inputs: &[&[u8]; N],
+        clients: &[Self; N],
+        messages: &[EvaluationElement<CS>; N],
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+    ) -> Result<[Output<CS::FinalizeHash>; N]> {
+        let mut outputs =
+            Self::batch_finalize(inputs.iter().copied(), clients, messages, proof, pk, info)?;
+        // This can't fail, all arrays have the same length.
+        try_array_from_fn(|_| {
+            outputs
+                .next()
+                .unwrap_or_else(|| Err(Error::new(ErrorKind::Protocol, Operation::Finalize)))
+        })
+    }
+
+    /// Like [`finalize`](Self::finalize), but verifies the `proof` with the
+    /// precomputation of a [`VerifierContext`], which also provides the
+    /// `info`, instead of the public key.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer than
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the `context` wasn't prepared with
+    ///   [`VerifierContext::poprf`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    pub fn finalize_with_context(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        context: &VerifierContext<'_, CS>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        debug_span!("finalize_with_context", mode = ?Mode::Poprf);
+
+        let clients = core::array::from_ref(self);
+        let messages = core::array::from_ref(evaluation_element);
+
+        let mut batch_result = Self::batch_finalize_with_context(
+            iter::once(input),
+            clients,
+            messages,
+            proof,
+            context,
+        )?;
+        batch_result
+            .next()
+            .unwrap_or_else(|| Err(Error::new(ErrorKind::Protocol, Operation::Finalize)))
+    }
+
+    /// Like [`batch_finalize`](Self::batch_finalize), but verifies the `proof`
+    /// with the precomputation of a [`VerifierContext`], which also provides
+    /// the `info`, instead of the public key.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Protocol`] if the `context` wasn't prepared with
+    ///   [`VerifierContext::poprf`].
+    /// - [`ErrorKind::Batch`] if the number of `inputs`, `clients` and
+    ///   `messages` don't match or is longer than [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    ///
+    /// The resulting messages can each fail individually with
+    /// [`ErrorKind::Input`] if the `input` is empty or longer than
+    /// [`u16::MAX`].
+    pub fn batch_finalize_with_context<
+        'a,
+        II: 'a + Iterator<Item = &'a [u8]> + ExactSizeIterator,
+        IC,
+        IM,
+    >(
+        inputs: II,
+        clients: &'a IC,
+        messages: &'a IM,
+        proof: &Proof<CS>,
+        context: &VerifierContext<'a, CS>,
+    ) -> Result<PoprfClientBatchFinalizeResult<'a, CS, II, IC, IM>>
+    where
+        CS: 'a,
+        &'a IC: 'a + IntoIterator<Item = &'a PoprfClient<CS>>,
+        <&'a IC as IntoIterator>::IntoIter: ExactSizeIterator,
+        &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+        <&'a IM as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        debug_span!(
+            "batch_finalize_with_context",
+            mode = ?Mode::Poprf,
+            batch_size = clients.into_iter().len(),
+        );
+
+        context.check_mode(Mode::Poprf)?;
+        let unblinded_elements = poprf_unblind(clients, messages, proof, context)?;
+
+        finalize_after_unblind::<'a, CS, _, _>(unblinded_elements, inputs, Some(context.info()))
+    }
+
+    /// Finalizes a batch in which each element was evaluated with its own
+    /// `info`, e.g. by
+    /// [`PoprfServer::batch_blind_evaluate_with_infos`].
+    ///
+    /// A proof is bound to the tweaked key of a single `info`, so each item of
+    /// `responses` carries its own proof and is verified individually against
+    /// the tweaked key of the aligned item of `infos`.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Batch`] if the number of `inputs`, `clients`, `responses`
+    /// and `infos` don't match.
+    ///
+    /// The resulting outputs can each fail individually with the errors of
+    /// [`finalize`](Self::finalize), carrying the index of the offending item.
+    pub fn batch_finalize_with_infos<'a, II, IC, IR, IF>(
+        inputs: II,
+        clients: &'a IC,
+        responses: &'a IR,
+        pk: <CS::Group as Group>::Elem,
+        infos: IF,
+    ) -> Result<PoprfClientBatchFinalizeWithInfosResult<'a, CS, II, IC, IR, IF>>
+    where
+        CS: 'a,
+        II: 'a + Iterator<Item = &'a [u8]> + ExactSizeIterator,
+        &'a IC: 'a + IntoIterator<Item = &'a PoprfClient<CS>>,
+        <&'a IC as IntoIterator>::IntoIter: ExactSizeIterator,
+        &'a IR: 'a + IntoIterator<Item = &'a PoprfServerEvaluateResult<CS>>,
+        <&'a IR as IntoIterator>::IntoIter: ExactSizeIterator,
+        IF: 'a + Iterator<Item = Option<&'a [u8]>> + ExactSizeIterator,
+    {
+        let clients = clients.into_iter();
+        let responses = responses.into_iter();
+
+        if inputs.len() != clients.len()
+            || inputs.len() != responses.len()
+            || inputs.len() != infos.len()
+        {
+            return Err(Error::new(ErrorKind::Batch, Operation::Finalize));
+        }
+
+        Ok(inputs
+            .zip(clients)
+            .zip(responses)
+            .zip(infos)
+            .zip_repeat(pk)
+            .enumerate()
+            .map(|(index, ((((input, client), response), info), pk))| {
+                client
+                    .finalize(input, &response.message, &response.proof, pk, info)
+                    .map_err(|error| error.with_index(index))
+            }))
+    }
+
+    /// Reconstructs a [PoprfClient] from its `blind` and the `input` it was
+    /// computed over, e.g. after persisting it in a custom format. See
+    /// [`PoprfClient::into_parts`] for the inverse.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `blind` is zero.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer than
+    ///   [`u16::MAX`].
+    pub fn from_parts(input: &[u8], blind: <CS::Group as Group>::Scalar) -> Result<Self> {
+        if CS::Group::is_zero_scalar(blind).into() {
+            return Err(ErrorKind::Deserialization.into());
+        }
+
+        Self::deterministic_blind_unchecked_inner(input, blind).map(|result| result.state)
+    }
+
+    /// Returns the `blind` of this [PoprfClient]. The `input` isn't stored
+    /// and has to be persisted separately. See [`PoprfClient::from_parts`]
+    /// for the inverse.
+    pub fn into_parts(self) -> <CS::Group as Group>::Scalar {
+        self.blind
+    }
+
+    /// Folds the `delta` returned by [`BlindedElement::rerandomize`] into this
+    /// client, so that it can finalize the server's response to the
+    /// re-randomized element.
+    pub fn apply_rerandomization(&mut self, delta: <CS::Group as Group>::Scalar) {
+        self.blind = self.blind * &delta;
+        self.blinded_element = self.blinded_element * &delta;
+    }
+
     /// Only used for test functions
     #[cfg(test)]
     pub fn get_blind(&self) -> <CS::Group as Group>::Scalar {
@@ -194,6 +606,88 @@ where
     }
 }
 
+impl<'a, CS: CipherSuite> PoprfClientWithInfo<'a, CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Binds the public key `pk` of the server and the public `info`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn new(pk: <CS::Group as Group>::Elem, info: Option<&'a [u8]>) -> Result<Self> {
+        Ok(Self {
+            context: VerifierContext::poprf(pk, info)?,
+        })
+    }
+
+    /// Binds the public key `pk` of the server and an empty `info`, the same as
+    /// [`new`](Self::new) with `None` or `Some(&[])`.
+    ///
+    /// An empty `info` still tweaks the key and is not the same as the VOPRF
+    /// mode: the outputs differ from a [`VoprfServer`] with the same private
+    /// key, and the server has to evaluate with an empty `info` as well.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn without_info(pk: <CS::Group as Group>::Elem) -> Result<Self> {
+        Self::new(pk, None)
+    }
+
+    /// Returns the bound `info`, to send it to the server together with the
+    /// [`BlindedElement`].
+    pub fn info(&self) -> &'a [u8] {
+        self.context.info()
+    }
+
+    /// Finalizes the `client` with the bound public key and `info`, like
+    /// [`PoprfClient::finalize`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer than
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify, e.g.
+    ///   because the server evaluated with another `info`.
+    pub fn finalize(
+        &self,
+        client: &PoprfClient<CS>,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        client.finalize_with_context(input, evaluation_element, proof, &self.context)
+    }
+
+    /// Finalizes the `clients` with the bound public key and `info`, like
+    /// [`PoprfClient::batch_finalize`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Batch`] if the number of `inputs`, `clients` and
+    ///   `messages` don't match or is longer than [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    ///
+    /// The resulting messages can each fail individually with
+    /// [`ErrorKind::Input`] if the `input` is empty or longer than
+    /// [`u16::MAX`].
+    pub fn batch_finalize<'b, II: 'b + Iterator<Item = &'b [u8]> + ExactSizeIterator, IC, IM>(
+        &'b self,
+        inputs: II,
+        clients: &'b IC,
+        messages: &'b IM,
+        proof: &Proof<CS>,
+    ) -> Result<PoprfClientBatchFinalizeResult<'b, CS, II, IC, IM>>
+    where
+        CS: 'b,
+        &'b IC: 'b + IntoIterator<Item = &'b PoprfClient<CS>>,
+        <&'b IC as IntoIterator>::IntoIter: ExactSizeIterator,
+        &'b IM: 'b + IntoIterator<Item = &'b EvaluationElement<CS>>,
+        <&'b IM as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        PoprfClient::batch_finalize_with_context(inputs, clients, messages, proof, &self.context)
+    }
+}
+
 impl<CS: CipherSuite> PoprfServer<CS>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
@@ -202,7 +696,7 @@ where
     /// Produces a new instance of a [PoprfServer] using a supplied RNG
     ///
     /// # Errors
-    /// [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
         let mut seed = GenericArray::<_, <CS::Group as Group>::ScalarLen>::default();
         rng.fill_bytes(&mut seed);
@@ -214,8 +708,8 @@ where
     /// bytes to represent the server's private key
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if the private key is not a valid point on
-    /// the group or zero.
+    /// [`ErrorKind::Deserialization`] if the private key is not a valid point
+    /// on the group or zero.
     pub fn new_with_key(key: &[u8]) -> Result<Self> {
         let sk = CS::Group::deserialize_scalar(key)?;
         let pk = CS::Group::base_elem() * &sk;
@@ -227,16 +721,57 @@ where
     ///
     /// Corresponds to DeriveKeyPair() function from the VOPRF specification.
     ///
+    /// The mode is part of the derivation, so the same `seed` and `info` derive
+    /// a different key than in the other modes. To run several modes off the
+    /// same key, derive it once and convert the server, see
+    /// [`From`]`<`[`OprfServer`]`>`.
+    ///
     /// # Errors
-    /// - [`Error::DeriveKeyPair`] if the `input` and `seed` together are longer
-    ///   then `u16::MAX - 3`.
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::DeriveKeyPair`] if the `input` and `seed` together are
+    ///   longer then `u16::MAX - 3`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn new_from_seed(seed: &[u8], info: &[u8]) -> Result<Self> {
         let (sk, pk) = derive_keypair::<CS>(seed, info, Mode::Poprf)?;
         Ok(Self { sk, pk })
     }
 
-    // Only used for tests
+    /// Derives a child [PoprfServer] from the private key of this server,
+    /// domain separated by `info`, e.g. to derive per-tenant keys from a
+    /// single stored master key on the fly. The private key is used as the
+    /// seed of DeriveKeyPair(), see [`PoprfServer::new_from_seed`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::DeriveKeyPair`] if the `info` is longer then `u16::MAX -
+    ///   3` minus the private key length.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn derive_subkey(&self, info: &[u8]) -> Result<Self> {
+        let mut seed = CS::Group::serialize_scalar(self.sk);
+        let result = Self::new_from_seed(&seed, info);
+        seed.as_mut_slice().zeroize();
+        result
+    }
+
+    /// Produces a new instance of a [PoprfServer] from the server's private key
+    /// scalar. See [`PoprfServer::into_parts`] for the inverse.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the private key is zero.
+    pub fn from_parts(sk: <CS::Group as Group>::Scalar) -> Result<Self> {
+        if CS::Group::is_zero_scalar(sk).into() {
+            return Err(ErrorKind::Deserialization.into());
+        }
+
+        let pk = CS::Group::base_elem() * &sk;
+        Ok(Self { sk, pk })
+    }
+
+    /// Returns the server's private key scalar. See [`PoprfServer::from_parts`]
+    /// for the inverse.
+    pub fn into_parts(self) -> <CS::Group as Group>::Scalar {
+        self.sk
+    }
+
+    /// Only used for tests
     #[cfg(test)]
     pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
         self.sk
@@ -247,20 +782,25 @@ where
     /// to the client.
     ///
     /// # Errors
-    /// - [`Error::Info`] if the `info` is longer than `u16::MAX`.
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn blind_evaluate<R: RngCore + CryptoRng>(
         &self,
         rng: &mut R,
         blinded_element: &BlindedElement<CS>,
         info: Option<&[u8]>,
     ) -> Result<PoprfServerEvaluateResult<CS>> {
+        debug_span!("blind_evaluate", mode = ?Mode::Poprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Poprf.label());
+
         let PoprfServerBatchEvaluatePrepareResult {
             mut prepared_evaluation_elements,
             prepared_tweak,
         } = self.batch_blind_evaluate_prepare(iter::once(blinded_element), info)?;
 
-        let prepared_evaluation_element = prepared_evaluation_elements.next().unwrap();
+        let prepared_evaluation_element = prepared_evaluation_elements
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))?;
         let prepared_evaluation_elements = core::array::from_ref(&prepared_evaluation_element);
 
         let PoprfServerBatchEvaluateFinishResult {
@@ -271,21 +811,66 @@ where
             iter::once(blinded_element),
             prepared_evaluation_elements,
             &prepared_tweak,
-        )
-        .unwrap();
+        )?;
 
         Ok(PoprfServerEvaluateResult {
-            message: messages.next().unwrap(),
+            message: messages
+                .next()
+                .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))?,
             proof,
         })
     }
 
+    /// Alternative version of [`blind_evaluate`](Self::blind_evaluate) that
+    /// passes the public `info` through a `policy` before evaluation.
+    ///
+    /// The `policy` receives the `info`, or an empty slice if there is none,
+    /// and either rejects it by returning [`None`] or returns the canonical
+    /// form to evaluate with, e.g. after checking an expiry window or mapping a
+    /// timestamp to its bucket. The canonical `info` is returned with the
+    /// result, as the client has to finalize with the same one.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::InfoPolicy`] if the `policy` rejected the `info`.
+    /// - [`ErrorKind::Info`] if the canonical `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn blind_evaluate_with_policy<R, P, I>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        info: Option<&[u8]>,
+        policy: P,
+    ) -> Result<PoprfServerPolicyEvaluateResult<CS, I>>
+    where
+        R: RngCore + CryptoRng,
+        P: FnOnce(&[u8]) -> Option<I>,
+        I: AsRef<[u8]>,
+    {
+        let info = policy(info.unwrap_or_default()).ok_or_else(|| {
+            debug_event!("info rejected by policy");
+            Error::new(ErrorKind::InfoPolicy, Operation::Evaluate)
+        })?;
+
+        let PoprfServerEvaluateResult { message, proof } =
+            self.blind_evaluate(rng, blinded_element, Some(info.as_ref()))?;
+
+        Ok(PoprfServerPolicyEvaluateResult {
+            message,
+            proof,
+            info,
+        })
+    }
+
     /// Allows for batching of the evaluation of multiple [BlindedElement]
     /// messages from a [PoprfClient]
     ///
     /// # Errors
-    /// - [`Error::Info`] if the `info` is longer than `u16::MAX`.
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    ///
+    /// To stream the [`EvaluationElement`]s as they are computed instead, use
+    /// [`batch_blind_evaluate_finish`](Self::batch_blind_evaluate_finish),
+    /// which returns them lazily.
     #[cfg(feature = "alloc")]
     pub fn batch_blind_evaluate<'a, R: RngCore + CryptoRng, IE>(
         &self,
@@ -298,6 +883,14 @@ where
         &'a IE: 'a + IntoIterator<Item = &'a BlindedElement<CS>>,
         <&'a IE as IntoIterator>::IntoIter: ExactSizeIterator,
     {
+        debug_span!(
+            "batch_blind_evaluate",
+            mode = ?Mode::Poprf,
+            batch_size = blinded_elements.into_iter().len(),
+        );
+        count!("voprf_evaluations_total", blinded_elements.into_iter().len(), "mode" => Mode::Poprf.label());
+        record!("voprf_batch_size", blinded_elements.into_iter().len(), "mode" => Mode::Poprf.label());
+
         let PoprfServerBatchEvaluatePrepareResult {
             prepared_evaluation_elements,
             prepared_tweak,
@@ -305,29 +898,115 @@ where
 
         let prepared_evaluation_elements: Vec<_> = prepared_evaluation_elements.collect();
 
-        // This can't fail because we know the size of the inputs.
         let PoprfServerBatchEvaluateFinishResult { messages, proof } =
             Self::batch_blind_evaluate_finish::<_, _, Vec<_>>(
                 rng,
                 blinded_elements.into_iter(),
                 &prepared_evaluation_elements,
                 &prepared_tweak,
-            )
-            .unwrap();
+            )?;
 
         let messages: Vec<_> = messages.collect();
 
         Ok(PoprfServerBatchEvaluateResult { messages, proof })
     }
 
+    /// Alternative version of `batch_blind_evaluate` for a fixed number of
+    /// [BlindedElement]s without memory allocation.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Batch`] if `N` is longer than [`u16::MAX`].
+    pub fn batch_blind_evaluate_array<R: RngCore + CryptoRng, const N: usize>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>; N],
+        info: Option<&[u8]>,
+    ) -> Result<PoprfServerBatchEvaluateArrayResult<CS, N>> {
+        debug_span!("batch_blind_evaluate", mode = ?Mode::Poprf, batch_size = N);
+        count!("voprf_evaluations_total", N, "mode" => Mode::Poprf.label());
+        record!("voprf_batch_size", N, "mode" => Mode::Poprf.label());
+
+        let PoprfServerBatchEvaluatePrepareResult {
+            mut prepared_evaluation_elements,
+            prepared_tweak,
+        } = self.batch_blind_evaluate_prepare(blinded_elements.iter(), info)?;
+        let prepared_evaluation_elements: [_; N] = try_array_from_fn(|_| {
+            prepared_evaluation_elements
+                .next()
+                .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))
+        })?;
+
+        let PoprfServerBatchEvaluateFinishResult {
+            mut messages,
+            proof,
+        } = Self::batch_blind_evaluate_finish(
+            rng,
+            blinded_elements.iter(),
+            &prepared_evaluation_elements,
+            &prepared_tweak,
+        )?;
+        let messages = try_array_from_fn(|_| {
+            messages
+                .next()
+                .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))
+        })?;
+
+        Ok(PoprfServerBatchEvaluateArrayResult { messages, proof })
+    }
+
+    /// Evaluates a batch of [BlindedElement]s, each with its own `info`.
+    ///
+    /// A proof is bound to the tweaked key of a single `info`, so unlike
+    /// [`batch_blind_evaluate`](Self::batch_blind_evaluate) a separate proof is
+    /// generated for every element. Use
+    /// [`PoprfClient::batch_finalize_with_infos`] to finalize the results.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Batch`] if the number of `blinded_elements` and `infos`
+    ///   don't match.
+    /// - [`ErrorKind::Info`] if any `info` is longer than `u16::MAX`, carrying
+    ///   the index of the offending item.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed,
+    ///   carrying the index of the offending item.
+    #[cfg(feature = "alloc")]
+    pub fn batch_blind_evaluate_with_infos<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        infos: &[Option<&[u8]>],
+    ) -> Result<Vec<PoprfServerEvaluateResult<CS>>> {
+        debug_span!(
+            "batch_blind_evaluate_with_infos",
+            mode = ?Mode::Poprf,
+            batch_size = blinded_elements.len(),
+        );
+        record!("voprf_batch_size", blinded_elements.len(), "mode" => Mode::Poprf.label());
+
+        if blinded_elements.len() != infos.len() {
+            return Err(Error::new(ErrorKind::Batch, Operation::Evaluate));
+        }
+
+        blinded_elements
+            .iter()
+            .zip(infos)
+            .enumerate()
+            .map(|(index, (blinded_element, info))| {
+                self.blind_evaluate(rng, blinded_element, *info)
+                    .map_err(|error| error.with_index(index))
+            })
+            .collect()
+    }
+
     /// Alternative version of `batch_blind_evaluate` without
     /// memory allocation. Returned [`PreparedEvaluationElement`] have to
     /// be [`collect`](Iterator::collect)ed and passed into
     /// [`batch_blind_evaluate_finish`](Self::batch_blind_evaluate_finish).
     ///
     /// # Errors
-    /// - [`Error::Info`] if the `info` is longer than `u16::MAX`.
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn batch_blind_evaluate_prepare<'a, I: Iterator<Item = &'a BlindedElement<CS>>>(
         &self,
         blinded_elements: I,
@@ -339,11 +1018,12 @@ where
         let tweak = compute_tweak::<CS>(self.sk, info)?;
 
         Ok(PoprfServerBatchEvaluatePrepareResult {
-            prepared_evaluation_elements: blinded_elements.zip(iter::repeat(tweak)).map(
+            prepared_evaluation_elements: blinded_elements.zip_repeat(tweak).map(
                 |(blinded_element, tweak)| {
-                    PreparedEvaluationElement(EvaluationElement(
-                        blinded_element.0 * &CS::Group::invert_scalar(tweak),
-                    ))
+                    PreparedEvaluationElement(EvaluationElement(kernel::blind_evaluate::<CS>(
+                        invert_scalar::<CS>(tweak),
+                        blinded_element.0,
+                    )))
                 },
             ),
             prepared_tweak: PoprfPreparedTweak(tweak),
@@ -354,7 +1034,7 @@ where
     /// for more details.
     ///
     /// # Errors
-    /// [`Error::Batch`] if the number of `blinded_elements` and
+    /// [`ErrorKind::Batch`] if the number of `blinded_elements` and
     /// `prepared_evaluation_elements` don't match or is longer then
     /// [`u16::MAX`]
     pub fn batch_blind_evaluate_finish<
@@ -401,25 +1081,68 @@ where
 
     /// Computes the output of the VOPRF on the server side
     ///
+    /// An `info` of `None` is the same as an empty `info`, like in
+    /// [`PoprfClient::finalize`]. In 0.4.0-pre.4 and before, `None` was left
+    /// out of the hashed output, which then didn't match the client's
+    /// output.
+    ///
     /// # Errors
-    /// [`Error::Input`]  if the `input` is longer then [`u16::MAX`].
+    /// [`ErrorKind::Input`]  if the `input` is longer then [`u16::MAX`].
     pub fn evaluate(
         &self,
         input: &[u8],
         info: Option<&[u8]>,
-    ) -> Result<Output<<CS as CipherSuite>::Hash>> {
-        let input_element = hash_to_group::<CS>(input, Mode::Poprf)?;
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
+        debug_span!("evaluate", mode = ?Mode::Poprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Poprf.label());
+
+        let input_element = hash_to_group::<CS>(input, Mode::Poprf)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        self.evaluate_element(input, input_element, info)
+    }
+
+    /// Like [`PoprfServer::evaluate`], but in the long input mode, see
+    /// [`PoprfClient::blind_long_input`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn evaluate_long_input(
+        &self,
+        input: &[u8],
+        info: Option<&[u8]>,
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
+        debug_span!("evaluate", mode = ?Mode::Poprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Poprf.label());
+
+        let prehashed_input = prehash_long_input::<CS>(input, Mode::Poprf);
+        let input_element = hash_long_input_to_group::<CS>(&prehashed_input, Mode::Poprf)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        self.evaluate_element(&prehashed_input, input_element, info)
+    }
+
+    /// Can only fail with [`ErrorKind::Info`], [`ErrorKind::Input`] and
+    /// [`ErrorKind::Protocol`].
+    fn evaluate_element(
+        &self,
+        input: &[u8],
+        input_element: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
         if CS::Group::is_identity_elem(input_element).into() {
-            return Err(Error::Input);
+            return Err(Error::new(ErrorKind::Input, Operation::Evaluate));
         };
 
-        let tweak = compute_tweak::<CS>(self.sk, info)?;
+        // None for info is treated the same as empty bytes, like in the client's
+        // finalize
+        let info = info.unwrap_or_default();
+        let tweak = compute_tweak::<CS>(self.sk, Some(info))?;
 
-        let evaluated_element = input_element * &CS::Group::invert_scalar(tweak);
+        let evaluated_element = input_element * &invert_scalar::<CS>(tweak);
 
         let issued_element = CS::Group::serialize_elem(evaluated_element);
 
-        server_evaluate_hash_input::<CS>(input, info, issued_element)
+        server_evaluate_hash_input::<CS>(input, Some(info), issued_element)
     }
 
     /// Retrieves the server's public key
@@ -428,6 +1151,43 @@ where
     }
 }
 
+/// Uses the private key of an [`OprfServer`] in partially-oblivious mode.
+///
+/// The outputs of both servers remain domain-separated: the mode is part of the
+/// context string used to hash inputs and compute outputs, so the same input
+/// yields unrelated outputs in each mode. However, the key itself is shared:
+/// the public key published for this mode also commits to the base mode key,
+/// and a compromise of one server compromises all of them. The specification
+/// recommends a distinct key per mode, only share keys if the key management
+/// requires it.
+impl<CS: CipherSuite> From<OprfServer<CS>> for PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn from(server: OprfServer<CS>) -> Self {
+        let sk = server.sk;
+        let pk = CS::Group::base_elem() * &sk;
+        Self { sk, pk }
+    }
+}
+
+/// Uses the key pair of a [`VoprfServer`] in partially-oblivious mode. See
+/// [`From`]`<`[`OprfServer`]`>` for the consequences of sharing a key between
+/// modes.
+impl<CS: CipherSuite> From<VoprfServer<CS>> for PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn from(server: VoprfServer<CS>) -> Self {
+        Self {
+            sk: server.sk,
+            pk: server.pk,
+        }
+    }
+}
+
 impl<CS: CipherSuite> BlindedElement<CS>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
@@ -480,6 +1240,7 @@ where
 /////////////////////////
 
 /// Contains the fields that are returned by a verifiable client blind
+#[derive_where(Clone)]
 #[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
 pub struct PoprfClientBlindResult<CS: CipherSuite>
 where
@@ -492,11 +1253,58 @@ where
     pub message: BlindedElement<CS>,
 }
 
+/// Contains the fields that are returned by a partially verifiable client
+/// batch blind of a fixed number of inputs
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct PoprfClientBatchBlindArrayResult<CS: CipherSuite, const N: usize>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The states to be persisted on the client
+    pub states: [PoprfClient<CS>; N],
+    /// The messages to send to the server
+    pub messages: [BlindedElement<CS>; N],
+}
+
 /// Concrete return type for [`PoprfClient::batch_finalize`].
 pub type PoprfClientBatchFinalizeResult<'a, CS, II, IC, IM> =
     FinalizeAfterUnblindResult<'a, CS, PoprfUnblindResult<'a, CS, IC, IM>, II>;
 
+/// Concrete return type for [`PoprfClient::batch_finalize_with_infos`].
+pub type PoprfClientBatchFinalizeWithInfosResult<'a, CS, II, IC, IR, IF> = Map<
+    Enumerate<
+        ZipRepeat<
+            Zip<
+                Zip<
+                    Zip<II, <&'a IC as IntoIterator>::IntoIter>,
+                    <&'a IR as IntoIterator>::IntoIter,
+                >,
+                IF,
+            >,
+            <<CS as CipherSuite>::Group as Group>::Elem,
+        >,
+    >,
+    fn(
+        (
+            usize,
+            (
+                (
+                    (
+                        (&'a [u8], &'a PoprfClient<CS>),
+                        &'a PoprfServerEvaluateResult<CS>,
+                    ),
+                    Option<&'a [u8]>,
+                ),
+                <<CS as CipherSuite>::Group as Group>::Elem,
+            ),
+        ),
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>>,
+>;
+
 /// Contains the fields that are returned by a verifiable server evaluate
+#[derive_where(Clone)]
 #[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
 pub struct PoprfServerEvaluateResult<CS: CipherSuite>
 where
@@ -509,7 +1317,40 @@ where
     pub proof: Proof<CS>,
 }
 
+/// Contains the fields that are returned by a partially verifiable server
+/// evaluate with an info policy
+#[derive_where(Clone; I)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem, I)]
+pub struct PoprfServerPolicyEvaluateResult<CS: CipherSuite, I>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The message to send to the client
+    pub message: EvaluationElement<CS>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+    /// The canonical info the message was evaluated with
+    pub info: I,
+}
+
+/// Concrete return type for [`PoprfClient::finalize_multi_key`].
+pub type PoprfClientMultiKeyFinalizeResult<'a, CS, K, I> = Map<
+    ZipRepeat<I, (&'a PoprfClient<CS>, &'a [u8], Option<&'a [u8]>)>,
+    fn(
+        (
+            (
+                K,
+                <<CS as CipherSuite>::Group as Group>::Elem,
+                &'a PoprfServerEvaluateResult<CS>,
+            ),
+            (&'a PoprfClient<CS>, &'a [u8], Option<&'a [u8]>),
+        ),
+    ) -> (K, Result<Output<<CS as CipherSuite>::FinalizeHash>>),
+>;
+
 /// Contains the fields that are returned by a verifiable server batch evaluate
+#[derive_where(Clone)]
 #[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
 #[cfg(feature = "alloc")]
 pub struct PoprfServerBatchEvaluateResult<CS: CipherSuite>
@@ -523,10 +1364,25 @@ where
     pub proof: Proof<CS>,
 }
 
+/// Contains the fields that are returned by a partially verifiable server
+/// batch evaluate of a fixed number of elements
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct PoprfServerBatchEvaluateArrayResult<CS: CipherSuite, const N: usize>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The messages to send to the client
+    pub messages: [EvaluationElement<CS>; N],
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
 /// Concrete type of [`EvaluationElement`]s in
 /// [`PoprfServerBatchEvaluatePrepareResult`].
 pub type PoprfServerBatchEvaluatePreparedEvaluationElements<CS, I> = Map<
-    Zip<I, Repeat<<<CS as CipherSuite>::Group as Group>::Scalar>>,
+    ZipRepeat<I, <<CS as CipherSuite>::Group as Group>::Scalar>,
     fn(
         (
             &BlindedElement<CS>,
@@ -545,7 +1401,7 @@ pub type PoprfServerBatchEvaluatePreparedEvaluationElements<CS, I> = Map<
 )]
 pub struct PoprfPreparedTweak<CS: CipherSuite>(
     #[cfg_attr(feature = "serde", serde(with = "Scalar::<CS::Group>"))]
-    <CS::Group as Group>::Scalar,
+    pub(crate)  <CS::Group as Group>::Scalar,
 )
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
@@ -595,8 +1451,8 @@ where
 /// Inner function for POPRF blind. Computes the tweaked key from the server
 /// public key and info.
 ///
-/// Can only fail with [`Error::Info`] or [`Error::Protocol`]
-fn compute_tweaked_key<CS: CipherSuite>(
+/// Can only fail with [`ErrorKind::Info`] or [`ErrorKind::Protocol`]
+pub(crate) fn compute_tweaked_key<CS: CipherSuite>(
     pk: <CS::Group as Group>::Elem,
     info: Option<&[u8]>,
 ) -> Result<<CS::Group as Group>::Elem>
@@ -613,29 +1469,32 @@ where
     // tweakedKey = T + pkS
     // if tweakedKey == G.Identity():
     //   raise InvalidInputError
-    let info_len = i2osp_2(info.len()).map_err(|_| Error::Info)?;
+    let info_len =
+        i2osp_2(info.len()).map_err(|_| Error::new(ErrorKind::Info, Operation::Finalize))?;
     let framed_info = [STR_INFO.as_slice(), &info_len, info];
 
     let dst =
         GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string::<CS>(Mode::Poprf));
     // This can't fail, the size of the `input` is known.
-    let m = CS::Group::hash_to_scalar::<CS::Hash>(&framed_info, &dst).unwrap();
+    let m = CS::Group::hash_to_scalar::<CS::Hash>(&framed_info, &dst)
+        .map_err(|_| Error::new(ErrorKind::Protocol, Operation::Finalize))?;
 
     let t = CS::Group::base_elem() * &m;
     let tweaked_key = t + &pk;
 
-    // Check if resulting element
-    match bool::from(CS::Group::is_identity_elem(tweaked_key)) {
-        true => Err(Error::Protocol),
-        false => Ok(tweaked_key),
-    }
+    // Check if resulting element is the identity
+    Option::from(CtOption::new(
+        tweaked_key,
+        !CS::Group::is_identity_elem(tweaked_key),
+    ))
+    .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Finalize))
 }
 
 /// Inner function for POPRF evaluate. Computes the tweak from the server
 /// private key and info.
 ///
-/// Can only fail with [`Error::Info`] and [`Error::Protocol`].
-fn compute_tweak<CS: CipherSuite>(
+/// Can only fail with [`ErrorKind::Info`] and [`ErrorKind::Protocol`].
+pub(crate) fn compute_tweak<CS: CipherSuite>(
     sk: <CS::Group as Group>::Scalar,
     info: Option<&[u8]>,
 ) -> Result<<CS::Group as Group>::Scalar>
@@ -651,21 +1510,22 @@ where
     // t = skS + m
     // if t == 0:
     //   raise InverseError
-    let info_len = i2osp_2(info.len()).map_err(|_| Error::Info)?;
+    let info_len =
+        i2osp_2(info.len()).map_err(|_| Error::new(ErrorKind::Info, Operation::Evaluate))?;
     let framed_info = [STR_INFO.as_slice(), &info_len, info];
 
     let dst =
         GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string::<CS>(Mode::Poprf));
     // This can't fail, the size of the `input` is known.
-    let m = CS::Group::hash_to_scalar::<CS::Hash>(&framed_info, &dst).unwrap();
+    let m = CS::Group::hash_to_scalar::<CS::Hash>(&framed_info, &dst)
+        .map_err(|_| Error::new(ErrorKind::Protocol, Operation::Evaluate))?;
 
     let t = sk + &m;
 
-    // Check if resulting element is equal to zero
-    match bool::from(CS::Group::is_zero_scalar(t)) {
-        true => Err(Error::Protocol),
-        false => Ok(t),
-    }
+    // Check if resulting element is equal to zero, only the final `Choice`
+    // branches on the secret tweak
+    Option::from(CtOption::new(t, !CS::Group::is_zero_scalar(t)))
+        .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))
 }
 
 type PoprfUnblindResult<'a, CS, IC, IM> = Map<
@@ -684,14 +1544,12 @@ type PoprfUnblindResult<'a, CS, IC, IM> = Map<
     ) -> <<CS as CipherSuite>::Group as Group>::Elem,
 >;
 
-/// Can only fail with [`Error::Info`], [`Error::Protocol`], [`Error::Batch] or
-/// [`Error::ProofVerification`].
+/// Can only fail with [`ErrorKind::Batch] or [`ErrorKind::ProofVerification`].
 fn poprf_unblind<'a, CS: 'a + CipherSuite, IC, IM>(
     clients: &'a IC,
     messages: &'a IM,
-    pk: <CS::Group as Group>::Elem,
     proof: &Proof<CS>,
-    info: Option<&[u8]>,
+    context: &VerifierContext<'_, CS>,
 ) -> Result<PoprfUnblindResult<'a, CS, IC, IM>>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
@@ -701,11 +1559,6 @@ where
     &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
     <&'a IM as IntoIterator>::IntoIter: ExactSizeIterator,
 {
-    let info = info.unwrap_or_default();
-    let tweaked_key = compute_tweaked_key::<CS>(pk, Some(info))?;
-
-    let g = CS::Group::base_elem();
-
     let blinds = clients
         .into_iter()
         // Convert to `fn` pointer to make a return type possible.
@@ -713,29 +1566,28 @@ where
     let evaluation_elements = messages.into_iter().map(|element| element.0);
     let blinded_elements = clients.into_iter().map(|client| client.blinded_element);
 
-    verify_proof(
-        g,
-        tweaked_key,
-        evaluation_elements,
-        blinded_elements,
-        proof,
-        Mode::Poprf,
-    )?;
+    context.verify(evaluation_elements, blinded_elements, proof)?;
 
     Ok(blinds
-        .zip(messages.into_iter())
-        .map(|(blind, x)| x.0 * &CS::Group::invert_scalar(blind)))
+        .zip(messages)
+        .map(|(blind, x)| kernel::unblind::<CS>(blind, x.0)))
 }
 
 type FinalizeAfterUnblindResult<'a, CS, IE, II> = Map<
-    Zip<Zip<IE, II>, Repeat<&'a [u8]>>,
+    Enumerate<ZipRepeat<Zip<IE, II>, &'a [u8]>>,
     fn(
-        ((<<CS as CipherSuite>::Group as Group>::Elem, &[u8]), &[u8]),
-    ) -> Result<GenericArray<u8, <<CS as CipherSuite>::Hash as OutputSizeUser>::OutputSize>>,
+        (
+            usize,
+            ((<<CS as CipherSuite>::Group as Group>::Elem, &[u8]), &[u8]),
+        ),
+    ) -> Result<
+        GenericArray<u8, <<CS as CipherSuite>::FinalizeHash as OutputSizeUser>::OutputSize>,
+    >,
 >;
 
-/// Can only fail with [`Error::Batch`] and returned values can only fail with
-/// [`Error::Info`] or [`Error::Input`] individually.
+/// Can only fail with [`ErrorKind::Batch`] and returned values can only fail
+/// with [`ErrorKind::Info`] or [`ErrorKind::Input`] individually, carrying the
+/// index of the offending item.
 fn finalize_after_unblind<
     'a,
     CS: CipherSuite,
@@ -751,33 +1603,19 @@ where
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
     if unblinded_elements.len() != inputs.len() {
-        return Err(Error::Batch);
+        return Err(Error::new(ErrorKind::Batch, Operation::Finalize));
     }
 
     let info = info.unwrap_or_default();
 
-    Ok(unblinded_elements.zip(inputs).zip(iter::repeat(info)).map(
-        |((unblinded_element, input), info)| {
-            let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
-
-            // hashInput = I2OSP(len(input), 2) || input ||
-            //             I2OSP(len(info), 2) || info ||
-            //             I2OSP(len(unblindedElement), 2) || unblindedElement ||
-            //             "Finalize"
-            // return Hash(hashInput)
-            let output = CS::Hash::new()
-                .chain_update(i2osp_2(input.as_ref().len()).map_err(|_| Error::Input)?)
-                .chain_update(input.as_ref())
-                .chain_update(i2osp_2(info.as_ref().len()).map_err(|_| Error::Info)?)
-                .chain_update(info.as_ref())
-                .chain_update(elem_len)
-                .chain_update(CS::Group::serialize_elem(unblinded_element))
-                .chain_update(STR_FINALIZE)
-                .finalize();
-
-            Ok(output)
-        },
-    ))
+    Ok(unblinded_elements
+        .zip(inputs)
+        .zip_repeat(info)
+        .enumerate()
+        .map(|(index, ((unblinded_element, input), info))| {
+            kernel::finalize::<CS>(input, Some(info), unblinded_element)
+                .map_err(|error| error.with_index(index))
+        }))
 }
 
 ///////////
@@ -790,6 +1628,7 @@ mod tests {
     use core::ops::Add;
     use core::ptr;
 
+    use ::alloc::vec;
     use generic_array::typenum::Sum;
     use generic_array::ArrayLength;
     use rand::rngs::OsRng;
@@ -803,7 +1642,7 @@ mod tests {
         key: <CS::Group as Group>::Scalar,
         info: &[u8],
         mode: Mode,
-    ) -> Output<CS::Hash>
+    ) -> Output<CS::FinalizeHash>
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
             IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
@@ -814,7 +1653,7 @@ mod tests {
         let point = CS::Group::hash_to_curve::<CS::Hash>(&[input], &dst).unwrap();
 
         // evaluatedElement = G.ScalarInverse(t) * blindedElement
-        let res = point * &CS::Group::invert_scalar(t);
+        let res = point * &invert_scalar::<CS>(t);
 
         finalize_after_unblind::<CS, _, _>(iter::once(res), iter::once(input), Some(info))
             .unwrap()
@@ -850,6 +1689,237 @@ mod tests {
         assert_eq!(client_finalize_result, res2);
     }
 
+    fn verifiable_batch_array_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let inputs: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let info = b"info";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let PoprfClientBatchBlindArrayResult { states, messages } =
+            PoprfClient::<CS>::batch_blind_array(&inputs, &mut rng).unwrap();
+        let PoprfServerBatchEvaluateArrayResult { messages, proof } = server
+            .batch_blind_evaluate_array(&mut rng, &messages, Some(info))
+            .unwrap();
+        let outputs = PoprfClient::batch_finalize_array(
+            &inputs,
+            &states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+            Some(info),
+        )
+        .unwrap();
+
+        for (input, output) in inputs.iter().zip(outputs) {
+            let res2 = prf::<CS>(input, server.get_private_key(), info, Mode::Poprf);
+            assert_eq!(output, res2);
+        }
+
+        // Errors carry the index of the offending input
+        let inputs: [&[u8]; 2] = [b"input", &[0; 1 << 16]];
+        let PoprfClientBatchBlindArrayResult { states, messages } =
+            PoprfClient::<CS>::batch_blind_array(&inputs, &mut rng).unwrap();
+        let PoprfServerBatchEvaluateArrayResult { messages, proof } = server
+            .batch_blind_evaluate_array(&mut rng, &messages, Some(info))
+            .unwrap();
+        let result = PoprfClient::batch_finalize_array(
+            &inputs,
+            &states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+            Some(info),
+        );
+        assert_eq!(
+            result,
+            Err(Error::new(ErrorKind::Input, Operation::Finalize).with_index(1))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    fn verifiable_batch_streaming<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let inputs: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let info = b"info";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let PoprfClientBatchBlindArrayResult { states, messages } =
+            PoprfClient::<CS>::batch_blind_array(&inputs, &mut rng).unwrap();
+
+        let PoprfServerBatchEvaluatePrepareResult {
+            prepared_evaluation_elements,
+            prepared_tweak,
+        } = server
+            .batch_blind_evaluate_prepare(messages.iter(), Some(info))
+            .unwrap();
+        assert_eq!(prepared_evaluation_elements.len(), inputs.len());
+        let prepared_evaluation_elements: Vec<_> = prepared_evaluation_elements.collect();
+        let PoprfServerBatchEvaluateFinishResult { messages, proof } =
+            PoprfServer::batch_blind_evaluate_finish(
+                &mut rng,
+                messages.iter(),
+                &prepared_evaluation_elements,
+                &prepared_tweak,
+            )
+            .unwrap();
+        assert_eq!(messages.len(), inputs.len());
+        let messages: Vec<_> = messages.collect();
+
+        let outputs = PoprfClient::batch_finalize(
+            inputs.iter().copied(),
+            &states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+            Some(info),
+        )
+        .unwrap();
+        assert_eq!(outputs.len(), inputs.len());
+
+        // Outputs can be produced from either end
+        for (input, output) in inputs.iter().rev().zip(outputs.rev()) {
+            let res2 = prf::<CS>(input, server.get_private_key(), info, Mode::Poprf);
+            assert_eq!(output.unwrap(), res2);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn verifiable_batch_retrieval_with_infos<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let inputs: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let infos: [Option<&[u8]>; 3] = [Some(b"first info"), Some(b"second info"), None];
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let PoprfClientBatchBlindArrayResult { states, messages } =
+            PoprfClient::<CS>::batch_blind_array(&inputs, &mut rng).unwrap();
+        let responses = server
+            .batch_blind_evaluate_with_infos(&mut rng, &messages, &infos)
+            .unwrap();
+        let outputs = PoprfClient::batch_finalize_with_infos(
+            inputs.iter().copied(),
+            &states,
+            &responses,
+            server.get_public_key(),
+            infos.iter().copied(),
+        )
+        .unwrap();
+
+        for ((input, info), output) in inputs.iter().zip(infos).zip(outputs) {
+            let res2 = prf::<CS>(
+                input,
+                server.get_private_key(),
+                info.unwrap_or_default(),
+                Mode::Poprf,
+            );
+            assert_eq!(output.unwrap(), res2);
+        }
+
+        // Swapped infos only fail the affected items
+        let swapped_infos = [infos[1], infos[0], infos[2]];
+        let outputs: Vec<_> = PoprfClient::batch_finalize_with_infos(
+            inputs.iter().copied(),
+            &states,
+            &responses,
+            server.get_public_key(),
+            swapped_infos.iter().copied(),
+        )
+        .unwrap()
+        .collect();
+        for (index, output) in outputs[..2].iter().enumerate() {
+            assert_eq!(
+                output,
+                &Err(
+                    Error::new(ErrorKind::ProofVerification, Operation::VerifyProof)
+                        .with_index(index)
+                )
+            );
+        }
+        assert!(outputs[2].is_ok());
+
+        assert_eq!(
+            server
+                .batch_blind_evaluate_with_infos(&mut rng, &messages, &infos[..2])
+                .map(|_| ()),
+            Err(Error::new(ErrorKind::Batch, Operation::Evaluate))
+        );
+        assert!(matches!(
+            PoprfClient::batch_finalize_with_infos(
+                inputs.iter().copied(),
+                &states,
+                &responses,
+                server.get_public_key(),
+                infos[..2].iter().copied(),
+            ),
+            Err(error) if error == Error::new(ErrorKind::Batch, Operation::Finalize)
+        ));
+    }
+
+    fn verifiable_retrieval_with_policy<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        // Accepts timestamps up to an expiry and maps them to hourly buckets
+        fn policy(info: &[u8]) -> Option<[u8; 8]> {
+            let mut timestamp = [0; 8];
+            timestamp.copy_from_slice(info.get(..8)?);
+            let timestamp = u64::from_be_bytes(timestamp);
+
+            (timestamp < 10_000).then(|| (timestamp / 3600).to_be_bytes())
+        }
+
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+
+        let info = 7200_u64.to_be_bytes();
+        let server_result = server
+            .blind_evaluate_with_policy(&mut rng, &client_blind_result.message, Some(&info), policy)
+            .unwrap();
+        assert_eq!(server_result.info, 2_u64.to_be_bytes());
+
+        let output = client_blind_result
+            .state
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+                Some(&server_result.info),
+            )
+            .unwrap();
+        let res2 = prf::<CS>(
+            input,
+            server.get_private_key(),
+            &server_result.info,
+            Mode::Poprf,
+        );
+        assert_eq!(output, res2);
+
+        for info in [&10_000_u64.to_be_bytes()[..], &[], &[0; 4]] {
+            let result = server.blind_evaluate_with_policy(
+                &mut rng,
+                &client_blind_result.message,
+                Some(info),
+                policy,
+            );
+            assert!(matches!(
+                result,
+                Err(error) if error == Error::new(ErrorKind::InfoPolicy, Operation::Evaluate)
+            ));
+        }
+    }
+
     fn verifiable_bad_public_key<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -879,6 +1949,41 @@ mod tests {
         assert!(client_finalize_result.is_err());
     }
 
+    fn verifiable_long_input_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = vec![1; usize::from(u16::MAX) + 1];
+        let info = b"info";
+        let mut rng = OsRng;
+        let client_blind_result = PoprfClient::<CS>::blind_long_input(&input, &mut rng).unwrap();
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        assert!(server.evaluate(&input, Some(info)).is_err());
+        let server_result = server
+            .blind_evaluate(&mut rng, &client_blind_result.message, Some(info))
+            .unwrap();
+        let client_finalize = client_blind_result
+            .state
+            .finalize_long_input(
+                &input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+                Some(info),
+            )
+            .unwrap();
+        assert_eq!(
+            client_finalize,
+            server.evaluate_long_input(&input, Some(info)).unwrap()
+        );
+
+        // The long input mode is domain separated from the regular mode, even for
+        // the pre-hashed input
+        let prehashed_input = prehash_long_input::<CS>(&input, Mode::Poprf);
+        assert!(client_finalize != server.evaluate(&prehashed_input, Some(info)).unwrap());
+    }
+
     fn verifiable_server_evaluate<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -916,6 +2021,182 @@ mod tests {
         assert!(client_finalize != server_evaluate);
     }
 
+    fn verifiable_retrieval_with_bound_info<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let finalizer = PoprfClientWithInfo::new(server.get_public_key(), Some(b"info")).unwrap();
+        assert_eq!(finalizer.info(), b"info");
+
+        for _ in 0..2 {
+            let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let server_result = server
+                .blind_evaluate(
+                    &mut rng,
+                    &client_blind_result.message,
+                    Some(finalizer.info()),
+                )
+                .unwrap();
+            let output = finalizer
+                .finalize(
+                    &client_blind_result.state,
+                    input,
+                    &server_result.message,
+                    &server_result.proof,
+                )
+                .unwrap();
+            assert_eq!(output, server.evaluate(input, Some(b"info")).unwrap());
+
+            let clients = [client_blind_result.state];
+            let messages = [server_result.message];
+            let mut outputs = finalizer
+                .batch_finalize(
+                    iter::once(input.as_slice()),
+                    &clients,
+                    &messages,
+                    &server_result.proof,
+                )
+                .unwrap();
+            assert_eq!(outputs.next().unwrap().unwrap(), output);
+        }
+
+        // An evaluation with another info fails to verify
+        let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = server
+            .blind_evaluate(&mut rng, &client_blind_result.message, Some(b"other"))
+            .unwrap();
+        assert_eq!(
+            finalizer
+                .finalize(
+                    &client_blind_result.state,
+                    input,
+                    &server_result.message,
+                    &server_result.proof,
+                )
+                .map(|_| ()),
+            Err(Error::new(
+                ErrorKind::ProofVerification,
+                Operation::VerifyProof
+            ))
+        );
+
+        // No info is the same as an empty one
+        let finalizer = PoprfClientWithInfo::<CS>::new(server.get_public_key(), None).unwrap();
+        assert_eq!(finalizer.info(), b"");
+    }
+
+    fn empty_info_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let finalizer = PoprfClientWithInfo::without_info(server.get_public_key()).unwrap();
+        assert_eq!(finalizer.info(), b"");
+
+        let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = server
+            .blind_evaluate(&mut rng, &client_blind_result.message, None)
+            .unwrap();
+        let output = finalizer
+            .finalize(
+                &client_blind_result.state,
+                input,
+                &server_result.message,
+                &server_result.proof,
+            )
+            .unwrap();
+        assert_eq!(output, server.evaluate(input, None).unwrap());
+
+        // The key is still tweaked, so the output isn't the one of the VOPRF mode
+        let voprf_server = VoprfServer::<CS>::from_parts(server.into_parts()).unwrap();
+        assert_ne!(output, voprf_server.evaluate(input).unwrap());
+    }
+
+    fn no_info_is_empty_info<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let expected = server.evaluate(input, None).unwrap();
+        assert_eq!(server.evaluate(input, Some(b"")).unwrap(), expected);
+        assert_eq!(
+            server.evaluate_long_input(input, None).unwrap(),
+            server.evaluate_long_input(input, Some(b"")).unwrap()
+        );
+
+        for server_info in [None, Some(b"".as_slice())] {
+            let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let server_result = server
+                .blind_evaluate(&mut rng, &client_blind_result.message, server_info)
+                .unwrap();
+
+            for client_info in [None, Some(b"".as_slice())] {
+                let output = client_blind_result
+                    .state
+                    .finalize(
+                        input,
+                        &server_result.message,
+                        &server_result.proof,
+                        server.get_public_key(),
+                        client_info,
+                    )
+                    .unwrap();
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    fn parts_roundtrip<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let info = b"info";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let server = PoprfServer::<CS>::from_parts(server.into_parts()).unwrap();
+        let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = server
+            .blind_evaluate(&mut rng, &client_blind_result.message, Some(info))
+            .unwrap();
+
+        let blind = client_blind_result.state.into_parts();
+        let client = PoprfClient::<CS>::from_parts(input, blind).unwrap();
+        let client_finalize_result = client
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+                Some(info),
+            )
+            .unwrap();
+        assert_eq!(
+            client_finalize_result,
+            server.evaluate(input, Some(info)).unwrap()
+        );
+
+        assert!(matches!(
+            PoprfClient::<CS>::from_parts(input, CS::Group::zero_scalar()),
+            Err(error) if error.kind() == ErrorKind::Deserialization
+        ));
+        assert!(matches!(
+            PoprfServer::<CS>::from_parts(CS::Group::zero_scalar()),
+            Err(error) if error.kind() == ErrorKind::Deserialization
+        ));
+    }
+
     fn zeroize_verifiable_client<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -976,16 +2257,38 @@ mod tests {
             use crate::Ristretto255;
 
             verifiable_retrieval::<Ristretto255>();
+            verifiable_batch_array_retrieval::<Ristretto255>();
+            verifiable_retrieval_with_policy::<Ristretto255>();
+            #[cfg(feature = "alloc")]
+            verifiable_batch_streaming::<Ristretto255>();
+            #[cfg(feature = "alloc")]
+            verifiable_batch_retrieval_with_infos::<Ristretto255>();
             verifiable_bad_public_key::<Ristretto255>();
             verifiable_server_evaluate::<Ristretto255>();
+            verifiable_long_input_retrieval::<Ristretto255>();
+            verifiable_retrieval_with_bound_info::<Ristretto255>();
+            no_info_is_empty_info::<Ristretto255>();
+            empty_info_retrieval::<Ristretto255>();
+            parts_roundtrip::<Ristretto255>();
 
             zeroize_verifiable_client::<Ristretto255>();
             zeroize_verifiable_server::<Ristretto255>();
         }
 
         verifiable_retrieval::<NistP256>();
+        verifiable_batch_array_retrieval::<NistP256>();
+        verifiable_retrieval_with_policy::<NistP256>();
+        #[cfg(feature = "alloc")]
+        verifiable_batch_streaming::<NistP256>();
+        #[cfg(feature = "alloc")]
+        verifiable_batch_retrieval_with_infos::<NistP256>();
         verifiable_bad_public_key::<NistP256>();
         verifiable_server_evaluate::<NistP256>();
+        verifiable_long_input_retrieval::<NistP256>();
+        verifiable_retrieval_with_bound_info::<NistP256>();
+        no_info_is_empty_info::<NistP256>();
+        empty_info_retrieval::<NistP256>();
+        parts_roundtrip::<NistP256>();
 
         zeroize_verifiable_client::<NistP256>();
         zeroize_verifiable_server::<NistP256>();
@@ -0,0 +1,107 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the canonicalization of password-like inputs before blinding
+
+use alloc::string::String;
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Canonicalizes a password-like `input` before it is blinded, so the same
+/// password typed on different platforms results in the same output.
+///
+/// The OPRF output depends on every byte of the input, but keyboards and
+/// operating systems disagree on how to encode the same text. E.g. "é" may be
+/// entered as the single code point U+00E9 or as "e" followed by the combining
+/// accent U+0301. This applies the Unicode Normalization Form C (NFC) to the
+/// `input`. If `case_folding` is `true`, it is lowercased first, with the
+/// locale-independent mapping of [`char::to_lowercase`], and normalized
+/// afterwards, because lowercasing can produce unnormalized sequences.
+///
+/// Clients of the same deployment must all agree on the same `case_folding`
+/// and changing it later changes the output for every input that isn't
+/// already lowercase:
+///
+/// ```
+/// use voprf::normalize_input;
+///
+/// assert_eq!(normalize_input("Cafe\u{301}", false), "Caf\u{e9}");
+/// assert_eq!(normalize_input("Cafe\u{301}", true), "caf\u{e9}");
+/// ```
+pub fn normalize_input(input: &str, case_folding: bool) -> String {
+    if case_folding {
+        input.chars().flat_map(char::to_lowercase).nfc().collect()
+    } else {
+        input.nfc().collect()
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use digest::core_api::BlockSizeUser;
+    use digest::OutputSizeUser;
+    use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{run_locally, CipherSuite, Group, Result};
+
+    fn normalized_outputs_match<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let private_key = CS::Group::serialize_scalar(CS::Group::random_scalar(&mut OsRng));
+
+        let composed = "p\u{e4}ssw\u{f6}rd";
+        let decomposed = "pa\u{308}sswo\u{308}rd";
+        let output = |input: &str| run_locally::<CS, _>(&mut OsRng, &private_key, input.as_bytes());
+
+        // Without normalization the same password results in different outputs
+        assert_ne!(output(composed).unwrap(), output(decomposed).unwrap());
+        assert_eq!(
+            output(&normalize_input(composed, false)).unwrap(),
+            output(&normalize_input(decomposed, false)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        // Canonically equivalent sequences are composed
+        assert_eq!(normalize_input("e\u{301}", false), "\u{e9}");
+        assert_eq!(normalize_input("\u{e9}", false), "\u{e9}");
+        assert_eq!(normalize_input("\u{212b}", false), "\u{c5}");
+        assert_eq!(normalize_input("", true), "");
+
+        // Compatibility equivalents and case are preserved without folding
+        assert_eq!(normalize_input("\u{fb01}", false), "\u{fb01}");
+        assert_eq!(normalize_input("P\u{c5}SS", false), "P\u{c5}SS");
+
+        // Case folding is applied before composing
+        assert_eq!(normalize_input("PA\u{30a}SS", true), "p\u{e5}ss");
+        assert_eq!(normalize_input("\u{212a}elvin", true), "kelvin");
+        assert_eq!(normalize_input("\u{130}", true), "i\u{307}");
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            normalized_outputs_match::<Ristretto255>();
+        }
+
+        normalized_outputs_match::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the derivation of pseudonymous tags from identifiers
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::sequence::Concat;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::GenericArray;
+
+use crate::common::{create_context_string, i2osp_2};
+use crate::{
+    evaluate_full, expand_message_xmd, CipherSuite, Error, ErrorKind, Mode, Operation, Result,
+};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_PSEUDONYM: [u8; 10] = *b"Pseudonym-";
+
+///////////////////////////
+// Convenience Functions //
+// ===================== //
+///////////////////////////
+
+/// Maps an `identifier` to a pseudonymous tag of `N` bytes, e.g. to join
+/// datasets in an analytics pipeline without revealing the identifiers
+/// joined on.
+///
+/// The tag is derived from the base mode output of
+/// [`evaluate_full`] with the private key:
+///
+/// ```text
+/// output = evaluate_full(privateKey, identifier)
+/// tag = expand_message_xmd(output || I2OSP(len(context), 2) || context,
+///                          "Pseudonym-" || contextString, N)
+/// ```
+///
+/// This centralizes the rules that are easy to get wrong when composing these
+/// steps by hand:
+/// - Tags of different lengths are unrelated, instead of prefixes of each
+///   other, so changing `N` can't link an old dataset with a new one.
+/// - Tags of different `context`s, e.g. one per dataset, are unlinkable without
+///   the private key. An empty `context` is valid.
+/// - Tags can't be confused with outputs of the OPRF itself.
+///
+/// Anyone who obtains the OPRF output of an identifier, e.g. a client running
+/// the oblivious protocol with [`OprfClient`](crate::OprfClient), can compute
+/// its tags for every context, so a private key used for pseudonymization
+/// should not evaluate anything else. Tags collide after about `2^(4 * N)`
+/// identifiers, `N` should be at least 16 for large datasets.
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::{pseudonymize, OprfServer};
+///
+/// let private_key = OprfServer::<CipherSuite>::new(&mut OsRng)
+///     .unwrap()
+///     .serialize();
+///
+/// let identifier = b"alice@example.com";
+/// let tag = pseudonymize::<CipherSuite, 16>(&private_key, identifier, b"dataset-1").unwrap();
+/// let other = pseudonymize::<CipherSuite, 16>(&private_key, identifier, b"dataset-2").unwrap();
+/// assert_ne!(tag, other);
+/// ```
+///
+/// # Errors
+/// - [`ErrorKind::Deserialization`] if the private key is not a valid point on
+///   the group or zero.
+/// - [`ErrorKind::Input`] if the `identifier` or `context` is longer then
+///   [`u16::MAX`], or if `N` is zero, longer then [`u16::MAX`] or longer then
+///   255 times the output size of [`CipherSuite::Hash`].
+pub fn pseudonymize<CS: CipherSuite, const N: usize>(
+    private_key_bytes: &[u8],
+    identifier: &[u8],
+    context: &[u8],
+) -> Result<[u8; N]>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let context_len = i2osp_2(context.len())
+        .map_err(|_| Error::new(ErrorKind::Input, Operation::Pseudonymize))?;
+    let output = evaluate_full::<CS>(private_key_bytes, identifier)?;
+
+    let dst = GenericArray::from(STR_PSEUDONYM).concat(create_context_string::<CS>(Mode::Oprf));
+    let mut tag = [0; N];
+    expand_message_xmd::<CS::Hash>(&[&output, &context_len, context], &dst, &mut tag)
+        .map_err(|error| error.with_operation(Operation::Pseudonymize))?;
+
+    Ok(tag)
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{Group, OprfClient, OprfServer};
+
+    fn pseudonym_tags<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let key = server.serialize();
+
+        let tag = pseudonymize::<CS, 16>(&key, b"alice", b"dataset").unwrap();
+        assert_eq!(
+            tag,
+            pseudonymize::<CS, 16>(&key, b"alice", b"dataset").unwrap()
+        );
+        assert_ne!(
+            tag,
+            pseudonymize::<CS, 16>(&key, b"bob", b"dataset").unwrap()
+        );
+        assert_ne!(
+            tag,
+            pseudonymize::<CS, 16>(&key, b"alice", b"other").unwrap()
+        );
+        assert_ne!(tag, pseudonymize::<CS, 16>(&key, b"alice", b"").unwrap());
+        let other_key = CS::Group::serialize_scalar(CS::Group::random_scalar(&mut rng));
+        assert_ne!(
+            tag,
+            pseudonymize::<CS, 16>(&other_key, b"alice", b"dataset").unwrap()
+        );
+
+        // Longer tags don't extend shorter ones
+        let long_tag = pseudonymize::<CS, 32>(&key, b"alice", b"dataset").unwrap();
+        assert_ne!(tag[..], long_tag[..16]);
+
+        // Clients of the oblivious protocol derive the same tags
+        let client_blind_result = OprfClient::<CS>::blind(b"alice", &mut rng).unwrap();
+        let output = client_blind_result
+            .state
+            .finalize(
+                b"alice",
+                &server.blind_evaluate(&client_blind_result.message),
+            )
+            .unwrap();
+        let dst = GenericArray::from(STR_PSEUDONYM).concat(create_context_string::<CS>(Mode::Oprf));
+        let mut expected = [0; 16];
+        expand_message_xmd::<CS::Hash>(&[&output, &[0, 7], b"dataset"], &dst, &mut expected)
+            .unwrap();
+        assert_eq!(tag, expected);
+
+        let error = Error::new(ErrorKind::Input, Operation::Pseudonymize);
+        assert!(matches!(
+            pseudonymize::<CS, 0>(&key, b"alice", b"dataset"),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            pseudonymize::<CS, 16>(&key, b"alice", &[0; u16::MAX as usize + 1]),
+            Err(e) if e == error
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            pseudonym_tags::<Ristretto255>();
+        }
+
+        pseudonym_tags::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,530 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the Pythia partially oblivious PRF over BLS12-381
+
+use bls12_381_::{G1Projective, G2Projective};
+use generic_array::sequence::Concat;
+use generic_array::typenum::U9;
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::common::{invert_scalar, STR_HASH_TO_GROUP, STR_HASH_TO_SCALAR};
+use crate::group::{hash_to_g2, pairing, GtElem};
+use crate::{
+    Bls12_381, Bls12_381Elem, Bls12_381Scalar, CipherSuite, Error, ErrorKind, Group, Operation,
+    Result,
+};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_PYTHIA: [u8; 7] = *b"Pythia-";
+const STR_TWEAK: [u8; 6] = *b"Tweak-";
+
+/// The hash function of the [`Bls12_381`] ciphersuite
+type Hash = <Bls12_381 as CipherSuite>::Hash;
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A client which engages with a [`PythiaServer`].
+///
+/// Pythia is the partially oblivious PRF of Everspaugh et al., "The Pythia PRF
+/// Service", USENIX Security 2015: the output for an `input` and a public
+/// `tweak`, e.g. a user ID the server rate-limits by, is
+///
+/// ```text
+/// e(H1(tweak), H2(input))^k
+/// ```
+///
+/// in the target group of the BLS12-381 pairing. The server only learns the
+/// `tweak` and proves the evaluation against its public key in G1. Unlike the
+/// [`PoprfServer`](crate::PoprfServer), its key can be rotated without the
+/// clients: a [`PythiaUpdateToken`] moves stored [`PythiaOutput`]s and the
+/// public key to the new key, so the outputs have to be stored instead of a
+/// hash of them.
+///
+/// ```
+/// use rand::rngs::OsRng;
+/// use voprf::{PythiaClient, PythiaServer};
+///
+/// let mut rng = OsRng;
+/// let server = PythiaServer::new(&mut rng).unwrap();
+///
+/// let blind_result = PythiaClient::blind(b"password", &mut rng).unwrap();
+/// let evaluate_result = server
+///     .blind_evaluate(&mut rng, &blind_result.message, b"alice")
+///     .unwrap();
+/// let mut output = blind_result
+///     .state
+///     .finalize(
+///         &evaluate_result.message,
+///         &evaluate_result.proof,
+///         server.get_public_key(),
+///         b"alice",
+///     )
+///     .unwrap();
+///
+/// // Rotate the key and update the stored output
+/// let new_server = PythiaServer::new(&mut rng).unwrap();
+/// let token = server.update_token(&new_server);
+/// output.apply_update(&token);
+/// assert!(output == new_server.evaluate(b"password", b"alice").unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PythiaClient {
+    pub(crate) blind: Bls12_381Scalar,
+    pub(crate) blinded_element: G2Projective,
+}
+
+/// A server which engages with a [`PythiaClient`].
+#[derive(Clone, Debug)]
+pub struct PythiaServer {
+    pub(crate) sk: Bls12_381Scalar,
+    pub(crate) pk: Bls12_381Elem,
+}
+
+/// The blinded input a [`PythiaClient`] sends, in G2.
+#[derive(Clone, Debug)]
+pub struct PythiaBlindedElement(pub(crate) G2Projective);
+
+/// The evaluation a [`PythiaServer`] returns, in the target group.
+#[derive(Clone, Debug)]
+pub struct PythiaEvaluationElement(pub(crate) GtElem);
+
+/// The proof that a [`PythiaEvaluationElement`] was computed with the private
+/// key of the public key of the server.
+#[derive(Clone, Debug)]
+pub struct PythiaProof {
+    pub(crate) c_scalar: Bls12_381Scalar,
+    pub(crate) s_scalar: Bls12_381Scalar,
+}
+
+/// The output of Pythia, in the target group.
+///
+/// Applications store the serialization of the output to keep it updatable by
+/// a [`PythiaUpdateToken`], or a hash of it otherwise.
+#[derive(Clone, Debug)]
+pub struct PythiaOutput(pub(crate) GtElem);
+
+/// Moves [`PythiaOutput`]s and the public key from the key of a
+/// [`PythiaServer`] to another, see [`PythiaServer::update_token`].
+///
+/// The token is the quotient of both private keys, it reveals the new key to
+/// anyone who knows the old one.
+#[derive(Clone, Debug)]
+pub struct PythiaUpdateToken(pub(crate) Bls12_381Scalar);
+
+/// Contains the fields that are returned by a client blind
+#[derive(Debug)]
+pub struct PythiaClientBlindResult {
+    /// The state to be persisted on the client
+    pub state: PythiaClient,
+    /// The message to send to the server
+    pub message: PythiaBlindedElement,
+}
+
+/// Contains the fields that are returned by a server evaluate
+#[derive(Debug)]
+pub struct PythiaServerEvaluateResult {
+    /// The message to send to the client
+    pub message: PythiaEvaluationElement,
+    /// The proof for the client to verify
+    pub proof: PythiaProof,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl PythiaClient {
+    /// Computes the first step of the protocol, blinding the `input` with a
+    /// random scalar.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        rng: &mut R,
+    ) -> Result<PythiaClientBlindResult> {
+        let blind = Bls12_381::random_scalar(rng);
+        let blinded_element = hash_input(input)? * bls12_381_::Scalar::from(blind);
+
+        Ok(PythiaClientBlindResult {
+            state: Self {
+                blind,
+                blinded_element,
+            },
+            message: PythiaBlindedElement(blinded_element),
+        })
+    }
+
+    /// Verifies the `proof` of the evaluation against the `public_key` of the
+    /// server and unblinds the output for the same `tweak` the server
+    /// evaluated with.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `tweak` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the proof failed to verify.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn finalize(
+        &self,
+        evaluation_element: &PythiaEvaluationElement,
+        proof: &PythiaProof,
+        public_key: Bls12_381Elem,
+        tweak: &[u8],
+    ) -> Result<PythiaOutput> {
+        let base = compute_base(tweak, &self.blinded_element)
+            .map_err(|error| error.with_operation(Operation::Finalize))?;
+        verify_proof(public_key, &base, &evaluation_element.0, proof)?;
+
+        let inverse = invert_scalar::<Bls12_381>(self.blind);
+        Ok(PythiaOutput(
+            evaluation_element.0.pow(&bls12_381_::Scalar::from(inverse)),
+        ))
+    }
+}
+
+impl PythiaServer {
+    /// Produces a new instance of a [`PythiaServer`] using a supplied RNG
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
+        let sk = Bls12_381::random_scalar(rng);
+        let pk = Bls12_381::base_elem() * &sk;
+        Ok(Self { sk, pk })
+    }
+
+    /// Produces a new instance of a [`PythiaServer`] using a supplied set of
+    /// bytes to represent the server's private key
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the private key is not a valid scalar
+    /// or zero.
+    pub fn new_with_key(key: &[u8]) -> Result<Self> {
+        let sk = Bls12_381::deserialize_scalar(key)?;
+        let pk = Bls12_381::base_elem() * &sk;
+        Ok(Self { sk, pk })
+    }
+
+    /// Evaluates the blinded element of a client for the public `tweak` and
+    /// proves the evaluation against the public key of this server.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `tweak` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &PythiaBlindedElement,
+        tweak: &[u8],
+    ) -> Result<PythiaServerEvaluateResult> {
+        let base = compute_base(tweak, &blinded_element.0)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        let evaluation = base.pow(&self.sk.into());
+        let proof = generate_proof(rng, self.sk, self.pk, &base, &evaluation)?;
+
+        Ok(PythiaServerEvaluateResult {
+            message: PythiaEvaluationElement(evaluation),
+            proof,
+        })
+    }
+
+    /// Computes the output for an `input` and a `tweak` directly, without
+    /// blinding.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::Info`] if the `tweak` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn evaluate(&self, input: &[u8], tweak: &[u8]) -> Result<PythiaOutput> {
+        let point = hash_input(input).map_err(|error| error.with_operation(Operation::Evaluate))?;
+        let base = compute_base(tweak, &point)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+
+        Ok(PythiaOutput(base.pow(&self.sk.into())))
+    }
+
+    /// Computes the token that moves outputs and the public key of this server
+    /// to the key of `new_server`.
+    pub fn update_token(&self, new_server: &Self) -> PythiaUpdateToken {
+        PythiaUpdateToken(new_server.sk * &invert_scalar::<Bls12_381>(self.sk))
+    }
+
+    /// Returns the public key of the server, in G1.
+    pub fn get_public_key(&self) -> Bls12_381Elem {
+        self.pk
+    }
+}
+
+impl PythiaBlindedElement {
+    /// Returns the blinded element in G2.
+    pub fn value(&self) -> G2Projective {
+        self.0
+    }
+}
+
+impl PythiaOutput {
+    /// Moves the output to the key the `token` was computed for.
+    pub fn apply_update(&mut self, token: &PythiaUpdateToken) {
+        self.0 = self.0.pow(&token.0.into());
+    }
+}
+
+impl PythiaUpdateToken {
+    /// Moves the `public_key` of the old server to the new one, so clients can
+    /// check the public key of the new server against it.
+    pub fn update_public_key(&self, public_key: Bls12_381Elem) -> Bls12_381Elem {
+        public_key * &self.0
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl Drop for PythiaClient {
+    fn drop(&mut self) {
+        self.blind.zeroize();
+        self.blinded_element.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for PythiaClient {}
+
+impl Drop for PythiaServer {
+    fn drop(&mut self) {
+        self.sk.zeroize();
+        self.pk.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for PythiaServer {}
+
+impl Drop for PythiaOutput {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for PythiaOutput {}
+
+// Outputs are compared in constant-time.
+impl PartialEq for PythiaOutput {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl Eq for PythiaOutput {}
+
+impl Drop for PythiaUpdateToken {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for PythiaUpdateToken {}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+/// Returns `"Pythia-" || I2OSP(suite ID, 2)`.
+fn create_context_string() -> GenericArray<u8, U9> {
+    GenericArray::from(STR_PYTHIA).concat(Bls12_381::ID.to_be_bytes().into())
+}
+
+/// Hashes the `input` to G2.
+///
+/// Can only fail with [`ErrorKind::Input`].
+fn hash_input(input: &[u8]) -> Result<G2Projective> {
+    let dst = GenericArray::from(STR_HASH_TO_GROUP).concat(create_context_string());
+    let point = hash_to_g2::<Hash>(&[input], &dst)
+        .map_err(|_| Error::new(ErrorKind::Input, Operation::Blind))?;
+
+    if bool::from(point.is_identity()) {
+        return Err(Error::new(ErrorKind::Input, Operation::Blind));
+    }
+
+    Ok(point)
+}
+
+/// Computes `e(H1(tweak), element)`.
+///
+/// Can only fail with [`ErrorKind::Info`] or [`ErrorKind::Protocol`]. The
+/// returned error doesn't carry an [`Operation`] yet.
+fn compute_base(tweak: &[u8], element: &G2Projective) -> Result<GtElem> {
+    let dst = GenericArray::from(STR_HASH_TO_GROUP)
+        .concat(STR_TWEAK.into())
+        .concat(create_context_string());
+    let tweak = Bls12_381::hash_to_curve::<Hash>(&[tweak], &dst)
+        .map_err(|_| Error::from(ErrorKind::Info))?;
+
+    pairing(&G1Projective::from(tweak), element).ok_or_else(|| ErrorKind::Protocol.into())
+}
+
+/// Proves that `log_G(pk) = log_base(evaluation)` in G1 and the target group.
+fn generate_proof<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    sk: Bls12_381Scalar,
+    pk: Bls12_381Elem,
+    base: &GtElem,
+    evaluation: &GtElem,
+) -> Result<PythiaProof> {
+    let r = Bls12_381::random_scalar(rng);
+    let t1 = Bls12_381::base_elem() * &r;
+    let t2 = base.pow(&r.into());
+    let c_scalar = compute_challenge(pk, base, evaluation, t1, &t2)
+        .map_err(|error| error.with_operation(Operation::Evaluate))?;
+    let s_scalar = r - &(c_scalar * &sk);
+
+    Ok(PythiaProof { c_scalar, s_scalar })
+}
+
+fn verify_proof(
+    pk: Bls12_381Elem,
+    base: &GtElem,
+    evaluation: &GtElem,
+    proof: &PythiaProof,
+) -> Result<()> {
+    // t1 = s * G + c * pk
+    let t1 = Bls12_381::base_elem() * &proof.s_scalar + &(pk * &proof.c_scalar);
+    // t2 = base^s * evaluation^c
+    let t2 = base
+        .pow(&proof.s_scalar.into())
+        .mul(&evaluation.pow(&proof.c_scalar.into()));
+    let c = compute_challenge(pk, base, evaluation, t1, &t2)
+        .map_err(|error| error.with_operation(Operation::VerifyProof))?;
+
+    if c.ct_eq(&proof.c_scalar).into() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::ProofVerification,
+            Operation::VerifyProof,
+        ))
+    }
+}
+
+/// Computes the challenge of the proof:
+///
+/// ```text
+/// c = G.HashToScalar(pk || base || evaluation || t1 || t2,
+///                    "HashToScalar-" || contextString)
+/// ```
+///
+/// All values have a fixed length. Can only fail with [`ErrorKind::Input`].
+/// The returned error doesn't carry an [`Operation`] yet.
+fn compute_challenge(
+    pk: Bls12_381Elem,
+    base: &GtElem,
+    evaluation: &GtElem,
+    t1: Bls12_381Elem,
+    t2: &GtElem,
+) -> Result<Bls12_381Scalar> {
+    let pk = Bls12_381::serialize_elem(pk);
+    let base = base.to_bytes();
+    let evaluation = evaluation.to_bytes();
+    let t1 = Bls12_381::serialize_elem(t1);
+    let t2 = t2.to_bytes();
+
+    let input = [
+        pk.as_slice(),
+        base.as_slice(),
+        evaluation.as_slice(),
+        t1.as_slice(),
+        t2.as_slice(),
+    ];
+    let dst = GenericArray::from(STR_HASH_TO_SCALAR).concat(create_context_string());
+
+    Bls12_381::hash_to_scalar::<Hash>(&input, &dst).map_err(|_| ErrorKind::Input.into())
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    fn pythia_retrieval() {
+        let mut rng = OsRng;
+        let server = PythiaServer::new(&mut rng).unwrap();
+
+        let blind_result = PythiaClient::blind(b"input", &mut rng).unwrap();
+        let message = PythiaBlindedElement::deserialize(&blind_result.message.serialize()).unwrap();
+        let evaluate_result = server.blind_evaluate(&mut rng, &message, b"tweak").unwrap();
+        let evaluation =
+            PythiaEvaluationElement::deserialize(&evaluate_result.message.serialize()).unwrap();
+        let proof = PythiaProof::deserialize(&evaluate_result.proof.serialize()).unwrap();
+        let output = blind_result
+            .state
+            .finalize(&evaluation, &proof, server.get_public_key(), b"tweak")
+            .unwrap();
+
+        assert!(output == server.evaluate(b"input", b"tweak").unwrap());
+        assert!(output == PythiaOutput::deserialize(&output.serialize()).unwrap());
+        assert!(output != server.evaluate(b"input", b"other tweak").unwrap());
+        assert!(output != server.evaluate(b"other input", b"tweak").unwrap());
+
+        // The evaluation is bound to the tweak and the key
+        let error = Error::new(ErrorKind::ProofVerification, Operation::VerifyProof);
+        assert!(matches!(
+            blind_result.state.finalize(&evaluation, &proof, server.get_public_key(), b"other tweak"),
+            Err(e) if e == error
+        ));
+        let other_server = PythiaServer::new(&mut rng).unwrap();
+        assert!(matches!(
+            blind_result.state.finalize(&evaluation, &proof, other_server.get_public_key(), b"tweak"),
+            Err(e) if e == error
+        ));
+    }
+
+    fn pythia_key_update() {
+        let mut rng = OsRng;
+        let server = PythiaServer::new(&mut rng).unwrap();
+        let new_server = PythiaServer::new(&mut rng).unwrap();
+        let mut output = server.evaluate(b"input", b"tweak").unwrap();
+
+        let token = server.update_token(&new_server);
+        let token = PythiaUpdateToken::deserialize(&token.serialize()).unwrap();
+        output.apply_update(&token);
+
+        assert!(output == new_server.evaluate(b"input", b"tweak").unwrap());
+        assert!(bool::from(
+            token
+                .update_public_key(server.get_public_key())
+                .ct_eq(&new_server.get_public_key())
+        ));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        pythia_retrieval();
+        pythia_key_update();
+
+        Ok(())
+    }
+}
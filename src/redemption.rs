@@ -0,0 +1,242 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the one-time redemption of tokens
+
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::{ArrayLength, GenericArray};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    CipherSuite, Error, ErrorKind, Operation, OprfServer, PoprfServer, Result, VoprfServer,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// Records the tokens that were already redeemed, so every token can only be
+/// redeemed once.
+///
+/// Tokens are recorded by their output, which is unique for every input, info
+/// and key, and reveals nothing about the input. A store is only needed for
+/// the lifetime of the key it records tokens of. Deployments with many servers
+/// can share a store, e.g. by implementing this trait with `SET NX` of Redis.
+pub trait NonceStore {
+    /// Inserts `nonce` if it isn't present yet. Returns `true` if it was
+    /// inserted, `false` if it was already present.
+    fn insert_if_absent(&mut self, nonce: &[u8]) -> bool;
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> OprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Redeems a token: checks that `output` is the output of `input` under
+    /// the key of this server and records it in the `store`, so it can't be
+    /// redeemed again. See [`NonceStore`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::Token`] if `output` isn't the output of `input`.
+    /// - [`ErrorKind::Replay`] if the token was already redeemed.
+    pub fn redeem<S: NonceStore + ?Sized>(
+        &self,
+        input: &[u8],
+        output: &Output<CS::FinalizeHash>,
+        store: &mut S,
+    ) -> Result<()> {
+        redeem(self.evaluate(input), output, store)
+    }
+}
+
+impl<CS: CipherSuite> VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Redeems a token, like [`OprfServer::redeem`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::Token`] if `output` isn't the output of `input`.
+    /// - [`ErrorKind::Replay`] if the token was already redeemed.
+    pub fn redeem<S: NonceStore + ?Sized>(
+        &self,
+        input: &[u8],
+        output: &Output<CS::FinalizeHash>,
+        store: &mut S,
+    ) -> Result<()> {
+        redeem(self.evaluate(input), output, store)
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Redeems a token with public `info`, like [`OprfServer::redeem`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is longer then [`u16::MAX`].
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::Token`] if `output` isn't the output of `input`.
+    /// - [`ErrorKind::Replay`] if the token was already redeemed.
+    pub fn redeem<S: NonceStore + ?Sized>(
+        &self,
+        input: &[u8],
+        info: Option<&[u8]>,
+        output: &Output<CS::FinalizeHash>,
+        store: &mut S,
+    ) -> Result<()> {
+        redeem(self.evaluate(input, info), output, store)
+    }
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+/// An unbounded store in memory, for a single server.
+#[cfg(feature = "alloc")]
+impl NonceStore for BTreeSet<Vec<u8>> {
+    fn insert_if_absent(&mut self, nonce: &[u8]) -> bool {
+        self.insert(nonce.to_vec())
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+/// Only records the token if it is valid, so invalid tokens can't fill the
+/// `store` or block valid ones.
+fn redeem<N: ArrayLength<u8>, S: NonceStore + ?Sized>(
+    expected: Result<GenericArray<u8, N>>,
+    output: &GenericArray<u8, N>,
+    store: &mut S,
+) -> Result<()> {
+    let expected = expected.map_err(|error| error.with_operation(Operation::Redeem))?;
+
+    if !bool::from(expected.ct_eq(output)) {
+        return Err(Error::new(ErrorKind::Token, Operation::Redeem));
+    }
+
+    if store.insert_if_absent(output) {
+        Ok(())
+    } else {
+        debug_event!("token replayed");
+        Err(Error::new(ErrorKind::Replay, Operation::Redeem))
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use ::alloc::collections::BTreeSet;
+    use ::alloc::vec::Vec;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{OprfClient, PoprfClient};
+
+    fn redeem_once<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let mut store = BTreeSet::<Vec<u8>>::new();
+        let replay = Error::new(ErrorKind::Replay, Operation::Redeem);
+        let invalid = Error::new(ErrorKind::Token, Operation::Redeem);
+
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = OprfClient::<CS>::blind(b"nonce", &mut rng).unwrap();
+        let message = server.blind_evaluate(&client_blind_result.message);
+        let output = client_blind_result
+            .state
+            .finalize(b"nonce", &message)
+            .unwrap();
+
+        // An invalid token doesn't block the valid one
+        assert_eq!(server.redeem(b"other", &output, &mut store), Err(invalid));
+        server.redeem(b"nonce", &output, &mut store).unwrap();
+        assert_eq!(server.redeem(b"nonce", &output, &mut store), Err(replay));
+
+        let voprf_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let output = voprf_server.evaluate(b"nonce").unwrap();
+        voprf_server.redeem(b"nonce", &output, &mut store).unwrap();
+        assert_eq!(
+            voprf_server.redeem(b"nonce", &output, &mut store),
+            Err(replay)
+        );
+
+        // The same input can be redeemed once per info
+        let poprf_server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        for info in [b"first", b"other"] {
+            let client_blind_result = PoprfClient::<CS>::blind(b"nonce", &mut rng).unwrap();
+            let result = poprf_server
+                .blind_evaluate(&mut rng, &client_blind_result.message, Some(info))
+                .unwrap();
+            let output = client_blind_result
+                .state
+                .finalize(
+                    b"nonce",
+                    &result.message,
+                    &result.proof,
+                    poprf_server.get_public_key(),
+                    Some(info),
+                )
+                .unwrap();
+            poprf_server
+                .redeem(b"nonce", Some(info), &output, &mut store)
+                .unwrap();
+            assert_eq!(
+                poprf_server.redeem(b"nonce", Some(info), &output, &mut store),
+                Err(replay)
+            );
+        }
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            redeem_once::<Ristretto255>();
+        }
+
+        redeem_once::<NistP256>();
+
+        Ok(())
+    }
+}
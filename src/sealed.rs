@@ -0,0 +1,390 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the encryption of persisted states under a caller-provided AEAD
+
+use alloc::vec::Vec;
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use generic_array::{ArrayLength, GenericArray};
+use rand_core::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::serialization::{tag, StateKind};
+use crate::{CipherSuite, Error, ErrorKind, Mode, Operation, Result};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_SEALED_STATE: [u8; 17] = *b"VOPRF-SealedState";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// An AEAD that seals persisted client and server states, see
+/// [`VoprfClient::seal`](crate::VoprfClient::seal).
+///
+/// The functions mirror `AeadInPlace` of the [`aead`](https://docs.rs/aead)
+/// crate, so implementations of it only have to forward to it:
+///
+/// ```text
+/// impl StateCipher for XChaCha20Poly1305Key {
+///     type NonceLen = U24;
+///     type TagLen = U16;
+///     type Error = aead::Error;
+///
+///     fn encrypt_in_place(&self, nonce, associated_data, buffer) -> Result<(), aead::Error> {
+///         self.0.encrypt_in_place(nonce, associated_data, buffer)
+///     }
+///
+///     ...
+/// }
+/// ```
+///
+/// Nonces are drawn at random for every sealed state, so the AEAD should
+/// either have nonces of at least 24 bytes, like XChaCha20-Poly1305, or be
+/// resistant to nonce reuse, like AES-GCM-SIV. With the 12-byte nonces of
+/// AES-GCM, a key must not seal more than 2^32 states.
+pub trait StateCipher {
+    /// Length of a nonce
+    type NonceLen: ArrayLength<u8>;
+    /// Length of the authentication tag appended to the ciphertext
+    type TagLen: ArrayLength<u8>;
+    /// Error returned when encryption or decryption fails
+    type Error;
+
+    /// Encrypts `buffer` in place and appends the authentication tag.
+    ///
+    /// # Errors
+    /// If the `buffer` can't be encrypted, e.g. because it's too long.
+    fn encrypt_in_place(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceLen>,
+        associated_data: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> core::result::Result<(), Self::Error>;
+
+    /// Authenticates and decrypts `buffer` in place and removes the
+    /// authentication tag.
+    ///
+    /// # Errors
+    /// If the `buffer` or the `associated_data` fail to authenticate.
+    fn decrypt_in_place(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceLen>,
+        associated_data: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> core::result::Result<(), Self::Error>;
+}
+
+///////////////////////
+// Utility Functions //
+// ================= //
+///////////////////////
+
+/// Seals the serialized `state` of `mode` with `cipher` under a random nonce,
+/// which is prepended to the ciphertext.
+///
+/// Can only fail with [`ErrorKind::Sealing`].
+pub(crate) fn seal<CS: CipherSuite, C: StateCipher, R: RngCore + CryptoRng>(
+    cipher: &C,
+    rng: &mut R,
+    mode: Mode,
+    kind: StateKind,
+    state: &[u8],
+    associated_data: &[u8],
+) -> Result<Vec<u8>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut nonce = GenericArray::<u8, C::NonceLen>::default();
+    rng.fill_bytes(&mut nonce);
+
+    // Reserved upfront, so the plaintext isn't left behind by a reallocation.
+    let mut buffer = Zeroizing::new(Vec::with_capacity(state.len() + C::TagLen::USIZE));
+    buffer.extend_from_slice(state);
+    cipher
+        .encrypt_in_place(
+            &nonce,
+            &associated_data_for::<CS>(mode, kind, associated_data),
+            &mut buffer,
+        )
+        .map_err(|_| Error::new(ErrorKind::Sealing, Operation::SealState))?;
+
+    Ok(nonce.iter().chain(buffer.iter()).copied().collect())
+}
+
+/// Opens the state of `mode` sealed with [`seal`] and returns the serialized
+/// state.
+///
+/// Can only fail with [`ErrorKind::Sealing`].
+pub(crate) fn open<CS: CipherSuite, C: StateCipher>(
+    cipher: &C,
+    mode: Mode,
+    kind: StateKind,
+    sealed: &[u8],
+    associated_data: &[u8],
+) -> Result<Zeroizing<Vec<u8>>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let error = Error::new(ErrorKind::Sealing, Operation::OpenState);
+
+    if sealed.len() < C::NonceLen::USIZE + C::TagLen::USIZE {
+        return Err(error);
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(C::NonceLen::USIZE);
+    let mut buffer = Zeroizing::new(ciphertext.to_vec());
+    cipher
+        .decrypt_in_place(
+            GenericArray::from_slice(nonce),
+            &associated_data_for::<CS>(mode, kind, associated_data),
+            &mut buffer,
+        )
+        .map_err(|_| error)?;
+
+    Ok(buffer)
+}
+
+/// The associated data binding a sealed state to its suite, mode and kind: a
+/// domain separation label, the tag of [`serialize_tagged`] and the caller's
+/// `associated_data`.
+///
+/// [`serialize_tagged`]: crate::VoprfClient::serialize_tagged
+fn associated_data_for<CS: CipherSuite>(
+    mode: Mode,
+    kind: StateKind,
+    associated_data: &[u8],
+) -> Vec<u8>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    STR_SEALED_STATE
+        .iter()
+        .chain(tag::<CS>(mode, kind).iter())
+        .chain(associated_data)
+        .copied()
+        .collect()
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Add;
+
+    use digest::Digest;
+    use generic_array::typenum::{Sum, U16, U24};
+    use rand::rngs::OsRng;
+    use sha2::Sha256;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+    use crate::{Group, OprfClient, OprfServer, PoprfClient, VoprfClient, VoprfServer};
+
+    /// An insecure AEAD for tests only: a SHA-256 keystream with a SHA-256 MAC.
+    struct TestCipher([u8; 32]);
+
+    impl TestCipher {
+        fn apply_keystream(&self, nonce: &[u8], buffer: &mut [u8]) {
+            for (counter, chunk) in buffer.chunks_mut(32).enumerate() {
+                let keystream = Sha256::new()
+                    .chain_update(self.0)
+                    .chain_update(nonce)
+                    .chain_update((counter as u64).to_be_bytes())
+                    .finalize();
+
+                for (byte, key) in chunk.iter_mut().zip(keystream) {
+                    *byte ^= key;
+                }
+            }
+        }
+
+        fn mac(&self, nonce: &[u8], associated_data: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+            Sha256::new()
+                .chain_update(b"mac")
+                .chain_update(self.0)
+                .chain_update(nonce)
+                .chain_update((associated_data.len() as u64).to_be_bytes())
+                .chain_update(associated_data)
+                .chain_update(ciphertext)
+                .finalize()[..16]
+                .to_vec()
+        }
+    }
+
+    impl StateCipher for TestCipher {
+        type NonceLen = U24;
+        type TagLen = U16;
+        type Error = ();
+
+        fn encrypt_in_place(
+            &self,
+            nonce: &GenericArray<u8, U24>,
+            associated_data: &[u8],
+            buffer: &mut Vec<u8>,
+        ) -> core::result::Result<(), ()> {
+            self.apply_keystream(nonce, buffer);
+            let mac = self.mac(nonce, associated_data, buffer);
+            buffer.extend_from_slice(&mac);
+
+            Ok(())
+        }
+
+        fn decrypt_in_place(
+            &self,
+            nonce: &GenericArray<u8, U24>,
+            associated_data: &[u8],
+            buffer: &mut Vec<u8>,
+        ) -> core::result::Result<(), ()> {
+            let len = buffer.len().checked_sub(16).ok_or(())?;
+            let mac = self.mac(nonce, associated_data, &buffer[..len]);
+
+            if !bool::from(mac.ct_eq(&buffer[len..])) {
+                return Err(());
+            }
+
+            buffer.truncate(len);
+            self.apply_keystream(nonce, buffer);
+
+            Ok(())
+        }
+    }
+
+    fn sealed_states<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+        Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ElemLen>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let cipher = TestCipher([1; 32]);
+        let input = b"input";
+        let session = b"session";
+        let error = Err(Error::new(ErrorKind::Sealing, Operation::OpenState));
+
+        let client = VoprfClient::<CS>::blind(input, &mut rng).unwrap().state;
+        let sealed = client.seal(&cipher, &mut rng, session).unwrap();
+        assert_eq!(
+            sealed.len(),
+            24 + VoprfClient::<CS>::LEN + 16,
+            "the nonce is prepended and the tag appended"
+        );
+        let opened = VoprfClient::<CS>::open(&cipher, &sealed, session).unwrap();
+        assert_eq!(opened.serialize(), client.serialize());
+
+        // Nonces are random, so sealing twice differs
+        assert_ne!(client.seal(&cipher, &mut rng, session).unwrap(), sealed);
+
+        // The associated data, the key and the ciphertext are authenticated
+        assert_eq!(
+            VoprfClient::<CS>::open(&cipher, &sealed, b"other").map(|_| ()),
+            error
+        );
+        assert_eq!(
+            VoprfClient::<CS>::open(&TestCipher([2; 32]), &sealed, session).map(|_| ()),
+            error
+        );
+        let mut tampered = sealed.clone();
+        tampered[30] ^= 1;
+        assert_eq!(
+            VoprfClient::<CS>::open(&cipher, &tampered, session).map(|_| ()),
+            error
+        );
+        assert_eq!(
+            VoprfClient::<CS>::open(&cipher, &sealed[..39], session).map(|_| ()),
+            error
+        );
+
+        // The mode is bound, even though the clients have the same length
+        assert_eq!(
+            PoprfClient::<CS>::open(&cipher, &sealed, session).map(|_| ()),
+            error
+        );
+
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let sealed = server.seal(&cipher, &mut rng, &[]).unwrap();
+        let opened = VoprfServer::<CS>::open(&cipher, &sealed, &[]).unwrap();
+        assert_eq!(opened.serialize(), server.serialize());
+
+        // So is the kind, even though clients and servers have the same length
+        assert_eq!(
+            VoprfClient::<CS>::open(&cipher, &sealed, &[]).map(|_| ()),
+            error
+        );
+
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let sealed = server.seal(&cipher, &mut rng, &[]).unwrap();
+        let opened = OprfServer::<CS>::open(&cipher, &sealed, &[]).unwrap();
+        assert_eq!(opened.serialize(), server.serialize());
+        assert_eq!(
+            OprfClient::<CS>::open(&cipher, &sealed, &[]).map(|_| ()),
+            error
+        );
+
+        let client = OprfClient::<CS>::blind(input, &mut rng).unwrap().state;
+        let sealed = client.seal(&cipher, &mut rng, &[]).unwrap();
+        assert_eq!(
+            OprfServer::<CS>::open(&cipher, &sealed, &[]).map(|_| ()),
+            error
+        );
+    }
+
+    fn sealed_suites<CS: CipherSuite, OtherCS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <OtherCS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<OtherCS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+        Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ElemLen>: ArrayLength<u8>,
+        <OtherCS::Group as Group>::ScalarLen: Add<<OtherCS::Group as Group>::ElemLen>,
+        Sum<<OtherCS::Group as Group>::ScalarLen, <OtherCS::Group as Group>::ElemLen>:
+            ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let cipher = TestCipher([1; 32]);
+
+        let client = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap().state;
+        let sealed = client.seal(&cipher, &mut rng, &[]).unwrap();
+        assert_eq!(
+            VoprfClient::<OtherCS>::open(&cipher, &sealed, &[]).map(|_| ()),
+            Err(Error::new(ErrorKind::Sealing, Operation::OpenState))
+        );
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            sealed_states::<Ristretto255>();
+            sealed_suites::<Ristretto255, NistP256>();
+        }
+
+        sealed_states::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the page-locked storage of secrets
+//!
+//! Locking memory is left to `memsec`, but placing a value of any type in pages
+//! of its own can't be done without `unsafe`. It is allowed on the items that
+//! need it only.
+
+use alloc::alloc::{alloc, dealloc, Layout};
+use core::fmt::{self, Debug, Formatter};
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+use crate::{Error, ErrorKind, Operation, Result};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// Stores a secret, e.g. a whole `VoprfServer`, in memory that is locked into
+/// RAM with `mlock`, so it is never written to swap, and on Linux excluded from
+/// core dumps with `madvise(MADV_DONTDUMP)`, both through [`memsec::mlock`].
+///
+/// The value gets pages of its own, so unlocking them never unlocks another
+/// value. On drop, the value is dropped and its pages are zeroized before they
+/// are unlocked and freed. Copies made before the value was moved into
+/// [`LockedBox::new`], e.g. on the stack, aren't covered.
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::{LockedBox, OprfServer};
+///
+/// let server = LockedBox::new(OprfServer::<CipherSuite>::new(&mut OsRng).unwrap()).unwrap();
+/// # let blinded_element = voprf::OprfClient::<CipherSuite>::blind(b"input", &mut OsRng)
+/// #     .unwrap()
+/// #     .message;
+/// let evaluation_element = server.blind_evaluate(&blinded_element);
+/// ```
+pub struct LockedBox<T> {
+    ptr: NonNull<T>,
+    layout: Layout,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<T> LockedBox<T> {
+    /// Moves `value` into locked memory.
+    ///
+    /// # Errors
+    /// [`ErrorKind::SecureMemory`] if the memory couldn't be allocated or
+    /// locked, e.g. because the limit of locked memory of the process is
+    /// reached.
+    #[allow(unsafe_code)]
+    pub fn new(value: T) -> Result<Self> {
+        let error = Error::new(ErrorKind::SecureMemory, Operation::LockMemory);
+
+        // SAFETY: `sysconf` has no preconditions.
+        let page_size =
+            usize::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).map_err(|_| error)?;
+        let size = mem::size_of::<T>()
+            .max(1)
+            .checked_add(page_size - 1)
+            .ok_or(error)?
+            / page_size
+            * page_size;
+        let layout = Layout::from_size_align(size, page_size.max(mem::align_of::<T>()))
+            .map_err(|_| error)?;
+
+        // SAFETY: `layout` has a non-zero size.
+        let ptr = NonNull::new(unsafe { alloc(layout) })
+            .ok_or(error)?
+            .cast::<T>();
+
+        // SAFETY: the range is the page-aligned allocation made above.
+        if !unsafe { memsec::mlock(ptr.as_ptr().cast(), size) } {
+            // SAFETY: `ptr` was allocated above with `layout` and isn't used
+            // afterwards.
+            unsafe { dealloc(ptr.as_ptr().cast(), layout) };
+            return Err(error);
+        }
+
+        // SAFETY: the allocation is valid for writes and aligned for `T`.
+        unsafe { ptr.as_ptr().write(value) };
+
+        Ok(Self { ptr, layout })
+    }
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+impl<T> Deref for LockedBox<T> {
+    type Target = T;
+
+    #[allow(unsafe_code)]
+    fn deref(&self) -> &T {
+        // SAFETY: `ptr` holds a valid `T` as long as `self` lives.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for LockedBox<T> {
+    #[allow(unsafe_code)]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: `ptr` holds a valid `T` as long as `self` lives.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for LockedBox<T> {
+    #[allow(unsafe_code)]
+    fn drop(&mut self) {
+        let memory = self.ptr.as_ptr();
+
+        // SAFETY: `ptr` holds a valid `T` until it is dropped here, afterwards
+        // the allocation made with `layout` is only treated as bytes, which
+        // `memsec::munlock` zeroizes before unlocking them. They are freed last
+        // and never used again.
+        unsafe {
+            ptr::drop_in_place(memory);
+            memsec::munlock(memory.cast(), self.layout.size());
+            dealloc(memory.cast(), self.layout);
+        }
+    }
+}
+
+impl<T> Debug for LockedBox<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedBox").finish_non_exhaustive()
+    }
+}
+
+// SAFETY: `LockedBox` owns its `T` like a `Box` does.
+#[allow(unsafe_code)]
+unsafe impl<T: Send> Send for LockedBox<T> {}
+
+// SAFETY: `LockedBox` only hands out references to its `T` like a `Box` does.
+#[allow(unsafe_code)]
+unsafe impl<T: Sync> Sync for LockedBox<T> {}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use digest::core_api::BlockSizeUser;
+    use digest::OutputSizeUser;
+    use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{CipherSuite, OprfClient, OprfServer};
+
+    fn locked_server<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let expected = server.evaluate(b"input").unwrap();
+
+        let server = LockedBox::new(server).unwrap();
+        let client_blind_result = OprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let message = server.blind_evaluate(&client_blind_result.message);
+        let output = client_blind_result
+            .state
+            .finalize(b"input", &message)
+            .unwrap();
+        assert_eq!(output, expected);
+
+        assert_eq!(server.ptr.as_ptr() as usize % server.layout.align(), 0);
+        assert_eq!(server.layout.size() % server.layout.align(), 0);
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            locked_server::<Ristretto255>();
+        }
+
+        locked_server::<NistP256>();
+
+        // Zero-sized values still get a page of their own
+        let unit = LockedBox::new(())?;
+        assert!(unit.layout.size() > 0);
+
+        Ok(())
+    }
+}
@@ -8,19 +8,53 @@
 //! Handles the serialization of each of the components used in the VOPRF
 //! protocol
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{self, Display, Formatter, LowerHex, UpperHex, Write};
+use core::iter::{Enumerate, Map};
 use core::ops::Add;
+use core::slice::ChunksExact;
+use core::str::FromStr;
 
+#[cfg(feature = "pythia")]
+use bls12_381_::{G2Affine, G2Projective};
 use digest::core_api::BlockSizeUser;
 use digest::OutputSizeUser;
 use generic_array::sequence::Concat;
-use generic_array::typenum::{IsLess, IsLessOrEqual, Sum, Unsigned, U256};
+use generic_array::typenum::{IsLess, IsLessOrEqual, Sum, Unsigned, U16, U2, U256, U4, U8};
+#[cfg(feature = "pythia")]
+use generic_array::typenum::{U128, U32, U576, U64, U96};
 use generic_array::{ArrayLength, GenericArray};
-
+#[cfg(any(feature = "alloc", feature = "elligator"))]
+use rand_core::{CryptoRng, RngCore};
+#[cfg(any(feature = "elligator", feature = "pythia"))]
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
+
+#[cfg(any(feature = "elligator", feature = "pythia"))]
+use crate::group::ALLOW_IDENTITY;
+#[cfg(feature = "dodis-yampolskiy")]
+use crate::DyVrfServer;
+#[cfg(feature = "elligator")]
+use crate::UniformGroup;
+#[cfg(feature = "pythia")]
+use crate::{
+    group::GtElem, Bls12_381, PythiaBlindedElement, PythiaClient, PythiaEvaluationElement,
+    PythiaOutput, PythiaProof, PythiaServer, PythiaUpdateToken,
+};
+#[cfg(feature = "alloc")]
+use crate::{sealed, ChunkedProof, IssuerDirectory, StateCipher};
 use crate::{
-    BlindedElement, CipherSuite, Error, EvaluationElement, Group, OprfClient, OprfServer,
-    PoprfClient, PoprfServer, Proof, Result, VoprfClient, VoprfServer,
+    BlindedElement, CipherSuite, Error, ErrorKind, EvaluationElement, Group, KeyCommitment,
+    KeyPossessionProof, KeyShare, Mode, Operation, OprfClient, OprfServer, PoprfClient,
+    PoprfServer, Proof, Result, VoprfClient, VoprfServer,
 };
 
+/// The number of elements [`EvaluationElement::serialize_batch`] passes to
+/// [`Group::serialize_elem_batch`] at once, bounding the stack it uses.
+const SERIALIZE_BATCH_SIZE: usize = 32;
+
 //////////////////////////////////////////////////////////
 // Serialization and Deserialization for High-Level API //
 // ==================================================== //
@@ -34,18 +68,24 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, OprfClientLen<CS>> {
-        CS::Group::serialize_scalar(self.blind)
+    /// Length of the serialization in bytes, equal to [`OprfClientLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize = <CS::Group as Group>::ScalarLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, OprfClientLen<CS>>> {
+        Zeroizing::new(CS::Group::serialize_scalar(self.blind))
     }
 
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
         let blind = deserialize_scalar::<CS::Group>(&mut input)?;
 
+        check_end(input, Operation::DeserializeScalar)?;
+
         Ok(Self { blind })
     }
 }
@@ -61,24 +101,33 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, VoprfClientLen<CS>>
+    /// Length of the serialization in bytes, equal to [`VoprfClientLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize =
+        <CS::Group as Group>::ScalarLen::USIZE + <CS::Group as Group>::ElemLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, VoprfClientLen<CS>>>
     where
         <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
         VoprfClientLen<CS>: ArrayLength<u8>,
     {
-        <CS::Group as Group>::serialize_scalar(self.blind)
-            .concat(<CS::Group as Group>::serialize_elem(self.blinded_element))
+        concat_zeroizing(&[
+            &Zeroizing::new(CS::Group::serialize_scalar(self.blind)),
+            &CS::Group::serialize_elem(self.blinded_element),
+        ])
     }
 
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
         let blind = deserialize_scalar::<CS::Group>(&mut input)?;
         let blinded_element = deserialize_elem::<CS::Group>(&mut input)?;
 
+        check_end(input, Operation::DeserializeElem)?;
+
         Ok(Self {
             blind,
             blinded_element,
@@ -97,24 +146,33 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, PoprfClientLen<CS>>
+    /// Length of the serialization in bytes, equal to [`PoprfClientLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize =
+        <CS::Group as Group>::ScalarLen::USIZE + <CS::Group as Group>::ElemLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, PoprfClientLen<CS>>>
     where
         <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
         PoprfClientLen<CS>: ArrayLength<u8>,
     {
-        <CS::Group as Group>::serialize_scalar(self.blind)
-            .concat(<CS::Group as Group>::serialize_elem(self.blinded_element))
+        concat_zeroizing(&[
+            &Zeroizing::new(CS::Group::serialize_scalar(self.blind)),
+            &CS::Group::serialize_elem(self.blinded_element),
+        ])
     }
 
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
         let blind = deserialize_scalar::<CS::Group>(&mut input)?;
         let blinded_element = deserialize_elem::<CS::Group>(&mut input)?;
 
+        check_end(input, Operation::DeserializeElem)?;
+
         Ok(Self {
             blind,
             blinded_element,
@@ -130,18 +188,24 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, OprfServerLen<CS>> {
-        CS::Group::serialize_scalar(self.sk)
+    /// Length of the serialization in bytes, equal to [`OprfServerLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize = <CS::Group as Group>::ScalarLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, OprfServerLen<CS>>> {
+        Zeroizing::new(CS::Group::serialize_scalar(self.sk))
     }
 
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
         let sk = deserialize_scalar::<CS::Group>(&mut input)?;
 
+        check_end(input, Operation::DeserializeScalar)?;
+
         Ok(Self { sk })
     }
 }
@@ -157,23 +221,33 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, VoprfServerLen<CS>>
+    /// Length of the serialization in bytes, equal to [`VoprfServerLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize =
+        <CS::Group as Group>::ScalarLen::USIZE + <CS::Group as Group>::ElemLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, VoprfServerLen<CS>>>
     where
         <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
         VoprfServerLen<CS>: ArrayLength<u8>,
     {
-        CS::Group::serialize_scalar(self.sk).concat(CS::Group::serialize_elem(self.pk))
+        concat_zeroizing(&[
+            &Zeroizing::new(CS::Group::serialize_scalar(self.sk)),
+            &CS::Group::serialize_elem(self.pk),
+        ])
     }
 
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
         let sk = deserialize_scalar::<CS::Group>(&mut input)?;
         let pk = deserialize_elem::<CS::Group>(&mut input)?;
 
+        check_end(input, Operation::DeserializeElem)?;
+
         Ok(Self { sk, pk })
     }
 }
@@ -189,23 +263,33 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, PoprfServerLen<CS>>
+    /// Length of the serialization in bytes, equal to [`PoprfServerLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize =
+        <CS::Group as Group>::ScalarLen::USIZE + <CS::Group as Group>::ElemLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, PoprfServerLen<CS>>>
     where
         <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
         PoprfServerLen<CS>: ArrayLength<u8>,
     {
-        CS::Group::serialize_scalar(self.sk).concat(CS::Group::serialize_elem(self.pk))
+        concat_zeroizing(&[
+            &Zeroizing::new(CS::Group::serialize_scalar(self.sk)),
+            &CS::Group::serialize_elem(self.pk),
+        ])
     }
 
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
         let sk = deserialize_scalar::<CS::Group>(&mut input)?;
         let pk = deserialize_elem::<CS::Group>(&mut input)?;
 
+        check_end(input, Operation::DeserializeElem)?;
+
         Ok(Self { sk, pk })
     }
 }
@@ -221,6 +305,10 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
+    /// Length of the serialization in bytes, equal to [`ProofLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize = <CS::Group as Group>::ScalarLen::USIZE * 2;
+
     /// Serialization into bytes
     pub fn serialize(&self) -> GenericArray<u8, ProofLen<CS>>
     where
@@ -234,209 +322,2176 @@ where
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
-        let c_scalar = deserialize_scalar::<CS::Group>(&mut input)?;
-        let s_scalar = deserialize_scalar::<CS::Group>(&mut input)?;
+        count_errors("proof", || {
+            let c_scalar = deserialize_scalar::<CS::Group>(&mut input)
+                .map_err(|error| error.with_operation(Operation::DeserializeProof))?;
+            let s_scalar = deserialize_scalar::<CS::Group>(&mut input)
+                .map_err(|error| error.with_operation(Operation::DeserializeProof))?;
+
+            check_end(input, Operation::DeserializeProof)?;
 
-        Ok(Proof { c_scalar, s_scalar })
+            Ok(Proof { c_scalar, s_scalar })
+        })
     }
 }
 
-/// Length of [`BlindedElement`] in bytes for serialization.
-pub type BlindedElementLen<CS> = <<CS as CipherSuite>::Group as Group>::ElemLen;
-
-impl<CS: CipherSuite> BlindedElement<CS>
+#[cfg(feature = "alloc")]
+impl<CS: CipherSuite> ChunkedProof<CS>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
     /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, BlindedElementLen<CS>> {
-        CS::Group::serialize_elem(self.0)
+    ///
+    /// The encoding is I2OSP(chunk size, 2) || the proof of every chunk.
+    pub fn serialize(&self) -> Vec<u8>
+    where
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+        ProofLen<CS>: ArrayLength<u8>,
+    {
+        let mut output = Vec::with_capacity(2 + self.proofs.len() * ProofLen::<CS>::USIZE);
+        output.extend_from_slice(&self.chunk_size.to_be_bytes());
+
+        for proof in &self.proofs {
+            output.extend_from_slice(&proof.serialize());
+        }
+
+        output
     }
 
     /// Deserialization from bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input` or the
+    /// chunk size is zero.
     pub fn deserialize(mut input: &[u8]) -> Result<Self> {
-        let value = deserialize_elem::<CS::Group>(&mut input)?;
+        count_errors("chunked_proof", || {
+            let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeProof);
 
-        Ok(Self(value))
+            let chunk_size = deserialize_u16(&mut input).ok_or(error)?;
+
+            if chunk_size == 0 {
+                return Err(error);
+            }
+
+            let mut proofs = Vec::new();
+
+            while !input.is_empty() {
+                let c_scalar = deserialize_scalar::<CS::Group>(&mut input)
+                    .map_err(|error| error.with_operation(Operation::DeserializeProof))?;
+                let s_scalar = deserialize_scalar::<CS::Group>(&mut input)
+                    .map_err(|error| error.with_operation(Operation::DeserializeProof))?;
+                proofs.push(Proof { c_scalar, s_scalar });
+            }
+
+            Ok(Self { chunk_size, proofs })
+        })
     }
 }
 
-/// Length of [`EvaluationElement`] in bytes for serialization.
-pub type EvaluationElementLen<CS> = <<CS as CipherSuite>::Group as Group>::ElemLen;
-
-impl<CS: CipherSuite> EvaluationElement<CS>
+impl<CS: CipherSuite> TryFrom<&[u8]> for Proof<CS>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    ProofLen<CS>: ArrayLength<u8>,
 {
-    /// Serialization into bytes
-    pub fn serialize(&self) -> GenericArray<u8, EvaluationElementLen<CS>> {
-        CS::Group::serialize_elem(self.0)
-    }
+    type Error = Error;
 
-    /// Deserialization from bytes
+    /// Deserialization from exactly [`ProofLen`] bytes
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if failed to deserialize `input`.
-    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
-        let value = deserialize_elem::<CS::Group>(&mut input)?;
+    /// [`ErrorKind::Deserialization`] if `input` has the wrong length or
+    /// failed to deserialize.
+    fn try_from(input: &[u8]) -> Result<Self> {
+        if input.len() != ProofLen::<CS>::USIZE {
+            return Err(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeProof,
+            ));
+        }
 
-        Ok(Self(value))
+        Self::deserialize(input)
     }
 }
 
-fn deserialize_elem<G: Group>(input: &mut &[u8]) -> Result<G::Elem> {
-    let input = input
-        .take_ext(G::ElemLen::USIZE)
-        .ok_or(Error::Deserialization)?;
-    G::deserialize_elem(input)
+/// Formats the serialized [`Proof`] as lowercase hex.
+impl<CS: CipherSuite> Display for Proof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    ProofLen<CS>: ArrayLength<u8>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
 }
 
-fn deserialize_scalar<G: Group>(input: &mut &[u8]) -> Result<G::Scalar> {
-    let input = input
-        .take_ext(G::ScalarLen::USIZE)
-        .ok_or(Error::Deserialization)?;
-    G::deserialize_scalar(input)
+/// Formats the serialized [`Proof`] as lowercase hex.
+impl<CS: CipherSuite> LowerHex for Proof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    ProofLen<CS>: ArrayLength<u8>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
 }
 
-trait SliceExt {
-    fn take_ext(self: &mut &Self, take: usize) -> Option<&Self>;
+/// Formats the serialized [`Proof`] as uppercase hex.
+impl<CS: CipherSuite> UpperHex for Proof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    ProofLen<CS>: ArrayLength<u8>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, true)
+    }
 }
 
-impl<T> SliceExt for [T] {
-    fn take_ext(self: &mut &Self, take: usize) -> Option<&Self> {
-        if take > self.len() {
-            return None;
-        }
+/// Parses a [`Proof`] from the hex format produced by its [`Display`]
+/// implementation. Both lowercase and uppercase hex are accepted.
+impl<CS: CipherSuite> FromStr for Proof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    ProofLen<CS>: ArrayLength<u8>,
+{
+    type Err = Error;
 
-        let (front, back) = self.split_at(take);
-        *self = back;
-        Some(front)
+    fn from_str(input: &str) -> Result<Self> {
+        let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeProof);
+        let bytes = parse_hex::<ProofLen<CS>>(input).ok_or(error)?;
+
+        Self::deserialize(&bytes)
     }
 }
 
-#[cfg(feature = "serde")]
-pub(crate) mod serde {
-    use core::marker::PhantomData;
+/// Length of [`BlindedElement`] in bytes for serialization.
+pub type BlindedElementLen<CS> = <<CS as CipherSuite>::Group as Group>::ElemLen;
 
-    use generic_array::GenericArray;
-    use serde::de::{Deserializer, Error};
-    use serde::ser::Serializer;
-    use serde::{Deserialize, Serialize};
+/// Iterator over the elements of [`BlindedElement::deserialize_batch`].
+pub type BlindedElementDeserializeBatchResult<'a, CS> =
+    Map<Enumerate<ChunksExact<'a, u8>>, fn((usize, &'a [u8])) -> Result<BlindedElement<CS>>>;
 
-    use crate::Group;
+impl<CS: CipherSuite> BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Length of the serialization in bytes, equal to [`BlindedElementLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize = <CS::Group as Group>::ElemLen::USIZE;
 
-    pub(crate) struct Element<G: Group>(PhantomData<G>);
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, BlindedElementLen<CS>> {
+        CS::Group::serialize_elem(self.0)
+    }
 
-    impl<'de, G: Group> Element<G> {
-        pub(crate) fn deserialize<D>(deserializer: D) -> Result<G::Elem, D::Error>
-        where
-            D: Deserializer<'de>,
-        {
-            GenericArray::<_, G::ElemLen>::deserialize(deserializer)
-                .and_then(|bytes| G::deserialize_elem(&bytes).map_err(D::Error::custom))
-        }
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        count_errors("blinded_element", || {
+            let value = deserialize_elem::<CS::Group>(&mut input)?;
 
-        pub(crate) fn serialize<S>(self_: &G::Elem, serializer: S) -> Result<S::Ok, S::Error>
-        where
-            S: Serializer,
-        {
-            G::serialize_elem(*self_).serialize(serializer)
-        }
+            check_end(input, Operation::DeserializeElem)?;
+
+            Ok(Self(value))
+        })
     }
 
-    pub(crate) struct Scalar<G: Group>(PhantomData<G>);
+    /// Deserializes a list of `count` concatenated serializations, e.g. the
+    /// body of a batch request. The length of `input` is checked once, the
+    /// elements are then deserialized one by one by the returned iterator.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if `input` isn't exactly `count`
+    /// serializations long. Items of the iterator fail like
+    /// [`deserialize`](Self::deserialize) does, carrying the index of the
+    /// offending item.
+    pub fn deserialize_batch(
+        input: &[u8],
+        count: usize,
+    ) -> Result<BlindedElementDeserializeBatchResult<'_, CS>> {
+        let inputs = count_errors("blinded_element", || split_batch::<CS::Group>(input, count))?;
+
+        Ok(inputs.enumerate().map(|(index, input)| {
+            Self::deserialize(input).map_err(|error| error.with_index(index))
+        }))
+    }
+}
 
-    impl<'de, G: Group> Scalar<G> {
-        pub(crate) fn deserialize<D>(deserializer: D) -> Result<G::Scalar, D::Error>
-        where
-            D: Deserializer<'de>,
-        {
-            GenericArray::<_, G::ScalarLen>::deserialize(deserializer)
-                .and_then(|bytes| G::deserialize_scalar(&bytes).map_err(D::Error::custom))
-        }
+#[cfg(feature = "elligator")]
+impl<CS: CipherSuite> BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    CS::Group: UniformGroup,
+{
+    /// Encoding into bytes that are indistinguishable from uniformly random
+    /// ones, see [`UniformGroup`]. Every call returns a different encoding.
+    pub fn to_uniform_bytes<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+    ) -> GenericArray<u8, UniformElementLen<CS>> {
+        CS::Group::encode_uniform(self.0, rng)
+    }
 
-        pub(crate) fn serialize<S>(self_: &G::Scalar, serializer: S) -> Result<S::Ok, S::Error>
-        where
-            S: Serializer,
-        {
-            G::serialize_scalar(*self_).serialize(serializer)
-        }
+    /// Decoding from the output of [`BlindedElement::to_uniform_bytes`]
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if `input` has the wrong length or
+    /// decodes to the identity element.
+    pub fn from_uniform_bytes(input: &[u8]) -> Result<Self> {
+        count_errors("blinded_element", || {
+            decode_uniform::<CS::Group>(input).map(Self)
+        })
     }
 }
 
-#[cfg(test)]
-mod test {
-    use proptest::collection::vec;
-    use proptest::prelude::*;
+/// Formats the serialized [`BlindedElement`] as lowercase hex.
+impl<CS: CipherSuite> Display for BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
+}
 
-    use crate::{
-        BlindedElement, EvaluationElement, OprfClient, OprfServer, PoprfClient, PoprfServer, Proof,
-        VoprfClient, VoprfServer,
-    };
+/// Formats the serialized [`BlindedElement`] as lowercase hex.
+impl<CS: CipherSuite> LowerHex for BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
+}
 
-    macro_rules! test_deserialize {
-        ($item:ident, $bytes:ident) => {
-            #[cfg(feature = "ristretto255")]
-            {
-                let _ = $item::<crate::Ristretto255>::deserialize(&$bytes[..]);
-            }
+/// Formats the serialized [`BlindedElement`] as uppercase hex.
+impl<CS: CipherSuite> UpperHex for BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, true)
+    }
+}
 
-            let _ = $item::<p256::NistP256>::deserialize(&$bytes[..]);
-        };
+/// Parses a [`BlindedElement`] from the hex format produced by its [`Display`]
+/// implementation. Both lowercase and uppercase hex are accepted.
+impl<CS: CipherSuite> FromStr for BlindedElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeElem);
+        let bytes = parse_hex::<BlindedElementLen<CS>>(input).ok_or(error)?;
+
+        Self::deserialize(&bytes)
     }
+}
 
-    proptest! {
-        #[test]
-        fn test_nocrash_oprf_client(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(OprfClient, bytes);
-        }
+/// Length of the uniform encodings of [`BlindedElement`] and
+/// [`EvaluationElement`] in bytes.
+#[cfg(feature = "elligator")]
+pub type UniformElementLen<CS> = <<CS as CipherSuite>::Group as UniformGroup>::UniformLen;
 
-        #[test]
-        fn test_nocrash_voprf_client(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(VoprfClient, bytes);
-        }
+/// Length of [`EvaluationElement`] in bytes for serialization.
+pub type EvaluationElementLen<CS> = <<CS as CipherSuite>::Group as Group>::ElemLen;
 
-        #[test]
-        fn test_nocrash_poprf_client(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(PoprfClient, bytes);
-        }
+/// Iterator over the elements of [`EvaluationElement::deserialize_batch`].
+pub type EvaluationElementDeserializeBatchResult<'a, CS> =
+    Map<Enumerate<ChunksExact<'a, u8>>, fn((usize, &'a [u8])) -> Result<EvaluationElement<CS>>>;
 
-        #[test]
-        fn test_nocrash_oprf_server(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(OprfServer, bytes);
-        }
+impl<CS: CipherSuite> EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Length of the serialization in bytes, equal to [`EvaluationElementLen`],
+    /// to allocate buffers or check lengths before deserialization.
+    pub const LEN: usize = <CS::Group as Group>::ElemLen::USIZE;
 
-        #[test]
-        fn test_nocrash_voprf_server(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(VoprfServer, bytes);
-        }
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, EvaluationElementLen<CS>> {
+        CS::Group::serialize_elem(self.0)
+    }
 
-        #[test]
-        fn test_nocrash_poprf_server(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(PoprfServer, bytes);
+    /// Serializes a batch of `elements` into `output`, like
+    /// [`serialize`](Self::serialize) does for each of them, but sharing work
+    /// across the batch where the group supports it, see
+    /// [`Group::serialize_elem_batch`]. Only as many elements as fit into
+    /// `output` are serialized.
+    pub fn serialize_batch(
+        elements: &[Self],
+        output: &mut [GenericArray<u8, EvaluationElementLen<CS>>],
+    ) {
+        let mut elems = [CS::Group::identity_elem(); SERIALIZE_BATCH_SIZE];
+
+        for (elements, output) in elements
+            .chunks(SERIALIZE_BATCH_SIZE)
+            .zip(output.chunks_mut(SERIALIZE_BATCH_SIZE))
+        {
+            for (elem, element) in elems.iter_mut().zip(elements) {
+                *elem = element.0;
+            }
+
+            if let Some(elems) = elems.get(..elements.len()) {
+                CS::Group::serialize_elem_batch(elems, output);
+            }
         }
+    }
 
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        count_errors("evaluation_element", || {
+            let value = deserialize_elem::<CS::Group>(&mut input)?;
 
-        #[test]
-        fn test_nocrash_blinded_element(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(BlindedElement, bytes);
-        }
+            check_end(input, Operation::DeserializeElem)?;
 
-        #[test]
-        fn test_nocrash_evaluation_element(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(EvaluationElement, bytes);
-        }
+            Ok(Self(value))
+        })
+    }
 
-        #[test]
-        fn test_nocrash_proof(bytes in vec(any::<u8>(), 0..200)) {
-            test_deserialize!(Proof, bytes);
+    /// Deserializes a list of `count` concatenated serializations, e.g. the
+    /// body of a batch request. The length of `input` is checked once, the
+    /// elements are then deserialized one by one by the returned iterator.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if `input` isn't exactly `count`
+    /// serializations long. Items of the iterator fail like
+    /// [`deserialize`](Self::deserialize) does, carrying the index of the
+    /// offending item.
+    pub fn deserialize_batch(
+        input: &[u8],
+        count: usize,
+    ) -> Result<EvaluationElementDeserializeBatchResult<'_, CS>> {
+        let inputs = count_errors("evaluation_element", || {
+            split_batch::<CS::Group>(input, count)
+        })?;
+
+        Ok(inputs.enumerate().map(|(index, input)| {
+            Self::deserialize(input).map_err(|error| error.with_index(index))
+        }))
+    }
+}
+
+#[cfg(feature = "elligator")]
+impl<CS: CipherSuite> EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    CS::Group: UniformGroup,
+{
+    /// Encoding into bytes that are indistinguishable from uniformly random
+    /// ones, see [`UniformGroup`]. Every call returns a different encoding.
+    pub fn to_uniform_bytes<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+    ) -> GenericArray<u8, UniformElementLen<CS>> {
+        CS::Group::encode_uniform(self.0, rng)
+    }
+
+    /// Decoding from the output of [`EvaluationElement::to_uniform_bytes`]
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if `input` has the wrong length or
+    /// decodes to the identity element.
+    pub fn from_uniform_bytes(input: &[u8]) -> Result<Self> {
+        count_errors("evaluation_element", || {
+            decode_uniform::<CS::Group>(input).map(Self)
+        })
+    }
+}
+
+/// Formats the serialized [`EvaluationElement`] as lowercase hex.
+impl<CS: CipherSuite> Display for EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
+}
+
+/// Formats the serialized [`EvaluationElement`] as lowercase hex.
+impl<CS: CipherSuite> LowerHex for EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
+}
+
+/// Formats the serialized [`EvaluationElement`] as uppercase hex.
+impl<CS: CipherSuite> UpperHex for EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, true)
+    }
+}
+
+/// Parses a [`EvaluationElement`] from the hex format produced by its
+/// [`Display`] implementation. Both lowercase and uppercase hex are accepted.
+impl<CS: CipherSuite> FromStr for EvaluationElement<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeElem);
+        let bytes = parse_hex::<EvaluationElementLen<CS>>(input).ok_or(error)?;
+
+        Self::deserialize(&bytes)
+    }
+}
+
+/// Length of [`KeyPossessionProof`] in bytes for serialization.
+pub type KeyPossessionProofLen<CS> = Sum<
+    <<CS as CipherSuite>::Group as Group>::ScalarLen,
+    <<CS as CipherSuite>::Group as Group>::ScalarLen,
+>;
+
+impl<CS: CipherSuite> KeyPossessionProof<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Length of the serialization in bytes, equal to
+    /// [`KeyPossessionProofLen`], to allocate buffers or check lengths
+    /// before deserialization.
+    pub const LEN: usize = <CS::Group as Group>::ScalarLen::USIZE * 2;
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, KeyPossessionProofLen<CS>>
+    where
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+        KeyPossessionProofLen<CS>: ArrayLength<u8>,
+    {
+        CS::Group::serialize_scalar(self.c_scalar)
+            .concat(CS::Group::serialize_scalar(self.s_scalar))
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let c_scalar = deserialize_scalar::<CS::Group>(&mut input)
+            .map_err(|error| error.with_operation(Operation::DeserializeKeyPossessionProof))?;
+        let s_scalar = deserialize_scalar::<CS::Group>(&mut input)
+            .map_err(|error| error.with_operation(Operation::DeserializeKeyPossessionProof))?;
+
+        check_end(input, Operation::DeserializeKeyPossessionProof)?;
+
+        Ok(Self { c_scalar, s_scalar })
+    }
+}
+
+/// Length of [`KeyShare`] in bytes for serialization.
+pub type KeyShareLen<CS> = Sum<U2, <<CS as CipherSuite>::Group as Group>::ScalarLen>;
+
+impl<CS: CipherSuite> KeyShare<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Length of the serialization in bytes, equal to [`KeyShareLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize = 2 + <CS::Group as Group>::ScalarLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    ///
+    /// The encoding is I2OSP(index, 2) || share.
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, KeyShareLen<CS>>>
+    where
+        U2: Add<<CS::Group as Group>::ScalarLen>,
+        KeyShareLen<CS>: ArrayLength<u8>,
+    {
+        concat_zeroizing(&[
+            &self.index.to_be_bytes(),
+            &Zeroizing::new(CS::Group::serialize_scalar(self.value)),
+        ])
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeKeyShare);
+
+        let index = deserialize_u16(&mut input).ok_or(error)?;
+        let value = deserialize_scalar::<CS::Group>(&mut input)
+            .map_err(|error| error.with_operation(Operation::DeserializeKeyShare))?;
+
+        check_end(input, Operation::DeserializeKeyShare)?;
+
+        Ok(Self { index, value })
+    }
+}
+
+/// Length of [`KeyCommitment`] in bytes for serialization.
+pub type KeyCommitmentLen<CS> = Sum<
+    Sum<Sum<U2, <<CS as CipherSuite>::Group as Group>::ElemLen>, U16>,
+    <<CS as CipherSuite>::Hash as OutputSizeUser>::OutputSize,
+>;
+
+impl<CS: CipherSuite> KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Length of the serialization in bytes, equal to [`KeyCommitmentLen`], to
+    /// allocate buffers or check lengths before deserialization.
+    pub const LEN: usize = 2
+        + <CS::Group as Group>::ElemLen::USIZE
+        + 16
+        + <CS::Hash as OutputSizeUser>::OutputSize::USIZE;
+
+    /// Serialization into bytes
+    ///
+    /// The encoding is I2OSP(suite ID, 2) || public key || I2OSP(not before, 8)
+    /// || I2OSP(not after, 8) || key ID.
+    pub fn serialize(&self) -> GenericArray<u8, KeyCommitmentLen<CS>>
+    where
+        U2: Add<<CS::Group as Group>::ElemLen>,
+        Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+        Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+            ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+        KeyCommitmentLen<CS>: ArrayLength<u8>,
+    {
+        let not_before = GenericArray::<_, U8>::from(self.not_before.to_be_bytes());
+        let not_after = GenericArray::<_, U8>::from(self.not_after.to_be_bytes());
+
+        GenericArray::<_, U2>::from(CS::ID.to_be_bytes())
+            .concat(CS::Group::serialize_elem(self.public_key))
+            .concat(not_before.concat(not_after))
+            .concat(self.key_id.clone())
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// This doesn't verify the commitment, see [`KeyCommitment::verify`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input` or it
+    /// belongs to a different ciphersuite.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let error = Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeKeyCommitment,
+        );
+
+        let suite_id = input.take_ext(2).ok_or(error)?;
+
+        if suite_id != CS::ID.to_be_bytes() {
+            return Err(error);
+        }
+
+        let public_key = deserialize_elem::<CS::Group>(&mut input)
+            .map_err(|error| error.with_operation(Operation::DeserializeKeyCommitment))?;
+        let not_before = deserialize_u64(&mut input).ok_or(error)?;
+        let not_after = deserialize_u64(&mut input).ok_or(error)?;
+        let key_id = input
+            .take_ext(<CS::Hash as OutputSizeUser>::OutputSize::USIZE)
+            .ok_or(error)?;
+
+        check_end(input, Operation::DeserializeKeyCommitment)?;
+
+        Ok(Self {
+            public_key,
+            not_before,
+            not_after,
+            key_id: GenericArray::clone_from_slice(key_id),
+        })
+    }
+}
+
+/// Formats the serialized [`KeyCommitment`] as lowercase hex, e.g. to put the
+/// public key of a server into a configuration file.
+impl<CS: CipherSuite> Display for KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    U2: Add<<CS::Group as Group>::ElemLen>,
+    Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+    Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+        ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+    KeyCommitmentLen<CS>: ArrayLength<u8>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
+}
+
+/// Formats the serialized [`KeyCommitment`] as lowercase hex.
+impl<CS: CipherSuite> LowerHex for KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    U2: Add<<CS::Group as Group>::ElemLen>,
+    Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+    Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+        ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+    KeyCommitmentLen<CS>: ArrayLength<u8>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, false)
+    }
+}
+
+/// Formats the serialized [`KeyCommitment`] as uppercase hex.
+impl<CS: CipherSuite> UpperHex for KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    U2: Add<<CS::Group as Group>::ElemLen>,
+    Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+    Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+        ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+    KeyCommitmentLen<CS>: ArrayLength<u8>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_hex(&self.serialize(), f, true)
+    }
+}
+
+/// Parses a [`KeyCommitment`] from the hex format produced by its [`Display`]
+/// implementation. Both lowercase and uppercase hex are accepted.
+///
+/// This doesn't verify the commitment, see [`KeyCommitment::verify`].
+impl<CS: CipherSuite> FromStr for KeyCommitment<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    U2: Add<<CS::Group as Group>::ElemLen>,
+    Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+    Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+        ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+    KeyCommitmentLen<CS>: ArrayLength<u8>,
+{
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let error = Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeKeyCommitment,
+        );
+        let bytes = parse_hex::<KeyCommitmentLen<CS>>(input).ok_or(error)?;
+
+        Self::deserialize(&bytes)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<CS: CipherSuite> IssuerDirectory<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Serialization into bytes
+    ///
+    /// The encoding is the concatenation of the serialized [`KeyCommitment`] of
+    /// every entry, in canonical order.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(self.entries.len() * KeyCommitment::<CS>::LEN);
+
+        for entry in &self.entries {
+            output.extend_from_slice(&CS::ID.to_be_bytes());
+            output.extend_from_slice(&CS::Group::serialize_elem(entry.public_key));
+            output.extend_from_slice(&entry.not_before.to_be_bytes());
+            output.extend_from_slice(&entry.not_after.to_be_bytes());
+            output.extend_from_slice(&entry.key_id);
+        }
+
+        output
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// The entries may be in any order and are re-serialized in canonical
+    /// order. This doesn't verify the entries, see
+    /// [`IssuerDirectory::select_pinned`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`, an
+    /// entry belongs to a different ciphersuite or two entries have the same
+    /// key ID.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let error = Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeKeyCommitment,
+        );
+
+        let mut directory = Self::new();
+
+        while !input.is_empty() {
+            let entry = input.take_ext(KeyCommitment::<CS>::LEN).ok_or(error)?;
+            directory
+                .insert(KeyCommitment::deserialize(entry)?)
+                .map_err(|_| error)?;
+        }
+
+        Ok(directory)
+    }
+}
+
+#[cfg(feature = "dodis-yampolskiy")]
+impl<CS: CipherSuite> DyVrfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = <CS::Group as Group>::ScalarLen::USIZE;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, <CS::Group as Group>::ScalarLen>> {
+        Zeroizing::new(CS::Group::serialize_scalar(self.sk))
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let sk = deserialize_scalar::<CS::Group>(&mut input)?;
+        let pk = CS::Group::base_elem() * &sk;
+
+        check_end(input, Operation::DeserializeScalar)?;
+
+        Ok(Self { sk, pk })
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl PythiaClient {
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = 128;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, U128>> {
+        concat_zeroizing(&[
+            &Zeroizing::new(Bls12_381::serialize_scalar(self.blind)),
+            &G2Affine::from(self.blinded_element).to_compressed(),
+        ])
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let blind = deserialize_scalar::<Bls12_381>(&mut input)?;
+        let blinded_element = deserialize_g2(&mut input)?;
+
+        check_end(input, Operation::DeserializeElem)?;
+
+        Ok(Self {
+            blind,
+            blinded_element,
+        })
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl PythiaServer {
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = 32;
+
+    /// Serialization into bytes, which are zeroized when dropped
+    pub fn serialize(&self) -> Zeroizing<GenericArray<u8, U32>> {
+        Zeroizing::new(Bls12_381::serialize_scalar(self.sk))
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let sk = deserialize_scalar::<Bls12_381>(&mut input)?;
+        let pk = Bls12_381::base_elem() * &sk;
+
+        check_end(input, Operation::DeserializeScalar)?;
+
+        Ok(Self { sk, pk })
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl PythiaBlindedElement {
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = 96;
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, U96> {
+        GenericArray::clone_from_slice(&G2Affine::from(self.0).to_compressed())
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let value = deserialize_g2(&mut input)?;
+
+        check_end(input, Operation::DeserializeElem)?;
+
+        Ok(Self(value))
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl PythiaEvaluationElement {
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = 576;
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, U576> {
+        self.0.to_bytes()
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let value = deserialize_gt(&mut input)?;
+
+        if !ALLOW_IDENTITY && bool::from(value.ct_eq(&GtElem::identity())) {
+            return Err(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem,
+            ));
+        }
+
+        check_end(input, Operation::DeserializeElem)?;
+
+        Ok(Self(value))
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl PythiaProof {
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = 64;
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, U64> {
+        Bls12_381::serialize_scalar(self.c_scalar)
+            .concat(Bls12_381::serialize_scalar(self.s_scalar))
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let c_scalar = deserialize_scalar::<Bls12_381>(&mut input)
+            .map_err(|error| error.with_operation(Operation::DeserializeProof))?;
+        let s_scalar = deserialize_scalar::<Bls12_381>(&mut input)
+            .map_err(|error| error.with_operation(Operation::DeserializeProof))?;
+
+        check_end(input, Operation::DeserializeProof)?;
+
+        Ok(Self { c_scalar, s_scalar })
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl PythiaOutput {
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = 576;
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, U576> {
+        self.0.to_bytes()
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let value = deserialize_gt(&mut input)?;
+
+        check_end(input, Operation::DeserializeElem)?;
+
+        Ok(Self(value))
+    }
+}
+
+#[cfg(feature = "pythia")]
+impl PythiaUpdateToken {
+    /// Length of the serialization in bytes, to allocate buffers or check
+    /// lengths before deserialization.
+    pub const LEN: usize = 32;
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> GenericArray<u8, U32> {
+        Bls12_381::serialize_scalar(self.0)
+    }
+
+    /// Deserialization from bytes
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if failed to deserialize `input`.
+    pub fn deserialize(mut input: &[u8]) -> Result<Self> {
+        let value = deserialize_scalar::<Bls12_381>(&mut input)?;
+
+        check_end(input, Operation::DeserializeScalar)?;
+
+        Ok(Self(value))
+    }
+}
+
+////////////////////////////////////////////
+// Tagged Serialization of the API States //
+// ====================================== //
+////////////////////////////////////////////
+
+/// Length of a state serialized with its tag, see
+/// [`VoprfClient::serialize_tagged`], where `L` is the length of the state.
+pub type TaggedLen<L> = Sum<U4, L>;
+
+/// Whether a tagged state is the one of a client or of a server, so states of
+/// the same mode and length can't be confused with each other.
+#[derive(Clone, Copy)]
+pub(crate) enum StateKind {
+    Client,
+    Server,
+}
+
+impl StateKind {
+    fn to_u8(self) -> u8 {
+        match self {
+            StateKind::Client => 0,
+            StateKind::Server => 1,
+        }
+    }
+}
+
+/// Implements `serialize_tagged` and `deserialize_tagged` on a state of the
+/// given mode and kind, with the bounds `serialize` of that state requires.
+macro_rules! impl_tagged {
+    ($state:ident, $mode:expr, $kind:expr, $len:ident $(, $bound:ty: $bounded:path)*) => {
+        impl<CS: CipherSuite> $state<CS>
+        where
+            <CS::Hash as OutputSizeUser>::OutputSize:
+                IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        {
+            /// Serialization into bytes, prefixed with a tag of the big-endian
+            /// [`CipherSuite::ID`], the mode byte of the context string and
+            /// whether the state is a client's or a server's, so bytes of
+            /// another suite, mode or kind of state are rejected by
+            /// [`deserialize_tagged`](Self::deserialize_tagged) instead of
+            /// being silently accepted if they happen to have the same length.
+            /// The bytes are zeroized when dropped.
+            pub fn serialize_tagged(&self) -> Zeroizing<GenericArray<u8, TaggedLen<$len<CS>>>>
+            where
+                $($bound: $bounded,)*
+                U4: Add<$len<CS>>,
+                TaggedLen<$len<CS>>: ArrayLength<u8>,
+            {
+                concat_zeroizing(&[&tag::<CS>($mode, $kind), &self.serialize()])
+            }
+
+            /// Deserialization from bytes produced by
+            /// [`serialize_tagged`](Self::serialize_tagged)
+            ///
+            /// # Errors
+            /// - [`ErrorKind::SuiteMismatch`] if the tag is of another suite,
+            ///   mode or kind of state.
+            /// - [`ErrorKind::Deserialization`] if failed to deserialize
+            ///   `input`.
+            pub fn deserialize_tagged(input: &[u8]) -> Result<Self> {
+                Self::deserialize(check_tag::<CS>(input, $mode, $kind)?)
+            }
+
+            /// Seals the state with the AEAD `cipher` for persistence, binding
+            /// the suite, the mode, whether it is a client's or a server's
+            /// state and the caller's `associated_data`, e.g. a session ID, to
+            /// it. Open it again with [`open`](Self::open).
+            ///
+            /// # Errors
+            /// [`ErrorKind::Sealing`] if the `cipher` fails to encrypt.
+            #[cfg(feature = "alloc")]
+            pub fn seal<C: StateCipher, R: RngCore + CryptoRng>(
+                &self,
+                cipher: &C,
+                rng: &mut R,
+                associated_data: &[u8],
+            ) -> Result<Vec<u8>>
+            where
+                $($bound: $bounded,)*
+            {
+                let state = self.serialize();
+
+                sealed::seal::<CS, _, _>(cipher, rng, $mode, $kind, &state, associated_data)
+            }
+
+            /// Opens a state sealed with [`seal`](Self::seal) with the same
+            /// `cipher` and `associated_data`.
+            ///
+            /// # Errors
+            /// - [`ErrorKind::Sealing`] if the `sealed` bytes fail to
+            ///   authenticate, e.g. because they were sealed with another key,
+            ///   suite, mode, kind of state or `associated_data`.
+            /// - [`ErrorKind::Deserialization`] if failed to deserialize the
+            ///   opened state.
+            #[cfg(feature = "alloc")]
+            pub fn open<C: StateCipher>(
+                cipher: &C,
+                sealed: &[u8],
+                associated_data: &[u8],
+            ) -> Result<Self> {
+                let state = sealed::open::<CS, _>(cipher, $mode, $kind, sealed, associated_data)?;
+
+                Self::deserialize(&state)
+            }
+        }
+    };
+}
+
+impl_tagged!(OprfClient, Mode::Oprf, StateKind::Client, OprfClientLen);
+impl_tagged!(
+    VoprfClient,
+    Mode::Voprf,
+    StateKind::Client,
+    VoprfClientLen,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+    VoprfClientLen<CS>: ArrayLength<u8>
+);
+impl_tagged!(
+    PoprfClient,
+    Mode::Poprf,
+    StateKind::Client,
+    PoprfClientLen,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+    PoprfClientLen<CS>: ArrayLength<u8>
+);
+impl_tagged!(OprfServer, Mode::Oprf, StateKind::Server, OprfServerLen);
+impl_tagged!(
+    VoprfServer,
+    Mode::Voprf,
+    StateKind::Server,
+    VoprfServerLen,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+    VoprfServerLen<CS>: ArrayLength<u8>
+);
+impl_tagged!(
+    PoprfServer,
+    Mode::Poprf,
+    StateKind::Server,
+    PoprfServerLen,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+    PoprfServerLen<CS>: ArrayLength<u8>
+);
+
+/// The tag of a state of `mode` and `kind`: the big-endian [`CipherSuite::ID`]
+/// followed by the mode byte and the kind byte, 0 for clients and 1 for
+/// servers.
+pub(crate) fn tag<CS: CipherSuite>(mode: Mode, kind: StateKind) -> GenericArray<u8, U4>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    GenericArray::from(CS::ID.to_be_bytes()).concat([mode.to_u8(), kind.to_u8()].into())
+}
+
+/// Strips the tag of a state of `mode` and `kind` from `input`.
+///
+/// Can only fail with [`ErrorKind::SuiteMismatch`] or
+/// [`ErrorKind::Deserialization`] if `input` is too short to hold a tag.
+fn check_tag<CS: CipherSuite>(mut input: &[u8], mode: Mode, kind: StateKind) -> Result<&[u8]>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let tag_bytes = input.take_ext(U4::USIZE).ok_or(Error::new(
+        ErrorKind::Deserialization,
+        Operation::DeserializeTagged,
+    ))?;
+
+    if tag_bytes != tag::<CS>(mode, kind).as_slice() {
+        return Err(Error::new(
+            ErrorKind::SuiteMismatch,
+            Operation::DeserializeTagged,
+        ));
+    }
+
+    Ok(input)
+}
+
+/// Checks at compile time that a serialized state of `LEN` bytes fits into a
+/// store of `CAPACITY` bytes, e.g. to provision the columns of a database or
+/// the slots of a fixed-size session store from the `LEN` constants, like
+/// [`VoprfClient::LEN`]. The check is only available as the constant
+/// [`AssertStateLen::LEN`], which is `LEN` if it fits:
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # type CipherSuite = voprf::Ristretto255;
+/// # #[cfg(not(feature = "ristretto255"))]
+/// # type CipherSuite = p256::NistP256;
+/// use voprf::{AssertStateLen, VoprfClient};
+///
+/// const SESSION_LEN: usize = AssertStateLen::<{ VoprfClient::<CipherSuite>::LEN }, 65>::LEN;
+/// let session = [0; SESSION_LEN];
+/// ```
+///
+/// A state that doesn't fit fails to compile:
+///
+/// ```compile_fail
+/// use p256::NistP256;
+/// use voprf::{AssertStateLen, VoprfClient};
+///
+/// const SESSION_LEN: usize = AssertStateLen::<{ VoprfClient::<NistP256>::LEN }, 64>::LEN;
+/// ```
+///
+/// The serialized sizes in bytes of the built-in ciphersuites are:
+///
+/// | State                                                  | [`Ristretto255`](crate::Ristretto255) | P-256 |
+/// | ------------------------------------------------------ | ------------------------------------- | ----- |
+/// | [`OprfClient`], [`OprfServer`]                         | 32                                    | 32    |
+/// | [`VoprfClient`], [`PoprfClient`]                       | 64                                    | 65    |
+/// | [`VoprfServer`], [`PoprfServer`]                       | 64                                    | 65    |
+/// | [`BlindedElement`], [`EvaluationElement`]              | 32                                    | 33    |
+/// | [`Proof`]                                              | 64                                    | 64    |
+#[derive(Debug)]
+pub enum AssertStateLen<const LEN: usize, const CAPACITY: usize> {}
+
+impl<const LEN: usize, const CAPACITY: usize> AssertStateLen<LEN, CAPACITY> {
+    /// `LEN`, failing to compile if it is larger than `CAPACITY`.
+    pub const LEN: usize = {
+        assert!(
+            LEN <= CAPACITY,
+            "serialized state is larger than its capacity"
+        );
+        LEN
+    };
+}
+
+/// Concatenates `parts` into bytes that are zeroized when dropped. They are
+/// written in place, so unlike with `Concat` no unzeroized copy of a secret
+/// part is left behind. `L` has to be the sum of the lengths of the `parts`.
+fn concat_zeroizing<L: ArrayLength<u8>>(parts: &[&[u8]]) -> Zeroizing<GenericArray<u8, L>> {
+    let mut output = Zeroizing::new(GenericArray::default());
+    output
+        .iter_mut()
+        .zip(parts.iter().flat_map(|part| part.iter()))
+        .for_each(|(output, byte)| *output = *byte);
+
+    output
+}
+
+/// Checks that all of `input` was deserialized, so trailing bytes are rejected
+/// instead of silently dropped.
+fn check_end(input: &[u8], operation: Operation) -> Result<()> {
+    if input.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::Deserialization, operation))
+    }
+}
+
+/// Runs `deserialize` on a message, counting its failures in
+/// `voprf_deserialization_errors_total` if the `metrics` feature is enabled.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn count_errors<T>(message: &'static str, deserialize: impl FnOnce() -> Result<T>) -> Result<T> {
+    let result = deserialize();
+
+    if result.is_err() {
+        count!("voprf_deserialization_errors_total", 1, "message" => message);
+    }
+
+    result
+}
+
+/// Splits a list of `count` serialized elements, checking its length once.
+fn split_batch<G: Group>(input: &[u8], count: usize) -> Result<ChunksExact<'_, u8>> {
+    let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeElem);
+
+    if count.checked_mul(G::ElemLen::USIZE) != Some(input.len()) {
+        return Err(error);
+    }
+
+    Ok(input.chunks_exact(G::ElemLen::USIZE))
+}
+
+fn deserialize_elem<G: Group>(input: &mut &[u8]) -> Result<G::Elem> {
+    let input = input.take_ext(G::ElemLen::USIZE).ok_or(Error::new(
+        ErrorKind::Deserialization,
+        Operation::DeserializeElem,
+    ))?;
+    G::deserialize_elem(input)
+}
+
+#[cfg(feature = "elligator")]
+fn decode_uniform<G: UniformGroup>(input: &[u8]) -> Result<G::Elem> {
+    let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeElem);
+    let input = GenericArray::from_exact_iter(input.iter().copied()).ok_or(error)?;
+    let elem = G::decode_uniform(&input);
+
+    if ALLOW_IDENTITY || !bool::from(elem.ct_eq(&G::identity_elem())) {
+        Ok(elem)
+    } else {
+        Err(error)
+    }
+}
+
+fn deserialize_scalar<G: Group>(input: &mut &[u8]) -> Result<G::Scalar> {
+    let input = input.take_ext(G::ScalarLen::USIZE).ok_or(Error::new(
+        ErrorKind::Deserialization,
+        Operation::DeserializeScalar,
+    ))?;
+    G::deserialize_scalar(input)
+}
+
+/// Decodes a G2 element and checks that it is in the prime-order subgroup and
+/// not the identity, unless the **dangerous** `danger-allow-identity` feature
+/// is enabled.
+#[cfg(feature = "pythia")]
+fn deserialize_g2(input: &mut &[u8]) -> Result<G2Projective> {
+    input
+        .take_ext(96)
+        .and_then(|bytes| bytes.try_into().ok())
+        .and_then(|bytes| Option::<G2Affine>::from(G2Affine::from_compressed(bytes)))
+        .filter(|point| ALLOW_IDENTITY || !bool::from(point.is_identity()))
+        .map(G2Projective::from)
+        .ok_or(Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeElem,
+        ))
+}
+
+/// Decodes an element of the target group of the pairing.
+#[cfg(feature = "pythia")]
+fn deserialize_gt(input: &mut &[u8]) -> Result<GtElem> {
+    input
+        .take_ext(576)
+        .and_then(GtElem::from_bytes)
+        .ok_or(Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeElem,
+        ))
+}
+
+/// Formats `bytes` as hex. Runs in constant time with respect to the bytes, so
+/// it can be used on secrets.
+fn fmt_hex(bytes: &[u8], f: &mut Formatter<'_>, upper: bool) -> fmt::Result {
+    for byte in bytes {
+        f.write_char(encode_hex_digit(byte >> 4, upper))?;
+        f.write_char(encode_hex_digit(byte & 0xF, upper))?;
+    }
+
+    Ok(())
+}
+
+/// Encodes `bytes` as lowercase hex into `output`, which has to be twice as
+/// long. Runs in constant time with respect to the bytes, like [`fmt_hex`].
+#[cfg(feature = "serde")]
+fn encode_hex(bytes: &[u8], output: &mut [u8]) {
+    for (digits, byte) in output.chunks_exact_mut(2).zip(bytes) {
+        for (digit, nibble) in digits.iter_mut().zip([byte >> 4, byte & 0xF]) {
+            // Hex digits are ASCII
+            *digit = encode_hex_digit(nibble, false) as u8;
+        }
+    }
+}
+
+fn parse_hex<L: ArrayLength<u8>>(input: &str) -> Option<GenericArray<u8, L>> {
+    let input = input.as_bytes();
+
+    if input.len() != L::USIZE * 2 {
+        return None;
+    }
+
+    let mut bytes = GenericArray::<u8, L>::default();
+
+    if decode_hex(input, &mut bytes) {
+        Some(bytes)
+    } else {
+        bytes.zeroize();
+        None
+    }
+}
+
+/// Decodes the lowercase or uppercase hex `input` into `output`, which has to
+/// be half as long, and returns if `input` is valid. Runs in constant time with
+/// respect to the characters of `input`, only whether it is valid is leaked.
+pub(crate) fn decode_hex(input: &[u8], output: &mut [u8]) -> bool {
+    let mut invalid = 0;
+
+    for (byte, chunk) in output.iter_mut().zip(input.chunks_exact(2)) {
+        let mut value = 0;
+
+        for &digit in chunk {
+            let nibble = decode_hex_digit(digit);
+            invalid |= nibble >> 4;
+            value = value << 4 | (nibble & 0xF);
+        }
+
+        *byte = value.to_le_bytes()[0];
+    }
+
+    invalid == 0 && input.len() == output.len() * 2
+}
+
+/// Maps a nibble to its hex digit without a table lookup or a branch on the
+/// nibble: digits from `a` on are offset from `0` by the gap between them.
+fn encode_hex_digit(nibble: u8, upper: bool) -> char {
+    let nibble = u32::from(nibble);
+    // Is all ones if `nibble >= 10`, zero otherwise
+    let letter = 0u32.wrapping_sub((9u32.wrapping_sub(nibble) >> 8) & 1);
+    let gap = if upper {
+        b'A' - b'0' - 10
+    } else {
+        b'a' - b'0' - 10
+    };
+
+    let digit = nibble + u32::from(b'0') + (letter & u32::from(gap));
+
+    char::from(digit.to_le_bytes()[0])
+}
+
+/// Maps a hex digit to its nibble without a table lookup or a branch, or to a
+/// value with bits above the nibble set if it isn't a hex digit. Each range of
+/// digits contributes the nibble plus one if the digit lies in it.
+fn decode_hex_digit(digit: u8) -> u32 {
+    let digit = u32::from(digit);
+    // Is all ones if `low <= digit <= high`, zero otherwise
+    let within = |low: u32, high: u32| {
+        0u32.wrapping_sub(
+            ((low.wrapping_sub(1).wrapping_sub(digit) & digit.wrapping_sub(high + 1)) >> 31) & 1,
+        )
+    };
+
+    let mut nibble = 0u32.wrapping_sub(1);
+    nibble = nibble.wrapping_add(within(0x30, 0x39) & digit.wrapping_sub(0x30 - 1));
+    nibble = nibble.wrapping_add(within(0x61, 0x66) & digit.wrapping_sub(0x61 - 11));
+    nibble = nibble.wrapping_add(within(0x41, 0x46) & digit.wrapping_sub(0x41 - 11));
+
+    nibble
+}
+
+fn deserialize_u16(input: &mut &[u8]) -> Option<u16> {
+    let mut bytes = [0; 2];
+    bytes.copy_from_slice(input.take_ext(2)?);
+    Some(u16::from_be_bytes(bytes))
+}
+
+fn deserialize_u64(input: &mut &[u8]) -> Option<u64> {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(input.take_ext(8)?);
+    Some(u64::from_be_bytes(bytes))
+}
+
+trait SliceExt {
+    fn take_ext<'a>(self: &mut &'a Self, take: usize) -> Option<&'a Self>;
+}
+
+impl<T> SliceExt for [T] {
+    fn take_ext<'a>(self: &mut &'a Self, take: usize) -> Option<&'a Self> {
+        if take > self.len() {
+            return None;
+        }
+
+        let (front, back) = self.split_at(take);
+        *self = back;
+        Some(front)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) mod serde {
+    use core::fmt::{self, Formatter};
+    use core::marker::PhantomData;
+    use core::str;
+
+    use generic_array::{ArrayLength, GenericArray};
+    use serde::de::{Deserializer, Error, IgnoredAny, SeqAccess, Visitor};
+    use serde::ser::{Error as _, Serializer};
+    use serde::{Deserialize, Serialize};
+    use zeroize::{Zeroize, Zeroizing};
+
+    use super::{encode_hex, parse_hex};
+    use crate::Group;
+
+    pub(crate) struct Element<G: Group>(PhantomData<G>);
+
+    impl<'de, G: Group> Element<G> {
+        pub(crate) fn deserialize<D>(deserializer: D) -> Result<G::Elem, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            GenericArray::<_, G::ElemLen>::deserialize(deserializer)
+                .and_then(|bytes| G::deserialize_elem(&bytes).map_err(D::Error::custom))
+        }
+
+        pub(crate) fn serialize<S>(self_: &G::Elem, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            G::serialize_elem(*self_).serialize(serializer)
+        }
+    }
+
+    /// Scalars are the secrets of the states, e.g. keys and blinds. Text
+    /// formats, which tell apart with [`Serializer::is_human_readable`],
+    /// store them as lowercase hex encoded and decoded in constant time,
+    /// instead of the numbers of a sequence, whose formatting isn't. The
+    /// sequences written by earlier versions are still accepted.
+    pub(crate) struct Scalar<G: Group>(PhantomData<G>);
+
+    /// The longest scalar, in bytes, that can be serialized as hex, which is
+    /// written to a buffer on the stack.
+    const MAX_HEX_SCALAR_LEN: usize = 128;
+
+    impl<'de, G: Group> Scalar<G> {
+        pub(crate) fn deserialize<D>(deserializer: D) -> Result<G::Scalar, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            if deserializer.is_human_readable() {
+                deserializer.deserialize_any(HexVisitor::<G::ScalarLen>(PhantomData))
+            } else {
+                GenericArray::<_, G::ScalarLen>::deserialize(deserializer)
+            }
+            .and_then(|bytes| {
+                let bytes = Zeroizing::new(bytes);
+                G::deserialize_scalar(&bytes).map_err(D::Error::custom)
+            })
+        }
+
+        pub(crate) fn serialize<S>(self_: &G::Scalar, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let bytes = Zeroizing::new(G::serialize_scalar(*self_));
+
+            if serializer.is_human_readable() {
+                // Written to a buffer that is zeroized, instead of the `String`
+                // that `Serializer::collect_str` might allocate.
+                let mut buffer = Zeroizing::new([0; 2 * MAX_HEX_SCALAR_LEN]);
+                let hex = buffer
+                    .get_mut(..bytes.len() * 2)
+                    .ok_or_else(|| S::Error::custom("scalar is too long to serialize as hex"))?;
+                encode_hex(&bytes, hex);
+
+                serializer.serialize_str(str::from_utf8(hex).map_err(S::Error::custom)?)
+            } else {
+                bytes.serialize(serializer)
+            }
+        }
+    }
+
+    struct HexVisitor<L>(PhantomData<L>);
+
+    impl<L: ArrayLength<u8>> HexVisitor<L> {
+        fn fill_from_seq<'de, A: SeqAccess<'de>>(
+            &self,
+            bytes: &mut GenericArray<u8, L>,
+            mut seq: A,
+        ) -> Result<(), A::Error> {
+            for (index, byte) in bytes.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(index, self))?;
+            }
+
+            if seq.next_element::<IgnoredAny>()?.is_some() {
+                return Err(A::Error::invalid_length(L::USIZE + 1, self));
+            }
+
+            Ok(())
+        }
+    }
+
+    impl<'de, L: ArrayLength<u8>> Visitor<'de> for HexVisitor<L> {
+        type Value = GenericArray<u8, L>;
+
+        fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{} bytes as hex or as a sequence", L::USIZE)
+        }
+
+        // The error doesn't include `value`, which is secret.
+        fn visit_str<E: Error>(self, value: &str) -> Result<Self::Value, E> {
+            parse_hex(value).ok_or_else(|| E::custom("invalid hex"))
+        }
+
+        // The format of earlier versions
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = GenericArray::default();
+            let result = self.fill_from_seq(&mut bytes, seq);
+
+            if result.is_err() {
+                bytes.zeroize();
+            }
+
+            result.map(|()| bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+    use core::fmt::{self, Display, Formatter};
+    use core::ops::Add;
+
+    use digest::core_api::BlockSizeUser;
+    use digest::OutputSizeUser;
+    use generic_array::typenum::{IsLess, IsLessOrEqual, Sum, U16, U2, U256, U4};
+    use generic_array::ArrayLength;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use rand::rngs::OsRng;
+
+    use crate::{
+        AssertStateLen, BlindedElement, CipherSuite, Error, ErrorKind, EvaluationElement, Group,
+        KeyCommitment, KeyCommitmentLen, KeyPossessionProof, KeyPossessionProofLen, KeyShareLen,
+        Operation, OprfClient, OprfServer, PoprfClient, PoprfServer, Proof, ProofLen, TaggedLen,
+        VoprfClient, VoprfServer,
+    };
+
+    fn proof_encodings<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+        ProofLen<CS>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let proof = server
+            .blind_evaluate(&mut rng, &client_blind_result.message)
+            .proof;
+        let bytes = proof.serialize();
+
+        let decoded = Proof::<CS>::try_from(&bytes[..]).unwrap();
+        assert_eq!(decoded.serialize(), bytes);
+
+        let hex = proof.to_string();
+        assert_eq!(hex, hex::encode(&bytes));
+        assert_eq!(hex.parse::<Proof<CS>>().unwrap().serialize(), bytes);
+        assert_eq!(
+            hex.to_uppercase().parse::<Proof<CS>>().unwrap().serialize(),
+            bytes
+        );
+        assert_eq!(format!("{:x}", proof), hex);
+        assert_eq!(Proof::<CS>::LEN, bytes.len());
+        assert_eq!(format!("{:X}", proof), hex.to_uppercase());
+
+        let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeProof);
+        let mut long = bytes.to_vec();
+        long.push(0);
+        assert!(matches!(Proof::<CS>::try_from(&long[..]), Err(e) if e == error));
+        assert!(matches!(Proof::<CS>::try_from(&bytes[1..]), Err(e) if e == error));
+        assert!(matches!(hex[1..].parse::<Proof<CS>>(), Err(e) if e == error));
+        assert!(matches!(format!("x{}", &hex[1..]).parse::<Proof<CS>>(), Err(e) if e == error));
+    }
+
+    #[test]
+    fn test_proof_encodings() {
+        #[cfg(feature = "ristretto255")]
+        proof_encodings::<crate::Ristretto255>();
+
+        proof_encodings::<p256::NistP256>();
+    }
+
+    fn public_value_encodings<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        U2: Add<<CS::Group as Group>::ElemLen>,
+        Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+        Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+            ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+        KeyCommitmentLen<CS>: ArrayLength<u8>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+        Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ElemLen>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let message = client_blind_result.message;
+        let evaluation = server.blind_evaluate(&mut rng, &message).message;
+
+        assert_eq!(BlindedElement::<CS>::LEN, message.serialize().len());
+        assert_eq!(EvaluationElement::<CS>::LEN, evaluation.serialize().len());
+        assert_eq!(VoprfServer::<CS>::LEN, server.serialize().len());
+        assert_eq!(
+            VoprfClient::<CS>::LEN,
+            client_blind_result.state.serialize().len()
+        );
+
+        let hex = message.to_string();
+        assert_eq!(hex, hex::encode(message.serialize()));
+        assert_eq!(format!("{:X}", message), hex.to_uppercase());
+        assert!(hex.parse::<BlindedElement<CS>>().unwrap() == message);
+
+        let hex = format!("{:x}", evaluation);
+        assert_eq!(hex, hex::encode(evaluation.serialize()));
+        assert!(hex.to_uppercase().parse::<EvaluationElement<CS>>().unwrap() == evaluation);
+
+        // Public keys are written through their commitment
+        let commitment = server.key_commitment(0, 10);
+        assert_eq!(KeyCommitment::<CS>::LEN, commitment.serialize().len());
+        let hex = commitment.to_string();
+        assert_eq!(hex, hex::encode(commitment.serialize()));
+        assert!(hex.parse::<KeyCommitment<CS>>().unwrap() == commitment);
+
+        let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeElem);
+        assert!(matches!(hex[1..].parse::<BlindedElement<CS>>(), Err(e) if e == error));
+        assert!(matches!(
+            hex[2..].parse::<KeyCommitment<CS>>(),
+            Err(e) if e == Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeKeyCommitment
+            )
+        ));
+    }
+
+    #[test]
+    fn test_public_value_encodings() {
+        #[cfg(feature = "ristretto255")]
+        public_value_encodings::<crate::Ristretto255>();
+
+        public_value_encodings::<p256::NistP256>();
+
+        // Outputs already format as hex
+        let server = VoprfServer::<p256::NistP256>::new(&mut OsRng).unwrap();
+        let output = server.evaluate(b"input").unwrap();
+        assert_eq!(format!("{:x}", output), hex::encode(output));
+    }
+
+    #[test]
+    fn test_state_lens() {
+        use p256::NistP256;
+
+        const P256_CLIENT_LEN: usize = AssertStateLen::<{ VoprfClient::<NistP256>::LEN }, 65>::LEN;
+        assert_eq!(P256_CLIENT_LEN, 65);
+        assert_eq!(OprfClient::<NistP256>::LEN, 32);
+        assert_eq!(PoprfClient::<NistP256>::LEN, 65);
+        assert_eq!(OprfServer::<NistP256>::LEN, 32);
+        assert_eq!(VoprfServer::<NistP256>::LEN, 65);
+        assert_eq!(PoprfServer::<NistP256>::LEN, 65);
+        assert_eq!(BlindedElement::<NistP256>::LEN, 33);
+        assert_eq!(EvaluationElement::<NistP256>::LEN, 33);
+        assert_eq!(Proof::<NistP256>::LEN, 64);
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            const RISTRETTO_CLIENT_LEN: usize =
+                AssertStateLen::<{ VoprfClient::<Ristretto255>::LEN }, 64>::LEN;
+            assert_eq!(RISTRETTO_CLIENT_LEN, 64);
+            assert_eq!(OprfClient::<Ristretto255>::LEN, 32);
+            assert_eq!(PoprfClient::<Ristretto255>::LEN, 64);
+            assert_eq!(OprfServer::<Ristretto255>::LEN, 32);
+            assert_eq!(VoprfServer::<Ristretto255>::LEN, 64);
+            assert_eq!(PoprfServer::<Ristretto255>::LEN, 64);
+            assert_eq!(BlindedElement::<Ristretto255>::LEN, 32);
+            assert_eq!(EvaluationElement::<Ristretto255>::LEN, 32);
+            assert_eq!(Proof::<Ristretto255>::LEN, 64);
+        }
+    }
+
+    fn tagged_states<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ElemLen>,
+        Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ElemLen>: ArrayLength<u8>,
+        U4: Add<<CS::Group as Group>::ScalarLen>
+            + Add<Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ElemLen>>,
+        TaggedLen<<CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+        TaggedLen<Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ElemLen>>:
+            ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let mismatch = Error::new(ErrorKind::SuiteMismatch, Operation::DeserializeTagged);
+
+        let client = OprfClient::<CS>::blind(b"input", &mut rng).unwrap().state;
+        let bytes = client.serialize_tagged();
+        assert_eq!(&bytes[..2], CS::ID.to_be_bytes());
+        assert_eq!(bytes[2], 0);
+        assert_eq!(bytes[3], 0);
+        assert_eq!(&bytes[4..], client.serialize().as_slice());
+        assert_eq!(
+            OprfClient::<CS>::deserialize_tagged(&bytes)
+                .unwrap()
+                .serialize(),
+            client.serialize()
+        );
+
+        // A client state is rejected as a server state, even though both are a
+        // single scalar
+        assert_eq!(OprfClient::<CS>::LEN, OprfServer::<CS>::LEN);
+        assert!(matches!(
+            OprfServer::<CS>::deserialize_tagged(&bytes),
+            Err(e) if e == mismatch
+        ));
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        assert!(matches!(
+            OprfClient::<CS>::deserialize_tagged(&server.serialize_tagged()),
+            Err(e) if e == mismatch
+        ));
+
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let bytes = server.serialize_tagged();
+        assert_eq!(bytes[2], 1);
+        assert_eq!(bytes[3], 1);
+        assert!(matches!(
+            VoprfClient::<CS>::deserialize_tagged(&bytes),
+            Err(e) if e == mismatch
+        ));
+        assert_eq!(
+            VoprfServer::<CS>::deserialize_tagged(&bytes)
+                .unwrap()
+                .serialize(),
+            server.serialize()
+        );
+
+        // A state of another mode is rejected, even with the same layout
+        assert!(matches!(
+            PoprfServer::<CS>::deserialize_tagged(&bytes),
+            Err(e) if e == mismatch
+        ));
+        let client = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap().state;
+        assert!(matches!(
+            PoprfClient::<CS>::deserialize_tagged(&client.serialize_tagged()),
+            Err(e) if e == mismatch
+        ));
+
+        // Untagged bytes are rejected
+        assert!(matches!(
+            OprfServer::<CS>::deserialize_tagged(&[0; 2]),
+            Err(e) if e == Error::new(ErrorKind::Deserialization, Operation::DeserializeTagged)
+        ));
+    }
+
+    #[test]
+    fn test_tagged_states() {
+        #[cfg(feature = "ristretto255")]
+        {
+            tagged_states::<crate::Ristretto255>();
+
+            // States of the same length but another suite are rejected
+            let server = OprfServer::<p256::NistP256>::new(&mut OsRng).unwrap();
+            assert_eq!(
+                OprfServer::<p256::NistP256>::LEN,
+                OprfServer::<crate::Ristretto255>::LEN
+            );
+            assert!(matches!(
+                OprfServer::<crate::Ristretto255>::deserialize_tagged(&server.serialize_tagged()),
+                Err(e) if e == Error::new(ErrorKind::SuiteMismatch, Operation::DeserializeTagged)
+            ));
+        }
+
+        tagged_states::<p256::NistP256>();
+    }
+
+    /// Checks that `bytes` deserialize with `deserialize` into an item that
+    /// serializes with `serialize` to the same bytes, and that any trailing
+    /// byte or missing byte is rejected.
+    fn assert_canonical<T>(
+        bytes: &[u8],
+        deserialize: impl Fn(&[u8]) -> Result<T, Error>,
+        serialize: impl Fn(&T) -> Vec<u8>,
+    ) {
+        assert_eq!(serialize(&deserialize(bytes).unwrap()), bytes);
+
+        let mut long = bytes.to_vec();
+        long.push(0);
+        assert_eq!(
+            deserialize(&long).err().unwrap().kind(),
+            ErrorKind::Deserialization
+        );
+        assert_eq!(
+            deserialize(&bytes[..bytes.len() - 1]).err().unwrap().kind(),
+            ErrorKind::Deserialization
+        );
+    }
+
+    fn canonical_encodings<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+        <CS::Group as Group>::ScalarLen:
+            Add<<CS::Group as Group>::ElemLen> + Add<<CS::Group as Group>::ScalarLen>,
+        Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ElemLen>: ArrayLength<u8>,
+        ProofLen<CS>: ArrayLength<u8>,
+        U2: Add<<CS::Group as Group>::ElemLen> + Add<<CS::Group as Group>::ScalarLen>,
+        Sum<U2, <CS::Group as Group>::ElemLen>: ArrayLength<u8> + Add<U16>,
+        Sum<Sum<U2, <CS::Group as Group>::ElemLen>, U16>:
+            ArrayLength<u8> + Add<<CS::Hash as OutputSizeUser>::OutputSize>,
+        KeyCommitmentLen<CS>: ArrayLength<u8>,
+        KeyShareLen<CS>: ArrayLength<u8>,
+        KeyPossessionProofLen<CS>: ArrayLength<u8>,
+    {
+        let mut rng = OsRng;
+        let oprf_server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let voprf_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let poprf_server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let oprf_client = OprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let voprf_client = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let poprf_client = PoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let result = voprf_server.blind_evaluate(&mut rng, &voprf_client.message);
+
+        assert_canonical(
+            &oprf_client.state.serialize(),
+            OprfClient::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &voprf_client.state.serialize(),
+            VoprfClient::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &poprf_client.state.serialize(),
+            PoprfClient::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &oprf_server.serialize(),
+            OprfServer::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &voprf_server.serialize(),
+            VoprfServer::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &poprf_server.serialize(),
+            PoprfServer::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &voprf_client.message.serialize(),
+            BlindedElement::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &result.message.serialize(),
+            EvaluationElement::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &result.proof.serialize(),
+            Proof::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &voprf_server.key_commitment(0, 10).serialize(),
+            KeyCommitment::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        assert_canonical(
+            &voprf_server
+                .prove_key_possession(&mut rng, b"context")
+                .unwrap()
+                .serialize(),
+            KeyPossessionProof::<CS>::deserialize,
+            |item| item.serialize().to_vec(),
+        );
+        #[cfg(feature = "alloc")]
+        {
+            let shares = oprf_server.split_key(&mut rng, 2, 3).unwrap();
+            assert_canonical(
+                &shares[0].serialize(),
+                crate::KeyShare::<CS>::deserialize,
+                |item| item.serialize().to_vec(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_canonical_encodings() {
+        #[cfg(feature = "ristretto255")]
+        canonical_encodings::<crate::Ristretto255>();
+
+        canonical_encodings::<p256::NistP256>();
+    }
+
+    fn batch_deserialization<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let len = BlindedElement::<CS>::LEN;
+        let messages: Vec<_> = (0..3)
+            .map(|_| OprfClient::<CS>::blind(b"input", &mut rng).unwrap().message)
+            .collect();
+        let mut bytes: Vec<u8> = messages
+            .iter()
+            .flat_map(|message| message.serialize())
+            .collect();
+
+        let batch: Vec<_> = BlindedElement::<CS>::deserialize_batch(&bytes, 3)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(batch == messages);
+
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let evaluations: Vec<_> = messages
+            .iter()
+            .map(|message| server.blind_evaluate(message))
+            .collect();
+        let evaluation_bytes: Vec<u8> = evaluations
+            .iter()
+            .flat_map(|evaluation| evaluation.serialize())
+            .collect();
+        let batch: Vec<_> = EvaluationElement::<CS>::deserialize_batch(&evaluation_bytes, 3)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(batch == evaluations);
+
+        // The length is checked up front
+        let error = Error::new(ErrorKind::Deserialization, Operation::DeserializeElem);
+        for (input, count) in [(&bytes[..], 2), (&bytes[..], 4), (&bytes[1..], 3)] {
+            assert!(matches!(
+                BlindedElement::<CS>::deserialize_batch(input, count),
+                Err(e) if e == error
+            ));
+        }
+        assert!(matches!(
+            BlindedElement::<CS>::deserialize_batch(&bytes, usize::MAX),
+            Err(e) if e == error
+        ));
+        assert_eq!(
+            BlindedElement::<CS>::deserialize_batch(&[], 0)
+                .unwrap()
+                .count(),
+            0
+        );
+
+        // Invalid elements carry their index
+        for byte in &mut bytes[len..2 * len] {
+            *byte = 0xFF;
+        }
+        let mut batch = BlindedElement::<CS>::deserialize_batch(&bytes, 3).unwrap();
+        assert!(batch.next().unwrap().is_ok());
+        assert_eq!(batch.next().unwrap().err().unwrap().index(), Some(1));
+        assert!(batch.next().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_batch_deserialization() {
+        #[cfg(feature = "ristretto255")]
+        batch_deserialization::<crate::Ristretto255>();
+
+        batch_deserialization::<p256::NistP256>();
+    }
+
+    #[test]
+    fn test_hex_codec() {
+        struct Hex<'a>(&'a [u8], bool);
+
+        impl Display for Hex<'_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                super::fmt_hex(self.0, f, self.1)
+            }
+        }
+
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(Hex(&bytes, false).to_string(), hex::encode(&bytes));
+        assert_eq!(Hex(&bytes, true).to_string(), hex::encode_upper(&bytes));
+
+        for encoded in [hex::encode(&bytes), hex::encode_upper(&bytes)] {
+            let decoded = super::parse_hex::<U256>(&encoded).unwrap();
+            assert_eq!(decoded[..], bytes[..]);
+        }
+
+        // Every character is classified like the `hex` crate does
+        for digit in 0..=255 {
+            let mut byte = [0];
+            let valid = super::decode_hex(&[b'0', digit], &mut byte);
+            assert_eq!(valid, digit.is_ascii_hexdigit());
+
+            if valid {
+                assert_eq!(hex::decode([b'0', digit]).unwrap(), byte);
+            }
+        }
+
+        assert!(!super::decode_hex(b"0", &mut []));
+        assert!(!super::decode_hex(b"000", &mut [0]));
+        assert!(super::parse_hex::<U2>("0g00").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_text() {
+        use p256::NistP256;
+
+        let mut rng = OsRng;
+
+        // Text formats store secrets as hex, encoded in constant time
+        let server = VoprfServer::<NistP256>::new(&mut rng).unwrap();
+        let json = serde_json::to_value(&server).unwrap();
+        assert_eq!(
+            json["sk"],
+            hex::encode(NistP256::serialize_scalar(server.sk))
+        );
+        assert!(serde_json::from_value::<VoprfServer<NistP256>>(json).unwrap() == server);
+
+        let client = OprfClient::<NistP256>::blind(b"input", &mut rng)
+            .unwrap()
+            .state;
+        let blind = NistP256::serialize_scalar(client.blind);
+        let json = serde_json::to_value(&client).unwrap();
+        assert_eq!(json, serde_json::json!({ "blind": hex::encode(blind) }));
+        assert!(serde_json::from_value::<OprfClient<NistP256>>(json).unwrap() == client);
+
+        let json = serde_json::json!({ "blind": hex::encode_upper(blind) });
+        assert!(serde_json::from_value::<OprfClient<NistP256>>(json).unwrap() == client);
+
+        // Errors don't include the secret
+        let invalid = "0g".repeat(32);
+        let error =
+            serde_json::from_value::<OprfClient<NistP256>>(serde_json::json!({ "blind": invalid }))
+                .unwrap_err();
+        assert!(!error.to_string().contains(&invalid));
+
+        // States written by earlier versions, with scalars as sequences of
+        // numbers, are still accepted
+        let json = r#"{"blind":[1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1]}"#;
+        let client = serde_json::from_str::<OprfClient<NistP256>>(json).unwrap();
+        assert_eq!(NistP256::serialize_scalar(client.blind).as_slice(), [1; 32]);
+        assert_eq!(
+            serde_json::to_value(&client).unwrap(),
+            serde_json::json!({ "blind": "01".repeat(32) })
+        );
+
+        let json = serde_json::json!({
+            "sk": NistP256::serialize_scalar(server.sk).to_vec(),
+            "pk": NistP256::serialize_elem(server.pk).to_vec(),
+        });
+        assert!(serde_json::from_value::<VoprfServer<NistP256>>(json).unwrap() == server);
+
+        for blind in [&[1; 31][..], &[1; 33]] {
+            assert!(serde_json::from_value::<OprfClient<NistP256>>(
+                serde_json::json!({ "blind": blind })
+            )
+            .is_err());
+        }
+    }
+
+    /// Checks that deserializing arbitrary bytes doesn't crash and that all
+    /// accepted bytes are canonical.
+    macro_rules! test_deserialize {
+        ($item:ident, $bytes:ident) => {
+            #[cfg(feature = "ristretto255")]
+            {
+                if let Ok(item) = $item::<crate::Ristretto255>::deserialize(&$bytes[..]) {
+                    assert_eq!(&item.serialize()[..], &$bytes[..]);
+                }
+            }
+
+            if let Ok(item) = $item::<p256::NistP256>::deserialize(&$bytes[..]) {
+                assert_eq!(&item.serialize()[..], &$bytes[..]);
+            }
+        };
+    }
+
+    proptest! {
+        #[test]
+        fn test_nocrash_oprf_client(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(OprfClient, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_voprf_client(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(VoprfClient, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_poprf_client(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(PoprfClient, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_oprf_server(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(OprfServer, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_voprf_server(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(VoprfServer, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_poprf_server(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(PoprfServer, bytes);
+        }
+
+
+        #[test]
+        fn test_nocrash_blinded_element(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(BlindedElement, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_evaluation_element(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(EvaluationElement, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_proof(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(Proof, bytes);
+        }
+
+        #[test]
+        fn test_nocrash_key_commitment(bytes in vec(any::<u8>(), 0..200)) {
+            test_deserialize!(KeyCommitment, bytes);
         }
     }
 }
@@ -0,0 +1,460 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the dispatch of requests to servers of multiple ciphersuites
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+
+use crate::dyn_suite::DynRng;
+use crate::{
+    BlindedElement, CipherSuite, DynCryptoRng, Error, ErrorKind, EvaluationElement, Group,
+    Operation, OprfServer, OprfServerOps, PoprfServer, Proof, Result, VoprfServer,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// An object-safe server of a single ciphersuite, that evaluates serialized
+/// requests. Implemented for [`OprfServer`], [`VoprfServer`] and
+/// [`PoprfServer`], to be registered in a [`ServerPool`].
+pub trait PoolServer {
+    /// The [`CipherSuite::ID`] of the server.
+    fn suite_id(&self) -> u16;
+
+    /// Evaluates a serialized [`BlindedElement`] and returns the serialized
+    /// response: the [`EvaluationElement`], followed
+    /// by the [`Proof`] in the verifiable modes.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `blinded_element` can't be
+    ///   deserialized.
+    /// - [`ErrorKind::Info`] if `info` is given to the base or verifiable mode,
+    ///   or is longer than [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    fn blind_evaluate(
+        &self,
+        rng: &mut dyn DynCryptoRng,
+        blinded_element: &[u8],
+        info: Option<&[u8]>,
+    ) -> Result<Vec<u8>>;
+}
+
+/// A registry of servers of multiple ciphersuites, that dispatches requests by
+/// their leading suite ID and tags the responses with it, so issuers that
+/// support several ciphersuites don't have to hand-roll the dispatch.
+///
+/// Requests and responses are prefixed with the two bytes of the
+/// [`CipherSuite::ID`], see [`tag_request`] and [`split_response`]:
+///
+/// ```text
+/// request = I2OSP(suiteID, 2) || blindedElement
+/// response = I2OSP(suiteID, 2) || evaluationElement || proof
+/// ```
+///
+/// where the `proof` is omitted in the base mode.
+///
+/// ```
+/// # #[cfg(feature = "ristretto255")]
+/// # {
+/// use p256::NistP256;
+/// use rand::rngs::OsRng;
+/// use voprf::{
+///     split_response, tag_request, EvaluationElement, Proof, Ristretto255, ServerPool,
+///     VoprfClient, VoprfServer,
+/// };
+///
+/// let mut rng = OsRng;
+/// let server = VoprfServer::<NistP256>::new(&mut rng).unwrap();
+/// let public_key = server.get_public_key();
+///
+/// let mut pool = ServerPool::new();
+/// pool.insert(VoprfServer::<Ristretto255>::new(&mut rng).unwrap())
+///     .unwrap();
+/// pool.insert(server).unwrap();
+///
+/// let client_blind_result = VoprfClient::<NistP256>::blind(b"input", &mut rng).unwrap();
+/// let request = tag_request(&client_blind_result.message);
+/// let response = pool.blind_evaluate(&mut rng, &request, None).unwrap();
+///
+/// let body = split_response::<NistP256>(&response).unwrap();
+/// let (message, proof) = body.split_at(EvaluationElement::<NistP256>::LEN);
+/// let output = client_blind_result.state.finalize(
+///     b"input",
+///     &EvaluationElement::deserialize(message).unwrap(),
+///     &Proof::deserialize(proof).unwrap(),
+///     public_key,
+/// );
+/// assert!(output.is_ok());
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ServerPool {
+    servers: BTreeMap<u16, Box<dyn PoolServer>>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl ServerPool {
+    /// Creates an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the `server` for its suite ID.
+    ///
+    /// # Errors
+    /// [`ErrorKind::SuiteId`] if a server of the same suite ID is already
+    /// registered.
+    pub fn insert<S: PoolServer + 'static>(&mut self, server: S) -> Result<()> {
+        let suite_id = server.suite_id();
+
+        if self.servers.contains_key(&suite_id) {
+            return Err(Error::new(ErrorKind::SuiteId, Operation::Evaluate));
+        }
+
+        self.servers.insert(suite_id, Box::new(server));
+
+        Ok(())
+    }
+
+    /// Removes and returns the server of the `suite_id`, e.g. when a
+    /// ciphersuite is retired.
+    pub fn remove(&mut self, suite_id: u16) -> Option<Box<dyn PoolServer>> {
+        self.servers.remove(&suite_id)
+    }
+
+    /// Returns the suite IDs of all registered servers in ascending order.
+    pub fn suite_ids(&self) -> impl Iterator<Item = u16> + '_ {
+        self.servers.keys().copied()
+    }
+
+    /// Dispatches a suite-tagged `request` to the server of its suite ID, and
+    /// returns the response tagged with the same suite ID.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `request` is shorter than a
+    ///   suite ID or its blinded element can't be deserialized.
+    /// - [`ErrorKind::SuiteId`] if no server of the suite ID is registered.
+    /// - The errors of [`PoolServer::blind_evaluate`].
+    pub fn blind_evaluate(
+        &self,
+        rng: &mut dyn DynCryptoRng,
+        request: &[u8],
+        info: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let (suite_id, blinded_element) = split_suite_id(request)?;
+        let server = self
+            .servers
+            .get(&suite_id)
+            .ok_or_else(|| Error::new(ErrorKind::SuiteId, Operation::Evaluate))?;
+
+        let response = server.blind_evaluate(rng, blinded_element, info)?;
+
+        let mut tagged_response = Vec::with_capacity(2 + response.len());
+        tagged_response.extend_from_slice(&suite_id.to_be_bytes());
+        tagged_response.extend_from_slice(&response);
+
+        Ok(tagged_response)
+    }
+}
+
+impl Debug for ServerPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerPool")
+            .field("suite_ids", &self.servers.keys())
+            .finish()
+    }
+}
+
+//////////////////////////
+// Trait Implementations //
+// ===================== //
+//////////////////////////
+
+impl<CS: CipherSuite> PoolServer for OprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn suite_id(&self) -> u16 {
+        CS::ID
+    }
+
+    fn blind_evaluate(
+        &self,
+        rng: &mut dyn DynCryptoRng,
+        blinded_element: &[u8],
+        info: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let blinded_element = BlindedElement::deserialize(blinded_element)?;
+        let message =
+            OprfServerOps::blind_evaluate(self, &mut DynRng(rng), &blinded_element, info)?;
+
+        Ok(message.serialize().to_vec())
+    }
+}
+
+impl<CS: CipherSuite> PoolServer for VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn suite_id(&self) -> u16 {
+        CS::ID
+    }
+
+    fn blind_evaluate(
+        &self,
+        rng: &mut dyn DynCryptoRng,
+        blinded_element: &[u8],
+        info: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let blinded_element = BlindedElement::deserialize(blinded_element)?;
+        let result = OprfServerOps::blind_evaluate(self, &mut DynRng(rng), &blinded_element, info)?;
+
+        Ok(serialize_verifiable_response(
+            &result.message,
+            &result.proof,
+        ))
+    }
+}
+
+impl<CS: CipherSuite> PoolServer for PoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn suite_id(&self) -> u16 {
+        CS::ID
+    }
+
+    fn blind_evaluate(
+        &self,
+        rng: &mut dyn DynCryptoRng,
+        blinded_element: &[u8],
+        info: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let blinded_element = BlindedElement::deserialize(blinded_element)?;
+        let result = OprfServerOps::blind_evaluate(self, &mut DynRng(rng), &blinded_element, info)?;
+
+        Ok(serialize_verifiable_response(
+            &result.message,
+            &result.proof,
+        ))
+    }
+}
+
+///////////////////////////
+// Convenience Functions //
+// ===================== //
+///////////////////////////
+
+/// Serializes a [`BlindedElement`] into a request for a [`ServerPool`],
+/// prefixed with the suite ID of `CS`.
+pub fn tag_request<CS: CipherSuite>(blinded_element: &BlindedElement<CS>) -> Vec<u8>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let blinded_element = blinded_element.serialize();
+
+    let mut request = Vec::with_capacity(2 + blinded_element.len());
+    request.extend_from_slice(&CS::ID.to_be_bytes());
+    request.extend_from_slice(&blinded_element);
+
+    request
+}
+
+/// Checks that a `response` of a [`ServerPool`] is tagged with the suite ID of
+/// `CS` and returns the response without the tag.
+///
+/// # Errors
+/// - [`ErrorKind::Deserialization`] if the `response` is shorter than a suite
+///   ID.
+/// - [`ErrorKind::SuiteId`] if the `response` is tagged with another suite ID.
+pub fn split_response<CS: CipherSuite>(response: &[u8]) -> Result<&[u8]>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let (suite_id, response) = split_suite_id(response)?;
+
+    if suite_id != CS::ID {
+        return Err(Error::new(ErrorKind::SuiteId, Operation::Finalize));
+    }
+
+    Ok(response)
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+fn serialize_verifiable_response<CS: CipherSuite>(
+    message: &EvaluationElement<CS>,
+    proof: &Proof<CS>,
+) -> Vec<u8>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut response = message.serialize().to_vec();
+    response.extend_from_slice(&CS::Group::serialize_scalar(proof.c_scalar));
+    response.extend_from_slice(&CS::Group::serialize_scalar(proof.s_scalar));
+
+    response
+}
+
+fn split_suite_id(message: &[u8]) -> Result<(u16, &[u8])> {
+    match message {
+        [first, second, rest @ ..] => Ok((u16::from_be_bytes([*first, *second]), rest)),
+        _ => Err(Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeElem,
+        )),
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{OprfClient, PoprfClient, VoprfClient};
+
+    fn pool_dispatch<CS1: CipherSuite + 'static, CS2: CipherSuite + 'static>()
+    where
+        <CS1::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS1::Hash as BlockSizeUser>::BlockSize>,
+        <CS2::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS2::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let oprf_server = OprfServer::<CS1>::new(&mut rng).unwrap();
+        let poprf_server = PoprfServer::<CS2>::new(&mut rng).unwrap();
+        let expected_oprf = oprf_server.evaluate(input).unwrap();
+        let expected_poprf = poprf_server.evaluate(input, Some(b"info")).unwrap();
+        let public_key = poprf_server.get_public_key();
+
+        let mut pool = ServerPool::new();
+        pool.insert(oprf_server).unwrap();
+        pool.insert(poprf_server).unwrap();
+        let error = Error::new(ErrorKind::SuiteId, Operation::Evaluate);
+        assert!(matches!(
+            pool.insert(VoprfServer::<CS1>::new(&mut rng).unwrap()),
+            Err(e) if e == error
+        ));
+        assert_eq!(pool.suite_ids().count(), 2);
+
+        let client_blind_result = OprfClient::<CS1>::blind(input, &mut rng).unwrap();
+        let response = pool
+            .blind_evaluate(&mut rng, &tag_request(&client_blind_result.message), None)
+            .unwrap();
+        let message =
+            EvaluationElement::deserialize(split_response::<CS1>(&response).unwrap()).unwrap();
+        assert_eq!(
+            client_blind_result.state.finalize(input, &message).unwrap(),
+            expected_oprf
+        );
+        assert_eq!(
+            split_response::<CS2>(&response).map_err(|error| error.kind()),
+            Err(ErrorKind::SuiteId)
+        );
+
+        let client_blind_result = PoprfClient::<CS2>::blind(input, &mut rng).unwrap();
+        let request = tag_request(&client_blind_result.message);
+        let response = pool
+            .blind_evaluate(&mut rng, &request, Some(b"info"))
+            .unwrap();
+        let (message, proof) = split_response::<CS2>(&response)
+            .unwrap()
+            .split_at(EvaluationElement::<CS2>::LEN);
+        let output = client_blind_result
+            .state
+            .finalize(
+                input,
+                &EvaluationElement::deserialize(message).unwrap(),
+                &Proof::deserialize(proof).unwrap(),
+                public_key,
+                Some(b"info"),
+            )
+            .unwrap();
+        assert_eq!(output, expected_poprf);
+
+        // Requests with an unknown suite ID or without one are rejected
+        let mut unknown = request.clone();
+        unknown[..2].copy_from_slice(&0xFFFFu16.to_be_bytes());
+        assert!(matches!(
+            pool.blind_evaluate(&mut rng, &unknown, Some(b"info")),
+            Err(e) if e == error
+        ));
+        assert!(matches!(
+            pool.blind_evaluate(&mut rng, &request[..1], Some(b"info")),
+            Err(e) if e.kind() == ErrorKind::Deserialization
+        ));
+
+        // Errors of the server are passed through
+        let client_blind_result = VoprfClient::<CS1>::blind(input, &mut rng).unwrap();
+        assert!(matches!(
+            pool.blind_evaluate(&mut rng, &tag_request(&client_blind_result.message), Some(b"info")),
+            Err(e) if e.kind() == ErrorKind::Info
+        ));
+
+        assert!(pool.remove(CS1::ID).is_some());
+        assert!(pool.remove(CS1::ID).is_none());
+        assert!(pool.suite_ids().eq([CS2::ID]));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            pool_dispatch::<Ristretto255, NistP256>();
+            pool_dispatch::<NistP256, Ristretto255>();
+        }
+
+        #[cfg(feature = "toy-group")]
+        pool_dispatch::<NistP256, crate::ToyGroup>();
+
+        #[cfg(feature = "jubjub")]
+        pool_dispatch::<crate::Jubjub, NistP256>();
+
+        #[cfg(feature = "pasta")]
+        pool_dispatch::<crate::Pallas, crate::Vesta>();
+
+        #[cfg(feature = "sm2")]
+        pool_dispatch::<crate::Sm2, NistP256>();
+
+        #[cfg(feature = "secp256k1")]
+        pool_dispatch::<crate::Secp256k1, NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,526 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A simplified interface to all modes, taking and returning bytes
+//!
+//! Every mode is a struct with three steps: `client_blind` on the client,
+//! `server_evaluate` on the server and `client_finalize` on the client again.
+//! Messages between them are plain bytes, ready to be sent, and the client
+//! keeps its state in between in the returned handle, which can be
+//! serialized if the steps run in different processes. Server keys are
+//! generated with `server_keygen` and passed as bytes as well.
+//!
+//! ```
+//! # #[cfg(feature = "ristretto255")]
+//! # type CipherSuite = voprf::Ristretto255;
+//! # #[cfg(not(feature = "ristretto255"))]
+//! # type CipherSuite = p256::NistP256;
+//! use rand::rngs::OsRng;
+//! use voprf::simple::Voprf;
+//!
+//! let mut rng = OsRng;
+//! let keys = Voprf::<CipherSuite>::server_keygen(&mut rng).unwrap();
+//!
+//! let blind_result = Voprf::<CipherSuite>::client_blind(b"input", &mut rng).unwrap();
+//! let response =
+//!     Voprf::<CipherSuite>::server_evaluate(keys.private_key(), &blind_result.message, &mut rng)
+//!         .unwrap();
+//! let output = Voprf::<CipherSuite>::client_finalize(
+//!     &blind_result.state,
+//!     b"input",
+//!     &response,
+//!     keys.public_key(),
+//! )
+//! .unwrap();
+//! ```
+//!
+//! Batching, multiple keys and the other extensions of this crate are only
+//! available through the full interface, see the [crate documentation](crate).
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use core::marker::PhantomData;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use crate::{
+    BlindedElement, CipherSuite, Error, ErrorKind, EvaluationElement, Group, Operation, OprfClient,
+    OprfServer, PoprfClient, PoprfServer, Proof, Result, VoprfClient, VoprfServer,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// The base mode, without verifiability or public input.
+#[derive_where(Clone, Copy, Debug)]
+pub struct Oprf<CS: CipherSuite>(PhantomData<CS>)
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>;
+
+/// The verifiable mode, where the client checks that the server evaluated with
+/// the key of its public key.
+#[derive_where(Clone, Copy, Debug)]
+pub struct Voprf<CS: CipherSuite>(PhantomData<CS>)
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>;
+
+/// The partially-oblivious mode, which is verifiable and binds the output to a
+/// public `info` known to both parties.
+#[derive_where(Clone, Copy, Debug)]
+pub struct Poprf<CS: CipherSuite>(PhantomData<CS>)
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>;
+
+/// The serialized keys of a server, returned by `server_keygen`. The private
+/// key is zeroized on drop.
+#[derive_where(Clone)]
+pub struct KeyPair<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    private_key: GenericArray<u8, <CS::Group as Group>::ScalarLen>,
+    public_key: GenericArray<u8, <CS::Group as Group>::ElemLen>,
+}
+
+/// Contains the client state `S` to keep until `client_finalize` and the
+/// serialized message to send to the server. The state can be stored with its
+/// `serialize` function.
+#[derive(Debug)]
+pub struct BlindResult<S> {
+    /// The state of the client
+    pub state: S,
+    /// The message to send to the server
+    pub message: Vec<u8>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> Oprf<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Generates the keys of a new server. The public key isn't needed in this
+    /// mode.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn server_keygen<R: RngCore + CryptoRng>(rng: &mut R) -> Result<KeyPair<CS>> {
+        let server = OprfServer::<CS>::new(rng)?;
+        Ok(KeyPair::new(server.into_parts()))
+    }
+
+    /// Blinds the `input` on the client.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn client_blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        rng: &mut R,
+    ) -> Result<BlindResult<OprfClient<CS>>> {
+        let result = OprfClient::blind(input, rng)?;
+
+        Ok(BlindResult {
+            state: result.state,
+            message: result.message.serialize().to_vec(),
+        })
+    }
+
+    /// Evaluates the `message` of the client with the server's `private_key`,
+    /// returning the response to send back.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the `private_key` or the `message`
+    /// are invalid.
+    pub fn server_evaluate(private_key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+        let server = OprfServer::<CS>::new_with_key(private_key)?;
+        let message = BlindedElement::deserialize(message)?;
+
+        Ok(server.blind_evaluate(&message).serialize().to_vec())
+    }
+
+    /// Computes the output for `input` from the `response` of the server.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `response` is invalid.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    pub fn client_finalize(
+        state: &OprfClient<CS>,
+        input: &[u8],
+        response: &[u8],
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let message = EvaluationElement::deserialize(response)?;
+
+        state.finalize(input, &message)
+    }
+}
+
+impl<CS: CipherSuite> Voprf<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Generates the keys of a new server. The public key has to be known to
+    /// clients.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn server_keygen<R: RngCore + CryptoRng>(rng: &mut R) -> Result<KeyPair<CS>> {
+        let server = VoprfServer::<CS>::new(rng)?;
+        Ok(KeyPair::new(server.into_parts()))
+    }
+
+    /// Blinds the `input` on the client.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn client_blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        rng: &mut R,
+    ) -> Result<BlindResult<VoprfClient<CS>>> {
+        let result = VoprfClient::blind(input, rng)?;
+
+        Ok(BlindResult {
+            state: result.state,
+            message: result.message.serialize().to_vec(),
+        })
+    }
+
+    /// Evaluates the `message` of the client with the server's `private_key`,
+    /// returning the response to send back, which includes the proof.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `private_key` or the `message`
+    ///   are invalid.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn server_evaluate<R: RngCore + CryptoRng>(
+        private_key: &[u8],
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Vec<u8>> {
+        let server = VoprfServer::<CS>::new_with_key(private_key)?;
+        let message = BlindedElement::deserialize(message)?;
+        let result = server.try_blind_evaluate(rng, &message)?;
+
+        Ok(serialize_response(&result.message, &result.proof))
+    }
+
+    /// Verifies the `response` of the server against its `public_key` and
+    /// computes the output for `input`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `response` or the `public_key`
+    ///   are invalid.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the proof failed to verify.
+    pub fn client_finalize(
+        state: &VoprfClient<CS>,
+        input: &[u8],
+        response: &[u8],
+        public_key: &[u8],
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let (message, proof) = deserialize_response(response)?;
+        let public_key = CS::Group::deserialize_elem(public_key)?;
+
+        state.finalize(input, &message, &proof, public_key)
+    }
+}
+
+impl<CS: CipherSuite> Poprf<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Generates the keys of a new server. The public key has to be known to
+    /// clients.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn server_keygen<R: RngCore + CryptoRng>(rng: &mut R) -> Result<KeyPair<CS>> {
+        let server = PoprfServer::<CS>::new(rng)?;
+        Ok(KeyPair::new(server.into_parts()))
+    }
+
+    /// Blinds the `input` on the client.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn client_blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        rng: &mut R,
+    ) -> Result<BlindResult<PoprfClient<CS>>> {
+        let result = PoprfClient::blind(input, rng)?;
+
+        Ok(BlindResult {
+            state: result.state,
+            message: result.message.serialize().to_vec(),
+        })
+    }
+
+    /// Evaluates the `message` of the client with the server's `private_key`
+    /// and the public `info`, returning the response to send back, which
+    /// includes the proof.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `private_key` or the `message`
+    ///   are invalid.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn server_evaluate<R: RngCore + CryptoRng>(
+        private_key: &[u8],
+        message: &[u8],
+        info: &[u8],
+        rng: &mut R,
+    ) -> Result<Vec<u8>> {
+        let server = PoprfServer::<CS>::new_with_key(private_key)?;
+        let message = BlindedElement::deserialize(message)?;
+        let result = server.blind_evaluate(rng, &message, Some(info))?;
+
+        Ok(serialize_response(&result.message, &result.proof))
+    }
+
+    /// Verifies the `response` of the server against its `public_key` and the
+    /// public `info` and computes the output for `input`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `response` or the `public_key`
+    ///   are invalid.
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the proof failed to verify.
+    pub fn client_finalize(
+        state: &PoprfClient<CS>,
+        input: &[u8],
+        response: &[u8],
+        public_key: &[u8],
+        info: &[u8],
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let (message, proof) = deserialize_response(response)?;
+        let public_key = CS::Group::deserialize_elem(public_key)?;
+
+        state.finalize(input, &message, &proof, public_key, Some(info))
+    }
+}
+
+impl<CS: CipherSuite> KeyPair<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn new(private_key: <CS::Group as Group>::Scalar) -> Self {
+        Self {
+            private_key: CS::Group::serialize_scalar(private_key),
+            public_key: CS::Group::serialize_elem(CS::Group::base_elem() * &private_key),
+        }
+    }
+
+    /// Returns the serialized private key, to pass to `server_evaluate`.
+    pub fn private_key(&self) -> &[u8] {
+        &self.private_key
+    }
+
+    /// Returns the serialized public key, to pass to `client_finalize`.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+/// The private key isn't printed, as it is secret.
+impl<CS: CipherSuite> Debug for KeyPair<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<CS: CipherSuite> Drop for KeyPair<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn drop(&mut self) {
+        self.private_key.as_mut_slice().zeroize();
+    }
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+/// Serializes the response of the verifiable modes, the evaluation element
+/// followed by the proof.
+fn serialize_response<CS: CipherSuite>(
+    message: &EvaluationElement<CS>,
+    proof: &Proof<CS>,
+) -> Vec<u8>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut response = message.serialize().to_vec();
+    response.extend_from_slice(&CS::Group::serialize_scalar(proof.c_scalar));
+    response.extend_from_slice(&CS::Group::serialize_scalar(proof.s_scalar));
+    response
+}
+
+fn deserialize_response<CS: CipherSuite>(
+    response: &[u8],
+) -> Result<(EvaluationElement<CS>, Proof<CS>)>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+
+    if response.len() != elem_len + Proof::<CS>::LEN {
+        return Err(Error::new(
+            ErrorKind::Deserialization,
+            Operation::DeserializeElem,
+        ));
+    }
+
+    let (message, proof) = response.split_at(elem_len);
+
+    Ok((
+        EvaluationElement::deserialize(message)?,
+        Proof::deserialize(proof)?,
+    ))
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    fn simple_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let input = b"input";
+        let info = b"info";
+
+        let keys = Oprf::<CS>::server_keygen(&mut rng).unwrap();
+        let blind_result = Oprf::<CS>::client_blind(input, &mut rng).unwrap();
+        let response =
+            Oprf::<CS>::server_evaluate(keys.private_key(), &blind_result.message).unwrap();
+        let output = Oprf::<CS>::client_finalize(&blind_result.state, input, &response).unwrap();
+        let server = OprfServer::<CS>::new_with_key(keys.private_key()).unwrap();
+        assert_eq!(output, server.evaluate(input).unwrap());
+
+        let keys = Voprf::<CS>::server_keygen(&mut rng).unwrap();
+        let blind_result = Voprf::<CS>::client_blind(input, &mut rng).unwrap();
+        let response =
+            Voprf::<CS>::server_evaluate(keys.private_key(), &blind_result.message, &mut rng)
+                .unwrap();
+        let output =
+            Voprf::<CS>::client_finalize(&blind_result.state, input, &response, keys.public_key())
+                .unwrap();
+        let server = VoprfServer::<CS>::new_with_key(keys.private_key()).unwrap();
+        assert_eq!(output, server.evaluate(input).unwrap());
+
+        // A response of another key fails to verify
+        let other_keys = Voprf::<CS>::server_keygen(&mut rng).unwrap();
+        assert_eq!(
+            Voprf::<CS>::client_finalize(
+                &blind_result.state,
+                input,
+                &response,
+                other_keys.public_key()
+            )
+            .map(|_| ()),
+            Err(Error::new(
+                ErrorKind::ProofVerification,
+                Operation::VerifyProof
+            ))
+        );
+        assert_eq!(
+            Voprf::<CS>::client_finalize(
+                &blind_result.state,
+                input,
+                &response[1..],
+                keys.public_key()
+            )
+            .map(|_| ()),
+            Err(Error::new(
+                ErrorKind::Deserialization,
+                Operation::DeserializeElem
+            ))
+        );
+
+        let keys = Poprf::<CS>::server_keygen(&mut rng).unwrap();
+        let blind_result = Poprf::<CS>::client_blind(input, &mut rng).unwrap();
+        let response =
+            Poprf::<CS>::server_evaluate(keys.private_key(), &blind_result.message, info, &mut rng)
+                .unwrap();
+        let output = Poprf::<CS>::client_finalize(
+            &blind_result.state,
+            input,
+            &response,
+            keys.public_key(),
+            info,
+        )
+        .unwrap();
+        let server = PoprfServer::<CS>::new_with_key(keys.private_key()).unwrap();
+        assert_eq!(output, server.evaluate(input, Some(info)).unwrap());
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            simple_retrieval::<Ristretto255>();
+        }
+
+        simple_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
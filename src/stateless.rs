@@ -0,0 +1,226 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains a server that derives its keys on demand from a master seed
+
+use core::fmt::{self, Debug, Formatter};
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "alloc")]
+use crate::VoprfServerBatchEvaluateResult;
+use crate::{BlindedElement, CipherSuite, Group, Result, VoprfServer, VoprfServerEvaluateResult};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A server in verifiable mode that doesn't store any evaluation keys. Instead
+/// the key for a key ID is derived on every request from the master seed with
+/// DeriveKeyPair(), using the key ID as info, and zeroized afterwards.
+///
+/// This way horizontally-scaled servers only need to share the master seed,
+/// not the key material of every epoch.
+#[derive_where(Clone, ZeroizeOnDrop)]
+pub struct StatelessServer<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    seed: [u8; 32],
+    _cs: core::marker::PhantomData<CS>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> StatelessServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Produces a new [StatelessServer] from the master seed.
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self {
+            seed,
+            _cs: core::marker::PhantomData,
+        }
+    }
+
+    /// Derives the [VoprfServer] for `key_id`. Equal to
+    /// [`VoprfServer::new_from_seed`] with the master seed and `key_id` as
+    /// info. The returned server is zeroized on drop.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::DeriveKeyPair`](crate::ErrorKind::DeriveKeyPair) if the
+    ///   `key_id` is longer then `u16::MAX - 35`.
+    /// - [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    ///   fails and can't be completed.
+    pub fn derive_server(&self, key_id: &[u8]) -> Result<VoprfServer<CS>> {
+        VoprfServer::new_from_seed(&self.seed, key_id)
+    }
+
+    /// Retrieves the public key for `key_id`, to be published to clients.
+    ///
+    /// # Errors
+    /// See [`StatelessServer::derive_server`].
+    pub fn get_public_key(&self, key_id: &[u8]) -> Result<<CS::Group as Group>::Elem> {
+        self.derive_server(key_id)
+            .map(|server| server.get_public_key())
+    }
+
+    /// Computes the second step of the protocol with the key for `key_id`. See
+    /// [`VoprfServer::blind_evaluate`].
+    ///
+    /// # Errors
+    /// See [`StatelessServer::derive_server`].
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        key_id: &[u8],
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>> {
+        self.derive_server(key_id)?
+            .try_blind_evaluate(rng, blinded_element)
+    }
+
+    /// Allows for batching of the evaluation of multiple [BlindedElement]s
+    /// with the key for `key_id`. See [`VoprfServer::batch_blind_evaluate`].
+    ///
+    /// # Errors
+    /// - See [`StatelessServer::derive_server`].
+    /// - [`ErrorKind::Batch`](crate::ErrorKind::Batch) if the number of
+    ///   `blinded_elements` is longer then [`u16::MAX`].
+    #[cfg(feature = "alloc")]
+    pub fn batch_blind_evaluate<'a, R: RngCore + CryptoRng, I>(
+        &self,
+        rng: &mut R,
+        key_id: &[u8],
+        blinded_elements: &'a I,
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>>
+    where
+        CS: 'a,
+        &'a I: IntoIterator<Item = &'a BlindedElement<CS>>,
+        <&'a I as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        self.derive_server(key_id)?
+            .batch_blind_evaluate(rng, blinded_elements)
+    }
+
+    /// Computes the output of the protocol on the server side with the key for
+    /// `key_id`. See [`VoprfServer::evaluate`].
+    ///
+    /// # Errors
+    /// - See [`StatelessServer::derive_server`].
+    /// - [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is longer
+    ///   then [`u16::MAX`].
+    pub fn evaluate(&self, key_id: &[u8], input: &[u8]) -> Result<Output<CS::FinalizeHash>> {
+        self.derive_server(key_id)?.evaluate(input)
+    }
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+/// The master seed isn't printed, as every key is derived from it.
+impl<CS: CipherSuite> Debug for StatelessServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatelessServer").finish_non_exhaustive()
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+    use crate::VoprfClient;
+
+    fn stateless_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let key_id = b"epoch 1";
+        let mut rng = OsRng;
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+
+        // Two instances sharing the master seed derive the same keys
+        let server_1 = StatelessServer::<CS>::new(seed);
+        let server_2 = StatelessServer::<CS>::new(seed);
+        let public_key = server_1.get_public_key(key_id).unwrap();
+        assert!(bool::from(
+            public_key.ct_eq(&server_2.get_public_key(key_id).unwrap())
+        ));
+        assert!(!bool::from(
+            public_key.ct_eq(&server_1.get_public_key(b"epoch 2").unwrap())
+        ));
+
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = server_1
+            .blind_evaluate(&mut rng, key_id, &client_blind_result.message)
+            .unwrap();
+        let client_finalize = client_blind_result
+            .state
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                public_key,
+            )
+            .unwrap();
+        assert_eq!(client_finalize, server_2.evaluate(key_id, input).unwrap());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_debug_redacts_seed() {
+        use alloc::format;
+
+        let server = StatelessServer::<p256::NistP256>::new([0xab; 32]);
+        let debug = format!("{:?}", server);
+
+        assert_eq!(debug, "StatelessServer { .. }");
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            stateless_retrieval::<Ristretto255>();
+        }
+
+        stateless_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
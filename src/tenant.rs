@@ -0,0 +1,194 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains a server holding the keys of many tenants in one master key
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{BlindedElement, CipherSuite, Group, Result, VoprfServer, VoprfServerEvaluateResult};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_TENANT: [u8; 6] = *b"Tenant";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A [`VoprfServer`] for many tenants, e.g. the customers of a hosted issuer,
+/// each with their own key, which only stores a single master key.
+///
+/// The key of a tenant is derived from the master key on the fly with
+/// [`VoprfServer::derive_subkey`], domain separated by its ID, so tenants can
+/// be added without any state. The derivation is additionally separated from
+/// other subkeys of the master key, so the master key can also be used with
+/// [`derive_subkey`](VoprfServer::derive_subkey) directly. Clients verify the
+/// proofs of a tenant against its own public key, published with
+/// [`public_key_for`](Self::public_key_for), and their outputs only depend on
+/// the key of their tenant.
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct TenantServer<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    tenants: VoprfServer<CS>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> TenantServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Creates a server for the tenants of the `master` key.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    /// fails and can't be completed.
+    pub fn new(master: &VoprfServer<CS>) -> Result<Self> {
+        Ok(Self {
+            tenants: master.derive_subkey(&STR_TENANT)?,
+        })
+    }
+
+    /// Derives the [`VoprfServer`] of the tenant `tenant_id`, e.g. to batch
+    /// evaluations of the same tenant.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::DeriveKeyPair`](crate::ErrorKind::DeriveKeyPair) if the
+    ///   `tenant_id` is longer then `u16::MAX - 3` minus the private key
+    ///   length.
+    /// - [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    ///   fails and can't be completed.
+    pub fn tenant(&self, tenant_id: &[u8]) -> Result<VoprfServer<CS>> {
+        self.tenants.derive_subkey(tenant_id)
+    }
+
+    /// Returns the public key of the tenant `tenant_id`, for its clients to
+    /// verify the proofs against.
+    ///
+    /// # Errors
+    /// See [`tenant`](Self::tenant).
+    pub fn public_key_for(&self, tenant_id: &[u8]) -> Result<<CS::Group as Group>::Elem> {
+        Ok(self.tenant(tenant_id)?.get_public_key())
+    }
+
+    /// Evaluates the `blinded_element` of a client of the tenant `tenant_id`
+    /// with the key of that tenant, like [`VoprfServer::blind_evaluate`].
+    ///
+    /// # Errors
+    /// See [`tenant`](Self::tenant).
+    pub fn evaluate_for<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        tenant_id: &[u8],
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>> {
+        debug_span!("evaluate_for");
+
+        self.tenant(tenant_id)?
+            .try_blind_evaluate(rng, blinded_element)
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{Error, ErrorKind, Operation, VoprfClient};
+
+    fn tenant_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let input = b"input";
+        let master = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let server = TenantServer::new(&master).unwrap();
+
+        let mut outputs = [None, None];
+        for (tenant_id, output) in [b"first", b"other"].iter().zip(&mut outputs) {
+            let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let result = server
+                .evaluate_for(&mut rng, *tenant_id, &client_blind_result.message)
+                .unwrap();
+            let public_key = server.public_key_for(*tenant_id).unwrap();
+            *output = Some(
+                client_blind_result
+                    .state
+                    .finalize(input, &result.message, &result.proof, public_key)
+                    .unwrap(),
+            );
+            assert_eq!(
+                *output,
+                Some(server.tenant(*tenant_id).unwrap().evaluate(input).unwrap())
+            );
+
+            // The proof doesn't verify against the master key
+            assert_eq!(
+                client_blind_result
+                    .state
+                    .finalize(
+                        input,
+                        &result.message,
+                        &result.proof,
+                        master.get_public_key()
+                    )
+                    .map(|_| ()),
+                Err(Error::new(
+                    ErrorKind::ProofVerification,
+                    Operation::VerifyProof
+                ))
+            );
+        }
+        assert_ne!(outputs[0], outputs[1]);
+
+        // Tenant keys are separated from other subkeys of the master key
+        let subkey = master.derive_subkey(b"first").unwrap();
+        assert_ne!(
+            subkey.evaluate(input).unwrap(),
+            server.tenant(b"first").unwrap().evaluate(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            tenant_retrieval::<Ristretto255>();
+        }
+
+        tenant_retrieval::<NistP256>();
+
+        Ok(())
+    }
+}
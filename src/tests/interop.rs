@@ -0,0 +1,168 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Cross-checks random transcripts against a reference implementation.
+//!
+//! The reference is the command in the `VOPRF_REFERENCE` environment variable,
+//! e.g. `python3 scripts/voprf_reference.py`, and the tests are skipped if it
+//! isn't set. It is run once per transcript and receives a JSON object with the
+//! parameters of a test vector, in the format of the vectors of the
+//! specification:
+//!
+//! ```text
+//! {"Suite": "P-256, SHA-256", "Mode": "VOPRF", "Seed": "..", "KeyInfo": "..",
+//!  "Input": "..,..", "Info": "..", "Blind": "..,..", "ProofRandomScalar": ".."}
+//! ```
+//!
+//! It has to print the completed test vector, with the `skSm`, `pkSm`,
+//! `BlindedElement`, `EvaluationElement`, `Proof` and `Output` fields, which
+//! are then checked like the vectors of the specification. `VOPRF_ROUNDS` sets
+//! the number of transcripts per suite and mode. Only the suites assigned by
+//! the specification are covered, the others have no reference.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Add;
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, Sum, U256};
+use generic_array::ArrayLength;
+use json::JsonValue;
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+
+use crate::tests::test_cfrg_vectors::{
+    check_oprf_vectors, check_poprf_vectors, check_voprf_vectors, populate_test_vectors,
+    VOPRFTestVectorParameters,
+};
+use crate::{CipherSuite, Group, Result};
+
+const DEFAULT_ROUNDS: usize = 8;
+
+fn random_bytes(rng: &mut OsRng, min: usize, max: usize) -> Vec<u8> {
+    let mut bytes = alloc::vec![0; rng.gen_range(min..=max)];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn random_scalar<CS: CipherSuite>(rng: &mut OsRng) -> String {
+    hex::encode(CS::Group::serialize_scalar(CS::Group::random_scalar(rng)))
+}
+
+// Asks the reference to complete a random transcript
+fn reference_vector<CS: CipherSuite>(
+    reference: &str,
+    suite: &str,
+    mode: &str,
+) -> VOPRFTestVectorParameters {
+    let mut rng = OsRng;
+    let batch_size = rng.gen_range(1..=3);
+    let inputs: Vec<_> = (0..batch_size)
+        .map(|_| hex::encode(random_bytes(&mut rng, 1, 64)))
+        .collect();
+    let blinds: Vec<_> = (0..batch_size)
+        .map(|_| random_scalar::<CS>(&mut rng))
+        .collect();
+
+    let mut request = JsonValue::new_object();
+    request["Suite"] = suite.into();
+    request["Mode"] = mode.into();
+    request["Seed"] = hex::encode(random_bytes(&mut rng, 32, 32)).into();
+    request["KeyInfo"] = hex::encode(random_bytes(&mut rng, 0, 16)).into();
+    request["Input"] = inputs.join(",").into();
+    request["Blind"] = blinds.join(",").into();
+    if mode == "POPRF" {
+        request["Info"] = hex::encode(random_bytes(&mut rng, 0, 16)).into();
+    }
+    if mode != "OPRF" {
+        request["ProofRandomScalar"] = random_scalar::<CS>(&mut rng).into();
+    }
+
+    let mut arguments = reference.split_whitespace();
+    let program = arguments.next().expect("`VOPRF_REFERENCE` is empty");
+    let mut child = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Could not run the reference");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(request.dump().as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "The reference failed on {}",
+        request
+    );
+
+    let response = json::parse(&String::from_utf8(output.stdout).unwrap())
+        .expect("Could not parse the response of the reference");
+    for (key, value) in response.entries() {
+        request[key] = value.clone();
+    }
+
+    populate_test_vectors(&request)
+}
+
+fn interop<CS: CipherSuite>(reference: &str, suite: &str, rounds: usize) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+{
+    for mode in ["OPRF", "VOPRF", "POPRF"] {
+        let tvs: Vec<_> = (0..rounds)
+            .map(|_| reference_vector::<CS>(reference, suite, mode))
+            .collect();
+
+        match mode {
+            "OPRF" => check_oprf_vectors::<CS>(&tvs)?,
+            "VOPRF" => check_voprf_vectors::<CS>(&tvs)?,
+            _ => check_poprf_vectors::<CS>(&tvs)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_interop() -> Result<()> {
+    use p256::NistP256;
+
+    let reference = match env::var("VOPRF_REFERENCE") {
+        Ok(reference) => reference,
+        Err(_) => {
+            std::println!("Skipping the interop tests, `VOPRF_REFERENCE` isn't set");
+            return Ok(());
+        }
+    };
+    let rounds = env::var("VOPRF_ROUNDS").map_or(DEFAULT_ROUNDS, |rounds| {
+        rounds
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid `VOPRF_ROUNDS`: {:?}", rounds))
+    });
+
+    #[cfg(feature = "ristretto255")]
+    {
+        use crate::Ristretto255;
+
+        interop::<Ristretto255>(&reference, "ristretto255, SHA-512", rounds)?;
+    }
+
+    interop::<NistP256>(&reference, "P-256, SHA-256", rounds)?;
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Checks the metrics documented in the crate root with a recorder that keeps
+//! the values of every thread apart, so tests running in parallel don't see
+//! each other's metrics
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::cell::RefCell;
+use std::sync::Once;
+use std::thread_local;
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use metrics::{
+    Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+    SharedString, Unit,
+};
+use rand::rngs::OsRng;
+
+use crate::{
+    BlindedElement, CipherSuite, ErrorKind, OprfClient, OprfServer, Result, VoprfClient,
+    VoprfServer,
+};
+
+thread_local! {
+    static VALUES: RefCell<BTreeMap<String, Vec<f64>>> = RefCell::new(BTreeMap::new());
+}
+
+struct ThreadLocalRecorder;
+
+struct Handle(String);
+
+impl Handle {
+    fn new(key: &Key) -> Arc<Self> {
+        let labels: Vec<_> = key
+            .labels()
+            .map(|label| std::format!("{}={}", label.key(), label.value()))
+            .collect();
+
+        Arc::new(Self(std::format!("{}{{{}}}", key.name(), labels.join(","))))
+    }
+
+    fn push(&self, value: f64) {
+        VALUES.with(|values| {
+            values
+                .borrow_mut()
+                .entry(self.0.clone())
+                .or_default()
+                .push(value)
+        });
+    }
+}
+
+impl CounterFn for Handle {
+    fn increment(&self, value: u64) {
+        self.push(value as f64);
+    }
+
+    fn absolute(&self, _: u64) {
+        unimplemented!()
+    }
+}
+
+impl HistogramFn for Handle {
+    fn record(&self, value: f64) {
+        self.push(value);
+    }
+}
+
+impl Recorder for ThreadLocalRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(Handle::new(key))
+    }
+
+    fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(Handle::new(key))
+    }
+}
+
+/// Installs the recorder and takes the values recorded by this thread so far.
+fn take_values() -> BTreeMap<String, Vec<f64>> {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| metrics::set_global_recorder(ThreadLocalRecorder).unwrap());
+
+    VALUES.with(|values| values.take())
+}
+
+fn sum(values: &BTreeMap<String, Vec<f64>>, key: &str) -> f64 {
+    values.get(key).map_or(0.0, |values| values.iter().sum())
+}
+
+fn record_metrics<CS: CipherSuite>() -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    let mut rng = OsRng;
+    take_values();
+
+    let server = OprfServer::<CS>::new(&mut rng)?;
+    let blind_result = OprfClient::<CS>::blind(b"input", &mut rng)?;
+    server.blind_evaluate(&blind_result.message);
+    server.evaluate(b"input")?;
+    server.evaluate_batch([b"a".as_slice(), b"b"]).count();
+
+    let values = take_values();
+    assert_eq!(sum(&values, "voprf_evaluations_total{mode=oprf}"), 4.0);
+
+    let server = VoprfServer::<CS>::new(&mut rng)?;
+    let other_server = VoprfServer::<CS>::new(&mut rng)?;
+    let blind_results = [
+        VoprfClient::<CS>::blind(b"a", &mut rng)?,
+        VoprfClient::<CS>::blind(b"b", &mut rng)?,
+        VoprfClient::<CS>::blind(b"c", &mut rng)?,
+    ];
+    let messages: Vec<_> = blind_results
+        .iter()
+        .map(|result| result.message.clone())
+        .collect();
+    server.batch_blind_evaluate(&mut rng, &messages)?;
+    let result = server.blind_evaluate(&mut rng, &blind_results[0].message);
+
+    let values = take_values();
+    assert_eq!(sum(&values, "voprf_evaluations_total{mode=voprf}"), 4.0);
+    assert_eq!(values["voprf_batch_size{mode=voprf}"], [3.0]);
+
+    // A proof of another key fails to verify
+    let error = blind_results[0]
+        .state
+        .finalize(
+            b"a",
+            &result.message,
+            &result.proof,
+            other_server.get_public_key(),
+        )
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ProofVerification);
+
+    let values = take_values();
+    assert_eq!(
+        sum(
+            &values,
+            "voprf_proof_verification_failures_total{mode=voprf}"
+        ),
+        1.0
+    );
+
+    // Deserializing valid messages doesn't count
+    BlindedElement::<CS>::deserialize(&messages[0].serialize())?;
+    assert!(BlindedElement::<CS>::deserialize(&[0xFF; 3]).is_err());
+    assert!(BlindedElement::<CS>::deserialize_batch(&[0xFF; 3], 2).is_err());
+
+    let values = take_values();
+    assert_eq!(
+        sum(
+            &values,
+            "voprf_deserialization_errors_total{message=blinded_element}"
+        ),
+        2.0
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_functionality() -> Result<()> {
+    use p256::NistP256;
+
+    #[cfg(feature = "ristretto255")]
+    {
+        use crate::Ristretto255;
+
+        record_metrics::<Ristretto255>()?;
+    }
+
+    record_metrics::<NistP256>()?;
+
+    Ok(())
+}
@@ -6,6 +6,11 @@
 // of this source tree.
 
 mod cfrg_vectors;
-mod mock_rng;
+#[cfg(feature = "interop")]
+mod interop;
+#[cfg(feature = "metrics")]
+mod metrics;
+pub(crate) mod mock_rng;
 mod parser;
+mod send_sync;
 mod test_cfrg_vectors;
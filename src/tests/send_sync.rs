@@ -0,0 +1,79 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Asserts the thread-safety contract documented in the crate root
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+
+use crate::{
+    Blinded, BlindedElement, CipherSuite, Client, Error, Evaluated, EvaluationElement, Group,
+    KeyCommitment, OprfClient, OprfClientBlindResult, OprfServer, PoprfClient,
+    PoprfClientBatchBlindArrayResult, PoprfClientBlindResult, PoprfPreparedTweak, PoprfServer,
+    PoprfServerBatchEvaluateArrayResult, PoprfServerEvaluateResult, PreparedEvaluationElement,
+    Proof, Transcript, VoprfClient, VoprfClientBatchBlindArrayResult, VoprfClientBlindResult,
+    VoprfServer, VoprfServerBatchEvaluateArrayResult, VoprfServerEvaluateResult,
+};
+
+fn assert_clone_send_sync<T: Clone + Send + Sync>() {}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn clone_send_sync<CS: CipherSuite>()
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::Elem: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+    CS::Hash: Clone + Send + Sync,
+{
+    assert_clone_send_sync::<OprfClient<CS>>();
+    assert_clone_send_sync::<OprfServer<CS>>();
+    assert_clone_send_sync::<VoprfClient<CS>>();
+    assert_clone_send_sync::<VoprfServer<CS>>();
+    assert_clone_send_sync::<PoprfClient<CS>>();
+    assert_clone_send_sync::<PoprfServer<CS>>();
+
+    assert_clone_send_sync::<BlindedElement<CS>>();
+    assert_clone_send_sync::<EvaluationElement<CS>>();
+    assert_clone_send_sync::<PreparedEvaluationElement<CS>>();
+    assert_clone_send_sync::<Proof<CS>>();
+    assert_clone_send_sync::<PoprfPreparedTweak<CS>>();
+    assert_clone_send_sync::<KeyCommitment<CS>>();
+    assert_clone_send_sync::<Transcript<CS>>();
+
+    assert_clone_send_sync::<OprfClientBlindResult<CS>>();
+    assert_clone_send_sync::<VoprfClientBlindResult<CS>>();
+    assert_clone_send_sync::<VoprfClientBatchBlindArrayResult<CS, 2>>();
+    assert_clone_send_sync::<VoprfServerEvaluateResult<CS>>();
+    assert_clone_send_sync::<VoprfServerBatchEvaluateArrayResult<CS, 2>>();
+    assert_clone_send_sync::<PoprfClientBlindResult<CS>>();
+    assert_clone_send_sync::<PoprfClientBatchBlindArrayResult<CS, 2>>();
+    assert_clone_send_sync::<PoprfServerEvaluateResult<CS>>();
+    assert_clone_send_sync::<PoprfServerBatchEvaluateArrayResult<CS, 2>>();
+    #[cfg(feature = "alloc")]
+    {
+        assert_clone_send_sync::<crate::VoprfServerBatchEvaluateResult<CS>>();
+        assert_clone_send_sync::<crate::PoprfServerBatchEvaluateResult<CS>>();
+    }
+
+    // Typestate clients are deliberately not `Clone`, so they can't be
+    // finalized twice.
+    assert_send_sync::<Client<VoprfClient<CS>, Blinded>>();
+    assert_send_sync::<Client<VoprfClient<CS>, Evaluated<VoprfServerEvaluateResult<CS>>>>();
+
+    assert_clone_send_sync::<Error>();
+}
+
+#[test]
+fn test_clone_send_sync() {
+    #[cfg(feature = "ristretto255")]
+    clone_send_sync::<crate::Ristretto255>();
+
+    clone_send_sync::<p256::NistP256>();
+}
@@ -16,16 +16,18 @@ use generic_array::typenum::{IsLess, IsLessOrEqual, Sum, U256};
 use generic_array::ArrayLength;
 use json::JsonValue;
 
+use crate::common::{generate_proof_with_nonce, verify_proof};
 use crate::tests::mock_rng::CycleRng;
 use crate::tests::parser::*;
 use crate::{
-    BlindedElement, CipherSuite, EvaluationElement, Group, OprfClient, OprfServer, PoprfClient,
-    PoprfServer, PoprfServerBatchEvaluateFinishResult, PoprfServerBatchEvaluatePrepareResult,
-    Proof, Result, VoprfClient, VoprfServer, VoprfServerBatchEvaluateFinishResult,
+    BlindedElement, CipherSuite, EvaluationElement, Group, Mode, OprfClient, OprfServer,
+    PoprfClient, PoprfServer, PoprfServerBatchEvaluateFinishResult,
+    PoprfServerBatchEvaluatePrepareResult, Proof, Result, VoprfClient, VoprfServer,
+    VoprfServerBatchEvaluateFinishResult,
 };
 
 #[derive(Debug)]
-struct VOPRFTestVectorParameters {
+pub(super) struct VOPRFTestVectorParameters {
     seed: Vec<u8>,
     sksm: Vec<u8>,
     pksm: Vec<u8>,
@@ -40,7 +42,7 @@ struct VOPRFTestVectorParameters {
     output: Vec<Vec<u8>>,
 }
 
-fn populate_test_vectors(values: &JsonValue) -> VOPRFTestVectorParameters {
+pub(super) fn populate_test_vectors(values: &JsonValue) -> VOPRFTestVectorParameters {
     VOPRFTestVectorParameters {
         seed: decode(values, "Seed"),
         sksm: decode(values, "skSm"),
@@ -60,15 +62,15 @@ fn populate_test_vectors(values: &JsonValue) -> VOPRFTestVectorParameters {
 fn decode(values: &JsonValue, key: &str) -> Vec<u8> {
     values[key]
         .as_str()
-        .and_then(|s| hex::decode(&s).ok())
+        .and_then(|s| hex::decode(s).ok())
         .unwrap_or_default()
 }
 
 fn decode_vec(values: &JsonValue, key: &str) -> Vec<Vec<u8>> {
     let s = values[key].as_str().unwrap();
     let res = match s.contains(',') {
-        true => Some(s.split(',').map(|x| hex::decode(&x).unwrap()).collect()),
-        false => Some(vec![hex::decode(&s).unwrap()]),
+        true => Some(s.split(',').map(|x| hex::decode(x).unwrap()).collect()),
+        false => Some(vec![hex::decode(s).unwrap()]),
     };
     res.unwrap()
 }
@@ -99,11 +101,7 @@ fn test_vectors() -> Result<()> {
             String::from("OPRF")
         );
         assert_ne!(ristretto_oprf_tvs.len(), 0);
-        test_oprf_seed_to_key::<Ristretto255>(&ristretto_oprf_tvs)?;
-        test_oprf_blind::<Ristretto255>(&ristretto_oprf_tvs)?;
-        test_oprf_blind_evaluate::<Ristretto255>(&ristretto_oprf_tvs)?;
-        test_oprf_finalize::<Ristretto255>(&ristretto_oprf_tvs)?;
-        test_oprf_evaluate::<Ristretto255>(&ristretto_oprf_tvs)?;
+        check_oprf_vectors::<Ristretto255>(&ristretto_oprf_tvs)?;
 
         let ristretto_voprf_tvs = json_to_test_vectors!(
             rfc,
@@ -111,11 +109,7 @@ fn test_vectors() -> Result<()> {
             String::from("VOPRF")
         );
         assert_ne!(ristretto_voprf_tvs.len(), 0);
-        test_voprf_seed_to_key::<Ristretto255>(&ristretto_voprf_tvs)?;
-        test_voprf_blind::<Ristretto255>(&ristretto_voprf_tvs)?;
-        test_voprf_blind_evaluate::<Ristretto255>(&ristretto_voprf_tvs)?;
-        test_voprf_finalize::<Ristretto255>(&ristretto_voprf_tvs)?;
-        test_voprf_evaluate::<Ristretto255>(&ristretto_voprf_tvs)?;
+        check_voprf_vectors::<Ristretto255>(&ristretto_voprf_tvs)?;
 
         let ristretto_poprf_tvs = json_to_test_vectors!(
             rfc,
@@ -123,43 +117,70 @@ fn test_vectors() -> Result<()> {
             String::from("POPRF")
         );
         assert_ne!(ristretto_poprf_tvs.len(), 0);
-        test_poprf_seed_to_key::<Ristretto255>(&ristretto_poprf_tvs)?;
-        test_poprf_blind::<Ristretto255>(&ristretto_poprf_tvs)?;
-        test_poprf_blind_evaluate::<Ristretto255>(&ristretto_poprf_tvs)?;
-        test_poprf_finalize::<Ristretto255>(&ristretto_poprf_tvs)?;
-        test_poprf_evaluate::<Ristretto255>(&ristretto_poprf_tvs)?;
+        check_poprf_vectors::<Ristretto255>(&ristretto_poprf_tvs)?;
     }
 
     let p256_oprf_tvs =
         json_to_test_vectors!(rfc, String::from("P-256, SHA-256"), String::from("OPRF"));
     assert_ne!(p256_oprf_tvs.len(), 0);
-    test_oprf_seed_to_key::<NistP256>(&p256_oprf_tvs)?;
-    test_oprf_blind::<NistP256>(&p256_oprf_tvs)?;
-    test_oprf_blind_evaluate::<NistP256>(&p256_oprf_tvs)?;
-    test_oprf_finalize::<NistP256>(&p256_oprf_tvs)?;
-    test_oprf_evaluate::<NistP256>(&p256_oprf_tvs)?;
+    check_oprf_vectors::<NistP256>(&p256_oprf_tvs)?;
 
     let p256_voprf_tvs =
         json_to_test_vectors!(rfc, String::from("P-256, SHA-256"), String::from("VOPRF"));
     assert_ne!(p256_voprf_tvs.len(), 0);
-    test_voprf_seed_to_key::<NistP256>(&p256_voprf_tvs)?;
-    test_voprf_blind::<NistP256>(&p256_voprf_tvs)?;
-    test_voprf_blind_evaluate::<NistP256>(&p256_voprf_tvs)?;
-    test_voprf_finalize::<NistP256>(&p256_voprf_tvs)?;
-    test_voprf_evaluate::<NistP256>(&p256_voprf_tvs)?;
+    check_voprf_vectors::<NistP256>(&p256_voprf_tvs)?;
 
     let p256_poprf_tvs =
         json_to_test_vectors!(rfc, String::from("P-256, SHA-256"), String::from("POPRF"));
     assert_ne!(p256_poprf_tvs.len(), 0);
-    test_poprf_seed_to_key::<NistP256>(&p256_poprf_tvs)?;
-    test_poprf_blind::<NistP256>(&p256_poprf_tvs)?;
-    test_poprf_blind_evaluate::<NistP256>(&p256_poprf_tvs)?;
-    test_poprf_finalize::<NistP256>(&p256_poprf_tvs)?;
-    test_poprf_evaluate::<NistP256>(&p256_poprf_tvs)?;
+    check_poprf_vectors::<NistP256>(&p256_poprf_tvs)?;
 
     Ok(())
 }
 
+// Runs all checks of the base mode against the test vectors
+pub(super) fn check_oprf_vectors<CS: CipherSuite>(tvs: &[VOPRFTestVectorParameters]) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    test_oprf_seed_to_key::<CS>(tvs)?;
+    test_oprf_blind::<CS>(tvs)?;
+    test_oprf_blind_evaluate::<CS>(tvs)?;
+    test_oprf_finalize::<CS>(tvs)?;
+    test_oprf_evaluate::<CS>(tvs)
+}
+
+// Runs all checks of the verifiable mode against the test vectors
+pub(super) fn check_voprf_vectors<CS: CipherSuite>(tvs: &[VOPRFTestVectorParameters]) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+{
+    test_voprf_seed_to_key::<CS>(tvs)?;
+    test_voprf_blind::<CS>(tvs)?;
+    test_voprf_blind_evaluate::<CS>(tvs)?;
+    test_voprf_finalize::<CS>(tvs)?;
+    test_voprf_evaluate::<CS>(tvs)
+}
+
+// Runs all checks of the partially-oblivious mode against the test vectors
+pub(super) fn check_poprf_vectors<CS: CipherSuite>(tvs: &[VOPRFTestVectorParameters]) -> Result<()>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+{
+    test_poprf_seed_to_key::<CS>(tvs)?;
+    test_poprf_blind::<CS>(tvs)?;
+    test_poprf_blind_evaluate::<CS>(tvs)?;
+    test_poprf_finalize::<CS>(tvs)?;
+    test_poprf_evaluate::<CS>(tvs)
+}
+
 fn test_oprf_seed_to_key<CS: CipherSuite>(tvs: &[VOPRFTestVectorParameters]) -> Result<()>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
@@ -336,10 +357,30 @@ where
             .batch_blind_evaluate_finish(&mut rng, blinded_elements.iter(), &prepared_elements)?;
         let messages: Vec<_> = messages.collect();
 
-        for (parameter, message) in parameters.evaluation_element.iter().zip(messages) {
+        for (parameter, message) in parameters.evaluation_element.iter().zip(&messages) {
             assert_eq!(&parameter, &message.serialize().as_slice());
         }
 
+        // The nonce of the proof is hedged, so it only matches the test vectors
+        // when given directly
+        let g = CS::Group::base_elem();
+        verify_proof(
+            g,
+            server.get_public_key(),
+            blinded_elements.iter().map(|element| element.0),
+            messages.iter().map(|element| element.0),
+            &proof,
+            Mode::Voprf,
+        )?;
+        let proof = generate_proof_with_nonce::<CS>(
+            CS::Group::deserialize_scalar(&parameters.proof_random_scalar)?,
+            server.get_private_key(),
+            g,
+            server.get_public_key(),
+            blinded_elements.iter().map(|element| element.0),
+            messages.iter().map(|element| element.0),
+            Mode::Voprf,
+        )?;
         assert_eq!(&parameters.proof, &proof.serialize().as_slice());
     }
     Ok(())
@@ -377,10 +418,31 @@ where
 
         let messages: Vec<_> = messages.collect();
 
-        for (parameter, message) in parameters.evaluation_element.iter().zip(messages) {
+        for (parameter, message) in parameters.evaluation_element.iter().zip(&messages) {
             assert_eq!(&parameter, &message.serialize().as_slice());
         }
 
+        // The nonce of the proof is hedged, so it only matches the test vectors
+        // when given directly
+        let g = CS::Group::base_elem();
+        let tweaked_key = g * &prepared_tweak.0;
+        verify_proof(
+            g,
+            tweaked_key,
+            messages.iter().map(|element| element.0),
+            blinded_elements.iter().map(|element| element.0),
+            &proof,
+            Mode::Poprf,
+        )?;
+        let proof = generate_proof_with_nonce::<CS>(
+            CS::Group::deserialize_scalar(&parameters.proof_random_scalar)?,
+            prepared_tweak.0,
+            g,
+            tweaked_key,
+            messages.iter().map(|element| element.0),
+            blinded_elements.iter().map(|element| element.0),
+            Mode::Poprf,
+        )?;
         assert_eq!(&parameters.proof, &proof.serialize().as_slice());
     }
     Ok(())
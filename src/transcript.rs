@@ -0,0 +1,287 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains a transcript of the messages exchanged in a protocol run
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Digest, Output, OutputSizeUser};
+use generic_array::sequence::Concat;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::GenericArray;
+
+use crate::common::{create_context_string, i2osp_2, i2osp_2_array};
+use crate::{
+    BlindedElement, CipherSuite, ErrorKind, EvaluationElement, Group, Mode, Proof, Result,
+};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+const STR_TRANSCRIPT: [u8; 11] = *b"Transcript-";
+const STR_BLINDED_ELEMENT: [u8; 14] = *b"BlindedElement";
+const STR_EVALUATION_ELEMENT: [u8; 17] = *b"EvaluationElement";
+const STR_PROOF: [u8; 5] = *b"Proof";
+const STR_INFO: [u8; 4] = *b"Info";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// Accumulates the messages of a protocol run and produces a hash binding all
+/// of them, e.g. to channel-bind the run into the key schedule of a
+/// higher-level protocol.
+///
+/// Messages are absorbed with a label and a length prefix, so the resulting
+/// hash depends on their order and boundaries. Both parties have to append the
+/// same messages in the same order to arrive at the same hash.
+///
+/// With the `alloc` feature the transcript also records the canonical byte
+/// string it absorbed, see [`Transcript::as_bytes`], e.g. for audit logs of
+/// regulated deployments. A transcript only contains public messages, never
+/// inputs, blinds or keys, so it can be logged as is.
+#[derive_where(Clone; CS::Hash)]
+#[derive_where(Debug)]
+#[derive_where(skip_inner(Debug))]
+pub struct Transcript<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    hash: CS::Hash,
+    #[cfg(feature = "alloc")]
+    bytes: Vec<u8>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> Transcript<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Starts a new transcript for a protocol run in the given `mode`.
+    pub fn new(mode: Mode) -> Self {
+        // dst = "Transcript-" || contextString
+        let dst = GenericArray::from(STR_TRANSCRIPT).concat(create_context_string::<CS>(mode));
+        let mut transcript = Self {
+            hash: CS::Hash::new(),
+            #[cfg(feature = "alloc")]
+            bytes: Vec::new(),
+        };
+        transcript.update(&i2osp_2_array(&dst));
+        transcript.update(&dst);
+
+        transcript
+    }
+
+    /// Appends a [`BlindedElement`] sent by the client.
+    pub fn append_blinded_element(&mut self, blinded_element: &BlindedElement<CS>) {
+        self.append(
+            &STR_BLINDED_ELEMENT,
+            &[&CS::Group::serialize_elem(blinded_element.0)],
+        );
+    }
+
+    /// Appends an [`EvaluationElement`] sent by the server.
+    pub fn append_evaluation_element(&mut self, evaluation_element: &EvaluationElement<CS>) {
+        self.append(
+            &STR_EVALUATION_ELEMENT,
+            &[&CS::Group::serialize_elem(evaluation_element.0)],
+        );
+    }
+
+    /// Appends a [`Proof`] sent by the server.
+    pub fn append_proof(&mut self, proof: &Proof<CS>) {
+        self.append(
+            &STR_PROOF,
+            &[
+                &CS::Group::serialize_scalar(proof.c_scalar),
+                &CS::Group::serialize_scalar(proof.s_scalar),
+            ],
+        );
+    }
+
+    /// Appends the public `info` used in partially-oblivious mode.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Info`] if the `info` is longer than [`u16::MAX`].
+    pub fn append_info(&mut self, info: &[u8]) -> Result<()> {
+        i2osp_2(info.len()).map_err(|_| ErrorKind::Info)?;
+        self.append(&STR_INFO, &[info]);
+
+        Ok(())
+    }
+
+    /// Returns the hash binding all messages appended so far.
+    pub fn finalize(self) -> Output<CS::Hash> {
+        self.hash.finalize()
+    }
+
+    /// Returns the canonical byte string of all messages appended so far:
+    ///
+    /// ```text
+    /// I2OSP(len(dst), 2) || dst ||
+    /// I2OSP(len(label), 2) || label || I2OSP(len(message), 2) || message || ...
+    /// ```
+    ///
+    /// where `dst = "Transcript-" || contextString`. The hash of
+    /// [`Transcript::finalize`] is the hash of this byte string, so an auditor
+    /// can check a logged byte string against a logged hash with
+    /// [`Transcript::digest`].
+    #[cfg(feature = "alloc")]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the canonical byte string of all messages appended so far and
+    /// the hash binding them, see [`Transcript::as_bytes`].
+    #[cfg(feature = "alloc")]
+    pub fn finalize_with_bytes(self) -> (Vec<u8>, Output<CS::Hash>) {
+        (self.bytes, self.hash.finalize())
+    }
+
+    /// Computes the hash of a canonical byte string returned by
+    /// [`Transcript::as_bytes`], which equals the hash of
+    /// [`Transcript::finalize`] of the same transcript.
+    pub fn digest(bytes: &[u8]) -> Output<CS::Hash> {
+        CS::Hash::digest(bytes)
+    }
+
+    /// Absorbs I2OSP(len(label), 2) || label || I2OSP(len(message), 2) ||
+    /// message, where message is the concatenation of `parts`.
+    fn append(&mut self, label: &[u8], parts: &[&[u8]]) {
+        // Labels are constants and the length of messages is checked by the
+        // callers, so these can't fail.
+        let label_len = i2osp_2(label.len()).unwrap_or_default();
+        let message_len = i2osp_2(parts.iter().map(|part| part.len()).sum()).unwrap_or_default();
+
+        self.update(&label_len);
+        self.update(label);
+        self.update(&message_len);
+        for part in parts {
+            self.update(part);
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.hash.update(data);
+        #[cfg(feature = "alloc")]
+        self.bytes.extend_from_slice(data);
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{PoprfClient, PoprfServer};
+
+    fn transcript_binding<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let info = b"info";
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = PoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let server_result = server
+            .blind_evaluate(&mut rng, &client_blind_result.message, Some(info))
+            .unwrap();
+
+        let run = |info: &[&[u8]]| {
+            let mut transcript = Transcript::<CS>::new(Mode::Poprf);
+            for info in info {
+                transcript.append_info(info).unwrap();
+            }
+            transcript.append_blinded_element(&client_blind_result.message);
+            transcript.append_evaluation_element(&server_result.message);
+            transcript.append_proof(&server_result.proof);
+            transcript.finalize()
+        };
+
+        // Both parties arrive at the same hash
+        assert_eq!(run(&[info]), run(&[info]));
+        // Boundaries between messages are bound
+        assert_ne!(run(&[b"in", b"fo"]), run(&[b"inf", b"o"]));
+
+        // The order of messages is bound
+        let mut transcript = Transcript::<CS>::new(Mode::Poprf);
+        transcript.append_evaluation_element(&server_result.message);
+        transcript.append_blinded_element(&client_blind_result.message);
+        transcript.append_info(info).unwrap();
+        transcript.append_proof(&server_result.proof);
+        assert_ne!(transcript.finalize(), run(&[info]));
+
+        // The mode is bound
+        assert_ne!(
+            Transcript::<CS>::new(Mode::Voprf).finalize(),
+            Transcript::<CS>::new(Mode::Poprf).finalize()
+        );
+
+        // The canonical byte string hashes to the same digest
+        #[cfg(feature = "alloc")]
+        {
+            let transcript = || {
+                let mut transcript = Transcript::<CS>::new(Mode::Poprf);
+                transcript.append_info(info).unwrap();
+                transcript
+            };
+            let bytes = transcript().as_bytes().to_vec();
+            assert_eq!(bytes[..2], [0, 22]);
+            assert_eq!(bytes[2..13], STR_TRANSCRIPT);
+            assert_eq!(
+                bytes[24..],
+                [0, 4, b'I', b'n', b'f', b'o', 0, 4, b'i', b'n', b'f', b'o']
+            );
+            let (logged_bytes, digest) = transcript().finalize_with_bytes();
+            assert_eq!(logged_bytes, bytes);
+            assert_eq!(digest, transcript().finalize());
+            assert_eq!(Transcript::<CS>::digest(&logged_bytes), digest);
+        }
+
+        let mut transcript = Transcript::<CS>::new(Mode::Poprf);
+        assert_eq!(
+            transcript
+                .append_info(&[0; 1 << 16])
+                .map_err(|error| error.kind()),
+            Err(ErrorKind::Info)
+        );
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            transcript_binding::<Ristretto255>();
+        }
+
+        transcript_binding::<NistP256>();
+
+        Ok(())
+    }
+}
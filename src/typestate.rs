@@ -0,0 +1,325 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains a typestate wrapper around the client APIs of all modes
+
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    BlindedElement, CipherSuite, EvaluationElement, Group, OprfClient, PoprfClient,
+    PoprfServerEvaluateResult, Result, VoprfClient, VoprfServerEvaluateResult,
+};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A client of any mode, wrapped so that the protocol step it is currently in
+/// is tracked in its type.
+///
+/// A [`Client`] starts out [`Blinded`], has to receive the server's response to
+/// become [`Evaluated`] and can only then be finalized. Finalization consumes
+/// the client, so a state can't be finalized twice. To persist a [`Blinded`]
+/// client between requests, use [`Client::into_state`] and wrap the
+/// deserialized state again with [`From`].
+#[derive(Debug)]
+pub struct Client<C, S> {
+    client: C,
+    state: S,
+}
+
+/// State of a [`Client`] that has blinded its input and is waiting for the
+/// server's response.
+#[derive(Clone, Copy, Debug)]
+pub struct Blinded;
+
+/// State of a [`Client`] that has received the server's response `R` and can
+/// be finalized.
+#[derive(Debug)]
+pub struct Evaluated<R>(R);
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<C> From<C> for Client<C, Blinded> {
+    fn from(client: C) -> Self {
+        Self {
+            client,
+            state: Blinded,
+        }
+    }
+}
+
+impl<C> Client<C, Blinded> {
+    /// Returns the underlying client state, e.g. to serialize it.
+    pub fn into_state(self) -> C {
+        self.client
+    }
+}
+
+impl<CS: CipherSuite> Client<OprfClient<CS>, Blinded>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Computes the first step of the protocol in base mode, see
+    /// [`OprfClient::blind`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty or
+    /// longer then [`u16::MAX`].
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<CS>)> {
+        let result = OprfClient::blind(input, blinding_factor_rng)?;
+        Ok((result.state.into(), result.message))
+    }
+
+    /// Stores the server's response, making the client ready to be finalized.
+    pub fn receive(
+        self,
+        message: EvaluationElement<CS>,
+    ) -> Client<OprfClient<CS>, Evaluated<EvaluationElement<CS>>> {
+        Client {
+            client: self.client,
+            state: Evaluated(message),
+        }
+    }
+}
+
+impl<CS: CipherSuite> Client<OprfClient<CS>, Evaluated<EvaluationElement<CS>>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Computes the last step of the protocol in base mode, see
+    /// [`OprfClient::finalize`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty or
+    /// longer then [`u16::MAX`].
+    pub fn finalize(self, input: &[u8]) -> Result<Output<CS::FinalizeHash>> {
+        self.client.finalize(input, &self.state.0)
+    }
+}
+
+impl<CS: CipherSuite> Client<VoprfClient<CS>, Blinded>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Computes the first step of the protocol in verifiable mode, see
+    /// [`VoprfClient::blind`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty or
+    /// longer then [`u16::MAX`].
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<CS>)> {
+        let result = VoprfClient::blind(input, blinding_factor_rng)?;
+        Ok((result.state.into(), result.message))
+    }
+
+    /// Stores the server's response, making the client ready to be finalized.
+    pub fn receive(
+        self,
+        result: VoprfServerEvaluateResult<CS>,
+    ) -> Client<VoprfClient<CS>, Evaluated<VoprfServerEvaluateResult<CS>>> {
+        Client {
+            client: self.client,
+            state: Evaluated(result),
+        }
+    }
+}
+
+impl<CS: CipherSuite> Client<VoprfClient<CS>, Evaluated<VoprfServerEvaluateResult<CS>>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Computes the last step of the protocol in verifiable mode, see
+    /// [`VoprfClient::finalize`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty
+    ///   or longer then [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`](crate::ErrorKind::ProofVerification)
+    ///   if the proof failed to verify.
+    pub fn finalize(
+        self,
+        input: &[u8],
+        pk: <CS::Group as Group>::Elem,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let VoprfServerEvaluateResult { message, proof } = &self.state.0;
+        self.client.finalize(input, message, proof, pk)
+    }
+}
+
+impl<CS: CipherSuite> Client<PoprfClient<CS>, Blinded>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Computes the first step of the protocol in partially-oblivious mode,
+    /// see [`PoprfClient::blind`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty or
+    /// longer than [`u16::MAX`].
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<CS>)> {
+        let result = PoprfClient::blind(input, blinding_factor_rng)?;
+        Ok((result.state.into(), result.message))
+    }
+
+    /// Stores the server's response, making the client ready to be finalized.
+    pub fn receive(
+        self,
+        result: PoprfServerEvaluateResult<CS>,
+    ) -> Client<PoprfClient<CS>, Evaluated<PoprfServerEvaluateResult<CS>>> {
+        Client {
+            client: self.client,
+            state: Evaluated(result),
+        }
+    }
+}
+
+impl<CS: CipherSuite> Client<PoprfClient<CS>, Evaluated<PoprfServerEvaluateResult<CS>>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Computes the last step of the protocol in partially-oblivious mode, see
+    /// [`PoprfClient::finalize`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`](crate::ErrorKind::Info) if the `info` is longer
+    ///   than `u16::MAX`.
+    /// - [`ErrorKind::Input`](crate::ErrorKind::Input) if the `input` is empty
+    ///   or longer than [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    ///   fails and can't be completed.
+    /// - [`ErrorKind::ProofVerification`](crate::ErrorKind::ProofVerification)
+    ///   if the proof failed to verify.
+    pub fn finalize(
+        self,
+        input: &[u8],
+        pk: <CS::Group as Group>::Elem,
+        info: Option<&[u8]>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        let PoprfServerEvaluateResult { message, proof } = &self.state.0;
+        self.client.finalize(input, message, proof, pk, info)
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{OprfServer, PoprfServer, VoprfServer};
+
+    fn oprf_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let (client, message) = Client::<OprfClient<CS>, _>::blind(input, &mut rng).unwrap();
+        let client = client.receive(server.blind_evaluate(&message));
+        let output = client.finalize(input).unwrap();
+        assert_eq!(output, server.evaluate(input).unwrap());
+    }
+
+    fn voprf_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let (client, message) = Client::<VoprfClient<CS>, _>::blind(input, &mut rng).unwrap();
+        let client = client.receive(server.blind_evaluate(&mut rng, &message));
+        let output = client.finalize(input, server.get_public_key()).unwrap();
+        assert_eq!(output, server.evaluate(input).unwrap());
+    }
+
+    fn poprf_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let info = Some(b"info".as_slice());
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let (client, message) = Client::<PoprfClient<CS>, _>::blind(input, &mut rng).unwrap();
+        let client = client.receive(server.blind_evaluate(&mut rng, &message, info).unwrap());
+        let output = client
+            .finalize(input, server.get_public_key(), info)
+            .unwrap();
+        assert_eq!(output, server.evaluate(input, info).unwrap());
+    }
+
+    fn persisted_state<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let (client, message) = Client::<OprfClient<CS>, _>::blind(input, &mut rng).unwrap();
+        let bytes = client.into_state().serialize();
+
+        let client = Client::from(OprfClient::<CS>::deserialize(&bytes).unwrap());
+        let client = client.receive(server.blind_evaluate(&message));
+        let output = client.finalize(input).unwrap();
+        assert_eq!(output, server.evaluate(input).unwrap());
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            oprf_retrieval::<Ristretto255>();
+            voprf_retrieval::<Ristretto255>();
+            poprf_retrieval::<Ristretto255>();
+            persisted_state::<Ristretto255>();
+        }
+
+        oprf_retrieval::<NistP256>();
+        voprf_retrieval::<NistP256>();
+        poprf_retrieval::<NistP256>();
+        persisted_state::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the usage counters and rotation policies of server keys
+
+use core::fmt::{self, Debug, Formatter};
+use core::mem;
+
+use digest::core_api::BlockSizeUser;
+use digest::OutputSizeUser;
+use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    BlindedElement, CipherSuite, EvaluationElement, Group, OprfServer, PoprfServer,
+    PoprfServerEvaluateResult, Result, VoprfServer, VoprfServerEvaluateResult,
+};
+#[cfg(feature = "alloc")]
+use crate::{PoprfServerBatchEvaluateResult, VoprfServerBatchEvaluateResult};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// The usage of a server key, as counted by a [`CountedServer`]. Only
+/// successful evaluations are counted, all counters saturate at [`u64::MAX`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct KeyUsage {
+    /// The number of blinded elements evaluated.
+    pub evaluations: u64,
+    /// The number of batch evaluations, single evaluations aren't included.
+    pub batches: u64,
+    /// The number of bytes of blinded elements evaluated.
+    pub bytes: u64,
+    /// The timestamp the key was taken into use at, e.g. in seconds since the
+    /// Unix epoch.
+    pub created_at: u64,
+}
+
+/// Decides when the key of a [`CountedServer`] is due for rotation.
+pub trait RotationPolicy {
+    /// Returns `true` if a key with `usage` should be rotated at the timestamp
+    /// `now`, in the same unit as [`KeyUsage::created_at`].
+    fn should_rotate(&self, usage: &KeyUsage, now: u64) -> bool;
+}
+
+/// A [`RotationPolicy`] that rotates after a number of evaluations or an age of
+/// the key, whichever comes first.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct UsageLimits {
+    /// The number of evaluations after which the key is rotated, if any.
+    pub max_evaluations: Option<u64>,
+    /// The age after which the key is rotated, if any.
+    pub max_age: Option<u64>,
+}
+
+/// A server that counts its evaluations in a [`KeyUsage`] and consults a
+/// [`RotationPolicy`] about them.
+pub struct CountedServer<'a, S> {
+    server: S,
+    usage: KeyUsage,
+    policy: &'a dyn RotationPolicy,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<'a, S> CountedServer<'a, S> {
+    /// Produces a new [`CountedServer`] whose key was taken into use at
+    /// `created_at`, and which consults `policy` about rotating it.
+    pub fn new(server: S, created_at: u64, policy: &'a dyn RotationPolicy) -> Self {
+        Self {
+            server,
+            usage: KeyUsage {
+                created_at,
+                ..KeyUsage::default()
+            },
+            policy,
+        }
+    }
+
+    /// Returns the usage of the key so far, e.g. to export it as metrics.
+    pub fn usage(&self) -> KeyUsage {
+        self.usage
+    }
+
+    /// Returns `true` if the [`RotationPolicy`] considers the key due for
+    /// rotation at the timestamp `now`.
+    pub fn should_rotate(&self, now: u64) -> bool {
+        let rotate = self.policy.should_rotate(&self.usage, now);
+
+        if rotate {
+            debug_event!(
+                evaluations = self.usage.evaluations,
+                batches = self.usage.batches,
+                bytes = self.usage.bytes,
+                created_at = self.usage.created_at,
+                "key rotation due",
+            );
+        }
+
+        rotate
+    }
+
+    /// Replaces the server by one with a new key taken into use at `now`,
+    /// resetting the usage. Returns the previous server, e.g. to keep
+    /// redeeming its tokens for a while.
+    pub fn rotate(&mut self, server: S, now: u64) -> S {
+        self.usage = KeyUsage {
+            created_at: now,
+            ..KeyUsage::default()
+        };
+
+        mem::replace(&mut self.server, server)
+    }
+
+    /// Returns the wrapped server, e.g. to evaluate inputs directly.
+    pub fn server(&self) -> &S {
+        &self.server
+    }
+
+    /// Returns the wrapped server.
+    pub fn into_server(self) -> S {
+        self.server
+    }
+
+    fn count<G: Group>(&mut self, batch: Option<usize>) {
+        let evaluations = batch.map_or(1, |len| u64::try_from(len).unwrap_or(u64::MAX));
+
+        self.usage.evaluations = self.usage.evaluations.saturating_add(evaluations);
+        self.usage.bytes = self
+            .usage
+            .bytes
+            .saturating_add(evaluations.saturating_mul(G::ElemLen::U64));
+
+        if batch.is_some() {
+            self.usage.batches = self.usage.batches.saturating_add(1);
+        }
+    }
+}
+
+impl<'a, CS: CipherSuite> CountedServer<'a, OprfServer<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`OprfServer::blind_evaluate`], counting the evaluation.
+    pub fn blind_evaluate(
+        &mut self,
+        blinded_element: &BlindedElement<CS>,
+    ) -> EvaluationElement<CS> {
+        let message = self.server.blind_evaluate(blinded_element);
+        self.count::<CS::Group>(None);
+
+        message
+    }
+}
+
+impl<'a, CS: CipherSuite> CountedServer<'a, VoprfServer<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`VoprfServer::try_blind_evaluate`], counting the evaluation.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    /// fails and can't be completed.
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>> {
+        let result = self.server.try_blind_evaluate(rng, blinded_element)?;
+        self.count::<CS::Group>(None);
+
+        Ok(result)
+    }
+
+    /// Like [`VoprfServer::batch_blind_evaluate`], counting the evaluations.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Batch`](crate::ErrorKind::Batch) if the number of
+    /// `blinded_elements` is longer then [`u16::MAX`]
+    #[cfg(feature = "alloc")]
+    pub fn batch_blind_evaluate<'b, R: RngCore + CryptoRng, I>(
+        &mut self,
+        rng: &mut R,
+        blinded_elements: &'b I,
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>>
+    where
+        CS: 'b,
+        &'b I: IntoIterator<Item = &'b BlindedElement<CS>>,
+        <&'b I as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        let result = self.server.batch_blind_evaluate(rng, blinded_elements)?;
+        self.count::<CS::Group>(Some(result.messages.len()));
+
+        Ok(result)
+    }
+}
+
+impl<'a, CS: CipherSuite> CountedServer<'a, PoprfServer<CS>>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Like [`PoprfServer::blind_evaluate`], counting the evaluation.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`](crate::ErrorKind::Info) if the `info` is longer
+    ///   than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    ///   fails and can't be completed.
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        info: Option<&[u8]>,
+    ) -> Result<PoprfServerEvaluateResult<CS>> {
+        let result = self.server.blind_evaluate(rng, blinded_element, info)?;
+        self.count::<CS::Group>(None);
+
+        Ok(result)
+    }
+
+    /// Like [`PoprfServer::batch_blind_evaluate`], counting the evaluations.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`](crate::ErrorKind::Info) if the `info` is longer
+    ///   than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`](crate::ErrorKind::Protocol) if the protocol
+    ///   fails and can't be completed.
+    #[cfg(feature = "alloc")]
+    pub fn batch_blind_evaluate<'b, R: RngCore + CryptoRng, IE>(
+        &mut self,
+        rng: &mut R,
+        blinded_elements: &'b IE,
+        info: Option<&[u8]>,
+    ) -> Result<PoprfServerBatchEvaluateResult<CS>>
+    where
+        CS: 'b,
+        &'b IE: 'b + IntoIterator<Item = &'b BlindedElement<CS>>,
+        <&'b IE as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        let result = self
+            .server
+            .batch_blind_evaluate(rng, blinded_elements, info)?;
+        self.count::<CS::Group>(Some(result.messages.len()));
+
+        Ok(result)
+    }
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+impl RotationPolicy for UsageLimits {
+    fn should_rotate(&self, usage: &KeyUsage, now: u64) -> bool {
+        self.max_evaluations
+            .map_or(false, |max| usage.evaluations >= max)
+            || self
+                .max_age
+                .map_or(false, |max| now.saturating_sub(usage.created_at) >= max)
+    }
+}
+
+impl<F: Fn(&KeyUsage, u64) -> bool> RotationPolicy for F {
+    fn should_rotate(&self, usage: &KeyUsage, now: u64) -> bool {
+        self(usage, now)
+    }
+}
+
+impl<S: Debug> Debug for CountedServer<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountedServer")
+            .field("server", &self.server)
+            .field("usage", &self.usage)
+            .finish_non_exhaustive()
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "alloc")]
+    use alloc::vec;
+
+    use rand::rngs::OsRng;
+    use subtle::ConstantTimeEq;
+
+    use super::*;
+    use crate::{OprfClient, PoprfClient, VoprfClient};
+
+    fn counted_evaluations<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let limits = UsageLimits {
+            max_evaluations: Some(3),
+            max_age: Some(100),
+        };
+
+        let mut server = CountedServer::new(OprfServer::<CS>::new(&mut rng).unwrap(), 10, &limits);
+        let client_blind_result = OprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        server.blind_evaluate(&client_blind_result.message);
+        assert!(!server.should_rotate(10));
+        assert!(server.should_rotate(110));
+
+        let mut server = CountedServer::new(VoprfServer::<CS>::new(&mut rng).unwrap(), 10, &limits);
+        let client_blind_result = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        server
+            .blind_evaluate(&mut rng, &client_blind_result.message)
+            .unwrap();
+        #[cfg(feature = "alloc")]
+        {
+            let messages = vec![client_blind_result.message.clone(); 2];
+            server.batch_blind_evaluate(&mut rng, &messages).unwrap();
+            assert_eq!(
+                server.usage(),
+                KeyUsage {
+                    evaluations: 3,
+                    batches: 1,
+                    bytes: 3 * <CS::Group as Group>::ElemLen::U64,
+                    created_at: 10,
+                }
+            );
+            assert!(server.should_rotate(10));
+        }
+
+        let next = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let previous = server.rotate(next, 20);
+        assert_eq!(server.usage().evaluations, 0);
+        assert_eq!(server.usage().created_at, 20);
+        assert!(!server.should_rotate(20));
+        assert!(!bool::from(
+            server
+                .server()
+                .get_public_key()
+                .ct_eq(&previous.get_public_key())
+        ));
+
+        // Failed evaluations aren't counted
+        let policy = |usage: &KeyUsage, _: u64| usage.bytes > 0;
+        let mut server = CountedServer::new(PoprfServer::<CS>::new(&mut rng).unwrap(), 0, &policy);
+        let client_blind_result = PoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let too_long = [0; u16::MAX as usize + 1];
+        assert!(server
+            .blind_evaluate(&mut rng, &client_blind_result.message, Some(&too_long))
+            .is_err());
+        assert!(!server.should_rotate(0));
+        server
+            .blind_evaluate(&mut rng, &client_blind_result.message, Some(b"info"))
+            .unwrap();
+        assert!(server.should_rotate(0));
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            counted_evaluations::<Ristretto255>();
+        }
+
+        counted_evaluations::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,660 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Contains the precomputation of clients verifying many proofs of one key
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use derive_where::derive_where;
+use digest::core_api::BlockSizeUser;
+use digest::{Output, OutputSizeUser};
+#[cfg(feature = "alloc")]
+use generic_array::typenum::Unsigned;
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
+use generic_array::GenericArray;
+#[cfg(feature = "alloc")]
+use subtle::ConstantTimeEq;
+
+use crate::chunked_proof::BatchProof;
+use crate::{kernel, CipherSuite, Error, ErrorKind, Group, Mode, Operation, Proof, Result};
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// The setup of verifying proofs from one issuer key, e.g. for a client
+/// finalizing many batches of the same issuer within a session.
+///
+/// Verifying a proof requires the serialized public key and a seed hashed
+/// from it, which don't depend on the batch; in the POPRF mode also the
+/// tweaked key, which requires a scalar multiplication and a hash to scalar.
+/// The context computes these once and is passed to
+/// [`VoprfClient::finalize_with_context`](crate::VoprfClient::finalize_with_context)
+/// or
+/// [`PoprfClient::finalize_with_context`](crate::PoprfClient::finalize_with_context)
+/// and their batch variants instead of the public key. The verification itself
+/// uses [`Group::vartime_double_mul_base`] for the multiplications with the
+/// key and [`Group::vartime_double_mul`] for the ones with the composites of
+/// the batch, which groups like Ristretto255 and Secp256k1 speed up with
+/// variable-time arithmetic.
+///
+/// With the `alloc` feature, [`precompute`](Self::precompute) additionally
+/// builds tables of multiples of the base element and the key, which replace
+/// the multiplications with them by additions, and computes the composites of
+/// the batch with one multi-scalar multiplication. The unblinding of every
+/// output stays a constant-time scalar multiplication, so finalizing gains
+/// less than verifying: `cargo bench --bench verifier` compares the variants,
+/// e.g. a batch of 16 finalizes 1.5 times as fast with Ristretto255 and 1.6
+/// times with P-256.
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Elem)]
+pub struct VerifierContext<'a, CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    mode: Mode,
+    /// The public key, or the tweaked key in the POPRF mode
+    pub(crate) key: <CS::Group as Group>::Elem,
+    key_bytes: GenericArray<u8, <CS::Group as Group>::ElemLen>,
+    seed: Output<CS::Hash>,
+    pub(crate) info: &'a [u8],
+    #[cfg(feature = "alloc")]
+    tables: Option<Tables<CS::Group>>,
+}
+
+/// The precomputed tables of a [`VerifierContext`].
+#[cfg(feature = "alloc")]
+#[derive_where(Clone)]
+#[derive_where(Debug; G::Elem)]
+struct Tables<G: Group> {
+    base: Table<G>,
+    key: Table<G>,
+    /// If scalars are serialized in little-endian instead of big-endian
+    little_endian: bool,
+}
+
+/// The multiples 1 to 15 of an element for every 4-bit window of a scalar, the
+/// least significant first, so multiplying the element with a scalar takes at
+/// most one addition per window and no doubling.
+#[cfg(feature = "alloc")]
+#[derive_where(Clone)]
+#[derive_where(Debug; G::Elem)]
+struct Table<G: Group>(Vec<[G::Elem; 15]>);
+
+/// A [`Proof`] that is verified with the precomputation of a
+/// [`VerifierContext`].
+pub(crate) struct ContextProof<'c, 'a, CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    pub(crate) context: &'c VerifierContext<'a, CS>,
+    pub(crate) proof: &'c Proof<CS>,
+}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<'a, CS: CipherSuite> VerifierContext<'a, CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// Prepares the verification of proofs from the verifiable server with
+    /// the public key `pk`.
+    pub fn voprf(pk: <CS::Group as Group>::Elem) -> Self {
+        Self::new(pk, Mode::Voprf, &[])
+    }
+
+    /// Prepares the verification of proofs from the partially-oblivious server
+    /// with the public key `pk` with the public `info`.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Info`] if the `info` is longer than `u16::MAX`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn poprf(pk: <CS::Group as Group>::Elem, info: Option<&'a [u8]>) -> Result<Self> {
+        let info = info.unwrap_or_default();
+        let tweaked_key = crate::poprf::compute_tweaked_key::<CS>(pk, Some(info))?;
+
+        Ok(Self::new(tweaked_key, Mode::Poprf, info))
+    }
+
+    fn new(key: <CS::Group as Group>::Elem, mode: Mode, info: &'a [u8]) -> Self {
+        let key_bytes = CS::Group::serialize_elem(key);
+        let seed = kernel::composite_seed::<CS>(&key_bytes, mode);
+
+        Self {
+            mode,
+            key,
+            key_bytes,
+            seed,
+            info,
+            #[cfg(feature = "alloc")]
+            tables: None,
+        }
+    }
+
+    /// Precomputes tables of multiples of the base element and the key, so
+    /// verifying a proof multiplies neither of them, and verifies the
+    /// composites of the batch with a variable-time multi-scalar
+    /// multiplication instead of one scalar multiplication per element. This
+    /// is worth it for verifiers of many proofs of one key, e.g. token
+    /// redeemers: the tables hold 1920 elements for 32-byte scalars.
+    ///
+    /// Groups whose scalars aren't serialized as integers, in either byte
+    /// order, can't be split into windows and keep verifying without tables.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn precompute(mut self) -> Self {
+        self.tables = Tables::new(self.key);
+        self
+    }
+
+    /// Returns the public `info` the context was prepared for, which is empty
+    /// in the VOPRF mode.
+    pub fn info(&self) -> &'a [u8] {
+        self.info
+    }
+
+    /// Checks that the context was prepared for `mode`.
+    ///
+    /// Can only fail with [`ErrorKind::Protocol`].
+    pub(crate) fn check_mode(&self, mode: Mode) -> Result<()> {
+        if self.mode == mode {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::Protocol, Operation::Finalize))
+        }
+    }
+
+    /// Verifies that `ds` are `cs` multiplied by the discrete logarithm of the
+    /// key of the context.
+    ///
+    /// Can only fail with [`ErrorKind::ProofVerification`] or
+    /// [`ErrorKind::Batch`].
+    pub(crate) fn verify(
+        &self,
+        cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        proof: &Proof<CS>,
+    ) -> Result<()> {
+        debug_span!("verify_proof", mode = ?self.mode, batch_size = cs.len());
+
+        #[cfg(feature = "alloc")]
+        let result = match &self.tables {
+            Some(tables) => self.verify_with_tables(tables, cs, ds, proof),
+            None => self.verify_without_tables(cs, ds, proof),
+        };
+        #[cfg(not(feature = "alloc"))]
+        let result = self.verify_without_tables(cs, ds, proof);
+
+        result.map_err(|error| {
+            if error.kind() == ErrorKind::ProofVerification {
+                debug_event!("proof failed to verify");
+                count!("voprf_proof_verification_failures_total", 1, "mode" => self.mode.label());
+            }
+
+            error
+        })
+    }
+
+    fn verify_without_tables(
+        &self,
+        cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        proof: &Proof<CS>,
+    ) -> Result<()> {
+        kernel::verify_proof_with_seed(
+            CS::Group::base_elem(),
+            self.key,
+            &self.key_bytes,
+            &self.seed,
+            cs,
+            ds,
+            proof,
+            self.mode,
+        )
+    }
+
+    #[cfg(feature = "alloc")]
+    fn verify_with_tables(
+        &self,
+        tables: &Tables<CS::Group>,
+        cs: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        ds: impl ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        proof: &Proof<CS>,
+    ) -> Result<()> {
+        let mut scalars = Vec::with_capacity(cs.len());
+        let mut c_elems = Vec::with_capacity(cs.len());
+        let mut d_elems = Vec::with_capacity(cs.len());
+
+        for result in kernel::composite_scalars::<CS, _, _>(&self.seed, cs, ds, self.mode)
+            .map_err(|error| error.with_operation(Operation::VerifyProof))?
+        {
+            let (scalar, c, d) =
+                result.map_err(|error| error.with_operation(Operation::VerifyProof))?;
+            scalars.push(scalar);
+            c_elems.push(c);
+            d_elems.push(d);
+        }
+
+        let m = tables.multi_mul(&scalars, &c_elems);
+        let z = tables.multi_mul(&scalars, &d_elems);
+        let t2 = tables.base.mul(tables.digits(proof.s_scalar))
+            + &tables.key.mul(tables.digits(proof.c_scalar));
+        let t3 = CS::Group::vartime_double_mul(proof.s_scalar, m, proof.c_scalar, z);
+
+        kernel::check_challenge(&self.key_bytes, m, z, t2, t3, proof, self.mode)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<G: Group> Tables<G> {
+    /// How many elements [`multi_mul`](Self::multi_mul) keeps the multiples of
+    /// at once.
+    const CHUNK_SIZE: usize = 64;
+
+    /// Returns `None` if the scalars of the group aren't serialized as
+    /// integers.
+    fn new(key: G::Elem) -> Option<Self> {
+        let mut first = GenericArray::<u8, G::ScalarLen>::default();
+        *first.first_mut()? = 1;
+        let first = G::deserialize_scalar(&first).ok()?;
+        let mut last = GenericArray::<u8, G::ScalarLen>::default();
+        *last.last_mut()? = 1;
+        let last = G::deserialize_scalar(&last).ok()?;
+
+        let tables = Self {
+            base: Table::new(G::base_elem()),
+            key: Table::new(key),
+            // Only the least significant byte set to one is the scalar one
+            little_endian: (first * &last).ct_eq(&last).into(),
+        };
+
+        // A scalar with digits in all windows is only multiplied correctly if
+        // its serialization is an integer in the detected byte order
+        let mut check = first + &last;
+
+        for _ in 0..4 {
+            check = check * &check;
+        }
+
+        if tables
+            .key
+            .mul(tables.digits(check))
+            .ct_eq(&(key * &check))
+            .into()
+        {
+            Some(tables)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the 4-bit windows of the `scalar`, the least significant first.
+    fn digits(&self, scalar: G::Scalar) -> impl Iterator<Item = u8> {
+        let mut bytes = G::serialize_scalar(scalar);
+
+        if !self.little_endian {
+            bytes.reverse();
+        }
+
+        bytes.into_iter().flat_map(|byte| [byte & 0xF, byte >> 4])
+    }
+
+    /// Computes the sum of the `elems` multiplied with the public `scalars` in
+    /// variable time, sharing the doublings between all of them.
+    fn multi_mul(&self, scalars: &[G::Scalar], elems: &[G::Elem]) -> G::Elem {
+        // Nothing is shared with a single element, which the group multiplies
+        // faster
+        if let ([scalar], [elem]) = (scalars, elems) {
+            return *elem * scalar;
+        }
+
+        let mut result = G::identity_elem();
+
+        for (scalars, elems) in scalars
+            .chunks(Self::CHUNK_SIZE)
+            .zip(elems.chunks(Self::CHUNK_SIZE))
+        {
+            let multiples: Vec<_> = elems.iter().map(|elem| multiples::<G>(*elem)).collect();
+            let digits: Vec<Vec<_>> = scalars
+                .iter()
+                .map(|scalar| self.digits(*scalar).collect())
+                .collect();
+            // The most significant window first
+            let mut digits: Vec<_> = digits.iter().map(|digits| digits.iter().rev()).collect();
+            let mut sum = G::identity_elem();
+
+            for _ in 0..G::ScalarLen::USIZE * 2 {
+                for _ in 0..4 {
+                    sum = sum + &sum;
+                }
+
+                for (multiples, digits) in multiples.iter().zip(&mut digits) {
+                    if let Some(multiple) = digits
+                        .next()
+                        .and_then(|digit| usize::from(*digit).checked_sub(1))
+                        .and_then(|digit| multiples.get(digit))
+                    {
+                        sum = sum + multiple;
+                    }
+                }
+            }
+
+            result = result + &sum;
+        }
+
+        result
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<G: Group> Table<G> {
+    fn new(elem: G::Elem) -> Self {
+        let mut windows = Vec::with_capacity(G::ScalarLen::USIZE * 2);
+        let mut weight = elem;
+
+        for _ in 0..G::ScalarLen::USIZE * 2 {
+            let multiples = multiples::<G>(weight);
+            // 16 times the weight of this window
+            weight = multiples[14] + &weight;
+            windows.push(multiples);
+        }
+
+        Self(windows)
+    }
+
+    /// Multiplies the element of the table with the public scalar of the
+    /// `digits` in variable time.
+    fn mul(&self, digits: impl Iterator<Item = u8>) -> G::Elem {
+        let mut result = G::identity_elem();
+
+        for (multiples, digit) in self.0.iter().zip(digits) {
+            if let Some(multiple) = usize::from(digit)
+                .checked_sub(1)
+                .and_then(|digit| multiples.get(digit))
+            {
+                result = result + multiple;
+            }
+        }
+
+        result
+    }
+}
+
+/// Returns `elem` multiplied with 1 to 15.
+#[cfg(feature = "alloc")]
+fn multiples<G: Group>(elem: G::Elem) -> [G::Elem; 15] {
+    let mut multiples = [elem; 15];
+    let mut sum = elem;
+
+    for multiple in multiples.iter_mut().skip(1) {
+        sum = sum + &elem;
+        *multiple = sum;
+    }
+
+    multiples
+}
+
+///////////////////////////
+// Trait Implementations //
+// ===================== //
+///////////////////////////
+
+/// Ignores `a`, `b` and `mode`, which are the ones of the context.
+impl<CS: CipherSuite> BatchProof<CS> for ContextProof<'_, '_, CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn verify_batch<IC, ID>(
+        &self,
+        _: <CS::Group as Group>::Elem,
+        _: <CS::Group as Group>::Elem,
+        cs: IC,
+        ds: ID,
+        _: Mode,
+    ) -> Result<()>
+    where
+        IC: ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+        ID: ExactSizeIterator<Item = <CS::Group as Group>::Elem>,
+    {
+        self.context.verify(cs, ds, self.proof)
+    }
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{PoprfClient, PoprfServer, VoprfClient, VoprfServer};
+
+    /// Returns the `context` and, with the `alloc` feature, the `context` with
+    /// precomputed tables.
+    fn with_tables<'a, CS: CipherSuite>(
+        context: &VerifierContext<'a, CS>,
+    ) -> Vec<VerifierContext<'a, CS>>
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        #[cfg(feature = "alloc")]
+        return vec![context.clone(), context.clone().precompute()];
+        #[cfg(not(feature = "alloc"))]
+        vec![context.clone()]
+    }
+
+    fn verify_with_context<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let input = b"input";
+        let info = b"info";
+
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let context = VerifierContext::voprf(server.get_public_key());
+        let contexts = with_tables(&context);
+
+        // Every context verifies more than one proof
+        for context in contexts.iter().chain(&contexts) {
+            let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let result = server.blind_evaluate(&mut rng, &client_blind_result.message);
+            let output = client_blind_result
+                .state
+                .finalize_with_context(input, &result.message, &result.proof, context)
+                .unwrap();
+            assert_eq!(
+                output,
+                client_blind_result
+                    .state
+                    .finalize(
+                        input,
+                        &result.message,
+                        &result.proof,
+                        server.get_public_key()
+                    )
+                    .unwrap()
+            );
+        }
+
+        // A batch is verified like with the public key
+        let blind_results = [
+            VoprfClient::<CS>::blind(input, &mut rng).unwrap(),
+            VoprfClient::<CS>::blind(input, &mut rng).unwrap(),
+            VoprfClient::<CS>::blind(input, &mut rng).unwrap(),
+        ];
+        let clients = blind_results.clone().map(|result| result.state);
+        let messages = blind_results.map(|result| result.message);
+        let result = server
+            .batch_blind_evaluate_array(&mut rng, &messages)
+            .unwrap();
+        let inputs = [input; 3];
+        let outputs: Vec<_> = VoprfClient::batch_finalize(
+            &inputs,
+            &clients,
+            &result.messages,
+            &result.proof,
+            server.get_public_key(),
+        )
+        .unwrap()
+        .collect();
+        for context in &contexts {
+            let context_outputs: Vec<_> = VoprfClient::batch_finalize_with_context(
+                &inputs,
+                &clients,
+                &result.messages,
+                &result.proof,
+                context,
+            )
+            .unwrap()
+            .collect();
+            assert_eq!(outputs, context_outputs);
+        }
+
+        // The proof doesn't verify against another key
+        let other = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let result = other.blind_evaluate(&mut rng, &client_blind_result.message);
+        for context in &contexts {
+            assert_eq!(
+                client_blind_result
+                    .state
+                    .finalize_with_context(input, &result.message, &result.proof, context)
+                    .map(|_| ()),
+                Err(Error::new(
+                    ErrorKind::ProofVerification,
+                    Operation::VerifyProof
+                ))
+            );
+        }
+
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let context = VerifierContext::poprf(server.get_public_key(), Some(info)).unwrap();
+        assert_eq!(context.info(), info);
+        let contexts = with_tables(&context);
+
+        for context in &contexts {
+            let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let result = server
+                .blind_evaluate(&mut rng, &client_blind_result.message, Some(info))
+                .unwrap();
+            let output = client_blind_result
+                .state
+                .finalize_with_context(input, &result.message, &result.proof, context)
+                .unwrap();
+            assert_eq!(output, server.evaluate(input, Some(info)).unwrap());
+        }
+
+        // A context of the wrong mode is rejected
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let voprf_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let result = voprf_server.blind_evaluate(&mut rng, &client_blind_result.message);
+        assert_eq!(
+            client_blind_result
+                .state
+                .finalize_with_context(input, &result.message, &result.proof, &context)
+                .map(|_| ()),
+            Err(Error::new(ErrorKind::Protocol, Operation::Finalize))
+        );
+    }
+
+    // Checks the multiplications with the tables against the ones of the group
+    #[cfg(feature = "alloc")]
+    fn tables<G: Group>() {
+        let mut rng = OsRng;
+        let key = G::base_elem() * &G::random_scalar(&mut rng);
+        let tables = Tables::<G>::new(key).unwrap();
+
+        for scalar in [G::random_scalar(&mut rng), G::zero_scalar()] {
+            assert!(bool::from(
+                tables
+                    .base
+                    .mul(tables.digits(scalar))
+                    .ct_eq(&(G::base_elem() * &scalar))
+            ));
+            assert!(bool::from(
+                tables
+                    .key
+                    .mul(tables.digits(scalar))
+                    .ct_eq(&(key * &scalar))
+            ));
+        }
+
+        // More elements than fit into one chunk, with the identity and a zero
+        // scalar
+        let mut scalars: Vec<_> = (0..Tables::<G>::CHUNK_SIZE + 6)
+            .map(|_| G::random_scalar(&mut rng))
+            .collect();
+        let mut elems: Vec<_> = scalars
+            .iter()
+            .map(|_| G::base_elem() * &G::random_scalar(&mut rng))
+            .collect();
+        scalars[1] = G::zero_scalar();
+        elems[2] = G::identity_elem();
+
+        for len in [0, 1, 2, scalars.len()] {
+            let expected = scalars[..len]
+                .iter()
+                .zip(&elems[..len])
+                .fold(G::identity_elem(), |sum, (scalar, elem)| {
+                    sum + &(*elem * scalar)
+                });
+            assert!(bool::from(
+                tables
+                    .multi_mul(&scalars[..len], &elems[..len])
+                    .ct_eq(&expected)
+            ));
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tables() {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        tables::<crate::Ristretto255>();
+        tables::<NistP256>();
+        #[cfg(feature = "secp256k1")]
+        tables::<crate::Secp256k1>();
+        #[cfg(feature = "toy-group")]
+        tables::<crate::ToyGroup>();
+    }
+
+    #[test]
+    fn test_functionality() -> Result<()> {
+        use p256::NistP256;
+
+        #[cfg(feature = "ristretto255")]
+        {
+            use crate::Ristretto255;
+
+            verify_with_context::<Ristretto255>();
+        }
+
+        verify_with_context::<NistP256>();
+
+        Ok(())
+    }
+}
@@ -9,23 +9,32 @@
 
 #[cfg(feature = "alloc")]
 use alloc::vec::Vec;
-use core::iter::{self, Map, Repeat, Zip};
+use core::iter::{self, Enumerate, Map, Zip};
+#[cfg(feature = "alloc")]
+use core::ops::Range;
 
 use derive_where::derive_where;
 use digest::core_api::BlockSizeUser;
-use digest::{Digest, Output, OutputSizeUser};
-use generic_array::typenum::{IsLess, IsLessOrEqual, Unsigned, U256};
+use digest::{Output, OutputSizeUser};
+use generic_array::typenum::{IsLess, IsLessOrEqual, U256};
 use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
+use zeroize::Zeroize;
 
 use crate::common::{
-    derive_keypair, deterministic_blind_unchecked, generate_proof, hash_to_group, i2osp_2,
-    server_evaluate_hash_input, verify_proof, BlindedElement, EvaluationElement, Mode,
-    PreparedEvaluationElement, Proof, STR_FINALIZE,
+    array_map_ref, blind_hashed_element, derive_keypair, deterministic_blind_long_input_unchecked,
+    deterministic_blind_unchecked, generate_proof, hash_long_input_to_group, hash_to_group,
+    prehash_long_input, server_evaluate_hash_input, try_array_from_fn, try_array_from_results,
+    BlindedElement, EvaluationElement, Mode, PreparedEvaluationElement, Proof, ZipRepeat,
+    ZipRepeatExt,
 };
 #[cfg(feature = "serde")]
 use crate::serialization::serde::{Element, Scalar};
-use crate::{CipherSuite, Error, Group, Result};
+use crate::verifier::ContextProof;
+use crate::{
+    kernel, BatchProof, CipherSuite, Error, ErrorKind, Group, Operation, OprfServer, PoprfServer,
+    Result, VerifierContext,
+};
 
 ////////////////////////////
 // High-level API Structs //
@@ -86,7 +95,8 @@ where
     /// DH-OPRF.
     ///
     /// # Errors
-    /// [`Error::Input`] if the `input` is empty or longer then [`u16::MAX`].
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
     pub fn blind<R: RngCore + CryptoRng>(
         input: &[u8],
         blinding_factor_rng: &mut R,
@@ -95,6 +105,24 @@ where
         Self::deterministic_blind_unchecked_inner(input, blind)
     }
 
+    /// Allows for batching of the blinding of a fixed number of `inputs`
+    /// without memory allocation.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if any of the `inputs` is empty or longer then
+    /// [`u16::MAX`], carrying the index of the offending input.
+    pub fn batch_blind_array<R: RngCore + CryptoRng, const N: usize>(
+        inputs: &[&[u8]; N],
+        blinding_factor_rng: &mut R,
+    ) -> Result<VoprfClientBatchBlindArrayResult<CS, N>> {
+        let states: [Self; N] = try_array_from_results(array_map_ref(inputs, |input| {
+            Self::blind(input, blinding_factor_rng).map(|result| result.state)
+        }))?;
+        let messages = array_map_ref(&states, |state| BlindedElement(state.blinded_element));
+
+        Ok(VoprfClientBatchBlindArrayResult { states, messages })
+    }
+
     /// Computes the first step for the multiplicative blinding version of
     /// DH-OPRF, taking a blinding factor scalar as input instead of sampling
     /// from an RNG.
@@ -105,7 +133,8 @@ where
     /// on the validity of the blinding factor!
     ///
     /// # Errors
-    /// [`Error::Input`] if the `input` is empty or longer then [`u16::MAX`].
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
     #[cfg(any(feature = "danger", test))]
     pub fn deterministic_blind_unchecked(
         input: &[u8],
@@ -114,7 +143,7 @@ where
         Self::deterministic_blind_unchecked_inner(input, blind)
     }
 
-    /// Can only fail with [`Error::Input`].
+    /// Can only fail with [`ErrorKind::Input`].
     fn deterministic_blind_unchecked_inner(
         input: &[u8],
         blind: <CS::Group as Group>::Scalar,
@@ -129,47 +158,218 @@ where
         })
     }
 
+    /// Like [`VoprfClient::blind`], but in the long input mode, which
+    /// pre-hashes the `input` and therefore accepts inputs of any length. The
+    /// client has to be finalized with [`VoprfClient::finalize_long_input`] and
+    /// the outputs are unrelated to the ones of the regular mode.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn blind_long_input<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<VoprfClientBlindResult<CS>> {
+        let blind = CS::Group::random_scalar(blinding_factor_rng);
+        let blinded_element =
+            deterministic_blind_long_input_unchecked::<CS>(input, &blind, Mode::Voprf)?;
+        Ok(VoprfClientBlindResult {
+            state: Self {
+                blind,
+                blinded_element,
+            },
+            message: BlindedElement(blinded_element),
+        })
+    }
+
+    /// Hashes the `input` to the element [`VoprfClient::blind`] blinds, the
+    /// expensive half of blinding, which doesn't involve the blind. It can
+    /// therefore run on a helper device, e.g. the phone of a constrained
+    /// smartcard client, which then only runs [`VoprfClient::blind_element`].
+    /// Note that the helper learns the `input`, so this is only useful if it
+    /// is trusted with the input, but not with the blind.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn hash_input_to_element(input: &[u8]) -> Result<<CS::Group as Group>::Elem> {
+        hash_to_group::<CS>(input, Mode::Voprf)
+            .map_err(|error| error.with_operation(Operation::Blind))
+    }
+
+    /// Like [`VoprfClient::blind`], but takes the `hashed_element` the input
+    /// was already hashed to with [`VoprfClient::hash_input_to_element`],
+    /// e.g. by a helper device or an earlier stage of a pipeline, instead
+    /// of hashing the input again, so only the scalar multiplication with
+    /// the blind runs here. The client still has to be finalized with the
+    /// input, whose output it only computes if the `hashed_element` was
+    /// hashed from it.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`] if the `hashed_element` is the identity element.
+    pub fn blind_element<R: RngCore + CryptoRng>(
+        hashed_element: <CS::Group as Group>::Elem,
+        blinding_factor_rng: &mut R,
+    ) -> Result<VoprfClientBlindResult<CS>> {
+        debug_span!("blind", mode = ?Mode::Voprf);
+
+        let blind = CS::Group::random_scalar(blinding_factor_rng);
+        let blinded_element = blind_hashed_element::<CS>(hashed_element, &blind)?;
+        Ok(VoprfClientBlindResult {
+            state: Self {
+                blind,
+                blinded_element,
+            },
+            message: BlindedElement(blinded_element),
+        })
+    }
+
+    /// Like [`VoprfClient::finalize`], but in the long input mode, see
+    /// [`VoprfClient::blind_long_input`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    pub fn finalize_long_input(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        self.finalize(
+            &prehash_long_input::<CS>(input, Mode::Voprf),
+            evaluation_element,
+            proof,
+            pk,
+        )
+    }
+
     /// Computes the third step for the multiplicative blinding version of
     /// DH-OPRF, in which the client unblinds the server's message.
     ///
+    /// Like [`OprfClient::finalize`](crate::OprfClient::finalize), the `input`
+    /// is borrowed and not captured in the client. It has to be equal to the
+    /// `input` passed to [`blind`](Self::blind): the `proof` only covers the
+    /// blinded element, so a different `input` still verifies, but the output
+    /// is unrelated to it.
+    ///
     /// # Errors
-    /// - [`Error::Input`] if the `input` is empty or longer then [`u16::MAX`].
-    /// - [`Error::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
     pub fn finalize(
         &self,
         input: &[u8],
         evaluation_element: &EvaluationElement<CS>,
         proof: &Proof<CS>,
         pk: <CS::Group as Group>::Elem,
-    ) -> Result<Output<CS::Hash>> {
+    ) -> Result<Output<CS::FinalizeHash>> {
+        debug_span!("finalize", mode = ?Mode::Voprf);
+
         let inputs = core::array::from_ref(&input);
         let clients = core::array::from_ref(self);
         let messages = core::array::from_ref(evaluation_element);
 
         let mut batch_result = Self::batch_finalize(inputs, clients, messages, proof, pk)?;
-        batch_result.next().unwrap()
+        batch_result
+            .next()
+            .unwrap_or_else(|| Err(Error::new(ErrorKind::Protocol, Operation::Finalize)))
+    }
+
+    /// Finalizes the server's response, accepting a proof under any of the
+    /// public keys in `pks`, e.g. while issuer keys are rotated and several are
+    /// valid at the same time.
+    ///
+    /// Returns the index into `pks` of the key the proof verified against,
+    /// together with the output.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify
+    ///   against all of the `pks`.
+    pub fn finalize_with_any_key(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pks: &[<CS::Group as Group>::Elem],
+    ) -> Result<(usize, Output<CS::FinalizeHash>)> {
+        for (index, pk) in pks.iter().enumerate() {
+            match self.finalize(input, evaluation_element, proof, *pk) {
+                Ok(output) => return Ok((index, output)),
+                Err(error) if error.kind() == ErrorKind::ProofVerification => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::ProofVerification,
+            Operation::VerifyProof,
+        ))
+    }
+
+    /// Finalizes the same blinded `input` against the responses of multiple
+    /// servers, e.g. when querying several issuers with one [BlindedElement].
+    ///
+    /// Each item of `responses` is a caller-chosen key identifier, the public
+    /// key of the server and its response. The identifier is returned together
+    /// with the result of that server, so outputs stay associated with the key
+    /// they were computed under. Note that sending the same [BlindedElement] to
+    /// multiple servers allows them to link these requests.
+    ///
+    /// The resulting outputs can each fail individually with the errors of
+    /// [`finalize`](Self::finalize).
+    pub fn finalize_multi_key<'a, K, I>(
+        &'a self,
+        input: &'a [u8],
+        responses: I,
+    ) -> VoprfClientMultiKeyFinalizeResult<'a, CS, K, I::IntoIter>
+    where
+        I: IntoIterator<
+            Item = (
+                K,
+                <CS::Group as Group>::Elem,
+                &'a VoprfServerEvaluateResult<CS>,
+            ),
+        >,
+    {
+        responses.into_iter().zip_repeat((self, input)).map(
+            |((key, pk, response), (client, input))| {
+                let output = client.finalize(input, &response.message, &response.proof, pk);
+                (key, output)
+            },
+        )
     }
 
     /// Allows for batching of the finalization of multiple [VoprfClient]
     /// and [EvaluationElement] pairs
     ///
     /// # Errors
-    /// - [`Error::Batch`] if the number of `clients` and `messages` don't match
-    ///   or is longer then [`u16::MAX`].
-    /// - [`Error::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Batch`] if the number of `clients` and `messages` don't
+    ///   match or is longer then [`u16::MAX`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    ///
+    /// The `proof` is either a single [`Proof`] or a
+    /// [`ChunkedProof`](crate::ChunkedProof).
     ///
-    /// The resulting messages can each fail individually with [`Error::Input`]
-    /// if the `input` is empty or longer then [`u16::MAX`].
-    pub fn batch_finalize<'a, I: 'a, II, IC, IM>(
+    /// The resulting messages can each fail individually with
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    ///
+    /// The outputs are computed lazily, from either end, as the returned
+    /// iterator is consumed.
+    pub fn batch_finalize<'a, I, II, IC, IM, P>(
         inputs: &'a II,
         clients: &'a IC,
         messages: &'a IM,
-        proof: &Proof<CS>,
+        proof: &P,
         pk: <CS::Group as Group>::Elem,
     ) -> Result<VoprfClientBatchFinalizeResult<'a, CS, I, II, IC, IM>>
     where
         CS: 'a,
-        I: AsRef<[u8]>,
+        P: BatchProof<CS> + ?Sized,
+        I: 'a + AsRef<[u8]>,
         &'a II: 'a + IntoIterator<Item = I>,
         <&'a II as IntoIterator>::IntoIter: ExactSizeIterator,
         &'a IC: 'a + IntoIterator<Item = &'a VoprfClient<CS>>,
@@ -177,6 +377,12 @@ where
         &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
         <&'a IM as IntoIterator>::IntoIter: ExactSizeIterator,
     {
+        debug_span!(
+            "batch_finalize",
+            mode = ?Mode::Voprf,
+            batch_size = clients.into_iter().len(),
+        );
+
         let unblinded_elements = verifiable_unblind(clients, messages, pk, proof)?;
         let inputs_and_unblinded_elements = inputs.into_iter().zip(unblinded_elements);
         Ok(finalize_after_unblind::<CS, _, _>(
@@ -184,6 +390,218 @@ where
         ))
     }
 
+    /// Alternative version of [`batch_finalize`](Self::batch_finalize) for a
+    /// fixed number of items, returning the outputs in an array.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    /// - [`ErrorKind::Input`] if any of the `inputs` is empty or longer then
+    ///   [`u16::MAX`], carrying the index of the offending input.
+    pub fn batch_finalize_array<const N: usize>(
+        inputs: &[&[u8]; N],
+        clients: &[Self; N],
+        messages: &[EvaluationElement<CS>; N],
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+    ) -> Result<[Output<CS::FinalizeHash>; N]> {
+        let mut outputs = Self::batch_finalize(inputs, clients, messages, proof, pk)?;
+        // This can't fail, all arrays have the same length.
+        try_array_from_fn(|_| {
+            outputs
+                .next()
+                .unwrap_or_else(|| Err(Error::new(ErrorKind::Protocol, Operation::Finalize)))
+        })
+    }
+
+    /// Like [`finalize`](Self::finalize), but verifies the `proof` with the
+    /// precomputation of a [`VerifierContext`] instead of the public key.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the `context` wasn't prepared with
+    ///   [`VerifierContext::voprf`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    pub fn finalize_with_context(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        context: &VerifierContext<'_, CS>,
+    ) -> Result<Output<CS::FinalizeHash>> {
+        debug_span!("finalize_with_context", mode = ?Mode::Voprf);
+
+        let inputs = core::array::from_ref(&input);
+        let clients = core::array::from_ref(self);
+        let messages = core::array::from_ref(evaluation_element);
+
+        let mut batch_result =
+            Self::batch_finalize_with_context(inputs, clients, messages, proof, context)?;
+        batch_result
+            .next()
+            .unwrap_or_else(|| Err(Error::new(ErrorKind::Protocol, Operation::Finalize)))
+    }
+
+    /// Like [`batch_finalize`](Self::batch_finalize), but verifies the `proof`
+    /// with the precomputation of a [`VerifierContext`] instead of the public
+    /// key.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Batch`] if the number of `clients` and `messages` don't
+    ///   match or is longer then [`u16::MAX`].
+    /// - [`ErrorKind::Protocol`] if the `context` wasn't prepared with
+    ///   [`VerifierContext::voprf`].
+    /// - [`ErrorKind::ProofVerification`] if the `proof` failed to verify.
+    ///
+    /// The resulting messages can each fail individually with
+    /// [`ErrorKind::Input`] if the `input` is empty or longer then
+    /// [`u16::MAX`].
+    pub fn batch_finalize_with_context<'a, I, II, IC, IM>(
+        inputs: &'a II,
+        clients: &'a IC,
+        messages: &'a IM,
+        proof: &Proof<CS>,
+        context: &VerifierContext<'_, CS>,
+    ) -> Result<VoprfClientBatchFinalizeResult<'a, CS, I, II, IC, IM>>
+    where
+        CS: 'a,
+        I: 'a + AsRef<[u8]>,
+        &'a II: 'a + IntoIterator<Item = I>,
+        <&'a II as IntoIterator>::IntoIter: ExactSizeIterator,
+        &'a IC: 'a + IntoIterator<Item = &'a VoprfClient<CS>>,
+        <&'a IC as IntoIterator>::IntoIter: ExactSizeIterator,
+        &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+        <&'a IM as IntoIterator>::IntoIter: ExactSizeIterator,
+    {
+        debug_span!(
+            "batch_finalize_with_context",
+            mode = ?Mode::Voprf,
+            batch_size = clients.into_iter().len(),
+        );
+
+        context.check_mode(Mode::Voprf)?;
+        let proof = ContextProof { context, proof };
+
+        let unblinded_elements = verifiable_unblind(clients, messages, context.key, &proof)?;
+        let inputs_and_unblinded_elements = inputs.into_iter().zip(unblinded_elements);
+        Ok(finalize_after_unblind::<CS, _, _>(
+            inputs_and_unblinded_elements,
+        ))
+    }
+
+    /// Identifies which of the `messages` are inconsistent with the public key
+    /// after the `proof` of a [`batch_finalize`](Self::batch_finalize) failed
+    /// to verify, so only those items have to be retried.
+    ///
+    /// A single proof can't tell which items are wrong, so the batch is
+    /// divided and conquered: `prove` is called with halves of failing ranges
+    /// and has to return the server's proof for the `messages` of that range,
+    /// e.g. from [`VoprfServer::prove`]. Returns the indices of the
+    /// inconsistent `messages` in ascending order, which is empty if the
+    /// `proof` verifies.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Batch`] if the number of `clients` and `messages` don't
+    ///   match or is longer then [`u16::MAX`].
+    /// - Any error returned by `prove`.
+    #[cfg(feature = "alloc")]
+    pub fn batch_find_inconsistent<F>(
+        clients: &[Self],
+        messages: &[EvaluationElement<CS>],
+        proof: &Proof<CS>,
+        pk: <CS::Group as Group>::Elem,
+        mut prove: F,
+    ) -> Result<Vec<usize>>
+    where
+        F: FnMut(Range<usize>) -> Result<Proof<CS>>,
+    {
+        debug_span!(
+            "batch_find_inconsistent",
+            mode = ?Mode::Voprf,
+            batch_size = clients.len(),
+        );
+
+        if clients.len() != messages.len() {
+            return Err(Error::new(ErrorKind::Batch, Operation::VerifyProof));
+        }
+
+        let verify = |range: Range<usize>, proof: &Proof<CS>| {
+            let clients = clients.get(range.clone()).unwrap_or_default();
+            let messages = messages.get(range).unwrap_or_default();
+
+            match proof.verify_batch(
+                CS::Group::base_elem(),
+                pk,
+                clients.iter().map(|client| client.blinded_element),
+                messages.iter().map(|message| message.0),
+                Mode::Voprf,
+            ) {
+                Ok(()) => Ok(true),
+                Err(error) if error.kind() == ErrorKind::ProofVerification => Ok(false),
+                Err(error) => Err(error),
+            }
+        };
+
+        let mut inconsistent = Vec::new();
+
+        if verify(0..clients.len(), proof)? {
+            return Ok(inconsistent);
+        }
+
+        // Ranges that failed to verify, latest first
+        let mut failing = Vec::new();
+        failing.push(0..clients.len());
+
+        while let Some(range) = failing.pop() {
+            if range.len() == 1 {
+                inconsistent.push(range.start);
+                continue;
+            }
+
+            let middle = range.start + range.len() / 2;
+
+            // Push the upper half first to find indices in ascending order
+            for half in [middle..range.end, range.start..middle] {
+                if !verify(half.clone(), &prove(half.clone())?)? {
+                    failing.push(half);
+                }
+            }
+        }
+
+        Ok(inconsistent)
+    }
+
+    /// Reconstructs a [VoprfClient] from its `blind` and the `input` it was
+    /// computed over, e.g. after persisting it in a custom format. See
+    /// [`VoprfClient::into_parts`] for the inverse.
+    ///
+    /// # Errors
+    /// - [`ErrorKind::Deserialization`] if the `blind` is zero.
+    /// - [`ErrorKind::Input`] if the `input` is empty or longer then
+    ///   [`u16::MAX`].
+    pub fn from_parts(input: &[u8], blind: <CS::Group as Group>::Scalar) -> Result<Self> {
+        if CS::Group::is_zero_scalar(blind).into() {
+            return Err(ErrorKind::Deserialization.into());
+        }
+
+        Self::deterministic_blind_unchecked_inner(input, blind).map(|result| result.state)
+    }
+
+    /// Returns the `blind` of this [VoprfClient]. The `input` isn't stored
+    /// and has to be persisted separately. See [`VoprfClient::from_parts`]
+    /// for the inverse.
+    pub fn into_parts(self) -> <CS::Group as Group>::Scalar {
+        self.blind
+    }
+
+    /// Folds the `delta` returned by [`BlindedElement::rerandomize`] into this
+    /// client, so that it can finalize the server's response to the
+    /// re-randomized element.
+    pub fn apply_rerandomization(&mut self, delta: <CS::Group as Group>::Scalar) {
+        self.blind = self.blind * &delta;
+        self.blinded_element = self.blinded_element * &delta;
+    }
+
     /// Only used for test functions
     #[cfg(test)]
     pub fn from_blind_and_element(
@@ -196,7 +614,7 @@ where
         }
     }
 
-    // Only used for test functions
+    /// Only used for test functions
     #[cfg(test)]
     pub fn get_blind(&self) -> <CS::Group as Group>::Scalar {
         self.blind
@@ -211,7 +629,7 @@ where
     /// Produces a new instance of a [VoprfServer] using a supplied RNG
     ///
     /// # Errors
-    /// [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self> {
         let mut seed = GenericArray::<_, <CS::Group as Group>::ScalarLen>::default();
         rng.fill_bytes(&mut seed);
@@ -223,8 +641,8 @@ where
     /// bytes to represent the server's private key
     ///
     /// # Errors
-    /// [`Error::Deserialization`] if the private key is not a valid point on
-    /// the group or zero.
+    /// [`ErrorKind::Deserialization`] if the private key is not a valid point
+    /// on the group or zero.
     pub fn new_with_key(key: &[u8]) -> Result<Self> {
         let sk = CS::Group::deserialize_scalar(key)?;
         let pk = CS::Group::base_elem() * &sk;
@@ -236,16 +654,57 @@ where
     ///
     /// Corresponds to DeriveKeyPair() function from the VOPRF specification.
     ///
+    /// The mode is part of the derivation, so the same `seed` and `info` derive
+    /// a different key than in the other modes. To run several modes off the
+    /// same key, derive it once and convert the server, see
+    /// [`From`]`<`[`OprfServer`]`>`.
+    ///
     /// # Errors
-    /// - [`Error::DeriveKeyPair`] if the `input` and `seed` together are longer
-    ///   then `u16::MAX - 3`.
-    /// - [`Error::Protocol`] if the protocol fails and can't be completed.
+    /// - [`ErrorKind::DeriveKeyPair`] if the `input` and `seed` together are
+    ///   longer then `u16::MAX - 3`.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
     pub fn new_from_seed(seed: &[u8], info: &[u8]) -> Result<Self> {
         let (sk, pk) = derive_keypair::<CS>(seed, info, Mode::Voprf)?;
         Ok(Self { sk, pk })
     }
 
-    // Only used for tests
+    /// Derives a child [VoprfServer] from the private key of this server,
+    /// domain separated by `info`, e.g. to derive per-tenant keys from a
+    /// single stored master key on the fly. The private key is used as the
+    /// seed of DeriveKeyPair(), see [`VoprfServer::new_from_seed`].
+    ///
+    /// # Errors
+    /// - [`ErrorKind::DeriveKeyPair`] if the `info` is longer then `u16::MAX -
+    ///   3` minus the private key length.
+    /// - [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn derive_subkey(&self, info: &[u8]) -> Result<Self> {
+        let mut seed = CS::Group::serialize_scalar(self.sk);
+        let result = Self::new_from_seed(&seed, info);
+        seed.as_mut_slice().zeroize();
+        result
+    }
+
+    /// Produces a new instance of a [VoprfServer] from the server's private key
+    /// scalar. See [`VoprfServer::into_parts`] for the inverse.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Deserialization`] if the private key is zero.
+    pub fn from_parts(sk: <CS::Group as Group>::Scalar) -> Result<Self> {
+        if CS::Group::is_zero_scalar(sk).into() {
+            return Err(ErrorKind::Deserialization.into());
+        }
+
+        let pk = CS::Group::base_elem() * &sk;
+        Ok(Self { sk, pk })
+    }
+
+    /// Returns the server's private key scalar. See [`VoprfServer::from_parts`]
+    /// for the inverse.
+    pub fn into_parts(self) -> <CS::Group as Group>::Scalar {
+        self.sk
+    }
+
+    /// Only used for tests
     #[cfg(test)]
     pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
         self.sk
@@ -254,38 +713,66 @@ where
     /// Computes the second step for the multiplicative blinding version of
     /// DH-OPRF. This message is sent from the server (who holds the OPRF key)
     /// to the client.
+    ///
+    /// # Panics
+    /// Never in practice, see [`VoprfServer::try_blind_evaluate`] for a
+    /// version that is guaranteed not to panic.
+    // This can't fail because we know the size of the inputs.
+    #[allow(clippy::unwrap_used)]
     pub fn blind_evaluate<R: RngCore + CryptoRng>(
         &self,
         rng: &mut R,
         blinded_element: &BlindedElement<CS>,
     ) -> VoprfServerEvaluateResult<CS> {
+        self.try_blind_evaluate(rng, blinded_element).unwrap()
+    }
+
+    /// Fallible version of [`VoprfServer::blind_evaluate`] that is guaranteed
+    /// not to panic.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn try_blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>> {
+        debug_span!("blind_evaluate", mode = ?Mode::Voprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Voprf.label());
+
         let mut prepared_evaluation_elements =
             self.batch_blind_evaluate_prepare(iter::once(blinded_element));
-        let prepared_evaluation_element = [prepared_evaluation_elements.next().unwrap()];
+        let prepared_evaluation_element = [prepared_evaluation_elements
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))?];
 
-        // This can't fail because we know the size of the inputs.
         let VoprfServerBatchEvaluateFinishResult {
             mut messages,
             proof,
-        } = self
-            .batch_blind_evaluate_finish(
-                rng,
-                iter::once(blinded_element),
-                &prepared_evaluation_element,
-            )
-            .unwrap();
+        } = self.batch_blind_evaluate_finish(
+            rng,
+            iter::once(blinded_element),
+            &prepared_evaluation_element,
+        )?;
 
-        let message = messages.next().unwrap();
+        let message = messages
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))?;
 
-        VoprfServerEvaluateResult { message, proof }
+        Ok(VoprfServerEvaluateResult { message, proof })
     }
 
     /// Allows for batching of the evaluation of multiple [BlindedElement]
     /// messages from a [VoprfClient]
     ///
     /// # Errors
-    /// [`Error::Batch`] if the number of `blinded_elements` and
+    /// [`ErrorKind::Batch`] if the number of `blinded_elements` and
     /// `evaluation_elements` don't match or is longer then [`u16::MAX`]
+    ///
+    /// To stream the [`EvaluationElement`]s as they are computed instead, use
+    /// [`evaluate_only`](Self::evaluate_only) and [`prove`](Self::prove), or
+    /// [`batch_blind_evaluate_finish`](Self::batch_blind_evaluate_finish),
+    /// which return them lazily.
     #[cfg(feature = "alloc")]
     pub fn batch_blind_evaluate<'a, R: RngCore + CryptoRng, I>(
         &self,
@@ -297,6 +784,14 @@ where
         &'a I: IntoIterator<Item = &'a BlindedElement<CS>>,
         <&'a I as IntoIterator>::IntoIter: ExactSizeIterator,
     {
+        debug_span!(
+            "batch_blind_evaluate",
+            mode = ?Mode::Voprf,
+            batch_size = blinded_elements.into_iter().len(),
+        );
+        count!("voprf_evaluations_total", blinded_elements.into_iter().len(), "mode" => Mode::Voprf.label());
+        record!("voprf_batch_size", blinded_elements.into_iter().len(), "mode" => Mode::Voprf.label());
+
         let prepared_evaluation_elements = self
             .batch_blind_evaluate_prepare(blinded_elements.into_iter())
             .collect();
@@ -311,6 +806,44 @@ where
         Ok(VoprfServerBatchEvaluateResult { messages, proof })
     }
 
+    /// Alternative version of `batch_blind_evaluate` for a fixed number of
+    /// [BlindedElement]s without memory allocation.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Batch`] if `N` is longer then [`u16::MAX`].
+    pub fn batch_blind_evaluate_array<R: RngCore + CryptoRng, const N: usize>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>; N],
+    ) -> Result<VoprfServerBatchEvaluateArrayResult<CS, N>> {
+        debug_span!("batch_blind_evaluate", mode = ?Mode::Voprf, batch_size = N);
+        count!("voprf_evaluations_total", N, "mode" => Mode::Voprf.label());
+        record!("voprf_batch_size", N, "mode" => Mode::Voprf.label());
+
+        let mut prepared_evaluation_elements =
+            self.batch_blind_evaluate_prepare(blinded_elements.iter());
+        let prepared_evaluation_elements: [_; N] = try_array_from_fn(|_| {
+            prepared_evaluation_elements
+                .next()
+                .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))
+        })?;
+        let VoprfServerBatchEvaluateFinishResult {
+            mut messages,
+            proof,
+        } = self.batch_blind_evaluate_finish(
+            rng,
+            blinded_elements.iter(),
+            &prepared_evaluation_elements,
+        )?;
+        let messages = try_array_from_fn(|_| {
+            messages
+                .next()
+                .ok_or_else(|| Error::new(ErrorKind::Protocol, Operation::Evaluate))
+        })?;
+
+        Ok(VoprfServerBatchEvaluateArrayResult { messages, proof })
+    }
+
     /// Alternative version of `batch_blind_evaluate` without memory allocation.
     /// Returned [`PreparedEvaluationElement`] have to be
     /// [`collect`](Iterator::collect)ed and passed into
@@ -323,9 +856,12 @@ where
         CS: 'a,
     {
         blinded_elements
-            .zip(iter::repeat(self.sk))
+            .zip_repeat(self.sk)
             .map(|(blinded_element, sk)| {
-                PreparedEvaluationElement(EvaluationElement(blinded_element.0 * &sk))
+                PreparedEvaluationElement(EvaluationElement(kernel::blind_evaluate::<CS>(
+                    sk,
+                    blinded_element.0,
+                )))
             })
     }
 
@@ -333,7 +869,7 @@ where
     /// for more details.
     ///
     /// # Errors
-    /// [`Error::Batch`] if the number of `blinded_elements` and
+    /// [`ErrorKind::Batch`] if the number of `blinded_elements` and
     /// `evaluation_elements` don't match or is longer then [`u16::MAX`]
     pub fn batch_blind_evaluate_finish<
         'a,
@@ -352,15 +888,10 @@ where
         &'b IE: IntoIterator<Item = &'b PreparedEvaluationElement<CS>>,
         <&'b IE as IntoIterator>::IntoIter: ExactSizeIterator,
     {
-        let g = CS::Group::base_elem();
-        let proof = generate_proof(
+        let proof = self.prove(
             rng,
-            self.sk,
-            g,
-            self.pk,
-            blinded_elements.map(|element| element.0),
-            evaluation_elements.into_iter().map(|element| element.0 .0),
-            Mode::Voprf,
+            blinded_elements,
+            evaluation_elements.into_iter().map(|element| &element.0),
         )?;
 
         let messages = evaluation_elements.into_iter().map(<fn(
@@ -372,46 +903,185 @@ where
         Ok(VoprfServerBatchEvaluateFinishResult { messages, proof })
     }
 
-    /// Computes the output of the POPRF on the server side
+    /// Evaluates the `blinded_elements` without proving them, so the
+    /// [`EvaluationElement`]s can be streamed to the client right away. The
+    /// proof for the whole batch is computed afterwards with
+    /// [`prove`](Self::prove), e.g. asynchronously, and sent in a trailer.
     ///
-    /// # Errors
-    /// [`Error::Input`]  if the `input` is longer then [`u16::MAX`].
-    pub fn evaluate(&self, input: &[u8]) -> Result<Output<<CS as CipherSuite>::Hash>> {
-        let input_element = hash_to_group::<CS>(input, Mode::Voprf)?;
-        if CS::Group::is_identity_elem(input_element).into() {
-            return Err(Error::Input);
-        };
-        let evaluated_element = input_element * &self.sk;
-
-        let issued_element = CS::Group::serialize_elem(evaluated_element);
-
-        server_evaluate_hash_input::<CS>(input, None, issued_element)
-    }
-
-    /// Retrieves the server's public key
-    pub fn get_public_key(&self) -> <CS::Group as Group>::Elem {
-        self.pk
+    /// The client can only finalize the evaluation elements after verifying
+    /// the proof.
+    pub fn evaluate_only<'a, I: Iterator<Item = &'a BlindedElement<CS>>>(
+        &self,
+        blinded_elements: I,
+    ) -> VoprfServerEvaluateOnlyElements<CS, I>
+    where
+        CS: 'a,
+    {
+        blinded_elements
+            .zip_repeat(self.sk)
+            .map(|(blinded_element, sk)| {
+                EvaluationElement(kernel::blind_evaluate::<CS>(sk, blinded_element.0))
+            })
     }
-}
-
-/////////////////////////
-// Convenience Structs //
-//==================== //
-/////////////////////////
 
-/// Contains the fields that are returned by a verifiable client blind
-#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
-pub struct VoprfClientBlindResult<CS: CipherSuite>
-where
-    <CS::Hash as OutputSizeUser>::OutputSize:
-        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
-{
+    /// Computes the proof for a batch of `blinded_elements` and the
+    /// `evaluation_elements` returned by [`evaluate_only`](Self::evaluate_only)
+    /// for them, in the same order.
+    ///
+    /// # Errors
+    /// [`ErrorKind::Batch`] if the number of `blinded_elements` and
+    /// `evaluation_elements` don't match or is longer then [`u16::MAX`]
+    pub fn prove<
+        'a,
+        'b,
+        R: RngCore + CryptoRng,
+        IB: Iterator<Item = &'a BlindedElement<CS>> + ExactSizeIterator,
+        IE: Iterator<Item = &'b EvaluationElement<CS>> + ExactSizeIterator,
+    >(
+        &self,
+        rng: &mut R,
+        blinded_elements: IB,
+        evaluation_elements: IE,
+    ) -> Result<Proof<CS>>
+    where
+        CS: 'a + 'b,
+    {
+        let g = CS::Group::base_elem();
+        generate_proof(
+            rng,
+            self.sk,
+            g,
+            self.pk,
+            blinded_elements.map(|element| element.0),
+            evaluation_elements.map(|element| element.0),
+            Mode::Voprf,
+        )
+    }
+
+    /// Computes the output of the POPRF on the server side
+    ///
+    /// # Errors
+    /// [`ErrorKind::Input`]  if the `input` is longer then [`u16::MAX`].
+    pub fn evaluate(&self, input: &[u8]) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
+        debug_span!("evaluate", mode = ?Mode::Voprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Voprf.label());
+
+        let input_element = hash_to_group::<CS>(input, Mode::Voprf)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        self.evaluate_element(input, input_element)
+    }
+
+    /// Like [`VoprfServer::evaluate`], but in the long input mode, see
+    /// [`VoprfClient::blind_long_input`].
+    ///
+    /// # Errors
+    /// [`ErrorKind::Protocol`] if the protocol fails and can't be completed.
+    pub fn evaluate_long_input(
+        &self,
+        input: &[u8],
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
+        debug_span!("evaluate", mode = ?Mode::Voprf);
+        count!("voprf_evaluations_total", 1, "mode" => Mode::Voprf.label());
+
+        let prehashed_input = prehash_long_input::<CS>(input, Mode::Voprf);
+        let input_element = hash_long_input_to_group::<CS>(&prehashed_input, Mode::Voprf)
+            .map_err(|error| error.with_operation(Operation::Evaluate))?;
+        self.evaluate_element(&prehashed_input, input_element)
+    }
+
+    /// Can only fail with [`ErrorKind::Input`].
+    fn evaluate_element(
+        &self,
+        input: &[u8],
+        input_element: <CS::Group as Group>::Elem,
+    ) -> Result<Output<<CS as CipherSuite>::FinalizeHash>> {
+        if CS::Group::is_identity_elem(input_element).into() {
+            return Err(Error::new(ErrorKind::Input, Operation::Evaluate));
+        };
+        let evaluated_element = input_element * &self.sk;
+
+        let issued_element = CS::Group::serialize_elem(evaluated_element);
+
+        server_evaluate_hash_input::<CS>(input, None, issued_element)
+    }
+
+    /// Retrieves the server's public key
+    pub fn get_public_key(&self) -> <CS::Group as Group>::Elem {
+        self.pk
+    }
+}
+
+/// Uses the private key of an [`OprfServer`] in verifiable mode.
+///
+/// The outputs of both servers remain domain-separated: the mode is part of the
+/// context string used to hash inputs and compute outputs, so the same input
+/// yields unrelated outputs in each mode. However, the key itself is shared:
+/// the public key published for this mode also commits to the base mode key,
+/// and a compromise of one server compromises all of them. The specification
+/// recommends a distinct key per mode, only share keys if the key management
+/// requires it.
+impl<CS: CipherSuite> From<OprfServer<CS>> for VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn from(server: OprfServer<CS>) -> Self {
+        let sk = server.sk;
+        let pk = CS::Group::base_elem() * &sk;
+        Self { sk, pk }
+    }
+}
+
+/// Uses the key pair of a [`PoprfServer`] in verifiable mode. See
+/// [`From`]`<`[`OprfServer`]`>` for the consequences of sharing a key between
+/// modes.
+impl<CS: CipherSuite> From<PoprfServer<CS>> for VoprfServer<CS>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    fn from(server: PoprfServer<CS>) -> Self {
+        Self {
+            sk: server.sk,
+            pk: server.pk,
+        }
+    }
+}
+
+/////////////////////////
+// Convenience Structs //
+//==================== //
+/////////////////////////
+
+/// Contains the fields that are returned by a verifiable client blind
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct VoprfClientBlindResult<CS: CipherSuite>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
     /// The state to be persisted on the client
     pub state: VoprfClient<CS>,
     /// The message to send to the server
     pub message: BlindedElement<CS>,
 }
 
+/// Contains the fields that are returned by a verifiable client batch blind
+/// of a fixed number of inputs
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct VoprfClientBatchBlindArrayResult<CS: CipherSuite, const N: usize>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The states to be persisted on the client
+    pub states: [VoprfClient<CS>; N],
+    /// The messages to send to the server
+    pub messages: [BlindedElement<CS>; N],
+}
+
 /// Concrete return type for [`VoprfClient::batch_finalize`].
 pub type VoprfClientBatchFinalizeResult<'a, C, I, II, IC, IM> = FinalizeAfterUnblindResult<
     'a,
@@ -420,7 +1090,23 @@ pub type VoprfClientBatchFinalizeResult<'a, C, I, II, IC, IM> = FinalizeAfterUnb
     Zip<<&'a II as IntoIterator>::IntoIter, VoprfUnblindResult<'a, C, IC, IM>>,
 >;
 
+/// Concrete return type for [`VoprfClient::finalize_multi_key`].
+pub type VoprfClientMultiKeyFinalizeResult<'a, CS, K, I> = Map<
+    ZipRepeat<I, (&'a VoprfClient<CS>, &'a [u8])>,
+    fn(
+        (
+            (
+                K,
+                <<CS as CipherSuite>::Group as Group>::Elem,
+                &'a VoprfServerEvaluateResult<CS>,
+            ),
+            (&'a VoprfClient<CS>, &'a [u8]),
+        ),
+    ) -> (K, Result<Output<<CS as CipherSuite>::FinalizeHash>>),
+>;
+
 /// Contains the fields that are returned by a verifiable server evaluate
+#[derive_where(Clone)]
 #[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
 pub struct VoprfServerEvaluateResult<CS: CipherSuite>
 where
@@ -434,6 +1120,7 @@ where
 }
 
 /// Contains the fields that are returned by a verifiable server batch evaluate
+#[derive_where(Clone)]
 #[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
 #[cfg(feature = "alloc")]
 pub struct VoprfServerBatchEvaluateResult<CS: CipherSuite>
@@ -447,10 +1134,25 @@ where
     pub proof: Proof<CS>,
 }
 
+/// Contains the fields that are returned by a verifiable server batch evaluate
+/// of a fixed number of elements
+#[derive_where(Clone)]
+#[derive_where(Debug; <CS::Group as Group>::Scalar, <CS::Group as Group>::Elem)]
+pub struct VoprfServerBatchEvaluateArrayResult<CS: CipherSuite, const N: usize>
+where
+    <CS::Hash as OutputSizeUser>::OutputSize:
+        IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+{
+    /// The messages to send to the client
+    pub messages: [EvaluationElement<CS>; N],
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
 /// Concrete type of [`EvaluationElement`]s returned by
 /// [`VoprfServer::batch_blind_evaluate_prepare`].
 pub type VoprfServerBatchEvaluatePreparedEvaluationElements<CS, I> = Map<
-    Zip<I, Repeat<<<CS as CipherSuite>::Group as Group>::Scalar>>,
+    ZipRepeat<I, <<CS as CipherSuite>::Group as Group>::Scalar>,
     fn(
         (
             &BlindedElement<CS>,
@@ -459,6 +1161,18 @@ pub type VoprfServerBatchEvaluatePreparedEvaluationElements<CS, I> = Map<
     ) -> PreparedEvaluationElement<CS>,
 >;
 
+/// Concrete type of [`EvaluationElement`]s returned by
+/// [`VoprfServer::evaluate_only`].
+pub type VoprfServerEvaluateOnlyElements<CS, I> = Map<
+    ZipRepeat<I, <<CS as CipherSuite>::Group as Group>::Scalar>,
+    fn(
+        (
+            &BlindedElement<CS>,
+            <<CS as CipherSuite>::Group as Group>::Scalar,
+        ),
+    ) -> EvaluationElement<CS>,
+>;
+
 /// Concrete type of [`EvaluationElement`]s in
 /// [`VoprfServerBatchEvaluateFinishResult`].
 pub type VoprfServerBatchEvaluateFinishedMessages<'a, CS, I> = Map<
@@ -502,12 +1216,12 @@ type VoprfUnblindResult<'a, CS, IC, IM> = Map<
     ) -> <<CS as CipherSuite>::Group as Group>::Elem,
 >;
 
-/// Can only fail with [`Error::Batch] or [`Error::ProofVerification`].
-fn verifiable_unblind<'a, CS: 'a + CipherSuite, IC, IM>(
+/// Can only fail with [`ErrorKind::Batch] or [`ErrorKind::ProofVerification`].
+fn verifiable_unblind<'a, CS: 'a + CipherSuite, IC, IM, P: BatchProof<CS> + ?Sized>(
     clients: &'a IC,
     messages: &'a IM,
     pk: <CS::Group as Group>::Elem,
-    proof: &Proof<CS>,
+    proof: &P,
 ) -> Result<VoprfUnblindResult<'a, CS, IC, IM>>
 where
     <CS::Hash as OutputSizeUser>::OutputSize:
@@ -526,26 +1240,22 @@ where
     let evaluation_elements = messages.into_iter().map(|element| element.0);
     let blinded_elements = clients.into_iter().map(|client| client.blinded_element);
 
-    verify_proof(
-        g,
-        pk,
-        blinded_elements,
-        evaluation_elements,
-        proof,
-        Mode::Voprf,
-    )?;
+    proof.verify_batch(g, pk, blinded_elements, evaluation_elements, Mode::Voprf)?;
 
     Ok(blinds
-        .zip(messages.into_iter())
-        .map(|(blind, x)| x.0 * &CS::Group::invert_scalar(blind)))
+        .zip(messages)
+        .map(|(blind, x)| kernel::unblind::<CS>(blind, x.0)))
 }
 
 type FinalizeAfterUnblindResult<'a, C, I, IE> = Map<
-    IE,
-    fn((I, <<C as CipherSuite>::Group as Group>::Elem)) -> Result<Output<<C as CipherSuite>::Hash>>,
+    Enumerate<IE>,
+    fn(
+        (usize, (I, <<C as CipherSuite>::Group as Group>::Elem)),
+    ) -> Result<Output<<C as CipherSuite>::FinalizeHash>>,
 >;
 
-/// Returned values can only fail with [`Error::Input`].
+/// Returned values can only fail with [`ErrorKind::Input`], which carries the
+/// index of the offending item.
 fn finalize_after_unblind<
     'a,
     CS: CipherSuite,
@@ -558,21 +1268,12 @@ where
     <CS::Hash as OutputSizeUser>::OutputSize:
         IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
 {
-    inputs_and_unblinded_elements.map(|(input, unblinded_element)| {
-        let elem_len = <CS::Group as Group>::ElemLen::U16.to_be_bytes();
-
-        // hashInput = I2OSP(len(input), 2) || input ||
-        //             I2OSP(len(unblindedElement), 2) || unblindedElement ||
-        //             "Finalize"
-        // return Hash(hashInput)
-        Ok(CS::Hash::new()
-            .chain_update(i2osp_2(input.as_ref().len()).map_err(|_| Error::Input)?)
-            .chain_update(input.as_ref())
-            .chain_update(elem_len)
-            .chain_update(CS::Group::serialize_elem(unblinded_element))
-            .chain_update(STR_FINALIZE)
-            .finalize())
-    })
+    inputs_and_unblinded_elements
+        .enumerate()
+        .map(|(index, (input, unblinded_element))| {
+            kernel::finalize::<CS>(input.as_ref(), None, unblinded_element)
+                .map_err(|error| error.with_index(index))
+        })
 }
 
 ///////////
@@ -582,6 +1283,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use core::hash::{Hash, Hasher};
     use core::ops::Add;
     use core::ptr;
 
@@ -591,16 +1293,18 @@ mod tests {
     use generic_array::typenum::Sum;
     use generic_array::ArrayLength;
     use rand::rngs::OsRng;
+    use subtle::ConstantTimeEq;
 
     use super::*;
-    use crate::common::{create_context_string, STR_HASH_TO_GROUP};
-    use crate::Group;
+    use crate::common::{create_context_string, derive_hedged_nonce, STR_HASH_TO_GROUP};
+    use crate::tests::mock_rng::CycleRng;
+    use crate::{Group, OprfClient, PoprfServer};
 
     fn prf<CS: CipherSuite>(
         input: &[u8],
         key: <CS::Group as Group>::Scalar,
         mode: Mode,
-    ) -> Output<CS::Hash>
+    ) -> Output<CS::FinalizeHash>
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
             IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
@@ -687,6 +1391,235 @@ mod tests {
         assert_eq!(client_finalize_result, res2);
     }
 
+    fn verifiable_batch_streaming<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let inputs: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        for input in inputs {
+            let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+
+        let prepared_evaluation_elements =
+            server.batch_blind_evaluate_prepare(client_messages.iter());
+        assert_eq!(prepared_evaluation_elements.len(), inputs.len());
+        let prepared_evaluation_elements: Vec<_> = prepared_evaluation_elements.collect();
+        let VoprfServerBatchEvaluateFinishResult { messages, proof } = server
+            .batch_blind_evaluate_finish(
+                &mut rng,
+                client_messages.iter(),
+                &prepared_evaluation_elements,
+            )
+            .unwrap();
+        assert_eq!(messages.len(), inputs.len());
+        let messages: Vec<_> = messages.collect();
+
+        // Evaluation elements can be produced from either end
+        let evaluation_elements = server.evaluate_only(client_messages.iter());
+        assert_eq!(evaluation_elements.len(), inputs.len());
+        for (message, evaluation_element) in messages.iter().rev().zip(evaluation_elements.rev()) {
+            assert!(*message == evaluation_element);
+        }
+
+        let outputs = VoprfClient::batch_finalize(
+            &inputs,
+            &client_states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+        )
+        .unwrap();
+        assert_eq!(outputs.len(), inputs.len());
+
+        for (input, output) in inputs.iter().rev().zip(outputs.rev()) {
+            let res2 = prf::<CS>(input, server.get_private_key(), Mode::Voprf);
+            assert_eq!(output.unwrap(), res2);
+        }
+    }
+
+    fn verifiable_deferred_proof<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let inputs: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        for input in inputs {
+            let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let messages: Vec<_> = server.evaluate_only(client_messages.iter()).collect();
+        let proof = server
+            .prove(&mut rng, client_messages.iter(), messages.iter())
+            .unwrap();
+        let client_finalize_result = VoprfClient::batch_finalize(
+            &inputs,
+            &client_states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+        for (input, output) in inputs.iter().zip(client_finalize_result) {
+            assert_eq!(
+                output,
+                prf::<CS>(input, server.get_private_key(), Mode::Voprf)
+            );
+        }
+
+        // The evaluation elements have to match the blinded elements
+        assert!(matches!(
+            server.prove(&mut rng, client_messages.iter(), messages.iter().take(2)),
+            Err(e) if e.kind() == ErrorKind::Batch
+        ));
+        let proof = server
+            .prove(&mut rng, client_messages.iter(), messages.iter().rev())
+            .unwrap();
+        assert!(VoprfClient::batch_finalize(
+            &inputs,
+            &client_states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    fn verifiable_batch_find_inconsistent<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        for _ in 0..10 {
+            let client_blind_result = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let VoprfServerBatchEvaluateResult {
+            mut messages,
+            proof,
+        } = server
+            .batch_blind_evaluate(&mut rng, &client_messages)
+            .unwrap();
+
+        let proofs = core::cell::Cell::new(0);
+        let mut prove = |range: core::ops::Range<usize>, messages: &[EvaluationElement<CS>]| {
+            proofs.set(proofs.get() + 1);
+            server.prove(
+                &mut rng,
+                client_messages[range.clone()].iter(),
+                messages[range].iter(),
+            )
+        };
+        assert!(VoprfClient::batch_find_inconsistent(
+            &client_states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+            |range| prove(range, &messages),
+        )
+        .unwrap()
+        .is_empty());
+
+        // Corrupt two of the evaluation elements
+        let other_server = VoprfServer::<CS>::new(&mut OsRng).unwrap();
+        for index in [3, 7] {
+            messages[index] = other_server
+                .blind_evaluate(&mut OsRng, &client_messages[index])
+                .message;
+        }
+        let corrupted = messages.clone();
+        let inconsistent = VoprfClient::batch_find_inconsistent(
+            &client_states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+            |range| prove(range, &corrupted),
+        )
+        .unwrap();
+        assert_eq!(inconsistent, [3, 7]);
+        assert!(proofs.get() < 2 * messages.len());
+
+        assert!(matches!(
+            VoprfClient::batch_find_inconsistent(
+                &client_states,
+                &messages[..9],
+                &proof,
+                server.get_public_key(),
+                |range| prove(range, &corrupted),
+            ),
+            Err(e) if e == Error::new(ErrorKind::Batch, Operation::VerifyProof)
+        ));
+    }
+
+    fn verifiable_batch_error_index<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        for _ in 0..3 {
+            let client_blind_result = VoprfClient::<CS>::blind(b"input", &mut rng).unwrap();
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let prepared_evaluation_elements: Vec<_> = server
+            .batch_blind_evaluate_prepare(client_messages.iter())
+            .collect();
+        let VoprfServerBatchEvaluateFinishResult { messages, proof } = server
+            .batch_blind_evaluate_finish(
+                &mut rng,
+                client_messages.iter(),
+                &prepared_evaluation_elements,
+            )
+            .unwrap();
+        let messages: Vec<_> = messages.collect();
+
+        // The second input is too long to be finalized
+        let inputs = vec![
+            b"input".to_vec(),
+            vec![0; usize::from(u16::MAX) + 1],
+            b"input".to_vec(),
+        ];
+        let results: Vec<_> = VoprfClient::batch_finalize(
+            &inputs,
+            &client_states,
+            &messages,
+            &proof,
+            server.get_public_key(),
+        )
+        .unwrap()
+        .collect();
+
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1],
+            Err(Error::new(ErrorKind::Input, Operation::Finalize).with_index(1))
+        );
+        assert!(results[2].is_ok());
+    }
+
     fn verifiable_batch_bad_public_key<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -728,6 +1661,87 @@ mod tests {
         assert!(client_finalize_result.is_err());
     }
 
+    fn verifiable_multi_key_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let servers = [
+            VoprfServer::<CS>::new(&mut rng).unwrap(),
+            VoprfServer::<CS>::new(&mut rng).unwrap(),
+        ];
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_results: Vec<_> = servers
+            .iter()
+            .map(|server| server.blind_evaluate(&mut rng, &client_blind_result.message))
+            .collect();
+
+        // The second response is checked against the wrong key
+        let responses = [
+            ("first", servers[0].get_public_key(), &server_results[0]),
+            ("second", servers[0].get_public_key(), &server_results[1]),
+            ("second", servers[1].get_public_key(), &server_results[1]),
+        ];
+        let outputs: Vec<_> = client_blind_result
+            .state
+            .finalize_multi_key(input, responses)
+            .collect();
+
+        assert_eq!(outputs[0].0, "first");
+        assert_eq!(outputs[0].1, servers[0].evaluate(input));
+        assert!(matches!(
+            outputs[1],
+            ("second", Err(error)) if error.kind() == ErrorKind::ProofVerification
+        ));
+        assert_eq!(outputs[2].0, "second");
+        assert_eq!(outputs[2].1, servers[1].evaluate(input));
+    }
+
+    fn verifiable_any_key_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let old_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let new_server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let pks = [old_server.get_public_key(), new_server.get_public_key()];
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = new_server.blind_evaluate(&mut rng, &client_blind_result.message);
+
+        let (index, output) = client_blind_result
+            .state
+            .finalize_with_any_key(input, &server_result.message, &server_result.proof, &pks)
+            .unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(output, new_server.evaluate(input).unwrap());
+
+        let result = client_blind_result.state.finalize_with_any_key(
+            input,
+            &server_result.message,
+            &server_result.proof,
+            &pks[..1],
+        );
+        assert_eq!(
+            result,
+            Err(Error::new(
+                ErrorKind::ProofVerification,
+                Operation::VerifyProof
+            ))
+        );
+
+        let result = client_blind_result.state.finalize_with_any_key(
+            &[0; 1 << 16],
+            &server_result.message,
+            &server_result.proof,
+            &pks,
+        );
+        assert!(matches!(result, Err(error) if error.kind() == ErrorKind::Input));
+    }
+
     fn verifiable_bad_public_key<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -753,6 +1767,57 @@ mod tests {
         assert!(client_finalize_result.is_err());
     }
 
+    fn hedged_proof_nonces<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let mut rng = OsRng;
+        let k = CS::Group::random_scalar(&mut rng);
+        let other_k = CS::Group::random_scalar(&mut rng);
+        let m = CS::Group::base_elem() * &CS::Group::random_scalar(&mut rng);
+        let z = m * &k;
+        let entropy = [1; 32];
+        let nonce = |k, m, z, entropy| {
+            CS::Group::serialize_scalar(
+                derive_hedged_nonce::<CS>(k, m, z, entropy, Mode::Voprf).unwrap(),
+            )
+        };
+
+        // The nonce is deterministic, but depends on every input
+        assert_eq!(nonce(k, m, z, &entropy), nonce(k, m, z, &entropy));
+        assert_ne!(nonce(k, m, z, &entropy), nonce(other_k, m, z, &entropy));
+        assert_ne!(nonce(k, m, z, &entropy), nonce(k, z, m, &entropy));
+        assert_ne!(nonce(k, m, z, &entropy), nonce(k, m, z, &[2; 32]));
+
+        // Proofs generated with a constant RNG still verify and differ between
+        // statements
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let mut constant_rng = CycleRng::new(vec![1; 32]);
+        let blind_result_1 = VoprfClient::<CS>::blind(b"input 1", &mut rng).unwrap();
+        let blind_result_2 = VoprfClient::<CS>::blind(b"input 2", &mut rng).unwrap();
+        let result_1 = server.blind_evaluate(&mut constant_rng, &blind_result_1.message);
+        let result_2 = server.blind_evaluate(&mut constant_rng, &blind_result_2.message);
+        assert_ne!(
+            CS::Group::serialize_scalar(result_1.proof.s_scalar),
+            CS::Group::serialize_scalar(result_2.proof.s_scalar)
+        );
+        for (blind_result, result, input) in [
+            (blind_result_1, result_1, b"input 1"),
+            (blind_result_2, result_2, b"input 2"),
+        ] {
+            blind_result
+                .state
+                .finalize(
+                    input,
+                    &result.message,
+                    &result.proof,
+                    server.get_public_key(),
+                )
+                .unwrap();
+        }
+    }
+
     fn verifiable_server_evaluate<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -786,6 +1851,285 @@ mod tests {
         assert!(client_finalize != server_evaluate);
     }
 
+    fn verifiable_long_input_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = vec![1; usize::from(u16::MAX) + 1];
+        let mut rng = OsRng;
+        let client_blind_result = VoprfClient::<CS>::blind_long_input(&input, &mut rng).unwrap();
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        assert!(server.evaluate(&input).is_err());
+        let server_result = server.blind_evaluate(&mut rng, &client_blind_result.message);
+        let client_finalize = client_blind_result
+            .state
+            .finalize_long_input(
+                &input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+            )
+            .unwrap();
+        assert_eq!(client_finalize, server.evaluate_long_input(&input).unwrap());
+
+        // The long input mode is domain separated from the regular mode, even for
+        // the pre-hashed input
+        let prehashed_input = prehash_long_input::<CS>(&input, Mode::Voprf);
+        assert!(client_finalize != server.evaluate(&prehashed_input).unwrap());
+    }
+
+    fn verifiable_rerandomized_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+
+        // A proxy re-randomizes the request before forwarding it
+        let (message, delta) = client_blind_result.message.rerandomize(&mut rng);
+        assert!(message != client_blind_result.message);
+        let server_result = server.blind_evaluate(&mut rng, &message);
+
+        // The response can't be finalized without folding in `delta`
+        assert!(client_blind_result
+            .state
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+            )
+            .is_err());
+
+        let mut state = client_blind_result.state;
+        state.apply_rerandomization(delta);
+        let client_finalize = state
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+            )
+            .unwrap();
+        assert_eq!(client_finalize, server.evaluate(input).unwrap());
+    }
+
+    fn derive_subkeys<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let master = VoprfServer::<CS>::new(&mut rng).unwrap();
+
+        // Subkeys are deterministic and separated by `info`
+        let key = |server: &VoprfServer<CS>| CS::Group::serialize_scalar(server.get_private_key());
+        let server = master.derive_subkey(b"tenant 1").unwrap();
+        assert_eq!(
+            key(&server),
+            key(&master.derive_subkey(b"tenant 1").unwrap())
+        );
+        assert!(key(&server) != key(&master.derive_subkey(b"tenant 2").unwrap()));
+        assert!(key(&server) != key(&master));
+
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = server.blind_evaluate(&mut rng, &client_blind_result.message);
+        let client_finalize = client_blind_result
+            .state
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+            )
+            .unwrap();
+        assert_eq!(client_finalize, server.evaluate(input).unwrap());
+    }
+
+    fn shared_key_conversion<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let oprf_server = OprfServer::<CS>::new(&mut rng).unwrap();
+        let voprf_server = VoprfServer::from(oprf_server.clone());
+        let poprf_server = PoprfServer::from(oprf_server.clone());
+
+        let sk = CS::Group::serialize_scalar(oprf_server.get_private_key());
+        let pk = CS::Group::serialize_elem(CS::Group::base_elem() * &oprf_server.get_private_key());
+        assert_eq!(
+            CS::Group::serialize_scalar(voprf_server.get_private_key()),
+            sk
+        );
+        assert_eq!(
+            CS::Group::serialize_scalar(poprf_server.get_private_key()),
+            sk
+        );
+        assert_eq!(CS::Group::serialize_elem(voprf_server.get_public_key()), pk);
+        assert_eq!(CS::Group::serialize_elem(poprf_server.get_public_key()), pk);
+
+        let converted_server = VoprfServer::from(poprf_server);
+        assert_eq!(
+            CS::Group::serialize_scalar(converted_server.get_private_key()),
+            sk
+        );
+        assert_eq!(
+            CS::Group::serialize_elem(converted_server.get_public_key()),
+            pk
+        );
+
+        // Outputs stay domain-separated between modes
+        assert!(oprf_server.evaluate(input).unwrap() != voprf_server.evaluate(input).unwrap());
+
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = voprf_server.blind_evaluate(&mut rng, &client_blind_result.message);
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                voprf_server.get_public_key(),
+            )
+            .unwrap();
+        assert_eq!(
+            client_finalize_result,
+            voprf_server.evaluate(input).unwrap()
+        );
+    }
+
+    fn parts_roundtrip<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let server = VoprfServer::<CS>::from_parts(server.into_parts()).unwrap();
+        let client_blind_result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = server.blind_evaluate(&mut rng, &client_blind_result.message);
+
+        let blind = client_blind_result.state.into_parts();
+        let client = VoprfClient::<CS>::from_parts(input, blind).unwrap();
+        let client_finalize_result = client
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+            )
+            .unwrap();
+        assert_eq!(client_finalize_result, server.evaluate(input).unwrap());
+
+        assert!(matches!(
+            VoprfClient::<CS>::from_parts(input, CS::Group::zero_scalar()),
+            Err(error) if error.kind() == ErrorKind::Deserialization
+        ));
+        assert!(matches!(
+            VoprfServer::<CS>::from_parts(CS::Group::zero_scalar()),
+            Err(error) if error.kind() == ErrorKind::Deserialization
+        ));
+    }
+
+    fn message_equality<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        // Records the bytes fed into it to compare hashes
+        #[derive(Default)]
+        struct RecordingHasher(Vec<u8>);
+
+        impl Hasher for RecordingHasher {
+            fn finish(&self) -> u64 {
+                0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                self.0.extend_from_slice(bytes);
+            }
+        }
+
+        fn hash(value: &impl Hash) -> Vec<u8> {
+            let mut hasher = RecordingHasher::default();
+            value.hash(&mut hasher);
+            hasher.0
+        }
+
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result_1 = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let client_blind_result_2 = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let message = client_blind_result_1.message.clone();
+
+        assert!(client_blind_result_1.message == message);
+        assert_eq!(hash(&client_blind_result_1.message), hash(&message));
+        assert!(client_blind_result_1.message != client_blind_result_2.message);
+        assert_ne!(
+            hash(&client_blind_result_1.message),
+            hash(&client_blind_result_2.message)
+        );
+
+        // Evaluating the same blinded element twice is detected
+        let server_result_1 = server.blind_evaluate(&mut rng, &message);
+        let server_result_2 = server.blind_evaluate(&mut rng, &message);
+        let server_result_3 = server.blind_evaluate(&mut rng, &client_blind_result_2.message);
+        assert!(server_result_1.message == server_result_2.message);
+        assert_eq!(
+            hash(&server_result_1.message),
+            hash(&server_result_2.message)
+        );
+        assert!(server_result_1.message != server_result_3.message);
+    }
+
+    fn hashed_element_retrieval<CS: CipherSuite>()
+    where
+        <CS::Hash as OutputSizeUser>::OutputSize:
+            IsLess<U256> + IsLessOrEqual<<CS::Hash as BlockSizeUser>::BlockSize>,
+    {
+        let input = b"input";
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng).unwrap();
+
+        let hashed_element = VoprfClient::<CS>::hash_input_to_element(input).unwrap();
+        assert!(bool::from(
+            hashed_element.ct_eq(&hash_to_group::<CS>(input, Mode::Voprf).unwrap())
+        ));
+        assert!(!bool::from(hashed_element.ct_eq(
+            &OprfClient::<CS>::hash_input_to_element(input).unwrap()
+        )));
+        let client_blind_result =
+            VoprfClient::<CS>::blind_element(hashed_element, &mut rng).unwrap();
+
+        // The server evaluates the already deserialized element
+        let blinded_element = BlindedElement::from_value(client_blind_result.message.get_value());
+        let server_result = server.blind_evaluate(&mut rng, &blinded_element);
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize(
+                input,
+                &server_result.message,
+                &server_result.proof,
+                server.get_public_key(),
+            )
+            .unwrap();
+        assert_eq!(client_finalize_result, server.evaluate(input).unwrap());
+
+        assert_eq!(
+            VoprfClient::<CS>::blind_element(CS::Group::identity_elem(), &mut rng).map(|_| ()),
+            Err(Error::new(ErrorKind::Input, Operation::Blind))
+        );
+    }
+
     fn zeroize_voprf_client<CS: CipherSuite>()
     where
         <CS::Hash as OutputSizeUser>::OutputSize:
@@ -844,9 +2188,24 @@ mod tests {
 
             verifiable_retrieval::<Ristretto255>();
             verifiable_batch_retrieval::<Ristretto255>();
+            verifiable_batch_streaming::<Ristretto255>();
+            verifiable_deferred_proof::<Ristretto255>();
+            #[cfg(feature = "alloc")]
+            verifiable_batch_find_inconsistent::<Ristretto255>();
+            verifiable_batch_error_index::<Ristretto255>();
+            verifiable_multi_key_retrieval::<Ristretto255>();
+            verifiable_any_key_retrieval::<Ristretto255>();
             verifiable_bad_public_key::<Ristretto255>();
             verifiable_batch_bad_public_key::<Ristretto255>();
             verifiable_server_evaluate::<Ristretto255>();
+            hedged_proof_nonces::<Ristretto255>();
+            verifiable_long_input_retrieval::<Ristretto255>();
+            verifiable_rerandomized_retrieval::<Ristretto255>();
+            derive_subkeys::<Ristretto255>();
+            shared_key_conversion::<Ristretto255>();
+            parts_roundtrip::<Ristretto255>();
+            message_equality::<Ristretto255>();
+            hashed_element_retrieval::<Ristretto255>();
 
             zeroize_voprf_client::<Ristretto255>();
             zeroize_voprf_server::<Ristretto255>();
@@ -854,9 +2213,24 @@ mod tests {
 
         verifiable_retrieval::<NistP256>();
         verifiable_batch_retrieval::<NistP256>();
+        verifiable_batch_streaming::<NistP256>();
+        verifiable_deferred_proof::<NistP256>();
+        #[cfg(feature = "alloc")]
+        verifiable_batch_find_inconsistent::<NistP256>();
+        verifiable_batch_error_index::<NistP256>();
+        verifiable_multi_key_retrieval::<NistP256>();
+        verifiable_any_key_retrieval::<NistP256>();
         verifiable_bad_public_key::<NistP256>();
         verifiable_batch_bad_public_key::<NistP256>();
         verifiable_server_evaluate::<NistP256>();
+        hedged_proof_nonces::<NistP256>();
+        verifiable_long_input_retrieval::<NistP256>();
+        verifiable_rerandomized_retrieval::<NistP256>();
+        derive_subkeys::<NistP256>();
+        shared_key_conversion::<NistP256>();
+        parts_roundtrip::<NistP256>();
+        message_equality::<NistP256>();
+        hashed_element_retrieval::<NistP256>();
 
         zeroize_voprf_client::<NistP256>();
         zeroize_voprf_server::<NistP256>();
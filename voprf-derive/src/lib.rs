@@ -0,0 +1,159 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Derive macro for the `CipherSuite` trait of the `voprf` crate. Use it
+//! through the `derive` feature of `voprf`, which documents it at
+//! `voprf::CipherSuite`.
+
+#![deny(unsafe_code)]
+#![warn(clippy::cargo, missing_docs, rust_2018_idioms)]
+#![allow(clippy::multiple_crate_versions)]
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, DeriveInput, Error, Expr, Ident, Result, Token, Type};
+
+/// Implements `voprf::CipherSuite` with the group, hash and ID given in the
+/// `#[voprf(...)]` attribute, and generates a test module that runs
+/// `voprf::check_conformance` on it.
+#[proc_macro_derive(CipherSuite, attributes(voprf))]
+pub fn derive_cipher_suite(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "a ciphersuite can't have generic parameters",
+        ));
+    }
+
+    let mut group: Option<Type> = None;
+    let mut hash: Option<Type> = None;
+    let mut finalize_hash: Option<Type> = None;
+    let mut id: Option<Expr> = None;
+    let mut tests: Option<Ident> = None;
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("voprf"))
+    {
+        attr.parse_args_with(|input: ParseStream<'_>| {
+            while !input.is_empty() {
+                let key: Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+
+                if key == "group" {
+                    set(&mut group, &key, input)?;
+                } else if key == "hash" {
+                    set(&mut hash, &key, input)?;
+                } else if key == "finalize_hash" {
+                    set(&mut finalize_hash, &key, input)?;
+                } else if key == "id" {
+                    set(&mut id, &key, input)?;
+                } else if key == "tests" {
+                    set(&mut tests, &key, input)?;
+                } else {
+                    return Err(Error::new_spanned(
+                        key,
+                        "unknown key, expected `group`, `hash`, `finalize_hash`, `id` or `tests`",
+                    ));
+                }
+
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+            }
+
+            Ok(())
+        })?;
+    }
+
+    let group = group.ok_or_else(|| missing("group"))?;
+    let hash = hash.ok_or_else(|| missing("hash"))?;
+    let finalize_hash = finalize_hash.unwrap_or_else(|| hash.clone());
+    let id = id.ok_or_else(|| missing("id"))?;
+    let name = &input.ident;
+    let tests =
+        tests.unwrap_or_else(|| format_ident!("{}_conformance", to_snake_case(&name.to_string())));
+
+    Ok(quote! {
+        impl ::voprf::CipherSuite for #name {
+            const ID: u16 = #id;
+
+            type Group = #group;
+
+            type Hash = #hash;
+
+            type FinalizeHash = #finalize_hash;
+        }
+
+        #[cfg(test)]
+        mod #tests {
+            #[test]
+            fn conformance() {
+                ::voprf::check_conformance::<super::#name>().unwrap();
+            }
+        }
+    })
+}
+
+/// Parses the value of `key` from `input` into `value`, unless it was already
+/// given.
+fn set<T: Parse>(value: &mut Option<T>, key: &Ident, input: ParseStream<'_>) -> Result<()> {
+    if value.is_some() {
+        return Err(Error::new_spanned(key, "duplicate key"));
+    }
+
+    *value = Some(input.parse()?);
+    Ok(())
+}
+
+fn missing(key: &str) -> Error {
+    Error::new(
+        Span::call_site(),
+        format!("missing `{}` in `#[voprf(...)]`", key),
+    )
+}
+
+/// Converts the `UpperCamelCase` name of a struct to `snake_case`, e.g.
+/// `Ristretto255Sha256` to `ristretto255_sha256`.
+fn to_snake_case(name: &str) -> String {
+    let mut snake_case = String::with_capacity(name.len() + 4);
+    let mut previous_is_lowercase = false;
+
+    for character in name.chars() {
+        if character.is_uppercase() && previous_is_lowercase {
+            snake_case.push('_');
+        }
+
+        previous_is_lowercase = character.is_lowercase() || character.is_ascii_digit();
+        snake_case.extend(character.to_lowercase());
+    }
+
+    snake_case
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snake_case() {
+        assert_eq!(to_snake_case("Ristretto255Sha256"), "ristretto255_sha256");
+        assert_eq!(to_snake_case("P384"), "p384");
+        assert_eq!(to_snake_case("NistP256"), "nist_p256");
+    }
+}